@@ -6,11 +6,13 @@ check() {
 		/usr/libexec/stratisd-min \
 		$systemdutildir/system-generators/stratis-setup-generator \
 		thin_check \
+		thin_dump \
 		thin_repair \
 		mkfs.xfs \
 		xfs_admin \
 		xfs_growfs \
 		xfs_db \
+		xfs_repair \
 		udevadm \
 		plymouth \
 		/usr/sbin/plymouthd \
@@ -37,11 +39,13 @@ install() {
 	inst_multiple stratis-min \
 		/usr/libexec/stratisd-min \
 		thin_check \
+		thin_dump \
 		thin_repair \
 		mkfs.xfs \
 		xfs_admin \
 		xfs_growfs \
 		xfs_db \
+		xfs_repair \
 		udevadm \
 		plymouth \
 		/usr/sbin/plymouthd \
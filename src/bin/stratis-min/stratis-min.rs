@@ -8,7 +8,9 @@ use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
 use serde_json::{json, Map, Value};
 
 use stratisd::{
-    engine::{EncryptionInfo, KeyDescription, PoolUuid, UnlockMethod, CLEVIS_TANG_TRUST_URL},
+    engine::{
+        CipherInfo, EncryptionInfo, KeyDescription, PoolUuid, UnlockMethod, CLEVIS_TANG_TRUST_URL,
+    },
     jsonrpc::client::{filesystem, key, pool, report},
     stratis::{StratisError, VERSION},
 };
@@ -208,6 +210,9 @@ fn main() -> Result<(), Box<dyn Error>> {
                 EncryptionInfo {
                     key_description,
                     clevis_info,
+                    cipher_info: CipherInfo::default(),
+                    pbkdf_params: None,
+                    header_dir: None,
                 },
             )?;
             Ok(())
@@ -40,6 +40,12 @@ fn parse_args() -> App<'static, 'static> {
                 .takes_value(false)
                 .help("Enables sim engine."),
         )
+        .arg(
+            Arg::with_name("read_only")
+                .long("--read-only")
+                .takes_value(false)
+                .help("Rejects all requests that would modify the engine's state."),
+        )
 }
 
 fn get_long_help(app: &mut App) -> Result<String, Box<dyn Error>> {
@@ -145,7 +151,12 @@ fn main() -> Result<(), Box<dyn Error>> {
         println!("{}", help);
         Ok(())
     } else {
-        run(args.is_present("sim"))?;
+        run(
+            args.is_present("sim"),
+            args.is_present("read_only"),
+            None,
+            None,
+        )?;
         Ok(())
     }
 }
@@ -3,12 +3,15 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use std::{
+    convert::TryFrom,
     env,
     fs::{File, OpenOptions},
     io::{Read, Write},
     os::unix::io::AsRawFd,
+    path::Path,
     process::exit,
     str::FromStr,
+    time::Duration,
 };
 
 use clap::{App, Arg};
@@ -19,7 +22,14 @@ use nix::{
     unistd::getpid,
 };
 
-use stratisd::stratis::{run, StratisError, StratisResult, VERSION};
+use stratisd::{
+    engine::{
+        set_command_sandbox, set_device_path_style, set_dm_retry_policy, set_operation_timeout,
+        set_startup_device_wait, set_startup_key_policy, set_udev_settle_policy, CommandSandbox,
+        DevicePathStyle, DmRetryPolicy, StartupKeyPolicy, UdevSettlePolicy,
+    },
+    stratis::{run, EventSink, StratisError, StratisResult, VERSION},
+};
 
 const STRATISD_PID_PATH: &str = "/run/stratisd.pid";
 const STRATISD_MIN_PID_PATH: &str = "/run/stratisd-min.pid";
@@ -123,6 +133,96 @@ fn main() {
                 .long("sim")
                 .help("Use simulator engine"),
         )
+        .arg(
+            Arg::with_name("read-only")
+                .long("read-only")
+                .help("Reject all D-Bus requests that would modify the engine's state"),
+        )
+        .arg(
+            Arg::with_name("dev-path")
+                .empty_values(false)
+                .long("dev-path")
+                .takes_value(true)
+                .help("Sets the directory in which the symlinks to filesystem devices are placed"),
+        )
+        .arg(
+            Arg::with_name("dm-retries")
+                .empty_values(false)
+                .long("dm-retries")
+                .takes_value(true)
+                .validator(|s| s.parse::<u32>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Sets how often a devicemapper operation that failed because a device was busy is retried"),
+        )
+        .arg(
+            Arg::with_name("dm-retry-backoff")
+                .empty_values(false)
+                .long("dm-retry-backoff")
+                .takes_value(true)
+                .validator(|s| s.parse::<u64>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Sets the time in milliseconds to wait before the first retry of a devicemapper operation; it is doubled for each further retry"),
+        )
+        .arg(
+            Arg::with_name("startup-device-wait")
+                .empty_values(false)
+                .long("startup-device-wait")
+                .takes_value(true)
+                .validator(|s| s.parse::<u64>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Sets the time in seconds to wait at startup for the missing devices of pools before leaving the pools to be set up when the devices appear"),
+        )
+        .arg(
+            Arg::with_name("startup-key-policy")
+                .empty_values(false)
+                .long("startup-key-policy")
+                .takes_value(true)
+                .validator(|s| StartupKeyPolicy::try_from(s.as_str()).map(|_| ()).map_err(|e| e.to_string()))
+                .help("Sets how encrypted pools bound to a key in the kernel keyring are unlocked at startup: manual leaves them locked, fail-fast unlocks those whose key is present, and wait:SECONDS[:INTERVAL_SECONDS] also looks for missing keys again until SECONDS have passed"),
+        )
+        .arg(
+            Arg::with_name("operation-timeout")
+                .empty_values(false)
+                .long("operation-timeout")
+                .takes_value(true)
+                .validator(|s| s.parse::<u64>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Sets the time in seconds that a D-Bus request waits for the engine to finish its current operation before failing with a timeout error"),
+        )
+        .arg(
+            Arg::with_name("udev-settle-timeout")
+                .empty_values(false)
+                .long("udev-settle-timeout")
+                .takes_value(true)
+                .validator(|s| s.parse::<u64>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Sets the time in seconds that the engine waits at most for udev to process the events for the devices it changes"),
+        )
+        .arg(
+            Arg::with_name("udev-settle-targeted")
+                .long("udev-settle-targeted")
+                .help("Waits only until udev has released a device that is about to be removed, rather than until udev has processed all events"),
+        )
+        .arg(
+            Arg::with_name("command-clean-env")
+                .long("command-clean-env")
+                .help("Runs external commands with only a fixed PATH and the C locale in their environment, rather than with the environment of stratisd"),
+        )
+        .arg(
+            Arg::with_name("command-namespaces")
+                .long("command-namespaces")
+                .help("Runs the external commands that operate on devices and filesystems in network, IPC, and UTS namespaces of their own"),
+        )
+        .arg(
+            Arg::with_name("device-path-style")
+                .empty_values(false)
+                .long("device-path-style")
+                .possible_values(&["devnode", "by-id", "by-path"])
+                .help("Sets the kind of path by which block devices are reported"),
+        )
+        .arg(
+            Arg::with_name("event-sink")
+                .empty_values(false)
+                .long("event-sink")
+                .takes_value(true)
+                .validator(|s| EventSink::try_from(s.as_str()).map(|_| ()).map_err(|e| e.to_string()))
+                .help("Forwards engine events as JSON to udp:HOST:PORT, to the local syslog daemon with syslog, or to a remote one with syslog:HOST:PORT"),
+        )
         .arg(
             Arg::with_name("log-level")
                 .empty_values(false)
@@ -141,7 +241,57 @@ fn main() {
             Err(err) => Err(err),
             Ok(_) => {
                 initialize_log(matches.value_of("log-level"));
-                run(matches.is_present("sim"))
+                let default_policy = DmRetryPolicy::default();
+                set_dm_retry_policy(DmRetryPolicy {
+                    attempts: matches
+                        .value_of("dm-retries")
+                        .map(|s| s.parse::<u32>().expect("validated by argument parser") + 1)
+                        .unwrap_or(default_policy.attempts),
+                    backoff: matches
+                        .value_of("dm-retry-backoff")
+                        .map(|s| {
+                            Duration::from_millis(s.parse().expect("validated by argument parser"))
+                        })
+                        .unwrap_or(default_policy.backoff),
+                });
+                if let Some(wait) = matches.value_of("startup-device-wait") {
+                    set_startup_device_wait(Duration::from_secs(
+                        wait.parse().expect("validated by argument parser"),
+                    ));
+                }
+                if let Some(policy) = matches.value_of("startup-key-policy") {
+                    set_startup_key_policy(
+                        StartupKeyPolicy::try_from(policy).expect("validated by argument parser"),
+                    );
+                }
+                if let Some(timeout) = matches.value_of("operation-timeout") {
+                    set_operation_timeout(Some(Duration::from_secs(
+                        timeout.parse().expect("validated by argument parser"),
+                    )));
+                }
+                set_udev_settle_policy(UdevSettlePolicy {
+                    timeout: matches.value_of("udev-settle-timeout").map(|timeout| {
+                        Duration::from_secs(timeout.parse().expect("validated by argument parser"))
+                    }),
+                    targeted: matches.is_present("udev-settle-targeted"),
+                });
+                set_command_sandbox(CommandSandbox {
+                    clean_env: matches.is_present("command-clean-env"),
+                    namespaces: matches.is_present("command-namespaces"),
+                });
+                if let Some(style) = matches.value_of("device-path-style") {
+                    set_device_path_style(
+                        DevicePathStyle::try_from(style).expect("validated by argument parser"),
+                    );
+                }
+                run(
+                    matches.is_present("sim"),
+                    matches.is_present("read-only"),
+                    matches.value_of("dev-path").map(Path::new),
+                    matches.value_of("event-sink").map(|sink| {
+                        EventSink::try_from(sink).expect("validated by argument parser")
+                    }),
+                )
             }
         }
     };
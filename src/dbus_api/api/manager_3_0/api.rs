@@ -2,20 +2,27 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use dbus_tree::{Access, EmitsChangedSignal, Factory, MTSync, Method, Property};
+use dbus_tree::{Access, EmitsChangedSignal, Factory, MTSync, Method, Property, Signal};
 
 use crate::dbus_api::{
     api::manager_3_0::{
         methods::{
-            create_pool, destroy_pool, engine_state_report, set_key, unlock_pool, unset_key,
+            active_operations, benchmark_device, capabilities, create_from_template, create_pool,
+            destroy_pool, device_allocation_preview, encryption_audit, engine_state_report,
+            events_since, export_pool, list_objects, lock_stats, move_filesystem, pause_monitoring,
+            pending_background_work, reconcile, report_versions, resource_usage, resume_monitoring,
+            set_key, set_read_only_mode, set_udev_settle_policy, start_pool, startup_order,
+            startup_progress, transaction, udev_settle_policy, unlock_pool, unset_key,
+            validate_device_set, validate_pool_name, version_info,
         },
         props::get_version,
     },
-    types::TData,
+    consts,
+    types::{MethodAccess, TData},
 };
 
 pub fn destroy_pool_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("DestroyPool", (), destroy_pool)
+    f.method("DestroyPool", MethodAccess::ReadWrite, destroy_pool)
         .in_arg(("pool", "o"))
         // In order from left to right:
         // b: true if a valid UUID is returned - otherwise no action was performed
@@ -27,6 +34,22 @@ pub fn destroy_pool_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<T
         .out_arg(("return_string", "s"))
 }
 
+pub fn export_pool_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("ExportPool", MethodAccess::ReadWrite, export_pool)
+        .in_arg(("pool", "o"))
+        // The pool is torn down without touching its data and is not set up
+        // again until it is started with StartPool.
+        //
+        // In order from left to right:
+        // b: true if a valid UUID is returned - otherwise no action was performed
+        // s: String representation of pool UUID that was exported
+        //
+        // Rust representation: (bool, String)
+        .out_arg(("result", "(bs)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
 pub fn version_property(f: &Factory<MTSync<TData>, TData>) -> Property<MTSync<TData>, TData> {
     f.property::<&str, _>("Version", ())
         .access(Access::Read)
@@ -34,7 +57,7 @@ pub fn version_property(f: &Factory<MTSync<TData>, TData>) -> Property<MTSync<TD
         .on_get(get_version)
 }
 pub fn unset_key_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("UnsetKey", (), unset_key)
+    f.method("UnsetKey", MethodAccess::ReadWrite, unset_key)
         .in_arg(("key_desc", "s"))
         // b: true if the key was unset from the keyring. false if the key
         //    was not present in the keyring before the operation.
@@ -46,7 +69,7 @@ pub fn unset_key_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TDat
 }
 
 pub fn set_key_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("SetKey", (), set_key)
+    f.method("SetKey", MethodAccess::ReadWrite, set_key)
         .in_arg(("key_desc", "s"))
         .in_arg(("key_fd", "h"))
         .in_arg(("interactive", "b"))
@@ -61,7 +84,7 @@ pub fn set_key_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>
 }
 
 pub fn unlock_pool_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("UnlockPool", (), unlock_pool)
+    f.method("UnlockPool", MethodAccess::ReadWrite, unlock_pool)
         .in_arg(("pool_uuid", "s"))
         .in_arg(("unlock_method", "s"))
         // b: true if some encrypted devices were newly opened.
@@ -74,20 +97,449 @@ pub fn unlock_pool_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TD
         .out_arg(("return_string", "s"))
 }
 
+pub fn start_pool_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("StartPool", MethodAccess::ReadWrite, start_pool)
+        .in_arg(("pool_uuid", "s"))
+        // In order from left to right:
+        // b: true if the pool was newly started
+        // o: the object path of the started pool
+        //
+        // Rust representation: (bool, dbus::Path)
+        .out_arg(("result", "(bo)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn startup_order_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("StartupOrder", MethodAccess::ReadOnly, startup_order)
+        // a(ss): Array of tuples of the UUID and the name of each pool that
+        // is started automatically, in the order in which the pools are
+        // started when stratisd starts.
+        //
+        // Rust representation: Vec<(String, String)>
+        .out_arg(("results", "a(ss)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn startup_progress_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("StartupProgress", MethodAccess::ReadOnly, startup_progress)
+        // In order from left to right:
+        // b: true if the scan and the setup are complete; if not, the
+        //    values are those so far
+        // t: the number of devices of pools found by the startup scan
+        // t: the number of pools set up at startup
+        // as: the UUIDs of the pools found at startup which are still
+        //     waiting for missing devices
+        // t: the time taken by the scan and the setup in milliseconds
+        //
+        // Rust representation: (bool, u64, u64, Vec<String>, u64)
+        .out_arg(("result", "(bttast)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn encryption_audit_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("EncryptionAudit", MethodAccess::ReadOnly, encryption_audit)
+        // In order from left to right, for each encrypted pool:
+        // s: the UUID of the pool
+        // (bs): the name of the pool, if it is not locked
+        // s: the cipher used to encrypt the pool
+        // t: the size of the media encryption key in bits
+        // (b(uuu)): the PBKDF iterations, memory in KiB, and parallelism of
+        //           the keyring keyslot, if they were specified
+        // b: true if the pool is bound to a key in the kernel keyring
+        // (bs): the Clevis pin the pool is bound with, if any
+        // (b(uu)): the number of keyslots in use and the number of keyslots
+        //          available, if they could be read
+        //
+        // Rust representation: Vec<(String, (bool, String), String, u64,
+        //     (bool, (u32, u32, u32)), bool, (bool, String), (bool, (u32, u32)))>
+        .out_arg(("result", "a(s(bs)st(b(uuu))b(bs)(b(uu)))"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn reconcile_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("Reconcile", MethodAccess::ReadWrite, reconcile)
+        // In order from left to right, for each pool that is set up:
+        // s: the UUID of the pool
+        // (bs): the reason the metadata of the pool could not be reloaded,
+        //       if it could not
+        // as: the differences that were corrected by adopting the value
+        //     recorded in the metadata on disk
+        // as: the differences that can only be corrected by stopping the
+        //     pool and starting it again
+        //
+        // Rust representation: Vec<(String, (bool, String), Vec<String>, Vec<String>)>
+        .out_arg(("results", "a(s(bs)asas)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn events_since_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("EventsSince", MethodAccess::ReadOnly, events_since)
+        // t: the sequence number of the first event to return
+        .in_arg(("since", "t"))
+        // a(tss): Array of the events kept with a sequence number of at
+        // least since, oldest first, each with its sequence number, the
+        // time it occurred, and the event as a JSON object. Only the most
+        // recent events are kept; a gap between since and the first
+        // sequence number shows that events were missed.
+        // t: the sequence number that the next event will get, to pass as
+        // since in the next call
+        //
+        // Rust representation: (Vec<(u64, String, String)>, u64)
+        .out_arg(("results", "(a(tss)t)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn capabilities_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("Capabilities", MethodAccess::ReadOnly, capabilities)
+        // a{ss}: Dictionary mapping the name of each kernel feature on which
+        // some operations depend to "supported", "unsupported", or
+        // "unknown", if support could not be determined yet
+        //
+        // Rust representation: HashMap<String, String>
+        .out_arg(("results", "a{ss}"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn lock_stats_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("LockStats", MethodAccess::ReadOnly, lock_stats)
+        // a(sttt): Array of the contention on each of the engine's major
+        // locks: the name of the lock, the number of tasks waiting for it,
+        // the number of times it has been acquired, and the total time spent
+        // waiting for it in microseconds
+        //
+        // Rust representation: Vec<(String, u64, u64, u64)>
+        .out_arg(("results", "a(sttt)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn resource_usage_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("ResourceUsage", MethodAccess::ReadOnly, resource_usage)
+        // In order from left to right:
+        // t: the resident set size of the daemon in bytes
+        // t: the number of file descriptors the daemon has open
+        // t: the number of devicemapper devices managed by the daemon
+        //
+        // Rust representation: (u64, u64, u64)
+        .out_arg(("result", "(ttt)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn list_objects_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("ListObjects", MethodAccess::ReadOnly, list_objects)
+        // a(oss): Array of tuples of the object path, the type, one of
+        // "pool", "filesystem", or "blockdev", and the UUID of each pool,
+        // filesystem, and blockdev object
+        //
+        // Rust representation: Vec<(dbus::Path, &str, String)>
+        .out_arg(("results", "a(oss)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn report_versions_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("ReportVersions", MethodAccess::ReadOnly, report_versions)
+        // a(st): Array of tuples of the name of each report, including the
+        // engine state report, and the version of its JSON schema, which is
+        // also the value of the report's report_version field
+        //
+        // Rust representation: Vec<(&str, u64)>
+        .out_arg(("results", "a(st)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn version_info_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("VersionInfo", MethodAccess::ReadOnly, version_info)
+        // In order from left to right:
+        // s: the version of stratisd
+        // t: the newest version of the pool metadata format that stratisd
+        // supports
+        // a(sst): for each pool, its UUID, its name, and the version of the
+        // format of its metadata
+        //
+        // Rust representation: (&str, u64, Vec<(String, String, u64)>)
+        .out_arg(("results", "(sta(sst))"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn benchmark_device_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("BenchmarkDevice", MethodAccess::ReadWrite, benchmark_device)
+        // s: the device node of a device that is not in use
+        .in_arg(("devnode", "s"))
+        // In order from left to right:
+        // t: sequential read throughput in bytes per second
+        // t: sequential write throughput in bytes per second
+        // t: random 4 KiB read throughput in bytes per second
+        // t: random 4 KiB write throughput in bytes per second
+        // t: mean random read latency in microseconds
+        // t: mean random write latency in microseconds
+        //
+        // Rust representation: (u64, u64, u64, u64, u64, u64)
+        .out_arg(("result", "(tttttt)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn device_allocation_preview_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "DeviceAllocationPreview",
+        MethodAccess::ReadOnly,
+        device_allocation_preview,
+    )
+    // s: the device node of a device that is not in use
+    .in_arg(("devnode", "s"))
+    // b: true if the device would be encrypted
+    .in_arg(("encrypted", "b"))
+    // In order from left to right:
+    // t: logical sector size in bytes
+    // t: physical sector size in bytes
+    // t: optimal I/O size in bytes, 0 if the device reports none
+    // t: alignment that suits the device in bytes
+    // t: size of the extents in which data is allocated in sectors
+    // b: true if the data region starts at a multiple of the alignment
+    // a(stt): the regions of the device, in order, as the name, the
+    //         start in sectors, and the length in sectors of each
+    //
+    // Rust representation: (u64, u64, u64, u64, u64, bool, Vec<(String, u64, u64)>)
+    .out_arg(("result", "(tttttba(stt))"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn validate_device_set_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "ValidateDeviceSet",
+        MethodAccess::ReadOnly,
+        validate_device_set,
+    )
+    .in_arg(("devices", "as"))
+    // b: true if the pool to be created would be encrypted
+    .in_arg(("encrypted", "b"))
+    // In order from left to right:
+    // b: true if a pool could be created from all the devices
+    // s: total space in bytes that would be available for data
+    // a(ssss): for each device, in the order specified, the device
+    // node, the verdict, one of "usable", "too_small", "claimed",
+    // "foreign_signature", "wrong_sector_size", "duplicate", or
+    // "unavailable", a description of the verdict, and the space in
+    // bytes that would be available for data on the device
+    //
+    // Rust representation: (bool, String, Vec<(String, &str, String, String)>)
+    .out_arg(("results", "(bsa(ssss))"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn validate_pool_name_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "ValidatePoolName",
+        MethodAccess::ReadOnly,
+        validate_pool_name,
+    )
+    .in_arg(("name", "s"))
+    // as: the reasons why a new pool could not be given the name, none
+    // if it could
+    .out_arg(("results", "as"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
 pub fn engine_state_report_method(
     f: &Factory<MTSync<TData>, TData>,
 ) -> Method<MTSync<TData>, TData> {
-    f.method("EngineStateReport", (), engine_state_report)
-        // s: JSON engine state report as a string.
+    f.method(
+        "EngineStateReport",
+        MethodAccess::ReadOnly,
+        engine_state_report,
+    )
+    // s: JSON engine state report as a string.
+    //
+    // Rust representation: Value
+    .out_arg(("result", "s"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn set_read_only_mode_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method("SetReadOnlyMode", MethodAccess::Control, set_read_only_mode)
+        // b: true to reject all requests that would modify the engine's state
+        .in_arg(("read_only", "b"))
+        // b: true if the mode was changed
+        .out_arg(("result", "b"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn pause_monitoring_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("PauseMonitoring", MethodAccess::Control, pause_monitoring)
+        // b: true if monitoring was running before
+        .out_arg(("result", "b"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn resume_monitoring_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("ResumeMonitoring", MethodAccess::Control, resume_monitoring)
+        // b: true if monitoring was paused before
+        .out_arg(("result", "b"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn udev_settle_policy_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "UdevSettlePolicy",
+        MethodAccess::ReadOnly,
+        udev_settle_policy,
+    )
+    // (bt): the longest time in seconds to wait for udev, if any
+    // b: true if only the device about to be removed is waited for
+    //
+    // Rust representation: ((bool, u64), bool)
+    .out_arg(("result", "((bt)b)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn set_udev_settle_policy_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "SetUdevSettlePolicy",
+        MethodAccess::ReadWrite,
+        set_udev_settle_policy,
+    )
+    // (bt): the longest time in seconds to wait for udev, if any
+    .in_arg(("timeout", "(bt)"))
+    // b: true to wait only for the device about to be removed
+    .in_arg(("targeted", "b"))
+    // b: true if the policy was changed
+    .out_arg(("result", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn active_operations_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "ActiveOperations",
+        MethodAccess::ReadOnly,
+        active_operations,
+    )
+    // a(sot): Array of tuples of the D-Bus method name, the object path
+    // the method was called on, and the time in milliseconds since
+    // handling of the method call started, oldest first. This includes
+    // method calls that are waiting for another method call to finish.
+    //
+    // Rust representation: Vec<(String, dbus::Path, u64)>
+    .out_arg(("results", "a(sot)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn pending_background_work_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "PendingBackgroundWork",
+        MethodAccess::ReadOnly,
+        pending_background_work,
+    )
+    // a(os(b(tt))): Array of the work in progress on pools which would be
+    // interrupted by stopping stratisd. In order from left to right:
+    // o: the object path of the pool
+    // s: the operation in progress on the pool, e.g. "thin_repair", or
+    // the D-Bus method being handled on the pool or one of its
+    // filesystems or blockdevs
+    // b: true if the method has reported its progress
+    // tt: the amount of work done so far and the total amount of work
+    //
+    // Rust representation: Vec<(dbus::Path, String, (bool, (u64, u64)))>
+    .out_arg(("results", "a(os(b(tt)))"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn move_filesystem_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("MoveFilesystem", MethodAccess::ReadWrite, move_filesystem)
+        .in_arg(("fs_uuid", "s"))
+        .in_arg(("dest_pool_uuid", "s"))
+        // o: Object path of the filesystem in the destination pool
+        .out_arg(("result", "o"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn move_filesystem_progress_signal(f: &Factory<MTSync<TData>, TData>) -> Signal<TData> {
+    f.signal(consts::MANAGER_MOVE_FILESYSTEM_PROGRESS_SIGNAL, ())
+        // t: The number of sectors copied so far
+        .sarg::<u64, _>("copied")
+        // t: The total number of sectors to copy
+        .sarg::<u64, _>("total")
+}
+
+pub fn create_from_template_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "CreateFromTemplate",
+        MethodAccess::ReadWrite,
+        create_from_template,
+    )
+    .in_arg(("name", "s"))
+    // s: JSON template of a pool configuration, as returned by
+    // Pool.ExportConfig
+    .in_arg(("template", "s"))
+    .in_arg(("devices", "as"))
+    // In order from left to right:
+    // b: true if a pool was created and object paths were returned
+    // o: Object path for Pool
+    // a(o): Array of object paths for block devices
+    //
+    // Rust representation: (bool, (dbus::Path, Vec<dbus::Path>))
+    .out_arg(("result", "(b(oao))"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn transaction_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("Transaction", MethodAccess::ReadWrite, transaction)
+        // s: JSON array of operations, performed in order as a unit; if one
+        // fails, the changes made by the ones before it are reverted. Each
+        // operation is an object with an "operation" key, one of
+        // "create_pool", "create_filesystem", "bind_keyring", or
+        // "bind_clevis"
+        .in_arg(("operations", "s"))
+        // b: true if any change was made
+        // ao: Object paths of the pools and filesystems created
         //
-        // Rust representation: Value
-        .out_arg(("result", "s"))
+        // Rust representation: (bool, Vec<dbus::Path>)
+        .out_arg(("result", "(bao)"))
         .out_arg(("return_code", "q"))
         .out_arg(("return_string", "s"))
 }
 
 pub fn create_pool_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("CreatePool", (), create_pool)
+    f.method("CreatePool", MethodAccess::ReadWrite, create_pool)
         .in_arg(("name", "s"))
         .in_arg(("redundancy", "(bq)"))
         .in_arg(("devices", "as"))
@@ -106,6 +558,41 @@ pub fn create_pool_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TD
         //
         // Rust representation: (bool, (String, String))
         .in_arg(("clevis_info", "(b(ss))"))
+        // Optional cipher to encrypt the pool with, if the pool is encrypted.
+        // If not specified, aes-xts-plain64 with a 512 bit key is used.
+        // b: true if a cipher is specified
+        // s: cipher, one of "aes-xts-plain64", "serpent-xts-plain64", or
+        // "twofish-xts-plain64"
+        // t: key size in bits, either 256 or 512
+        //
+        // Rust representation: (bool, (String, u64))
+        .in_arg(("cipher_info", "(b(st))"))
+        // Optional stripe configuration for the data tier. If not specified,
+        // the devices in the data tier are concatenated.
+        // b: true if the data tier should be striped
+        // q: the number of devices over which data is striped, at least 2
+        // t: the stripe size in sectors, a power of two no less than 8
+        //
+        // Rust representation: (bool, (u16, u64))
+        .in_arg(("stripe_info", "(b(qt))"))
+        // Optional PBKDF parameters for the passphrase in the kernel keyring,
+        // if the pool is encrypted with one. If not specified, cryptsetup
+        // chooses them by benchmarking each device.
+        // b: true if PBKDF parameters are specified
+        // u: the number of iterations of argon2id, at least 4
+        // u: the memory used by argon2id in KiB, from 32 to 4194304
+        // u: the number of threads used by argon2id, from 1 to 4
+        //
+        // Rust representation: (bool, (u32, u32, u32))
+        .in_arg(("pbkdf_params", "(b(uuu))"))
+        // Optional directory in which the LUKS2 header of each device is
+        // stored in a file instead of on the device. Requires that the pool
+        // is encrypted with a key description only.
+        // b: true if a header directory is specified
+        // s: the absolute path of the directory
+        //
+        // Rust representation: (bool, String)
+        .in_arg(("header_dir", "(bs)"))
         // In order from left to right:
         // b: true if a pool was created and object paths were returned
         // o: Object path for Pool
@@ -2,27 +2,39 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use std::{convert::TryFrom, os::unix::io::AsRawFd, path::Path};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
+use chrono::SecondsFormat;
 use dbus::{
     arg::{Array, OwnedFd},
     Message,
 };
 use dbus_tree::{MTSync, MethodInfo, MethodResult};
 
+use devicemapper::{Bytes, Sectors};
+
 use crate::{
     dbus_api::{
         blockdev::create_dbus_blockdev,
         consts,
-        pool::create_dbus_pool,
+        filesystem::create_dbus_filesystem,
+        pool::{create_dbus_pool, register_pool},
         types::{DbusErrorEnum, TData, OK_STRING},
-        util::{engine_to_dbus_err_tuple, get_next_arg, tuple_to_option},
+        util::{engine_to_dbus_err_tuple, get_next_arg, option_to_tuple, tuple_to_option},
     },
     engine::{
-        CreateAction, DeleteAction, EncryptionInfo, EngineAction, KeyDescription,
-        MappingCreateAction, MappingDeleteAction, Name, PoolUuid, UnlockMethod,
+        self, CipherInfo, CreateAction, DeleteAction, DeviceVerdict, EncryptionInfo, Engine,
+        EngineAction, FilesystemUuid, KeyDescription, MappingCreateAction, MappingDeleteAction,
+        Name, PbkdfParams, PoolTemplate, PoolUuid, StratisUuid, StripeConfig, TransactionEffect,
+        TransactionOperation, UdevSettlePolicy, UnlockMethod, STRAT_METADATA_VERSION,
     },
-    stratis::StratisError,
+    stratis::{StratisError, VERSION},
 };
 
 type EncryptionParams = (Option<(bool, String)>, Option<(bool, (String, String))>);
@@ -54,7 +66,56 @@ pub fn destroy_pool(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
         }
     };
 
-    let msg = match log_action!(dbus_context.engine.blocking_lock().destroy_pool(pool_uuid)) {
+    let msg = match log_action!(dbus_context.lock_engine()?.destroy_pool(pool_uuid)) {
+        Ok(DeleteAction::Deleted(uuid)) => {
+            dbus_context.push_remove(&pool_path, consts::pool_interface_list());
+            return_message.append3(
+                (true, uuid_to_string!(uuid)),
+                DbusErrorEnum::OK as u16,
+                OK_STRING.to_string(),
+            )
+        }
+        Ok(DeleteAction::Identity) => return_message.append3(
+            default_return,
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn export_pool(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let pool_path: dbus::Path<'static> = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+
+    let default_return = (false, uuid_to_string!(PoolUuid::nil()));
+    let return_message = message.method_return();
+
+    let pool_uuid = match m
+        .tree
+        .get(&pool_path)
+        .and_then(|op| op.get_data().as_ref())
+        .map(|d| &d.uuid)
+    {
+        Some(uuid) => *typed_uuid!(uuid; Pool; default_return; return_message),
+        None => {
+            return Ok(vec![return_message.append3(
+                default_return,
+                DbusErrorEnum::OK as u16,
+                OK_STRING.to_string(),
+            )]);
+        }
+    };
+
+    let msg = match log_action!(dbus_context.lock_engine()?.export_pool(pool_uuid)) {
         Ok(DeleteAction::Deleted(uuid)) => {
             dbus_context.push_remove(&pool_path, consts::pool_interface_list());
             return_message.append3(
@@ -86,17 +147,15 @@ pub fn unset_key(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
     let default_return = false;
     let return_message = message.method_return();
 
-    let msg = match log_action!(dbus_context
-        .engine
-        .blocking_lock()
-        .get_key_handler_mut()
-        .unset(&match KeyDescription::try_from(key_desc_str) {
+    let msg = match log_action!(dbus_context.lock_engine()?.get_key_handler_mut().unset(
+        &match KeyDescription::try_from(key_desc_str) {
             Ok(kd) => kd,
             Err(e) => {
                 let (rc, rs) = engine_to_dbus_err_tuple(&e);
                 return Ok(vec![return_message.append3(default_return, rc, rs)]);
             }
-        })) {
+        }
+    )) {
         Ok(idem_resp) => {
             let return_value = matches!(idem_resp, MappingDeleteAction::Deleted(_));
             return_message.append3(
@@ -124,20 +183,16 @@ pub fn set_key(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
     let default_return = (false, false);
     let return_message = message.method_return();
 
-    let msg = match log_action!(dbus_context
-        .engine
-        .blocking_lock()
-        .get_key_handler_mut()
-        .set(
-            &match KeyDescription::try_from(key_desc_str) {
-                Ok(kd) => kd,
-                Err(e) => {
-                    let (rc, rs) = engine_to_dbus_err_tuple(&e);
-                    return Ok(vec![return_message.append3(default_return, rc, rs)]);
-                }
-            },
-            key_fd.as_raw_fd(),
-        )) {
+    let msg = match log_action!(dbus_context.lock_engine()?.get_key_handler_mut().set(
+        &match KeyDescription::try_from(key_desc_str) {
+            Ok(kd) => kd,
+            Err(e) => {
+                let (rc, rs) = engine_to_dbus_err_tuple(&e);
+                return Ok(vec![return_message.append3(default_return, rc, rs)]);
+            }
+        },
+        key_fd.as_raw_fd(),
+    )) {
         Ok(idem_resp) => {
             let return_value = match idem_resp {
                 MappingCreateAction::Created(_) => (true, false),
@@ -191,8 +246,7 @@ pub fn unlock_pool(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
     };
 
     let msg = match log_action!(dbus_context
-        .engine
-        .blocking_lock()
+        .lock_engine()?
         .unlock_pool(pool_uuid, unlock_method))
     {
         Ok(unlock_action) => match unlock_action.changed() {
@@ -218,115 +272,1154 @@ pub fn unlock_pool(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
     Ok(vec![msg])
 }
 
-pub fn engine_state_report(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+pub fn start_pool(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
     let message: &Message = m.msg;
+    let mut iter = message.iter_init();
 
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let default_return = (false, dbus::Path::default());
     let return_message = message.method_return();
-    let default_return = String::new();
 
-    let dbus_context = m.tree.get_data();
-    let lock = dbus_context.engine.blocking_lock();
+    let pool_uuid_str: &str = get_next_arg(&mut iter, 0)?;
+    let pool_uuid = match PoolUuid::parse_str(pool_uuid_str) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            let e = StratisError::Chained(
+                "Malformed UUID passed to StartPool".to_string(),
+                Box::new(e),
+            );
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
 
-    let msg = match serde_json::to_string(&lock.engine_state_report()) {
-        Ok(string) => {
-            return_message.append3(string, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let msg = match mutex_lock.start_pool(pool_uuid) {
+        Ok(CreateAction::Created(uuid)) => {
+            info!("Pool with UUID {} was started", uuid);
+            let (pool_name, pool) = get_pool!(mutex_lock; uuid; default_return; return_message);
+            let pool_path: dbus::Path =
+                register_pool(dbus_context, object_path.clone(), &pool_name, uuid, pool);
+            return_message.append3(
+                (true, pool_path),
+                DbusErrorEnum::OK as u16,
+                OK_STRING.to_string(),
+            )
         }
+        Ok(CreateAction::Identity) => return_message.append3(
+            default_return,
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
         Err(e) => {
-            let (rc, rs) = engine_to_dbus_err_tuple(&e.into());
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
             return_message.append3(default_return, rc, rs)
         }
     };
-
     Ok(vec![msg])
 }
 
-pub fn create_pool(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+pub fn startup_order(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
     let message: &Message = m.msg;
-    let mut iter = message.iter_init();
-
-    let name: &str = get_next_arg(&mut iter, 0)?;
-    let redundancy_tuple: (bool, u16) = get_next_arg(&mut iter, 1)?;
-    let devs: Array<&str, _> = get_next_arg(&mut iter, 2)?;
-    let (key_desc_tuple, clevis_tuple): EncryptionParams = (
-        Some(get_next_arg(&mut iter, 3)?),
-        Some(get_next_arg(&mut iter, 4)?),
-    );
 
+    let dbus_context = m.tree.get_data();
     let return_message = message.method_return();
 
-    let default_return: (bool, (dbus::Path<'static>, Vec<dbus::Path<'static>>)) =
-        (false, (dbus::Path::default(), Vec::new()));
+    let lock = dbus_context.lock_engine()?;
+    let order = lock
+        .startup_order()
+        .into_iter()
+        .filter_map(|uuid| {
+            lock.get_pool(uuid)
+                .map(|(name, _)| (uuid_to_string!(uuid), name.to_string()))
+        })
+        .collect::<Vec<_>>();
 
-    let key_desc = match key_desc_tuple.and_then(tuple_to_option) {
-        Some(kds) => match KeyDescription::try_from(kds) {
-            Ok(kd) => Some(kd),
-            Err(e) => {
-                let (rc, rs) = engine_to_dbus_err_tuple(&e);
-                return Ok(vec![return_message.append3(default_return, rc, rs)]);
-            }
-        },
-        None => None,
+    Ok(vec![return_message.append3(
+        order,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn startup_progress(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let return_message = message.method_return();
+
+    // While the engine is starting up, it is locked, so the progress so far
+    // is read from where the engine publishes it.
+    let progress = match engine::startup_progress_so_far() {
+        Some(progress) => progress,
+        None => dbus_context.lock_engine()?.startup_progress(),
     };
 
-    let clevis_info = match clevis_tuple.and_then(tuple_to_option) {
-        Some((pin, json_string)) => match serde_json::from_str(json_string.as_str()) {
-            Ok(j) => Some((pin, j)),
-            Err(e) => {
-                let (rc, rs) = engine_to_dbus_err_tuple(&StratisError::Serde(e));
-                return Ok(vec![return_message.append3(default_return, rc, rs)]);
+    Ok(vec![return_message.append3(
+        (
+            progress.complete,
+            progress.devices_scanned,
+            progress.pools_set_up,
+            progress
+                .pools_pending
+                .iter()
+                .map(|uuid| uuid_to_string!(uuid))
+                .collect::<Vec<_>>(),
+            u64::try_from(progress.duration.as_millis()).unwrap_or(u64::MAX),
+        ),
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn encryption_audit(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let return_message = message.method_return();
+
+    let audits = dbus_context
+        .lock_engine()?
+        .encryption_audit()
+        .into_iter()
+        .map(|audit| {
+            (
+                uuid_to_string!(audit.uuid),
+                option_to_tuple(audit.name.map(|n| n.to_string()), String::new()),
+                audit.cipher_info.cipher,
+                audit.cipher_info.key_size as u64,
+                option_to_tuple(
+                    audit
+                        .pbkdf_params
+                        .map(|p| (p.iterations, p.memory_kib, p.parallelism)),
+                    (0, 0, 0),
+                ),
+                audit.keyring,
+                option_to_tuple(audit.clevis_pin, String::new()),
+                option_to_tuple(audit.keyslots.map(|k| (k.used, k.total)), (0, 0)),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![return_message.append3(
+        audits,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn reconcile(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let return_message = message.method_return();
+
+    let results = dbus_context
+        .lock_engine()?
+        .reconcile()
+        .into_iter()
+        .map(|(uuid, result)| match result {
+            Ok(reconciliation) => (
+                uuid_to_string!(uuid),
+                (false, String::new()),
+                reconciliation.corrected,
+                reconciliation.unresolved,
+            ),
+            Err(err) => {
+                warn!(
+                    "Failed to reconcile pool with UUID {} with its metadata: {}",
+                    uuid, err
+                );
+                (
+                    uuid_to_string!(uuid),
+                    (true, err.to_string()),
+                    Vec::new(),
+                    Vec::new(),
+                )
             }
-        },
-        None => None,
-    };
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![return_message.append3(
+        results,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn events_since(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let since: u64 = get_next_arg(&mut iter, 0)?;
 
-    let object_path = m.path.get_name();
     let dbus_context = m.tree.get_data();
-    let mut mutex_lock = dbus_context.engine.blocking_lock();
-    let result = log_action!(mutex_lock.create_pool(
-        name,
-        &devs.map(|x| Path::new(x)).collect::<Vec<&Path>>(),
-        tuple_to_option(redundancy_tuple),
-        &EncryptionInfo {
-            key_description: key_desc,
-            clevis_info,
+    let return_message = message.method_return();
+
+    let (entries, next) = dbus_context.event_feed.events_since(since);
+    let events = entries
+        .into_iter()
+        .map(|entry| {
+            (
+                entry.seq,
+                entry.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+                entry.event,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![return_message.append3(
+        (events, next),
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn capabilities(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let return_message = message.method_return();
+
+    let features = dbus_context
+        .lock_engine()?
+        .kernel_features()
+        .into_iter()
+        .map(|(feature, support)| (feature.to_string(), support.to_string()))
+        .collect::<HashMap<_, _>>();
+
+    Ok(vec![return_message.append3(
+        features,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn lock_stats(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let return_message = message.method_return();
+
+    let stats = engine::lock_stats()
+        .into_iter()
+        .map(|(name, stats)| {
+            (
+                name.to_string(),
+                stats.waiters,
+                stats.acquisitions,
+                u64::try_from(stats.total_wait.as_micros()).unwrap_or(u64::MAX),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![return_message.append3(
+        stats,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn resource_usage(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let return_message = message.method_return();
+    let default_return = (0u64, 0u64, 0u64);
+
+    let dbus_context = m.tree.get_data();
+    let msg = match dbus_context.lock_engine()?.resource_usage() {
+        Ok(usage) => return_message.append3(
+            (
+                u64::try_from(*usage.rss).unwrap_or(u64::MAX),
+                usage.open_fds,
+                usage.dm_devices,
+            ),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
         }
-    ));
+    };
+    Ok(vec![msg])
+}
 
-    let msg = match result {
-        Ok(pool_uuid_action) => {
-            let results = match pool_uuid_action {
-                CreateAction::Created(uuid) => {
-                    let (_, pool) = get_pool!(mutex_lock; uuid; default_return; return_message);
+pub fn benchmark_device(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
 
-                    let pool_object_path: dbus::Path = create_dbus_pool(
-                        dbus_context,
-                        object_path.clone(),
-                        &Name::new(name.to_string()),
-                        uuid,
-                        pool,
-                    );
+    let devnode: &str = get_next_arg(&mut iter, 0)?;
 
-                    let bd_paths = pool
-                        .blockdevs()
-                        .into_iter()
-                        .map(|(uuid, tier, bd)| {
-                            create_dbus_blockdev(
-                                dbus_context,
-                                pool_object_path.clone(),
-                                uuid,
-                                tier,
-                                bd,
-                            )
-                        })
-                        .collect::<Vec<_>>();
-                    (true, (pool_object_path, bd_paths))
-                }
-                CreateAction::Identity => default_return,
-            };
-            return_message.append3(results, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+    let return_message = message.method_return();
+    let default_return = (0u64, 0u64, 0u64, 0u64, 0u64, 0u64);
+
+    let dbus_context = m.tree.get_data();
+    let work = match dbus_context
+        .lock_engine()?
+        .benchmark_device(Path::new(devnode))
+    {
+        Ok(work) => work,
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
         }
-        Err(x) => {
-            let (rc, rs) = engine_to_dbus_err_tuple(&x);
+    };
+
+    // The benchmark runs once the engine lock has been released, so that
+    // other requests are not held up.
+    let msg = match work(&mut |_, _| ()) {
+        Ok(benchmark) => return_message.append3(
+            (
+                benchmark.sequential_read,
+                benchmark.sequential_write,
+                benchmark.random_read,
+                benchmark.random_write,
+                benchmark.read_latency.as_micros() as u64,
+                benchmark.write_latency.as_micros() as u64,
+            ),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn device_allocation_preview(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let devnode: &str = get_next_arg(&mut iter, 0)?;
+    let encrypted: bool = get_next_arg(&mut iter, 1)?;
+
+    let return_message = message.method_return();
+    let default_return = (
+        0u64,
+        0u64,
+        0u64,
+        0u64,
+        0u64,
+        false,
+        Vec::<(String, u64, u64)>::new(),
+    );
+
+    let dbus_context = m.tree.get_data();
+    let msg = match dbus_context
+        .lock_engine()?
+        .device_allocation_preview(Path::new(devnode), encrypted)
+    {
+        Ok(preview) => return_message.append3(
+            (
+                preview.logical_sector_size,
+                preview.physical_sector_size,
+                preview.optimal_io_size,
+                preview.alignment,
+                *preview.extent_size,
+                preview.data_aligned,
+                preview
+                    .layout
+                    .into_iter()
+                    .map(|(name, start, length)| (name, *start, *length))
+                    .collect::<Vec<_>>(),
+            ),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn list_objects(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let return_message = message.method_return();
+
+    let objects = m
+        .tree
+        .iter()
+        .filter_map(|op| {
+            op.get_data().as_ref().map(|data| {
+                let (object_type, uuid) = match data.uuid {
+                    StratisUuid::Pool(uuid) => ("pool", uuid_to_string!(uuid)),
+                    StratisUuid::Fs(uuid) => ("filesystem", uuid_to_string!(uuid)),
+                    StratisUuid::Dev(uuid) => ("blockdev", uuid_to_string!(uuid)),
+                };
+                (op.get_name().clone(), object_type, uuid)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![return_message.append3(
+        objects,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn report_versions(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let return_message = message.method_return();
+
+    Ok(vec![return_message.append3(
+        engine::report_versions(),
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn version_info(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let return_message = message.method_return();
+
+    let dbus_context = m.tree.get_data();
+    let pools = dbus_context
+        .lock_engine()?
+        .pools()
+        .into_iter()
+        .map(|(name, uuid, pool)| {
+            (
+                uuid_to_string!(uuid),
+                name.to_string(),
+                pool.metadata_version(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![return_message.append3(
+        (VERSION, u64::from(STRAT_METADATA_VERSION), pools),
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn validate_device_set(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let devs: Array<&str, _> = get_next_arg(&mut iter, 0)?;
+    let encrypted: bool = get_next_arg(&mut iter, 1)?;
+
+    let return_message = message.method_return();
+    let default_return: (bool, String, Vec<(String, &str, String, String)>) =
+        (false, "0".to_string(), Vec::new());
+
+    let dbus_context = m.tree.get_data();
+    let result = dbus_context.lock_engine()?.validate_device_set(
+        &devs.map(|x| Path::new(x)).collect::<Vec<&Path>>(),
+        encrypted,
+    );
+
+    let msg = match result {
+        Ok(verdicts) => {
+            let feasible =
+                !verdicts.is_empty() && verdicts.iter().all(|(_, verdict)| verdict.is_usable());
+            let usable = |verdict: &DeviceVerdict| match verdict {
+                DeviceVerdict::Usable(size) => *size,
+                _ => Bytes(0),
+            };
+            let total = verdicts
+                .iter()
+                .map(|(_, verdict)| usable(verdict))
+                .fold(Bytes(0), |acc, size| acc + size);
+            let devices = verdicts
+                .iter()
+                .map(|(devnode, verdict)| {
+                    (
+                        devnode.display().to_string(),
+                        verdict.name(),
+                        verdict.to_string(),
+                        (*usable(verdict)).to_string(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            return_message.append3(
+                (feasible, (*total).to_string(), devices),
+                DbusErrorEnum::OK as u16,
+                OK_STRING.to_string(),
+            )
+        }
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn validate_pool_name(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let name: &str = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let problems = dbus_context.lock_engine()?.validate_pool_name(name);
+
+    Ok(vec![message.method_return().append3(
+        problems,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn engine_state_report(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let return_message = message.method_return();
+    let default_return = String::new();
+
+    let dbus_context = m.tree.get_data();
+    let lock = dbus_context.lock_engine()?;
+
+    let msg = match serde_json::to_string(&lock.engine_state_report()) {
+        Ok(string) => {
+            return_message.append3(string, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e.into());
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn create_pool(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let name: &str = get_next_arg(&mut iter, 0)?;
+    let redundancy_tuple: (bool, u16) = get_next_arg(&mut iter, 1)?;
+    let devs: Array<&str, _> = get_next_arg(&mut iter, 2)?;
+    let (key_desc_tuple, clevis_tuple): EncryptionParams = (
+        Some(get_next_arg(&mut iter, 3)?),
+        Some(get_next_arg(&mut iter, 4)?),
+    );
+    let cipher_tuple: (bool, (String, u64)) = get_next_arg(&mut iter, 5)?;
+    let stripe_tuple: (bool, (u16, u64)) = get_next_arg(&mut iter, 6)?;
+    let pbkdf_tuple: (bool, (u32, u32, u32)) = get_next_arg(&mut iter, 7)?;
+    let header_dir_tuple: (bool, &str) = get_next_arg(&mut iter, 8)?;
+
+    let return_message = message.method_return();
+
+    let default_return: (bool, (dbus::Path<'static>, Vec<dbus::Path<'static>>)) =
+        (false, (dbus::Path::default(), Vec::new()));
+
+    let key_desc = match key_desc_tuple.and_then(tuple_to_option) {
+        Some(kds) => match KeyDescription::try_from(kds) {
+            Ok(kd) => Some(kd),
+            Err(e) => {
+                let (rc, rs) = engine_to_dbus_err_tuple(&e);
+                return Ok(vec![return_message.append3(default_return, rc, rs)]);
+            }
+        },
+        None => None,
+    };
+
+    let clevis_info = match clevis_tuple.and_then(tuple_to_option) {
+        Some((pin, json_string)) => match serde_json::from_str(json_string.as_str()) {
+            Ok(j) => Some((pin, j)),
+            Err(e) => {
+                let (rc, rs) = engine_to_dbus_err_tuple(&StratisError::Serde(e));
+                return Ok(vec![return_message.append3(default_return, rc, rs)]);
+            }
+        },
+        None => None,
+    };
+
+    let cipher_info = match tuple_to_option(cipher_tuple) {
+        // A key size which does not fit in a usize is not supported anyway.
+        Some((cipher, key_size)) => {
+            match CipherInfo::new(&cipher, usize::try_from(key_size).unwrap_or(usize::MAX)) {
+                Ok(cipher_info) => cipher_info,
+                Err(e) => {
+                    let (rc, rs) = engine_to_dbus_err_tuple(&e);
+                    return Ok(vec![return_message.append3(default_return, rc, rs)]);
+                }
+            }
+        }
+        None => CipherInfo::default(),
+    };
+
+    let pbkdf_params = match tuple_to_option(pbkdf_tuple) {
+        Some((iterations, memory_kib, parallelism)) => {
+            match PbkdfParams::new(iterations, memory_kib, parallelism) {
+                Ok(params) => Some(params),
+                Err(e) => {
+                    let (rc, rs) = engine_to_dbus_err_tuple(&e);
+                    return Ok(vec![return_message.append3(default_return, rc, rs)]);
+                }
+            }
+        }
+        None => None,
+    };
+
+    let stripe = tuple_to_option(stripe_tuple).map(|(count, size)| StripeConfig {
+        count,
+        size: Sectors(size),
+    });
+
+    let object_path = m.path.get_name();
+    let dbus_context = m.tree.get_data();
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let result = log_action!(mutex_lock.create_pool(
+        name,
+        &devs.map(|x| Path::new(x)).collect::<Vec<&Path>>(),
+        tuple_to_option(redundancy_tuple),
+        &EncryptionInfo {
+            key_description: key_desc,
+            clevis_info,
+            cipher_info,
+            pbkdf_params,
+            header_dir: tuple_to_option(header_dir_tuple).map(PathBuf::from),
+        },
+        stripe
+    ));
+
+    let msg = match result {
+        Ok(pool_uuid_action) => {
+            let results = match pool_uuid_action {
+                CreateAction::Created(uuid) => {
+                    let (_, pool) = get_pool!(mutex_lock; uuid; default_return; return_message);
+
+                    let pool_object_path: dbus::Path = create_dbus_pool(
+                        dbus_context,
+                        object_path.clone(),
+                        &Name::new(name.to_string()),
+                        uuid,
+                        pool,
+                    );
+
+                    let bd_paths = pool
+                        .blockdevs()
+                        .into_iter()
+                        .map(|(uuid, tier, bd)| {
+                            create_dbus_blockdev(
+                                dbus_context,
+                                pool_object_path.clone(),
+                                uuid,
+                                tier,
+                                bd,
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    (true, (pool_object_path, bd_paths))
+                }
+                CreateAction::Identity => default_return,
+            };
+            return_message.append3(results, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(x) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&x);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn create_from_template(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let name: &str = get_next_arg(&mut iter, 0)?;
+    let template_str: &str = get_next_arg(&mut iter, 1)?;
+    let devs: Array<&str, _> = get_next_arg(&mut iter, 2)?;
+
+    let return_message = message.method_return();
+
+    let default_return: (bool, (dbus::Path<'static>, Vec<dbus::Path<'static>>)) =
+        (false, (dbus::Path::default(), Vec::new()));
+
+    let template: PoolTemplate = match serde_json::from_str(template_str) {
+        Ok(template) => template,
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&StratisError::Serde(e));
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let object_path = m.path.get_name();
+    let dbus_context = m.tree.get_data();
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let result = log_action!(mutex_lock.create_pool_from_template(
+        name,
+        &devs.map(|x| Path::new(x)).collect::<Vec<&Path>>(),
+        &template,
+    ));
+
+    let msg = match result {
+        Ok(pool_uuid_action) => {
+            let results = match pool_uuid_action {
+                CreateAction::Created(uuid) => {
+                    let (pool_name, pool) =
+                        get_pool!(mutex_lock; uuid; default_return; return_message);
+
+                    let pool_object_path: dbus::Path =
+                        create_dbus_pool(dbus_context, object_path.clone(), &pool_name, uuid, pool);
+
+                    for (fs_name, fs_uuid, fs) in pool.filesystems() {
+                        create_dbus_filesystem(
+                            dbus_context,
+                            pool_object_path.clone(),
+                            &pool_name,
+                            &fs_name,
+                            fs_uuid,
+                            fs,
+                        );
+                    }
+                    let bd_paths = pool
+                        .blockdevs()
+                        .into_iter()
+                        .map(|(uuid, tier, bd)| {
+                            create_dbus_blockdev(
+                                dbus_context,
+                                pool_object_path.clone(),
+                                uuid,
+                                tier,
+                                bd,
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    (true, (pool_object_path, bd_paths))
+                }
+                CreateAction::Identity => default_return,
+            };
+            return_message.append3(results, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(x) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&x);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn transaction(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let operations_str: &str = get_next_arg(&mut iter, 0)?;
+
+    let return_message = message.method_return();
+
+    let default_return: (bool, Vec<dbus::Path<'static>>) = (false, Vec::new());
+
+    let operations: Vec<TransactionOperation> = match serde_json::from_str(operations_str) {
+        Ok(operations) => operations,
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&StratisError::Serde(e));
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let object_path = m.path.get_name();
+    let dbus_context = m.tree.get_data();
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let effects = match log_action!(mutex_lock.transaction(&operations)) {
+        Ok(effects) => effects,
+        Err(x) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&x);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let mut pool_paths: HashMap<PoolUuid, dbus::Path<'static>> = HashMap::new();
+    let mut created_paths = Vec::new();
+    for effect in effects.iter() {
+        match *effect {
+            TransactionEffect::PoolCreated(pool_uuid) => {
+                let (pool_name, pool) =
+                    get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+                let pool_object_path = create_dbus_pool(
+                    dbus_context,
+                    object_path.clone(),
+                    &pool_name,
+                    pool_uuid,
+                    pool,
+                );
+                for (uuid, tier, bd) in pool.blockdevs() {
+                    create_dbus_blockdev(dbus_context, pool_object_path.clone(), uuid, tier, bd);
+                }
+                pool_paths.insert(pool_uuid, pool_object_path.clone());
+                created_paths.push(pool_object_path);
+            }
+            TransactionEffect::FilesystemCreated(pool_uuid, fs_uuid) => {
+                let pool_object_path = match pool_paths.get(&pool_uuid) {
+                    Some(path) => path.clone(),
+                    None => match m
+                        .tree
+                        .iter()
+                        .find(|op| {
+                            op.get_data().as_ref().map_or(
+                                false,
+                                |d| matches!(d.uuid, StratisUuid::Pool(u) if u == pool_uuid),
+                            )
+                        })
+                        .map(|op| op.get_name().clone())
+                    {
+                        Some(path) => {
+                            pool_paths.insert(pool_uuid, path.clone());
+                            path
+                        }
+                        None => continue,
+                    },
+                };
+                let (pool_name, pool) =
+                    get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+                let (fs_name, fs) = pool
+                    .get_filesystem(fs_uuid)
+                    .expect("filesystem was just created");
+                created_paths.push(create_dbus_filesystem(
+                    dbus_context,
+                    pool_object_path,
+                    &pool_name,
+                    &fs_name,
+                    fs_uuid,
+                    fs,
+                ));
+            }
+            TransactionEffect::KeyringBound(_) | TransactionEffect::ClevisBound(_) => (),
+        }
+    }
+
+    Ok(vec![return_message.append3(
+        (!effects.is_empty(), created_paths),
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn set_read_only_mode(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let read_only: bool = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let return_message = message.method_return();
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let changed = mutex_lock.set_read_only_mode(read_only);
+    if changed {
+        info!(
+            "{} read-only mode",
+            if read_only { "Entered" } else { "Left" }
+        );
+    }
+
+    Ok(vec![return_message.append3(
+        changed,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+/// Set up the pools whose setup was deferred while monitoring was paused,
+/// and register them with D-Bus.
+fn register_deferred_pools(m: &MethodInfo<MTSync<TData>, TData>, engine: &mut dyn Engine) {
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    for (pool_name, pool_uuid) in engine.process_deferred_events() {
+        info!("Pool with UUID {} was set up", pool_uuid);
+        if let Some((_, pool)) = engine.get_pool(pool_uuid) {
+            register_pool(
+                dbus_context,
+                object_path.clone(),
+                &pool_name,
+                pool_uuid,
+                pool,
+            );
+        }
+    }
+}
+
+/// Pause or resume monitoring, logging the change.
+fn set_monitoring_paused(m: &MethodInfo<MTSync<TData>, TData>, paused: bool) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let return_message = message.method_return();
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let changed = mutex_lock.set_monitoring_paused(paused);
+    if changed {
+        info!("{} monitoring", if paused { "Paused" } else { "Resumed" });
+        register_deferred_pools(m, &mut *mutex_lock);
+    }
+
+    Ok(vec![return_message.append3(
+        changed,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn pause_monitoring(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    set_monitoring_paused(m, true)
+}
+
+pub fn resume_monitoring(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    set_monitoring_paused(m, false)
+}
+
+pub fn udev_settle_policy(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let return_message = message.method_return();
+
+    let policy = engine::udev_settle_policy();
+    Ok(vec![return_message.append3(
+        (
+            option_to_tuple(policy.timeout.map(|timeout| timeout.as_secs()), 0),
+            policy.targeted,
+        ),
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn set_udev_settle_policy(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let timeout_tuple: (bool, u64) = get_next_arg(&mut iter, 0)?;
+    let targeted: bool = get_next_arg(&mut iter, 1)?;
+
+    let return_message = message.method_return();
+
+    let policy = UdevSettlePolicy {
+        timeout: tuple_to_option(timeout_tuple).map(Duration::from_secs),
+        targeted,
+    };
+    let changed = engine::udev_settle_policy() != policy;
+    if changed {
+        engine::set_udev_settle_policy(policy);
+        info!("Set udev settle policy to {:?}", policy);
+    }
+
+    Ok(vec![return_message.append3(
+        changed,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn active_operations(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let return_message = message.method_return();
+
+    // The engine lock is not taken, so that this method can report on
+    // method calls that are holding or waiting for it.
+    let operations = dbus_context
+        .active_operations()
+        .into_iter()
+        .map(|(method, target, elapsed, _)| {
+            (
+                method,
+                target,
+                // A method call running for longer than u64::MAX milliseconds
+                // is not a concern.
+                u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![return_message.append3(
+        operations,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn pending_background_work(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let return_message = message.method_return();
+
+    // The pool or the child of a pool on which a method is called determines
+    // the pool whose work it is.
+    // FIXME: A filesystem move is requested on the manager object, so it is
+    // not attributed to a pool; it is listed by ActiveOperations.
+    let pool_path = |target: &dbus::Path<'static>| {
+        m.tree
+            .get(target)
+            .and_then(|opath| opath.get_data().as_ref())
+            .and_then(|op_cxt| match op_cxt.uuid {
+                StratisUuid::Pool(_) => Some(target.clone()),
+                StratisUuid::Fs(_) | StratisUuid::Dev(_) => Some(op_cxt.parent.clone()),
+            })
+    };
+    let mut work = dbus_context
+        .active_operations()
+        .into_iter()
+        .filter_map(|(method, target, _, progress)| {
+            pool_path(&target).map(|path| (path, method, option_to_tuple(progress, (0, 0))))
+        })
+        .collect::<Vec<_>>();
+
+    // Do not wait for the engine: while a method call holds it, this method
+    // would not return until the call is done, which defeats its purpose.
+    // That method call is listed above.
+    if let Some(engine) = dbus_context
+        .engine
+        .blocking_lock_timeout(Some(Duration::from_secs(0)))
+    {
+        for (_, pool_uuid, pool) in engine.pools() {
+            let operation = match pool.operation_in_progress() {
+                Some(operation) => operation,
+                None => continue,
+            };
+            if let Some(path) = m
+                .tree
+                .iter()
+                .find(|op| {
+                    op.get_data().as_ref().map_or(
+                        false,
+                        |d| matches!(d.uuid, StratisUuid::Pool(u) if u == pool_uuid),
+                    )
+                })
+                .map(|op| op.get_name().clone())
+            {
+                work.push((path, operation.to_string(), (false, (0, 0))));
+            }
+        }
+    }
+
+    Ok(vec![return_message.append3(
+        work,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn move_filesystem(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let fs_uuid_str: &str = get_next_arg(&mut iter, 0)?;
+    let dest_pool_uuid_str: &str = get_next_arg(&mut iter, 1)?;
+
+    let dbus_context = m.tree.get_data();
+    let default_return = dbus::Path::default();
+    let return_message = message.method_return();
+
+    let fs_uuid = match FilesystemUuid::parse_str(fs_uuid_str) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            let e = StratisError::Chained(
+                "Malformed UUID passed to MoveFilesystem".to_string(),
+                Box::new(e),
+            );
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+    let dest_pool_uuid = match PoolUuid::parse_str(dest_pool_uuid_str) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            let e = StratisError::Chained(
+                "Malformed UUID passed to MoveFilesystem".to_string(),
+                Box::new(e),
+            );
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let fs_path = m
+        .tree
+        .iter()
+        .find(|op| {
+            op.get_data().as_ref().map_or(
+                false,
+                |d| matches!(d.uuid, StratisUuid::Fs(u) if u == fs_uuid),
+            )
+        })
+        .map(|op| op.get_name().clone());
+    let dest_pool_path = match m
+        .tree
+        .iter()
+        .find(|op| {
+            op.get_data().as_ref().map_or(
+                false,
+                |d| matches!(d.uuid, StratisUuid::Pool(u) if u == dest_pool_uuid),
+            )
+        })
+        .map(|op| op.get_name().clone())
+    {
+        Some(path) => path,
+        None => {
+            let (rc, rs) = (
+                DbusErrorEnum::ERROR as u16,
+                format!("No pool with UUID {} found", dest_pool_uuid),
+            );
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let work = match dbus_context
+        .lock_engine()?
+        .start_move_filesystem(fs_uuid, dest_pool_uuid)
+    {
+        Ok(work) => work,
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    // The data is copied once the engine lock has been released, so that
+    // other requests are not held up. A progress signal is sent each time
+    // another percent of the data has been copied.
+    let mut last_percent = 0;
+    let mut progress = |copied: Sectors, total: Sectors| {
+        let percent = *copied * 100 / *total;
+        if percent != last_percent {
+            last_percent = percent;
+            if let Some(ref fs_path) = fs_path {
+                dbus_context.push_move_filesystem_progress(fs_path, *copied, *total);
+            }
+        }
+    };
+
+    let copied = work(&mut progress);
+
+    // The move must be finished whatever the outcome of the copy, so the
+    // engine lock is waited for without a timeout.
+    let mut mutex_lock = dbus_context.engine.blocking_lock();
+    let msg = match mutex_lock.finish_move_filesystem(fs_uuid, dest_pool_uuid, copied) {
+        Ok((_, new_fs_uuid)) => {
+            info!(
+                "Moved filesystem with UUID {} to pool with UUID {} as filesystem with UUID {}",
+                fs_uuid, dest_pool_uuid, new_fs_uuid
+            );
+            if let Some(fs_path) = fs_path {
+                dbus_context.push_remove(&fs_path, consts::filesystem_interface_list());
+            }
+            let (dest_pool_name, dest_pool) = mutex_lock
+                .get_pool(dest_pool_uuid)
+                .expect("filesystem was just moved to this pool");
+            let (fs_name, fs) = dest_pool
+                .get_filesystem(new_fs_uuid)
+                .expect("filesystem was just created");
+            let new_fs_path = create_dbus_filesystem(
+                dbus_context,
+                dest_pool_path,
+                &dest_pool_name,
+                &fs_name,
+                new_fs_uuid,
+                fs,
+            );
+            return_message.append3(new_fs_path, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
             return_message.append3(default_return, rc, rs)
         }
     };
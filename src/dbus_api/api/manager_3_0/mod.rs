@@ -3,6 +3,15 @@ mod methods;
 mod props;
 
 pub use api::{
-    create_pool_method, destroy_pool_method, engine_state_report_method, set_key_method,
-    unlock_pool_method, unset_key_method, version_property,
+    active_operations_method, benchmark_device_method, capabilities_method,
+    create_from_template_method, create_pool_method, destroy_pool_method,
+    device_allocation_preview_method, encryption_audit_method, engine_state_report_method,
+    events_since_method, export_pool_method, list_objects_method, lock_stats_method,
+    move_filesystem_method, move_filesystem_progress_signal, pause_monitoring_method,
+    pending_background_work_method, reconcile_method, report_versions_method,
+    resource_usage_method, resume_monitoring_method, set_key_method, set_read_only_mode_method,
+    set_udev_settle_policy_method, start_pool_method, startup_order_method,
+    startup_progress_method, transaction_method, udev_settle_policy_method, unlock_pool_method,
+    unset_key_method, validate_device_set_method, validate_pool_name_method, version_info_method,
+    version_property,
 };
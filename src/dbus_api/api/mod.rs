@@ -40,11 +40,39 @@ pub fn get_base_tree<'a>(
         .add(
             f.interface(consts::MANAGER_INTERFACE_NAME_3_0, ())
                 .add_m(manager_3_0::create_pool_method(&f))
+                .add_m(manager_3_0::create_from_template_method(&f))
+                .add_m(manager_3_0::transaction_method(&f))
                 .add_m(manager_3_0::set_key_method(&f))
                 .add_m(manager_3_0::unset_key_method(&f))
                 .add_m(manager_3_0::unlock_pool_method(&f))
+                .add_m(manager_3_0::start_pool_method(&f))
+                .add_m(manager_3_0::startup_order_method(&f))
+                .add_m(manager_3_0::startup_progress_method(&f))
+                .add_m(manager_3_0::encryption_audit_method(&f))
+                .add_m(manager_3_0::reconcile_method(&f))
                 .add_m(manager_3_0::destroy_pool_method(&f))
                 .add_m(manager_3_0::engine_state_report_method(&f))
+                .add_m(manager_3_0::events_since_method(&f))
+                .add_m(manager_3_0::export_pool_method(&f))
+                .add_m(manager_3_0::set_read_only_mode_method(&f))
+                .add_m(manager_3_0::pause_monitoring_method(&f))
+                .add_m(manager_3_0::resume_monitoring_method(&f))
+                .add_m(manager_3_0::udev_settle_policy_method(&f))
+                .add_m(manager_3_0::set_udev_settle_policy_method(&f))
+                .add_m(manager_3_0::active_operations_method(&f))
+                .add_m(manager_3_0::pending_background_work_method(&f))
+                .add_m(manager_3_0::capabilities_method(&f))
+                .add_m(manager_3_0::list_objects_method(&f))
+                .add_m(manager_3_0::lock_stats_method(&f))
+                .add_m(manager_3_0::resource_usage_method(&f))
+                .add_m(manager_3_0::report_versions_method(&f))
+                .add_m(manager_3_0::validate_device_set_method(&f))
+                .add_m(manager_3_0::validate_pool_name_method(&f))
+                .add_m(manager_3_0::version_info_method(&f))
+                .add_m(manager_3_0::benchmark_device_method(&f))
+                .add_m(manager_3_0::device_allocation_preview_method(&f))
+                .add_m(manager_3_0::move_filesystem_method(&f))
+                .add_s(manager_3_0::move_filesystem_progress_signal(&f))
                 .add_p(manager_3_0::version_property(&f)),
         )
         .add(
@@ -4,10 +4,13 @@
 
 use dbus_tree::{Factory, MTSync, Method};
 
-use crate::dbus_api::{api::report_3_0::methods::get_report, types::TData};
+use crate::dbus_api::{
+    api::report_3_0::methods::get_report,
+    types::{MethodAccess, TData},
+};
 
 pub fn get_report_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("GetReport", (), get_report)
+    f.method("GetReport", MethodAccess::ReadOnly, get_report)
         .in_arg(("name", "s"))
         // The report is a JSON object.
         //
@@ -32,7 +32,7 @@ pub fn get_report(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
     };
 
     let dbus_context = m.tree.get_data();
-    let mutex_lock = dbus_context.engine.blocking_lock();
+    let mutex_lock = dbus_context.lock_engine()?;
 
     let msg = match serde_json::to_string(&mutex_lock.get_report(report_type)) {
         Ok(string) => {
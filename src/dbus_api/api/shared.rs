@@ -11,7 +11,7 @@ use crate::{
         blockdev::get_blockdev_properties,
         filesystem::get_fs_properties,
         pool::get_pool_properties,
-        types::{GetManagedObjects, InterfacesAddedThreadSafe, TData},
+        types::{GetManagedObjects, InterfacesAddedThreadSafe, MethodAccess, TData},
         util::thread_safe_to_dbus_sendable,
     },
     engine::{DevUuid, Engine, FilesystemUuid, PoolUuid, StratisUuid},
@@ -99,7 +99,7 @@ pub fn get_managed_objects_method(
 
     fn get_managed_objects(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
         let dbus_context = m.tree.get_data();
-        let engine = dbus_context.engine.blocking_lock();
+        let engine = dbus_context.lock_engine()?;
 
         let properties: GetManagedObjects = m
             .tree
@@ -133,5 +133,9 @@ pub fn get_managed_objects_method(
         Ok(vec![m.msg.method_return().append1(properties)])
     }
 
-    f.method("GetManagedObjects", (), get_managed_objects)
+    f.method(
+        "GetManagedObjects",
+        MethodAccess::ReadOnly,
+        get_managed_objects,
+    )
 }
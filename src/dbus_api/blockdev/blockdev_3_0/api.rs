@@ -13,12 +13,12 @@ use crate::dbus_api::{
         },
     },
     consts,
-    types::TData,
+    types::{MethodAccess, TData},
     util::{get_parent, get_uuid},
 };
 
 pub fn set_userid_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("SetUserInfo", (), set_user_info)
+    f.method("SetUserInfo", MethodAccess::ReadWrite, set_user_info)
         .in_arg(("id", "(bs)"))
         // b: false if no change to the user info
         // s: UUID of the changed device
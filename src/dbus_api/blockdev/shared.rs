@@ -7,7 +7,7 @@ use dbus_tree::{MTSync, MethodErr, PropInfo, Tree};
 
 use crate::{
     dbus_api::types::TData,
-    engine::{BlockDev, BlockDevTier},
+    engine::{device_path, device_path_style, BlockDev, BlockDevTier},
 };
 
 /// Perform an operation on a `BlockDev` object for a given
@@ -107,8 +107,11 @@ pub fn blockdev_tier_prop(tier: BlockDevTier) -> u16 {
     tier as u16
 }
 
-// Generate a D-Bus representation of the physical path
+// Generate a D-Bus representation of the physical path, in the style of
+// path set for reporting devices.
 #[inline]
 pub fn blockdev_physical_path_prop(dev: &dyn BlockDev) -> String {
-    dev.devnode().display().to_string()
+    device_path(dev.devnode(), device_path_style())
+        .display()
+        .to_string()
 }
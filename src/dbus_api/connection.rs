@@ -15,9 +15,10 @@ use dbus::{
     },
     channel::{default_reply, MatchingReceiver, Sender},
     message::{MatchRule, SignalArgs},
-    Path,
+    Message, Path,
 };
-use dbus_tree::{MTSync, Tree};
+use dbus_tree::{MTSync, MethodErr, Tree};
+use devicemapper::Bytes;
 use futures::{
     executor::block_on,
     future::{select, Either},
@@ -36,9 +37,11 @@ use crate::{
     dbus_api::{
         consts,
         types::{DbusAction, InterfacesAddedThreadSafe, InterfacesRemoved, LockableTree, TData},
-        util::thread_safe_to_dbus_sendable,
+        util::{option_to_tuple, thread_safe_to_dbus_sendable},
+    },
+    engine::{
+        operation_timeout, AlertMetric, ExclusiveGuard, FilesystemUuid, PoolUuid, StratisUuid,
     },
-    engine::{ExclusiveGuard, StratisUuid},
     stratis::{StratisError, StratisResult},
 };
 
@@ -232,6 +235,123 @@ impl DbusTreeHandler {
                     }
                 }
             }
+            DbusAction::PoolOperationChange(pool_uuid, operation) => {
+                if let Some(item) = find_pool_path(&write_lock, pool_uuid) {
+                    let mut changed = HashMap::new();
+                    changed.insert(
+                        consts::POOL_OPERATION_PROP.into(),
+                        Variant(Box::new(option_to_tuple(
+                            operation.map(|op| op.to_string()),
+                            String::new(),
+                        )) as Box<dyn RefArg>),
+                    );
+
+                    if self
+                        .property_changed_invalidated_signal(
+                            &item,
+                            changed,
+                            vec![],
+                            &consts::standard_pool_interfaces(),
+                        )
+                        .is_err()
+                    {
+                        warn!("Signal on pool operation change was not sent to the D-Bus client");
+                    }
+                } else {
+                    warn!(
+                        "No D-Bus object found for pool with UUID {}; no signal was sent for the change of its operation in progress",
+                        pool_uuid
+                    );
+                }
+            }
+            DbusAction::PoolMetadataWriteFailure(pool_uuid, policy, read_only, error) => {
+                if let Some(item) = find_pool_path(&write_lock, pool_uuid) {
+                    if self
+                        .metadata_write_failed_signal(&item, &policy.to_string(), read_only, &error)
+                        .is_err()
+                    {
+                        warn!("Signal on pool metadata write failure was not sent to the D-Bus client");
+                    }
+                } else {
+                    warn!(
+                        "No D-Bus object found for pool with UUID {}; no signal was sent for the failure to write its metadata",
+                        pool_uuid
+                    );
+                }
+            }
+            DbusAction::PoolAlert(pool_uuid, metric, value, threshold) => {
+                if let Some(item) = find_pool_path(&write_lock, pool_uuid) {
+                    if self
+                        .pool_alert_signal(&item, pool_uuid, metric, value, threshold)
+                        .is_err()
+                    {
+                        warn!("Signal on pool alert was not sent to the D-Bus client");
+                    }
+                } else {
+                    warn!(
+                        "No D-Bus object found for pool with UUID {}; no signal was sent for the alert on metric {}",
+                        pool_uuid, metric
+                    );
+                }
+            }
+            DbusAction::PoolMetadataUndersized(pool_uuid, current, recommended) => {
+                if let Some(item) = find_pool_path(&write_lock, pool_uuid) {
+                    if self
+                        .metadata_undersized_signal(&item, pool_uuid, current, recommended)
+                        .is_err()
+                    {
+                        warn!(
+                            "Signal on undersized pool metadata was not sent to the D-Bus client"
+                        );
+                    }
+                } else {
+                    warn!(
+                        "No D-Bus object found for pool with UUID {}; no signal was sent for its undersized metadata",
+                        pool_uuid
+                    );
+                }
+            }
+            DbusAction::PoolSnapshotPruned(pool_uuid, fs_uuid, name) => {
+                // The engine destroyed the snapshot on its own, so no method
+                // handler has removed its object from the tree.
+                if let Some(fs_path) = find_filesystem_path(&write_lock, fs_uuid) {
+                    write_lock.remove(&fs_path);
+                    if self
+                        .removed_object_signal(fs_path, consts::filesystem_interface_list())
+                        .is_err()
+                    {
+                        warn!("Signal on object removal was not sent to the D-Bus client");
+                    }
+                }
+                if let Some(item) = find_pool_path(&write_lock, pool_uuid) {
+                    if self
+                        .snapshot_pruned_signal(&item, pool_uuid, fs_uuid, &name)
+                        .is_err()
+                    {
+                        warn!("Signal on pruned snapshot was not sent to the D-Bus client");
+                    }
+                } else {
+                    warn!(
+                        "No D-Bus object found for pool with UUID {}; no signal was sent for pruned snapshot {}",
+                        pool_uuid, name
+                    );
+                }
+            }
+            DbusAction::FsCowAlert(fs_uuid, unique, threshold) => {
+                if let Some(item) = find_filesystem_path(&write_lock, fs_uuid) {
+                    if self
+                        .cow_alert_signal(&item, fs_uuid, unique, threshold)
+                        .is_err()
+                    {
+                        warn!("Signal on filesystem copy-on-write alert was not sent to the D-Bus client");
+                    }
+                } else {
+                    warn!(
+                        "No D-Bus object found for filesystem with UUID {}; no signal was sent for its copy-on-write alert",
+                        fs_uuid
+                    );
+                }
+            }
         }
     }
 
@@ -272,6 +392,112 @@ impl DbusTreeHandler {
             })
     }
 
+    /// Send a MetadataWriteFailed signal on the D-Bus for the given pool.
+    fn metadata_write_failed_signal(
+        &self,
+        object: &Path,
+        policy: &str,
+        read_only: bool,
+        error: &str,
+    ) -> Result<(), dbus::Error> {
+        let msg = Message::new_signal(
+            object.clone(),
+            consts::POOL_INTERFACE_NAME_3_0,
+            consts::POOL_METADATA_WRITE_FAILED_SIGNAL,
+        )
+        .map_err(|e| dbus::Error::new_failed(&e))?
+        .append3(policy, read_only, error);
+        self.connection.send(msg).map(|_| ()).map_err(|_| {
+            dbus::Error::new_failed("Failed to send the requested signal on the D-Bus.")
+        })
+    }
+
+    /// Send a PoolAlert signal on the D-Bus for the given pool.
+    fn pool_alert_signal(
+        &self,
+        object: &Path,
+        pool_uuid: PoolUuid,
+        metric: AlertMetric,
+        value: u64,
+        threshold: u64,
+    ) -> Result<(), dbus::Error> {
+        let msg = Message::new_signal(
+            object.clone(),
+            consts::POOL_INTERFACE_NAME_3_0,
+            consts::POOL_ALERT_SIGNAL,
+        )
+        .map_err(|e| dbus::Error::new_failed(&e))?
+        .append2(uuid_to_string!(pool_uuid), metric.to_string())
+        .append2(value, threshold);
+        self.connection.send(msg).map(|_| ()).map_err(|_| {
+            dbus::Error::new_failed("Failed to send the requested signal on the D-Bus.")
+        })
+    }
+
+    /// Send a MetadataUndersized signal on the D-Bus for the given pool.
+    fn metadata_undersized_signal(
+        &self,
+        object: &Path,
+        pool_uuid: PoolUuid,
+        current: Bytes,
+        recommended: Bytes,
+    ) -> Result<(), dbus::Error> {
+        let msg = Message::new_signal(
+            object.clone(),
+            consts::POOL_INTERFACE_NAME_3_0,
+            consts::POOL_METADATA_UNDERSIZED_SIGNAL,
+        )
+        .map_err(|e| dbus::Error::new_failed(&e))?
+        .append3(
+            uuid_to_string!(pool_uuid),
+            *current as u64,
+            *recommended as u64,
+        );
+        self.connection.send(msg).map(|_| ()).map_err(|_| {
+            dbus::Error::new_failed("Failed to send the requested signal on the D-Bus.")
+        })
+    }
+
+    /// Send a SnapshotPruned signal on the D-Bus for the given pool.
+    fn snapshot_pruned_signal(
+        &self,
+        object: &Path,
+        pool_uuid: PoolUuid,
+        fs_uuid: FilesystemUuid,
+        name: &str,
+    ) -> Result<(), dbus::Error> {
+        let msg = Message::new_signal(
+            object.clone(),
+            consts::POOL_INTERFACE_NAME_3_0,
+            consts::POOL_SNAPSHOT_PRUNED_SIGNAL,
+        )
+        .map_err(|e| dbus::Error::new_failed(&e))?
+        .append3(uuid_to_string!(pool_uuid), uuid_to_string!(fs_uuid), name);
+        self.connection.send(msg).map(|_| ()).map_err(|_| {
+            dbus::Error::new_failed("Failed to send the requested signal on the D-Bus.")
+        })
+    }
+
+    /// Send a CowAlert signal on the D-Bus for the given filesystem.
+    fn cow_alert_signal(
+        &self,
+        object: &Path,
+        fs_uuid: FilesystemUuid,
+        unique: Bytes,
+        threshold: Bytes,
+    ) -> Result<(), dbus::Error> {
+        let msg = Message::new_signal(
+            object.clone(),
+            consts::FILESYSTEM_INTERFACE_NAME_3_0,
+            consts::FILESYSTEM_COW_ALERT_SIGNAL,
+        )
+        .map_err(|e| dbus::Error::new_failed(&e))?
+        .append3(uuid_to_string!(fs_uuid), *unique as u64, *threshold as u64);
+        self.connection.send(msg).map(|_| ()).map_err(|_| {
+            dbus::Error::new_failed("Failed to send the requested signal on the D-Bus.")
+        })
+    }
+
     fn property_changed_invalidated_signal(
         &self,
         object: &Path,
@@ -297,6 +523,66 @@ impl DbusTreeHandler {
     }
 }
 
+/// Find the object path of the pool with the given UUID in the D-Bus tree.
+fn find_pool_path(tree: &Tree<MTSync<TData>, TData>, pool_uuid: PoolUuid) -> Option<Path<'static>> {
+    tree.iter().find_map(|opath| {
+        opath
+            .get_data()
+            .as_ref()
+            .and_then(|op_cxt| match op_cxt.uuid {
+                StratisUuid::Pool(uuid) if uuid == pool_uuid => Some(opath.get_name().clone()),
+                _ => None,
+            })
+    })
+}
+
+fn find_filesystem_path(
+    tree: &Tree<MTSync<TData>, TData>,
+    fs_uuid: FilesystemUuid,
+) -> Option<Path<'static>> {
+    tree.iter().find_map(|opath| {
+        opath
+            .get_data()
+            .as_ref()
+            .and_then(|op_cxt| match op_cxt.uuid {
+                StratisUuid::Fs(uuid) if uuid == fs_uuid => Some(opath.get_name().clone()),
+                _ => None,
+            })
+    })
+}
+
+/// Return true if the method called by msg may be called in read-only
+/// mode, as marked where the method is defined. The methods of the standard
+/// D-Bus interfaces, which the tree provides, only read, since none of the
+/// properties can be set.
+fn permitted_in_read_only_mode(tree: &Tree<MTSync<TData>, TData>, msg: &Message) -> bool {
+    let interface = msg.interface();
+    if interface
+        .as_deref()
+        .map_or(false, |i| i.starts_with("org.freedesktop.DBus."))
+    {
+        return true;
+    }
+    let (path, member) = match (msg.path(), msg.member()) {
+        (Some(path), Some(member)) => (path.into_static(), member),
+        _ => return false,
+    };
+    tree.get(&path)
+        .and_then(|opath| {
+            opath
+                .iter()
+                .filter(|iface| {
+                    interface
+                        .as_ref()
+                        .map_or(true, |i| **iface.get_name() == **i)
+                })
+                .flat_map(|iface| iface.iter_m())
+                .find(|method| **method.get_name() == *member)
+                .map(|method| method.get_data().permitted_in_read_only_mode())
+        })
+        .unwrap_or(false)
+}
+
 /// Handler for a D-Bus receiving connection.
 /// stratisd has exactly one connection handler, but this handler spawns
 /// a thread for every D-Bus method.
@@ -332,6 +618,36 @@ impl DbusConnectionHandler {
                 let cloned_connection = Arc::clone(&connection);
                 spawn_blocking(move || {
                     let lock = cloned_tree.blocking_read();
+                    let dbus_context = lock.get_data();
+                    // The call is recorded before waiting on the engine
+                    // lock, so that waiting calls are listed as well.
+                    let operation = dbus_context.start_operation(&msg);
+                    // Methods permitted in read-only mode are checked
+                    // first so that they do not wait on the engine lock.
+                    if !permitted_in_read_only_mode(&lock, &msg) {
+                        // FIXME: An operation that is already running can
+                        // not be cancelled when it takes too long, as engine
+                        // operations are synchronous and may be blocked in
+                        // the kernel, e.g. in a devicemapper ioctl. The
+                        // timeout only keeps further requests from queueing
+                        // up behind it. Every wait for the engine lock on
+                        // behalf of the call, here and in the method
+                        // handler, is bounded by the timeout.
+                        let error = match dbus_context.lock_engine() {
+                            Ok(engine) if engine.read_only_mode() => Some(MethodErr::from((
+                                consts::READ_ONLY_MODE_ERROR,
+                                "stratisd is in read-only mode",
+                            ))),
+                            Ok(_) => None,
+                            Err(err) => Some(err),
+                        };
+                        if let Some(err) = error {
+                            if cloned_connection.send(err.to_message(&msg)).is_err() {
+                                warn!("Failed to send reply to D-Bus client");
+                            }
+                            return;
+                        }
+                    }
                     if let Some(msgs) = lock.handle(&msg) {
                         for msg in msgs {
                             if cloned_connection.send(msg).is_err() {
@@ -346,6 +662,18 @@ impl DbusConnectionHandler {
                             }
                         }
                     }
+                    drop(operation);
+                    // Handling the method may have caused events on pools,
+                    // e.g. a failure to write pool metadata. Draining them
+                    // notifies the engine observers, which send the signals.
+                    // If the engine stays busy, the events are left to be
+                    // drained after a later method call.
+                    if let Some(mut engine) = dbus_context
+                        .engine
+                        .blocking_lock_timeout(operation_timeout())
+                    {
+                        engine.drain_pool_events();
+                    }
                 });
                 true
             }),
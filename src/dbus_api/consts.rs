@@ -12,19 +12,51 @@ pub const REPORT_INTERFACE_NAME_3_0: &str = "org.storage.stratis3.Report.r0";
 
 pub const PROPERTY_FETCH_INTERFACE_NAME_3_0: &str = "org.storage.stratis3.FetchProperties.r0";
 
+/// The name of the D-Bus error returned by methods that would modify the
+/// state of the engine while stratisd is in read-only mode.
+pub const READ_ONLY_MODE_ERROR: &str = "org.storage.stratis3.Error.ReadOnlyMode";
+
+/// The name of the D-Bus error returned by methods that could not be
+/// started because the engine did not finish the operation it was busy
+/// with within the operation timeout.
+pub const TIMEOUT_ERROR: &str = "org.storage.stratis3.Error.Timeout";
+
 pub const KEY_LIST_PROP: &str = "KeyList";
 
 pub const LOCKED_POOL_DEVS: &str = "LockedPoolsWithDevs";
 
+pub const MANAGER_MOVE_FILESYSTEM_PROGRESS_SIGNAL: &str = "FilesystemMoveProgress";
+
 pub const POOL_INTERFACE_NAME_3_0: &str = "org.storage.stratis3.pool.r0";
 pub const POOL_NAME_PROP: &str = "Name";
 pub const POOL_UUID_PROP: &str = "Uuid";
 pub const POOL_HAS_CACHE_PROP: &str = "HasCache";
+pub const POOL_CACHE_BLOCK_SIZE_PROP: &str = "CacheBlockSize";
 pub const POOL_ENCRYPTED_PROP: &str = "Encrypted";
 pub const POOL_ENCRYPTION_KEY_DESC: &str = "KeyDescription";
 pub const POOL_TOTAL_SIZE_PROP: &str = "TotalPhysicalSize";
 pub const POOL_TOTAL_USED_PROP: &str = "TotalPhysicalUsed";
 pub const POOL_CLEVIS_INFO: &str = "ClevisInfo";
+pub const POOL_OPERATION_PROP: &str = "OperationInProgress";
+pub const POOL_CIPHER_INFO_PROP: &str = "CipherInfo";
+pub const POOL_PBKDF_PARAMS_PROP: &str = "PbkdfParams";
+pub const POOL_METADATA_WRITE_FAILURE_POLICY_PROP: &str = "MetadataWriteFailurePolicy";
+pub const POOL_SMALL_DEVICE_POLICY_PROP: &str = "SmallDevicePolicy";
+pub const POOL_ALLOCATION_STRATEGY_PROP: &str = "AllocationStrategy";
+pub const POOL_METADATA_WRITE_FAILED_SIGNAL: &str = "MetadataWriteFailed";
+pub const POOL_FLUSH_CACHE_PROGRESS_SIGNAL: &str = "FlushCacheProgress";
+pub const POOL_RELOCATE_METADATA_PROGRESS_SIGNAL: &str = "RelocateMetadataProgress";
+pub const POOL_ALERT_SIGNAL: &str = "PoolAlert";
+pub const POOL_METADATA_UNDERSIZED_SIGNAL: &str = "MetadataUndersized";
+pub const POOL_METADATA_UNDERSIZED_PROP: &str = "MetadataUndersized";
+pub const POOL_SNAPSHOT_PRUNED_SIGNAL: &str = "SnapshotPruned";
+pub const POOL_AUTO_PRUNE_SNAPSHOTS_ON_PRESSURE_PROP: &str = "AutoPruneSnapshotsOnPressure";
+pub const POOL_AUTO_START_PROP: &str = "AutoStart";
+pub const POOL_START_PRIORITY_PROP: &str = "StartPriority";
+pub const POOL_DETAILED_STATS_PROP: &str = "DetailedStats";
+pub const POOL_RESERVED_CACHE_DEVICES_PROP: &str = "ReservedCacheDevices";
+pub const POOL_REDUNDANCY_WARNINGS_PROP: &str = "RedundancyWarnings";
+pub const POOL_SEQUENCE_NUMBER_PROP: &str = "SequenceNumber";
 
 pub const FILESYSTEM_INTERFACE_NAME_3_0: &str = "org.storage.stratis3.filesystem.r0";
 pub const FILESYSTEM_NAME_PROP: &str = "Name";
@@ -33,6 +65,12 @@ pub const FILESYSTEM_USED_PROP: &str = "Used";
 pub const FILESYSTEM_DEVNODE_PROP: &str = "Devnode";
 pub const FILESYSTEM_POOL_PROP: &str = "Pool";
 pub const FILESYSTEM_CREATED_PROP: &str = "Created";
+pub const FILESYSTEM_SCRUB_PROGRESS_SIGNAL: &str = "ScrubProgress";
+pub const FILESYSTEM_WARM_CACHE_PROGRESS_SIGNAL: &str = "WarmCacheProgress";
+pub const FILESYSTEM_COW_ALERT_SIGNAL: &str = "CowAlert";
+pub const FILESYSTEM_COW_ALERT_THRESHOLD_PROP: &str = "CowAlertThreshold";
+pub const FILESYSTEM_MIN_FREE_GUARANTEE_PROP: &str = "MinFreeGuarantee";
+pub const FILESYSTEM_SEQUENCE_NUMBER_PROP: &str = "SequenceNumber";
 
 pub const BLOCKDEV_INTERFACE_NAME_3_0: &str = "org.storage.stratis3.blockdev.r0";
 pub const BLOCKDEV_DEVNODE_PROP: &str = "Devnode";
@@ -0,0 +1,99 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    dbus_api::types::DbusAction,
+    engine::{EngineEvent, EngineObserver, PoolEvent},
+};
+
+/// Observer of the engine which has a signal sent for each event that the
+/// engine detects on a pool on its own. Pools are added to, removed from,
+/// and renamed in the D-Bus tree by the handlers of the D-Bus methods and
+/// udev events that cause the change, so events of those kinds are ignored.
+#[derive(Debug)]
+pub struct DbusEngineObserver {
+    sender: UnboundedSender<DbusAction>,
+}
+
+impl DbusEngineObserver {
+    pub fn new(sender: UnboundedSender<DbusAction>) -> Self {
+        DbusEngineObserver { sender }
+    }
+}
+
+impl EngineObserver for DbusEngineObserver {
+    fn notify(&self, event: &EngineEvent) {
+        let (pool_uuid, action) = match event {
+            EngineEvent::Pool(pool_uuid, PoolEvent::OperationChanged(operation)) => (
+                *pool_uuid,
+                DbusAction::PoolOperationChange(*pool_uuid, *operation),
+            ),
+            EngineEvent::Pool(
+                pool_uuid,
+                PoolEvent::MetadataWriteFailed {
+                    policy,
+                    read_only,
+                    error,
+                },
+            ) => (
+                *pool_uuid,
+                DbusAction::PoolMetadataWriteFailure(
+                    *pool_uuid,
+                    *policy,
+                    *read_only,
+                    error.clone(),
+                ),
+            ),
+            EngineEvent::Pool(
+                pool_uuid,
+                PoolEvent::Alert {
+                    metric,
+                    value,
+                    threshold,
+                },
+            ) => (
+                *pool_uuid,
+                DbusAction::PoolAlert(*pool_uuid, *metric, *value, *threshold),
+            ),
+            EngineEvent::Pool(
+                pool_uuid,
+                PoolEvent::MetadataUndersized {
+                    current,
+                    recommended,
+                },
+            ) => (
+                *pool_uuid,
+                DbusAction::PoolMetadataUndersized(*pool_uuid, *current, *recommended),
+            ),
+            EngineEvent::Pool(pool_uuid, PoolEvent::SnapshotPruned { uuid, name }) => (
+                *pool_uuid,
+                DbusAction::PoolSnapshotPruned(*pool_uuid, *uuid, name.clone()),
+            ),
+            EngineEvent::Pool(
+                pool_uuid,
+                PoolEvent::FilesystemCowAlert {
+                    uuid,
+                    unique,
+                    threshold,
+                    ..
+                },
+            ) => (
+                *pool_uuid,
+                DbusAction::FsCowAlert(*uuid, *unique, *threshold),
+            ),
+            EngineEvent::PoolAdded { .. }
+            | EngineEvent::PoolRemoved(_)
+            | EngineEvent::PoolRenamed { .. } => return,
+        };
+        if let Err(e) = self.sender.send(action) {
+            warn!(
+                "D-Bus pool event could not be sent to the processing thread; \
+                no signal will be sent out for the event on pool with UUID {}: {}",
+                pool_uuid, e,
+            )
+        }
+    }
+}
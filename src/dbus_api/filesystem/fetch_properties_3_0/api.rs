@@ -6,23 +6,27 @@ use dbus_tree::{Factory, MTSync, Method};
 
 use crate::dbus_api::{
     filesystem::fetch_properties_3_0::methods::{get_all_properties, get_properties},
-    types::TData,
+    types::{MethodAccess, TData},
 };
 
 pub fn get_all_properties_method(
     f: &Factory<MTSync<TData>, TData>,
 ) -> Method<MTSync<TData>, TData> {
-    f.method("GetAllProperties", (), get_all_properties)
-        // a{s(bv)}: Dictionary of property names to tuples
-        // In the tuple:
-        // b: Indicates whether the property value fetched was successful
-        // v: If b is true, represents the value for the given property
-        //    If b is false, represents the error returned when fetching the property
-        .out_arg(("results", "a{s(bv)}"))
+    f.method(
+        "GetAllProperties",
+        MethodAccess::ReadOnly,
+        get_all_properties,
+    )
+    // a{s(bv)}: Dictionary of property names to tuples
+    // In the tuple:
+    // b: Indicates whether the property value fetched was successful
+    // v: If b is true, represents the value for the given property
+    //    If b is false, represents the error returned when fetching the property
+    .out_arg(("results", "a{s(bv)}"))
 }
 
 pub fn get_properties_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("GetProperties", (), get_properties)
+    f.method("GetProperties", MethodAccess::ReadOnly, get_properties)
         .in_arg(("properties", "as"))
         // a{s(bv)}: Dictionary of property names to tuples
         // In the tuple:
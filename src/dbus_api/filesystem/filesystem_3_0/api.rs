@@ -2,20 +2,27 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use dbus_tree::{Access, EmitsChangedSignal, Factory, MTSync, Method, Property};
+use dbus_tree::{Access, EmitsChangedSignal, Factory, MTSync, Method, Property, Signal};
 
 use crate::dbus_api::{
     consts,
     filesystem::filesystem_3_0::{
-        methods::rename_filesystem,
-        props::{get_filesystem_created, get_filesystem_devnode, get_filesystem_name},
+        methods::{
+            check_filesystem_consistency, filesystem_io_stats, latency_histogram,
+            rename_filesystem, repair_filesystem, scrub_filesystem, set_cow_alert_threshold,
+            set_filesystem_size, set_min_free_guarantee, shared_size, warm_cache_filesystem,
+        },
+        props::{
+            get_filesystem_cow_alert_threshold, get_filesystem_created, get_filesystem_devnode,
+            get_filesystem_min_free_guarantee, get_filesystem_name, get_filesystem_sequence_number,
+        },
     },
-    types::TData,
+    types::{MethodAccess, TData},
     util::{get_parent, get_uuid},
 };
 
 pub fn rename_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("SetName", (), rename_filesystem)
+    f.method("SetName", MethodAccess::ReadWrite, rename_filesystem)
         .in_arg(("name", "s"))
         // b: true if UUID of changed resource has been returned
         // s: UUID of changed resource
@@ -26,6 +33,179 @@ pub fn rename_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>,
         .out_arg(("return_string", "s"))
 }
 
+pub fn scrub_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("Scrub", MethodAccess::ReadOnly, scrub_filesystem)
+        // a(tt): Array of ranges that could not be read, each represented
+        // as a start offset and a length in sectors
+        //
+        // Rust representation: Vec<(u64, u64)>
+        .out_arg(("results", "a(tt)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn check_consistency_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "CheckConsistency",
+        MethodAccess::ReadOnly,
+        check_filesystem_consistency,
+    )
+    // b: true if the filesystem is consistent
+    // as: Array of inconsistencies found
+    //
+    // Rust representation: (bool, Vec<String>)
+    .out_arg(("results", "(bas)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn io_stats_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("IoStats", MethodAccess::ReadOnly, filesystem_io_stats)
+        // In order from left to right:
+        // s: the number of bytes read
+        // s: the number of bytes written
+        // t: the number of read operations
+        // t: the number of write operations
+        //
+        // Rust representation: (String, String, u64, u64)
+        .out_arg(("results", "(sstt)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn latency_histogram_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "LatencyHistogram",
+        MethodAccess::ReadOnly,
+        latency_histogram,
+    )
+    // a(tt): Array of histogram buckets, each represented as the lower
+    // bound of the bucket in nanoseconds and the number of I/Os whose
+    // latency falls into the bucket
+    //
+    // Rust representation: Vec<(u64, u64)>
+    .out_arg(("results", "a(tt)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn shared_size_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("SharedSize", MethodAccess::ReadOnly, shared_size)
+        // t: the number of bytes of data shared with the origin and the
+        // snapshots of the filesystem
+        // t: the number of bytes of data that only the filesystem holds,
+        // which destroying it would free
+        //
+        // Rust representation: (u64, u64)
+        .out_arg(("results", "(tt)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn scrub_progress_signal(f: &Factory<MTSync<TData>, TData>) -> Signal<TData> {
+    f.signal(consts::FILESYSTEM_SCRUB_PROGRESS_SIGNAL, ())
+        // t: The number of sectors read so far
+        .sarg::<u64, _>("read")
+        // t: The total number of sectors to read
+        .sarg::<u64, _>("total")
+}
+
+pub fn warm_cache_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("WarmCache", MethodAccess::ReadWrite, warm_cache_filesystem)
+        // b: true if a budget is specified
+        // t: the maximum number of sectors to read. If not specified, or
+        // larger than the pool's cache, the size of the cache is used.
+        .in_arg(("budget", "(bt)"))
+        // t: the number of sectors read
+        .out_arg(("result", "t"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn warm_cache_progress_signal(f: &Factory<MTSync<TData>, TData>) -> Signal<TData> {
+    f.signal(consts::FILESYSTEM_WARM_CACHE_PROGRESS_SIGNAL, ())
+        // t: The number of sectors read so far
+        .sarg::<u64, _>("read")
+        // t: The total number of sectors to read
+        .sarg::<u64, _>("total")
+}
+
+pub fn repair_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("Repair", MethodAccess::ReadWrite, repair_filesystem)
+        // b: true if the filesystem was repaired
+        .out_arg(("result", "b"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn set_size_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("SetSize", MethodAccess::ReadWrite, set_filesystem_size)
+        // t: The size to grow the filesystem to in sectors
+        .in_arg(("target_sectors", "t"))
+        // b: true if the XFS filesystem should also be grown; the filesystem
+        // must be mounted
+        .in_arg(("grow_fs", "b"))
+        // b: true if the size of the filesystem was changed
+        .out_arg(("result", "b"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn set_cow_alert_threshold_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "SetCowAlertThreshold",
+        MethodAccess::ReadWrite,
+        set_cow_alert_threshold,
+    )
+    // b: true if a threshold is specified; if not, the alert is disabled
+    // t: the number of bytes of data that only the filesystem may hold,
+    // rather than share with its origin and its snapshots, before an
+    // alert is raised
+    .in_arg(("threshold", "(bt)"))
+    // b: true if the threshold was changed
+    .out_arg(("result", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn set_min_free_guarantee_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "SetMinFreeGuarantee",
+        MethodAccess::ReadWrite,
+        set_min_free_guarantee,
+    )
+    // b: true if a guarantee is specified; if not, the guarantee is
+    // removed
+    // t: the number of bytes of free space that the pool keeps
+    // available for the filesystem to write to
+    //
+    // The pool refuses to create filesystems or snapshots, or to grow a
+    // filesystem with a guarantee, while it has no free space beyond what
+    // the guarantees may claim. Writes to other filesystems are only kept
+    // from consuming the guaranteed space by pruning snapshots, which
+    // requires the pool's automatic snapshot pruning policy to be enabled;
+    // with pruning disabled, the guarantee is not enforced against them.
+    .in_arg(("guarantee", "(bt)"))
+    // b: true if the guarantee was changed
+    .out_arg(("result", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn cow_alert_signal(f: &Factory<MTSync<TData>, TData>) -> Signal<TData> {
+    f.signal(consts::FILESYSTEM_COW_ALERT_SIGNAL, ())
+        // s: The UUID of the filesystem
+        .sarg::<&str, _>("filesystem_uuid")
+        // t: The number of bytes of data that only the filesystem holds
+        .sarg::<u64, _>("unique")
+        // t: The threshold that the unique data exceeds, in bytes
+        .sarg::<u64, _>("threshold")
+}
+
 pub fn devnode_property(f: &Factory<MTSync<TData>, TData>) -> Property<MTSync<TData>, TData> {
     f.property::<&str, _>(consts::FILESYSTEM_DEVNODE_PROP, ())
         .access(Access::Read)
@@ -54,6 +234,33 @@ pub fn uuid_property(f: &Factory<MTSync<TData>, TData>) -> Property<MTSync<TData
         .on_get(get_uuid)
 }
 
+pub fn cow_alert_threshold_property(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Property<MTSync<TData>, TData> {
+    f.property::<(bool, u64), _>(consts::FILESYSTEM_COW_ALERT_THRESHOLD_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::False)
+        .on_get(get_filesystem_cow_alert_threshold)
+}
+
+pub fn min_free_guarantee_property(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Property<MTSync<TData>, TData> {
+    f.property::<(bool, u64), _>(consts::FILESYSTEM_MIN_FREE_GUARANTEE_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::False)
+        .on_get(get_filesystem_min_free_guarantee)
+}
+
+pub fn sequence_number_property(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Property<MTSync<TData>, TData> {
+    f.property::<u64, _>(consts::FILESYSTEM_SEQUENCE_NUMBER_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::Const)
+        .on_get(get_filesystem_sequence_number)
+}
+
 pub fn created_property(f: &Factory<MTSync<TData>, TData>) -> Property<MTSync<TData>, TData> {
     f.property::<&str, _>("Created", ())
         .access(Access::Read)
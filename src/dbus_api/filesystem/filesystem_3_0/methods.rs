@@ -5,10 +5,12 @@
 use dbus::Message;
 use dbus_tree::{MTSync, MethodInfo, MethodResult};
 
+use devicemapper::{Bytes, Sectors};
+
 use crate::{
     dbus_api::{
         types::{DbusErrorEnum, TData, OK_STRING},
-        util::{engine_to_dbus_err_tuple, get_next_arg},
+        util::{engine_to_dbus_err_tuple, get_next_arg, tuple_to_option},
     },
     engine::{FilesystemUuid, RenameAction},
 };
@@ -38,7 +40,7 @@ pub fn rename_filesystem(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
         return_message
     );
 
-    let mut mutex_lock = dbus_context.engine.blocking_lock();
+    let mut mutex_lock = dbus_context.lock_engine()?;
     let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
 
     let uuid = typed_uuid!(filesystem_data.uuid; Fs; default_return; return_message);
@@ -72,3 +74,523 @@ pub fn rename_filesystem(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
 
     Ok(vec![msg])
 }
+
+pub fn scrub_filesystem(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: Vec<(u64, u64)> = Vec::new();
+
+    let filesystem_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let filesystem_data = get_data!(filesystem_path; default_return; return_message);
+
+    let pool_path = get_parent!(m; filesystem_data; default_return; return_message);
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let uuid = typed_uuid!(filesystem_data.uuid; Fs; default_return; return_message);
+    let work = {
+        let mutex_lock = dbus_context.lock_engine()?;
+        let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+        match pool.scrub_filesystem(uuid) {
+            Ok(work) => work,
+            Err(err) => {
+                let (rc, rs) = engine_to_dbus_err_tuple(&err);
+                return Ok(vec![return_message.append3(default_return, rc, rs)]);
+            }
+        }
+    };
+
+    // The data is read once the engine lock has been released, so that
+    // other requests are not held up. A progress signal is sent each time
+    // another percent of the data has been read.
+    let mut last_percent = 0;
+    let mut progress = |read: Sectors, total: Sectors| {
+        let percent = *read * 100 / *total;
+        if percent != last_percent {
+            last_percent = percent;
+            dbus_context.push_scrub_progress(object_path, *read, *total);
+        }
+    };
+
+    let msg = match work(&mut progress) {
+        Ok(ranges) => return_message.append3(
+            ranges
+                .into_iter()
+                .map(|(start, length)| (*start, *length))
+                .collect::<Vec<_>>(),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn check_filesystem_consistency(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: (bool, Vec<String>) = (false, Vec::new());
+
+    let filesystem_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let filesystem_data = get_data!(filesystem_path; default_return; return_message);
+
+    let pool_path = get_parent!(m; filesystem_data; default_return; return_message);
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let uuid = typed_uuid!(filesystem_data.uuid; Fs; default_return; return_message);
+    let work = {
+        let mutex_lock = dbus_context.lock_engine()?;
+        let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+        let (_, filesystem) = match pool.get_filesystem(uuid) {
+            Some(fs) => fs,
+            None => {
+                let error_message = format!(
+                    "pool {} doesn't know about filesystem {}",
+                    pool_uuid, filesystem_data.uuid
+                );
+                let (rc, rs) = (DbusErrorEnum::ERROR as u16, error_message);
+                return Ok(vec![return_message.append3(default_return, rc, rs)]);
+            }
+        };
+        match filesystem.check_consistency() {
+            Ok(work) => work,
+            Err(err) => {
+                let (rc, rs) = engine_to_dbus_err_tuple(&err);
+                return Ok(vec![return_message.append3(default_return, rc, rs)]);
+            }
+        }
+    };
+
+    // xfs_repair runs once the engine lock has been released, so that
+    // other requests are not held up.
+    let msg = match work(&mut |_, _| ()) {
+        Ok(check) => return_message.append3(
+            (check.consistent, check.problems),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn repair_filesystem(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let filesystem_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let filesystem_data = get_data!(filesystem_path; default_return; return_message);
+
+    let pool_path = get_parent!(m; filesystem_data; default_return; return_message);
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let uuid = typed_uuid!(filesystem_data.uuid; Fs; default_return; return_message);
+    let work = {
+        let mutex_lock = dbus_context.lock_engine()?;
+        let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+        let (_, filesystem) = match pool.get_filesystem(uuid) {
+            Some(fs) => fs,
+            None => {
+                let error_message = format!(
+                    "pool {} doesn't know about filesystem {}",
+                    pool_uuid, filesystem_data.uuid
+                );
+                let (rc, rs) = (DbusErrorEnum::ERROR as u16, error_message);
+                return Ok(vec![return_message.append3(default_return, rc, rs)]);
+            }
+        };
+        match filesystem.repair() {
+            Ok(work) => work,
+            Err(err) => {
+                let (rc, rs) = engine_to_dbus_err_tuple(&err);
+                return Ok(vec![return_message.append3(default_return, rc, rs)]);
+            }
+        }
+    };
+
+    // xfs_repair runs once the engine lock has been released, so that
+    // other requests are not held up.
+    let msg = match work(&mut |_, _| ()) {
+        Ok(repaired) => {
+            return_message.append3(repaired, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn set_filesystem_size(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let target_sectors: u64 = get_next_arg(&mut iter, 0)?;
+    let grow_fs: bool = get_next_arg(&mut iter, 1)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let filesystem_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let filesystem_data = get_data!(filesystem_path; default_return; return_message);
+
+    let pool_path = get_parent!(m; filesystem_data; default_return; return_message);
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let uuid = typed_uuid!(filesystem_data.uuid; Fs; default_return; return_message);
+    let msg = match pool.set_filesystem_size(&pool_name, uuid, Sectors(target_sectors), grow_fs) {
+        Ok(changed) => {
+            return_message.append3(changed, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn set_cow_alert_threshold(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let threshold_tuple: (bool, u64) = get_next_arg(&mut iter, 0)?;
+    let threshold = tuple_to_option(threshold_tuple).map(|t| Bytes(u128::from(t)));
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let filesystem_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let filesystem_data = get_data!(filesystem_path; default_return; return_message);
+
+    let pool_path = get_parent!(m; filesystem_data; default_return; return_message);
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let uuid = typed_uuid!(filesystem_data.uuid; Fs; default_return; return_message);
+    let msg = match pool.set_fs_cow_alert_threshold(&pool_name, uuid, threshold) {
+        Ok(changed) => {
+            return_message.append3(changed, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn set_min_free_guarantee(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let guarantee_tuple: (bool, u64) = get_next_arg(&mut iter, 0)?;
+    let guarantee = tuple_to_option(guarantee_tuple).map(|g| Bytes(u128::from(g)));
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let filesystem_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let filesystem_data = get_data!(filesystem_path; default_return; return_message);
+
+    let pool_path = get_parent!(m; filesystem_data; default_return; return_message);
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let uuid = typed_uuid!(filesystem_data.uuid; Fs; default_return; return_message);
+    let msg = match pool.set_fs_min_free_guarantee(&pool_name, uuid, guarantee) {
+        Ok(changed) => {
+            return_message.append3(changed, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn warm_cache_filesystem(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let budget_tuple: (bool, u64) = get_next_arg(&mut iter, 0)?;
+    let budget = tuple_to_option(budget_tuple).map(Sectors);
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = 0u64;
+
+    let filesystem_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let filesystem_data = get_data!(filesystem_path; default_return; return_message);
+
+    let pool_path = get_parent!(m; filesystem_data; default_return; return_message);
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let uuid = typed_uuid!(filesystem_data.uuid; Fs; default_return; return_message);
+    let work = {
+        let mutex_lock = dbus_context.lock_engine()?;
+        let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+        match pool.warm_cache(uuid, budget) {
+            Ok(work) => work,
+            Err(err) => {
+                let (rc, rs) = engine_to_dbus_err_tuple(&err);
+                return Ok(vec![return_message.append3(default_return, rc, rs)]);
+            }
+        }
+    };
+
+    // The data is read once the engine lock has been released, so that
+    // other requests are not held up. A progress signal is sent each time
+    // another percent of the data has been read.
+    let mut last_percent = 0;
+    let mut progress = |read: Sectors, total: Sectors| {
+        let percent = *read * 100 / *total;
+        if percent != last_percent {
+            last_percent = percent;
+            dbus_context.push_warm_cache_progress(object_path, *read, *total);
+        }
+    };
+
+    let msg = match work(&mut progress) {
+        Ok(read) => return_message.append3(*read, DbusErrorEnum::OK as u16, OK_STRING.to_string()),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn filesystem_io_stats(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: (String, String, u64, u64) = (String::new(), String::new(), 0, 0);
+
+    let filesystem_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let filesystem_data = get_data!(filesystem_path; default_return; return_message);
+
+    let pool_path = get_parent!(m; filesystem_data; default_return; return_message);
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let uuid = typed_uuid!(filesystem_data.uuid; Fs; default_return; return_message);
+    let (_, filesystem) = match pool.get_filesystem(uuid) {
+        Some(fs) => fs,
+        None => {
+            let error_message = format!(
+                "pool {} doesn't know about filesystem {}",
+                pool_uuid, filesystem_data.uuid
+            );
+            let (rc, rs) = (DbusErrorEnum::ERROR as u16, error_message);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let msg = match filesystem.io_stats() {
+        Ok(stats) => return_message.append3(
+            (
+                (*stats.read_bytes).to_string(),
+                (*stats.write_bytes).to_string(),
+                stats.read_ops,
+                stats.write_ops,
+            ),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn latency_histogram(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: Vec<(u64, u64)> = Vec::new();
+
+    let filesystem_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let filesystem_data = get_data!(filesystem_path; default_return; return_message);
+
+    let pool_path = get_parent!(m; filesystem_data; default_return; return_message);
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let uuid = typed_uuid!(filesystem_data.uuid; Fs; default_return; return_message);
+    let msg = match pool.filesystem_latency_histogram(uuid) {
+        Ok(histogram) => {
+            return_message.append3(histogram, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn shared_size(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = (0u64, 0u64);
+
+    let filesystem_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let filesystem_data = get_data!(filesystem_path; default_return; return_message);
+
+    let pool_path = get_parent!(m; filesystem_data; default_return; return_message);
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let uuid = typed_uuid!(filesystem_data.uuid; Fs; default_return; return_message);
+    let msg = match pool.filesystem_shared_size(uuid) {
+        Ok((shared, unique)) => return_message.append3(
+            (*shared as u64, *unique as u64),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
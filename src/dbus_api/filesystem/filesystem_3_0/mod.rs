@@ -3,5 +3,10 @@ mod methods;
 mod props;
 
 pub use api::{
-    created_property, devnode_property, name_property, pool_property, rename_method, uuid_property,
+    check_consistency_method, cow_alert_signal, cow_alert_threshold_property, created_property,
+    devnode_property, io_stats_method, latency_histogram_method, min_free_guarantee_property,
+    name_property, pool_property, rename_method, repair_method, scrub_method,
+    scrub_progress_signal, sequence_number_property, set_cow_alert_threshold_method,
+    set_min_free_guarantee_method, set_size_method, shared_size_method, uuid_property,
+    warm_cache_method, warm_cache_progress_signal,
 };
@@ -56,3 +56,28 @@ pub fn get_filesystem_created(
 ) -> Result<(), MethodErr> {
     get_filesystem_property(i, p, |(_, _, fs)| Ok(shared::fs_created_prop(fs)))
 }
+
+pub fn get_filesystem_cow_alert_threshold(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_filesystem_property(i, p, |(_, _, fs)| {
+        Ok(shared::fs_cow_alert_threshold_prop(fs))
+    })
+}
+
+pub fn get_filesystem_min_free_guarantee(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_filesystem_property(i, p, |(_, _, fs)| {
+        Ok(shared::fs_min_free_guarantee_prop(fs))
+    })
+}
+
+pub fn get_filesystem_sequence_number(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_filesystem_property(i, p, |(_, _, fs)| Ok(shared::fs_sequence_number_prop(fs)))
+}
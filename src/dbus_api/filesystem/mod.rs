@@ -39,11 +39,27 @@ pub fn create_dbus_filesystem<'a>(
         .add(
             f.interface(consts::FILESYSTEM_INTERFACE_NAME_3_0, ())
                 .add_m(filesystem_3_0::rename_method(&f))
+                .add_m(filesystem_3_0::scrub_method(&f))
+                .add_m(filesystem_3_0::check_consistency_method(&f))
+                .add_m(filesystem_3_0::repair_method(&f))
+                .add_m(filesystem_3_0::set_size_method(&f))
+                .add_m(filesystem_3_0::warm_cache_method(&f))
+                .add_m(filesystem_3_0::io_stats_method(&f))
+                .add_m(filesystem_3_0::latency_histogram_method(&f))
+                .add_m(filesystem_3_0::shared_size_method(&f))
+                .add_m(filesystem_3_0::set_cow_alert_threshold_method(&f))
+                .add_m(filesystem_3_0::set_min_free_guarantee_method(&f))
                 .add_p(filesystem_3_0::devnode_property(&f))
                 .add_p(filesystem_3_0::name_property(&f))
                 .add_p(filesystem_3_0::pool_property(&f))
                 .add_p(filesystem_3_0::uuid_property(&f))
-                .add_p(filesystem_3_0::created_property(&f)),
+                .add_p(filesystem_3_0::created_property(&f))
+                .add_p(filesystem_3_0::cow_alert_threshold_property(&f))
+                .add_p(filesystem_3_0::min_free_guarantee_property(&f))
+                .add_p(filesystem_3_0::sequence_number_property(&f))
+                .add_s(filesystem_3_0::scrub_progress_signal(&f))
+                .add_s(filesystem_3_0::warm_cache_progress_signal(&f))
+                .add_s(filesystem_3_0::cow_alert_signal(&f)),
         )
         .add(
             f.interface(consts::PROPERTY_FETCH_INTERFACE_NAME_3_0, ())
@@ -71,7 +87,10 @@ pub fn get_fs_properties(
             consts::FILESYSTEM_UUID_PROP => uuid_to_string!(fs_uuid),
             consts::FILESYSTEM_DEVNODE_PROP => shared::fs_devnode_prop(fs, pool_name, fs_name),
             consts::FILESYSTEM_POOL_PROP => parent,
-            consts::FILESYSTEM_CREATED_PROP => shared::fs_created_prop(fs)
+            consts::FILESYSTEM_CREATED_PROP => shared::fs_created_prop(fs),
+            consts::FILESYSTEM_COW_ALERT_THRESHOLD_PROP => shared::fs_cow_alert_threshold_prop(fs),
+            consts::FILESYSTEM_MIN_FREE_GUARANTEE_PROP => shared::fs_min_free_guarantee_prop(fs),
+            consts::FILESYSTEM_SEQUENCE_NUMBER_PROP => shared::fs_sequence_number_prop(fs)
         }
     }
 }
@@ -7,7 +7,7 @@ use dbus::Path;
 use dbus_tree::{MTSync, Tree};
 
 use crate::{
-    dbus_api::types::TData,
+    dbus_api::{types::TData, util::option_to_tuple},
     engine::{Filesystem, Name},
 };
 
@@ -76,3 +76,21 @@ pub fn fs_devnode_prop(fs: &dyn Filesystem, pool_name: &Name, fs_name: &Name) ->
 pub fn fs_created_prop(fs: &dyn Filesystem) -> String {
     fs.created().to_rfc3339_opts(SecondsFormat::Secs, true)
 }
+
+/// Generate D-Bus representation of the copy-on-write alert threshold
+/// property.
+pub fn fs_cow_alert_threshold_prop(fs: &dyn Filesystem) -> (bool, u64) {
+    option_to_tuple(fs.cow_alert_threshold().map(|t| *t as u64), 0)
+}
+
+/// Generate D-Bus representation of the minimum free space guarantee
+/// property.
+pub fn fs_min_free_guarantee_prop(fs: &dyn Filesystem) -> (bool, u64) {
+    option_to_tuple(fs.min_free_guarantee().map(|g| *g as u64), 0)
+}
+
+/// Generate D-Bus representation of the sequence number property.
+#[inline]
+pub fn fs_sequence_number_prop(fs: &dyn Filesystem) -> u64 {
+    fs.sequence_number()
+}
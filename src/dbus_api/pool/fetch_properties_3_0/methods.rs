@@ -14,16 +14,17 @@ use itertools::Itertools;
 use crate::dbus_api::{
     consts,
     pool::shared::{
-        get_pool_clevis_info, get_pool_encryption_key_desc, get_pool_has_cache,
-        get_pool_total_size, get_pool_total_used,
+        get_pool_cache_block_size, get_pool_clevis_info, get_pool_encryption_key_desc,
+        get_pool_has_cache, get_pool_total_size, get_pool_total_used,
     },
     types::TData,
     util::result_to_tuple,
 };
 
-const ALL_PROPERTIES: [&str; 5] = [
+const ALL_PROPERTIES: [&str; 6] = [
     consts::POOL_ENCRYPTION_KEY_DESC,
     consts::POOL_HAS_CACHE_PROP,
+    consts::POOL_CACHE_BLOCK_SIZE_PROP,
     consts::POOL_TOTAL_SIZE_PROP,
     consts::POOL_TOTAL_USED_PROP,
     consts::POOL_CLEVIS_INFO,
@@ -44,6 +45,9 @@ fn get_properties_shared(
                 Some((prop, result_to_tuple(get_pool_encryption_key_desc(m))))
             }
             consts::POOL_HAS_CACHE_PROP => Some((prop, result_to_tuple(get_pool_has_cache(m)))),
+            consts::POOL_CACHE_BLOCK_SIZE_PROP => {
+                Some((prop, result_to_tuple(get_pool_cache_block_size(m))))
+            }
             consts::POOL_TOTAL_SIZE_PROP => Some((prop, result_to_tuple(get_pool_total_size(m)))),
             consts::POOL_TOTAL_USED_PROP => Some((prop, result_to_tuple(get_pool_total_used(m)))),
             consts::POOL_CLEVIS_INFO => Some((prop, result_to_tuple(get_pool_clevis_info(m)))),
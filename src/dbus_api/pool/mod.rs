@@ -7,7 +7,9 @@ use dbus_tree::Factory;
 
 use crate::{
     dbus_api::{
+        blockdev::create_dbus_blockdev,
         consts,
+        filesystem::create_dbus_filesystem,
         types::{DbusContext, InterfacesAddedThreadSafe, OPContext},
         util::make_object_path,
     },
@@ -38,9 +40,15 @@ pub fn create_dbus_pool<'a>(
         .add(
             f.interface(consts::POOL_INTERFACE_NAME_3_0, ())
                 .add_m(pool_3_0::create_filesystems_method(&f))
+                .add_m(pool_3_0::create_filesystem_with_params_method(&f))
+                .add_m(pool_3_0::create_filesystem_on_devices_method(&f))
+                .add_m(pool_3_0::create_filesystem_from_template_method(&f))
+                .add_m(pool_3_0::explain_create_filesystem_method(&f))
                 .add_m(pool_3_0::destroy_filesystems_method(&f))
                 .add_m(pool_3_0::snapshot_filesystem_method(&f))
                 .add_m(pool_3_0::add_blockdevs_method(&f))
+                .add_m(pool_3_0::add_datadevs_with_override_method(&f))
+                .add_m(pool_3_0::add_datadevs_best_effort_method(&f))
                 .add_m(pool_3_0::bind_clevis_method(&f))
                 .add_m(pool_3_0::unbind_clevis_method(&f))
                 .add_m(pool_3_0::init_cache_method(&f))
@@ -50,9 +58,71 @@ pub fn create_dbus_pool<'a>(
                 .add_m(pool_3_0::rebind_keyring_method(&f))
                 .add_m(pool_3_0::rebind_clevis_method(&f))
                 .add_m(pool_3_0::rename_method(&f))
+                .add_m(pool_3_0::set_metadata_write_failure_policy_method(&f))
+                .add_m(pool_3_0::set_small_device_policy_method(&f))
+                .add_m(pool_3_0::set_allocation_strategy_method(&f))
+                .add_m(pool_3_0::operation_log_method(&f))
+                .add_m(pool_3_0::capacity_history_method(&f))
+                .add_m(pool_3_0::shrink_by_device_method(&f))
+                .add_m(pool_3_0::device_write_cache_state_method(&f))
+                .add_m(pool_3_0::device_in_flight_io_method(&f))
+                .add_m(pool_3_0::disable_device_write_cache_method(&f))
+                .add_m(pool_3_0::filesystem_size_limits_method(&f))
+                .add_m(pool_3_0::thin_pool_limits_method(&f))
+                .add_m(pool_3_0::set_auto_start_method(&f))
+                .add_m(pool_3_0::set_start_priority_method(&f))
+                .add_m(pool_3_0::set_auto_prune_snapshots_on_pressure_method(&f))
+                .add_m(pool_3_0::set_alert_thresholds_method(&f))
+                .add_m(pool_3_0::acknowledge_alert_method(&f))
+                .add_m(pool_3_0::list_active_alerts_method(&f))
+                .add_m(pool_3_0::reset_transient_state_method(&f))
+                .add_m(pool_3_0::enable_detailed_stats_method(&f))
+                .add_m(pool_3_0::reserve_cache_device_method(&f))
+                .add_m(pool_3_0::flush_cache_method(&f))
+                .add_m(pool_3_0::remove_cache_method(&f))
+                .add_m(pool_3_0::compact_metadata_method(&f))
+                .add_m(pool_3_0::relocate_metadata_method(&f))
+                .add_m(pool_3_0::verify_metadata_consistency_method(&f))
+                .add_m(pool_3_0::full_health_report_method(&f))
+                .add_m(pool_3_0::latency_stats_method(&f))
+                .add_m(pool_3_0::surface_test_method(&f))
+                .add_m(pool_3_0::filesystems_on_device_method(&f))
+                .add_m(pool_3_0::filesystems_pending_grow_method(&f))
+                .add_m(pool_3_0::structure_method(&f))
+                .add_m(pool_3_0::recommended_overprovision_limit_method(&f))
+                .add_m(pool_3_0::recommended_max_filesystems_method(&f))
+                .add_m(pool_3_0::supported_operations_method(&f))
+                .add_m(pool_3_0::blockdev_paths_method(&f))
+                .add_m(pool_3_0::simulate_reconfiguration_method(&f))
+                .add_m(pool_3_0::estimate_integrity_overhead_method(&f))
+                .add_m(pool_3_0::export_config_method(&f))
+                .add_m(pool_3_0::set_fs_deletion_grace_period_method(&f))
+                .add_m(pool_3_0::list_deleted_filesystems_method(&f))
+                .add_m(pool_3_0::undelete_filesystem_method(&f))
+                .add_m(pool_3_0::purge_deleted_filesystem_method(&f))
                 .add_p(pool_3_0::name_property(&f))
                 .add_p(pool_3_0::uuid_property(&f))
-                .add_p(pool_3_0::encrypted_property(&f)),
+                .add_p(pool_3_0::encrypted_property(&f))
+                .add_p(pool_3_0::cipher_info_property(&f))
+                .add_p(pool_3_0::pbkdf_params_property(&f))
+                .add_p(pool_3_0::operation_property(&f))
+                .add_p(pool_3_0::metadata_write_failure_policy_property(&f))
+                .add_p(pool_3_0::small_device_policy_property(&f))
+                .add_p(pool_3_0::allocation_strategy_property(&f))
+                .add_p(pool_3_0::auto_start_property(&f))
+                .add_p(pool_3_0::start_priority_property(&f))
+                .add_p(pool_3_0::detailed_stats_property(&f))
+                .add_p(pool_3_0::reserved_cache_devices_property(&f))
+                .add_p(pool_3_0::redundancy_warnings_property(&f))
+                .add_p(pool_3_0::metadata_undersized_property(&f))
+                .add_p(pool_3_0::auto_prune_snapshots_on_pressure_property(&f))
+                .add_p(pool_3_0::sequence_number_property(&f))
+                .add_s(pool_3_0::metadata_write_failed_signal(&f))
+                .add_s(pool_3_0::flush_cache_progress_signal(&f))
+                .add_s(pool_3_0::relocate_metadata_progress_signal(&f))
+                .add_s(pool_3_0::alert_signal(&f))
+                .add_s(pool_3_0::metadata_undersized_signal(&f))
+                .add_s(pool_3_0::snapshot_pruned_signal(&f)),
         )
         .add(
             f.interface(consts::PROPERTY_FETCH_INTERFACE_NAME_3_0, ())
@@ -66,6 +136,32 @@ pub fn create_dbus_pool<'a>(
     path
 }
 
+/// Register a pool in the engine with D-Bus, together with all of its
+/// filesystems and block devices.
+pub fn register_pool<'a>(
+    dbus_context: &DbusContext,
+    parent: dbus::Path<'static>,
+    pool_name: &Name,
+    pool_uuid: PoolUuid,
+    pool: &dyn Pool,
+) -> dbus::Path<'a> {
+    let pool_path = create_dbus_pool(dbus_context, parent, pool_name, pool_uuid, pool);
+    for (fs_name, fs_uuid, fs) in pool.filesystems() {
+        create_dbus_filesystem(
+            dbus_context,
+            pool_path.clone(),
+            pool_name,
+            &fs_name,
+            fs_uuid,
+            fs,
+        );
+    }
+    for (uuid, tier, bd) in pool.blockdevs() {
+        create_dbus_blockdev(dbus_context, pool_path.clone(), uuid, tier, bd);
+    }
+    pool_path
+}
+
 /// Get the initial state of all properties associated with a pool object.
 pub fn get_pool_properties(
     pool_name: &Name,
@@ -76,7 +172,21 @@ pub fn get_pool_properties(
         consts::POOL_INTERFACE_NAME_3_0 => {
             consts::POOL_NAME_PROP => shared::pool_name_prop(pool_name),
             consts::POOL_UUID_PROP => uuid_to_string!(pool_uuid),
-            consts::POOL_ENCRYPTED_PROP => shared::pool_enc_prop(pool)
+            consts::POOL_ENCRYPTED_PROP => shared::pool_enc_prop(pool),
+            consts::POOL_CIPHER_INFO_PROP => shared::pool_cipher_info_prop(pool),
+            consts::POOL_PBKDF_PARAMS_PROP => shared::pool_pbkdf_params_prop(pool),
+            consts::POOL_OPERATION_PROP => shared::pool_operation_prop(pool.operation_in_progress()),
+            consts::POOL_METADATA_WRITE_FAILURE_POLICY_PROP => pool.metadata_write_failure_policy().to_string(),
+            consts::POOL_SMALL_DEVICE_POLICY_PROP => pool.small_device_policy().to_string(),
+            consts::POOL_ALLOCATION_STRATEGY_PROP => pool.allocation_strategy().to_string(),
+            consts::POOL_AUTO_START_PROP => pool.auto_start(),
+            consts::POOL_START_PRIORITY_PROP => shared::pool_start_priority_prop(pool),
+            consts::POOL_DETAILED_STATS_PROP => pool.detailed_stats(),
+            consts::POOL_RESERVED_CACHE_DEVICES_PROP => shared::pool_reserved_cache_devices_prop(pool),
+            consts::POOL_REDUNDANCY_WARNINGS_PROP => shared::pool_redundancy_warnings_prop(pool),
+            consts::POOL_METADATA_UNDERSIZED_PROP => shared::pool_metadata_undersized_prop(pool),
+            consts::POOL_AUTO_PRUNE_SNAPSHOTS_ON_PRESSURE_PROP => shared::pool_auto_prune_snapshots_on_pressure_prop(pool),
+            consts::POOL_SEQUENCE_NUMBER_PROP => pool.sequence_number()
         }
     }
 }
@@ -2,67 +2,166 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use dbus_tree::{Access, EmitsChangedSignal, Factory, MTSync, Method, Property};
+use dbus_tree::{Access, EmitsChangedSignal, Factory, MTSync, Method, Property, Signal};
 
 use crate::dbus_api::{
     consts,
     pool::pool_3_0::{
         methods::{
-            add_cachedevs, add_datadevs, bind_clevis, bind_keyring, create_filesystems,
-            destroy_filesystems, init_cache, rebind_clevis, rebind_keyring, rename_pool,
-            snapshot_filesystem, unbind_clevis, unbind_keyring,
+            acknowledge_alert, add_cachedevs, add_datadevs, add_datadevs_best_effort,
+            add_datadevs_with_override, bind_clevis, bind_keyring, blockdev_paths,
+            capacity_history, compact_metadata, create_filesystem_from_template,
+            create_filesystem_on_devices, create_filesystem_with_params, create_filesystems,
+            destroy_filesystems, device_in_flight_io, device_write_cache_state,
+            disable_device_write_cache, enable_detailed_stats, estimate_integrity_overhead,
+            explain_create_filesystem, export_config, filesystem_size_limits,
+            filesystems_on_device, filesystems_pending_grow, flush_cache, full_health_report,
+            init_cache, latency_stats, list_active_alerts, list_deleted_filesystems, operation_log,
+            purge_deleted_filesystem, rebind_clevis, rebind_keyring, recommended_max_filesystems,
+            recommended_overprovision_limit, relocate_metadata, remove_cache, rename_pool,
+            reserve_cache_device, reset_transient_state, set_alert_thresholds,
+            set_allocation_strategy, set_auto_prune_snapshots_on_pressure, set_auto_start,
+            set_fs_deletion_grace_period, set_metadata_write_failure_policy,
+            set_small_device_policy, set_start_priority, shrink_by_device,
+            simulate_reconfiguration, snapshot_filesystem, structure, supported_operations,
+            surface_test, thin_pool_limits, unbind_clevis, unbind_keyring, undelete_filesystem,
+            verify_metadata_consistency,
+        },
+        props::{
+            get_pool_allocation_strategy, get_pool_auto_prune_snapshots_on_pressure,
+            get_pool_auto_start, get_pool_cipher_info, get_pool_detailed_stats, get_pool_encrypted,
+            get_pool_metadata_undersized, get_pool_metadata_write_failure_policy, get_pool_name,
+            get_pool_operation, get_pool_pbkdf_params, get_pool_redundancy_warnings,
+            get_pool_reserved_cache_devices, get_pool_sequence_number,
+            get_pool_small_device_policy, get_pool_start_priority,
         },
-        props::{get_pool_encrypted, get_pool_name},
     },
-    types::TData,
+    types::{MethodAccess, TData},
     util::get_uuid,
 };
 
 pub fn create_filesystems_method(
     f: &Factory<MTSync<TData>, TData>,
 ) -> Method<MTSync<TData>, TData> {
-    f.method("CreateFilesystems", (), create_filesystems)
-        .in_arg(("specs", "a(s(bs))"))
-        // b: true if filesystems were created
-        // a(os): Array of tuples with object paths and names
-        //
-        // Rust representation: (bool, Vec<(dbus::Path, String)>)
-        .out_arg(("results", "(ba(os))"))
-        .out_arg(("return_code", "q"))
-        .out_arg(("return_string", "s"))
+    f.method(
+        "CreateFilesystems",
+        MethodAccess::ReadWrite,
+        create_filesystems,
+    )
+    .in_arg(("specs", "a(s(bs))"))
+    // b: true if filesystems were created
+    // a(os): Array of tuples with object paths and names
+    //
+    // Rust representation: (bool, Vec<(dbus::Path, String)>)
+    .out_arg(("results", "(ba(os))"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn create_filesystem_with_params_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "CreateFilesystemWithParams",
+        MethodAccess::ReadWrite,
+        create_filesystem_with_params,
+    )
+    .in_arg(("name", "s"))
+    .in_arg(("size", "(bs)"))
+    // a{st}: mkfs.xfs parameters in bytes, keyed by one of "block_size",
+    // "inode_size", or "log_size"
+    .in_arg(("xfs_params", "a{st}"))
+    // b: true if the filesystem was created
+    // a(os): Array of tuples with object paths and names
+    //
+    // Rust representation: (bool, Vec<(dbus::Path, String)>)
+    .out_arg(("results", "(ba(os))"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn create_filesystem_on_devices_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "CreateFilesystemOnDevices",
+        MethodAccess::ReadWrite,
+        create_filesystem_on_devices,
+    )
+    .in_arg(("name", "s"))
+    .in_arg(("size", "(bs)"))
+    // as: UUIDs of the data devices from which the space backing the
+    // filesystem is preferably allocated
+    .in_arg(("devices", "as"))
+    // b: true if the filesystem was created
+    // a(os): Array of tuples with object paths and names
+    //
+    // Rust representation: (bool, Vec<(dbus::Path, String)>)
+    .out_arg(("results", "(ba(os))"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn explain_create_filesystem_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "ExplainCreateFilesystem",
+        MethodAccess::ReadOnly,
+        explain_create_filesystem,
+    )
+    .in_arg(("name", "s"))
+    .in_arg(("size", "(bs)"))
+    // a{st}: mkfs.xfs parameters in bytes, keyed by one of "block_size",
+    // "inode_size", or "log_size"
+    .in_arg(("xfs_params", "a{st}"))
+    // as: Array of the commands that CreateFilesystemWithParams would
+    // run, in order; device-mapper operations are given as the
+    // equivalent dmsetup commands
+    .out_arg(("results", "as"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
 }
 
 pub fn destroy_filesystems_method(
     f: &Factory<MTSync<TData>, TData>,
 ) -> Method<MTSync<TData>, TData> {
-    f.method("DestroyFilesystems", (), destroy_filesystems)
-        .in_arg(("filesystems", "ao"))
-        // b: true if filesystems were destroyed
-        // as: Array of UUIDs of destroyed filesystems
-        //
-        // Rust representation: (bool, Vec<String>)
-        .out_arg(("results", "(bas)"))
-        .out_arg(("return_code", "q"))
-        .out_arg(("return_string", "s"))
+    f.method(
+        "DestroyFilesystems",
+        MethodAccess::ReadWrite,
+        destroy_filesystems,
+    )
+    .in_arg(("filesystems", "ao"))
+    // b: true if filesystems were destroyed
+    // as: Array of UUIDs of destroyed filesystems
+    //
+    // Rust representation: (bool, Vec<String>)
+    .out_arg(("results", "(bas)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
 }
 
 pub fn snapshot_filesystem_method(
     f: &Factory<MTSync<TData>, TData>,
 ) -> Method<MTSync<TData>, TData> {
-    f.method("SnapshotFilesystem", (), snapshot_filesystem)
-        .in_arg(("origin", "o"))
-        .in_arg(("snapshot_name", "s"))
-        // b: false if no new snapshot was created
-        // s: Object path of new snapshot
-        //
-        // Rust representation: (bool, String)
-        .out_arg(("result", "(bo)"))
-        .out_arg(("return_code", "q"))
-        .out_arg(("return_string", "s"))
+    f.method(
+        "SnapshotFilesystem",
+        MethodAccess::ReadWrite,
+        snapshot_filesystem,
+    )
+    .in_arg(("origin", "o"))
+    .in_arg(("snapshot_name", "s"))
+    // b: false if no new snapshot was created
+    // s: Object path of new snapshot
+    //
+    // Rust representation: (bool, String)
+    .out_arg(("result", "(bo)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
 }
 
 pub fn add_blockdevs_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("AddDataDevs", (), add_datadevs)
+    f.method("AddDataDevs", MethodAccess::ReadWrite, add_datadevs)
         .in_arg(("devices", "as"))
         // b: Indicates if any data devices were added
         // ao: Array of object paths of created data devices
@@ -73,8 +172,47 @@ pub fn add_blockdevs_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<
         .out_arg(("return_string", "s"))
 }
 
+pub fn add_datadevs_with_override_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "AddDataDevsWithOverride",
+        MethodAccess::ReadWrite,
+        add_datadevs_with_override,
+    )
+    .in_arg(("devices", "as"))
+    // b: Indicates if any data devices were added
+    // ao: Array of object paths of created data devices
+    //
+    // Rust representation: (bool, Vec<dbus::path>)
+    .out_arg(("results", "(bao)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn add_datadevs_best_effort_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "AddDataDevsBestEffort",
+        MethodAccess::ReadWrite,
+        add_datadevs_best_effort,
+    )
+    .in_arg(("devices", "as"))
+    // a: One entry for each device, in the order given
+    // s: The device
+    // b: Indicates if the device was added
+    // o: Object path of the created data device, "/" if none
+    // s: The reason why the device could not be added, empty if none
+    //
+    // Rust representation: Vec<(String, bool, dbus::Path, String)>
+    .out_arg(("results", "a(sbos)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
 pub fn rename_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("SetName", (), rename_pool)
+    f.method("SetName", MethodAccess::ReadWrite, rename_pool)
         .in_arg(("name", "s"))
         // b: false if no pool was renamed
         // s: UUID of renamed pool
@@ -100,8 +238,14 @@ pub fn uuid_property(f: &Factory<MTSync<TData>, TData>) -> Property<MTSync<TData
 }
 
 pub fn init_cache_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("InitCache", (), init_cache)
+    f.method("InitCache", MethodAccess::ReadWrite, init_cache)
         .in_arg(("devices", "as"))
+        // b: true if the block size is specified
+        // t: cache block size in bytes, a power of two multiple of the
+        // sector size
+        //
+        // Rust representation: (bool, u64)
+        .in_arg(("block_size", "(bt)"))
         // b: Indicates if any cache devices were added
         // ao: Array of object paths of created cache devices
         //
@@ -112,7 +256,7 @@ pub fn init_cache_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TDa
 }
 
 pub fn add_cachedevs_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("AddCacheDevs", (), add_cachedevs)
+    f.method("AddCacheDevs", MethodAccess::ReadWrite, add_cachedevs)
         .in_arg(("devices", "as"))
         // b: Indicates if any cache devices were added
         // ao: Array of object paths of created cache devices
@@ -131,7 +275,7 @@ pub fn encrypted_property(f: &Factory<MTSync<TData>, TData>) -> Property<MTSync<
 }
 
 pub fn bind_clevis_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("Bind", (), bind_clevis)
+    f.method("Bind", MethodAccess::ReadWrite, bind_clevis)
         .in_arg(("pin", "s"))
         .in_arg(("json", "s"))
         // b: Indicates if new clevis bindings were added
@@ -143,7 +287,7 @@ pub fn bind_clevis_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TD
 }
 
 pub fn unbind_clevis_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("Unbind", (), unbind_clevis)
+    f.method("Unbind", MethodAccess::ReadWrite, unbind_clevis)
         // b: Indicates if clevis bindings were removed
         //
         // Rust representation: bool
@@ -153,7 +297,7 @@ pub fn unbind_clevis_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<
 }
 
 pub fn bind_keyring_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("BindKeyring", (), bind_keyring)
+    f.method("BindKeyring", MethodAccess::ReadWrite, bind_keyring)
         .in_arg(("key_desc", "s"))
         // b: Indicates if new keyring bindings were added
         //
@@ -164,7 +308,7 @@ pub fn bind_keyring_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<T
 }
 
 pub fn unbind_keyring_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("UnbindKeyring", (), unbind_keyring)
+    f.method("UnbindKeyring", MethodAccess::ReadWrite, unbind_keyring)
         // b: Indicates if keyring bindings were removed
         //
         // Rust representation: bool
@@ -174,7 +318,7 @@ pub fn unbind_keyring_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync
 }
 
 pub fn rebind_keyring_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("RebindKeyring", (), rebind_keyring)
+    f.method("RebindKeyring", MethodAccess::ReadWrite, rebind_keyring)
         .in_arg(("key_desc", "s"))
         // b: Indicates if keyring bindings were changed
         //
@@ -185,7 +329,7 @@ pub fn rebind_keyring_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync
 }
 
 pub fn rebind_clevis_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
-    f.method("RebindClevis", (), rebind_clevis)
+    f.method("RebindClevis", MethodAccess::ReadWrite, rebind_clevis)
         // b: Indicates if Clevis bindings were changed
         //
         // Rust representation: bool
@@ -193,3 +337,860 @@ pub fn rebind_clevis_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<
         .out_arg(("return_code", "q"))
         .out_arg(("return_string", "s"))
 }
+
+pub fn cipher_info_property(f: &Factory<MTSync<TData>, TData>) -> Property<MTSync<TData>, TData> {
+    // b: true if the pool is encrypted
+    // s: the cipher, e.g. "aes-xts-plain64"
+    // t: the size of the media encryption key in bits
+    f.property::<(bool, (&str, u64)), _>(consts::POOL_CIPHER_INFO_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::Const)
+        .on_get(get_pool_cipher_info)
+}
+
+pub fn pbkdf_params_property(f: &Factory<MTSync<TData>, TData>) -> Property<MTSync<TData>, TData> {
+    // b: true if PBKDF parameters were specified when the pool was created
+    // u: the number of iterations of argon2id
+    // u: the memory used by argon2id in KiB
+    // u: the number of threads used by argon2id
+    f.property::<(bool, (u32, u32, u32)), _>(consts::POOL_PBKDF_PARAMS_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::Const)
+        .on_get(get_pool_pbkdf_params)
+}
+
+pub fn operation_property(f: &Factory<MTSync<TData>, TData>) -> Property<MTSync<TData>, TData> {
+    f.property::<(bool, &str), _>(consts::POOL_OPERATION_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::True)
+        .on_get(get_pool_operation)
+}
+
+pub fn set_metadata_write_failure_policy_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "SetMetadataWriteFailurePolicy",
+        MethodAccess::ReadWrite,
+        set_metadata_write_failure_policy,
+    )
+    // s: One of "read_only", "retry_then_read_only", or "alert"
+    .in_arg(("policy", "s"))
+    // b: true if the policy was changed
+    .out_arg(("result", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn set_allocation_strategy_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "SetAllocationStrategy",
+        MethodAccess::ReadWrite,
+        set_allocation_strategy,
+    )
+    // s: One of "first_fit" or "best_fit"
+    .in_arg(("strategy", "s"))
+    // b: true if the strategy was changed
+    .out_arg(("result", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn set_small_device_policy_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "SetSmallDevicePolicy",
+        MethodAccess::ReadWrite,
+        set_small_device_policy,
+    )
+    // s: One of "warn", "reject", or "require_override"
+    .in_arg(("policy", "s"))
+    // b: true if the policy was changed
+    .out_arg(("result", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn shrink_by_device_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("ShrinkByDevice", MethodAccess::ReadWrite, shrink_by_device)
+        .in_arg(("dev_uuid", "s"))
+        // b: true if the device was removed from the pool
+        // s: UUID of the removed device
+        //
+        // Rust representation: (bool, String)
+        .out_arg(("result", "(bs)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn device_write_cache_state_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "DeviceWriteCacheState",
+        MethodAccess::ReadOnly,
+        device_write_cache_state,
+    )
+    .in_arg(("dev_uuid", "s"))
+    // b: true if the device has a volatile write cache enabled
+    .out_arg(("result", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn device_in_flight_io_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "DeviceInFlightIo",
+        MethodAccess::ReadOnly,
+        device_in_flight_io,
+    )
+    .in_arg(("dev_uuid", "s"))
+    // t: number of I/O requests to the device that have not completed
+    .out_arg(("result", "t"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn disable_device_write_cache_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "DisableDeviceWriteCache",
+        MethodAccess::ReadWrite,
+        disable_device_write_cache,
+    )
+    .in_arg(("dev_uuid", "s"))
+    // b: true if the write cache was previously enabled
+    .out_arg(("result", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn operation_log_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("OperationLog", MethodAccess::ReadOnly, operation_log)
+        // b: true if the number of entries returned is limited
+        // t: the maximum number of entries to return
+        .in_arg(("limit", "(bt)"))
+        // a(ss): Array of tuples of the time of the operation in rfc3339
+        // format and a description of the operation, oldest first
+        //
+        // Rust representation: Vec<(String, String)>
+        .out_arg(("results", "a(ss)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn capacity_history_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("CapacityHistory", MethodAccess::ReadOnly, capacity_history)
+        // b: true if only the samples taken since a time are requested
+        // s: the time in rfc3339 format
+        .in_arg(("since", "(bs)"))
+        // a(sss): Array of tuples of the time at which the sample was taken
+        // in rfc3339 format, the physical space used by the pool in bytes
+        // and the total physical space of the pool in bytes, oldest first
+        //
+        // Rust representation: Vec<(String, String, String)>
+        .out_arg(("results", "a(sss)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn metadata_write_failure_policy_property(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Property<MTSync<TData>, TData> {
+    f.property::<&str, _>(consts::POOL_METADATA_WRITE_FAILURE_POLICY_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::False)
+        .on_get(get_pool_metadata_write_failure_policy)
+}
+
+pub fn allocation_strategy_property(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Property<MTSync<TData>, TData> {
+    f.property::<&str, _>(consts::POOL_ALLOCATION_STRATEGY_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::False)
+        .on_get(get_pool_allocation_strategy)
+}
+
+pub fn sequence_number_property(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Property<MTSync<TData>, TData> {
+    f.property::<u64, _>(consts::POOL_SEQUENCE_NUMBER_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::Const)
+        .on_get(get_pool_sequence_number)
+}
+
+pub fn small_device_policy_property(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Property<MTSync<TData>, TData> {
+    f.property::<&str, _>(consts::POOL_SMALL_DEVICE_POLICY_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::False)
+        .on_get(get_pool_small_device_policy)
+}
+
+pub fn metadata_write_failed_signal(f: &Factory<MTSync<TData>, TData>) -> Signal<TData> {
+    f.signal(consts::POOL_METADATA_WRITE_FAILED_SIGNAL, ())
+        // s: The policy that was applied
+        .sarg::<&str, _>("policy")
+        // b: true if the pool has been marked read-only
+        .sarg::<bool, _>("read_only")
+        // s: The error encountered when writing the metadata
+        .sarg::<&str, _>("error")
+}
+
+pub fn flush_cache_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("FlushCache", MethodAccess::ReadWrite, flush_cache)
+        // t: the number of sectors written back from the cache
+        .out_arg(("result", "t"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn verify_metadata_consistency_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "VerifyMetadataConsistency",
+        MethodAccess::ReadOnly,
+        verify_metadata_consistency,
+    )
+    // b: true if all copies of the metadata agree
+    // as: Array of divergences found
+    //
+    // Rust representation: (bool, Vec<String>)
+    .out_arg(("results", "(bas)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn structure_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("Structure", MethodAccess::ReadOnly, structure)
+        // a(qtsssb): Array with an entry for each tier, the data tier first:
+        // the tier, 0 for data or 1 for cache, the number of block devices,
+        // their total size and the space used on them in bytes, the
+        // redundancy, and whether the tier is degraded
+        //
+        // Rust representation: Vec<(u16, u64, String, String, String, bool)>
+        .out_arg(("results", "a(qtsssb)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn recommended_max_filesystems_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "RecommendedMaxFilesystems",
+        MethodAccess::ReadOnly,
+        recommended_max_filesystems,
+    )
+    // t: The advised upper limit for the number of filesystems, given
+    // the size of the pool's metadata
+    .out_arg(("result", "t"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn recommended_overprovision_limit_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "RecommendedOverprovisionLimit",
+        MethodAccess::ReadOnly,
+        recommended_overprovision_limit,
+    )
+    // t: The advised upper limit for the overprovision ratio, in percent of
+    // the total physical size of the pool
+    .out_arg(("result", "t"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn filesystems_on_device_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "FilesystemsOnDevice",
+        MethodAccess::ReadOnly,
+        filesystems_on_device,
+    )
+    .in_arg(("dev_uuid", "s"))
+    // a(os): Array of the filesystems with data on the device, each
+    // with the amount of its data stored there in bytes
+    //
+    // Rust representation: Vec<(dbus::Path, String)>
+    .out_arg(("results", "a(os)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn filesystems_pending_grow_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "FilesystemsPendingGrow",
+        MethodAccess::ReadOnly,
+        filesystems_pending_grow,
+    )
+    // a(os): Array of the filesystems that have not been grown to fill
+    // their thin device, each with the unused size of the device in bytes
+    //
+    // Rust representation: Vec<(dbus::Path, String)>
+    .out_arg(("results", "a(os)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn full_health_report_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "FullHealthReport",
+        MethodAccess::ReadOnly,
+        full_health_report,
+    )
+    // b: true if no check found any problems
+    // a(sas): Array of checks, each with its name and the problems
+    // it found
+    //
+    // Rust representation: (bool, Vec<(String, Vec<String>)>)
+    .out_arg(("results", "(ba(sas))"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn latency_stats_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("LatencyStats", MethodAccess::ReadOnly, latency_stats)
+        // t: the number of read operations
+        // b: true if any read operations completed
+        // t: the average latency of read operations in nanoseconds
+        // t: the number of write operations
+        // b: true if any write operations completed
+        // t: the average latency of write operations in nanoseconds
+        // b: true if any operations completed
+        // t: the latency in nanoseconds below which 99% of all operations
+        // completed
+        //
+        // Rust representation: (u64, (bool, u64), u64, (bool, u64), (bool, u64))
+        .out_arg(("results", "(t(bt)t(bt)(bt))"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn surface_test_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("SurfaceTest", MethodAccess::ReadWrite, surface_test)
+        // b: true if every block device read back the pattern written to it
+        // a(sbs): Array of block devices, each with its UUID, whether it
+        // read back the pattern, and the problem found if it did not
+        //
+        // Rust representation: (bool, Vec<(String, bool, String)>)
+        .out_arg(("results", "(ba(sbs))"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn blockdev_paths_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("BlockDevPaths", MethodAccess::ReadOnly, blockdev_paths)
+        // s: the kind of path to report, one of "devnode", "by-id", or
+        // "by-path"; a device without a path of that kind is reported by
+        // its devnode
+        .in_arg(("style", "s"))
+        // a(ss): Array of block device UUIDs and paths
+        //
+        // Rust representation: Vec<(String, String)>
+        .out_arg(("results", "a(ss)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn supported_operations_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "SupportedOperations",
+        MethodAccess::ReadOnly,
+        supported_operations,
+    )
+    // as: Array of the operations that are currently valid on the pool,
+    // e.g. "init_cache" or "bind_clevis"
+    .out_arg(("results", "as"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn compact_metadata_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("CompactMetadata", MethodAccess::ReadWrite, compact_metadata)
+        // t: the number of stale metadata entries discarded
+        .out_arg(("result", "t"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn relocate_metadata_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "RelocateMetadata",
+        MethodAccess::ReadWrite,
+        relocate_metadata,
+    )
+    // as: UUIDs of the data devices to move the metadata to
+    .in_arg(("devices", "as"))
+    // b: true if the metadata was moved
+    .out_arg(("result", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn simulate_reconfiguration_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "SimulateReconfiguration",
+        MethodAccess::ReadOnly,
+        simulate_reconfiguration,
+    )
+    .in_arg(("add_devices", "as"))
+    .in_arg(("remove_devices", "as"))
+    // In order from left to right:
+    // s: the total size of the data tier in bytes
+    // s: the space occupied by metadata in bytes
+    // s: the space available for filesystem data in bytes
+    //
+    // Rust representation: (String, String, String)
+    .out_arg(("results", "(sss)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn estimate_integrity_overhead_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "EstimateIntegrityOverhead",
+        MethodAccess::ReadOnly,
+        estimate_integrity_overhead,
+    )
+    // One of "crc32c", "xxhash64", "sha1", "sha256"
+    .in_arg(("algorithm", "s"))
+    // In order from left to right:
+    // s: the size of the blocks that are checksummed in bytes
+    // s: the size of the checksum of each block in bytes
+    // s: the space taken up by the checksums and journals in bytes
+    // t: the bytes written to the devices per 100 bytes of data written
+    //
+    // Rust representation: (String, String, String, u64)
+    .out_arg(("results", "(ssst)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn export_config_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("ExportConfig", MethodAccess::ReadOnly, export_config)
+        // s: JSON template of the configuration of the pool, without its
+        // devices, data, or UUIDs, for use with Manager.CreateFromTemplate
+        .out_arg(("results", "s"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn remove_cache_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("RemoveCache", MethodAccess::ReadWrite, remove_cache)
+        // b: true if the pool had a cache
+        // as: Array of UUIDs of the removed cache devices
+        //
+        // Rust representation: (bool, Vec<String>)
+        .out_arg(("results", "(bas)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn flush_cache_progress_signal(f: &Factory<MTSync<TData>, TData>) -> Signal<TData> {
+    f.signal(consts::POOL_FLUSH_CACHE_PROGRESS_SIGNAL, ())
+        // t: The number of sectors written back so far
+        .sarg::<u64, _>("flushed")
+        // t: The total number of sectors to write back
+        .sarg::<u64, _>("total")
+}
+
+pub fn relocate_metadata_progress_signal(f: &Factory<MTSync<TData>, TData>) -> Signal<TData> {
+    f.signal(consts::POOL_RELOCATE_METADATA_PROGRESS_SIGNAL, ())
+        // t: The number of sectors copied so far
+        .sarg::<u64, _>("copied")
+        // t: The total number of sectors to copy
+        .sarg::<u64, _>("total")
+}
+
+pub fn filesystem_size_limits_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "FilesystemSizeLimits",
+        MethodAccess::ReadOnly,
+        filesystem_size_limits,
+    )
+    // In order from left to right:
+    // s: the minimum size of a filesystem in bytes
+    // s: the maximum size of a filesystem in bytes
+    //
+    // Rust representation: (String, String)
+    .out_arg(("results", "(ss)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn thin_pool_limits_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("ThinPoolLimits", MethodAccess::ReadOnly, thin_pool_limits)
+        // In order from left to right:
+        // s: the current size of the thin pool's data device in bytes
+        // s: the maximum size of the thin pool's data device in bytes
+        // s: the current size of the thin pool's metadata device in bytes
+        // s: the maximum size of the thin pool's metadata device in bytes
+        //
+        // Rust representation: (String, String, String, String)
+        .out_arg(("results", "(ssss)"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn set_auto_start_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method("SetAutoStart", MethodAccess::ReadWrite, set_auto_start)
+        // b: false if the pool should only be started on request
+        .in_arg(("auto_start", "b"))
+        // b: true if the setting was changed
+        .out_arg(("result", "b"))
+        .out_arg(("return_code", "q"))
+        .out_arg(("return_string", "s"))
+}
+
+pub fn auto_start_property(f: &Factory<MTSync<TData>, TData>) -> Property<MTSync<TData>, TData> {
+    f.property::<bool, _>(consts::POOL_AUTO_START_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::False)
+        .on_get(get_pool_auto_start)
+}
+
+pub fn set_alert_thresholds_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "SetAlertThresholds",
+        MethodAccess::ReadWrite,
+        set_alert_thresholds,
+    )
+    // a{st}: Dictionary of alert thresholds keyed by metric; a metric
+    // which is not in the dictionary has no threshold. The metrics are
+    // "physical_usage" and "metadata_usage" in percent, "overprovision_ratio"
+    // in percent of the physical size of the pool, and "degraded", for
+    // which the only threshold is 1.
+    //
+    // Rust representation: HashMap<String, u64>
+    .in_arg(("thresholds", "a{st}"))
+    // b: true if the thresholds were changed
+    .out_arg(("results", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn acknowledge_alert_method(f: &Factory<MTSync<TData>, TData>) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "AcknowledgeAlert",
+        MethodAccess::ReadWrite,
+        acknowledge_alert,
+    )
+    .in_arg(("metric", "s"))
+    // b: true if the alert was not already acknowledged
+    .out_arg(("results", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn reset_transient_state_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "ResetTransientState",
+        MethodAccess::ReadWrite,
+        reset_transient_state,
+    )
+    // b: true if any suspended device was resumed or any transient
+    // state was cleared
+    .out_arg(("results", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn list_active_alerts_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "ListActiveAlerts",
+        MethodAccess::ReadOnly,
+        list_active_alerts,
+    )
+    // a(sttb): Array of tuples of the metric, its value, its threshold,
+    // and whether the alert has been acknowledged
+    //
+    // Rust representation: Vec<(String, u64, u64, bool)>
+    .out_arg(("results", "a(sttb)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn alert_signal(f: &Factory<MTSync<TData>, TData>) -> Signal<TData> {
+    f.signal(consts::POOL_ALERT_SIGNAL, ())
+        // s: The UUID of the pool
+        .sarg::<&str, _>("pool_uuid")
+        // s: The metric which reached its threshold
+        .sarg::<&str, _>("metric")
+        // t: The value of the metric
+        .sarg::<u64, _>("value")
+        // t: The threshold that was reached
+        .sarg::<u64, _>("threshold")
+}
+
+pub fn metadata_undersized_signal(f: &Factory<MTSync<TData>, TData>) -> Signal<TData> {
+    f.signal(consts::POOL_METADATA_UNDERSIZED_SIGNAL, ())
+        // s: The UUID of the pool
+        .sarg::<&str, _>("pool_uuid")
+        // t: The size of the thin pool metadata device in bytes
+        .sarg::<u64, _>("current")
+        // t: The recommended size for the current size of the data tier
+        .sarg::<u64, _>("recommended")
+}
+
+pub fn metadata_undersized_property(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Property<MTSync<TData>, TData> {
+    // b: true if the thin pool metadata device is undersized
+    // t: the size of the thin pool metadata device in bytes
+    // t: the recommended size for the current size of the data tier
+    f.property::<(bool, (u64, u64)), _>(consts::POOL_METADATA_UNDERSIZED_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::False)
+        .on_get(get_pool_metadata_undersized)
+}
+
+pub fn set_start_priority_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "SetStartPriority",
+        MethodAccess::ReadWrite,
+        set_start_priority,
+    )
+    // In order from left to right:
+    // b: false to clear the start priority
+    // u: the start priority; pools with lower values are started first
+    .in_arg(("start_priority", "(bu)"))
+    // b: true if the setting was changed
+    .out_arg(("result", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn start_priority_property(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Property<MTSync<TData>, TData> {
+    f.property::<(bool, u32), _>(consts::POOL_START_PRIORITY_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::False)
+        .on_get(get_pool_start_priority)
+}
+
+pub fn set_auto_prune_snapshots_on_pressure_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "SetAutoPruneSnapshotsOnPressure",
+        MethodAccess::ReadWrite,
+        set_auto_prune_snapshots_on_pressure,
+    )
+    // In order from left to right:
+    // b: false to disable the automatic pruning of snapshots
+    // t: the physical usage of the pool, in percent of its total physical
+    // size, at or above which the oldest snapshots are destroyed
+    .in_arg(("threshold", "(bt)"))
+    // b: true if the setting was changed
+    .out_arg(("result", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn auto_prune_snapshots_on_pressure_property(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Property<MTSync<TData>, TData> {
+    f.property::<(bool, u64), _>(consts::POOL_AUTO_PRUNE_SNAPSHOTS_ON_PRESSURE_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::False)
+        .on_get(get_pool_auto_prune_snapshots_on_pressure)
+}
+
+pub fn snapshot_pruned_signal(f: &Factory<MTSync<TData>, TData>) -> Signal<TData> {
+    f.signal(consts::POOL_SNAPSHOT_PRUNED_SIGNAL, ())
+        // s: The UUID of the pool
+        .sarg::<&str, _>("pool_uuid")
+        // s: The UUID of the destroyed snapshot
+        .sarg::<&str, _>("filesystem_uuid")
+        // s: The name of the destroyed snapshot
+        .sarg::<&str, _>("name")
+}
+
+pub fn enable_detailed_stats_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "EnableDetailedStats",
+        MethodAccess::ReadWrite,
+        enable_detailed_stats,
+    )
+    // b: true to collect latency histograms for the pool's filesystems
+    .in_arg(("enabled", "b"))
+    // b: true if the setting was changed
+    .out_arg(("result", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn detailed_stats_property(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Property<MTSync<TData>, TData> {
+    f.property::<bool, _>(consts::POOL_DETAILED_STATS_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::False)
+        .on_get(get_pool_detailed_stats)
+}
+
+pub fn reserve_cache_device_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "ReserveCacheDevice",
+        MethodAccess::ReadWrite,
+        reserve_cache_device,
+    )
+    .in_arg(("devices", "as"))
+    // b: Indicates if any devices were reserved
+    // as: Array of UUIDs of the newly reserved devices
+    //
+    // Rust representation: (bool, Vec<String>)
+    .out_arg(("results", "(bas)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn reserved_cache_devices_property(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Property<MTSync<TData>, TData> {
+    // a(ss): Array of UUID and device path of each reserved cache device
+    f.property::<Vec<(&str, &str)>, _>(consts::POOL_RESERVED_CACHE_DEVICES_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::False)
+        .on_get(get_pool_reserved_cache_devices)
+}
+
+pub fn redundancy_warnings_property(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Property<MTSync<TData>, TData> {
+    // a(sas): Array of the physical disks which back more than one block
+    // device of the pool, each with the UUIDs of the block devices
+    f.property::<Vec<(&str, Vec<&str>)>, _>(consts::POOL_REDUNDANCY_WARNINGS_PROP, ())
+        .access(Access::Read)
+        .emits_changed(EmitsChangedSignal::False)
+        .on_get(get_pool_redundancy_warnings)
+}
+
+pub fn set_fs_deletion_grace_period_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "SetFilesystemDeletionGracePeriod",
+        MethodAccess::ReadWrite,
+        set_fs_deletion_grace_period,
+    )
+    // b: true if destroyed filesystems are kept for a grace period
+    // t: the grace period in seconds
+    .in_arg(("period", "(bt)"))
+    // b: true if the grace period was changed
+    .out_arg(("result", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn list_deleted_filesystems_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "ListDeletedFilesystems",
+        MethodAccess::ReadOnly,
+        list_deleted_filesystems,
+    )
+    // a(sssss): Array of tuples of the UUID of a deleted filesystem, its
+    // name, its size in bytes, the time at which it was deleted and the
+    // time after which it is destroyed, both in rfc3339 format
+    //
+    // Rust representation: Vec<(String, String, String, String, String)>
+    .out_arg(("results", "a(sssss)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn undelete_filesystem_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "UndeleteFilesystem",
+        MethodAccess::ReadWrite,
+        undelete_filesystem,
+    )
+    .in_arg(("uuid", "s"))
+    // b: false if the filesystem was not deleted
+    // o: Object path of the undeleted filesystem
+    //
+    // Rust representation: (bool, String)
+    .out_arg(("result", "(bo)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn purge_deleted_filesystem_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "PurgeDeletedFilesystem",
+        MethodAccess::ReadWrite,
+        purge_deleted_filesystem,
+    )
+    .in_arg(("uuid", "s"))
+    // b: false if there was no such deleted filesystem
+    .out_arg(("result", "b"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
+
+pub fn create_filesystem_from_template_method(
+    f: &Factory<MTSync<TData>, TData>,
+) -> Method<MTSync<TData>, TData> {
+    f.method(
+        "CreateFilesystemFromTemplate",
+        MethodAccess::ReadWrite,
+        create_filesystem_from_template,
+    )
+    // s: the name template, with a run of '#' characters that is replaced
+    // with the next number, e.g. "data-###"
+    .in_arg(("template", "s"))
+    .in_arg(("size", "(bs)"))
+    // o: Object path of the new filesystem
+    // s: Name of the new filesystem
+    //
+    // Rust representation: (dbus::Path, String)
+    .out_arg(("result", "(os)"))
+    .out_arg(("return_code", "q"))
+    .out_arg(("return_string", "s"))
+}
@@ -2,27 +2,36 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use std::{collections::HashMap, convert::TryFrom};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
+use chrono::{DateTime, SecondsFormat, Utc};
 use dbus::{arg::Array, Message};
 use dbus_tree::{MTSync, MethodInfo, MethodResult};
 use serde_json::Value;
 
-use devicemapper::Bytes;
+use devicemapper::{Bytes, Sectors};
 
 use crate::{
     dbus_api::{
-        consts::filesystem_interface_list,
+        blockdev::create_dbus_blockdev,
+        consts::{blockdev_interface_list, filesystem_interface_list},
         filesystem::create_dbus_filesystem,
         pool::shared::{add_blockdevs, BlockDevOp},
         types::{DbusErrorEnum, TData, OK_STRING},
-        util::{engine_to_dbus_err_tuple, get_next_arg, tuple_to_option},
+        util::{engine_to_dbus_err_tuple, get_next_arg, option_to_tuple, tuple_to_option},
     },
     engine::{
-        CreateAction, DeleteAction, EngineAction, FilesystemUuid, KeyDescription, Name, PoolUuid,
-        RenameAction,
+        device_path, AlertMetric, AlertThresholds, AllocationStrategy, BlockDevTier, CreateAction,
+        DeleteAction, DevUuid, DevicePathStyle, EngineAction, FilesystemUuid, IntegrityAlgorithm,
+        KeyDescription, MetadataWriteFailurePolicy, Name, PoolUuid, ReconfigurationChanges,
+        RenameAction, SmallDevicePolicy, StratisUuid, XfsParams,
     },
-    stratis::StratisError,
+    stratis::{StratisError, StratisResult},
 };
 
 pub fn create_filesystems(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
@@ -53,7 +62,7 @@ pub fn create_filesystems(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult
         return_message
     );
 
-    let mut mutex_lock = dbus_context.engine.blocking_lock();
+    let mut mutex_lock = dbus_context.lock_engine()?;
     let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
 
     let filesystem_specs = match filesystems
@@ -148,7 +157,7 @@ pub fn destroy_filesystems(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult
         return_message
     );
 
-    let mut mutex_lock = dbus_context.engine.blocking_lock();
+    let mut mutex_lock = dbus_context.lock_engine()?;
     let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
 
     let mut filesystem_map: HashMap<FilesystemUuid, dbus::Path<'static>> = HashMap::new();
@@ -236,7 +245,7 @@ pub fn snapshot_filesystem(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult
         }
     };
 
-    let mut mutex_lock = dbus_context.engine.blocking_lock();
+    let mut mutex_lock = dbus_context.lock_engine()?;
     let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
 
     let msg = match log_action!(pool.snapshot_filesystem(
@@ -278,6 +287,69 @@ pub fn add_datadevs(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
     add_blockdevs(m, BlockDevOp::AddData)
 }
 
+pub fn add_datadevs_with_override(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    add_blockdevs(m, BlockDevOp::AddDataOverride)
+}
+
+pub fn add_datadevs_best_effort(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let devs: Array<&str, _> = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: Vec<(String, bool, dbus::Path, String)> = Vec::new();
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let blockdevs = devs.map(|x| Path::new(x)).collect::<Vec<&Path>>();
+    let results = pool
+        .add_datadevs_best_effort(pool_uuid, &*pool_name, &blockdevs)
+        .into_iter()
+        .map(|(path, result)| {
+            let devnode = path.display().to_string();
+            match result {
+                Ok(Some(uuid)) => {
+                    let blockdev_path = create_dbus_blockdev(
+                        dbus_context,
+                        object_path.clone(),
+                        uuid,
+                        BlockDevTier::Data,
+                        pool.get_blockdev(uuid)
+                            .expect("just inserted by add_datadevs_best_effort")
+                            .1,
+                    );
+                    (devnode, true, blockdev_path, String::new())
+                }
+                Ok(None) => (devnode, false, dbus::Path::default(), String::new()),
+                Err(err) => {
+                    let (_, rs) = engine_to_dbus_err_tuple(&err);
+                    (devnode, false, dbus::Path::default(), rs)
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![return_message.append3(
+        results,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
 pub fn rename_pool(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
     let message: &Message = m.msg;
     let mut iter = message.iter_init();
@@ -300,11 +372,7 @@ pub fn rename_pool(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
         return_message
     );
 
-    let msg = match log_action!(dbus_context
-        .engine
-        .blocking_lock()
-        .rename_pool(pool_uuid, new_name))
-    {
+    let msg = match log_action!(dbus_context.lock_engine()?.rename_pool(pool_uuid, new_name)) {
         Ok(RenameAction::NoSource) => {
             let error_message = format!("engine doesn't know about pool {}", pool_uuid);
             let (rc, rs) = (DbusErrorEnum::ERROR as u16, error_message);
@@ -361,7 +429,7 @@ pub fn bind_clevis(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
         return_message
     );
 
-    let mut mutex_lock = dbus_context.engine.blocking_lock();
+    let mut mutex_lock = dbus_context.lock_engine()?;
     let (_, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
 
     let json: Value = match serde_json::from_str(&json_string) {
@@ -405,7 +473,7 @@ pub fn unbind_clevis(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
         return_message
     );
 
-    let mut mutex_lock = dbus_context.engine.blocking_lock();
+    let mut mutex_lock = dbus_context.lock_engine()?;
     let (_, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
 
     let msg = match log_action!(pool.unbind_clevis()) {
@@ -452,7 +520,7 @@ pub fn bind_keyring(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
         return_message
     );
 
-    let mut mutex_lock = dbus_context.engine.blocking_lock();
+    let mut mutex_lock = dbus_context.lock_engine()?;
     let (_, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
 
     let msg = match log_action!(pool.bind_keyring(&key_desc)) {
@@ -489,7 +557,7 @@ pub fn unbind_keyring(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
         return_message
     );
 
-    let mut mutex_lock = dbus_context.engine.blocking_lock();
+    let mut mutex_lock = dbus_context.lock_engine()?;
     let (_, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
 
     let msg = match log_action!(pool.unbind_keyring()) {
@@ -536,7 +604,7 @@ pub fn rebind_keyring(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
         return_message
     );
 
-    let mut mutex_lock = dbus_context.engine.blocking_lock();
+    let mut mutex_lock = dbus_context.lock_engine()?;
     let (_, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
 
     let msg = match log_action!(pool.rebind_keyring(&key_desc)) {
@@ -581,7 +649,7 @@ pub fn rebind_clevis(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
         return_message
     );
 
-    let mut mutex_lock = dbus_context.engine.blocking_lock();
+    let mut mutex_lock = dbus_context.lock_engine()?;
     let (_, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
 
     let msg = match log_action!(pool.rebind_clevis()) {
@@ -593,3 +661,2302 @@ pub fn rebind_clevis(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
     };
     Ok(vec![msg])
 }
+
+pub fn set_metadata_write_failure_policy(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let policy_str: &str = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let policy = match MetadataWriteFailurePolicy::try_from(policy_str) {
+        Ok(policy) => policy,
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.set_metadata_write_failure_policy(&pool_name, policy) {
+        Ok(changed) => {
+            return_message.append3(changed, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn set_small_device_policy(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let policy_str: &str = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let policy = match SmallDevicePolicy::try_from(policy_str) {
+        Ok(policy) => policy,
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.set_small_device_policy(&pool_name, policy) {
+        Ok(changed) => {
+            return_message.append3(changed, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn set_allocation_strategy(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let strategy_str: &str = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let strategy = match AllocationStrategy::try_from(strategy_str) {
+        Ok(strategy) => strategy,
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.set_allocation_strategy(&pool_name, strategy) {
+        Ok(changed) => {
+            return_message.append3(changed, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn set_alert_thresholds(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let thresholds_map: HashMap<&str, u64> = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let thresholds = match thresholds_map.into_iter().try_fold(
+        AlertThresholds::default(),
+        |mut thresholds, (metric, threshold)| {
+            AlertMetric::try_from(metric).map(|metric| {
+                thresholds.set(metric, Some(threshold));
+                thresholds
+            })
+        },
+    ) {
+        Ok(thresholds) => thresholds,
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.set_alert_thresholds(&pool_name, thresholds) {
+        Ok(changed) => {
+            return_message.append3(changed, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn acknowledge_alert(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let metric_str: &str = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let metric = match AlertMetric::try_from(metric_str) {
+        Ok(metric) => metric,
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.acknowledge_alert(metric) {
+        Ok(changed) => {
+            return_message.append3(changed, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn reset_transient_state(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match log_action!(pool.reset_transient_state(&pool_name, pool_uuid)) {
+        Ok(reset) => return_message.append3(reset, DbusErrorEnum::OK as u16, OK_STRING.to_string()),
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn list_active_alerts(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: Vec<(String, u64, u64, bool)> = Vec::new();
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let alerts = pool
+        .active_alerts()
+        .into_iter()
+        .map(|alert| {
+            (
+                alert.metric.to_string(),
+                alert.value,
+                alert.threshold,
+                alert.acknowledged,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![return_message.append3(
+        alerts,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn operation_log(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let limit_tuple: (bool, u64) = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: Vec<(String, String)> = Vec::new();
+
+    // A limit which does not fit in a usize can not be exceeded anyway.
+    let limit =
+        tuple_to_option(limit_tuple).map(|limit| usize::try_from(limit).unwrap_or(usize::MAX));
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.operation_log(limit) {
+        Ok(entries) => return_message.append3(
+            entries
+                .into_iter()
+                .map(|entry| {
+                    (
+                        entry.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+                        entry.operation,
+                    )
+                })
+                .collect::<Vec<_>>(),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn capacity_history(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let since_tuple: (bool, &str) = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: Vec<(String, String, String)> = Vec::new();
+
+    let since = match tuple_to_option(since_tuple)
+        .map(|since| {
+            DateTime::parse_from_rfc3339(since)
+                .map(|since| since.with_timezone(&Utc))
+                .map_err(|e| {
+                    StratisError::Msg(format!(
+                        "Could not parse {} as a time in rfc3339 format: {}",
+                        since, e
+                    ))
+                })
+        })
+        .transpose()
+    {
+        Ok(since) => since,
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let samples = pool
+        .capacity_history(since)
+        .into_iter()
+        .map(|sample| {
+            (
+                sample.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+                (*sample.used.bytes()).to_string(),
+                (*sample.total.bytes()).to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![return_message.append3(
+        samples,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn create_filesystem_with_params(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let name: &str = get_next_arg(&mut iter, 0)?;
+    let size_tuple: (bool, &str) = get_next_arg(&mut iter, 1)?;
+    let xfs_params: HashMap<String, u64> = get_next_arg(&mut iter, 2)?;
+    let dbus_context = m.tree.get_data();
+
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: (bool, Vec<(dbus::Path, &str)>) = (false, Vec::new());
+
+    let size = match tuple_to_option(size_tuple)
+        .map(|val| {
+            val.parse::<u128>().map_err(|_| {
+                format!(
+                    "Could not parse filesystem size string {} to integer value",
+                    val
+                )
+            })
+        })
+        .transpose()
+    {
+        Ok(size_opt) => size_opt.map(Bytes),
+        Err(err) => {
+            let (rc, rs) = (DbusErrorEnum::ERROR as u16, err);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let mut mkfs_params = XfsParams::default();
+    for (key, value) in xfs_params {
+        let value = Some(Bytes::from(value));
+        match key.as_str() {
+            "block_size" => mkfs_params.block_size = value,
+            "inode_size" => mkfs_params.inode_size = value,
+            "log_size" => mkfs_params.log_size = value,
+            _ => {
+                let (rc, rs) = (
+                    DbusErrorEnum::ERROR as u16,
+                    format!("Unknown mkfs.xfs parameter {}", key),
+                );
+                return Ok(vec![return_message.append3(default_return, rc, rs)]);
+            }
+        }
+    }
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let result = log_action!(pool.create_filesystem_with_params(
+        &pool_name,
+        pool_uuid,
+        name,
+        size,
+        &mkfs_params
+    ));
+
+    let infos = match result {
+        Ok(created_set) => created_set.changed(),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let return_value = match infos {
+        Some(ref newly_created_filesystems) => {
+            let v = newly_created_filesystems
+                .iter()
+                .map(|&(name, uuid, _)| {
+                    let filesystem = pool
+                        .get_filesystem(uuid)
+                        .expect("just inserted by create_filesystem_with_params")
+                        .1;
+                    (
+                        create_dbus_filesystem(
+                            dbus_context,
+                            object_path.clone(),
+                            &pool_name,
+                            &Name::new(name.to_string()),
+                            uuid,
+                            filesystem,
+                        ),
+                        name,
+                    )
+                })
+                .collect::<Vec<_>>();
+            (true, v)
+        }
+        None => default_return,
+    };
+
+    Ok(vec![return_message.append3(
+        return_value,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn create_filesystem_on_devices(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let name: &str = get_next_arg(&mut iter, 0)?;
+    let size_tuple: (bool, &str) = get_next_arg(&mut iter, 1)?;
+    let device_strs: Vec<&str> = get_next_arg(&mut iter, 2)?;
+    let dbus_context = m.tree.get_data();
+
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: (bool, Vec<(dbus::Path, &str)>) = (false, Vec::new());
+
+    let size = match tuple_to_option(size_tuple)
+        .map(|val| {
+            val.parse::<u128>().map_err(|_| {
+                format!(
+                    "Could not parse filesystem size string {} to integer value",
+                    val
+                )
+            })
+        })
+        .transpose()
+    {
+        Ok(size_opt) => size_opt.map(Bytes),
+        Err(err) => {
+            let (rc, rs) = (DbusErrorEnum::ERROR as u16, err);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let devices = match device_strs
+        .into_iter()
+        .map(|dev_uuid_str| {
+            DevUuid::parse_str(dev_uuid_str).map_err(|e| {
+                StratisError::Chained(
+                    "Malformed UUID passed to CreateFilesystemOnDevices".to_string(),
+                    Box::new(e),
+                )
+            })
+        })
+        .collect::<StratisResult<Vec<_>>>()
+    {
+        Ok(devices) => devices,
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let result =
+        log_action!(pool.create_filesystem_on_devices(&pool_name, pool_uuid, name, size, &devices));
+
+    let infos = match result {
+        Ok(created_set) => created_set.changed(),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let return_value = match infos {
+        Some(ref newly_created_filesystems) => {
+            let v = newly_created_filesystems
+                .iter()
+                .map(|&(name, uuid, _)| {
+                    let filesystem = pool
+                        .get_filesystem(uuid)
+                        .expect("just inserted by create_filesystem_on_devices")
+                        .1;
+                    (
+                        create_dbus_filesystem(
+                            dbus_context,
+                            object_path.clone(),
+                            &pool_name,
+                            &Name::new(name.to_string()),
+                            uuid,
+                            filesystem,
+                        ),
+                        name,
+                    )
+                })
+                .collect::<Vec<_>>();
+            (true, v)
+        }
+        None => default_return,
+    };
+
+    Ok(vec![return_message.append3(
+        return_value,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn explain_create_filesystem(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let name: &str = get_next_arg(&mut iter, 0)?;
+    let size_tuple: (bool, &str) = get_next_arg(&mut iter, 1)?;
+    let xfs_params: HashMap<String, u64> = get_next_arg(&mut iter, 2)?;
+    let dbus_context = m.tree.get_data();
+
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: Vec<String> = Vec::new();
+
+    let size = match tuple_to_option(size_tuple)
+        .map(|val| {
+            val.parse::<u128>().map_err(|_| {
+                format!(
+                    "Could not parse filesystem size string {} to integer value",
+                    val
+                )
+            })
+        })
+        .transpose()
+    {
+        Ok(size_opt) => size_opt.map(Bytes),
+        Err(err) => {
+            let (rc, rs) = (DbusErrorEnum::ERROR as u16, err);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let mut mkfs_params = XfsParams::default();
+    for (key, value) in xfs_params {
+        let value = Some(Bytes::from(value));
+        match key.as_str() {
+            "block_size" => mkfs_params.block_size = value,
+            "inode_size" => mkfs_params.inode_size = value,
+            "log_size" => mkfs_params.log_size = value,
+            _ => {
+                let (rc, rs) = (
+                    DbusErrorEnum::ERROR as u16,
+                    format!("Unknown mkfs.xfs parameter {}", key),
+                );
+                return Ok(vec![return_message.append3(default_return, rc, rs)]);
+            }
+        }
+    }
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.explain_create_filesystem(pool_uuid, name, size, &mkfs_params) {
+        Ok(actions) => return_message.append3(
+            actions
+                .iter()
+                .map(|action| action.to_string())
+                .collect::<Vec<_>>(),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn shrink_by_device(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: (bool, String) = (false, uuid_to_string!(DevUuid::nil()));
+
+    let dev_uuid_str: &str = get_next_arg(&mut iter, 0)?;
+    let dev_uuid = match DevUuid::parse_str(dev_uuid_str) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            let e = StratisError::Chained(
+                "Malformed UUID passed to ShrinkByDevice".to_string(),
+                Box::new(e),
+            );
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match log_action!(pool.shrink_by_device(&pool_name, dev_uuid)) {
+        Ok(DeleteAction::Identity) => return_message.append3(
+            default_return,
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Ok(DeleteAction::Deleted(uuid)) => {
+            if let Some(op) = m.tree.iter().find(|op| {
+                op.get_data().as_ref().map_or(
+                    false,
+                    |d| matches!(d.uuid, StratisUuid::Dev(u) if u == uuid),
+                )
+            }) {
+                dbus_context.push_remove(op.get_name(), blockdev_interface_list());
+            }
+            return_message.append3(
+                (true, uuid_to_string!(uuid)),
+                DbusErrorEnum::OK as u16,
+                OK_STRING.to_string(),
+            )
+        }
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn device_write_cache_state(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let dev_uuid_str: &str = get_next_arg(&mut iter, 0)?;
+    let dev_uuid = match DevUuid::parse_str(dev_uuid_str) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            let e = StratisError::Chained(
+                "Malformed UUID passed to DeviceWriteCacheState".to_string(),
+                Box::new(e),
+            );
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.device_write_cache_enabled(dev_uuid) {
+        Ok(b) => return_message.append3(b, DbusErrorEnum::OK as u16, OK_STRING.to_string()),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn device_in_flight_io(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = 0u64;
+
+    let dev_uuid_str: &str = get_next_arg(&mut iter, 0)?;
+    let dev_uuid = match DevUuid::parse_str(dev_uuid_str) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            let e = StratisError::Chained(
+                "Malformed UUID passed to DeviceInFlightIo".to_string(),
+                Box::new(e),
+            );
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.device_in_flight_io(dev_uuid) {
+        Ok(count) => return_message.append3(count, DbusErrorEnum::OK as u16, OK_STRING.to_string()),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn disable_device_write_cache(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let dev_uuid_str: &str = get_next_arg(&mut iter, 0)?;
+    let dev_uuid = match DevUuid::parse_str(dev_uuid_str) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            let e = StratisError::Chained(
+                "Malformed UUID passed to DisableDeviceWriteCache".to_string(),
+                Box::new(e),
+            );
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match log_action!(pool.disable_device_write_cache(dev_uuid)) {
+        Ok(b) => return_message.append3(b, DbusErrorEnum::OK as u16, OK_STRING.to_string()),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn filesystem_size_limits(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = (String::new(), String::new());
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let (min, max) = pool.filesystem_size_limits();
+    Ok(vec![return_message.append3(
+        ((*min.bytes()).to_string(), (*max.bytes()).to_string()),
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn thin_pool_limits(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = (String::new(), String::new(), String::new(), String::new());
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let limits = pool.thin_pool_limits();
+    Ok(vec![return_message.append3(
+        (
+            (*limits.data_size.bytes()).to_string(),
+            (*limits.max_data_size.bytes()).to_string(),
+            (*limits.meta_size.bytes()).to_string(),
+            (*limits.max_meta_size.bytes()).to_string(),
+        ),
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn set_auto_start(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let auto_start: bool = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.set_auto_start(&pool_name, auto_start) {
+        Ok(changed) => {
+            return_message.append3(changed, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn set_start_priority(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let start_priority: (bool, u32) = get_next_arg(&mut iter, 0)?;
+    let start_priority = tuple_to_option(start_priority);
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.set_start_priority(&pool_name, start_priority) {
+        Ok(changed) => {
+            return_message.append3(changed, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn set_auto_prune_snapshots_on_pressure(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let threshold: (bool, u64) = get_next_arg(&mut iter, 0)?;
+    let threshold = tuple_to_option(threshold);
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match log_action!(pool.set_auto_prune_snapshots_on_pressure(&pool_name, threshold)) {
+        Ok(changed) => {
+            return_message.append3(changed, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn enable_detailed_stats(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let enabled: bool = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.set_detailed_stats(&pool_name, enabled) {
+        Ok(changed) => {
+            return_message.append3(changed, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn reserve_cache_device(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let devs: Array<&str, _> = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: (bool, Vec<String>) = (false, Vec::new());
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let blockdevs = devs.map(Path::new).collect::<Vec<&Path>>();
+
+    let msg = match log_action!(pool.reserve_cache_devices(pool_uuid, &pool_name, &blockdevs))
+        .map(|bds| bds.changed())
+    {
+        Ok(Some(uuids)) => return_message.append3(
+            (
+                true,
+                uuids
+                    .iter()
+                    .map(|uuid| uuid_to_string!(uuid))
+                    .collect::<Vec<_>>(),
+            ),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Ok(None) => return_message.append3(
+            default_return,
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn flush_cache(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = 0u64;
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let work = {
+        let mut mutex_lock = dbus_context.lock_engine()?;
+        let (_, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+        match pool.start_flush_cache() {
+            Ok(work) => work,
+            Err(err) => {
+                let (rc, rs) = engine_to_dbus_err_tuple(&err);
+                return Ok(vec![return_message.append3(default_return, rc, rs)]);
+            }
+        }
+    };
+
+    // The dirty blocks are written back once the engine lock has been
+    // released, so that other requests are not held up. A progress signal
+    // is sent each time another percent of the dirty data has been written
+    // back.
+    let mut last_percent = 0;
+    let mut progress = |flushed: Sectors, total: Sectors| {
+        let percent = *flushed * 100 / *total;
+        if percent != last_percent {
+            last_percent = percent;
+            dbus_context.push_flush_cache_progress(object_path, *flushed, *total);
+        }
+    };
+
+    let flushed = work(&mut progress);
+
+    // The cache must be switched back to its own policy whatever the
+    // outcome, so the engine lock is waited for without a timeout.
+    let mut mutex_lock = dbus_context.engine.blocking_lock();
+    let (_, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+    let msg = match pool.finish_flush_cache(flushed) {
+        Ok(flushed) => {
+            return_message.append3(*flushed, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn relocate_metadata(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let device_strs: Vec<&str> = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let devices = match device_strs
+        .into_iter()
+        .map(|dev_uuid_str| {
+            DevUuid::parse_str(dev_uuid_str).map_err(|e| {
+                StratisError::Chained(
+                    "Malformed UUID passed to RelocateMetadata".to_string(),
+                    Box::new(e),
+                )
+            })
+        })
+        .collect::<StratisResult<Vec<_>>>()
+    {
+        Ok(devices) => devices,
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    // Send a progress signal each time another percent of the metadata
+    // has been copied.
+    let mut last_percent = 0;
+    let mut progress = |copied: Sectors, total: Sectors| {
+        let percent = *copied * 100 / *total;
+        if percent != last_percent {
+            last_percent = percent;
+            dbus_context.push_relocate_metadata_progress(object_path, *copied, *total);
+        }
+    };
+
+    let msg =
+        match log_action!(pool.relocate_metadata(&pool_name, pool_uuid, &devices, &mut progress)) {
+            Ok(relocated) => {
+                return_message.append3(relocated, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+            }
+            Err(err) => {
+                let (rc, rs) = engine_to_dbus_err_tuple(&err);
+                return_message.append3(default_return, rc, rs)
+            }
+        };
+
+    Ok(vec![msg])
+}
+
+pub fn compact_metadata(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = 0u64;
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.compact_metadata() {
+        Ok(discarded) => {
+            return_message.append3(discarded, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn verify_metadata_consistency(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: (bool, Vec<String>) = (false, Vec::new());
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.verify_metadata_consistency(&pool_name) {
+        Ok(check) => return_message.append3(
+            (check.consistent, check.problems),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn structure(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: Vec<(u16, u64, String, String, String, bool)> = Vec::new();
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let tiers = pool
+        .structure()
+        .into_iter()
+        .map(|tier| {
+            (
+                tier.tier as u16,
+                tier.device_count as u64,
+                (*tier.total_size).to_string(),
+                (*tier.used_size).to_string(),
+                tier.redundancy.to_string(),
+                tier.degraded,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![return_message.append3(
+        tiers,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn recommended_overprovision_limit(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = 0u64;
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.recommended_overprovision_limit() {
+        Ok(limit) => return_message.append3(limit, DbusErrorEnum::OK as u16, OK_STRING.to_string()),
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn recommended_max_filesystems(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = 0u64;
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.recommended_max_filesystems() {
+        Ok(limit) => return_message.append3(limit, DbusErrorEnum::OK as u16, OK_STRING.to_string()),
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn filesystems_on_device(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: Vec<(dbus::Path, String)> = Vec::new();
+
+    let dev_uuid_str: &str = get_next_arg(&mut iter, 0)?;
+    let dev_uuid = match DevUuid::parse_str(dev_uuid_str) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            let e = StratisError::Chained(
+                "Malformed UUID passed to FilesystemsOnDevice".to_string(),
+                Box::new(e),
+            );
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.filesystems_on_device(dev_uuid) {
+        Ok(filesystems) => return_message.append3(
+            filesystems
+                .into_iter()
+                .filter_map(|(fs_uuid, amount)| {
+                    m.tree
+                        .iter()
+                        .find(|op| {
+                            op.get_data().as_ref().map_or(
+                                false,
+                                |d| matches!(d.uuid, StratisUuid::Fs(u) if u == fs_uuid),
+                            )
+                        })
+                        .map(|op| (op.get_name().clone(), (*amount).to_string()))
+                })
+                .collect::<Vec<_>>(),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn filesystems_pending_grow(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: Vec<(dbus::Path, String)> = Vec::new();
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.filesystems_pending_grow() {
+        Ok(filesystems) => return_message.append3(
+            filesystems
+                .into_iter()
+                .filter_map(|(fs_uuid, unused)| {
+                    m.tree
+                        .iter()
+                        .find(|op| {
+                            op.get_data().as_ref().map_or(
+                                false,
+                                |d| matches!(d.uuid, StratisUuid::Fs(u) if u == fs_uuid),
+                            )
+                        })
+                        .map(|op| (op.get_name().clone(), (*unused).to_string()))
+                })
+                .collect::<Vec<_>>(),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn full_health_report(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: (bool, Vec<(String, Vec<String>)>) = (false, Vec::new());
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.full_health_report(&pool_name) {
+        Ok(report) => return_message.append3(
+            (
+                report.healthy(),
+                report
+                    .checks
+                    .into_iter()
+                    .map(|check| (check.name, check.problems))
+                    .collect::<Vec<_>>(),
+            ),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn latency_stats(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: (u64, (bool, u64), u64, (bool, u64), (bool, u64)) =
+        (0, (false, 0), 0, (false, 0), (false, 0));
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let nanos = |d: Option<Duration>| {
+        option_to_tuple(
+            d.map(|d| u64::try_from(d.as_nanos()).unwrap_or(u64::MAX)),
+            0,
+        )
+    };
+
+    let msg = match pool.latency_stats() {
+        Ok(stats) => return_message.append3(
+            (
+                stats.read_ops,
+                nanos(stats.read_average),
+                stats.write_ops,
+                nanos(stats.write_average),
+                nanos(stats.p99),
+            ),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn surface_test(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: (bool, Vec<(String, bool, String)>) = (false, Vec::new());
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.surface_test() {
+        Ok(results) => return_message.append3(
+            (
+                results.iter().all(|result| result.problem.is_none()),
+                results
+                    .into_iter()
+                    .map(|result| {
+                        (
+                            uuid_to_string!(result.dev_uuid),
+                            result.problem.is_none(),
+                            result.problem.unwrap_or_default(),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn blockdev_paths(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+
+    let style_str: &str = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: Vec<(String, String)> = Vec::new();
+
+    let style = match DevicePathStyle::try_from(style_str) {
+        Ok(style) => style,
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    Ok(vec![return_message.append3(
+        pool.blockdevs()
+            .into_iter()
+            .map(|(uuid, _, bd)| {
+                (
+                    uuid_to_string!(uuid),
+                    device_path(bd.devnode(), style).display().to_string(),
+                )
+            })
+            .collect::<Vec<_>>(),
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn supported_operations(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: Vec<String> = Vec::new();
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    Ok(vec![return_message.append3(
+        pool.supported_operations()
+            .iter()
+            .map(|operation| operation.to_string())
+            .collect::<Vec<_>>(),
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn simulate_reconfiguration(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let add_devices: Array<&str, _> = get_next_arg(&mut iter, 0)?;
+    let remove_devices: Array<&str, _> = get_next_arg(&mut iter, 1)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = (String::new(), String::new(), String::new());
+
+    let changes = ReconfigurationChanges {
+        add_data_devices: add_devices.map(PathBuf::from).collect(),
+        remove_data_devices: match remove_devices
+            .map(DevUuid::parse_str)
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(uuids) => uuids,
+            Err(e) => {
+                let e = StratisError::Chained(
+                    "Malformed UUID passed to SimulateReconfiguration".to_string(),
+                    Box::new(e),
+                );
+                let (rc, rs) = engine_to_dbus_err_tuple(&e);
+                return Ok(vec![return_message.append3(default_return, rc, rs)]);
+            }
+        },
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match pool.simulate_reconfiguration(&changes) {
+        Ok(estimate) => return_message.append3(
+            (
+                (*estimate.total.bytes()).to_string(),
+                (*estimate.overhead.bytes()).to_string(),
+                (*estimate.usable.bytes()).to_string(),
+            ),
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn estimate_integrity_overhead(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let algorithm_str: &str = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = (String::new(), String::new(), String::new(), 0u64);
+
+    let algorithm = match IntegrityAlgorithm::try_from(algorithm_str) {
+        Ok(algorithm) => algorithm,
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let overhead = pool.estimate_integrity_overhead(algorithm);
+    Ok(vec![return_message.append3(
+        (
+            (*overhead.block_size).to_string(),
+            (*overhead.tag_size).to_string(),
+            (*overhead.metadata.bytes()).to_string(),
+            overhead.write_amplification_percent,
+        ),
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn export_config(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = String::new();
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match serde_json::to_string(&pool.export_config()) {
+        Ok(template) => {
+            return_message.append3(template, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&StratisError::Serde(e));
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+
+    Ok(vec![msg])
+}
+
+pub fn remove_cache(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: (bool, Vec<String>) = (false, Vec::new());
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match log_action!(pool.remove_cache(pool_uuid, &pool_name)) {
+        Ok(uuids) => {
+            let uuid_vec: Vec<String> = if let Some(ref changed_uuids) = uuids.changed() {
+                for uuid in changed_uuids {
+                    if let Some(op) = m.tree.iter().find(|op| {
+                        op.get_data().as_ref().map_or(
+                            false,
+                            |d| matches!(d.uuid, StratisUuid::Dev(u) if u == *uuid),
+                        )
+                    }) {
+                        dbus_context.push_remove(op.get_name(), blockdev_interface_list());
+                    }
+                }
+                changed_uuids
+                    .iter()
+                    .map(|uuid| uuid_to_string!(uuid))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            return_message.append3(
+                (true, uuid_vec),
+                DbusErrorEnum::OK as u16,
+                OK_STRING.to_string(),
+            )
+        }
+        Err(err) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&err);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn set_fs_deletion_grace_period(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let period_tuple: (bool, u64) = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let period = tuple_to_option(period_tuple).map(Duration::from_secs);
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match log_action!(pool.set_fs_deletion_grace_period(&pool_name, pool_uuid, period)) {
+        Ok(changed) => {
+            return_message.append3(changed, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn list_deleted_filesystems(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return: Vec<(String, String, String, String, String)> = Vec::new();
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let deleted = pool
+        .deleted_filesystems()
+        .into_iter()
+        .map(|fs| {
+            (
+                uuid_to_string!(fs.uuid),
+                fs.name,
+                (*fs.size.bytes()).to_string(),
+                fs.deleted.to_rfc3339_opts(SecondsFormat::Secs, true),
+                fs.purge_after.to_rfc3339_opts(SecondsFormat::Secs, true),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![return_message.append3(
+        deleted,
+        DbusErrorEnum::OK as u16,
+        OK_STRING.to_string(),
+    )])
+}
+
+pub fn undelete_filesystem(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let fs_uuid_str: &str = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = (false, dbus::Path::default());
+
+    let fs_uuid = match FilesystemUuid::parse_str(fs_uuid_str) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            let e = StratisError::Chained(
+                "Malformed UUID passed to UndeleteFilesystem".to_string(),
+                Box::new(e),
+            );
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match log_action!(pool.undelete_filesystem(&pool_name, pool_uuid, fs_uuid)) {
+        Ok(true) => {
+            let (fs_name, fs) = pool
+                .get_filesystem(fs_uuid)
+                .expect("just undeleted by undelete_filesystem");
+            let fs_object_path: dbus::Path = create_dbus_filesystem(
+                dbus_context,
+                object_path.clone(),
+                &pool_name,
+                &fs_name,
+                fs_uuid,
+                fs,
+            );
+            return_message.append3(
+                (true, fs_object_path),
+                DbusErrorEnum::OK as u16,
+                OK_STRING.to_string(),
+            )
+        }
+        Ok(false) => return_message.append3(
+            default_return,
+            DbusErrorEnum::OK as u16,
+            OK_STRING.to_string(),
+        ),
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn purge_deleted_filesystem(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let fs_uuid_str: &str = get_next_arg(&mut iter, 0)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = false;
+
+    let fs_uuid = match FilesystemUuid::parse_str(fs_uuid_str) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            let e = StratisError::Chained(
+                "Malformed UUID passed to PurgeDeletedFilesystem".to_string(),
+                Box::new(e),
+            );
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (_, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match log_action!(pool.purge_deleted_filesystem(pool_uuid, fs_uuid)) {
+        Ok(purged) => {
+            return_message.append3(purged, DbusErrorEnum::OK as u16, OK_STRING.to_string())
+        }
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
+
+pub fn create_filesystem_from_template(m: &MethodInfo<MTSync<TData>, TData>) -> MethodResult {
+    let message: &Message = m.msg;
+    let mut iter = message.iter_init();
+    let template: &str = get_next_arg(&mut iter, 0)?;
+    let size_tuple: (bool, &str) = get_next_arg(&mut iter, 1)?;
+
+    let dbus_context = m.tree.get_data();
+    let object_path = m.path.get_name();
+    let return_message = message.method_return();
+    let default_return = (dbus::Path::default(), String::new());
+
+    let size = match tuple_to_option(size_tuple)
+        .map(|val| {
+            val.parse::<u128>().map_err(|_| {
+                format!(
+                    "Could not parse filesystem size string {} to integer value",
+                    val
+                )
+            })
+        })
+        .transpose()
+    {
+        Ok(size) => size.map(Bytes),
+        Err(err) => {
+            let (rc, rs) = (DbusErrorEnum::ERROR as u16, err);
+            return Ok(vec![return_message.append3(default_return, rc, rs)]);
+        }
+    };
+
+    let pool_path = m
+        .tree
+        .get(object_path)
+        .expect("implicit argument must be in tree");
+    let pool_uuid = typed_uuid!(
+        get_data!(pool_path; default_return; return_message).uuid;
+        Pool;
+        default_return;
+        return_message
+    );
+
+    let mut mutex_lock = dbus_context.lock_engine()?;
+    let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
+
+    let msg = match log_action!(
+        pool.create_filesystem_from_template(&pool_name, pool_uuid, template, size)
+    ) {
+        Ok((fs_name, fs_uuid)) => {
+            let (_, fs) = pool
+                .get_filesystem(fs_uuid)
+                .expect("just created by create_filesystem_from_template");
+            let fs_object_path: dbus::Path = create_dbus_filesystem(
+                dbus_context,
+                object_path.clone(),
+                &pool_name,
+                &fs_name,
+                fs_uuid,
+                fs,
+            );
+            return_message.append3(
+                (fs_object_path, fs_name.to_string()),
+                DbusErrorEnum::OK as u16,
+                OK_STRING.to_string(),
+            )
+        }
+        Err(e) => {
+            let (rc, rs) = engine_to_dbus_err_tuple(&e);
+            return_message.append3(default_return, rc, rs)
+        }
+    };
+    Ok(vec![msg])
+}
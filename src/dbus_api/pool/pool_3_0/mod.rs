@@ -3,8 +3,32 @@ mod methods;
 mod props;
 
 pub use api::{
-    add_blockdevs_method, add_cachedevs_method, bind_clevis_method, bind_keyring_method,
-    create_filesystems_method, destroy_filesystems_method, encrypted_property, init_cache_method,
-    name_property, rebind_clevis_method, rebind_keyring_method, rename_method,
-    snapshot_filesystem_method, unbind_clevis_method, unbind_keyring_method, uuid_property,
+    acknowledge_alert_method, add_blockdevs_method, add_cachedevs_method,
+    add_datadevs_best_effort_method, add_datadevs_with_override_method, alert_signal,
+    allocation_strategy_property, auto_prune_snapshots_on_pressure_property, auto_start_property,
+    bind_clevis_method, bind_keyring_method, blockdev_paths_method, capacity_history_method,
+    cipher_info_property, compact_metadata_method, create_filesystem_from_template_method,
+    create_filesystem_on_devices_method, create_filesystem_with_params_method,
+    create_filesystems_method, destroy_filesystems_method, detailed_stats_property,
+    device_in_flight_io_method, device_write_cache_state_method, disable_device_write_cache_method,
+    enable_detailed_stats_method, encrypted_property, estimate_integrity_overhead_method,
+    explain_create_filesystem_method, export_config_method, filesystem_size_limits_method,
+    filesystems_on_device_method, filesystems_pending_grow_method, flush_cache_method,
+    flush_cache_progress_signal, full_health_report_method, init_cache_method,
+    latency_stats_method, list_active_alerts_method, list_deleted_filesystems_method,
+    metadata_undersized_property, metadata_undersized_signal, metadata_write_failed_signal,
+    metadata_write_failure_policy_property, name_property, operation_log_method,
+    operation_property, pbkdf_params_property, purge_deleted_filesystem_method,
+    rebind_clevis_method, rebind_keyring_method, recommended_max_filesystems_method,
+    recommended_overprovision_limit_method, redundancy_warnings_property, relocate_metadata_method,
+    relocate_metadata_progress_signal, remove_cache_method, rename_method,
+    reserve_cache_device_method, reserved_cache_devices_property, reset_transient_state_method,
+    sequence_number_property, set_alert_thresholds_method, set_allocation_strategy_method,
+    set_auto_prune_snapshots_on_pressure_method, set_auto_start_method,
+    set_fs_deletion_grace_period_method, set_metadata_write_failure_policy_method,
+    set_small_device_policy_method, set_start_priority_method, shrink_by_device_method,
+    simulate_reconfiguration_method, small_device_policy_property, snapshot_filesystem_method,
+    snapshot_pruned_signal, start_priority_property, structure_method, supported_operations_method,
+    surface_test_method, thin_pool_limits_method, unbind_clevis_method, unbind_keyring_method,
+    undelete_filesystem_method, uuid_property, verify_metadata_consistency_method,
 };
@@ -23,3 +23,121 @@ pub fn get_pool_encrypted(
 ) -> Result<(), MethodErr> {
     get_pool_property(i, p, |(_, _, pool)| Ok(shared::pool_enc_prop(pool)))
 }
+
+pub fn get_pool_cipher_info(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_pool_property(i, p, |(_, _, pool)| Ok(shared::pool_cipher_info_prop(pool)))
+}
+
+pub fn get_pool_pbkdf_params(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_pool_property(i, p, |(_, _, pool)| {
+        Ok(shared::pool_pbkdf_params_prop(pool))
+    })
+}
+
+pub fn get_pool_metadata_undersized(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_pool_property(i, p, |(_, _, pool)| {
+        Ok(shared::pool_metadata_undersized_prop(pool))
+    })
+}
+
+pub fn get_pool_operation(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_pool_property(i, p, |(_, _, pool)| {
+        Ok(shared::pool_operation_prop(pool.operation_in_progress()))
+    })
+}
+
+pub fn get_pool_metadata_write_failure_policy(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_pool_property(i, p, |(_, _, pool)| {
+        Ok(pool.metadata_write_failure_policy().to_string())
+    })
+}
+
+pub fn get_pool_allocation_strategy(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_pool_property(i, p, |(_, _, pool)| {
+        Ok(pool.allocation_strategy().to_string())
+    })
+}
+
+pub fn get_pool_sequence_number(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_pool_property(i, p, |(_, _, pool)| Ok(pool.sequence_number()))
+}
+
+pub fn get_pool_small_device_policy(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_pool_property(i, p, |(_, _, pool)| {
+        Ok(pool.small_device_policy().to_string())
+    })
+}
+
+pub fn get_pool_auto_start(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_pool_property(i, p, |(_, _, pool)| Ok(pool.auto_start()))
+}
+
+pub fn get_pool_auto_prune_snapshots_on_pressure(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_pool_property(i, p, |(_, _, pool)| {
+        Ok(shared::pool_auto_prune_snapshots_on_pressure_prop(pool))
+    })
+}
+
+pub fn get_pool_start_priority(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_pool_property(i, p, |(_, _, pool)| {
+        Ok(shared::pool_start_priority_prop(pool))
+    })
+}
+
+pub fn get_pool_detailed_stats(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_pool_property(i, p, |(_, _, pool)| Ok(pool.detailed_stats()))
+}
+
+pub fn get_pool_redundancy_warnings(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_pool_property(i, p, |(_, _, pool)| {
+        Ok(shared::pool_redundancy_warnings_prop(pool))
+    })
+}
+
+pub fn get_pool_reserved_cache_devices(
+    i: &mut IterAppend,
+    p: &PropInfo<MTSync<TData>, TData>,
+) -> Result<(), MethodErr> {
+    get_pool_property(i, p, |(_, _, pool)| {
+        Ok(shared::pool_reserved_cache_devices_prop(pool))
+    })
+}
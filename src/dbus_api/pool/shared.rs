@@ -10,19 +10,22 @@ use dbus::{
 };
 use dbus_tree::{MTSync, MethodErr, MethodInfo, MethodResult, PropInfo, Tree};
 
+use devicemapper::Bytes;
+
 use crate::{
     dbus_api::{
         blockdev::create_dbus_blockdev,
         types::{DbusErrorEnum, TData, OK_STRING},
-        util::{engine_to_dbus_err_tuple, get_next_arg, option_to_tuple},
+        util::{engine_to_dbus_err_tuple, get_next_arg, option_to_tuple, tuple_to_option},
     },
-    engine::{BlockDevTier, EngineAction, Name, Pool, PoolUuid},
+    engine::{BlockDevTier, EngineAction, Name, Pool, PoolOperation, PoolUuid},
 };
 
 pub enum BlockDevOp {
     InitCache,
     AddCache,
     AddData,
+    AddDataOverride,
 }
 
 pub fn pool_operation<F, R>(
@@ -77,6 +80,20 @@ pub fn get_pool_has_cache(m: &MethodInfo<MTSync<TData>, TData>) -> Result<bool,
     })
 }
 
+pub fn get_pool_cache_block_size(
+    m: &MethodInfo<MTSync<TData>, TData>,
+) -> Result<(bool, u64), String> {
+    pool_operation(m.tree, m.path.get_name(), |(_, _, pool)| {
+        Ok(option_to_tuple(
+            pool.cache_block_size().map(|size| {
+                // A cache block size is at most 1 GiB
+                u64::try_from(*size.bytes()).unwrap_or(u64::MAX)
+            }),
+            0,
+        ))
+    })
+}
+
 pub fn get_pool_total_size(m: &MethodInfo<MTSync<TData>, TData>) -> Result<String, String> {
     pool_operation(m.tree, m.path.get_name(), |(_, _, pool)| {
         Ok((*pool.total_physical_size().bytes()).to_string())
@@ -122,6 +139,30 @@ pub fn add_blockdevs(m: &MethodInfo<MTSync<TData>, TData>, op: BlockDevOp) -> Me
     let return_message = message.method_return();
     let default_return: (bool, Vec<dbus::Path>) = (false, Vec::new());
 
+    let cache_block_size = match op {
+        BlockDevOp::InitCache => {
+            let block_size_tuple: (bool, u64) = get_next_arg(&mut iter, 1)?;
+            match tuple_to_option(block_size_tuple) {
+                Some(value) => {
+                    let block_size = Bytes::from(value).sectors();
+                    if block_size.bytes() != Bytes::from(value) {
+                        let (rc, rs) = (
+                            DbusErrorEnum::ERROR as u16,
+                            format!(
+                                "Cache block size {} is not a multiple of the sector size",
+                                value
+                            ),
+                        );
+                        return Ok(vec![return_message.append3(default_return, rc, rs)]);
+                    }
+                    Some(block_size)
+                }
+                None => None,
+            }
+        }
+        BlockDevOp::AddCache | BlockDevOp::AddData | BlockDevOp::AddDataOverride => None,
+    };
+
     let pool_path = m
         .tree
         .get(object_path)
@@ -133,19 +174,24 @@ pub fn add_blockdevs(m: &MethodInfo<MTSync<TData>, TData>, op: BlockDevOp) -> Me
         return_message
     );
 
-    let mut mutex_lock = dbus_context.engine.blocking_lock();
+    let mut mutex_lock = dbus_context.lock_engine()?;
     let (pool_name, pool) = get_mut_pool!(mutex_lock; pool_uuid; default_return; return_message);
 
     let blockdevs = devs.map(|x| Path::new(x)).collect::<Vec<&Path>>();
 
     let result = match op {
-        BlockDevOp::InitCache => log_action!(pool.init_cache(pool_uuid, &*pool_name, &blockdevs)),
+        BlockDevOp::InitCache => {
+            log_action!(pool.init_cache(pool_uuid, &*pool_name, &blockdevs, cache_block_size))
+        }
         BlockDevOp::AddCache => {
             log_action!(pool.add_blockdevs(pool_uuid, &*pool_name, &blockdevs, BlockDevTier::Cache))
         }
         BlockDevOp::AddData => {
             log_action!(pool.add_blockdevs(pool_uuid, &*pool_name, &blockdevs, BlockDevTier::Data))
         }
+        BlockDevOp::AddDataOverride => {
+            log_action!(pool.add_datadevs_with_override(pool_uuid, &*pool_name, &blockdevs))
+        }
     };
     let msg = match result.map(|bds| bds.changed()) {
         Ok(Some(uuids)) => {
@@ -160,7 +206,7 @@ pub fn add_blockdevs(m: &MethodInfo<MTSync<TData>, TData>, op: BlockDevOp) -> Me
                         object_path.clone(),
                         *uuid,
                         match op {
-                            BlockDevOp::AddData => BlockDevTier::Data,
+                            BlockDevOp::AddData | BlockDevOp::AddDataOverride => BlockDevTier::Data,
                             _ => BlockDevTier::Cache,
                         },
                         pool.get_blockdev(*uuid)
@@ -219,3 +265,84 @@ pub fn pool_name_prop(name: &Name) -> String {
 pub fn pool_enc_prop(pool: &dyn Pool) -> bool {
     pool.is_encrypted()
 }
+
+/// Generate D-Bus representation of cipher info property.
+#[inline]
+pub fn pool_cipher_info_prop(pool: &dyn Pool) -> (bool, (String, u64)) {
+    option_to_tuple(
+        if pool.is_encrypted() {
+            let cipher_info = &pool.encryption_info().cipher_info;
+            Some((cipher_info.cipher.to_owned(), cipher_info.key_size as u64))
+        } else {
+            None
+        },
+        (String::new(), 0),
+    )
+}
+
+/// Generate D-Bus representation of PBKDF parameters property.
+#[inline]
+pub fn pool_pbkdf_params_prop(pool: &dyn Pool) -> (bool, (u32, u32, u32)) {
+    option_to_tuple(
+        pool.encryption_info()
+            .pbkdf_params
+            .map(|params| (params.iterations, params.memory_kib, params.parallelism)),
+        (0, 0, 0),
+    )
+}
+
+/// Generate D-Bus representation of metadata undersized property.
+#[inline]
+pub fn pool_metadata_undersized_prop(pool: &dyn Pool) -> (bool, (u64, u64)) {
+    option_to_tuple(
+        pool.metadata_undersized()
+            .map(|(current, recommended)| (*current as u64, *recommended as u64)),
+        (0, 0),
+    )
+}
+
+/// Generate D-Bus representation of the automatic snapshot pruning
+/// threshold property.
+#[inline]
+pub fn pool_auto_prune_snapshots_on_pressure_prop(pool: &dyn Pool) -> (bool, u64) {
+    option_to_tuple(pool.auto_prune_snapshots_on_pressure(), 0)
+}
+
+/// Generate D-Bus representation of start priority property.
+#[inline]
+pub fn pool_start_priority_prop(pool: &dyn Pool) -> (bool, u32) {
+    option_to_tuple(pool.start_priority(), 0)
+}
+
+/// Generate D-Bus representation of reserved cache devices property.
+#[inline]
+pub fn pool_reserved_cache_devices_prop(pool: &dyn Pool) -> Vec<(String, String)> {
+    pool.reserved_cache_devices()
+        .into_iter()
+        .map(|(uuid, path)| (uuid_to_string!(uuid), path.display().to_string()))
+        .collect()
+}
+
+/// Generate D-Bus representation of redundancy warnings property.
+#[inline]
+pub fn pool_redundancy_warnings_prop(pool: &dyn Pool) -> Vec<(String, Vec<String>)> {
+    pool.redundancy_warnings()
+        .into_iter()
+        .map(|warning| {
+            (
+                warning.disk,
+                warning
+                    .dev_uuids
+                    .into_iter()
+                    .map(|uuid| uuid_to_string!(uuid))
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+/// Generate D-Bus representation of operation in progress property.
+#[inline]
+pub fn pool_operation_prop(operation: Option<PoolOperation>) -> (bool, String) {
+    option_to_tuple(operation.map(|op| op.to_string()), String::new())
+}
@@ -8,19 +8,30 @@ use std::{
     fmt::{self, Debug},
     sync::{
         atomic::{AtomicU64, Ordering},
-        Arc,
+        Arc, Mutex,
     },
+    time::{Duration, Instant},
 };
 
 use dbus::{
     arg::{RefArg, Variant},
     blocking::SyncConnection,
-    Path,
+    channel::Sender,
+    Message, Path,
 };
-use dbus_tree::{DataType, MTSync, ObjectPath, Tree};
-use tokio::sync::{mpsc::UnboundedSender as TokioSender, RwLock};
+use dbus_tree::{DataType, MTSync, MethodErr, ObjectPath, Tree};
+use tokio::sync::{mpsc::UnboundedSender as TokioSender, MutexGuard, RwLock};
 
-use crate::engine::{Lockable, LockableEngine, StratisUuid};
+use devicemapper::Bytes;
+
+use crate::{
+    dbus_api::consts,
+    engine::{
+        operation_timeout, AlertMetric, Engine, ExclusiveGuard, FilesystemUuid, Lockable,
+        LockableEngine, MetadataWriteFailurePolicy, Name, PoolOperation, PoolUuid, StratisUuid,
+    },
+    stratis::EventFeed,
+};
 
 /// Type for lockable D-Bus tree object.
 pub type LockableTree = Lockable<Arc<RwLock<Tree<MTSync<TData>, TData>>>>;
@@ -54,6 +65,12 @@ pub enum DbusAction {
     Remove(Path<'static>, InterfacesRemoved),
     FsNameChange(Path<'static>, String),
     PoolNameChange(Path<'static>, String),
+    PoolOperationChange(PoolUuid, Option<PoolOperation>),
+    PoolMetadataWriteFailure(PoolUuid, MetadataWriteFailurePolicy, bool, String),
+    PoolAlert(PoolUuid, AlertMetric, u64, u64),
+    PoolMetadataUndersized(PoolUuid, Bytes, Bytes),
+    PoolSnapshotPruned(PoolUuid, FilesystemUuid, Name),
+    FsCowAlert(FilesystemUuid, Bytes, Bytes),
 }
 
 /// Context for an object path.
@@ -71,12 +88,40 @@ impl OPContext {
     }
 }
 
+/// A D-Bus method call that is being handled, or is waiting to acquire the
+/// engine lock.
+#[derive(Debug)]
+struct ActiveOperation {
+    method: String,
+    target: Path<'static>,
+    started: Instant,
+    // The amount of work done so far and the total amount of work, as last
+    // reported in a progress signal for the target
+    progress: Option<(u64, u64)>,
+}
+
+/// A D-Bus method call recorded by DbusContext::start_operation. Dropping
+/// the guard records that the call is complete.
+pub struct OperationGuard<'a> {
+    context: &'a DbusContext,
+    id: u64,
+}
+
+impl<'a> Drop for OperationGuard<'a> {
+    fn drop(&mut self) {
+        self.context.finish_operation(self.id);
+    }
+}
+
 #[derive(Clone)]
 pub struct DbusContext {
     next_index: Arc<AtomicU64>,
     pub(super) engine: LockableEngine,
     pub(super) sender: TokioSender<DbusAction>,
     connection: Arc<SyncConnection>,
+    next_operation_id: Arc<AtomicU64>,
+    active_operations: Arc<Mutex<HashMap<u64, ActiveOperation>>>,
+    pub(super) event_feed: EventFeed,
 }
 
 impl Debug for DbusContext {
@@ -85,6 +130,8 @@ impl Debug for DbusContext {
             .field("next_index", &self.next_index)
             .field("engine", &type_name::<LockableEngine>())
             .field("sender", &self.sender)
+            .field("active_operations", &self.active_operations)
+            .field("event_feed", &self.event_feed)
             .finish()
     }
 }
@@ -94,12 +141,16 @@ impl DbusContext {
         engine: LockableEngine,
         sender: TokioSender<DbusAction>,
         connection: Arc<SyncConnection>,
+        event_feed: EventFeed,
     ) -> DbusContext {
         DbusContext {
             engine,
             next_index: Arc::new(AtomicU64::new(0)),
             sender,
             connection,
+            next_operation_id: Arc::new(AtomicU64::new(0)),
+            active_operations: Arc::new(Mutex::new(HashMap::new())),
+            event_feed,
         }
     }
 
@@ -111,6 +162,101 @@ impl DbusContext {
         self.next_index.fetch_add(1, Ordering::SeqCst)
     }
 
+    /// Lock the engine on behalf of a D-Bus method call. If the engine is
+    /// busy with another operation for longer than the operation timeout,
+    /// give up and return an error, which is sent to the client in place
+    /// of the reply.
+    pub fn lock_engine(&self) -> Result<ExclusiveGuard<MutexGuard<'_, dyn Engine>>, MethodErr> {
+        self.engine
+            .blocking_lock_timeout(operation_timeout())
+            .ok_or_else(|| {
+                MethodErr::from((
+                    consts::TIMEOUT_ERROR,
+                    format!(
+                        "stratisd did not finish its current operation within {} seconds",
+                        operation_timeout().map_or(0, |t| t.as_secs())
+                    ),
+                ))
+            })
+    }
+
+    /// Record that the D-Bus method call in msg is waiting for the engine
+    /// lock or is being handled. The call is recorded until the returned
+    /// guard is dropped, even if handling the call panics.
+    pub fn start_operation(&self, msg: &Message) -> OperationGuard<'_> {
+        let id = self.next_operation_id.fetch_add(1, Ordering::SeqCst);
+        let operation = ActiveOperation {
+            method: format!(
+                "{}.{}",
+                msg.interface().as_deref().unwrap_or(""),
+                msg.member().as_deref().unwrap_or("")
+            ),
+            target: msg
+                .path()
+                .map(|p| p.into_static())
+                .unwrap_or_else(|| Path::from("/")),
+            started: Instant::now(),
+            progress: None,
+        };
+        self.active_operations
+            .lock()
+            .expect("no panics while the lock is held")
+            .insert(id, operation);
+        OperationGuard { context: self, id }
+    }
+
+    /// Record that handling of the D-Bus method call with the given
+    /// identifier is complete.
+    fn finish_operation(&self, id: u64) {
+        self.active_operations
+            .lock()
+            .expect("no panics while the lock is held")
+            .remove(&id);
+    }
+
+    /// Get the method name, target object path, elapsed time, and last
+    /// reported progress of every D-Bus method call currently being
+    /// handled, oldest first.
+    #[allow(clippy::type_complexity)]
+    pub fn active_operations(&self) -> Vec<(String, Path<'static>, Duration, Option<(u64, u64)>)> {
+        let mut operations = self
+            .active_operations
+            .lock()
+            .expect("no panics while the lock is held")
+            .values()
+            .map(|op| {
+                (
+                    op.started,
+                    op.method.clone(),
+                    op.target.clone(),
+                    op.progress,
+                )
+            })
+            .collect::<Vec<_>>();
+        operations.sort_by_key(|(started, _, _, _)| *started);
+        operations
+            .into_iter()
+            .map(|(started, method, target, progress)| {
+                (method, target, started.elapsed(), progress)
+            })
+            .collect()
+    }
+
+    /// Record the progress of the D-Bus method calls on the object with the
+    /// given path which report their progress in signals.
+    fn record_progress(&self, target: &Path<'static>, interface: &str, done: u64, total: u64) {
+        let method_prefix = format!("{}.", interface);
+        for op in self
+            .active_operations
+            .lock()
+            .expect("no panics while the lock is held")
+            .values_mut()
+            .filter(|op| &op.target == target && op.method.starts_with(&method_prefix))
+        {
+            op.progress = Some((done, total));
+        }
+    }
+
     pub fn push_add(
         &self,
         object_path: ObjectPath<MTSync<TData>, TData>,
@@ -169,6 +315,142 @@ impl DbusContext {
             )
         }
     }
+
+    /// Send a WarmCacheProgress signal for the filesystem with the given
+    /// object path. The signal is sent directly rather than through the
+    /// tree processing thread, since that thread can not run until the
+    /// WarmCache method call that emits the signal is complete.
+    pub fn push_warm_cache_progress(&self, item: &Path<'static>, read: u64, total: u64) {
+        if let Err(e) = self.send_progress_signal(
+            item,
+            consts::FILESYSTEM_INTERFACE_NAME_3_0,
+            consts::FILESYSTEM_WARM_CACHE_PROGRESS_SIGNAL,
+            read,
+            total,
+        ) {
+            warn!(
+                "Cache warming progress signal for filesystem with path {} could not be sent: {}",
+                item, e,
+            )
+        }
+    }
+
+    /// Send a ScrubProgress signal for the filesystem with the given object
+    /// path. Like WarmCacheProgress, the signal is sent directly.
+    pub fn push_scrub_progress(&self, item: &Path<'static>, read: u64, total: u64) {
+        if let Err(e) = self.send_progress_signal(
+            item,
+            consts::FILESYSTEM_INTERFACE_NAME_3_0,
+            consts::FILESYSTEM_SCRUB_PROGRESS_SIGNAL,
+            read,
+            total,
+        ) {
+            warn!(
+                "Scrub progress signal for filesystem with path {} could not be sent: {}",
+                item, e,
+            )
+        }
+    }
+
+    /// Send a FlushCacheProgress signal for the pool with the given object
+    /// path. Like WarmCacheProgress, the signal is sent directly.
+    pub fn push_flush_cache_progress(&self, item: &Path<'static>, flushed: u64, total: u64) {
+        if let Err(e) = self.send_progress_signal(
+            item,
+            consts::POOL_INTERFACE_NAME_3_0,
+            consts::POOL_FLUSH_CACHE_PROGRESS_SIGNAL,
+            flushed,
+            total,
+        ) {
+            warn!(
+                "Cache flush progress signal for pool with path {} could not be sent: {}",
+                item, e,
+            )
+        }
+    }
+
+    /// Send a RelocateMetadataProgress signal for the pool with the given
+    /// object path. Like WarmCacheProgress, the signal is sent directly.
+    pub fn push_relocate_metadata_progress(&self, item: &Path<'static>, copied: u64, total: u64) {
+        if let Err(e) = self.send_progress_signal(
+            item,
+            consts::POOL_INTERFACE_NAME_3_0,
+            consts::POOL_RELOCATE_METADATA_PROGRESS_SIGNAL,
+            copied,
+            total,
+        ) {
+            warn!(
+                "Metadata relocation progress signal for pool with path {} could not be sent: {}",
+                item, e,
+            )
+        }
+    }
+
+    /// Send a FilesystemMoveProgress signal on the manager object for the
+    /// filesystem with the given object path. Like WarmCacheProgress, the
+    /// signal is sent directly.
+    pub fn push_move_filesystem_progress(&self, item: &Path<'static>, copied: u64, total: u64) {
+        if let Err(e) = self.send_progress_signal(
+            &Path::from(consts::STRATIS_BASE_PATH),
+            consts::MANAGER_INTERFACE_NAME_3_0,
+            consts::MANAGER_MOVE_FILESYSTEM_PROGRESS_SIGNAL,
+            copied,
+            total,
+        ) {
+            warn!(
+                "Move progress signal for filesystem with path {} could not be sent: {}",
+                item, e,
+            )
+        }
+    }
+
+    /// Send a signal with the amount of work done so far and the total
+    /// amount of work directly on the connection, and record the progress
+    /// for the method calls on the object.
+    fn send_progress_signal(
+        &self,
+        path: &Path<'static>,
+        interface: &str,
+        signal: &str,
+        done: u64,
+        total: u64,
+    ) -> Result<(), String> {
+        self.record_progress(path, interface, done, total);
+        Message::new_signal(path.clone(), interface, signal)
+            .map(|msg| msg.append2(done, total))
+            .and_then(|msg| {
+                self.connection
+                    .send(msg)
+                    .map(|_| ())
+                    .map_err(|_| "message could not be sent".to_string())
+            })
+    }
+}
+
+/// How a D-Bus method affects the engine, marked where the method is
+/// defined. Only methods that do not modify the engine may be called in
+/// read-only mode.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MethodAccess {
+    /// The method may modify the engine or the devices that it manages.
+    ReadWrite,
+    /// The method only reads and reports state.
+    ReadOnly,
+    /// The method only controls the daemon itself, e.g. read-only mode or
+    /// monitoring, and so must remain available in read-only mode.
+    Control,
+}
+
+impl MethodAccess {
+    pub fn permitted_in_read_only_mode(self) -> bool {
+        self != MethodAccess::ReadWrite
+    }
+}
+
+impl Default for MethodAccess {
+    fn default() -> Self {
+        MethodAccess::ReadWrite
+    }
 }
 
 #[derive(Default, Debug)]
@@ -178,6 +460,6 @@ impl DataType for TData {
     type ObjectPath = Option<OPContext>;
     type Property = ();
     type Interface = ();
-    type Method = ();
+    type Method = MethodAccess;
     type Signal = ();
 }
@@ -5,10 +5,7 @@
 use tokio::sync::mpsc::UnboundedReceiver;
 
 use crate::{
-    dbus_api::{
-        blockdev::create_dbus_blockdev, filesystem::create_dbus_filesystem, pool::create_dbus_pool,
-        types::DbusContext,
-    },
+    dbus_api::{pool::register_pool, types::DbusContext},
     engine::{Name, Pool, PoolUuid, UdevEngineEvent},
     stratis::{StratisError, StratisResult},
 };
@@ -49,25 +46,12 @@ impl DbusUdevHandler {
 
     /// Register a pool in the engine with D-Bus.
     pub fn register_pool(&self, pool_name: &Name, pool_uuid: PoolUuid, pool: &dyn Pool) {
-        let pool_path = create_dbus_pool(
+        register_pool(
             &self.dbus_context,
             self.path.clone(),
             pool_name,
             pool_uuid,
             pool,
         );
-        for (fs_name, fs_uuid, fs) in pool.filesystems() {
-            create_dbus_filesystem(
-                &self.dbus_context,
-                pool_path.clone(),
-                pool_name,
-                &fs_name,
-                fs_uuid,
-                fs,
-            );
-        }
-        for (uuid, tier, bd) in pool.blockdevs() {
-            create_dbus_blockdev(&self.dbus_context, pool_path.clone(), uuid, tier, bd);
-        }
     }
 }
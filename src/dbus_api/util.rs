@@ -21,11 +21,12 @@ use crate::{
         api::get_base_tree,
         connection::{DbusConnectionHandler, DbusTreeHandler},
         consts,
+        event::DbusEngineObserver,
         types::{DbusContext, DbusErrorEnum, InterfacesAdded, InterfacesAddedThreadSafe, TData},
         udev::DbusUdevHandler,
     },
     engine::{Lockable, LockableEngine, UdevEngineEvent},
-    stratis::StratisError,
+    stratis::{EventFeed, StratisError},
 };
 
 /// Convert a tuple as option to an Option type
@@ -128,7 +129,9 @@ pub fn get_parent(i: &mut IterAppend, p: &PropInfo<MTSync<TData>, TData>) -> Res
     Ok(())
 }
 
-/// Create both ends of the D-Bus processing handlers.
+/// Create both ends of the D-Bus processing handlers, and subscribe an
+/// observer to the engine which handles the pool events that it detects,
+/// and the feed of events that clients can read with Manager.EventsSince.
 /// Returns a triple:
 /// 1. A DbusConnectionHandler which may be used to process D-Bus methods calls
 /// 2. A DbusUdevHandler which may be used to handle detected udev events
@@ -136,7 +139,11 @@ pub fn get_parent(i: &mut IterAppend, p: &PropInfo<MTSync<TData>, TData>) -> Res
 ///
 /// Messages may be:
 /// * received by the DbusUdevHandler from the udev thread,
-/// * sent by the DbusContext to the DbusTreeHandler
+/// * sent by the DbusContext to the DbusTreeHandler,
+/// * sent by the engine observer to the DbusTreeHandler
+///
+/// Must not be called from an async context, as the engine is locked in
+/// order to subscribe the observer.
 pub fn create_dbus_handlers(
     engine: LockableEngine,
     udev_receiver: UnboundedReceiver<UdevEngineEvent>,
@@ -144,11 +151,22 @@ pub fn create_dbus_handlers(
 ) -> Result<(DbusConnectionHandler, DbusUdevHandler, DbusTreeHandler), dbus::Error> {
     let conn = Arc::new(SyncConnection::new_system()?);
     let (sender, receiver) = unbounded_channel();
-    let (tree, object_path) = get_base_tree(DbusContext::new(engine, sender, Arc::clone(&conn)));
+    let event_feed = EventFeed::default();
+    {
+        let mut lock = engine.blocking_lock();
+        lock.subscribe(Box::new(DbusEngineObserver::new(sender.clone())));
+        lock.subscribe(Box::new(event_feed.clone()));
+    }
+    let (tree, object_path) = get_base_tree(DbusContext::new(
+        engine,
+        sender,
+        Arc::clone(&conn),
+        event_feed,
+    ));
     let dbus_context = tree.get_data().clone();
     conn.request_name(consts::STRATIS_BASE_SERVICE, false, true, true)?;
 
-    let tree = Lockable::new_shared(tree);
+    let tree = Lockable::new_shared("dbus_tree", tree);
     let connection =
         DbusConnectionHandler::new(Arc::clone(&conn), tree.clone(), trigger.subscribe());
     let udev = DbusUdevHandler::new(udev_receiver, object_path, dbus_context);
@@ -8,6 +8,7 @@ use std::{
     fmt::Debug,
     os::unix::io::RawFd,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use chrono::{DateTime, Utc};
@@ -16,13 +17,30 @@ use serde_json::Value;
 use devicemapper::{Bytes, Sectors};
 
 use crate::{
-    engine::types::{
-        BlockDevTier, Clevis, CreateAction, DeleteAction, DevUuid, EncryptionInfo, FilesystemUuid,
-        Key, KeyDescription, LockedPoolInfo, MappingCreateAction, MappingDeleteAction, Name,
-        PoolUuid, RegenAction, RenameAction, ReportType, SetCreateAction, SetDeleteAction,
-        SetUnlockAction, UdevEngineEvent, UnlockMethod,
+    engine::{
+        shared::{
+            apply_pool_template, apply_transaction_operation, integrity_overhead,
+            name_from_template, recommended_overprovision_limit, undo_transaction_effect,
+            STRAT_METADATA_VERSION,
+        },
+        types::{
+            ActiveAlert, AlertMetric, AlertThresholds, AllocationPreview, AllocationStrategy,
+            BlockDevTier, CapacityEstimate, CapacitySample, Clevis, CreateAction, DeleteAction,
+            DeletedFilesystem, DevUuid, DeviceBenchmark, DeviceVerdict, EncryptionAudit,
+            EncryptionInfo, EngineEvent, FilesystemCheck, FilesystemIoStats, FilesystemTemplate,
+            FilesystemUuid, HealthCheck, HealthReport, IntegrityAlgorithm, IntegrityOverhead,
+            KernelFeature, KernelFeatureSupport, Key, KeyDescription, KeyslotUsage, LatencyStats,
+            LockedPoolInfo, MappingCreateAction, MappingDeleteAction, MetadataCheck,
+            MetadataWriteFailurePolicy, Name, OperationLogEntry, PlannedAction, PoolCapability,
+            PoolEvent, PoolOperation, PoolState, PoolSummary, PoolTemplate, PoolUuid, ReadWork,
+            Reconciliation, ReconfigurationChanges, RedundancyWarning, RegenAction, RenameAction,
+            ReportType, ResourceUsage, SetCreateAction, SetDeleteAction, SetUnlockAction,
+            SmallDevicePolicy, StartupProgress, StripeConfig, SurfaceTestResult, ThinPoolLimits,
+            TierStructure, TransactionEffect, TransactionOperation, UdevEngineEvent, UnlockMethod,
+            XfsParams,
+        },
     },
-    stratis::StratisResult,
+    stratis::{StratisError, StratisResult},
 };
 
 pub const DEV_PATH: &str = "/dev/stratis";
@@ -74,11 +92,46 @@ pub trait Filesystem: Debug {
     /// When the filesystem was created.
     fn created(&self) -> DateTime<Utc>;
 
+    /// The size of the device on which the filesystem resides.
+    fn size(&self) -> Sectors;
+
+    /// Return true if the filesystem is mounted anywhere.
+    fn is_mounted(&self) -> StratisResult<bool>;
+
     /// path to mount the filesystem by
     fn path_to_mount_filesystem(&self, pool_name: &str, fs_name: &str) -> PathBuf;
 
     /// The amount of data stored on the filesystem, including overhead.
     fn used(&self) -> StratisResult<Bytes>;
+
+    /// The amount of data read and written and the number of read and write
+    /// operations done on the filesystem since its device was activated,
+    /// which happens when the pool is started.
+    fn io_stats(&self) -> StratisResult<FilesystemIoStats>;
+
+    /// Prepare checking the consistency of the filesystem without modifying
+    /// it. The work, which does not report progress, runs the check.
+    /// Returns an error if the filesystem is mounted.
+    fn check_consistency(&self) -> StratisResult<ReadWork<FilesystemCheck>>;
+
+    /// Prepare repairing the filesystem if a consistency check finds it
+    /// inconsistent. The work, which does not report progress, returns
+    /// true if the filesystem was repaired.
+    /// Returns an error if the filesystem is mounted.
+    fn repair(&self) -> StratisResult<ReadWork<bool>>;
+
+    /// The amount of data that the filesystem may hold apart from its
+    /// origin and its snapshots before an alert is raised, if any.
+    fn cow_alert_threshold(&self) -> Option<Bytes>;
+
+    /// The amount of free space that the pool keeps available for the
+    /// filesystem to write to, if any.
+    fn min_free_guarantee(&self) -> Option<Bytes>;
+
+    /// The position of the filesystem in the order in which the filesystems
+    /// of its pool were created, counting from 1. It is 0 for a filesystem
+    /// created before sequence numbers were assigned.
+    fn sequence_number(&self) -> u64;
 }
 
 pub trait BlockDev: Debug {
@@ -120,13 +173,44 @@ pub trait Pool: Debug {
     /// can only be initialized once and if an attempt is made to initialize it
     /// twice with different sets of block devices, the user should be notified
     /// of their error.
+    ///
+    /// If cache_block_size is not specified, a default block size is used.
     fn init_cache(
         &mut self,
         pool_uuid: PoolUuid,
         pool_name: &str,
         blockdevs: &[&Path],
+        cache_block_size: Option<Sectors>,
+    ) -> StratisResult<SetCreateAction<DevUuid>>;
+
+    /// Remove the cache from the pool, so that the pool uses its data tier
+    /// directly. Dirty blocks are written back to the data tier first, and
+    /// the Stratis metadata on the cache devices is wiped.
+    /// Returns the UUIDs of the removed cache devices, or an empty action if
+    /// the pool has no cache.
+    fn remove_cache(
+        &mut self,
+        pool_uuid: PoolUuid,
+        pool_name: &str,
+    ) -> StratisResult<SetDeleteAction<DevUuid>>;
+
+    /// Reserve the given block devices for a cache that has not yet been
+    /// initialized. The devices are claimed by the pool, but are not used
+    /// until the cache is initialized with init_cache, which then uses all
+    /// reserved devices in addition to any that it is given.
+    /// Returns an error if the cache has already been initialized.
+    /// Devices that are already reserved are not included in the result.
+    fn reserve_cache_devices(
+        &mut self,
+        pool_uuid: PoolUuid,
+        pool_name: &str,
+        blockdevs: &[&Path],
     ) -> StratisResult<SetCreateAction<DevUuid>>;
 
+    /// The UUIDs and paths of the block devices that are reserved for a
+    /// cache that has not yet been initialized.
+    fn reserved_cache_devices(&self) -> Vec<(DevUuid, PathBuf)>;
+
     /// Creates the filesystems specified by specs.
     /// Returns a list of the names of filesystems actually created.
     /// Returns an error if any of the specified names are already in use
@@ -139,6 +223,137 @@ pub trait Pool: Debug {
         specs: &[(&'b str, Option<Bytes>)],
     ) -> StratisResult<SetCreateAction<(&'b str, FilesystemUuid, Sectors)>>;
 
+    /// Creates a single filesystem with a name made from template, which
+    /// contains a run of '#' characters that is replaced with the number
+    /// following the highest number among the names of the filesystems in
+    /// the pool that match the template. Names of deleted filesystems that
+    /// are still kept are taken into account, so that they can be undeleted.
+    /// Returns the name and UUID of the new filesystem.
+    fn create_filesystem_from_template(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        template: &str,
+        size: Option<Bytes>,
+    ) -> StratisResult<(Name, FilesystemUuid)> {
+        let name = {
+            let filesystems = self.filesystems();
+            let deleted = self.deleted_filesystems();
+            name_from_template(
+                template,
+                filesystems
+                    .iter()
+                    .map(|(name, _, _)| &**name)
+                    .chain(deleted.iter().map(|fs| fs.name.as_str())),
+            )?
+        };
+        let uuid = self
+            .create_filesystems(pool_name, pool_uuid, &[(name.as_str(), size)])?
+            .changed()
+            .and_then(|created| created.first().map(|&(_, uuid, _)| uuid))
+            .ok_or_else(|| StratisError::Msg(format!("Filesystem {} was not created", name)))?;
+        Ok((Name::new(name), uuid))
+    }
+
+    /// Creates a single filesystem, preferring the given data devices for
+    /// the space that the pool allocates to back it. If the preferred
+    /// devices do not have enough space, the rest is allocated from the
+    /// other data devices and a warning is logged. If a filesystem with the
+    /// given name and size already exists, no action is taken.
+    // FIXME: The blocks of a filesystem are placed in the thin pool's data
+    // device by the kernel, which does not know about blockdevs. The
+    // preference can only govern which blockdevs the data device is
+    // extended onto, and it stays in effect for all later extensions until
+    // another filesystem is created with a preference. It is not saved in
+    // the pool-level metadata and so is lost when the pool is stopped.
+    fn create_filesystem_on_devices<'a>(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        name: &'a str,
+        size: Option<Bytes>,
+        devices: &[DevUuid],
+    ) -> StratisResult<SetCreateAction<(&'a str, FilesystemUuid, Sectors)>>;
+
+    /// Creates a single filesystem, passing the given parameters to
+    /// mkfs.xfs. The parameters are validated before any filesystem is
+    /// created. If a filesystem with the given name and size already
+    /// exists, no action is taken.
+    fn create_filesystem_with_params<'a>(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        name: &'a str,
+        size: Option<Bytes>,
+        mkfs_params: &XfsParams,
+    ) -> StratisResult<SetCreateAction<(&'a str, FilesystemUuid, Sectors)>>;
+
+    /// The actions that create_filesystem_with_params() would perform with
+    /// the same arguments, in order, without performing them. The
+    /// device-mapper operations are expressed as the equivalent dmsetup
+    /// commands. If a filesystem with the given name and size already
+    /// exists, no actions are returned.
+    // FIXME: Only the creation of a filesystem can be explained so far.
+    fn explain_create_filesystem(
+        &self,
+        pool_uuid: PoolUuid,
+        name: &str,
+        size: Option<Bytes>,
+        mkfs_params: &XfsParams,
+    ) -> StratisResult<Vec<PlannedAction>>;
+
+    /// The minimum and maximum sizes of a filesystem that may be created in
+    /// this pool, or to which a filesystem in this pool may be extended.
+    fn filesystem_size_limits(&self) -> (Sectors, Sectors);
+
+    /// The current and the maximum sizes of the data and metadata devices
+    /// of the pool's thin pool.
+    fn thin_pool_limits(&self) -> ThinPoolLimits;
+
+    /// The stripe configuration of the pool's data tier, if it is striped.
+    fn stripe_config(&self) -> Option<StripeConfig>;
+
+    /// The ranges of the filesystem with the given UUID that are mapped to
+    /// data in the thin pool, as (start, length) pairs in ascending order.
+    /// The rest of the filesystem reads as zeroes.
+    fn filesystem_mapped_ranges(
+        &self,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<Vec<(Sectors, Sectors)>>;
+
+    /// Prepare creating a filesystem with the given name which is a
+    /// block-level copy of the source filesystem, which must not be mounted
+    /// and must belong to a different pool. Only the given ranges of the
+    /// source, which are those mapped to data in its pool, are copied. The
+    /// copy is in progress on this pool until finish_copy_filesystem_from()
+    /// is called with the result of the returned work.
+    /// Returns an error if the name is already in use for a filesystem in
+    /// this pool or if this pool does not have room for the data stored on
+    /// the source filesystem.
+    fn start_copy_filesystem_from(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        name: &str,
+        source: &dyn Filesystem,
+        source_ranges: Vec<(Sectors, Sectors)>,
+    ) -> StratisResult<ReadWork<()>>;
+
+    /// Finish the copy started by start_copy_filesystem_from(), given the
+    /// result of its work. Returns the UUID of the new filesystem, or an
+    /// error if the copy failed, in which case the new filesystem is
+    /// removed.
+    fn finish_copy_filesystem_from(
+        &mut self,
+        pool_name: &str,
+        copied: StratisResult<()>,
+    ) -> StratisResult<FilesystemUuid>;
+
+    /// Mark the filesystem with the given UUID as being moved to another
+    /// pool, or clear the mark. While it is marked, the filesystem can not
+    /// be destroyed, snapshotted or resized.
+    fn set_filesystem_moving(&mut self, uuid: FilesystemUuid, moving: bool);
+
     /// Adds blockdevs specified by paths to pool.
     /// Returns a list of uuids corresponding to devices actually added.
     /// Returns an error if a blockdev can not be added because it is owned
@@ -153,6 +368,48 @@ pub trait Pool: Debug {
         tier: BlockDevTier,
     ) -> StratisResult<SetCreateAction<DevUuid>>;
 
+    /// Adds the data blockdevs specified by paths to the pool, even if some
+    /// of them are smaller than the existing data devices and the pool's
+    /// small device policy is SmallDevicePolicy::RequireOverride.
+    fn add_datadevs_with_override(
+        &mut self,
+        pool_uuid: PoolUuid,
+        pool_name: &str,
+        paths: &[&Path],
+    ) -> StratisResult<SetCreateAction<DevUuid>>;
+
+    /// Adds the data blockdevs specified by paths to the pool one at a time,
+    /// so that devices which can not be added do not prevent the others
+    /// from being added. Returns for each path, in order, the UUID of the
+    /// blockdev if it was added, None if it already belonged to the pool,
+    /// or the reason why it could not be added.
+    fn add_datadevs_best_effort(
+        &mut self,
+        pool_uuid: PoolUuid,
+        pool_name: &str,
+        paths: &[&Path],
+    ) -> Vec<(PathBuf, StratisResult<Option<DevUuid>>)> {
+        paths
+            .iter()
+            .map(|path| {
+                let result = self
+                    .add_blockdevs(pool_uuid, pool_name, &[*path], BlockDevTier::Data)
+                    .map(|action| action.changed().and_then(|uuids| uuids.first().cloned()));
+                (path.to_path_buf(), result)
+            })
+            .collect()
+    }
+
+    /// Remove the data blockdev with the given UUID from the pool.
+    /// Returns an error if any space on the blockdev is allocated, if it is
+    /// a cache blockdev, or if it is the last data blockdev in the pool.
+    /// Returns Identity if the pool does not contain the blockdev.
+    fn shrink_by_device(
+        &mut self,
+        pool_name: &str,
+        dev_uuid: DevUuid,
+    ) -> StratisResult<DeleteAction<DevUuid>>;
+
     /// Bind all devices in the given pool for automated unlocking
     /// using clevis.
     fn bind_clevis(
@@ -200,6 +457,21 @@ pub trait Pool: Debug {
         new_name: &str,
     ) -> StratisResult<RenameAction<FilesystemUuid>>;
 
+    /// Grow the filesystem with the given UUID to exactly the target size.
+    /// If grow_fs is true, the XFS filesystem is also grown to fill the new
+    /// size; this requires the filesystem to be mounted.
+    /// Returns true if the size of the filesystem was changed.
+    /// Returns an error if the target is smaller than the current size or
+    /// outside the limits of filesystem_size_limits(), or if the pool has
+    /// no free space left to back the growth.
+    fn set_filesystem_size(
+        &mut self,
+        pool_name: &str,
+        uuid: FilesystemUuid,
+        target_size: Sectors,
+        grow_fs: bool,
+    ) -> StratisResult<bool>;
+
     /// Snapshot filesystem
     /// Create a CoW snapshot of the origin
     fn snapshot_filesystem(
@@ -227,6 +499,154 @@ pub trait Pool: Debug {
     /// Get all the filesystems belonging to this pool.
     fn filesystems(&self) -> Vec<(Name, FilesystemUuid, &dyn Filesystem)>;
 
+    /// Prepare reading all the blocks allocated to the filesystem with the
+    /// given UUID so that any media errors on the underlying devices are
+    /// reported. Only the parts of the filesystem that are mapped to data
+    /// in the thin pool are read. The work returns the ranges of the
+    /// filesystem that could not be read as a list of (start, length) pairs.
+    fn scrub_filesystem(
+        &self,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<ReadWork<Vec<(Sectors, Sectors)>>>;
+
+    /// Prepare reading the filesystem with the given UUID from the start, so
+    /// that the data read may be promoted to the pool's cache. Only the
+    /// parts of the filesystem that are mapped to data in the thin pool are
+    /// read, and at most budget sectors of them. If budget is not specified,
+    /// or exceeds the size of the cache, the size of the cache is used, so
+    /// that the data read does not evict more than the entire contents of
+    /// the cache. The work returns the number of sectors read.
+    /// Returns an error if the pool has no cache.
+    fn warm_cache(
+        &self,
+        uuid: FilesystemUuid,
+        budget: Option<Sectors>,
+    ) -> StratisResult<ReadWork<Sectors>>;
+
+    /// Prepare writing back all dirty blocks in the pool's cache to the
+    /// data tier. The returned work waits until none remain; progress is
+    /// called with the number of sectors written back so far and the number
+    /// of sectors that were dirty when flushing began. The work returns the
+    /// number of sectors written back. The flush is in progress on the pool
+    /// until finish_flush_cache() is called with the result of the work.
+    /// Returns an error if the pool has no cache.
+    fn start_flush_cache(&mut self) -> StratisResult<ReadWork<Sectors>>;
+
+    /// Finish the flush started by start_flush_cache(), given the result of
+    /// its work. Returns the number of sectors written back.
+    fn finish_flush_cache(&mut self, flushed: StratisResult<Sectors>) -> StratisResult<Sectors>;
+
+    /// Move the thin pool's metadata device to the given data devices,
+    /// which are typically faster than the others. The metadata is copied
+    /// while the pool is suspended; progress is called with the number of
+    /// sectors copied so far and the total number of sectors to copy.
+    /// Returns true if the metadata was moved, false if it was already
+    /// stored entirely on the given devices.
+    /// Returns an error if any of the devices is not a data device of the
+    /// pool or if they do not have enough space available.
+    fn relocate_metadata(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        devices: &[DevUuid],
+        progress: &mut dyn FnMut(Sectors, Sectors),
+    ) -> StratisResult<bool>;
+
+    /// Rewrite the filesystem metadata of the pool in a compact form,
+    /// discarding stale entries. The rewrite is atomic; if it fails, the
+    /// metadata is left as it was.
+    /// Returns the number of stale entries discarded.
+    fn compact_metadata(&mut self) -> StratisResult<u64>;
+
+    /// Read every copy of the pool's metadata, both copies in the BDA of
+    /// each block device holding the latest metadata and the filesystem
+    /// records on the MDV, and compare them with each other and with the
+    /// current configuration of the pool. Nothing is changed.
+    fn verify_metadata_consistency(&self, pool_name: &str) -> StratisResult<MetadataCheck>;
+
+    /// The checks of the state of the pool's devices and of its thin pool,
+    /// as they are made as part of full_health_report().
+    fn device_health_checks(&self) -> Vec<HealthCheck>;
+
+    /// Write a pattern to the space that is reserved on each block device
+    /// of the pool, read it back, and compare it, to find devices that
+    /// accept writes but do not store the data faithfully. No data or
+    /// metadata of the pool is touched.
+    fn surface_test(&self) -> StratisResult<Vec<SurfaceTestResult>>;
+
+    /// The physical disks which back more than one block device of the
+    /// pool, each with the block devices it backs. Devices which share a
+    /// disk, such as two partitions of it or two paths to it, fail
+    /// together, so spreading the pool over them gives no protection
+    /// against the failure of the disk.
+    fn redundancy_warnings(&self) -> Vec<RedundancyWarning>;
+
+    /// Read the most recent metadata of the pool from its devices, the same
+    /// way it is read when the pool is set up, and the records of its
+    /// filesystems, and adopt the settings recorded in them wherever they
+    /// differ from those held in memory. Differences in the layout of the
+    /// pool and in the devices of its filesystems are reported but not
+    /// corrected.
+    fn reconcile(&mut self, pool_name: &str, pool_uuid: PoolUuid) -> StratisResult<Reconciliation>;
+
+    /// Check the health of the pool as a whole: the consistency of the
+    /// copies of its metadata, the state of its devices and its thin pool,
+    /// and the alerts that have not been acknowledged. Nothing is changed.
+    // FIXME: Pools have no integrity or RAID layer yet whose state could be
+    // checked as well.
+    fn full_health_report(&self, pool_name: &str) -> StratisResult<HealthReport> {
+        let mut checks = vec![HealthCheck {
+            name: "metadata".to_string(),
+            problems: self.verify_metadata_consistency(pool_name)?.problems,
+        }];
+        checks.extend(self.device_health_checks());
+        checks.push(HealthCheck {
+            name: "alerts".to_string(),
+            problems: self
+                .active_alerts()
+                .into_iter()
+                .filter(|alert| !alert.acknowledged)
+                .map(|alert| {
+                    format!(
+                        "{} is {}, at or above the alert threshold {}",
+                        alert.metric, alert.value, alert.threshold
+                    )
+                })
+                .collect(),
+        });
+        Ok(HealthReport { checks })
+    }
+
+    /// Compute the capacity that the pool would have if the given changes
+    /// were made to it, without making them.
+    /// Returns an error if any of the changes could not be made.
+    fn simulate_reconfiguration(
+        &self,
+        changes: &ReconfigurationChanges,
+    ) -> StratisResult<CapacityEstimate>;
+
+    /// The configuration of the pool as a template, from which a pool with
+    /// the same configuration can be created on other devices.
+    fn export_config(&self) -> PoolTemplate {
+        PoolTemplate {
+            encryption_info: self.encryption_info().into_owned(),
+            stripe: self.stripe_config(),
+            metadata_write_failure_policy: self.metadata_write_failure_policy(),
+            auto_start: self.auto_start(),
+            start_priority: self.start_priority(),
+            detailed_stats: self.detailed_stats(),
+            alert_thresholds: self.alert_thresholds(),
+            filesystems: self
+                .filesystems()
+                .into_iter()
+                .map(|(name, _, fs)| FilesystemTemplate {
+                    name: name.to_string(),
+                    size: fs.size(),
+                })
+                .collect(),
+        }
+    }
+
     /// Get the filesystem in this pool with this UUID.
     fn get_filesystem(&self, uuid: FilesystemUuid) -> Option<(Name, &dyn Filesystem)>;
 
@@ -237,6 +657,68 @@ pub trait Pool: Debug {
     /// All really means all. For example, it does not exclude cache blockdevs.
     fn blockdevs(&self) -> Vec<(DevUuid, BlockDevTier, &dyn BlockDev)>;
 
+    /// The shape of each tier of the pool: the number and size of its
+    /// block devices, its redundancy, and whether it is degraded. The data
+    /// tier comes first; the cache tier is included only if the pool has a
+    /// cache.
+    fn structure(&self) -> Vec<TierStructure>;
+
+    /// The filesystems that have data on the data device with the given
+    /// UUID, each with the amount of its data stored there. Data that a
+    /// filesystem shares with its snapshots is counted for each of them.
+    fn filesystems_on_device(
+        &self,
+        dev_uuid: DevUuid,
+    ) -> StratisResult<Vec<(FilesystemUuid, Bytes)>>;
+
+    /// The filesystems whose XFS filesystem has not been grown to fill their
+    /// thin device, each with the amount of the thin device left unused.
+    /// Such a filesystem can be grown with xfs_growfs.
+    fn filesystems_pending_grow(&self) -> StratisResult<Vec<(FilesystemUuid, Bytes)>>;
+
+    /// The amount of the data of the filesystem with the given UUID that it
+    /// shares with its origin and its snapshots, and the amount that only it
+    /// holds, as a pair (shared, unique). Destroying the filesystem frees
+    /// only the unique data.
+    fn filesystem_shared_size(&self, uuid: FilesystemUuid) -> StratisResult<(Bytes, Bytes)>;
+
+    /// Set the copy-on-write alert threshold of the filesystem with the
+    /// given UUID. Once the amount of data that only the filesystem holds
+    /// exceeds the threshold, an alert is raised when the pool is next
+    /// monitored. None disables the alert.
+    /// Returns true if the threshold was changed.
+    fn set_fs_cow_alert_threshold(
+        &mut self,
+        pool_name: &str,
+        uuid: FilesystemUuid,
+        threshold: Option<Bytes>,
+    ) -> StratisResult<bool>;
+
+    /// Set the minimum free space guarantee of the filesystem with the given
+    /// UUID. The pool then keeps enough free space available for the
+    /// filesystem to write the guaranteed amount, or to fill up if it has
+    /// less free space than that, and snapshots with a guarantee are not
+    /// pruned automatically. None removes the guarantee.
+    /// Returns true if the guarantee was changed.
+    /// Returns an error if the guarantee exceeds the size of the filesystem
+    /// or if the pool does not have enough free space to honor it along
+    /// with the guarantees of the other filesystems. While the pool has no
+    /// free space beyond what the guarantees may claim, creating
+    /// filesystems or snapshots, or growing a filesystem with a guarantee,
+    /// is refused.
+    // FIXME: The blocks of the thin pool are allocated by the kernel as the
+    // filesystems are written, so the other filesystems can not be kept from
+    // using the guaranteed space. The guarantee is upheld by pruning
+    // snapshots once the free space falls short of it, but only if the
+    // pool's automatic snapshot pruning policy is enabled; otherwise writes
+    // to the other filesystems may still consume the guaranteed space.
+    fn set_fs_min_free_guarantee(
+        &mut self,
+        pool_name: &str,
+        uuid: FilesystemUuid,
+        guarantee: Option<Bytes>,
+    ) -> StratisResult<bool>;
+
     /// Get the blockdev in this pool with this UUID.
     fn get_blockdev(&self, uuid: DevUuid) -> Option<(BlockDevTier, &dyn BlockDev)>;
 
@@ -249,6 +731,23 @@ pub trait Pool: Debug {
         user_info: Option<&str>,
     ) -> StratisResult<RenameAction<DevUuid>>;
 
+    /// Returns true if the blockdev specified by the uuid has a volatile
+    /// write cache enabled.
+    fn device_write_cache_enabled(&self, uuid: DevUuid) -> StratisResult<bool>;
+
+    /// The number of I/O requests to the blockdev specified by the uuid
+    /// which have not completed yet, reads and writes together.
+    fn device_in_flight_io(&self, uuid: DevUuid) -> StratisResult<u64>;
+
+    /// Turn off the volatile write cache of the blockdev specified by the
+    /// uuid on the device itself. Returns true if the write cache was
+    /// previously enabled. Returns an error if the device does not support
+    /// turning off its write cache.
+    fn disable_device_write_cache(&mut self, uuid: DevUuid) -> StratisResult<bool>;
+
+    /// The block size of the pool's cache, if the pool has a cache.
+    fn cache_block_size(&self) -> Option<Sectors>;
+
     /// true if the pool has a cache, otherwise false
     fn has_cache(&self) -> bool;
 
@@ -257,6 +756,272 @@ pub trait Pool: Debug {
 
     /// Get all encryption information for this pool.
     fn encryption_info(&self) -> Cow<EncryptionInfo>;
+
+    /// The keyslot usage of the data device of this pool with the most
+    /// keyslots in use. Returns an error if the pool is not encrypted.
+    fn keyslot_usage(&self) -> StratisResult<KeyslotUsage>;
+
+    /// The long-running operation currently in progress on this pool, if any.
+    fn operation_in_progress(&self) -> Option<PoolOperation>;
+
+    /// The operations that are currently valid on this pool, given its state
+    /// and configuration. An operation that is listed may still fail, for
+    /// example because the devices passed to it can not be used.
+    fn supported_operations(&self) -> Vec<PoolCapability>;
+
+    /// The action taken when writing the pool-level metadata fails.
+    fn metadata_write_failure_policy(&self) -> MetadataWriteFailurePolicy;
+
+    /// Set the action taken when writing the pool-level metadata fails.
+    /// Returns true if the policy was changed, otherwise false.
+    fn set_metadata_write_failure_policy(
+        &mut self,
+        pool_name: &str,
+        policy: MetadataWriteFailurePolicy,
+    ) -> StratisResult<bool>;
+
+    /// The action taken when a data device smaller than the existing data
+    /// devices is added to the pool.
+    fn small_device_policy(&self) -> SmallDevicePolicy;
+
+    /// Set the action taken when a data device smaller than the existing
+    /// data devices is added to the pool.
+    /// Returns true if the policy was changed, otherwise false.
+    fn set_small_device_policy(
+        &mut self,
+        pool_name: &str,
+        policy: SmallDevicePolicy,
+    ) -> StratisResult<bool>;
+
+    /// The strategy by which space is chosen when the pool allocates from
+    /// its data devices.
+    fn allocation_strategy(&self) -> AllocationStrategy;
+
+    /// Set the strategy by which space is chosen when the pool allocates
+    /// from its data devices. Space already allocated is not moved.
+    /// Returns true if the strategy was changed, otherwise false.
+    fn set_allocation_strategy(
+        &mut self,
+        pool_name: &str,
+        strategy: AllocationStrategy,
+    ) -> StratisResult<bool>;
+
+    /// Whether the pool is started automatically when stratisd starts or
+    /// when its devices are discovered.
+    fn auto_start(&self) -> bool;
+
+    /// Set whether the pool is started automatically.
+    /// Returns true if the setting was changed, otherwise false.
+    fn set_auto_start(&mut self, pool_name: &str, auto_start: bool) -> StratisResult<bool>;
+
+    /// The start priority of the pool. Pools with a lower start priority are
+    /// started before pools with a higher one; pools without a start
+    /// priority are started last.
+    fn start_priority(&self) -> Option<u32>;
+
+    /// Set or clear the start priority of the pool.
+    /// Returns true if the setting was changed, otherwise false.
+    fn set_start_priority(
+        &mut self,
+        pool_name: &str,
+        start_priority: Option<u32>,
+    ) -> StratisResult<bool>;
+
+    /// Whether detailed statistics, which include latency histograms, are
+    /// collected for the filesystems of the pool.
+    fn detailed_stats(&self) -> bool;
+
+    /// Enable or disable the collection of detailed statistics for the
+    /// filesystems of the pool. Collection is disabled by default because of
+    /// its overhead.
+    /// Returns true if the setting was changed, otherwise false.
+    fn set_detailed_stats(&mut self, pool_name: &str, enabled: bool) -> StratisResult<bool>;
+
+    /// The latency histogram of the I/O done on the filesystem with the
+    /// given UUID since detailed statistics were enabled, as a list of pairs
+    /// of the lower bound of each bucket in nanoseconds and the number of
+    /// I/Os in it. Returns an error if detailed statistics are not enabled.
+    fn filesystem_latency_histogram(&self, uuid: FilesystemUuid) -> StratisResult<Vec<(u64, u64)>>;
+
+    /// The average latency of the reads and the writes done on the
+    /// filesystems of the pool since detailed statistics were enabled, and
+    /// the 99th percentile of the latency of all of them. Returns an error
+    /// if detailed statistics are not enabled.
+    fn latency_stats(&self) -> StratisResult<LatencyStats>;
+
+    /// Remove and return all events that have occurred on this pool since
+    /// the last time this method was called.
+    fn drain_events(&mut self) -> Vec<PoolEvent>;
+
+    /// Get the most recent entries of the persistent operation log, oldest
+    /// first. If limit is specified, at most limit entries are returned.
+    fn operation_log(&self, limit: Option<usize>) -> StratisResult<Vec<OperationLogEntry>>;
+
+    /// Get the samples of the used and total physical space of the pool
+    /// that were collected while the pool was monitored, oldest first. If
+    /// since is specified, only the samples taken at or after that time are
+    /// returned. Samples are only kept for a limited time.
+    fn capacity_history(&self, since: Option<DateTime<Utc>>) -> Vec<CapacitySample>;
+
+    /// The thresholds at or above which alerts are raised for the metrics
+    /// of the pool.
+    fn alert_thresholds(&self) -> AlertThresholds;
+
+    /// Replace the thresholds at or above which alerts are raised for the
+    /// metrics of the pool. An alert is raised as a PoolEvent when the pool
+    /// is monitored and the value of a metric has reached its threshold.
+    /// Returns true if the thresholds were changed, otherwise false.
+    fn set_alert_thresholds(
+        &mut self,
+        pool_name: &str,
+        thresholds: AlertThresholds,
+    ) -> StratisResult<bool>;
+
+    /// The alerts on metrics of the pool that were at or above their
+    /// thresholds when the pool was last monitored.
+    fn active_alerts(&self) -> Vec<ActiveAlert>;
+
+    /// The size of the thin pool metadata device and the recommended size
+    /// for the current size of the data tier, if the metadata device was
+    /// found to be undersized when the pool was last monitored.
+    fn metadata_undersized(&self) -> Option<(Bytes, Bytes)>;
+
+    /// Acknowledge the active alert on the given metric, so that it is no
+    /// longer raised until the metric falls below its threshold and reaches
+    /// it again. Returns an error if there is no active alert on the metric.
+    /// Returns true if the alert was not already acknowledged, otherwise
+    /// false.
+    fn acknowledge_alert(&mut self, metric: AlertMetric) -> StratisResult<bool>;
+
+    /// Return the pool to a clean operational state after a crash: resume
+    /// any of its DM devices that were left suspended, clear the read-only
+    /// mark that a failed metadata write may have set, and refresh the
+    /// status of the thin pool, which determines the operation in progress.
+    /// Returns true if anything was reset, otherwise false.
+    fn reset_transient_state(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+    ) -> StratisResult<bool>;
+
+    /// An advisory upper limit for the overprovision ratio of the pool, in
+    /// percent of its total physical size, which may be used as the
+    /// threshold for the overprovision ratio alert. Beyond it, the
+    /// filesystems are likely to run out of space if they keep filling the
+    /// same share of their size as they do on average now.
+    // FIXME: Pools have no redundancy and no reserved space yet, which
+    // would lower the limit further.
+    fn recommended_overprovision_limit(&self) -> StratisResult<u64> {
+        let (fs_used, fs_size) = self.filesystems().iter().try_fold(
+            (Sectors(0), Sectors(0)),
+            |(used, size), (_, _, fs)| {
+                fs.used()
+                    .map(|fs_used| (used + fs_used.sectors(), size + fs.size()))
+            },
+        )?;
+        Ok(recommended_overprovision_limit(
+            self.total_physical_size(),
+            self.total_physical_used()?,
+            fs_used,
+            fs_size,
+        ))
+    }
+
+    /// An advisory estimate of the space and the additional writes that
+    /// protecting the data tier of the pool with checksums computed with
+    /// algorithm would take.
+    // FIXME: Pools have no integrity layer yet; the estimate only helps to
+    // decide whether to use one once they do.
+    fn estimate_integrity_overhead(&self, algorithm: IntegrityAlgorithm) -> IntegrityOverhead {
+        let data_devs = self
+            .blockdevs()
+            .into_iter()
+            .filter(|(_, tier, _)| *tier == BlockDevTier::Data)
+            .count();
+        integrity_overhead(algorithm, self.total_physical_size(), data_devs as u64)
+    }
+
+    /// The version of the format of the metadata of the pool.
+    // FIXME: The engine supports a single version of the format, and a pool
+    // is only set up if its metadata is of a version that the engine
+    // supports, so every pool that is set up has metadata of that version.
+    fn metadata_version(&self) -> u64 {
+        u64::from(STRAT_METADATA_VERSION)
+    }
+
+    /// The position of the pool in the order in which the pools were
+    /// created, counting from 1. Unlike the name, it never changes. It is
+    /// 0 for a pool created before sequence numbers were assigned.
+    fn sequence_number(&self) -> u64;
+
+    /// An advisory upper limit for the number of filesystems in the pool,
+    /// estimated from the size of the MDV, which holds a record for each
+    /// filesystem, and from the space left in the thin pool metadata, which
+    /// holds the mappings of each filesystem's thin device.
+    fn recommended_max_filesystems(&self) -> StratisResult<u64>;
+
+    /// The physical usage of the pool, in percent of its total physical
+    /// size, at or above which its snapshots are destroyed, oldest first,
+    /// when the pool is monitored. If None, no snapshots are destroyed.
+    fn auto_prune_snapshots_on_pressure(&self) -> Option<u64>;
+
+    /// Set or clear the threshold of the automatic snapshot pruning policy.
+    /// Returns an error if the threshold is not between 1 and 100.
+    /// Returns true if the policy was changed, otherwise false.
+    fn set_auto_prune_snapshots_on_pressure(
+        &mut self,
+        pool_name: &str,
+        threshold: Option<u64>,
+    ) -> StratisResult<bool>;
+
+    /// How long destroyed filesystems are kept so that they can be
+    /// undeleted. If None, filesystems are destroyed right away.
+    fn fs_deletion_grace_period(&self) -> Option<Duration>;
+
+    /// Set how long destroyed filesystems are kept. While a grace period is
+    /// set, destroy_filesystems unmounts and hides filesystems instead of
+    /// destroying them; they are destroyed when the pool is monitored after
+    /// the grace period is over.
+    /// Returns true if the grace period was changed, otherwise false.
+    fn set_fs_deletion_grace_period(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        period: Option<Duration>,
+    ) -> StratisResult<bool>;
+
+    /// The filesystems that have been destroyed but are kept for the
+    /// deletion grace period.
+    fn deleted_filesystems(&self) -> Vec<DeletedFilesystem>;
+
+    /// Destroy a deleted filesystem right away, without waiting for its
+    /// deletion grace period to be over.
+    /// Returns true if the filesystem was destroyed, false if there is no
+    /// such deleted filesystem.
+    fn purge_deleted_filesystem(
+        &mut self,
+        pool_uuid: PoolUuid,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<bool>;
+
+    /// Return a deleted filesystem, which is still kept, to the pool.
+    /// Returns true if the filesystem was undeleted, false if it was not
+    /// deleted, and an error if there is no such filesystem.
+    fn undelete_filesystem(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<bool>;
+}
+
+/// A receiver of the events that an engine reports, so that a front-end
+/// running in the same process as the engine can react to changes in its
+/// state.
+pub trait EngineObserver: Debug + Send + Sync {
+    /// Handle an event. This method is called while the engine is locked, so
+    /// it must not block, and must not try to lock the engine.
+    fn notify(&self, event: &EngineEvent);
 }
 
 pub trait Engine: Debug + Report + Send {
@@ -264,26 +1029,126 @@ pub trait Engine: Debug + Report + Send {
     /// Returns the UUID of the newly created pool.
     /// Returns an error if the redundancy code does not correspond to a
     /// supported redundancy.
+    /// If a stripe configuration is specified, the pool's data tier is
+    /// striped accordingly instead of concatenating the devices.
     fn create_pool(
         &mut self,
         name: &str,
         blockdev_paths: &[&Path],
         redundancy: Option<u16>,
         encryption_info: &EncryptionInfo,
+        stripe: Option<StripeConfig>,
     ) -> StratisResult<CreateAction<PoolUuid>>;
 
+    /// Create a pool on the given devices with the configuration in
+    /// template. The pool and its filesystems get fresh UUIDs. If the
+    /// configuration can not be applied in full, the pool is destroyed
+    /// again. If a pool with the given name already exists on exactly the
+    /// given devices, no action is taken.
+    fn create_pool_from_template(
+        &mut self,
+        name: &str,
+        blockdev_paths: &[&Path],
+        template: &PoolTemplate,
+    ) -> StratisResult<CreateAction<PoolUuid>> {
+        let pool_uuid = match self.create_pool(
+            name,
+            blockdev_paths,
+            None,
+            &template.encryption_info,
+            template.stripe,
+        )? {
+            CreateAction::Created(uuid) => uuid,
+            CreateAction::Identity => return Ok(CreateAction::Identity),
+        };
+
+        let result = {
+            let (pool_name, pool) = self.get_mut_pool(pool_uuid).expect("pool was just created");
+            apply_pool_template(pool, &pool_name, pool_uuid, template)
+        };
+        if let Err(err) = result {
+            if let Some((pool_name, pool)) = self.get_mut_pool(pool_uuid) {
+                let fs_uuids = pool
+                    .filesystems()
+                    .iter()
+                    .map(|(_, uuid, _)| *uuid)
+                    .collect::<Vec<_>>();
+                if let Err(e) = pool.destroy_filesystems(&pool_name, &fs_uuids) {
+                    warn!(
+                        "Failed to destroy the filesystems of pool {} created from a template: {}",
+                        pool_name, e
+                    );
+                }
+            }
+            if let Err(e) = self.destroy_pool(pool_uuid) {
+                warn!(
+                    "Failed to destroy pool {} created from a template: {}",
+                    name, e
+                );
+            }
+            return Err(err);
+        }
+        Ok(CreateAction::Created(pool_uuid))
+    }
+
+    /// Perform the given operations in order as a unit. If any operation
+    /// fails, the changes made by the operations before it are reverted, in
+    /// reverse order, and the error is returned.
+    /// Returns the changes made; operations that required no change, for
+    /// example creating a filesystem that already exists, contribute none.
+    // FIXME: Only the creation of pools and filesystems and the binding of
+    // a pool to a keyring or Clevis can be part of a transaction so far.
+    fn transaction(
+        &mut self,
+        operations: &[TransactionOperation],
+    ) -> StratisResult<Vec<TransactionEffect>> {
+        let mut effects = Vec::new();
+        for operation in operations {
+            match apply_transaction_operation(self, operation) {
+                Ok(effect) => effects.extend(effect),
+                Err(err) => {
+                    for effect in effects.into_iter().rev() {
+                        if let Err(e) = undo_transaction_effect(self, effect) {
+                            warn!(
+                                "Failed to revert {:?} of a failed transaction: {}",
+                                effect, e
+                            );
+                        }
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Ok(effects)
+    }
+
     /// Handle a libudev event.
     /// If the handling action resulted in pool creation, return the pool
     /// and its UUID.
     ///
+    /// While monitoring is paused, the event is only recorded and None is
+    /// returned. In read-only mode, a pool is set up without writing to it.
+    ///
     /// Precondition: the subsystem of the device evented on is "block".
     fn handle_event(&mut self, event: &UdevEngineEvent) -> Option<(Name, PoolUuid, &dyn Pool)>;
 
+    /// Handle the udev events that were recorded while monitoring was
+    /// paused. Returns the name and UUID of every pool that was set up.
+    /// Does nothing while monitoring is paused.
+    fn process_deferred_events(&mut self) -> Vec<(Name, PoolUuid)>;
+
     /// Destroy a pool.
     /// Ensures that the pool of the given UUID is absent on completion.
     /// Returns true if some action was necessary, otherwise false.
     fn destroy_pool(&mut self, uuid: PoolUuid) -> StratisResult<DeleteAction<PoolUuid>>;
 
+    /// Export a pool.
+    /// Stops managing the pool of the given UUID, tearing down its devices
+    /// but leaving all its data and metadata intact. The pool is not set up
+    /// again until it is started with start_pool().
+    /// Returns DeleteAction::Identity if there is no such pool.
+    fn export_pool(&mut self, uuid: PoolUuid) -> StratisResult<DeleteAction<PoolUuid>>;
+
     /// Rename pool with uuid to new_name.
     /// Raises an error if the mapping can't be applied because
     /// new_name is already in use.
@@ -294,6 +1159,19 @@ pub trait Engine: Debug + Report + Send {
         new_name: &str,
     ) -> StratisResult<RenameAction<PoolUuid>>;
 
+    /// Rebuild the live DM devices whose names and DM UUIDs embed the pool
+    /// UUID old_uuid so that they embed new_uuid instead, as is required
+    /// once the UUID of a pool has changed underneath them. Neither UUID may
+    /// belong to a started pool, as the engine refers to the devices of a
+    /// started pool by name. The devices must not be in use; in particular,
+    /// the filesystems on them must be unmounted.
+    /// Returns the old and the new name of every rebuilt device.
+    fn rename_pool_devices(
+        &mut self,
+        old_uuid: PoolUuid,
+        new_uuid: PoolUuid,
+    ) -> StratisResult<Vec<(String, String)>>;
+
     /// Unlock all encrypted devices registered under a given pool UUID.
     /// This method returns a `Vec<DevUuid>`. This `Vec` will contain UUIDs of
     /// devices that were newly unlocked while ignoring devices that are already
@@ -306,6 +1184,53 @@ pub trait Engine: Debug + Report + Send {
         unlock_method: UnlockMethod,
     ) -> StratisResult<SetUnlockAction<DevUuid>>;
 
+    /// Start the pool with the given UUID, which was not started because it
+    /// is marked not to be started automatically or was exported.
+    /// Returns CreateAction::Identity if the pool is already started.
+    fn start_pool(&mut self, pool_uuid: PoolUuid) -> StratisResult<CreateAction<PoolUuid>>;
+
+    /// The UUIDs of the pools that are started automatically, in the order
+    /// in which they are started when stratisd starts.
+    fn startup_order(&self) -> Vec<PoolUuid>;
+
+    /// Whether the kernel supports each of the features on which some
+    /// engine operations depend.
+    fn kernel_features(&self) -> HashMap<KernelFeature, KernelFeatureSupport>;
+
+    /// Check whether name could be given to a new pool. Return the reasons
+    /// why it could not, none if it could. The devicemapper names of a
+    /// pool are derived from its UUID, so only the name itself, the names
+    /// of the pools that are set up, and the path of the directory of
+    /// symlinks for the pool are checked.
+    fn validate_pool_name(&self, name: &str) -> Vec<String>;
+
+    /// Check whether the devices could be used to create a new pool,
+    /// without modifying them. Return a verdict for each device, in the
+    /// order in which the devices were specified.
+    fn validate_device_set(
+        &self,
+        blockdev_paths: &[&Path],
+        encrypted: bool,
+    ) -> StratisResult<Vec<(PathBuf, DeviceVerdict)>>;
+
+    /// Prepare measuring the sequential and random throughput and the
+    /// latency of the device with a short benchmark that uses direct I/O.
+    /// The work, which does not report progress, runs the benchmark; the
+    /// device is kept open exclusively until it is done. The data read from
+    /// the device is written back unchanged.
+    /// Returns an error if the device is in use or too small to be used
+    /// in a pool.
+    fn benchmark_device(&self, devnode: &Path) -> StratisResult<ReadWork<DeviceBenchmark>>;
+
+    /// Describe how the device would be laid out if it were added to a
+    /// pool, with or without encryption, and the I/O topology that the
+    /// device reports. The device must not be in use.
+    fn device_allocation_preview(
+        &self,
+        devnode: &Path,
+        encrypted: bool,
+    ) -> StratisResult<AllocationPreview>;
+
     /// Find the pool designated by uuid.
     fn get_pool(&self, uuid: PoolUuid) -> Option<(Name, &dyn Pool)>;
 
@@ -322,9 +1247,169 @@ pub trait Engine: Debug + Report + Send {
     /// Get mutable references to all pools belonging to this engine.
     fn pools_mut(&mut self) -> Vec<(Name, PoolUuid, &mut dyn Pool)>;
 
+    /// Get a summary of every pool known to this engine: first the pools
+    /// that are set up, then the locked pools. The used size of a pool is
+    /// None if it can not be determined.
+    fn pool_summaries(&self) -> Vec<PoolSummary> {
+        let mut summaries = self
+            .pools()
+            .into_iter()
+            .map(|(name, uuid, pool)| PoolSummary {
+                uuid,
+                name: Some(name),
+                state: pool
+                    .operation_in_progress()
+                    .map_or(PoolState::Running, PoolState::Busy),
+                total_physical_size: Some(pool.total_physical_size()),
+                total_physical_used: pool.total_physical_used().ok(),
+                encrypted: pool.is_encrypted(),
+            })
+            .collect::<Vec<_>>();
+        summaries.extend(
+            self.locked_pools()
+                .into_iter()
+                .map(|(uuid, _)| PoolSummary {
+                    uuid,
+                    name: None,
+                    state: PoolState::Locked,
+                    total_physical_size: None,
+                    total_physical_used: None,
+                    encrypted: true,
+                }),
+        );
+        summaries
+    }
+
+    /// Get an audit of the encryption of every encrypted pool known to this
+    /// engine: first the pools that are set up, then the locked pools.
+    fn encryption_audit(&self) -> Vec<EncryptionAudit> {
+        let mut audits = self
+            .pools()
+            .into_iter()
+            .filter(|(_, _, pool)| pool.is_encrypted())
+            .map(|(name, uuid, pool)| {
+                let info = pool.encryption_info();
+                let keyslots = pool
+                    .keyslot_usage()
+                    .map_err(|e| {
+                        warn!(
+                            "Failed to read the keyslot usage of pool with UUID {}: {}",
+                            uuid, e
+                        );
+                    })
+                    .ok();
+                EncryptionAudit {
+                    uuid,
+                    name: Some(name),
+                    cipher_info: info.cipher_info.clone(),
+                    pbkdf_params: info.pbkdf_params,
+                    keyring: info.key_description.is_some(),
+                    clevis_pin: info.clevis_info.as_ref().map(|(pin, _)| pin.clone()),
+                    keyslots,
+                }
+            })
+            .collect::<Vec<_>>();
+        audits.extend(
+            self.locked_pools()
+                .into_iter()
+                .map(|(uuid, locked)| EncryptionAudit {
+                    uuid,
+                    name: None,
+                    cipher_info: locked.info.cipher_info,
+                    pbkdf_params: locked.info.pbkdf_params,
+                    keyring: locked.info.key_description.is_some(),
+                    clevis_pin: locked.info.clevis_info.map(|(pin, _)| pin),
+                    keyslots: None,
+                }),
+        );
+        audits
+    }
+
+    /// Reconcile every pool that is set up with the metadata on its devices.
+    /// See Pool::reconcile().
+    fn reconcile(&mut self) -> Vec<(PoolUuid, StratisResult<Reconciliation>)> {
+        self.pools_mut()
+            .into_iter()
+            .map(|(name, uuid, pool)| (uuid, pool.reconcile(&name, uuid)))
+            .collect()
+    }
+
+    /// Prepare moving the filesystem with the given UUID to the pool with
+    /// UUID dest_pool_uuid. See Pool::start_copy_filesystem_from(). The
+    /// returned work, which copies the data, does not require access to
+    /// the engine; once it is done, finish_move_filesystem() must be called
+    /// with its result. In the meantime, the filesystem can not be
+    /// destroyed, snapshotted or resized, see Pool::set_filesystem_moving().
+    fn start_move_filesystem(
+        &mut self,
+        fs_uuid: FilesystemUuid,
+        dest_pool_uuid: PoolUuid,
+    ) -> StratisResult<ReadWork<()>>;
+
+    /// Finish the move started by start_move_filesystem(), given the result
+    /// of its work. If the copy succeeded, the filesystem is destroyed in
+    /// its original pool.
+    /// Returns the UUID of the pool to which the filesystem belonged and the
+    /// UUID of the filesystem in the destination pool.
+    fn finish_move_filesystem(
+        &mut self,
+        fs_uuid: FilesystemUuid,
+        dest_pool_uuid: PoolUuid,
+        copied: StratisResult<()>,
+    ) -> StratisResult<(PoolUuid, FilesystemUuid)>;
+
+    /// Move the filesystem with the given UUID to the pool with UUID
+    /// dest_pool_uuid. A copy of the filesystem is created in the destination
+    /// pool with the same name, and then the filesystem is destroyed in its
+    /// original pool. The filesystem must not be mounted. After each chunk
+    /// of data is copied, progress is called with the number of sectors
+    /// copied so far and the total number of sectors to copy.
+    /// Returns the UUID of the pool to which the filesystem belonged and the
+    /// UUID of the filesystem in the destination pool.
+    fn move_filesystem(
+        &mut self,
+        fs_uuid: FilesystemUuid,
+        dest_pool_uuid: PoolUuid,
+        progress: &mut dyn FnMut(Sectors, Sectors),
+    ) -> StratisResult<(PoolUuid, FilesystemUuid)> {
+        let work = self.start_move_filesystem(fs_uuid, dest_pool_uuid)?;
+        let copied = work(progress);
+        self.finish_move_filesystem(fs_uuid, dest_pool_uuid, copied)
+    }
+
     /// Notify the engine that an event has occurred on the DM file descriptor.
     fn evented(&mut self) -> StratisResult<()>;
 
+    /// Permanently destroy the deleted filesystems of all pools whose
+    /// deletion grace period is over. This is called periodically; it does
+    /// nothing in read-only mode or while monitoring is paused.
+    fn purge_deleted_filesystems(&mut self);
+
+    /// Remove and return the events that have occurred on all pools since
+    /// the last time this method was called. The observers subscribed to
+    /// the engine are notified of each event.
+    fn drain_pool_events(&mut self) -> Vec<(PoolUuid, PoolEvent)> {
+        let events = self
+            .pools_mut()
+            .into_iter()
+            .flat_map(|(_, pool_uuid, pool)| {
+                pool.drain_events()
+                    .into_iter()
+                    .map(move |event| (pool_uuid, event))
+            })
+            .collect::<Vec<_>>();
+        for (pool_uuid, event) in &events {
+            self.notify_observers(&EngineEvent::Pool(*pool_uuid, event.clone()));
+        }
+        events
+    }
+
+    /// Subscribe an observer to the events reported by this engine.
+    fn subscribe(&mut self, observer: Box<dyn EngineObserver>);
+
+    /// Notify all the observers subscribed to this engine of an event.
+    fn notify_observers(&self, event: &EngineEvent);
+
     /// Get the handler for kernel keyring operations.
     fn get_key_handler(&self) -> &dyn KeyActions;
 
@@ -333,4 +1418,40 @@ pub trait Engine: Debug + Report + Send {
 
     /// Return true if this engine is the simulator engine, otherwise false.
     fn is_sim(&self) -> bool;
+
+    /// The memory, file descriptors, and devicemapper devices used by the
+    /// daemon.
+    fn resource_usage(&self) -> StratisResult<ResourceUsage>;
+
+    /// Scan for the devices of pools and set up the pools found, waiting
+    /// for missing devices and unlocking pools as the startup settings
+    /// specify. The daemon calls this once, after starting the IPC layer,
+    /// and holds the engine lock meanwhile; the progress so far can be read
+    /// without the lock with startup_progress_so_far().
+    fn start_up(&mut self) -> StratisResult<()>;
+
+    /// The outcome of the scan for the devices of pools and the setup of
+    /// the pools performed by start_up().
+    fn startup_progress(&self) -> StartupProgress;
+
+    /// Return true if the engine is in read-only mode. In read-only mode,
+    /// the engine takes no action of its own that would modify pools, such
+    /// as extending a thin pool in response to a devicemapper event, and
+    /// pools are set up without writing to them.
+    fn read_only_mode(&self) -> bool;
+
+    /// Enter or leave read-only mode.
+    /// Returns true if the mode was changed, otherwise false.
+    fn set_read_only_mode(&mut self, read_only: bool) -> bool;
+
+    /// Return true if monitoring is paused. While monitoring is paused, the
+    /// engine does not handle devicemapper events, so it neither extends
+    /// pools nor raises alerts nor updates the state of devices on its own,
+    /// and it defers udev events until monitoring is resumed.
+    /// This is meant for debugging only.
+    fn monitoring_paused(&self) -> bool;
+
+    /// Pause or resume monitoring.
+    /// Returns true if monitoring was paused or resumed, otherwise false.
+    fn set_monitoring_paused(&mut self, paused: bool) -> bool;
 }
@@ -3,18 +3,35 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 pub use self::{
-    engine::{BlockDev, Engine, Filesystem, KeyActions, Pool, Report},
+    engine::{BlockDev, Engine, EngineObserver, Filesystem, KeyActions, Pool, Report},
+    shared::{device_path, device_path_style, set_device_path_style, STRAT_METADATA_VERSION},
     sim_engine::SimEngine,
     strat_engine::{
-        blkdev_size, crypt_metadata_size, get_dm, get_dm_init, StaticHeader, StaticHeaderResult,
-        StratEngine, StratKeyActions, BDA, CLEVIS_TANG_TRUST_URL,
+        blkdev_size, command_sandbox, crypt_metadata_size, get_dm, get_dm_init,
+        set_command_sandbox, set_dev_path, set_dm_retry_policy, set_startup_device_wait,
+        set_startup_key_policy, set_startup_read_only_mode, set_udev_settle_policy,
+        startup_progress_so_far, udev_settle_policy, CommandSandbox, DmRetryPolicy,
+        StartupKeyPolicy, StaticHeader, StaticHeaderResult, StratEngine, StratKeyActions,
+        UdevSettlePolicy, BDA, CLEVIS_TANG_TRUST_URL,
+    },
+    structures::{
+        lock_stats, operation_timeout, set_operation_timeout, ExclusiveGuard, SharedGuard,
     },
-    structures::{ExclusiveGuard, SharedGuard},
     types::{
-        BlockDevTier, CreateAction, DeleteAction, DevUuid, EncryptionInfo, EngineAction,
-        FilesystemUuid, KeyDescription, Lockable, LockableEngine, MappingCreateAction,
-        MappingDeleteAction, Name, PoolUuid, Redundancy, RenameAction, ReportType, SetCreateAction,
-        SetDeleteAction, StratisUuid, UdevEngineEvent, UnlockMethod,
+        report_versions, ActiveAlert, AlertMetric, AlertThresholds, AllocationPreview,
+        AllocationStrategy, BlockDevTier, CapacityEstimate, CapacitySample, CipherInfo,
+        CreateAction, DeleteAction, DeletedFilesystem, DevUuid, DeviceBenchmark, DevicePathStyle,
+        DeviceVerdict, EncryptionAudit, EncryptionInfo, EngineAction, EngineEvent, FilesystemCheck,
+        FilesystemIoStats, FilesystemTemplate, FilesystemUuid, HealthCheck, HealthReport,
+        IntegrityAlgorithm, IntegrityOverhead, KernelFeature, KernelFeatureSupport, KeyDescription,
+        KeyslotUsage, LatencyStats, LockStats, Lockable, LockableEngine, MappingCreateAction,
+        MappingDeleteAction, MetadataCheck, MetadataWriteFailurePolicy, Name, OperationLogEntry,
+        PbkdfParams, PlannedAction, PoolCapability, PoolEvent, PoolOperation, PoolState,
+        PoolSummary, PoolTemplate, PoolUuid, ReadWork, Reconciliation, ReconfigurationChanges,
+        Redundancy, RedundancyWarning, RenameAction, ReportType, ResourceUsage, SetCreateAction,
+        SetDeleteAction, SmallDevicePolicy, StartupProgress, StratisUuid, StripeConfig,
+        SurfaceTestResult, ThinPoolLimits, TierStructure, TransactionEffect, TransactionOperation,
+        UdevEngineEvent, UnlockMethod, XfsParams,
     },
 };
 
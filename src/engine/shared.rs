@@ -3,23 +3,38 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use std::{
-    collections::{hash_map::RandomState, HashMap, HashSet},
-    fs::File,
+    cmp::{max, min},
+    collections::{hash_map::RandomState, HashMap, HashSet, VecDeque},
+    convert::TryFrom,
+    fs::{self, File},
     io::Read,
     os::unix::io::{AsRawFd, FromRawFd, RawFd},
     path::{Path, PathBuf},
+    sync::RwLock,
 };
 
-use nix::poll::{poll, PollFd, PollFlags};
+use chrono::{DateTime, Duration, Utc};
+use nix::{
+    poll::{poll, PollFd, PollFlags},
+    unistd::{sysconf, SysconfVar},
+};
 use regex::Regex;
+use serde_json::Value;
 
-use devicemapper::{Bytes, Sectors, IEC, SECTOR_SIZE};
+use devicemapper::{Bytes, MetaBlocks, Sectors, IEC, SECTOR_SIZE};
 use libcryptsetup_rs::SafeMemHandle;
 
 use crate::{
     engine::{
-        engine::{Pool, MAX_STRATIS_PASS_SIZE},
-        types::{BlockDevTier, CreateAction, DevUuid, PoolUuid, SetCreateAction, SizedKeyMemory},
+        engine::{Engine, Pool, MAX_STRATIS_PASS_SIZE},
+        structures::Table,
+        types::{
+            AlertMetric, AlertThresholds, BlockDevTier, CapacitySample, CreateAction, DevUuid,
+            DevicePathStyle, EncryptionInfo, FilesystemUuid, IntegrityAlgorithm, IntegrityOverhead,
+            LatencyStats, PoolCapability, PoolTemplate, PoolUuid, ReadWork, SetCreateAction,
+            SizedKeyMemory, StripeConfig, ThinPoolLimits, TransactionEffect, TransactionOperation,
+            XfsParams,
+        },
     },
     stratis::{StratisError, StratisResult},
 };
@@ -33,6 +48,156 @@ pub const DEFAULT_THIN_DEV_SIZE: Sectors = Sectors(2 * IEC::Gi); // 1 TiB
 const MAX_THIN_DEV_SIZE: Sectors = Sectors(16 * IEC::Pi); // 8 EiB
 const MIN_THIN_DEV_SIZE: Sectors = Sectors(64 * IEC::Ki); // 32 MiB
 
+// The maximum allowable size of the thinpool metadata device
+pub const MAX_META_SIZE: MetaBlocks = MetaBlocks(255 * ((1 << 14) - 64));
+// The thinpool metadata device is kept at about this fraction of the
+// usable size of the data tier.
+pub const DATA_TO_META_RATIO: u16 = 1000;
+// The thinpool metadata device is reported as undersized if it is smaller
+// than this percentage of the recommended size.
+pub const META_UNDERSIZED_PCT: u64 = 90;
+
+// Ranges of mkfs.xfs parameters accepted by the engine
+// Version 5 XFS, which mkfs.xfs creates by default, requires blocks of at
+// least 1 KiB
+const MIN_XFS_BLOCK_SIZE: Bytes = Bytes(IEC::Ki as u128);
+// The largest block size XFS supports; a filesystem can only be mounted if
+// its block size does not also exceed the page size, see max_xfs_block_size()
+const MAX_XFS_BLOCK_SIZE: Bytes = Bytes(64 * IEC::Ki as u128);
+const DEFAULT_XFS_BLOCK_SIZE: Bytes = Bytes(4 * IEC::Ki as u128);
+// Version 5 XFS requires inodes of at least 512 bytes
+const MIN_XFS_INODE_SIZE: Bytes = Bytes(512);
+const MAX_XFS_INODE_SIZE: Bytes = Bytes(2 * IEC::Ki as u128);
+const MIN_XFS_LOG_SIZE: Bytes = Bytes(2 * IEC::Mi as u128);
+const MAX_XFS_LOG_SIZE: Bytes = Bytes(2038 * IEC::Mi as u128);
+
+/// Use a cache block size that the kernel docs indicate is the largest
+/// typical size.
+pub const DEFAULT_CACHE_BLOCK_SIZE: Sectors = Sectors(2048); // 1024 KiB
+
+// Range of dm-cache block sizes accepted by the kernel
+const MIN_CACHE_BLOCK_SIZE: Sectors = Sectors(64); // 32 KiB
+const MAX_CACHE_BLOCK_SIZE: Sectors = Sectors(2 * IEC::Mi); // 1 GiB
+
+// The smallest chunk size accepted by dm-stripe is 4 KiB
+const MIN_STRIPE_SIZE: Sectors = Sectors(8);
+
+/// The number of seconds for which samples of the capacity of a pool are
+/// kept.
+const CAPACITY_HISTORY_RETENTION_SECS: i64 = 24 * 60 * 60;
+
+/// The minimum number of seconds between two samples of the capacity of a
+/// pool that are kept.
+const CAPACITY_SAMPLE_INTERVAL_SECS: i64 = 60;
+
+/// The percentage of the space available to the filesystems of a pool that
+/// is held back when recommending an overprovision limit, so that the thin
+/// pool does not run out of space if the filesystems fill up a little more
+/// than estimated.
+const OVERPROVISION_RESERVE_PERCENT: u128 = 10;
+
+/// The smallest percentage of their size that the filesystems of a pool are
+/// assumed to fill when recommending an overprovision limit. It keeps the
+/// recommendation bounded while the filesystems are still nearly empty.
+const MIN_FILL_PERCENT: u128 = 25;
+
+/// The version of the format of the variable length metadata of a pool.
+/// It is the only version that the engine reads and writes.
+pub const STRAT_METADATA_VERSION: u8 = 1;
+
+/// The size of the MDV of a pool. The MDV is never extended.
+pub const INITIAL_MDV_SIZE: Sectors = Sectors(32 * IEC::Ki); // 16 MiB
+
+/// The space on the MDV that the record of a filesystem takes up: a file of
+/// one XFS block and its inode, rounded up generously.
+const MDV_SPACE_PER_FILESYSTEM: Bytes = Bytes(8 * IEC::Ki as u128);
+
+/// The percentage of the MDV that is assumed to be taken up by the log and
+/// the other metadata of the XFS filesystem on it and by the operation log
+/// of the pool, when recommending a limit for the number of filesystems.
+const MDV_OVERHEAD_PERCENT: u128 = 50;
+
+/// The space in the thin pool metadata that a thin device takes up apart
+/// from the mappings of its data: its entry in the device details tree and
+/// the nodes of its mapping tree that it does not share with a snapshot.
+const THIN_META_PER_FILESYSTEM: MetaBlocks = MetaBlocks(4);
+
+/// The percentage of the unused thin pool metadata that is held back for
+/// the mappings of data that is still to be written, when recommending a
+/// limit for the number of filesystems.
+const META_MAPPING_RESERVE_PERCENT: u128 = 50;
+
+/// The size of the blocks of which the checksums are kept when estimating
+/// the overhead of integrity protection.
+const INTEGRITY_BLOCK_SIZE: Bytes = Bytes(4 * IEC::Ki as u128);
+
+/// The size of the journal that an integrity layer keeps on each device, as
+/// assumed when estimating the overhead of integrity protection.
+const INTEGRITY_JOURNAL_SIZE: Sectors = Sectors(128 * IEC::Ki); // 64 MiB
+
+/// The upper bounds, in nanoseconds, of all but the last bucket of the
+/// latency histograms collected for filesystems when detailed statistics are
+/// enabled. The last bucket counts all I/O slower than the last bound.
+pub const LATENCY_HISTOGRAM_BOUNDARIES: [u64; 9] = [
+    100_000,       // 100 us
+    500_000,       // 500 us
+    1_000_000,     // 1 ms
+    5_000_000,     // 5 ms
+    10_000_000,    // 10 ms
+    50_000_000,    // 50 ms
+    100_000_000,   // 100 ms
+    500_000_000,   // 500 ms
+    1_000_000_000, // 1 s
+];
+
+/// Compute the latency statistics of a pool from the totals of the I/O done
+/// on its filesystems: the number of reads and the time in nanoseconds spent
+/// on them, the number of writes and the time in nanoseconds spent on them,
+/// and the counts of the buckets of the latency histogram bounded by
+/// LATENCY_HISTOGRAM_BOUNDARIES.
+// FIXME: dm-stats collects a single latency histogram for reads and writes,
+// so the 99th percentile can not be given for each of them separately.
+pub fn latency_stats(
+    (read_ops, read_ns, write_ops, write_ns): (u64, u64, u64, u64),
+    histogram: &[u64],
+) -> LatencyStats {
+    let average = |ns: u64, ops: u64| {
+        if ops == 0 {
+            None
+        } else {
+            Some(std::time::Duration::from_nanos(ns / ops))
+        }
+    };
+
+    let total = histogram.iter().sum::<u64>();
+    let p99 = if total == 0 {
+        None
+    } else {
+        let rank = (u128::from(total) * 99 + 99) / 100;
+        let mut seen = 0u128;
+        histogram
+            .iter()
+            .position(|count| {
+                seen += u128::from(*count);
+                seen >= rank
+            })
+            .and_then(|bucket| {
+                LATENCY_HISTOGRAM_BOUNDARIES
+                    .get(bucket)
+                    .or_else(|| LATENCY_HISTOGRAM_BOUNDARIES.last())
+            })
+            .map(|bound| std::time::Duration::from_nanos(*bound))
+    };
+
+    LatencyStats {
+        read_ops,
+        read_average: average(read_ns, read_ops),
+        write_ops,
+        write_average: average(write_ns, write_ops),
+        p99,
+    }
+}
+
 /// Called when the name of a requested pool coincides with the name of an
 /// existing pool. Returns an error if the specifications of the requested
 /// pool differ from the specifications of the existing pool, otherwise
@@ -185,6 +350,48 @@ pub fn validate_name(name: &str) -> StratisResult<()> {
     Ok(())
 }
 
+/// Make a name from a template that contains a single run of '#'
+/// characters by replacing the run with the number following the highest
+/// number among the existing names that match the template. The number is
+/// padded with zeros to the length of the run.
+pub fn name_from_template<'a, I>(template: &str, existing: I) -> StratisResult<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let start = template.find('#').ok_or_else(|| {
+        StratisError::Msg(format!(
+            "Name template {} contains no '#' placeholder",
+            template
+        ))
+    })?;
+    let width = template[start..]
+        .find(|c| c != '#')
+        .unwrap_or(template.len() - start);
+    let (prefix, suffix) = (&template[..start], &template[start + width..]);
+    if suffix.contains('#') {
+        return Err(StratisError::Msg(format!(
+            "Name template {} contains more than one '#' placeholder",
+            template
+        )));
+    }
+
+    let highest = existing
+        .into_iter()
+        .filter_map(|name| {
+            name.strip_prefix(prefix)
+                .and_then(|rest| rest.strip_suffix(suffix))
+                .filter(|digits| {
+                    digits.len() >= width && digits.chars().all(|c| c.is_ascii_digit())
+                })
+                .and_then(|digits| digits.parse::<u64>().ok())
+        })
+        .max()
+        .unwrap_or(0);
+    let name = format!("{}{:0width$}{}", prefix, highest + 1, suffix, width = width);
+    validate_name(&name)?;
+    Ok(name)
+}
+
 /// Verify that all paths are absolute.
 pub fn validate_paths(paths: &[&Path]) -> StratisResult<()> {
     let non_absolute_paths: Vec<&Path> = paths
@@ -206,6 +413,124 @@ pub fn validate_paths(paths: &[&Path]) -> StratisResult<()> {
     }
 }
 
+/// Validate the directory for detached LUKS2 headers in the encryption
+/// information of a new pool. Clevis can only bind and unlock a device
+/// whose header is on the device, so detached headers require that the
+/// pool is encrypted with a key in the kernel keyring alone.
+pub fn validate_header_dir(encryption_info: &EncryptionInfo) -> StratisResult<()> {
+    match encryption_info.header_dir {
+        Some(ref dir) if !dir.is_absolute() => Err(StratisError::Msg(format!(
+            "Header directory {} is not absolute",
+            dir.display()
+        ))),
+        Some(_) if encryption_info.key_description.is_none() => Err(StratisError::Msg(
+            "Detached headers require a key description in the kernel keyring".to_string(),
+        )),
+        Some(_) if encryption_info.clevis_info.is_some() => Err(StratisError::Msg(
+            "Detached headers can not be used with Clevis".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Validate a stripe configuration for a data tier made up of the devices
+/// at paths. Whether the devices are large enough is only known once they
+/// have been initialized.
+pub fn validate_stripe(stripe: StripeConfig, paths: &[&Path]) -> StratisResult<()> {
+    let num_devices = paths.iter().collect::<HashSet<_>>().len();
+    if stripe.count < 2 {
+        return Err(StratisError::Msg(format!(
+            "A stripe count of {} was specified, but a striped data tier requires at least 2 devices",
+            stripe.count
+        )));
+    }
+    if usize::from(stripe.count) > num_devices {
+        return Err(StratisError::Msg(format!(
+            "A stripe count of {} was specified, but only {} distinct devices were specified",
+            stripe.count, num_devices
+        )));
+    }
+    if !(*stripe.size).is_power_of_two() || stripe.size < MIN_STRIPE_SIZE {
+        return Err(StratisError::Msg(format!(
+            "Stripe size {} must be a power of two and at least {}",
+            stripe.size, MIN_STRIPE_SIZE
+        )));
+    }
+    Ok(())
+}
+
+/// Validate mkfs.xfs parameters against the ranges that mkfs.xfs accepts,
+/// so that an invalid value is rejected before mkfs.xfs is invoked.
+/// The largest XFS block size accepted: the smaller of the largest block
+/// size XFS supports and the page size of the system, since the kernel can
+/// not mount an XFS filesystem with blocks larger than a page.
+fn max_xfs_block_size() -> Bytes {
+    match sysconf(SysconfVar::PAGE_SIZE) {
+        Ok(Some(page_size)) if page_size > 0 => min(Bytes(page_size as u128), MAX_XFS_BLOCK_SIZE),
+        _ => DEFAULT_XFS_BLOCK_SIZE,
+    }
+}
+
+pub fn validate_xfs_params(params: &XfsParams) -> StratisResult<()> {
+    fn check_power_of_two_in_range(
+        param: &str,
+        value: Bytes,
+        min: Bytes,
+        max: Bytes,
+    ) -> StratisResult<()> {
+        if !value.0.is_power_of_two() || value < min || value > max {
+            Err(StratisError::Msg(format!(
+                "XFS {} {} must be a power of two between {} and {}",
+                param, value, min, max
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    if let Some(block_size) = params.block_size {
+        check_power_of_two_in_range(
+            "block size",
+            block_size,
+            MIN_XFS_BLOCK_SIZE,
+            max_xfs_block_size(),
+        )?;
+    }
+    let block_size = params.block_size.unwrap_or(DEFAULT_XFS_BLOCK_SIZE);
+
+    if let Some(inode_size) = params.inode_size {
+        check_power_of_two_in_range(
+            "inode size",
+            inode_size,
+            MIN_XFS_INODE_SIZE,
+            MAX_XFS_INODE_SIZE,
+        )?;
+        if inode_size.0 > block_size.0 / 2 {
+            return Err(StratisError::Msg(format!(
+                "XFS inode size {} must be no more than half of the block size {}",
+                inode_size, block_size
+            )));
+        }
+    }
+
+    if let Some(log_size) = params.log_size {
+        if log_size < MIN_XFS_LOG_SIZE || log_size > MAX_XFS_LOG_SIZE {
+            return Err(StratisError::Msg(format!(
+                "XFS log size {} must be between {} and {}",
+                log_size, MIN_XFS_LOG_SIZE, MAX_XFS_LOG_SIZE
+            )));
+        }
+        if log_size.0 % block_size.0 != 0 {
+            return Err(StratisError::Msg(format!(
+                "XFS log size {} must be a multiple of the block size {}",
+                log_size, block_size
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 pub fn validate_filesystem_size_specs<'a>(
     specs: &[(&'a str, Option<Bytes>)],
 ) -> StratisResult<HashMap<&'a str, Sectors>> {
@@ -241,10 +566,769 @@ pub fn validate_filesystem_size_specs<'a>(
         .collect::<StratisResult<HashMap<_, Sectors>>>()
 }
 
+/// Validate the target size of an existing filesystem whose thin device
+/// currently has the given size. XFS filesystems can not be shrunk, so the
+/// target must not be less than the current size, and it must be within
+/// the limits reported by filesystem_size_limits().
+pub fn validate_filesystem_target_size(
+    current_size: Sectors,
+    target_size: Sectors,
+) -> StratisResult<()> {
+    let (min_size, max_size) = filesystem_size_limits();
+    if target_size < current_size {
+        Err(StratisError::Msg(format!(
+            "Requested size {} is less than the current size {} of the filesystem; shrinking a filesystem is not supported",
+            target_size, current_size
+        )))
+    } else if target_size < min_size {
+        Err(StratisError::Msg(format!(
+            "Requested size {} is less than minimum required: {}",
+            target_size, min_size
+        )))
+    } else if target_size > max_size {
+        Err(StratisError::Msg(format!(
+            "Requested size {} is greater than maximum allowed: {}",
+            target_size, max_size
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// The minimum and maximum sizes of a filesystem accepted when creating or
+/// extending a filesystem. Since filesystems are thinly provisioned, the
+/// maximum does not depend on the capacity of the pool.
+pub fn filesystem_size_limits() -> (Sectors, Sectors) {
+    (MIN_THIN_DEV_SIZE, MAX_THIN_DEV_SIZE)
+}
+
+/// The limits of a thin pool with data and metadata devices of the given
+/// sizes. The size of the metadata device is limited by the kernel; since
+/// the metadata device is kept at a fixed fraction of the size of the data,
+/// that limits the size of the data device in turn.
+pub fn thin_pool_limits(data_size: Sectors, meta_size: Sectors) -> ThinPoolLimits {
+    ThinPoolLimits {
+        data_size,
+        max_data_size: MAX_META_SIZE.sectors() * DATA_TO_META_RATIO,
+        meta_size,
+        max_meta_size: MAX_META_SIZE.sectors(),
+    }
+}
+
+/// The recommended size of the thin pool metadata device for a data tier of
+/// the given usable size: the fixed fraction of the data, up to the maximum
+/// size allowed by the kernel.
+pub fn recommended_meta_size(datatier_usable: Sectors) -> MetaBlocks {
+    min(
+        (datatier_usable / DATA_TO_META_RATIO).metablocks(),
+        MAX_META_SIZE,
+    )
+}
+
+/// The UUID of the pool to which the filesystem with UUID fs_uuid belongs.
+fn filesystem_pool_uuid<P>(
+    pools: &Table<PoolUuid, P>,
+    fs_uuid: FilesystemUuid,
+) -> StratisResult<PoolUuid>
+where
+    P: Pool,
+{
+    pools
+        .iter()
+        .find(|(_, _, pool)| pool.get_filesystem(fs_uuid).is_some())
+        .map(|(_, uuid, _)| *uuid)
+        .ok_or_else(|| StratisError::Msg(format!("No filesystem with UUID {} found", fs_uuid)))
+}
+
+/// Prepare moving the filesystem with UUID fs_uuid from the pool to which it
+/// belongs to the pool with UUID dest_pool_uuid. See
+/// Engine::start_move_filesystem(). The source filesystem is marked as
+/// being moved until finish_move_filesystem() is called, so that its pool
+/// refuses to destroy, snapshot or resize it in the meantime.
+pub fn start_move_filesystem<P>(
+    pools: &mut Table<PoolUuid, P>,
+    fs_uuid: FilesystemUuid,
+    dest_pool_uuid: PoolUuid,
+) -> StratisResult<ReadWork<()>>
+where
+    P: Pool,
+{
+    let source_pool_uuid = filesystem_pool_uuid(pools, fs_uuid)?;
+    if source_pool_uuid == dest_pool_uuid {
+        return Err(StratisError::Msg(format!(
+            "Filesystem with UUID {} already belongs to pool with UUID {}",
+            fs_uuid, dest_pool_uuid
+        )));
+    }
+
+    // The destination pool is removed from the table while the copy is
+    // started, so that it can be modified while the source filesystem is
+    // inspected.
+    let (dest_pool_name, mut dest_pool) = pools
+        .remove_by_uuid(dest_pool_uuid)
+        .ok_or_else(|| StratisError::Msg(format!("No pool with UUID {} found", dest_pool_uuid)))?;
+    let work = {
+        let (_, source_pool) = pools.get_by_uuid(source_pool_uuid).expect("found above");
+        let (fs_name, fs) = source_pool.get_filesystem(fs_uuid).expect("found above");
+        source_pool
+            .filesystem_mapped_ranges(fs_uuid)
+            .and_then(|ranges| {
+                dest_pool.start_copy_filesystem_from(
+                    &dest_pool_name,
+                    dest_pool_uuid,
+                    &fs_name,
+                    fs,
+                    ranges,
+                )
+            })
+    };
+    pools.insert(dest_pool_name, dest_pool_uuid, dest_pool);
+    let work = work?;
+
+    let (_, source_pool) = pools
+        .get_mut_by_uuid(source_pool_uuid)
+        .expect("found above");
+    source_pool.set_filesystem_moving(fs_uuid, true);
+    Ok(work)
+}
+
+/// Finish the move started by start_move_filesystem(), given the result of
+/// its work. See Engine::finish_move_filesystem().
+pub fn finish_move_filesystem<P>(
+    pools: &mut Table<PoolUuid, P>,
+    fs_uuid: FilesystemUuid,
+    dest_pool_uuid: PoolUuid,
+    copied: StratisResult<()>,
+) -> StratisResult<(PoolUuid, FilesystemUuid)>
+where
+    P: Pool,
+{
+    let source_pool_uuid = filesystem_pool_uuid(pools, fs_uuid);
+    if let Ok(source_pool_uuid) = source_pool_uuid {
+        let (_, source_pool) = pools
+            .get_mut_by_uuid(source_pool_uuid)
+            .expect("found above");
+        source_pool.set_filesystem_moving(fs_uuid, false);
+    }
+    let copied = copied.and_then(|_| {
+        let source_pool_uuid = source_pool_uuid.as_ref().map_err(|_| {
+            StratisError::Msg(format!(
+                "Filesystem with UUID {} was removed while it was being moved",
+                fs_uuid
+            ))
+        })?;
+        let (_, source_pool) = pools.get_by_uuid(*source_pool_uuid).expect("found above");
+        let (_, fs) = source_pool.get_filesystem(fs_uuid).expect("found above");
+        if fs.is_mounted()? {
+            return Err(StratisError::Msg(format!(
+                "Filesystem with UUID {} was mounted while it was being moved",
+                fs_uuid
+            )));
+        }
+        Ok(())
+    });
+
+    let (dest_pool_name, dest_pool) = pools
+        .get_mut_by_uuid(dest_pool_uuid)
+        .ok_or_else(|| StratisError::Msg(format!("No pool with UUID {} found", dest_pool_uuid)))?;
+    let new_fs_uuid = dest_pool.finish_copy_filesystem_from(&dest_pool_name, copied)?;
+    let source_pool_uuid = source_pool_uuid.expect("the copy fails if not found");
+
+    let (source_pool_name, source_pool) = pools
+        .get_mut_by_uuid(source_pool_uuid)
+        .expect("found above");
+    source_pool
+        .destroy_filesystems(&source_pool_name, &[fs_uuid])
+        .map_err(|e| {
+            StratisError::Chained(
+                format!(
+                    "Filesystem with UUID {} was copied to filesystem with UUID {} in pool with UUID {} but could not be removed from pool with UUID {}",
+                    fs_uuid, new_fs_uuid, dest_pool_uuid, source_pool_uuid
+                ),
+                Box::new(e),
+            )
+        })?;
+
+    Ok((source_pool_uuid, new_fs_uuid))
+}
+
+/// Order pools by start priority. Pools with a lower start priority are
+/// started first, pools without a start priority are started after all pools
+/// that have one. Ties are broken by pool UUID, so that the order is stable.
+pub fn startup_order<I>(pools: I) -> Vec<PoolUuid>
+where
+    I: IntoIterator<Item = (PoolUuid, Option<u32>)>,
+{
+    let mut pools = pools.into_iter().collect::<Vec<_>>();
+    pools.sort_by_key(|(uuid, priority)| (priority.is_none(), *priority, uuid.0));
+    pools.into_iter().map(|(uuid, _)| uuid).collect()
+}
+
+/// Validate a dm-cache block size. The kernel requires a block size between
+/// 32 KiB and 1 GiB which is a multiple of 32 KiB; a power of two in that
+/// range is required.
+pub fn validate_cache_block_size(block_size: Sectors) -> StratisResult<()> {
+    if !(*block_size).is_power_of_two()
+        || block_size < MIN_CACHE_BLOCK_SIZE
+        || block_size > MAX_CACHE_BLOCK_SIZE
+    {
+        Err(StratisError::Msg(format!(
+            "Cache block size {} must be a power of two between {} and {}",
+            block_size, MIN_CACHE_BLOCK_SIZE, MAX_CACHE_BLOCK_SIZE
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Validate the alert thresholds of a pool. A threshold of 0 would raise an
+/// alert at all times, and the usage metrics can not exceed 100 percent, nor
+/// can the degraded metric exceed 1.
+pub fn validate_alert_thresholds(thresholds: &AlertThresholds) -> StratisResult<()> {
+    for metric in AlertMetric::ALL.iter() {
+        let max = match metric {
+            AlertMetric::PhysicalUsage | AlertMetric::MetadataUsage => 100,
+            AlertMetric::OverprovisionRatio => u64::MAX,
+            AlertMetric::Degraded => 1,
+        };
+        if let Some(threshold) = thresholds.get(*metric) {
+            if threshold == 0 || threshold > max {
+                return Err(StratisError::Msg(format!(
+                    "Alert threshold {} for metric {} must be between 1 and {}",
+                    threshold, metric, max
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Validate the threshold of the automatic snapshot pruning policy of a
+/// pool, which is a percentage of the total physical size like the physical
+/// usage alert metric.
+pub fn validate_auto_prune_threshold(threshold: Option<u64>) -> StratisResult<()> {
+    match threshold {
+        Some(threshold) if threshold == 0 || threshold > 100 => Err(StratisError::Msg(format!(
+            "Snapshot pruning threshold {} must be between 1 and 100",
+            threshold
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Recommend an upper limit for the overprovision ratio of a pool, in
+/// percent of its total physical size like the overprovision ratio alert
+/// metric. At the limit, the filesystems would use up the space still
+/// available to them, less a reserve, if they filled the same share of
+/// their size as they do on average now. The space available to them is
+/// the space that they use already and the space that is unused in the
+/// pool.
+pub fn recommended_overprovision_limit(
+    total: Sectors,
+    used: Sectors,
+    fs_used: Sectors,
+    fs_size: Sectors,
+) -> u64 {
+    if *total == 0 {
+        return 0;
+    }
+    let capacity = u128::from(min(*total, total.saturating_sub(*used) + *fs_used));
+    let fill_percent = if *fs_size == 0 {
+        MIN_FILL_PERCENT
+    } else {
+        max(
+            MIN_FILL_PERCENT,
+            u128::from(*fs_used) * 100 / u128::from(*fs_size),
+        )
+    };
+    u64::try_from(
+        capacity * (100 - OVERPROVISION_RESERVE_PERCENT) * 100
+            / (fill_percent * u128::from(*total)),
+    )
+    .unwrap_or(u64::MAX)
+}
+
+/// Recommend an upper limit for the number of filesystems in a pool with an
+/// MDV of mdv_size and a thin pool metadata device of size meta_total, of
+/// which meta_used is in use by fs_count filesystems. The limit is the
+/// smaller of the number of filesystem records that fit on the MDV and the
+/// number of thin devices that fit into the unused thin pool metadata,
+/// less the reserve for the mappings of data.
+pub fn recommended_max_filesystems(
+    mdv_size: Sectors,
+    meta_used: MetaBlocks,
+    meta_total: MetaBlocks,
+    fs_count: u64,
+) -> u64 {
+    let mdv_limit =
+        *mdv_size.bytes() * (100 - MDV_OVERHEAD_PERCENT) / 100 / *MDV_SPACE_PER_FILESYSTEM;
+    let meta_limit = u128::from(fs_count)
+        + u128::from(meta_total.saturating_sub(*meta_used)) * (100 - META_MAPPING_RESERVE_PERCENT)
+            / 100
+            / u128::from(*THIN_META_PER_FILESYSTEM);
+    u64::try_from(min(mdv_limit, meta_limit)).unwrap_or(u64::MAX)
+}
+
+/// Estimate the overhead of protecting data_size of data on dev_count
+/// devices with checksums computed with algorithm. Each device keeps the
+/// checksum of each of its blocks and a journal. Every block and its
+/// checksum are written twice, first to the journal and then in place.
+pub fn integrity_overhead(
+    algorithm: IntegrityAlgorithm,
+    data_size: Sectors,
+    dev_count: u64,
+) -> IntegrityOverhead {
+    let tag_size = algorithm.tag_size();
+    let tags = *data_size.bytes() / *INTEGRITY_BLOCK_SIZE * *tag_size;
+    let sector_size = SECTOR_SIZE as u128;
+    IntegrityOverhead {
+        block_size: INTEGRITY_BLOCK_SIZE,
+        tag_size,
+        metadata: Sectors(
+            u64::try_from((tags + sector_size - 1) / sector_size).unwrap_or(u64::MAX),
+        ) + INTEGRITY_JOURNAL_SIZE * dev_count,
+        write_amplification_percent: 200
+            + u64::try_from((200 * *tag_size + *INTEGRITY_BLOCK_SIZE - 1) / *INTEGRITY_BLOCK_SIZE)
+                .unwrap_or(u64::MAX),
+    }
+}
+
+/// Apply the configuration in template, except for the encryption and
+/// the stripe configuration, which are fixed when the pool is created, to
+/// the pool, and create the filesystems of the template in it.
+pub fn apply_pool_template(
+    pool: &mut dyn Pool,
+    pool_name: &str,
+    pool_uuid: PoolUuid,
+    template: &PoolTemplate,
+) -> StratisResult<()> {
+    pool.set_metadata_write_failure_policy(pool_name, template.metadata_write_failure_policy)?;
+    pool.set_auto_start(pool_name, template.auto_start)?;
+    pool.set_start_priority(pool_name, template.start_priority)?;
+    pool.set_detailed_stats(pool_name, template.detailed_stats)?;
+    pool.set_alert_thresholds(pool_name, template.alert_thresholds)?;
+    for fs in template.filesystems.iter() {
+        pool.create_filesystems(
+            pool_name,
+            pool_uuid,
+            &[(fs.name.as_str(), Some(fs.size.bytes()))],
+        )?;
+    }
+    Ok(())
+}
+
+/// The operations that are currently valid on pool, given its
+/// configuration. If blocked is true, the pool is in a state in which it
+/// must not be changed, and only operations that leave its data and its
+/// devices alone are valid.
+pub fn supported_operations(pool: &dyn Pool, blocked: bool) -> Vec<PoolCapability> {
+    let encryption_info = pool.encryption_info();
+    let encrypted = encryption_info.is_encrypted();
+    let has_key = encryption_info.key_description.is_some();
+    let has_clevis = encryption_info.clevis_info.is_some();
+    let has_cache = pool.has_cache();
+    let data_devices = pool
+        .blockdevs()
+        .iter()
+        .filter(|(_, tier, _)| *tier == BlockDevTier::Data)
+        .count();
+
+    PoolCapability::ALL
+        .iter()
+        .copied()
+        .filter(|operation| match operation {
+            PoolCapability::CreateFilesystems
+            | PoolCapability::DestroyFilesystems
+            | PoolCapability::SnapshotFilesystem
+            | PoolCapability::AddDataDevices
+            | PoolCapability::CompactMetadata => !blocked,
+            PoolCapability::RemoveDataDevice => !blocked && data_devices > 1,
+            PoolCapability::InitCache | PoolCapability::ReserveCacheDevices => {
+                !blocked && !encrypted && !has_cache
+            }
+            PoolCapability::AddCacheDevices | PoolCapability::RemoveCache => !blocked && has_cache,
+            PoolCapability::FlushCache => has_cache,
+            PoolCapability::BindKeyring => encrypted && !has_key,
+            PoolCapability::UnbindKeyring => has_key && has_clevis,
+            PoolCapability::RebindKeyring => has_key,
+            PoolCapability::BindClevis => encrypted && !has_clevis,
+            PoolCapability::UnbindClevis => has_clevis && has_key,
+            PoolCapability::RebindClevis => has_clevis,
+        })
+        .collect()
+}
+
+lazy_static! {
+    static ref DEVICE_PATH_STYLE: RwLock<DevicePathStyle> = RwLock::new(DevicePathStyle::default());
+}
+
+/// Set the kind of path by which devices are reported unless another kind
+/// is requested.
+pub fn set_device_path_style(style: DevicePathStyle) {
+    *DEVICE_PATH_STYLE
+        .write()
+        .expect("no thread panics while holding the lock") = style;
+}
+
+/// The kind of path by which devices are reported by default.
+pub fn device_path_style() -> DevicePathStyle {
+    *DEVICE_PATH_STYLE
+        .read()
+        .expect("no thread panics while holding the lock")
+}
+
+/// The path of the given style to the device with the given devnode. If
+/// there are several such paths, the first in lexical order is chosen, so
+/// that the same path is reported each time. If there is none, for example
+/// because udev has not created any, the devnode itself is returned.
+pub fn device_path(devnode: &Path, style: DevicePathStyle) -> PathBuf {
+    let dir = match style {
+        DevicePathStyle::Devnode => return devnode.to_owned(),
+        DevicePathStyle::ById => Path::new("/dev/disk/by-id"),
+        DevicePathStyle::ByPath => Path::new("/dev/disk/by-path"),
+    };
+    let target = match fs::canonicalize(devnode) {
+        Ok(target) => target,
+        Err(_) => return devnode.to_owned(),
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return devnode.to_owned(),
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| fs::canonicalize(path).map_or(false, |p| p == target))
+        .min()
+        .unwrap_or_else(|| devnode.to_owned())
+}
+
+/// The resident set size of this process and the number of file descriptors
+/// it has open, as reported by /proc/self.
+pub fn process_resource_usage() -> StratisResult<(Bytes, u64)> {
+    let mut status = String::new();
+    File::open("/proc/self/status")?.read_to_string(&mut status)?;
+    let rss_kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|value| {
+            value
+                .trim()
+                .trim_end_matches("kB")
+                .trim()
+                .parse::<u64>()
+                .ok()
+        })
+        .ok_or_else(|| {
+            StratisError::Msg("Could not find VmRSS in /proc/self/status".to_string())
+        })?;
+
+    // The directory that is being read is itself open while it is read.
+    let open_fds = fs::read_dir("/proc/self/fd")?.count().saturating_sub(1);
+
+    Ok((Bytes::from(rss_kib * IEC::Ki), open_fds as u64))
+}
+
+/// The UUID of the pool with the given name.
+fn pool_uuid_by_name<E: Engine + ?Sized>(engine: &E, name: &str) -> StratisResult<PoolUuid> {
+    engine
+        .pools()
+        .into_iter()
+        .find(|(pool_name, _, _)| &**pool_name == name)
+        .map(|(_, uuid, _)| uuid)
+        .ok_or_else(|| StratisError::Msg(format!("No pool with name {} found", name)))
+}
+
+/// Perform a single operation of a transaction.
+/// Returns the change made, or None if no change was necessary.
+pub fn apply_transaction_operation<E: Engine + ?Sized>(
+    engine: &mut E,
+    operation: &TransactionOperation,
+) -> StratisResult<Option<TransactionEffect>> {
+    match operation {
+        TransactionOperation::CreatePool {
+            name,
+            devices,
+            encryption_info,
+        } => {
+            let devices = devices.iter().map(|p| p.as_path()).collect::<Vec<_>>();
+            match engine.create_pool(name, &devices, None, encryption_info, None)? {
+                CreateAction::Created(uuid) => Ok(Some(TransactionEffect::PoolCreated(uuid))),
+                CreateAction::Identity => Ok(None),
+            }
+        }
+        TransactionOperation::CreateFilesystem { pool, name, size } => {
+            let pool_uuid = pool_uuid_by_name(engine, pool)?;
+            let (pool_name, pool) = engine.get_mut_pool(pool_uuid).expect("pool was just found");
+            Ok(pool
+                .create_filesystems(&pool_name, pool_uuid, &[(name.as_str(), *size)])?
+                .changed()
+                .and_then(|created| created.first().map(|&(_, uuid, _)| uuid))
+                .map(|uuid| TransactionEffect::FilesystemCreated(pool_uuid, uuid)))
+        }
+        TransactionOperation::BindKeyring {
+            pool,
+            key_description,
+        } => {
+            let pool_uuid = pool_uuid_by_name(engine, pool)?;
+            let (_, pool) = engine.get_mut_pool(pool_uuid).expect("pool was just found");
+            match pool.bind_keyring(key_description)? {
+                CreateAction::Created(_) => Ok(Some(TransactionEffect::KeyringBound(pool_uuid))),
+                CreateAction::Identity => Ok(None),
+            }
+        }
+        TransactionOperation::BindClevis {
+            pool,
+            pin,
+            clevis_info,
+        } => {
+            let pool_uuid = pool_uuid_by_name(engine, pool)?;
+            let (_, pool) = engine.get_mut_pool(pool_uuid).expect("pool was just found");
+            match pool.bind_clevis(pin, clevis_info)? {
+                CreateAction::Created(_) => Ok(Some(TransactionEffect::ClevisBound(pool_uuid))),
+                CreateAction::Identity => Ok(None),
+            }
+        }
+    }
+}
+
+/// Revert a change made by an operation of a transaction.
+pub fn undo_transaction_effect<E: Engine + ?Sized>(
+    engine: &mut E,
+    effect: TransactionEffect,
+) -> StratisResult<()> {
+    match effect {
+        TransactionEffect::PoolCreated(pool_uuid) => {
+            engine.destroy_pool(pool_uuid)?;
+        }
+        TransactionEffect::FilesystemCreated(pool_uuid, fs_uuid) => {
+            if let Some((pool_name, pool)) = engine.get_mut_pool(pool_uuid) {
+                pool.destroy_filesystems(&pool_name, &[fs_uuid])?;
+                // The filesystem is kept if the pool has a deletion grace
+                // period, but it never existed as far as the client is
+                // concerned.
+                pool.purge_deleted_filesystem(pool_uuid, fs_uuid)?;
+            }
+        }
+        TransactionEffect::KeyringBound(pool_uuid) => {
+            if let Some((_, pool)) = engine.get_mut_pool(pool_uuid) {
+                pool.unbind_keyring()?;
+            }
+        }
+        TransactionEffect::ClevisBound(pool_uuid) => {
+            if let Some((_, pool)) = engine.get_mut_pool(pool_uuid) {
+                pool.unbind_clevis()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The samples of the capacity of a pool taken while it was monitored,
+/// oldest first. Samples are kept for CAPACITY_HISTORY_RETENTION_SECS, and a
+/// sample taken less than CAPACITY_SAMPLE_INTERVAL_SECS after the previous
+/// one replaces it, so the number of samples is bounded however often the
+/// pool is checked.
+#[derive(Debug, Default)]
+pub struct CapacityHistory {
+    samples: VecDeque<CapacitySample>,
+}
+
+impl CapacityHistory {
+    /// Add a sample of the used and total physical space of a pool, taken
+    /// now, and drop the samples that are too old to be kept.
+    pub fn record(&mut self, used: Sectors, total: Sectors) {
+        self.record_at(Utc::now(), used, total);
+    }
+
+    fn record_at(&mut self, timestamp: DateTime<Utc>, used: Sectors, total: Sectors) {
+        if let Some(last) = self.samples.back() {
+            if timestamp - last.timestamp < Duration::seconds(CAPACITY_SAMPLE_INTERVAL_SECS) {
+                self.samples.pop_back();
+            }
+        }
+        self.samples.push_back(CapacitySample {
+            timestamp,
+            used,
+            total,
+        });
+
+        let oldest = timestamp - Duration::seconds(CAPACITY_HISTORY_RETENTION_SECS);
+        while self
+            .samples
+            .front()
+            .map(|sample| sample.timestamp < oldest)
+            .unwrap_or(false)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// The samples taken at or after since, or all the samples if since is
+    /// None, oldest first.
+    pub fn samples(&self, since: Option<DateTime<Utc>>) -> Vec<CapacitySample> {
+        self.samples
+            .iter()
+            .filter(|sample| since.map(|since| sample.timestamp >= since).unwrap_or(true))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Add the version of its schema to the top level of a report, so that
+/// clients can tell which format to expect.
+pub fn with_report_version(mut report: Value, version: u64) -> Value {
+    if let Value::Object(ref mut map) = report {
+        map.insert("report_version".to_string(), Value::from(version));
+    }
+    report
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    /// Verify that the averages are computed per operation, and that the
+    /// 99th percentile is the bound of the bucket it falls in.
+    fn test_latency_stats() {
+        assert_eq!(
+            latency_stats((0, 0, 0, 0), &[0; 10]),
+            LatencyStats::default()
+        );
+
+        let mut histogram = [0; 10];
+        histogram[1] = 98;
+        histogram[3] = 2;
+        let stats = latency_stats((10, 1_000_000, 90, 90_000_000), &histogram);
+        assert_eq!(stats.read_ops, 10);
+        assert_eq!(
+            stats.read_average,
+            Some(std::time::Duration::from_micros(100))
+        );
+        assert_eq!(
+            stats.write_average,
+            Some(std::time::Duration::from_millis(1))
+        );
+        assert_eq!(stats.p99, Some(std::time::Duration::from_millis(5)));
+
+        histogram[9] = 100;
+        let stats = latency_stats((10, 1_000_000, 90, 90_000_000), &histogram);
+        assert_eq!(stats.p99, Some(std::time::Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_validate_xfs_params() {
+        assert_matches!(validate_xfs_params(&XfsParams::default()), Ok(_));
+        assert_matches!(
+            validate_xfs_params(&XfsParams {
+                block_size: Some(Bytes(4096)),
+                inode_size: Some(Bytes(1024)),
+                log_size: Some(Bytes(64 * IEC::Mi as u128)),
+            }),
+            Ok(_)
+        );
+        assert_matches!(
+            validate_xfs_params(&XfsParams {
+                block_size: Some(Bytes(3000)),
+                ..XfsParams::default()
+            }),
+            Err(_)
+        );
+        assert_matches!(
+            validate_xfs_params(&XfsParams {
+                block_size: Some(Bytes(512)),
+                ..XfsParams::default()
+            }),
+            Err(_)
+        );
+        // Blocks larger than a page can not be mounted.
+        assert_matches!(
+            validate_xfs_params(&XfsParams {
+                block_size: Some(Bytes(max_xfs_block_size().0 * 2)),
+                ..XfsParams::default()
+            }),
+            Err(_)
+        );
+        assert_matches!(
+            validate_xfs_params(&XfsParams {
+                block_size: Some(Bytes(1024)),
+                inode_size: Some(Bytes(1024)),
+                ..XfsParams::default()
+            }),
+            Err(_)
+        );
+        assert_matches!(
+            validate_xfs_params(&XfsParams {
+                log_size: Some(Bytes(IEC::Mi as u128)),
+                ..XfsParams::default()
+            }),
+            Err(_)
+        );
+        assert_matches!(
+            validate_xfs_params(&XfsParams {
+                log_size: Some(Bytes(2 * IEC::Mi as u128 + 512)),
+                ..XfsParams::default()
+            }),
+            Err(_)
+        );
+    }
+
+    #[test]
+    fn test_name_from_template() {
+        assert_eq!(
+            name_from_template("data-###", Vec::new()).unwrap(),
+            "data-001"
+        );
+        assert_eq!(
+            name_from_template(
+                "data-###",
+                vec!["data-001", "data-007", "data-x", "other-009"]
+            )
+            .unwrap(),
+            "data-008"
+        );
+        assert_eq!(
+            name_from_template("#-fs", vec!["9-fs", "10-fs"]).unwrap(),
+            "11-fs"
+        );
+        assert_matches!(name_from_template("data", Vec::new()), Err(_));
+        assert_matches!(name_from_template("#-#", Vec::new()), Err(_));
+    }
+
+    #[test]
+    fn test_startup_order() {
+        let first = PoolUuid::new_v4();
+        let second = PoolUuid::new_v4();
+        let unordered = PoolUuid::new_v4();
+        assert_eq!(
+            startup_order(vec![
+                (unordered, None),
+                (second, Some(10)),
+                (first, Some(1))
+            ]),
+            vec![first, second, unordered]
+        );
+        assert_eq!(startup_order(Vec::new()), Vec::<PoolUuid>::new());
+    }
+
+    #[test]
+    fn test_validate_filesystem_target_size() {
+        let current = DEFAULT_THIN_DEV_SIZE;
+        assert_matches!(validate_filesystem_target_size(current, current), Ok(_));
+        assert_matches!(
+            validate_filesystem_target_size(current, current + Sectors(1)),
+            Ok(_)
+        );
+        assert_matches!(
+            validate_filesystem_target_size(current, current - Sectors(1)),
+            Err(_)
+        );
+        assert_matches!(
+            validate_filesystem_target_size(current, MAX_THIN_DEV_SIZE + Sectors(1)),
+            Err(_)
+        );
+    }
+
     #[test]
     fn test_validate_name() {
         assert_matches!(validate_name(&'\u{0}'.to_string()), Err(_));
@@ -281,4 +1365,198 @@ mod tests {
         assert_matches!(validate_name("ユニコード"), Ok(_));
         assert_matches!(validate_name("ユニコード?"), Err(_));
     }
+
+    #[test]
+    fn test_validate_cache_block_size() {
+        assert_matches!(validate_cache_block_size(Sectors(2048)), Ok(_));
+        assert_matches!(validate_cache_block_size(MIN_CACHE_BLOCK_SIZE), Ok(_));
+        assert_matches!(validate_cache_block_size(MAX_CACHE_BLOCK_SIZE), Ok(_));
+        assert_matches!(validate_cache_block_size(Sectors(3072)), Err(_));
+        assert_matches!(validate_cache_block_size(Sectors(32)), Err(_));
+        assert_matches!(
+            validate_cache_block_size(MAX_CACHE_BLOCK_SIZE * 2u64),
+            Err(_)
+        );
+    }
+
+    #[test]
+    fn test_validate_stripe() {
+        let paths = [Path::new("/dev/one"), Path::new("/dev/two")];
+        let stripe = |count, size| StripeConfig {
+            count,
+            size: Sectors(size),
+        };
+        assert_matches!(validate_stripe(stripe(2, 128), &paths), Ok(_));
+        assert_matches!(validate_stripe(stripe(2, 8), &paths), Ok(_));
+        assert_matches!(validate_stripe(stripe(1, 128), &paths), Err(_));
+        assert_matches!(validate_stripe(stripe(3, 128), &paths), Err(_));
+        assert_matches!(
+            validate_stripe(stripe(2, 128), &[paths[0], paths[0]]),
+            Err(_)
+        );
+        assert_matches!(validate_stripe(stripe(2, 4), &paths), Err(_));
+        assert_matches!(validate_stripe(stripe(2, 96), &paths), Err(_));
+    }
+
+    #[test]
+    fn test_validate_alert_thresholds() {
+        assert_matches!(
+            validate_alert_thresholds(&AlertThresholds::default()),
+            Ok(_)
+        );
+        assert_matches!(
+            validate_alert_thresholds(&AlertThresholds {
+                physical_usage: Some(90),
+                metadata_usage: Some(100),
+                overprovision_ratio: Some(300),
+                degraded: Some(1),
+            }),
+            Ok(_)
+        );
+        assert_matches!(
+            validate_alert_thresholds(&AlertThresholds {
+                physical_usage: Some(0),
+                ..AlertThresholds::default()
+            }),
+            Err(_)
+        );
+        assert_matches!(
+            validate_alert_thresholds(&AlertThresholds {
+                metadata_usage: Some(101),
+                ..AlertThresholds::default()
+            }),
+            Err(_)
+        );
+        assert_matches!(
+            validate_alert_thresholds(&AlertThresholds {
+                degraded: Some(2),
+                ..AlertThresholds::default()
+            }),
+            Err(_)
+        );
+    }
+
+    #[test]
+    fn test_capacity_history() {
+        let start = Utc::now();
+        let mut history = CapacityHistory::default();
+
+        history.record_at(start, Sectors(1), Sectors(10));
+        history.record_at(start + Duration::seconds(10), Sectors(2), Sectors(10));
+        assert_eq!(
+            history
+                .samples(None)
+                .iter()
+                .map(|sample| sample.used)
+                .collect::<Vec<_>>(),
+            vec![Sectors(2)]
+        );
+
+        history.record_at(start + Duration::hours(1), Sectors(3), Sectors(10));
+        history.record_at(start + Duration::hours(2), Sectors(4), Sectors(20));
+        assert_eq!(history.samples(None).len(), 3);
+        assert_eq!(
+            history
+                .samples(Some(start + Duration::hours(1)))
+                .iter()
+                .map(|sample| (sample.used, sample.total))
+                .collect::<Vec<_>>(),
+            vec![(Sectors(3), Sectors(10)), (Sectors(4), Sectors(20))]
+        );
+
+        history.record_at(start + Duration::hours(26), Sectors(5), Sectors(20));
+        assert_eq!(
+            history
+                .samples(None)
+                .iter()
+                .map(|sample| sample.used)
+                .collect::<Vec<_>>(),
+            vec![Sectors(4), Sectors(5)]
+        );
+    }
+
+    #[test]
+    /// A device without a path of the requested style is reported by its
+    /// devnode.
+    fn test_device_path() {
+        let devnode = Path::new("/dev/stratis-test-no-such-device");
+        assert_eq!(device_path(devnode, DevicePathStyle::Devnode), devnode);
+        assert_eq!(device_path(devnode, DevicePathStyle::ById), devnode);
+        assert_eq!(device_path(devnode, DevicePathStyle::ByPath), devnode);
+    }
+
+    #[test]
+    fn test_recommended_overprovision_limit() {
+        assert_eq!(
+            recommended_overprovision_limit(Sectors(0), Sectors(0), Sectors(0), Sectors(0)),
+            0
+        );
+        // Without filesystems, the minimum fill is assumed.
+        assert_eq!(
+            recommended_overprovision_limit(Sectors(1000), Sectors(0), Sectors(0), Sectors(0)),
+            360
+        );
+        // Half full filesystems in a half used pool.
+        assert_eq!(
+            recommended_overprovision_limit(
+                Sectors(1000),
+                Sectors(500),
+                Sectors(500),
+                Sectors(1000)
+            ),
+            180
+        );
+        // Full filesystems can not be overprovisioned.
+        assert_eq!(
+            recommended_overprovision_limit(
+                Sectors(1000),
+                Sectors(1000),
+                Sectors(1000),
+                Sectors(1000)
+            ),
+            90
+        );
+    }
+
+    #[test]
+    fn test_integrity_overhead() {
+        let overhead = integrity_overhead(IntegrityAlgorithm::Crc32c, Sectors(2 * IEC::Gi), 1);
+        assert_eq!(overhead.tag_size, Bytes(4));
+        assert_eq!(
+            overhead.metadata,
+            Sectors(2 * IEC::Mi) + INTEGRITY_JOURNAL_SIZE
+        );
+        assert_eq!(overhead.write_amplification_percent, 201);
+
+        let overhead = integrity_overhead(IntegrityAlgorithm::Sha256, Sectors(2 * IEC::Gi), 2);
+        assert_eq!(
+            overhead.metadata,
+            Sectors(16 * IEC::Mi) + INTEGRITY_JOURNAL_SIZE * 2u64
+        );
+        assert_eq!(overhead.write_amplification_percent, 202);
+
+        assert_eq!(
+            integrity_overhead(IntegrityAlgorithm::Xxhash64, Sectors(0), 0).metadata,
+            Sectors(0)
+        );
+    }
+
+    #[test]
+    fn test_recommended_max_filesystems() {
+        // Plenty of thin pool metadata; the MDV limits the filesystems.
+        assert_eq!(
+            recommended_max_filesystems(INITIAL_MDV_SIZE, MetaBlocks(0), MetaBlocks(IEC::Mi), 0),
+            1024
+        );
+        // Little thin pool metadata limits the filesystems.
+        assert_eq!(
+            recommended_max_filesystems(INITIAL_MDV_SIZE, MetaBlocks(0), MetaBlocks(400), 0),
+            50
+        );
+        // No thin pool metadata left, so no more filesystems than now.
+        assert_eq!(
+            recommended_max_filesystems(INITIAL_MDV_SIZE, MetaBlocks(400), MetaBlocks(400), 5),
+            5
+        );
+    }
 }
@@ -25,6 +25,7 @@ pub struct SimDev {
     hardware_info: Option<String>,
     initialization_time: u64,
     encryption_info: EncryptionInfo,
+    write_cache: bool,
 }
 
 impl SimDev {
@@ -75,6 +76,7 @@ impl SimDev {
                 hardware_info: None,
                 initialization_time: Utc::now().timestamp() as u64,
                 encryption_info: encryption_info.into_owned(),
+                write_cache: true,
             },
         )
     }
@@ -86,6 +88,19 @@ impl SimDev {
         set_blockdev_user_info!(self; user_info)
     }
 
+    /// Returns true if the simulated device has a volatile write cache enabled.
+    pub fn write_cache_enabled(&self) -> bool {
+        self.write_cache
+    }
+
+    /// Disable the simulated device's write cache.
+    /// Returns true if the write cache was previously enabled.
+    pub fn disable_write_cache(&mut self) -> bool {
+        let changed = self.write_cache;
+        self.write_cache = false;
+        changed
+    }
+
     /// Set the clevis info for a block device.
     pub fn set_clevis_info(&mut self, pin: &str, config: &Value) {
         self.encryption_info.clevis_info = Some((pin.to_owned(), config.clone()));
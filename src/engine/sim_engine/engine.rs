@@ -5,20 +5,30 @@
 use std::{
     collections::{hash_map::RandomState, HashMap, HashSet},
     iter::FromIterator,
-    path::Path,
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
 use serde_json::{json, Value};
 
+use devicemapper::{Bytes, Sectors, IEC};
+
 use crate::{
     engine::{
-        engine::{Engine, KeyActions, Pool, Report},
-        shared::{create_pool_idempotent_or_err, validate_name, validate_paths},
+        engine::{Engine, EngineObserver, KeyActions, Pool, Report},
+        shared::{
+            create_pool_idempotent_or_err, finish_move_filesystem, process_resource_usage,
+            start_move_filesystem, startup_order, validate_header_dir, validate_name,
+            validate_paths, validate_stripe, with_report_version,
+        },
         sim_engine::{keys::SimKeyActions, pool::SimPool},
         structures::Table,
         types::{
-            CreateAction, DeleteAction, DevUuid, EncryptionInfo, LockedPoolInfo, Name, PoolUuid,
-            RenameAction, ReportType, SetUnlockAction, UdevEngineEvent, UnlockMethod,
+            AllocationPreview, CreateAction, DeleteAction, DevUuid, DeviceBenchmark, DeviceVerdict,
+            EncryptionInfo, EngineEvent, FilesystemUuid, KernelFeature, KernelFeatureSupport,
+            LockedPoolInfo, Name, PoolUuid, ReadWork, RenameAction, ReportType, ResourceUsage,
+            SetUnlockAction, StartupProgress, StripeConfig, UdevEngineEvent, UnlockMethod,
+            ENGINE_STATE_REPORT_VERSION,
         },
     },
     stratis::{StratisError, StratisResult},
@@ -27,7 +37,11 @@ use crate::{
 #[derive(Debug, Default)]
 pub struct SimEngine {
     pools: Table<PoolUuid, SimPool>,
+    exported_pools: Table<PoolUuid, SimPool>,
     key_handler: SimKeyActions,
+    read_only_mode: bool,
+    monitoring_paused: bool,
+    observers: Vec<Box<dyn EngineObserver>>,
 }
 
 impl<'a> Into<Value> for &'a SimEngine {
@@ -53,22 +67,24 @@ impl<'a> Into<Value> for &'a SimEngine {
             ),
             "errored_pools": json!([]),
             "hopeless_devices": json!([]),
+            "monitoring_paused": self.monitoring_paused,
         })
     }
 }
 
 impl Report for SimEngine {
     fn engine_state_report(&self) -> Value {
-        self.into()
+        with_report_version(self.into(), ENGINE_STATE_REPORT_VERSION)
     }
 
     fn get_report(&self, report_type: ReportType) -> Value {
-        match report_type {
+        let report = match report_type {
             ReportType::ErroredPoolDevices => json!({
                 "errored_pools": json!([]),
                 "hopeless_devices": json!([]),
             }),
-        }
+        };
+        with_report_version(report, report_type.version())
     }
 }
 
@@ -79,6 +95,7 @@ impl Engine for SimEngine {
         blockdev_paths: &[&Path],
         redundancy: Option<u16>,
         encryption_info: &EncryptionInfo,
+        stripe: Option<StripeConfig>,
     ) -> StratisResult<CreateAction<PoolUuid>> {
         let redundancy = calculate_redundancy!(redundancy);
 
@@ -86,6 +103,18 @@ impl Engine for SimEngine {
 
         validate_paths(blockdev_paths)?;
 
+        if let Some(stripe) = stripe {
+            validate_stripe(stripe, blockdev_paths)?;
+        }
+
+        if encryption_info.is_encrypted() {
+            encryption_info.cipher_info.validate()?;
+            if let Some(ref params) = encryption_info.pbkdf_params {
+                params.validate()?;
+            }
+        }
+        validate_header_dir(encryption_info)?;
+
         if let Some(ref key_desc) = encryption_info.key_description {
             if !self.key_handler.contains_key(key_desc) {
                 return Err(StratisError::Msg(format!(
@@ -106,10 +135,23 @@ impl Engine for SimEngine {
                     let device_set: HashSet<_, RandomState> = HashSet::from_iter(blockdev_paths);
                     let devices = device_set.into_iter().cloned().collect::<Vec<&Path>>();
 
-                    let (pool_uuid, pool) = SimPool::new(&devices, redundancy, encryption_info);
+                    let (pool_uuid, mut pool) =
+                        SimPool::new(&devices, redundancy, encryption_info, stripe);
+                    pool.set_sequence_number(
+                        self.pools
+                            .iter()
+                            .map(|(_, _, pool)| pool.sequence_number())
+                            .max()
+                            .unwrap_or(0)
+                            + 1,
+                    );
 
-                    self.pools
-                        .insert(Name::new(name.to_owned()), pool_uuid, pool);
+                    let name = Name::new(name.to_owned());
+                    self.pools.insert(name.clone(), pool_uuid, pool);
+                    self.notify_observers(&EngineEvent::PoolAdded {
+                        uuid: pool_uuid,
+                        name,
+                    });
 
                     Ok(CreateAction::Created(pool_uuid))
                 }
@@ -121,6 +163,10 @@ impl Engine for SimEngine {
         None
     }
 
+    fn process_deferred_events(&mut self) -> Vec<(Name, PoolUuid)> {
+        Vec::new()
+    }
+
     fn destroy_pool(&mut self, uuid: PoolUuid) -> StratisResult<DeleteAction<PoolUuid>> {
         if let Some((_, pool)) = self.pools.get_by_uuid(uuid) {
             if pool.has_filesystems() {
@@ -134,9 +180,21 @@ impl Engine for SimEngine {
             .expect("Must succeed since self.pool.get_by_uuid() returned a value")
             .1
             .destroy()?;
+        self.notify_observers(&EngineEvent::PoolRemoved(uuid));
         Ok(DeleteAction::Deleted(uuid))
     }
 
+    fn export_pool(&mut self, uuid: PoolUuid) -> StratisResult<DeleteAction<PoolUuid>> {
+        match self.pools.remove_by_uuid(uuid) {
+            Some((name, pool)) => {
+                self.exported_pools.insert(name, uuid, pool);
+                self.notify_observers(&EngineEvent::PoolRemoved(uuid));
+                Ok(DeleteAction::Deleted(uuid))
+            }
+            None => Ok(DeleteAction::Identity),
+        }
+    }
+
     fn rename_pool(
         &mut self,
         uuid: PoolUuid,
@@ -149,11 +207,34 @@ impl Engine for SimEngine {
             .remove_by_uuid(uuid)
             .expect("Must succeed since self.pools.get_by_uuid() returned a value");
 
-        self.pools
-            .insert(Name::new(new_name.to_owned()), uuid, pool);
+        let new_name = Name::new(new_name.to_owned());
+        self.pools.insert(new_name.clone(), uuid, pool);
+        self.notify_observers(&EngineEvent::PoolRenamed {
+            uuid,
+            name: new_name,
+        });
         Ok(RenameAction::Renamed(uuid))
     }
 
+    fn rename_pool_devices(
+        &mut self,
+        old_uuid: PoolUuid,
+        new_uuid: PoolUuid,
+    ) -> StratisResult<Vec<(String, String)>> {
+        if let Some(uuid) = [old_uuid, new_uuid]
+            .iter()
+            .find(|uuid| self.pools.contains_uuid(**uuid))
+        {
+            return Err(StratisError::Msg(format!(
+                "The pool with UUID {} is started; the devices of a started pool can not be renamed",
+                uuid
+            )));
+        }
+
+        // The simulated pools have no DM devices.
+        Ok(Vec::new())
+    }
+
     fn unlock_pool(
         &mut self,
         _pool_uuid: PoolUuid,
@@ -162,6 +243,156 @@ impl Engine for SimEngine {
         Ok(SetUnlockAction::empty())
     }
 
+    fn start_pool(&mut self, pool_uuid: PoolUuid) -> StratisResult<CreateAction<PoolUuid>> {
+        if self.pools.contains_uuid(pool_uuid) {
+            Ok(CreateAction::Identity)
+        } else if let Some((name, pool)) = self.exported_pools.remove_by_uuid(pool_uuid) {
+            if self.pools.contains_name(&name) {
+                let err = StratisError::Msg(format!(
+                    "A pool named {} already exists; the exported pool with UUID {} can not be started",
+                    name, pool_uuid
+                ));
+                self.exported_pools.insert(name, pool_uuid, pool);
+                return Err(err);
+            }
+            self.pools.insert(name.clone(), pool_uuid, pool);
+            self.notify_observers(&EngineEvent::PoolAdded {
+                uuid: pool_uuid,
+                name,
+            });
+            Ok(CreateAction::Created(pool_uuid))
+        } else {
+            Err(StratisError::Msg(format!(
+                "No pool with UUID {} which is marked not to be started automatically was found",
+                pool_uuid
+            )))
+        }
+    }
+
+    fn startup_order(&self) -> Vec<PoolUuid> {
+        startup_order(
+            self.pools
+                .iter()
+                .filter(|(_, _, pool)| pool.auto_start())
+                .map(|(_, uuid, pool)| (*uuid, pool.start_priority())),
+        )
+    }
+
+    fn kernel_features(&self) -> HashMap<KernelFeature, KernelFeatureSupport> {
+        KernelFeature::ALL
+            .iter()
+            .map(|feature| (*feature, KernelFeatureSupport::Supported))
+            .collect()
+    }
+
+    fn validate_pool_name(&self, name: &str) -> Vec<String> {
+        if let Err(err) = validate_name(name) {
+            return vec![err.to_string()];
+        }
+        if self.pools.contains_name(name) {
+            return vec![format!("A pool named {} exists already", name)];
+        }
+        Vec::new()
+    }
+
+    fn validate_device_set(
+        &self,
+        blockdev_paths: &[&Path],
+        _encrypted: bool,
+    ) -> StratisResult<Vec<(PathBuf, DeviceVerdict)>> {
+        validate_paths(blockdev_paths)?;
+
+        let claimed: HashMap<PathBuf, PoolUuid> = self
+            .pools
+            .iter()
+            .flat_map(|(_, pool_uuid, pool)| {
+                pool.blockdevs()
+                    .into_iter()
+                    .map(move |(_, _, bd)| (bd.devnode().to_owned(), *pool_uuid))
+            })
+            .collect();
+
+        let mut seen = HashSet::new();
+        Ok(blockdev_paths
+            .iter()
+            .map(|path| {
+                let verdict = if let Some(pool_uuid) = claimed.get(*path) {
+                    DeviceVerdict::Claimed(*pool_uuid)
+                } else if !seen.insert(*path) {
+                    DeviceVerdict::Duplicate(path.to_path_buf())
+                } else {
+                    DeviceVerdict::Usable(Bytes::from(IEC::Gi))
+                };
+                (path.to_path_buf(), verdict)
+            })
+            .collect())
+    }
+
+    fn benchmark_device(&self, devnode: &Path) -> StratisResult<ReadWork<DeviceBenchmark>> {
+        match self
+            .validate_device_set(&[devnode], false)?
+            .pop()
+            .expect("one verdict for each device")
+        {
+            // The devices of the sim engine are as fast as a typical SSD.
+            (_, DeviceVerdict::Usable(_)) => Ok(Box::new(|_: &mut dyn FnMut(Sectors, Sectors)| {
+                Ok(DeviceBenchmark {
+                    sequential_read: 500 * IEC::Mi,
+                    sequential_write: 400 * IEC::Mi,
+                    random_read: 40 * IEC::Mi,
+                    random_write: 30 * IEC::Mi,
+                    read_latency: Duration::from_micros(100),
+                    write_latency: Duration::from_micros(130),
+                })
+            })),
+            (_, verdict) => Err(StratisError::Msg(format!(
+                "Device {} can not be benchmarked: {}",
+                devnode.display(),
+                verdict
+            ))),
+        }
+    }
+
+    fn device_allocation_preview(
+        &self,
+        devnode: &Path,
+        encrypted: bool,
+    ) -> StratisResult<AllocationPreview> {
+        match self
+            .validate_device_set(&[devnode], encrypted)?
+            .pop()
+            .expect("one verdict for each device")
+        {
+            // The devices of the sim engine have 4 KiB sectors, and their
+            // regions are all MiB aligned.
+            (_, DeviceVerdict::Usable(size)) => {
+                let mut layout = Vec::new();
+                let mut start = Sectors(0);
+                if encrypted {
+                    layout.push(("luks2_header".to_string(), start, Sectors(32 * IEC::Ki)));
+                    start += Sectors(32 * IEC::Ki);
+                }
+                layout.push(("stratis_metadata".to_string(), start, Sectors(8 * IEC::Ki)));
+                start += Sectors(8 * IEC::Ki);
+                layout.push(("data".to_string(), start, size.sectors()));
+                Ok(AllocationPreview {
+                    logical_sector_size: 4 * IEC::Ki,
+                    physical_sector_size: 4 * IEC::Ki,
+                    optimal_io_size: 0,
+                    alignment: 4 * IEC::Ki,
+                    extent_size: Sectors(2 * IEC::Ki),
+                    data_aligned: true,
+                    layout,
+                })
+            }
+            (_, verdict) => Err(StratisError::Msg(format!(
+                "Device {} could not be added to a pool: {}",
+                devnode.display(),
+                verdict
+            ))),
+        }
+    }
+
     fn get_pool(&self, uuid: PoolUuid) -> Option<(Name, &dyn Pool)> {
         get_pool!(self; uuid)
     }
@@ -188,10 +419,37 @@ impl Engine for SimEngine {
             .collect()
     }
 
+    fn start_move_filesystem(
+        &mut self,
+        fs_uuid: FilesystemUuid,
+        dest_pool_uuid: PoolUuid,
+    ) -> StratisResult<ReadWork<()>> {
+        start_move_filesystem(&mut self.pools, fs_uuid, dest_pool_uuid)
+    }
+
+    fn finish_move_filesystem(
+        &mut self,
+        fs_uuid: FilesystemUuid,
+        dest_pool_uuid: PoolUuid,
+        copied: StratisResult<()>,
+    ) -> StratisResult<(PoolUuid, FilesystemUuid)> {
+        finish_move_filesystem(&mut self.pools, fs_uuid, dest_pool_uuid, copied)
+    }
+
     fn evented(&mut self) -> StratisResult<()> {
         Ok(())
     }
 
+    fn purge_deleted_filesystems(&mut self) {
+        if self.read_only_mode || self.monitoring_paused {
+            return;
+        }
+
+        for (_, _, pool) in self.pools.iter_mut() {
+            pool.purge_deleted_filesystems();
+        }
+    }
+
     fn get_key_handler(&self) -> &dyn KeyActions {
         &self.key_handler as &dyn KeyActions
     }
@@ -203,15 +461,74 @@ impl Engine for SimEngine {
     fn is_sim(&self) -> bool {
         true
     }
+
+    fn resource_usage(&self) -> StratisResult<ResourceUsage> {
+        let (rss, open_fds) = process_resource_usage()?;
+        // The sim engine manages no devicemapper devices.
+        Ok(ResourceUsage {
+            rss,
+            open_fds,
+            dm_devices: 0,
+        })
+    }
+
+    fn start_up(&mut self) -> StratisResult<()> {
+        Ok(())
+    }
+
+    fn startup_progress(&self) -> StartupProgress {
+        // The sim engine finds no devices at startup.
+        StartupProgress {
+            complete: true,
+            ..StartupProgress::default()
+        }
+    }
+
+    fn read_only_mode(&self) -> bool {
+        self.read_only_mode
+    }
+
+    fn set_read_only_mode(&mut self, read_only: bool) -> bool {
+        let changed = self.read_only_mode != read_only;
+        self.read_only_mode = read_only;
+        changed
+    }
+
+    fn monitoring_paused(&self) -> bool {
+        self.monitoring_paused
+    }
+
+    fn set_monitoring_paused(&mut self, paused: bool) -> bool {
+        let changed = self.monitoring_paused != paused;
+        self.monitoring_paused = paused;
+        changed
+    }
+
+    fn subscribe(&mut self, observer: Box<dyn EngineObserver>) {
+        self.observers.push(observer);
+    }
+
+    fn notify_observers(&self, event: &EngineEvent) {
+        for observer in &self.observers {
+            observer.notify(event);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use std::{self, path::Path};
+    use std::{
+        self,
+        path::Path,
+        sync::{Arc, Mutex},
+    };
 
     use crate::engine::{
-        types::{EngineAction, RenameAction},
+        types::{
+            CipherInfo, EngineAction, PbkdfParams, PoolState, RenameAction, TransactionEffect,
+            TransactionOperation,
+        },
         Engine,
     };
 
@@ -229,6 +546,38 @@ mod tests {
         assert_matches!(SimEngine::default().destroy_pool(PoolUuid::new_v4()), Ok(_));
     }
 
+    #[test]
+    /// Pools are numbered in the order in which they are created, and a
+    /// pool keeps its number when it is renamed.
+    fn pool_sequence_numbers() {
+        let mut engine = SimEngine::default();
+        let uuid1 = engine
+            .create_pool(
+                "name1",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let uuid2 = engine
+            .create_pool(
+                "name2",
+                strs_to_paths!(["/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        assert_matches!(engine.rename_pool(uuid1, "name3"), Ok(_));
+        assert_eq!(engine.get_pool(uuid1).unwrap().1.sequence_number(), 1);
+        assert_eq!(engine.get_pool(uuid2).unwrap().1.sequence_number(), 2);
+    }
+
     #[test]
     /// Destroying an empty pool should succeed.
     fn destroy_empty_pool() {
@@ -239,6 +588,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -256,6 +606,7 @@ mod tests {
                 strs_to_paths!(["/s/d"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -274,6 +625,7 @@ mod tests {
                 strs_to_paths!(["/s/d"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -286,6 +638,30 @@ mod tests {
         assert_matches!(engine.destroy_pool(uuid), Err(_));
     }
 
+    #[test]
+    /// An exported pool is no longer listed and is listed again once it is
+    /// started.
+    fn export_and_start_pool() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "name",
+                strs_to_paths!(["/s/d"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        assert_matches!(engine.export_pool(uuid), Ok(DeleteAction::Deleted(_)));
+        assert!(engine.get_pool(uuid).is_none());
+        assert_matches!(engine.export_pool(uuid), Ok(DeleteAction::Identity));
+        assert_matches!(engine.start_pool(uuid), Ok(CreateAction::Created(_)));
+        assert!(engine.get_pool(uuid).is_some());
+        assert_matches!(engine.start_pool(uuid), Ok(CreateAction::Identity));
+    }
+
     #[test]
     /// Creating a new pool with the same name and arguments should return
     /// identity.
@@ -294,10 +670,10 @@ mod tests {
         let mut engine = SimEngine::default();
         let devices = strs_to_paths!(["/s/d"]);
         engine
-            .create_pool(name, devices, None, &EncryptionInfo::default())
+            .create_pool(name, devices, None, &EncryptionInfo::default(), None)
             .unwrap();
         assert_matches!(
-            engine.create_pool(name, devices, None, &EncryptionInfo::default()),
+            engine.create_pool(name, devices, None, &EncryptionInfo::default(), None),
             Ok(CreateAction::Identity)
         );
     }
@@ -313,6 +689,7 @@ mod tests {
                 strs_to_paths!(["/s/d"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap();
         assert_matches!(
@@ -321,6 +698,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             ),
             Err(_)
         );
@@ -337,7 +715,8 @@ mod tests {
                     "name",
                     strs_to_paths!([path, path]),
                     None,
-                    &EncryptionInfo::default()
+                    &EncryptionInfo::default(),
+                    None
                 )
                 .unwrap()
                 .changed()
@@ -356,6 +735,80 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 Some(std::u16::MAX),
                 &EncryptionInfo::default(),
+                None,
+            ),
+            Err(_)
+        );
+    }
+
+    #[test]
+    /// Creating an encrypted pool with an unsupported key size should fail
+    fn create_pool_unsupported_cipher() {
+        let mut engine = SimEngine::default();
+        assert_matches!(
+            engine.create_pool(
+                "name",
+                strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
+                None,
+                &EncryptionInfo {
+                    key_description: None,
+                    clevis_info: Some(("tang".to_string(), json!({"url": "http://tang"}))),
+                    cipher_info: CipherInfo {
+                        cipher: "aes-xts-plain64".to_string(),
+                        key_size: 128,
+                    },
+                    pbkdf_params: None,
+                    header_dir: None,
+                },
+                None,
+            ),
+            Err(_)
+        );
+    }
+
+    #[test]
+    /// Creating an encrypted pool with PBKDF parameters that cryptsetup does
+    /// not accept should fail
+    fn create_pool_unsupported_pbkdf_params() {
+        let mut engine = SimEngine::default();
+        assert_matches!(
+            engine.create_pool(
+                "name",
+                strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
+                None,
+                &EncryptionInfo {
+                    key_description: None,
+                    clevis_info: Some(("tang".to_string(), json!({"url": "http://tang"}))),
+                    pbkdf_params: Some(PbkdfParams {
+                        iterations: 4,
+                        memory_kib: 1024,
+                        parallelism: 8,
+                    }),
+                    ..EncryptionInfo::default()
+                },
+                None,
+            ),
+            Err(_)
+        );
+    }
+
+    #[test]
+    /// Creating an encrypted pool with detached headers should fail if the
+    /// pool is to be bound with Clevis
+    fn create_pool_detached_header_clevis() {
+        let mut engine = SimEngine::default();
+        assert_matches!(
+            engine.create_pool(
+                "name",
+                strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
+                None,
+                &EncryptionInfo {
+                    key_description: None,
+                    clevis_info: Some(("tang".to_string(), json!({"url": "http://tang"}))),
+                    header_dir: Some(PathBuf::from("/media/headers")),
+                    ..EncryptionInfo::default()
+                },
+                None,
             ),
             Err(_)
         );
@@ -382,6 +835,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -402,6 +856,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -423,6 +878,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -433,6 +889,7 @@ mod tests {
                 strs_to_paths!(["/dev/four", "/dev/five", "/dev/six"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap();
         assert_matches!(engine.rename_pool(uuid, new_name), Err(_));
@@ -449,6 +906,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap();
         assert_matches!(
@@ -456,4 +914,612 @@ mod tests {
             Ok(RenameAction::NoSource)
         );
     }
+
+    #[test]
+    /// Renaming the devices of a pool should fail if the pool is started
+    fn rename_pool_devices_started() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        assert_matches!(engine.rename_pool_devices(uuid, PoolUuid::new_v4()), Err(_));
+        assert_matches!(engine.rename_pool_devices(PoolUuid::new_v4(), uuid), Err(_));
+        assert_matches!(
+            engine.rename_pool_devices(PoolUuid::new_v4(), PoolUuid::new_v4()),
+            Ok(ref renamed) if renamed.is_empty()
+        );
+    }
+
+    #[test]
+    /// Moving a filesystem should remove it from its pool and create it with
+    /// the same name in the destination pool
+    fn move_filesystem() {
+        let mut engine = SimEngine::default();
+        let source_uuid = engine
+            .create_pool(
+                "source",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let dest_uuid = engine
+            .create_pool(
+                "dest",
+                strs_to_paths!(["/dev/three", "/dev/four"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(source_uuid).unwrap();
+        let (_, fs_uuid, _) = pool
+            .create_filesystems(&pool_name, source_uuid, &[("fs_name", None)])
+            .unwrap()
+            .changed()
+            .unwrap()[0];
+
+        assert_matches!(
+            engine.move_filesystem(fs_uuid, source_uuid, &mut |_, _| ()),
+            Err(_)
+        );
+        assert_matches!(
+            engine.move_filesystem(fs_uuid, PoolUuid::new_v4(), &mut |_, _| ()),
+            Err(_)
+        );
+
+        let (old_pool_uuid, new_fs_uuid) = engine
+            .move_filesystem(fs_uuid, dest_uuid, &mut |_, _| ())
+            .unwrap();
+        assert_eq!(old_pool_uuid, source_uuid);
+        assert!(engine
+            .get_pool(source_uuid)
+            .unwrap()
+            .1
+            .get_filesystem(fs_uuid)
+            .is_none());
+        assert_eq!(
+            engine
+                .get_pool(dest_uuid)
+                .unwrap()
+                .1
+                .get_filesystem(new_fs_uuid)
+                .unwrap()
+                .0
+                .to_string(),
+            "fs_name"
+        );
+        assert_matches!(
+            engine.move_filesystem(fs_uuid, source_uuid, &mut |_, _| ()),
+            Err(_)
+        );
+    }
+
+    #[test]
+    /// A filesystem which is being moved can not be destroyed, snapshotted
+    /// or resized until the move is finished.
+    fn move_filesystem_busy() {
+        let mut engine = SimEngine::default();
+        let source_uuid = engine
+            .create_pool(
+                "source",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let dest_uuid = engine
+            .create_pool(
+                "dest",
+                strs_to_paths!(["/dev/three", "/dev/four"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(source_uuid).unwrap();
+        let (_, fs_uuid, _) = pool
+            .create_filesystems(&pool_name, source_uuid, &[("fs_name", None)])
+            .unwrap()
+            .changed()
+            .unwrap()[0];
+
+        let work = engine.start_move_filesystem(fs_uuid, dest_uuid).unwrap();
+        let copied = work(&mut |_, _| ());
+
+        let (pool_name, pool) = engine.get_mut_pool(source_uuid).unwrap();
+        assert_matches!(pool.destroy_filesystems(&pool_name, &[fs_uuid]), Err(_));
+        assert_matches!(
+            pool.snapshot_filesystem(&pool_name, source_uuid, fs_uuid, "snapshot"),
+            Err(_)
+        );
+        assert_matches!(
+            pool.set_filesystem_size(&pool_name, fs_uuid, Sectors(1 << 30), false),
+            Err(_)
+        );
+
+        engine
+            .finish_move_filesystem(fs_uuid, dest_uuid, copied)
+            .unwrap();
+    }
+
+    #[test]
+    /// The startup order follows the start priorities and omits pools that
+    /// are not started automatically.
+    fn startup_order() {
+        let mut engine = SimEngine::default();
+        let first_uuid = engine
+            .create_pool(
+                "first",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let last_uuid = engine
+            .create_pool(
+                "last",
+                strs_to_paths!(["/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let stopped_uuid = engine
+            .create_pool(
+                "stopped",
+                strs_to_paths!(["/dev/three"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+
+        let (pool_name, pool) = engine.get_mut_pool(first_uuid).unwrap();
+        assert_matches!(pool.set_start_priority(&pool_name, Some(1)), Ok(true));
+        assert_matches!(pool.set_start_priority(&pool_name, Some(1)), Ok(false));
+        let (pool_name, pool) = engine.get_mut_pool(stopped_uuid).unwrap();
+        assert_matches!(pool.set_auto_start(&pool_name, false), Ok(true));
+
+        assert_eq!(engine.startup_order(), vec![first_uuid, last_uuid]);
+    }
+
+    #[test]
+    /// Every report carries the version of its schema.
+    fn report_version() {
+        let engine = SimEngine::default();
+        assert_eq!(
+            engine.engine_state_report()["report_version"],
+            ENGINE_STATE_REPORT_VERSION
+        );
+        let report_type = ReportType::ErroredPoolDevices;
+        assert_eq!(
+            engine.get_report(ReportType::ErroredPoolDevices)["report_version"],
+            report_type.version()
+        );
+    }
+
+    #[test]
+    /// A pool name is rejected if it is invalid or taken.
+    fn validate_pool_name() {
+        let mut engine = SimEngine::default();
+        engine
+            .create_pool(
+                "taken",
+                strs_to_paths!(["/s/d"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap();
+        assert!(engine.validate_pool_name("free").is_empty());
+        assert_eq!(engine.validate_pool_name("taken").len(), 1);
+        assert_eq!(engine.validate_pool_name("a/b").len(), 1);
+        assert_eq!(engine.validate_pool_name("..").len(), 1);
+    }
+
+    #[test]
+    /// Only free devices can be benchmarked.
+    fn benchmark_device() {
+        let mut engine = SimEngine::default();
+        engine
+            .create_pool(
+                "name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap();
+        assert!(engine.benchmark_device(Path::new("/dev/two")).unwrap()(&mut |_, _| ()).is_ok());
+        assert_matches!(engine.benchmark_device(Path::new("/dev/one")), Err(_));
+    }
+
+    #[test]
+    /// An allocation preview covers the whole device with consecutive
+    /// regions, the data last, and is only given for free devices.
+    fn device_allocation_preview() {
+        let mut engine = SimEngine::default();
+        engine
+            .create_pool(
+                "name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap();
+        assert_matches!(
+            engine.device_allocation_preview(Path::new("/dev/one"), false),
+            Err(_)
+        );
+
+        let plain = engine
+            .device_allocation_preview(Path::new("/dev/two"), false)
+            .unwrap();
+        let encrypted = engine
+            .device_allocation_preview(Path::new("/dev/two"), true)
+            .unwrap();
+        for preview in &[&plain, &encrypted] {
+            assert!(preview.layout.windows(2).all(|w| w[0].1 + w[0].2 == w[1].1));
+            assert_eq!(preview.layout.last().unwrap().0, "data");
+        }
+        assert!(encrypted.layout.len() > plain.layout.len());
+    }
+
+    #[test]
+    /// Validating a device set reports devices that are already in a pool
+    /// and devices that were specified more than once.
+    fn validate_device_set() {
+        let mut engine = SimEngine::default();
+        let pool_uuid = engine
+            .create_pool(
+                "name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+
+        let verdicts = engine
+            .validate_device_set(strs_to_paths!(["/dev/one", "/dev/two", "/dev/two"]), false)
+            .unwrap();
+        assert_eq!(
+            verdicts
+                .iter()
+                .map(|(_, verdict)| verdict.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                DeviceVerdict::Claimed(pool_uuid),
+                DeviceVerdict::Usable(Bytes::from(IEC::Gi)),
+                DeviceVerdict::Duplicate(PathBuf::from("/dev/two")),
+            ]
+        );
+        assert_matches!(
+            engine.validate_device_set(strs_to_paths!(["dev/one"]), false),
+            Err(_)
+        );
+    }
+
+    #[test]
+    /// Subscribed observers are notified when pools are created, renamed,
+    /// and destroyed.
+    fn observers_notified() {
+        #[derive(Debug, Default)]
+        struct Recorder(Arc<Mutex<Vec<EngineEvent>>>);
+
+        impl EngineObserver for Recorder {
+            fn notify(&self, event: &EngineEvent) {
+                self.0.lock().unwrap().push(event.clone());
+            }
+        }
+
+        let mut engine = SimEngine::default();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        engine.subscribe(Box::new(Recorder(Arc::clone(&events))));
+
+        let uuid = engine
+            .create_pool(
+                "old_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        engine.rename_pool(uuid, "new_name").unwrap();
+        engine.destroy_pool(uuid).unwrap();
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                EngineEvent::PoolAdded {
+                    uuid,
+                    name: Name::new("old_name".to_string()),
+                },
+                EngineEvent::PoolRenamed {
+                    uuid,
+                    name: Name::new("new_name".to_string()),
+                },
+                EngineEvent::PoolRemoved(uuid),
+            ]
+        );
+    }
+
+    #[test]
+    /// A summary is returned for each pool that is set up.
+    fn pool_summaries() {
+        let mut engine = SimEngine::default();
+        assert!(engine.pool_summaries().is_empty());
+
+        let pool_uuid = engine
+            .create_pool(
+                "name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let summaries = engine.pool_summaries();
+        assert_eq!(summaries.len(), 1);
+        let summary = &summaries[0];
+        assert_eq!(summary.uuid, pool_uuid);
+        assert_eq!(summary.name, Some(Name::new("name".to_string())));
+        assert_eq!(summary.state, PoolState::Running);
+        assert!(summary.total_physical_size.is_some());
+        assert!(!summary.encrypted);
+    }
+
+    #[test]
+    /// An audit is returned only for the encrypted pools, and it reports
+    /// the bindings of the pool but not their configuration.
+    fn encryption_audit() {
+        let mut engine = SimEngine::default();
+        engine
+            .create_pool(
+                "plain",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap();
+        let pool_uuid = engine
+            .create_pool(
+                "encrypted",
+                strs_to_paths!(["/dev/two"]),
+                None,
+                &EncryptionInfo {
+                    key_description: None,
+                    clevis_info: Some(("tang".to_string(), json!({"url": "http://tang"}))),
+                    ..EncryptionInfo::default()
+                },
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+
+        let audits = engine.encryption_audit();
+        assert_eq!(audits.len(), 1);
+        let audit = &audits[0];
+        assert_eq!(audit.uuid, pool_uuid);
+        assert_eq!(audit.name, Some(Name::new("encrypted".to_string())));
+        assert!(!audit.keyring);
+        assert_eq!(audit.clevis_pin.as_deref(), Some("tang"));
+        assert_eq!(audit.keyslots.map(|k| k.used), Some(1));
+    }
+
+    #[test]
+    /// A pool is created with the specified stripe configuration, and a
+    /// stripe configuration that the devices can not support is rejected.
+    fn create_pool_striped() {
+        let mut engine = SimEngine::default();
+        let stripe = StripeConfig {
+            count: 2,
+            size: Sectors(128),
+        };
+        let pool_uuid = engine
+            .create_pool(
+                "name",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                Some(stripe),
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        assert_eq!(
+            engine.get_pool(pool_uuid).unwrap().1.stripe_config(),
+            Some(stripe)
+        );
+
+        assert_matches!(
+            engine.create_pool(
+                "other",
+                strs_to_paths!(["/dev/three", "/dev/three"]),
+                None,
+                &EncryptionInfo::default(),
+                Some(stripe),
+            ),
+            Err(_)
+        );
+        assert_matches!(
+            engine.create_pool(
+                "other",
+                strs_to_paths!(["/dev/three", "/dev/four"]),
+                None,
+                &EncryptionInfo::default(),
+                Some(StripeConfig {
+                    count: 2,
+                    size: Sectors(100),
+                }),
+            ),
+            Err(_)
+        );
+    }
+
+    #[test]
+    /// The sim engine reports every kernel feature as supported.
+    fn kernel_features() {
+        let features = SimEngine::default().kernel_features();
+        assert_eq!(features.len(), KernelFeature::ALL.len());
+        assert!(features
+            .values()
+            .all(|support| *support == KernelFeatureSupport::Supported));
+    }
+
+    #[test]
+    /// A pool created from the template exported from another pool has the
+    /// same configuration, but its own UUIDs.
+    fn create_pool_from_template() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        pool.set_auto_start(&pool_name, false).unwrap();
+        pool.set_start_priority(&pool_name, Some(3)).unwrap();
+        pool.create_filesystems(&pool_name, uuid, &[("fs", None)])
+            .unwrap();
+        let template = pool.export_config();
+        assert_eq!(template.filesystems.len(), 1);
+
+        let new_uuid = engine
+            .create_pool_from_template("new_name", strs_to_paths!(["/dev/two"]), &template)
+            .unwrap()
+            .changed()
+            .unwrap();
+        assert_ne!(new_uuid, uuid);
+        let (_, new_pool) = engine.get_pool(new_uuid).unwrap();
+        assert_eq!(new_pool.export_config(), template);
+        assert_ne!(
+            new_pool.filesystems()[0].1,
+            engine.get_pool(uuid).unwrap().1.filesystems()[0].1
+        );
+
+        // A template that can not be applied leaves no pool behind.
+        let mut invalid = template;
+        invalid.alert_thresholds.physical_usage = Some(0);
+        assert_matches!(
+            engine.create_pool_from_template(
+                "other_name",
+                strs_to_paths!(["/dev/three"]),
+                &invalid
+            ),
+            Err(_)
+        );
+        assert_eq!(engine.pools().len(), 2);
+    }
+
+    #[test]
+    /// The operations of a transaction are performed as a unit: if one of
+    /// them fails, the changes made by the ones before it are reverted.
+    fn transaction() {
+        let mut engine = SimEngine::default();
+        let operations: Vec<TransactionOperation> = serde_json::from_str(
+            r#"[
+                {"operation": "create_pool", "name": "pool", "devices": ["/dev/one"]},
+                {"operation": "create_filesystem", "pool": "pool", "name": "fs1", "size": null},
+                {"operation": "create_filesystem", "pool": "pool", "name": "fs2", "size": null}
+            ]"#,
+        )
+        .unwrap();
+        let effects = engine.transaction(&operations).unwrap();
+        assert_eq!(effects.len(), 3);
+        assert_matches!(effects[0], TransactionEffect::PoolCreated(_));
+        let (_, uuid, pool) = engine.pools().into_iter().next().unwrap();
+        assert_eq!(effects[0], TransactionEffect::PoolCreated(uuid));
+        assert_eq!(pool.filesystems().len(), 2);
+
+        // Repeating the transaction requires no change.
+        assert!(engine.transaction(&operations).unwrap().is_empty());
+
+        let operations = vec![
+            TransactionOperation::CreatePool {
+                name: "other_pool".to_string(),
+                devices: vec![PathBuf::from("/dev/two")],
+                encryption_info: EncryptionInfo::default(),
+            },
+            TransactionOperation::CreateFilesystem {
+                pool: "other_pool".to_string(),
+                name: "fs".to_string(),
+                size: None,
+            },
+            TransactionOperation::CreateFilesystem {
+                pool: "pool".to_string(),
+                name: "fs3".to_string(),
+                size: None,
+            },
+            TransactionOperation::CreateFilesystem {
+                pool: "no_such_pool".to_string(),
+                name: "fs".to_string(),
+                size: None,
+            },
+        ];
+        assert_matches!(engine.transaction(&operations), Err(_));
+        assert_eq!(engine.pools().len(), 1);
+        assert_eq!(engine.get_pool(uuid).unwrap().1.filesystems().len(), 2);
+    }
+
+    #[test]
+    /// The resource usage of the daemon is reported, and the sim engine
+    /// manages no devicemapper devices.
+    fn resource_usage() {
+        let usage = SimEngine::default().resource_usage().unwrap();
+        assert!(usage.rss > Bytes(0));
+        assert!(usage.open_fds > 0);
+        assert_eq!(usage.dm_devices, 0);
+    }
+
+    #[test]
+    /// Verify that the sim engine reports that it found nothing at startup.
+    fn startup_progress() {
+        let progress = SimEngine::default().startup_progress();
+        assert_eq!(progress.devices_scanned, 0);
+        assert!(progress.pools_pending.is_empty());
+        assert!(progress.complete);
+    }
 }
@@ -9,13 +9,19 @@ use serde_json::{Map, Value};
 
 use devicemapper::{Bytes, Sectors};
 
-use crate::{engine::Filesystem, stratis::StratisResult};
+use crate::{
+    engine::{Filesystem, FilesystemCheck, FilesystemIoStats, ReadWork},
+    stratis::StratisResult,
+};
 
 #[derive(Debug)]
 pub struct SimFilesystem {
     rand: u32,
     created: DateTime<Utc>,
     size: Sectors,
+    cow_alert_threshold: Option<Bytes>,
+    min_free_guarantee: Option<Bytes>,
+    sequence_number: u64,
 }
 
 impl SimFilesystem {
@@ -24,11 +30,26 @@ impl SimFilesystem {
             rand: rand::random::<u32>(),
             created: Utc::now(),
             size,
+            cow_alert_threshold: None,
+            min_free_guarantee: None,
+            sequence_number: 0,
         }
     }
 
-    pub fn size(&self) -> Sectors {
-        self.size
+    pub fn set_size(&mut self, size: Sectors) {
+        self.size = size;
+    }
+
+    pub fn set_cow_alert_threshold(&mut self, threshold: Option<Bytes>) {
+        self.cow_alert_threshold = threshold;
+    }
+
+    pub fn set_min_free_guarantee(&mut self, guarantee: Option<Bytes>) {
+        self.min_free_guarantee = guarantee;
+    }
+
+    pub fn set_sequence_number(&mut self, sequence_number: u64) {
+        self.sequence_number = sequence_number;
     }
 }
 
@@ -43,6 +64,14 @@ impl Filesystem for SimFilesystem {
         self.created
     }
 
+    fn size(&self) -> Sectors {
+        self.size
+    }
+
+    fn is_mounted(&self) -> StratisResult<bool> {
+        Ok(false)
+    }
+
     fn path_to_mount_filesystem(&self, pool_name: &str, fs_name: &str) -> PathBuf {
         vec!["/somepath", pool_name, fs_name].iter().collect()
     }
@@ -50,6 +79,35 @@ impl Filesystem for SimFilesystem {
     fn used(&self) -> StratisResult<Bytes> {
         Ok((self.size / 2u64).bytes())
     }
+
+    fn io_stats(&self) -> StratisResult<FilesystemIoStats> {
+        Ok(FilesystemIoStats::default())
+    }
+
+    fn check_consistency(&self) -> StratisResult<ReadWork<FilesystemCheck>> {
+        Ok(Box::new(|_: &mut dyn FnMut(Sectors, Sectors)| {
+            Ok(FilesystemCheck {
+                consistent: true,
+                problems: Vec::new(),
+            })
+        }))
+    }
+
+    fn repair(&self) -> StratisResult<ReadWork<bool>> {
+        Ok(Box::new(|_: &mut dyn FnMut(Sectors, Sectors)| Ok(false)))
+    }
+
+    fn cow_alert_threshold(&self) -> Option<Bytes> {
+        self.cow_alert_threshold
+    }
+
+    fn min_free_guarantee(&self) -> Option<Bytes> {
+        self.min_free_guarantee
+    }
+
+    fn sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
 }
 
 impl<'a> Into<Value> for &'a SimFilesystem {
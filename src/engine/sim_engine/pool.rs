@@ -4,40 +4,75 @@
 
 use std::{
     borrow::Cow,
+    cmp::min,
     collections::{hash_map::RandomState, HashMap, HashSet},
-    iter::FromIterator,
-    path::Path,
+    iter::{once, FromIterator},
+    path::{Path, PathBuf},
+    time::Duration,
     vec::Vec,
 };
 
+use chrono::{DateTime, Utc};
 use serde_json::{Map, Value};
 
-use devicemapper::{Bytes, Sectors, IEC};
+use devicemapper::{Bytes, MetaBlocks, Sectors, IEC};
 
 use crate::{
     engine::{
         engine::{BlockDev, Filesystem, Pool},
         shared::{
-            init_cache_idempotent_or_err, validate_filesystem_size_specs, validate_name,
-            validate_paths,
+            filesystem_size_limits, init_cache_idempotent_or_err, recommended_max_filesystems,
+            supported_operations, thin_pool_limits, validate_alert_thresholds,
+            validate_auto_prune_threshold, validate_cache_block_size,
+            validate_filesystem_size_specs, validate_filesystem_target_size, validate_name,
+            validate_paths, validate_xfs_params, CapacityHistory, DATA_TO_META_RATIO,
+            DEFAULT_CACHE_BLOCK_SIZE, INITIAL_MDV_SIZE, LATENCY_HISTOGRAM_BOUNDARIES,
         },
         sim_engine::{blockdev::SimDev, filesystem::SimFilesystem},
         structures::Table,
         types::{
-            BlockDevTier, Clevis, CreateAction, DeleteAction, DevUuid, EncryptionInfo,
-            FilesystemUuid, Key, KeyDescription, Name, PoolUuid, Redundancy, RegenAction,
-            RenameAction, SetCreateAction, SetDeleteAction,
+            ActiveAlert, AlertMetric, AlertThresholds, AllocationStrategy, BlockDevTier,
+            CapacityEstimate, CapacitySample, Clevis, CreateAction, DeleteAction,
+            DeletedFilesystem, DevUuid, EncryptionInfo, FilesystemUuid, HealthCheck, Key,
+            KeyDescription, KeyslotUsage, LatencyStats, MetadataCheck, MetadataWriteFailurePolicy,
+            Name, OperationLogEntry, PlannedAction, PoolCapability, PoolEvent, PoolOperation,
+            PoolUuid, ReadWork, Reconciliation, ReconfigurationChanges, Redundancy,
+            RedundancyWarning, RegenAction, RenameAction, SetCreateAction, SetDeleteAction,
+            SmallDevicePolicy, StripeConfig, SurfaceTestResult, ThinPoolLimits, TierStructure,
+            XfsParams,
         },
     },
     stratis::{StratisError, StratisResult},
 };
 
+/// The maximum number of entries kept in the operation log.
+const OPERATION_LOG_MAX_ENTRIES: usize = 1024;
+
 #[derive(Debug)]
 pub struct SimPool {
     block_devs: HashMap<DevUuid, SimDev>,
     cache_devs: HashMap<DevUuid, SimDev>,
+    reserved_cache_devs: HashMap<DevUuid, SimDev>,
+    cache_block_size: Option<Sectors>,
     filesystems: Table<FilesystemUuid, SimFilesystem>,
+    deleted_filesystems: HashMap<FilesystemUuid, (Name, SimFilesystem, DateTime<Utc>)>,
+    fs_deletion_grace_period: Option<Duration>,
     redundancy: Redundancy,
+    metadata_write_failure_policy: MetadataWriteFailurePolicy,
+    small_device_policy: SmallDevicePolicy,
+    allocation_strategy: AllocationStrategy,
+    auto_start: bool,
+    detailed_stats: bool,
+    start_priority: Option<u32>,
+    operation_log: Vec<OperationLogEntry>,
+    capacity_history: CapacityHistory,
+    alert_thresholds: AlertThresholds,
+    stripe: Option<StripeConfig>,
+    auto_prune_snapshots_on_pressure: Option<u64>,
+    sequence_number: u64,
+    next_fs_sequence_number: u64,
+    pending_copy: Option<(Name, SimFilesystem)>,
+    moving_filesystems: HashSet<FilesystemUuid>,
 }
 
 impl SimPool {
@@ -45,24 +80,87 @@ impl SimPool {
         paths: &[&Path],
         redundancy: Redundancy,
         enc_info: &EncryptionInfo,
+        stripe: Option<StripeConfig>,
     ) -> (PoolUuid, SimPool) {
         let devices: HashSet<_, RandomState> = HashSet::from_iter(paths);
         let device_pairs = devices
             .iter()
             .map(|p| SimDev::new(p, Cow::Borrowed(enc_info)));
-        (
-            PoolUuid::new_v4(),
-            SimPool {
-                block_devs: device_pairs.collect(),
-                cache_devs: HashMap::new(),
-                filesystems: Table::default(),
-                redundancy,
-            },
-        )
+        let mut pool = SimPool {
+            block_devs: device_pairs.collect(),
+            cache_devs: HashMap::new(),
+            reserved_cache_devs: HashMap::new(),
+            cache_block_size: None,
+            filesystems: Table::default(),
+            deleted_filesystems: HashMap::new(),
+            fs_deletion_grace_period: None,
+            redundancy,
+            metadata_write_failure_policy: MetadataWriteFailurePolicy::default(),
+            small_device_policy: SmallDevicePolicy::default(),
+            allocation_strategy: AllocationStrategy::default(),
+            auto_start: true,
+            detailed_stats: false,
+            start_priority: None,
+            operation_log: Vec::new(),
+            capacity_history: CapacityHistory::default(),
+            alert_thresholds: AlertThresholds::default(),
+            stripe,
+            auto_prune_snapshots_on_pressure: None,
+            sequence_number: 0,
+            next_fs_sequence_number: 1,
+            pending_copy: None,
+            moving_filesystems: HashSet::new(),
+        };
+        pool.record_capacity_sample();
+        (PoolUuid::new_v4(), pool)
+    }
+
+    /// Set the sequence number of the pool. This is done once, when the
+    /// pool is created.
+    pub fn set_sequence_number(&mut self, sequence_number: u64) {
+        self.sequence_number = sequence_number;
+    }
+
+    /// Take the sequence number for a new filesystem.
+    fn new_fs_sequence_number(&mut self) -> u64 {
+        let sequence_number = self.next_fs_sequence_number;
+        self.next_fs_sequence_number += 1;
+        sequence_number
+    }
+
+    /// The time at which the deletion grace period of a filesystem deleted
+    /// at the given time is over.
+    fn purge_after(&self, deleted: DateTime<Utc>) -> DateTime<Utc> {
+        deleted
+            + self
+                .fs_deletion_grace_period
+                .and_then(|period| chrono::Duration::from_std(period).ok())
+                .unwrap_or_else(chrono::Duration::zero)
+    }
+
+    /// Whether the deletion grace period of a filesystem deleted at the
+    /// given time is over.
+    fn deletion_expired(&self, deleted: DateTime<Utc>) -> bool {
+        self.purge_after(deleted) <= Utc::now()
+    }
+
+    /// Drop the deleted filesystems whose grace period is over. Besides
+    /// being called periodically by the engine, this is done whenever
+    /// filesystems are deleted or undeleted.
+    pub fn purge_deleted_filesystems(&mut self) {
+        let expired = self
+            .deleted_filesystems
+            .iter()
+            .filter(|(_, (_, _, deleted))| self.deletion_expired(*deleted))
+            .map(|(uuid, _)| *uuid)
+            .collect::<Vec<_>>();
+        for uuid in expired {
+            self.deleted_filesystems.remove(&uuid);
+        }
     }
 
     pub fn has_filesystems(&self) -> bool {
-        !self.filesystems.is_empty()
+        !self.filesystems.is_empty() || self.pending_copy.is_some()
     }
 
     fn get_mut_blockdev_internal(&mut self, uuid: DevUuid) -> Option<(BlockDevTier, &mut SimDev)> {
@@ -77,6 +175,39 @@ impl SimPool {
             })
     }
 
+    fn log_operation(&mut self, operation: String) {
+        self.operation_log.push(OperationLogEntry {
+            timestamp: Utc::now(),
+            operation,
+        });
+        if self.operation_log.len() > OPERATION_LOG_MAX_ENTRIES {
+            let excess = self.operation_log.len() - OPERATION_LOG_MAX_ENTRIES;
+            self.operation_log.drain(..excess);
+        }
+    }
+
+    /// Return an error if the filesystem with the given UUID is being moved
+    /// to another pool.
+    fn check_filesystem_not_moving(&self, uuid: FilesystemUuid, action: &str) -> StratisResult<()> {
+        if self.moving_filesystems.contains(&uuid) {
+            return Err(StratisError::Msg(format!(
+                "Can not {} with UUID {} while it is being moved to another pool",
+                action, uuid
+            )));
+        }
+        Ok(())
+    }
+
+    /// The sim engine does not monitor its pools, so a sample is taken
+    /// whenever the capacity of the pool changes.
+    fn record_capacity_sample(&mut self) {
+        let used = self
+            .total_physical_used()
+            .expect("the sim engine can always compute the used space");
+        let total = self.total_physical_size();
+        self.capacity_history.record(used, total);
+    }
+
     fn datadevs_encrypted(&self) -> bool {
         self.encryption_info().is_encrypted()
     }
@@ -179,8 +310,12 @@ impl Pool for SimPool {
         _pool_uuid: PoolUuid,
         _pool_name: &str,
         blockdevs: &[&Path],
+        cache_block_size: Option<Sectors>,
     ) -> StratisResult<SetCreateAction<DevUuid>> {
         validate_paths(blockdevs)?;
+        if let Some(block_size) = cache_block_size {
+            validate_cache_block_size(block_size)?;
+        }
 
         if self.is_encrypted() {
             return Err(StratisError::Msg(
@@ -188,17 +323,25 @@ impl Pool for SimPool {
             ));
         }
         if !self.has_cache() {
-            if blockdevs.is_empty() {
+            if blockdevs.is_empty() && self.reserved_cache_devs.is_empty() {
                 return Err(StratisError::Msg(
                     "At least one blockdev path is required to initialize a cache.".to_string(),
                 ));
             }
+            let reserved: Vec<_> = self
+                .reserved_cache_devs
+                .values()
+                .map(|d| d.devnode().to_owned())
+                .collect();
             let blockdev_pairs: Vec<_> = blockdevs
                 .iter()
+                .filter(|p| !reserved.iter().any(|r| r.as_path() == **p))
                 .map(|p| SimDev::new(p, Cow::Owned(EncryptionInfo::default())))
+                .chain(self.reserved_cache_devs.drain())
                 .collect();
             let blockdev_uuids: Vec<_> = blockdev_pairs.iter().map(|(uuid, _)| *uuid).collect();
             self.cache_devs.extend(blockdev_pairs);
+            self.cache_block_size = Some(cache_block_size.unwrap_or(DEFAULT_CACHE_BLOCK_SIZE));
             Ok(SetCreateAction::new(blockdev_uuids))
         } else {
             init_cache_idempotent_or_err(
@@ -210,6 +353,81 @@ impl Pool for SimPool {
         }
     }
 
+    fn remove_cache(
+        &mut self,
+        _pool_uuid: PoolUuid,
+        _pool_name: &str,
+    ) -> StratisResult<SetDeleteAction<DevUuid>> {
+        let uuids = self
+            .cache_devs
+            .drain()
+            .map(|(uuid, _)| uuid)
+            .collect::<Vec<_>>();
+        if !uuids.is_empty() {
+            self.cache_block_size = None;
+            self.log_operation("remove cache".to_string());
+        }
+        Ok(SetDeleteAction::new(uuids))
+    }
+
+    fn reserve_cache_devices(
+        &mut self,
+        _pool_uuid: PoolUuid,
+        _pool_name: &str,
+        blockdevs: &[&Path],
+    ) -> StratisResult<SetCreateAction<DevUuid>> {
+        validate_paths(blockdevs)?;
+
+        if self.is_encrypted() {
+            return Err(StratisError::Msg(
+                "Use of a cache is not supported with an encrypted pool".to_string(),
+            ));
+        }
+        if self.has_cache() {
+            return Err(StratisError::Msg(
+                "The cache has already been initialized; you must use add_blockdevs to add devices to it.".to_string(),
+            ));
+        }
+
+        let devices: HashSet<_, RandomState> = HashSet::from_iter(blockdevs);
+        let filter: Vec<_> = self
+            .reserved_cache_devs
+            .values()
+            .map(|d| d.devnode())
+            .collect();
+        let filtered_device_pairs: Vec<_> = devices
+            .iter()
+            .map(|p| SimDev::new(p, Cow::Owned(EncryptionInfo::default())))
+            .filter(|(_, sd)| !filter.contains(&sd.devnode()))
+            .collect();
+
+        if filtered_device_pairs.is_empty() {
+            return Ok(SetCreateAction::empty());
+        }
+
+        let ret_uuids = filtered_device_pairs
+            .iter()
+            .map(|&(uuid, _)| uuid)
+            .collect();
+        self.reserved_cache_devs.extend(filtered_device_pairs);
+        self.log_operation(format!(
+            "reserve cache devices {}",
+            blockdevs
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        Ok(SetCreateAction::new(ret_uuids))
+    }
+
+    fn reserved_cache_devices(&self) -> Vec<(DevUuid, PathBuf)> {
+        self.reserved_cache_devs
+            .iter()
+            .map(|(uuid, d)| (*uuid, d.devnode().to_owned()))
+            .collect()
+    }
+
     fn create_filesystems<'a, 'b>(
         &'a mut self,
         _pool_name: &str,
@@ -242,9 +460,14 @@ impl Pool for SimPool {
         for (name, size) in spec_map {
             if !self.filesystems.contains_name(name) {
                 let uuid = FilesystemUuid::new_v4();
-                let new_filesystem = SimFilesystem::new(size);
+                let mut new_filesystem = SimFilesystem::new(size);
+                new_filesystem.set_sequence_number(self.new_fs_sequence_number());
                 self.filesystems
                     .insert(Name::new((name).to_owned()), uuid, new_filesystem);
+                self.log_operation(format!(
+                    "create filesystem {} with UUID {} and size {}",
+                    name, uuid, size
+                ));
                 result.push((name, uuid, size));
             }
         }
@@ -252,6 +475,182 @@ impl Pool for SimPool {
         Ok(SetCreateAction::new(result))
     }
 
+    fn create_filesystem_with_params<'a>(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        name: &'a str,
+        size: Option<Bytes>,
+        mkfs_params: &XfsParams,
+    ) -> StratisResult<SetCreateAction<(&'a str, FilesystemUuid, Sectors)>> {
+        validate_xfs_params(mkfs_params)?;
+        self.create_filesystems(pool_name, pool_uuid, &[(name, size)])
+    }
+
+    fn create_filesystem_on_devices<'a>(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        name: &'a str,
+        size: Option<Bytes>,
+        devices: &[DevUuid],
+    ) -> StratisResult<SetCreateAction<(&'a str, FilesystemUuid, Sectors)>> {
+        if let Some(uuid) = devices
+            .iter()
+            .find(|uuid| !self.block_devs.contains_key(uuid))
+        {
+            return Err(StratisError::Msg(format!(
+                "Blockdev {} does not belong to the data tier",
+                uuid
+            )));
+        }
+        self.create_filesystems(pool_name, pool_uuid, &[(name, size)])
+    }
+
+    fn explain_create_filesystem(
+        &self,
+        _pool_uuid: PoolUuid,
+        name: &str,
+        size: Option<Bytes>,
+        mkfs_params: &XfsParams,
+    ) -> StratisResult<Vec<PlannedAction>> {
+        validate_xfs_params(mkfs_params)?;
+        validate_filesystem_size_specs(&[(name, size)])?;
+        validate_name(name)?;
+        // The sim engine runs no commands.
+        Ok(Vec::new())
+    }
+
+    fn filesystem_size_limits(&self) -> (Sectors, Sectors) {
+        filesystem_size_limits()
+    }
+
+    fn thin_pool_limits(&self) -> ThinPoolLimits {
+        let data_size = self
+            .block_devs
+            .values()
+            .map(|bd| bd.size())
+            .sum::<Sectors>();
+        let limits = thin_pool_limits(data_size, Sectors(0));
+        ThinPoolLimits {
+            meta_size: min(data_size / DATA_TO_META_RATIO, limits.max_meta_size),
+            ..limits
+        }
+    }
+
+    fn sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
+
+    fn recommended_max_filesystems(&self) -> StratisResult<u64> {
+        // The filesystems of the sim engine take up no thin pool metadata.
+        Ok(recommended_max_filesystems(
+            INITIAL_MDV_SIZE,
+            MetaBlocks(0),
+            self.thin_pool_limits().meta_size.metablocks(),
+            self.filesystems.len() as u64,
+        ))
+    }
+
+    fn stripe_config(&self) -> Option<StripeConfig> {
+        self.stripe
+    }
+
+    fn filesystem_mapped_ranges(
+        &self,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<Vec<(Sectors, Sectors)>> {
+        self.filesystems
+            .get_by_uuid(uuid)
+            .ok_or_else(|| StratisError::Msg(format!("Filesystem with UUID {} not found", uuid)))?;
+        Ok(Vec::new())
+    }
+
+    fn start_copy_filesystem_from(
+        &mut self,
+        pool_name: &str,
+        _pool_uuid: PoolUuid,
+        name: &str,
+        source: &dyn Filesystem,
+        _source_ranges: Vec<(Sectors, Sectors)>,
+    ) -> StratisResult<ReadWork<()>> {
+        validate_name(name)?;
+
+        if self.pending_copy.is_some() {
+            return Err(StratisError::Msg(format!(
+                "Can not copy a filesystem while operation {} is in progress on the pool",
+                PoolOperation::CopyFilesystem
+            )));
+        }
+        if self.filesystems.contains_name(name) {
+            return Err(StratisError::Msg(format!(
+                "A filesystem named {} already exists in pool {}",
+                name, pool_name
+            )));
+        }
+        if source.is_mounted()? {
+            return Err(StratisError::Msg(
+                "The filesystem to be copied is mounted; it must be unmounted".to_string(),
+            ));
+        }
+
+        let size = source.size();
+        self.pending_copy = Some((Name::new(name.to_owned()), SimFilesystem::new(size)));
+        Ok(Box::new(
+            move |progress: &mut dyn FnMut(Sectors, Sectors)| {
+                if size > Sectors(0) {
+                    progress(size, size);
+                }
+                Ok(())
+            },
+        ))
+    }
+
+    fn finish_copy_filesystem_from(
+        &mut self,
+        pool_name: &str,
+        copied: StratisResult<()>,
+    ) -> StratisResult<FilesystemUuid> {
+        let (name, mut new_filesystem) = self.pending_copy.take().ok_or_else(|| {
+            StratisError::Msg("No filesystem is being copied into the pool".into())
+        })?;
+        copied?;
+        if self.filesystems.contains_name(&name) {
+            return Err(StratisError::Msg(format!(
+                "A filesystem named {} was created in pool {} while the copy was in progress",
+                name, pool_name
+            )));
+        }
+
+        let uuid = FilesystemUuid::new_v4();
+        new_filesystem.set_sequence_number(self.new_fs_sequence_number());
+        self.log_operation(format!(
+            "copy filesystem {} into pool as filesystem with UUID {}",
+            name, uuid
+        ));
+        self.filesystems.insert(name, uuid, new_filesystem);
+        Ok(uuid)
+    }
+
+    fn set_filesystem_moving(&mut self, uuid: FilesystemUuid, moving: bool) {
+        if moving {
+            self.moving_filesystems.insert(uuid);
+        } else {
+            self.moving_filesystems.remove(&uuid);
+        }
+    }
+
+    fn add_datadevs_with_override(
+        &mut self,
+        pool_uuid: PoolUuid,
+        pool_name: &str,
+        paths: &[&Path],
+    ) -> StratisResult<SetCreateAction<DevUuid>> {
+        // All simulated devices have the same size, so none of them is ever
+        // smaller than the existing ones.
+        self.add_blockdevs(pool_uuid, pool_name, paths, BlockDevTier::Data)
+    }
+
     fn add_blockdevs(
         &mut self,
         _pool_uuid: PoolUuid,
@@ -306,9 +705,47 @@ impl Pool for SimPool {
         };
 
         the_vec.extend(filtered_device_pairs);
+        self.log_operation(format!(
+            "add {} devices {}",
+            match tier {
+                BlockDevTier::Data => "data",
+                BlockDevTier::Cache => "cache",
+            },
+            paths
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        self.record_capacity_sample();
         Ok(SetCreateAction::new(ret_uuids))
     }
 
+    fn shrink_by_device(
+        &mut self,
+        _pool_name: &str,
+        dev_uuid: DevUuid,
+    ) -> StratisResult<DeleteAction<DevUuid>> {
+        if self.cache_devs.contains_key(&dev_uuid) {
+            return Err(StratisError::Msg(format!(
+                "Blockdev {} is a cache device; only data devices can be removed",
+                dev_uuid
+            )));
+        }
+        if !self.block_devs.contains_key(&dev_uuid) {
+            return Ok(DeleteAction::Identity);
+        }
+        if self.block_devs.len() <= 1 {
+            return Err(StratisError::Msg(format!(
+                "Blockdev {} is the only data device in the pool and can not be removed",
+                dev_uuid
+            )));
+        }
+        self.block_devs.remove(&dev_uuid);
+        self.log_operation(format!("remove data device with UUID {}", dev_uuid));
+        Ok(DeleteAction::Deleted(dev_uuid))
+    }
+
     fn bind_clevis(
         &mut self,
         pin: &str,
@@ -433,6 +870,10 @@ impl Pool for SimPool {
             Ok(
                 if encryption_info.key_description.as_ref() != Some(new_key_desc) {
                     self.add_key_desc(new_key_desc);
+                    self.log_operation(format!(
+                        "rebind keyring with key description {}",
+                        new_key_desc.as_application_str()
+                    ));
                     RenameAction::Renamed(Key)
                 } else {
                     RenameAction::Identity
@@ -476,12 +917,22 @@ impl Pool for SimPool {
         _pool_name: &str,
         fs_uuids: &[FilesystemUuid],
     ) -> StratisResult<SetDeleteAction<FilesystemUuid>> {
+        for &uuid in fs_uuids {
+            self.check_filesystem_not_moving(uuid, "destroy filesystem")?;
+        }
+
         let mut removed = Vec::new();
         for &uuid in fs_uuids {
-            if self.filesystems.remove_by_uuid(uuid).is_some() {
+            if let Some((name, fs)) = self.filesystems.remove_by_uuid(uuid) {
+                if self.fs_deletion_grace_period.is_some() {
+                    self.deleted_filesystems
+                        .insert(uuid, (name, fs, Utc::now()));
+                }
+                self.log_operation(format!("destroy filesystem with UUID {}", uuid));
                 removed.push(uuid);
             }
         }
+        self.purge_deleted_filesystems();
         Ok(SetDeleteAction::new(removed))
     }
 
@@ -506,6 +957,32 @@ impl Pool for SimPool {
         Ok(RenameAction::Renamed(uuid))
     }
 
+    fn set_filesystem_size(
+        &mut self,
+        _pool_name: &str,
+        uuid: FilesystemUuid,
+        target_size: Sectors,
+        _grow_fs: bool,
+    ) -> StratisResult<bool> {
+        self.check_filesystem_not_moving(uuid, "resize filesystem")?;
+
+        let (_, filesystem) = self.filesystems.get_mut_by_uuid(uuid).ok_or_else(|| {
+            StratisError::Msg(format!("Filesystem with UUID {} does not exist", uuid))
+        })?;
+        let current_size = filesystem.size();
+        validate_filesystem_target_size(current_size, target_size)?;
+
+        if current_size == target_size {
+            return Ok(false);
+        }
+        filesystem.set_size(target_size);
+        self.log_operation(format!(
+            "resize filesystem with UUID {} from {} to {}",
+            uuid, current_size, target_size
+        ));
+        Ok(true)
+    }
+
     fn snapshot_filesystem(
         &mut self,
         _pool_name: &str,
@@ -514,10 +991,11 @@ impl Pool for SimPool {
         snapshot_name: &str,
     ) -> StratisResult<CreateAction<(FilesystemUuid, &mut dyn Filesystem)>> {
         validate_name(snapshot_name)?;
+        self.check_filesystem_not_moving(origin_uuid, "snapshot filesystem")?;
 
         let target = self.filesystems.get_by_name(snapshot_name);
 
-        let snapshot = match self.filesystems.get_by_uuid(origin_uuid) {
+        let mut snapshot = match self.filesystems.get_by_uuid(origin_uuid) {
             Some((_, filesystem)) => {
                 if let Some((_, target_fs)) = target {
                     if target_fs.size() != filesystem.size() {
@@ -536,6 +1014,7 @@ impl Pool for SimPool {
                 return Err(StratisError::Msg(origin_uuid.to_string()));
             }
         };
+        snapshot.set_sequence_number(self.new_fs_sequence_number());
 
         let uuid = FilesystemUuid::new_v4();
         self.filesystems
@@ -572,12 +1051,325 @@ impl Pool for SimPool {
             .map(|(name, p)| (name, p as &dyn Filesystem))
     }
 
+    fn scrub_filesystem(
+        &self,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<ReadWork<Vec<(Sectors, Sectors)>>> {
+        self.filesystems
+            .get_by_uuid(uuid)
+            .ok_or_else(|| StratisError::Msg(format!("Filesystem with UUID {} not found", uuid)))?;
+        Ok(Box::new(|_: &mut dyn FnMut(Sectors, Sectors)| {
+            Ok(Vec::new())
+        }))
+    }
+
+    fn warm_cache(
+        &self,
+        uuid: FilesystemUuid,
+        budget: Option<Sectors>,
+    ) -> StratisResult<ReadWork<Sectors>> {
+        if !self.has_cache() {
+            return Err(StratisError::Msg(
+                "The pool has no cache to warm".to_string(),
+            ));
+        }
+        let cache_size = self
+            .cache_devs
+            .values()
+            .map(|bd| bd.size())
+            .sum::<Sectors>();
+        let (_, fs) = self
+            .filesystems
+            .get_by_uuid(uuid)
+            .ok_or_else(|| StratisError::Msg(format!("Filesystem with UUID {} not found", uuid)))?;
+        let total = min(
+            budget.map_or(cache_size, |budget| min(budget, cache_size)),
+            fs.size(),
+        );
+        Ok(Box::new(
+            move |progress: &mut dyn FnMut(Sectors, Sectors)| {
+                if total > Sectors(0) {
+                    progress(total, total);
+                }
+                Ok(total)
+            },
+        ))
+    }
+
+    fn start_flush_cache(&mut self) -> StratisResult<ReadWork<Sectors>> {
+        if !self.has_cache() {
+            return Err(StratisError::Msg(
+                "The pool has no cache to flush".to_string(),
+            ));
+        }
+        // The simulated cache never holds dirty blocks.
+        Ok(Box::new(|_| Ok(Sectors(0))))
+    }
+
+    fn finish_flush_cache(&mut self, flushed: StratisResult<Sectors>) -> StratisResult<Sectors> {
+        flushed
+    }
+
+    fn relocate_metadata(
+        &mut self,
+        _pool_name: &str,
+        _pool_uuid: PoolUuid,
+        devices: &[DevUuid],
+        _progress: &mut dyn FnMut(Sectors, Sectors),
+    ) -> StratisResult<bool> {
+        if devices.is_empty() {
+            return Err(StratisError::Msg(
+                "At least one data device must be given to relocate the metadata to".to_string(),
+            ));
+        }
+        if let Some(uuid) = devices
+            .iter()
+            .find(|uuid| !self.block_devs.contains_key(uuid))
+        {
+            return Err(StratisError::Msg(format!(
+                "Blockdev {} does not belong to the data tier",
+                uuid
+            )));
+        }
+        // The sim engine keeps no metadata device that could be moved.
+        Ok(false)
+    }
+
+    fn compact_metadata(&mut self) -> StratisResult<u64> {
+        // The sim engine keeps no metadata that could hold stale entries.
+        Ok(0)
+    }
+
+    fn verify_metadata_consistency(&self, _pool_name: &str) -> StratisResult<MetadataCheck> {
+        // The sim engine keeps no copies of its metadata that could diverge.
+        Ok(MetadataCheck {
+            consistent: true,
+            problems: Vec::new(),
+        })
+    }
+
+    fn device_health_checks(&self) -> Vec<HealthCheck> {
+        // The devices and the thin pool of the sim engine are imaginary and
+        // can not fail.
+        vec![
+            HealthCheck {
+                name: "devices".to_string(),
+                problems: Vec::new(),
+            },
+            HealthCheck {
+                name: "thin_pool".to_string(),
+                problems: Vec::new(),
+            },
+        ]
+    }
+
+    fn surface_test(&self) -> StratisResult<Vec<SurfaceTestResult>> {
+        // The devices of the sim engine are imaginary and store whatever is
+        // written to them.
+        Ok(self
+            .block_devs
+            .keys()
+            .chain(self.cache_devs.keys())
+            .map(|dev_uuid| SurfaceTestResult {
+                dev_uuid: *dev_uuid,
+                problem: None,
+            })
+            .collect())
+    }
+
+    fn redundancy_warnings(&self) -> Vec<RedundancyWarning> {
+        // The devices of the sim engine are imaginary and share no disk.
+        Vec::new()
+    }
+
+    fn reconcile(
+        &mut self,
+        _pool_name: &str,
+        _pool_uuid: PoolUuid,
+    ) -> StratisResult<Reconciliation> {
+        // The sim engine keeps no metadata apart from its state in memory.
+        Ok(Reconciliation::default())
+    }
+
+    fn simulate_reconfiguration(
+        &self,
+        changes: &ReconfigurationChanges,
+    ) -> StratisResult<CapacityEstimate> {
+        for dev_uuid in changes.remove_data_devices.iter() {
+            if !self.block_devs.contains_key(dev_uuid) {
+                return Err(StratisError::Msg(format!(
+                    "Pool has no data blockdev with UUID {}",
+                    dev_uuid
+                )));
+            }
+        }
+        let paths = changes
+            .add_data_devices
+            .iter()
+            .map(|p| p.as_path())
+            .collect::<Vec<_>>();
+        validate_paths(&paths)?;
+
+        let kept = self
+            .block_devs
+            .iter()
+            .filter(|(uuid, _)| !changes.remove_data_devices.contains(uuid))
+            .map(|(_, bd)| bd)
+            .collect::<Vec<_>>();
+        let added = paths
+            .iter()
+            .filter(|p| !kept.iter().any(|bd| bd.devnode() == **p))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|p| SimDev::new(p, self.encryption_info()).1)
+            .collect::<Vec<_>>();
+        if kept.is_empty() && added.is_empty() {
+            return Err(StratisError::Msg(
+                "The pool must retain at least one data device".into(),
+            ));
+        }
+
+        let total = kept
+            .iter()
+            .map(|bd| bd.size())
+            .chain(added.iter().map(|bd| bd.size()))
+            .sum::<Sectors>();
+        let limits = thin_pool_limits(total, Sectors(0));
+        // The thin pool meta device and its spare
+        let overhead = min(total / DATA_TO_META_RATIO, limits.max_meta_size) * 2u64;
+        Ok(CapacityEstimate {
+            total,
+            overhead,
+            usable: total - overhead,
+        })
+    }
+
     fn get_filesystem_by_name(&self, name: &Name) -> Option<(FilesystemUuid, &dyn Filesystem)> {
         self.filesystems
             .get_by_name(name)
             .map(|(uuid, p)| (uuid, p as &dyn Filesystem))
     }
 
+    fn structure(&self) -> Vec<TierStructure> {
+        let total_size = |devs: &HashMap<DevUuid, SimDev>| {
+            devs.values().map(|dev| dev.size()).sum::<Sectors>().bytes()
+        };
+
+        let mut tiers = vec![TierStructure {
+            tier: BlockDevTier::Data,
+            device_count: self.block_devs.len(),
+            total_size: total_size(&self.block_devs),
+            // The filesystems of the sim engine store no data.
+            used_size: Bytes(0),
+            redundancy: Redundancy::NONE,
+            degraded: false,
+        }];
+        if !self.cache_devs.is_empty() {
+            // All of a cache device is allocated to the cache.
+            let cache_size = total_size(&self.cache_devs);
+            tiers.push(TierStructure {
+                tier: BlockDevTier::Cache,
+                device_count: self.cache_devs.len(),
+                total_size: cache_size,
+                used_size: cache_size,
+                redundancy: Redundancy::NONE,
+                degraded: false,
+            });
+        }
+        tiers
+    }
+
+    fn filesystems_on_device(
+        &self,
+        dev_uuid: DevUuid,
+    ) -> StratisResult<Vec<(FilesystemUuid, Bytes)>> {
+        if self.block_devs.contains_key(&dev_uuid) {
+            // The filesystems of the sim engine store no data.
+            Ok(Vec::new())
+        } else if self.cache_devs.contains_key(&dev_uuid) {
+            Err(StratisError::Msg(format!(
+                "Blockdev {} is a cache device; filesystem data is only stored on data devices",
+                dev_uuid
+            )))
+        } else {
+            Err(StratisError::Msg(format!(
+                "Blockdev {} does not belong to the pool",
+                dev_uuid
+            )))
+        }
+    }
+
+    fn filesystems_pending_grow(&self) -> StratisResult<Vec<(FilesystemUuid, Bytes)>> {
+        // The filesystems of the sim engine always fill their devices.
+        Ok(Vec::new())
+    }
+
+    fn filesystem_shared_size(&self, uuid: FilesystemUuid) -> StratisResult<(Bytes, Bytes)> {
+        if self.filesystems.get_by_uuid(uuid).is_none() {
+            return Err(StratisError::Msg(format!(
+                "Filesystem with UUID {} not found",
+                uuid
+            )));
+        }
+        // The filesystems of the sim engine store no data.
+        Ok((Bytes(0), Bytes(0)))
+    }
+
+    fn set_fs_cow_alert_threshold(
+        &mut self,
+        _pool_name: &str,
+        uuid: FilesystemUuid,
+        threshold: Option<Bytes>,
+    ) -> StratisResult<bool> {
+        let (_, filesystem) = self.filesystems.get_mut_by_uuid(uuid).ok_or_else(|| {
+            StratisError::Msg(format!("Filesystem with UUID {} does not exist", uuid))
+        })?;
+        if filesystem.cow_alert_threshold() == threshold {
+            return Ok(false);
+        }
+        filesystem.set_cow_alert_threshold(threshold);
+        // The filesystems of the sim engine store no data, so the alert is
+        // never raised.
+        self.log_operation(format!(
+            "set copy-on-write alert threshold of filesystem with UUID {} to {}",
+            uuid,
+            threshold.map_or_else(|| "none".to_string(), |t| t.to_string())
+        ));
+        Ok(true)
+    }
+
+    fn set_fs_min_free_guarantee(
+        &mut self,
+        _pool_name: &str,
+        uuid: FilesystemUuid,
+        guarantee: Option<Bytes>,
+    ) -> StratisResult<bool> {
+        let (fs_name, filesystem) = self.filesystems.get_mut_by_uuid(uuid).ok_or_else(|| {
+            StratisError::Msg(format!("Filesystem with UUID {} does not exist", uuid))
+        })?;
+        if filesystem.min_free_guarantee() == guarantee {
+            return Ok(false);
+        }
+        if let Some(guarantee) = guarantee {
+            let size = filesystem.size().bytes();
+            if guarantee > size {
+                return Err(StratisError::Msg(format!(
+                    "The guarantee of {} exceeds the size {} of filesystem {}",
+                    guarantee, size, fs_name
+                )));
+            }
+        }
+        // The filesystems of the sim engine store no data, so the free space
+        // of the pool always honors the guarantee.
+        filesystem.set_min_free_guarantee(guarantee);
+        self.log_operation(format!(
+            "set minimum free space guarantee of filesystem with UUID {} to {}",
+            uuid,
+            guarantee.map_or_else(|| "none".to_string(), |g| g.to_string())
+        ));
+        Ok(true)
+    }
+
     fn blockdevs(&self) -> Vec<(DevUuid, BlockDevTier, &dyn BlockDev)> {
         self.block_devs
             .iter()
@@ -620,9 +1412,40 @@ impl Pool for SimPool {
         ))
     }
 
-    fn has_cache(&self) -> bool {
-        !self.cache_devs.is_empty()
-    }
+    fn device_write_cache_enabled(&self, uuid: DevUuid) -> StratisResult<bool> {
+        self.block_devs
+            .get(&uuid)
+            .or_else(|| self.cache_devs.get(&uuid))
+            .map(|bd| bd.write_cache_enabled())
+            .ok_or_else(|| StratisError::Msg(format!("Blockdev with UUID {} not found", uuid)))
+    }
+
+    fn device_in_flight_io(&self, uuid: DevUuid) -> StratisResult<u64> {
+        self.block_devs
+            .get(&uuid)
+            .or_else(|| self.cache_devs.get(&uuid))
+            .map(|_| 0)
+            .ok_or_else(|| StratisError::Msg(format!("Blockdev with UUID {} not found", uuid)))
+    }
+
+    fn disable_device_write_cache(&mut self, uuid: DevUuid) -> StratisResult<bool> {
+        let changed = self
+            .get_mut_blockdev_internal(uuid)
+            .map(|(_, bd)| bd.disable_write_cache())
+            .ok_or_else(|| StratisError::Msg(format!("Blockdev with UUID {} not found", uuid)))?;
+        if changed {
+            self.log_operation(format!("disable write cache of device with UUID {}", uuid));
+        }
+        Ok(changed)
+    }
+
+    fn cache_block_size(&self) -> Option<Sectors> {
+        self.cache_block_size
+    }
+
+    fn has_cache(&self) -> bool {
+        !self.cache_devs.is_empty()
+    }
 
     fn is_encrypted(&self) -> bool {
         self.datadevs_encrypted()
@@ -631,6 +1454,304 @@ impl Pool for SimPool {
     fn encryption_info(&self) -> Cow<EncryptionInfo> {
         Cow::Borrowed(self.encryption_info_impl())
     }
+
+    fn keyslot_usage(&self) -> StratisResult<KeyslotUsage> {
+        if !self.is_encrypted() {
+            return Err(StratisError::Msg(
+                "Requested pool does not appear to be encrypted".to_string(),
+            ));
+        }
+        let info = self.encryption_info_impl();
+        Ok(KeyslotUsage {
+            used: info.key_description.is_some() as u32 + info.clevis_info.is_some() as u32,
+            total: 32,
+        })
+    }
+
+    fn operation_in_progress(&self) -> Option<PoolOperation> {
+        self.pending_copy
+            .as_ref()
+            .map(|_| PoolOperation::CopyFilesystem)
+    }
+
+    fn supported_operations(&self) -> Vec<PoolCapability> {
+        supported_operations(self, false)
+    }
+
+    fn metadata_write_failure_policy(&self) -> MetadataWriteFailurePolicy {
+        self.metadata_write_failure_policy
+    }
+
+    fn set_metadata_write_failure_policy(
+        &mut self,
+        _pool_name: &str,
+        policy: MetadataWriteFailurePolicy,
+    ) -> StratisResult<bool> {
+        if self.metadata_write_failure_policy == policy {
+            Ok(false)
+        } else {
+            self.metadata_write_failure_policy = policy;
+            Ok(true)
+        }
+    }
+
+    fn small_device_policy(&self) -> SmallDevicePolicy {
+        self.small_device_policy
+    }
+
+    fn set_small_device_policy(
+        &mut self,
+        _pool_name: &str,
+        policy: SmallDevicePolicy,
+    ) -> StratisResult<bool> {
+        if self.small_device_policy == policy {
+            Ok(false)
+        } else {
+            self.small_device_policy = policy;
+            Ok(true)
+        }
+    }
+
+    fn allocation_strategy(&self) -> AllocationStrategy {
+        self.allocation_strategy
+    }
+
+    fn set_allocation_strategy(
+        &mut self,
+        _pool_name: &str,
+        strategy: AllocationStrategy,
+    ) -> StratisResult<bool> {
+        if self.allocation_strategy == strategy {
+            Ok(false)
+        } else {
+            self.allocation_strategy = strategy;
+            Ok(true)
+        }
+    }
+
+    fn auto_start(&self) -> bool {
+        self.auto_start
+    }
+
+    fn set_auto_start(&mut self, _pool_name: &str, auto_start: bool) -> StratisResult<bool> {
+        if self.auto_start == auto_start {
+            Ok(false)
+        } else {
+            self.auto_start = auto_start;
+            Ok(true)
+        }
+    }
+
+    fn start_priority(&self) -> Option<u32> {
+        self.start_priority
+    }
+
+    fn set_start_priority(
+        &mut self,
+        _pool_name: &str,
+        start_priority: Option<u32>,
+    ) -> StratisResult<bool> {
+        if self.start_priority == start_priority {
+            Ok(false)
+        } else {
+            self.start_priority = start_priority;
+            Ok(true)
+        }
+    }
+
+    fn detailed_stats(&self) -> bool {
+        self.detailed_stats
+    }
+
+    fn set_detailed_stats(&mut self, _pool_name: &str, enabled: bool) -> StratisResult<bool> {
+        if self.detailed_stats == enabled {
+            Ok(false)
+        } else {
+            self.detailed_stats = enabled;
+            Ok(true)
+        }
+    }
+
+    fn filesystem_latency_histogram(&self, uuid: FilesystemUuid) -> StratisResult<Vec<(u64, u64)>> {
+        if !self.detailed_stats {
+            return Err(StratisError::Msg(
+                "Detailed statistics are not enabled for the pool".to_string(),
+            ));
+        }
+        if self.filesystems.get_by_uuid(uuid).is_none() {
+            return Err(StratisError::Msg(format!(
+                "Filesystem with UUID {} not found",
+                uuid
+            )));
+        }
+        Ok(once(0)
+            .chain(LATENCY_HISTOGRAM_BOUNDARIES.iter().copied())
+            .map(|bound| (bound, 0))
+            .collect())
+    }
+
+    fn latency_stats(&self) -> StratisResult<LatencyStats> {
+        if !self.detailed_stats {
+            return Err(StratisError::Msg(
+                "Detailed statistics are not enabled for the pool".to_string(),
+            ));
+        }
+        Ok(LatencyStats::default())
+    }
+
+    fn drain_events(&mut self) -> Vec<PoolEvent> {
+        Vec::new()
+    }
+
+    fn operation_log(&self, limit: Option<usize>) -> StratisResult<Vec<OperationLogEntry>> {
+        let skip = limit.map_or(0, |limit| self.operation_log.len().saturating_sub(limit));
+        Ok(self.operation_log.iter().skip(skip).cloned().collect())
+    }
+
+    fn capacity_history(&self, since: Option<DateTime<Utc>>) -> Vec<CapacitySample> {
+        self.capacity_history.samples(since)
+    }
+
+    fn alert_thresholds(&self) -> AlertThresholds {
+        self.alert_thresholds
+    }
+
+    /// The sim engine does not monitor its pools, so no alerts are raised.
+    fn set_alert_thresholds(
+        &mut self,
+        _pool_name: &str,
+        thresholds: AlertThresholds,
+    ) -> StratisResult<bool> {
+        validate_alert_thresholds(&thresholds)?;
+        if self.alert_thresholds == thresholds {
+            Ok(false)
+        } else {
+            self.alert_thresholds = thresholds;
+            Ok(true)
+        }
+    }
+
+    fn active_alerts(&self) -> Vec<ActiveAlert> {
+        Vec::new()
+    }
+
+    fn metadata_undersized(&self) -> Option<(Bytes, Bytes)> {
+        // The sim engine has no metadata device.
+        None
+    }
+
+    fn acknowledge_alert(&mut self, metric: AlertMetric) -> StratisResult<bool> {
+        Err(StratisError::Msg(format!(
+            "There is no active alert on metric {}",
+            metric
+        )))
+    }
+
+    fn reset_transient_state(
+        &mut self,
+        _pool_name: &str,
+        _pool_uuid: PoolUuid,
+    ) -> StratisResult<bool> {
+        // The sim engine has no DM devices and no transient state.
+        Ok(false)
+    }
+
+    fn auto_prune_snapshots_on_pressure(&self) -> Option<u64> {
+        self.auto_prune_snapshots_on_pressure
+    }
+
+    fn set_auto_prune_snapshots_on_pressure(
+        &mut self,
+        _pool_name: &str,
+        threshold: Option<u64>,
+    ) -> StratisResult<bool> {
+        validate_auto_prune_threshold(threshold)?;
+        if self.auto_prune_snapshots_on_pressure == threshold {
+            Ok(false)
+        } else {
+            self.auto_prune_snapshots_on_pressure = threshold;
+            Ok(true)
+        }
+    }
+
+    fn fs_deletion_grace_period(&self) -> Option<Duration> {
+        self.fs_deletion_grace_period
+    }
+
+    fn set_fs_deletion_grace_period(
+        &mut self,
+        _pool_name: &str,
+        _pool_uuid: PoolUuid,
+        period: Option<Duration>,
+    ) -> StratisResult<bool> {
+        if self.fs_deletion_grace_period == period {
+            return Ok(false);
+        }
+        self.fs_deletion_grace_period = period;
+        self.purge_deleted_filesystems();
+        Ok(true)
+    }
+
+    fn deleted_filesystems(&self) -> Vec<DeletedFilesystem> {
+        self.deleted_filesystems
+            .iter()
+            .filter(|(_, (_, _, deleted))| !self.deletion_expired(*deleted))
+            .map(|(uuid, (name, fs, deleted))| DeletedFilesystem {
+                uuid: *uuid,
+                name: name.to_string(),
+                size: fs.size(),
+                deleted: *deleted,
+                purge_after: self.purge_after(*deleted),
+            })
+            .collect()
+    }
+
+    fn purge_deleted_filesystem(
+        &mut self,
+        _pool_uuid: PoolUuid,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<bool> {
+        if self.deleted_filesystems.remove(&uuid).is_some() {
+            self.log_operation(format!("purge deleted filesystem with UUID {}", uuid));
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn undelete_filesystem(
+        &mut self,
+        _pool_name: &str,
+        _pool_uuid: PoolUuid,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<bool> {
+        self.purge_deleted_filesystems();
+        if self.filesystems.contains_uuid(uuid) {
+            return Ok(false);
+        }
+        let name = match self.deleted_filesystems.get(&uuid) {
+            Some((name, _, _)) => name,
+            None => {
+                return Err(StratisError::Msg(format!(
+                    "No deleted filesystem with UUID {}",
+                    uuid
+                )))
+            }
+        };
+        if self.filesystems.contains_name(name) {
+            return Err(StratisError::Msg(format!(
+                "A filesystem with name {} already exists; rename it before undeleting filesystem with UUID {}",
+                name, uuid
+            )));
+        }
+        let (name, fs, _) = self
+            .deleted_filesystems
+            .remove(&uuid)
+            .expect("checked above");
+        self.filesystems.insert(name, uuid, fs);
+        self.log_operation(format!("undelete filesystem with UUID {}", uuid));
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
@@ -657,6 +1778,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -679,6 +1801,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -709,6 +1832,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -738,6 +1862,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -760,6 +1885,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -782,6 +1908,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -804,6 +1931,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -832,6 +1960,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -852,6 +1981,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -879,6 +2009,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -904,6 +2035,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -929,6 +2061,7 @@ mod tests {
                 strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
                 None,
                 &EncryptionInfo::default(),
+                None,
             )
             .unwrap()
             .changed()
@@ -944,4 +2077,1195 @@ mod tests {
             _ => false,
         });
     }
+
+    #[test]
+    /// Setting the metadata write failure policy is idempotent.
+    fn set_metadata_write_failure_policy() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        assert_eq!(
+            pool.metadata_write_failure_policy(),
+            MetadataWriteFailurePolicy::default()
+        );
+        assert_matches!(
+            pool.set_metadata_write_failure_policy(
+                &pool_name,
+                MetadataWriteFailurePolicy::ReadOnly
+            ),
+            Ok(true)
+        );
+        assert_matches!(
+            pool.set_metadata_write_failure_policy(
+                &pool_name,
+                MetadataWriteFailurePolicy::ReadOnly
+            ),
+            Ok(false)
+        );
+        assert_eq!(
+            pool.metadata_write_failure_policy(),
+            MetadataWriteFailurePolicy::ReadOnly
+        );
+    }
+
+    #[test]
+    /// Setting the allocation strategy is idempotent.
+    fn set_allocation_strategy() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        assert_eq!(pool.allocation_strategy(), AllocationStrategy::FirstFit);
+        assert_matches!(
+            pool.set_allocation_strategy(&pool_name, AllocationStrategy::BestFit),
+            Ok(true)
+        );
+        assert_matches!(
+            pool.set_allocation_strategy(&pool_name, AllocationStrategy::BestFit),
+            Ok(false)
+        );
+        assert_eq!(pool.allocation_strategy(), AllocationStrategy::BestFit);
+    }
+
+    #[test]
+    /// Setting the small device policy is idempotent.
+    fn set_small_device_policy() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        assert_eq!(pool.small_device_policy(), SmallDevicePolicy::Warn);
+        assert_matches!(
+            pool.set_small_device_policy(&pool_name, SmallDevicePolicy::RequireOverride),
+            Ok(true)
+        );
+        assert_matches!(
+            pool.set_small_device_policy(&pool_name, SmallDevicePolicy::RequireOverride),
+            Ok(false)
+        );
+        assert_eq!(
+            pool.small_device_policy(),
+            SmallDevicePolicy::RequireOverride
+        );
+        assert!(pool
+            .add_datadevs_with_override(uuid, &pool_name, strs_to_paths!(["/dev/three"]))
+            .unwrap()
+            .is_changed());
+    }
+
+    #[test]
+    /// Adding devices in best-effort mode reports a result for each device.
+    fn add_datadevs_best_effort() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let results = pool.add_datadevs_best_effort(
+            uuid,
+            &pool_name,
+            strs_to_paths!(["/dev/two", "/dev/one"]),
+        );
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, Path::new("/dev/two"));
+        assert_matches!(results[0].1, Ok(Some(_)));
+        assert_eq!(results[1].0, Path::new("/dev/one"));
+        assert_matches!(results[1].1, Ok(None));
+        assert_eq!(pool.blockdevs().len(), 2);
+    }
+
+    #[test]
+    /// Creating and destroying filesystems is recorded in the operation log.
+    fn operation_log() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let fs_uuid = pool
+            .create_filesystems(&pool_name, uuid, &[("fs_name", None)])
+            .unwrap()
+            .changed()
+            .unwrap()[0]
+            .1;
+        pool.destroy_filesystems(&pool_name, &[fs_uuid]).unwrap();
+        assert_eq!(pool.operation_log(None).unwrap().len(), 2);
+        let log = pool.operation_log(Some(1)).unwrap();
+        assert_eq!(log.len(), 1);
+        assert!(log[0].operation.starts_with("destroy filesystem"));
+    }
+
+    #[test]
+    /// Alert thresholds are validated, and setting the same thresholds
+    /// again is not a change.
+    fn set_alert_thresholds() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let thresholds = AlertThresholds {
+            physical_usage: Some(90),
+            degraded: Some(1),
+            ..AlertThresholds::default()
+        };
+        assert!(pool.set_alert_thresholds(&pool_name, thresholds).unwrap());
+        assert!(!pool.set_alert_thresholds(&pool_name, thresholds).unwrap());
+        assert_eq!(pool.alert_thresholds(), thresholds);
+        assert!(pool
+            .set_alert_thresholds(
+                &pool_name,
+                AlertThresholds {
+                    metadata_usage: Some(200),
+                    ..AlertThresholds::default()
+                }
+            )
+            .is_err());
+        assert_eq!(pool.alert_thresholds(), thresholds);
+        assert!(pool.active_alerts().is_empty());
+        assert!(pool.acknowledge_alert(AlertMetric::PhysicalUsage).is_err());
+    }
+
+    #[test]
+    /// Filesystems created from a template are numbered consecutively.
+    fn create_filesystem_from_template() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let (first, _) = pool
+            .create_filesystem_from_template(&pool_name, uuid, "data-###", None)
+            .unwrap();
+        let (second, second_uuid) = pool
+            .create_filesystem_from_template(&pool_name, uuid, "data-###", None)
+            .unwrap();
+        assert_eq!(&*first, "data-001");
+        assert_eq!(&*second, "data-002");
+        assert!(pool.get_filesystem(second_uuid).is_some());
+        assert!(pool
+            .create_filesystem_from_template(&pool_name, uuid, "data", None)
+            .is_err());
+    }
+
+    #[test]
+    /// The supported operations follow the configuration of the pool.
+    fn supported_operations() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let supported = pool.supported_operations();
+        assert!(supported.contains(&PoolCapability::InitCache));
+        assert!(!supported.contains(&PoolCapability::FlushCache));
+        assert!(!supported.contains(&PoolCapability::RemoveDataDevice));
+        assert!(!supported.contains(&PoolCapability::BindKeyring));
+
+        pool.init_cache(uuid, &pool_name, strs_to_paths!(["/dev/two"]), None)
+            .unwrap();
+        let supported = pool.supported_operations();
+        assert!(!supported.contains(&PoolCapability::InitCache));
+        assert!(supported.contains(&PoolCapability::FlushCache));
+        assert!(supported.contains(&PoolCapability::RemoveCache));
+    }
+
+    #[test]
+    /// A filesystem destroyed while a deletion grace period is set can be
+    /// undeleted until the grace period is removed.
+    fn fs_deletion_grace_period() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let fs_uuid = pool
+            .create_filesystems(&pool_name, uuid, &[("fs_name", None)])
+            .unwrap()
+            .changed()
+            .unwrap()[0]
+            .1;
+
+        assert!(pool
+            .set_fs_deletion_grace_period(&pool_name, uuid, Some(Duration::from_secs(3600)))
+            .unwrap());
+        pool.destroy_filesystems(&pool_name, &[fs_uuid]).unwrap();
+        assert!(pool.get_filesystem(fs_uuid).is_none());
+        assert_eq!(pool.deleted_filesystems().len(), 1);
+
+        assert!(pool.undelete_filesystem(&pool_name, uuid, fs_uuid).unwrap());
+        assert!(!pool.undelete_filesystem(&pool_name, uuid, fs_uuid).unwrap());
+        assert!(pool.get_filesystem(fs_uuid).is_some());
+
+        pool.destroy_filesystems(&pool_name, &[fs_uuid]).unwrap();
+        assert!(pool.purge_deleted_filesystem(uuid, fs_uuid).unwrap());
+        assert!(!pool.purge_deleted_filesystem(uuid, fs_uuid).unwrap());
+        assert!(pool.undelete_filesystem(&pool_name, uuid, fs_uuid).is_err());
+
+        let fs_uuid = pool
+            .create_filesystems(&pool_name, uuid, &[("fs_name", None)])
+            .unwrap()
+            .changed()
+            .unwrap()[0]
+            .1;
+        pool.destroy_filesystems(&pool_name, &[fs_uuid]).unwrap();
+        assert!(pool
+            .set_fs_deletion_grace_period(&pool_name, uuid, None)
+            .unwrap());
+        assert!(pool.deleted_filesystems().is_empty());
+        assert!(pool.undelete_filesystem(&pool_name, uuid, fs_uuid).is_err());
+    }
+
+    #[test]
+    /// A new sample replaces one taken less than the sample interval before.
+    fn capacity_history() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let history = pool.capacity_history(None);
+        assert_eq!(history.len(), 1);
+        let before = history[0];
+
+        pool.add_blockdevs(
+            uuid,
+            &pool_name,
+            strs_to_paths!(["/dev/two"]),
+            BlockDevTier::Data,
+        )
+        .unwrap();
+        let history = pool.capacity_history(None);
+        assert_eq!(history.len(), 1);
+        assert!(history[0].timestamp >= before.timestamp);
+
+        assert!(pool
+            .capacity_history(Some(history[0].timestamp + chrono::Duration::seconds(1)))
+            .is_empty());
+    }
+
+    #[test]
+    /// Filesystems can be grown to a target size, but not shrunk.
+    fn set_filesystem_size() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two", "/dev/three"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let (_, fs_uuid, size) = pool
+            .create_filesystems(&pool_name, uuid, &[("fs_name", None)])
+            .unwrap()
+            .changed()
+            .unwrap()[0];
+        let target_size = size + Sectors(2048);
+        assert!(pool
+            .set_filesystem_size(&pool_name, fs_uuid, target_size, false)
+            .unwrap());
+        assert!(!pool
+            .set_filesystem_size(&pool_name, fs_uuid, target_size, false)
+            .unwrap());
+        assert_matches!(
+            pool.set_filesystem_size(&pool_name, fs_uuid, size, false),
+            Err(_)
+        );
+    }
+
+    #[test]
+    /// Setting the copy-on-write alert threshold of a filesystem changes it
+    /// only if it differs, and fails for an unknown filesystem.
+    fn set_fs_cow_alert_threshold() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let (_, fs_uuid, _) = pool
+            .create_filesystems(&pool_name, uuid, &[("fs_name", None)])
+            .unwrap()
+            .changed()
+            .unwrap()[0];
+        let threshold = Some(Bytes(1024 * 1024));
+        assert!(pool
+            .set_fs_cow_alert_threshold(&pool_name, fs_uuid, threshold)
+            .unwrap());
+        assert!(!pool
+            .set_fs_cow_alert_threshold(&pool_name, fs_uuid, threshold)
+            .unwrap());
+        assert_eq!(
+            pool.get_filesystem(fs_uuid)
+                .unwrap()
+                .1
+                .cow_alert_threshold(),
+            threshold
+        );
+        assert!(pool
+            .set_fs_cow_alert_threshold(&pool_name, fs_uuid, None)
+            .unwrap());
+        assert!(pool
+            .set_fs_cow_alert_threshold(&pool_name, FilesystemUuid::new_v4(), threshold)
+            .is_err());
+    }
+
+    #[test]
+    /// Setting the minimum free space guarantee of a filesystem changes it
+    /// only if it differs, and fails if it exceeds the size of the
+    /// filesystem.
+    fn set_fs_min_free_guarantee() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let (_, fs_uuid, fs_size) = pool
+            .create_filesystems(&pool_name, uuid, &[("fs_name", None)])
+            .unwrap()
+            .changed()
+            .unwrap()[0];
+        let guarantee = Some(Bytes(1024 * 1024));
+        assert!(pool
+            .set_fs_min_free_guarantee(&pool_name, fs_uuid, guarantee)
+            .unwrap());
+        assert!(!pool
+            .set_fs_min_free_guarantee(&pool_name, fs_uuid, guarantee)
+            .unwrap());
+        assert_eq!(
+            pool.get_filesystem(fs_uuid).unwrap().1.min_free_guarantee(),
+            guarantee
+        );
+        assert!(pool
+            .set_fs_min_free_guarantee(&pool_name, fs_uuid, Some(fs_size.bytes() + Bytes(1)))
+            .is_err());
+        assert!(pool
+            .set_fs_min_free_guarantee(&pool_name, fs_uuid, None)
+            .unwrap());
+    }
+
+    #[test]
+    /// Filesystems and snapshots are numbered in the order in which they
+    /// are created, and a filesystem keeps its number when it is renamed.
+    fn filesystem_sequence_numbers() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let fs_uuids = pool
+            .create_filesystems(&pool_name, uuid, &[("fs_b", None), ("fs_a", None)])
+            .unwrap()
+            .changed()
+            .unwrap()
+            .into_iter()
+            .map(|(_, fs_uuid, _)| fs_uuid)
+            .collect::<Vec<_>>();
+        let snapshot_uuid = match pool
+            .snapshot_filesystem(&pool_name, uuid, fs_uuids[0], "snapshot")
+            .unwrap()
+        {
+            CreateAction::Created((snapshot_uuid, _)) => snapshot_uuid,
+            CreateAction::Identity => panic!("the snapshot must be created"),
+        };
+        pool.rename_filesystem(&pool_name, fs_uuids[0], "fs_c")
+            .unwrap();
+
+        let mut numbers = fs_uuids
+            .iter()
+            .map(|fs_uuid| pool.get_filesystem(*fs_uuid).unwrap().1.sequence_number())
+            .collect::<Vec<_>>();
+        numbers.sort_unstable();
+        assert_eq!(numbers, vec![1, 2]);
+        assert_eq!(
+            pool.get_filesystem(snapshot_uuid)
+                .unwrap()
+                .1
+                .sequence_number(),
+            3
+        );
+    }
+
+    #[test]
+    /// Data devices can be removed from a pool, but not the last one.
+    fn shrink_by_device() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let dev_uuids = pool
+            .blockdevs()
+            .iter()
+            .map(|(uuid, _, _)| *uuid)
+            .collect::<Vec<_>>();
+        assert_matches!(
+            pool.shrink_by_device(&pool_name, dev_uuids[0]),
+            Ok(DeleteAction::Deleted(_))
+        );
+        assert_matches!(
+            pool.shrink_by_device(&pool_name, dev_uuids[0]),
+            Ok(DeleteAction::Identity)
+        );
+        assert_matches!(pool.shrink_by_device(&pool_name, dev_uuids[1]), Err(_));
+        assert_eq!(pool.blockdevs().len(), 1);
+    }
+
+    #[test]
+    /// Disabling the write cache of a device is idempotent.
+    fn disable_device_write_cache() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (_, pool) = engine.get_mut_pool(uuid).unwrap();
+        let dev_uuid = pool.blockdevs()[0].0;
+        assert!(pool.device_write_cache_enabled(dev_uuid).unwrap());
+        assert!(pool.disable_device_write_cache(dev_uuid).unwrap());
+        assert!(!pool.disable_device_write_cache(dev_uuid).unwrap());
+        assert!(!pool.device_write_cache_enabled(dev_uuid).unwrap());
+        assert_matches!(pool.device_write_cache_enabled(DevUuid::new_v4()), Err(_));
+        assert_eq!(pool.device_in_flight_io(dev_uuid).unwrap(), 0);
+        assert_matches!(pool.device_in_flight_io(DevUuid::new_v4()), Err(_));
+    }
+
+    #[test]
+    /// Warming the cache requires a cache, and reads no more than the cache
+    /// can hold.
+    fn warm_cache() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let (_, fs_uuid, _) = pool
+            .create_filesystems(&pool_name, uuid, &[("fs_name", None)])
+            .unwrap()
+            .changed()
+            .unwrap()[0];
+        assert_matches!(pool.warm_cache(fs_uuid, None), Err(_));
+
+        pool.init_cache(uuid, &pool_name, strs_to_paths!(["/dev/three"]), None)
+            .unwrap();
+        let cache_size = Bytes::from(IEC::Gi).sectors();
+        let mut last_progress = None;
+        let work = pool.warm_cache(fs_uuid, Some(cache_size * 2u64)).unwrap();
+        assert_eq!(
+            work(&mut |read, total| last_progress = Some((read, total))).unwrap(),
+            cache_size
+        );
+        assert_eq!(last_progress, Some((cache_size, cache_size)));
+    }
+
+    #[test]
+    /// Flushing the cache requires a cache, and there is never anything to
+    /// write back from the simulated cache.
+    fn flush_cache() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        assert_matches!(pool.start_flush_cache(), Err(_));
+
+        pool.init_cache(uuid, &pool_name, strs_to_paths!(["/dev/two"]), None)
+            .unwrap();
+        let work = pool.start_flush_cache().unwrap();
+        let flushed = work(&mut |_, _| ());
+        assert_eq!(pool.finish_flush_cache(flushed).unwrap(), Sectors(0));
+    }
+
+    #[test]
+    /// Removing the cache removes the cache devices, and a cache can be
+    /// initialized again afterwards.
+    fn remove_cache() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        assert!(!pool.remove_cache(uuid, &pool_name).unwrap().is_changed());
+
+        let cache_uuids = pool
+            .init_cache(uuid, &pool_name, strs_to_paths!(["/dev/two"]), None)
+            .unwrap()
+            .changed()
+            .unwrap();
+        assert_eq!(
+            pool.remove_cache(uuid, &pool_name).unwrap().changed(),
+            Some(cache_uuids)
+        );
+        assert!(!pool.has_cache());
+        assert_eq!(pool.blockdevs().len(), 1);
+
+        assert_matches!(
+            pool.init_cache(uuid, &pool_name, strs_to_paths!(["/dev/two"]), None),
+            Ok(_)
+        );
+    }
+
+    #[test]
+    /// Verify that the cache block size is validated and recorded when the
+    /// cache is initialized.
+    fn cache_block_size() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        assert_eq!(pool.cache_block_size(), None);
+        assert_matches!(
+            pool.init_cache(
+                uuid,
+                &pool_name,
+                strs_to_paths!(["/dev/three"]),
+                Some(Sectors(1000))
+            ),
+            Err(_)
+        );
+        assert!(!pool.has_cache());
+        pool.init_cache(
+            uuid,
+            &pool_name,
+            strs_to_paths!(["/dev/three"]),
+            Some(Sectors(512)),
+        )
+        .unwrap();
+        assert_eq!(pool.cache_block_size(), Some(Sectors(512)));
+    }
+
+    #[test]
+    /// Filesystems of the minimum and of the maximum size can be created,
+    /// filesystems outside those limits can not.
+    fn filesystem_size_limits() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let (min, max) = pool.filesystem_size_limits();
+        assert!(min < max);
+        assert_matches!(
+            pool.create_filesystems(&pool_name, uuid, &[("small", Some(min.bytes()))]),
+            Ok(_)
+        );
+        assert_matches!(
+            pool.create_filesystems(&pool_name, uuid, &[("large", Some(max.bytes()))]),
+            Ok(_)
+        );
+        assert_matches!(
+            pool.create_filesystems(
+                &pool_name,
+                uuid,
+                &[("too_small", Some((min - Sectors(1)).bytes()))]
+            ),
+            Err(_)
+        );
+        assert_matches!(
+            pool.create_filesystems(
+                &pool_name,
+                uuid,
+                &[("too_large", Some((max + Sectors(1)).bytes()))]
+            ),
+            Err(_)
+        );
+    }
+
+    #[test]
+    /// The thin pool of a pool is within its limits, and adding data
+    /// devices grows its data device and the number of filesystems it is
+    /// recommended for.
+    fn thin_pool_limits() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let limits = pool.thin_pool_limits();
+        assert!(limits.data_size <= limits.max_data_size);
+        assert!(limits.meta_size <= limits.max_meta_size);
+        let max_filesystems = pool.recommended_max_filesystems().unwrap();
+        assert!(max_filesystems > 0);
+
+        pool.add_blockdevs(
+            uuid,
+            &pool_name,
+            strs_to_paths!(["/dev/two"]),
+            BlockDevTier::Data,
+        )
+        .unwrap();
+        assert!(pool.thin_pool_limits().data_size > limits.data_size);
+        assert!(pool.recommended_max_filesystems().unwrap() > max_filesystems);
+    }
+
+    #[test]
+    /// Pools start automatically by default; changing the flag is reported
+    /// only if the value actually changes.
+    fn set_auto_start() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        assert!(pool.auto_start());
+        assert_matches!(pool.set_auto_start(&pool_name, true), Ok(false));
+        assert_matches!(pool.set_auto_start(&pool_name, false), Ok(true));
+        assert!(!pool.auto_start());
+        assert_matches!(pool.set_auto_start(&pool_name, false), Ok(false));
+        assert_matches!(engine.start_pool(uuid), Ok(CreateAction::Identity));
+    }
+
+    #[test]
+    /// Latency histograms are only available while detailed statistics are
+    /// enabled.
+    fn detailed_stats() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let (_, fs_uuid, _) = pool
+            .create_filesystems(&pool_name, uuid, &[("fs_name", None)])
+            .unwrap()
+            .changed()
+            .unwrap()[0];
+        assert!(!pool.detailed_stats());
+        assert_matches!(pool.filesystem_latency_histogram(fs_uuid), Err(_));
+        assert_matches!(pool.latency_stats(), Err(_));
+
+        assert_matches!(pool.set_detailed_stats(&pool_name, true), Ok(true));
+        assert_matches!(pool.set_detailed_stats(&pool_name, true), Ok(false));
+        assert_eq!(
+            pool.filesystem_latency_histogram(fs_uuid).unwrap().len(),
+            LATENCY_HISTOGRAM_BOUNDARIES.len() + 1
+        );
+        assert_matches!(
+            pool.filesystem_latency_histogram(FilesystemUuid::new_v4()),
+            Err(_)
+        );
+        assert_eq!(pool.latency_stats().unwrap(), LatencyStats::default());
+
+        assert_matches!(pool.set_detailed_stats(&pool_name, false), Ok(true));
+        assert_matches!(pool.filesystem_latency_histogram(fs_uuid), Err(_));
+        assert_matches!(pool.latency_stats(), Err(_));
+    }
+
+    #[test]
+    /// Verify that reserved cache devices are used when the cache is
+    /// initialized and that no more can be reserved afterwards.
+    fn reserve_cache_devices() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        assert_matches!(pool.init_cache(uuid, &pool_name, &[], None), Err(_));
+
+        let reserved = pool
+            .reserve_cache_devices(uuid, &pool_name, strs_to_paths!(["/dev/two"]))
+            .unwrap()
+            .changed()
+            .unwrap();
+        assert_eq!(reserved.len(), 1);
+        assert!(pool
+            .reserve_cache_devices(uuid, &pool_name, strs_to_paths!(["/dev/two"]))
+            .unwrap()
+            .changed()
+            .is_none());
+        assert_eq!(pool.reserved_cache_devices().len(), 1);
+        assert!(!pool.has_cache());
+
+        let cache = pool
+            .init_cache(uuid, &pool_name, strs_to_paths!(["/dev/three"]), None)
+            .unwrap()
+            .changed()
+            .unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(&reserved[0]));
+        assert!(pool.has_cache());
+        assert!(pool.reserved_cache_devices().is_empty());
+
+        assert_matches!(
+            pool.reserve_cache_devices(uuid, &pool_name, strs_to_paths!(["/dev/four"])),
+            Err(_)
+        );
+    }
+
+    #[test]
+    /// Simulating a reconfiguration reports the capacity that results from
+    /// the changes, but does not change the pool.
+    fn simulate_reconfiguration() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (_, pool) = engine.get_pool(uuid).unwrap();
+        let dev_uuids = pool
+            .blockdevs()
+            .iter()
+            .map(|(uuid, _, _)| *uuid)
+            .collect::<Vec<_>>();
+
+        let unchanged = pool
+            .simulate_reconfiguration(&ReconfigurationChanges::default())
+            .unwrap();
+        assert_eq!(unchanged.usable + unchanged.overhead, unchanged.total);
+
+        let added = pool
+            .simulate_reconfiguration(&ReconfigurationChanges {
+                add_data_devices: vec![PathBuf::from("/dev/three")],
+                ..ReconfigurationChanges::default()
+            })
+            .unwrap();
+        assert!(added.total > unchanged.total);
+        assert!(added.usable > unchanged.usable);
+
+        let removed = pool
+            .simulate_reconfiguration(&ReconfigurationChanges {
+                remove_data_devices: vec![dev_uuids[0]],
+                ..ReconfigurationChanges::default()
+            })
+            .unwrap();
+        assert!(removed.total < unchanged.total);
+
+        assert_matches!(
+            pool.simulate_reconfiguration(&ReconfigurationChanges {
+                remove_data_devices: dev_uuids,
+                ..ReconfigurationChanges::default()
+            }),
+            Err(_)
+        );
+        assert_matches!(
+            pool.simulate_reconfiguration(&ReconfigurationChanges {
+                remove_data_devices: vec![DevUuid::new_v4()],
+                ..ReconfigurationChanges::default()
+            }),
+            Err(_)
+        );
+        assert_eq!(pool.blockdevs().len(), 2);
+    }
+
+    #[test]
+    /// A sim pool is always healthy, and its report contains every check.
+    fn full_health_report() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_pool(uuid).unwrap();
+        let report = pool.full_health_report(&pool_name).unwrap();
+        assert!(report.healthy());
+        assert_eq!(
+            report
+                .checks
+                .iter()
+                .map(|check| check.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["metadata", "devices", "thin_pool", "alerts"]
+        );
+    }
+
+    #[test]
+    /// The surface test of a sim pool reports every device as sound.
+    fn surface_test() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (_, pool) = engine.get_pool(uuid).unwrap();
+        let results = pool.surface_test().unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.problem.is_none()));
+    }
+
+    #[test]
+    /// The devices of the sim engine share no physical disk.
+    fn redundancy_warnings() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (_, pool) = engine.get_pool(uuid).unwrap();
+        assert_eq!(pool.redundancy_warnings(), Vec::new());
+    }
+
+    #[test]
+    /// Reconciling the sim pools finds nothing to correct.
+    fn reconcile() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let results = engine.reconcile();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, uuid);
+        assert_eq!(results[0].1.as_ref().unwrap(), &Reconciliation::default());
+    }
+
+    #[test]
+    /// Creating a filesystem on devices that are not data devices of the
+    /// pool fails, creating it on a data device succeeds.
+    fn create_filesystem_on_devices() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let dev_uuid = pool.blockdevs()[0].0;
+        assert_matches!(
+            pool.create_filesystem_on_devices(&pool_name, uuid, "fs", None, &[DevUuid::new_v4()]),
+            Err(_)
+        );
+        assert!(pool
+            .create_filesystem_on_devices(&pool_name, uuid, "fs", None, &[dev_uuid])
+            .unwrap()
+            .is_changed());
+    }
+
+    #[test]
+    /// Relocating the metadata requires data devices of the pool.
+    fn relocate_metadata() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let dev_uuid = pool.blockdevs()[0].0;
+        assert_matches!(
+            pool.relocate_metadata(&pool_name, uuid, &[], &mut |_, _| ()),
+            Err(_)
+        );
+        assert_matches!(
+            pool.relocate_metadata(&pool_name, uuid, &[DevUuid::new_v4()], &mut |_, _| ()),
+            Err(_)
+        );
+        assert_matches!(
+            pool.relocate_metadata(&pool_name, uuid, &[dev_uuid], &mut |_, _| ()),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    /// The structure of a pool has a cache tier only once a cache has been
+    /// initialized.
+    fn structure() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one", "/dev/two"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        let tiers = pool.structure();
+        assert_eq!(tiers.len(), 1);
+        assert_eq!(tiers[0].tier, BlockDevTier::Data);
+        assert_eq!(tiers[0].device_count, 2);
+
+        pool.init_cache(uuid, &pool_name, strs_to_paths!(["/dev/three"]), None)
+            .unwrap();
+        let tiers = pool.structure();
+        assert_eq!(tiers.len(), 2);
+        assert_eq!(tiers[1].tier, BlockDevTier::Cache);
+        assert_eq!(tiers[1].device_count, 1);
+        assert_eq!(tiers[1].used_size, tiers[1].total_size);
+    }
+
+    #[test]
+    /// The snapshot pruning threshold must be a percentage, and setting it
+    /// to its current value changes nothing.
+    fn auto_prune_snapshots_on_pressure() {
+        let mut engine = SimEngine::default();
+        let uuid = engine
+            .create_pool(
+                "pool_name",
+                strs_to_paths!(["/dev/one"]),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+        let (pool_name, pool) = engine.get_mut_pool(uuid).unwrap();
+        assert_eq!(pool.auto_prune_snapshots_on_pressure(), None);
+
+        assert!(pool
+            .set_auto_prune_snapshots_on_pressure(&pool_name, Some(0))
+            .is_err());
+        assert!(pool
+            .set_auto_prune_snapshots_on_pressure(&pool_name, Some(101))
+            .is_err());
+        assert!(pool
+            .set_auto_prune_snapshots_on_pressure(&pool_name, Some(90))
+            .unwrap());
+        assert!(!pool
+            .set_auto_prune_snapshots_on_pressure(&pool_name, Some(90))
+            .unwrap());
+        assert_eq!(pool.auto_prune_snapshots_on_pressure(), Some(90));
+        assert!(pool
+            .set_auto_prune_snapshots_on_pressure(&pool_name, None)
+            .unwrap());
+    }
 }
@@ -4,37 +4,41 @@
 
 // Code to handle the backing store of a pool.
 
-use std::{borrow::Cow, cmp, path::Path};
+use std::{borrow::Cow, cmp, path::Path, thread, time::Duration};
 
 use chrono::{DateTime, Utc};
 use serde_json::Value;
 
-use devicemapper::{CacheDev, Device, DmDevice, LinearDev, Sectors};
+use devicemapper::{
+    CacheDev, CacheDevStatus, DevId, Device, DmDevice, DmName, DmOptions, LinearDev,
+    LinearDevTargetParams, LinearTargetParams, Sectors, TargetLine,
+};
 
 use crate::{
     engine::{
+        shared::DEFAULT_CACHE_BLOCK_SIZE,
         strat_engine::{
             backstore::{
                 blockdev::StratBlockDev,
                 blockdevmgr::{map_to_dm, BlockDevMgr},
                 cache_tier::CacheTier,
                 data_tier::DataTier,
+                stripe::StripeDev,
             },
-            dm::get_dm,
+            dm::{get_dm, retry_dm},
             metadata::MDADataSize,
             names::{format_backstore_ids, CacheRole},
             serde_structs::{BackstoreSave, CapSave, Recordable},
             writing::wipe_sectors,
         },
-        types::{BlockDevTier, DevUuid, EncryptionInfo, KeyDescription, PoolUuid},
+        types::{
+            AllocationStrategy, BlockDevTier, DevUuid, EncryptionInfo, KeyDescription, PoolUuid,
+            ReadWork, StripeConfig,
+        },
     },
     stratis::{StratisError, StratisResult},
 };
 
-/// Use a cache block size that the kernel docs indicate is the largest
-/// typical size.
-const CACHE_BLOCK_SIZE: Sectors = Sectors(2048); // 1024 KiB
-
 /// Make a DM cache device. If the cache device is being made new,
 /// take extra steps to make it clean.
 fn make_cache(
@@ -76,10 +80,118 @@ fn make_cache(
         meta,
         cache,
         origin,
-        CACHE_BLOCK_SIZE,
+        cache_tier.block_size,
     )?)
 }
 
+/// The interval at which the number of dirty blocks is checked while the
+/// cache is being flushed.
+const CACHE_FLUSH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The number of dirty blocks in the cache device with the given name.
+/// The device is looked up by name so that the number can be obtained
+/// without access to the CacheDev, e.g. while flushing the cache.
+fn dirty_blocks(name: &DmName) -> StratisResult<u64> {
+    let (_, status) = get_dm().table_status(&DevId::Name(name), &DmOptions::new())?;
+    let status = match status.as_slice() {
+        [(_, _, _, params)] => params.parse::<CacheDevStatus>()?,
+        _ => {
+            return Err(StratisError::Msg(format!(
+                "Expected a single line of status for cache device {}, found {}",
+                name,
+                status.len()
+            )))
+        }
+    };
+    match status {
+        CacheDevStatus::Working(status) => Ok(status.performance.dirty),
+        CacheDevStatus::Error => Err(StratisError::Msg(
+            "devicemapper could not obtain the status of the cache device".to_string(),
+        )),
+        CacheDevStatus::Fail => Err(StratisError::Msg("The cache device has failed".to_string())),
+    }
+}
+
+/// Load the table of the cache device with a different replacement policy,
+/// or with its own table if policy is None, and make it live. The CacheDev
+/// does not know about the change, so its own table must be restored once
+/// the other policy is no longer required.
+fn load_cache_policy(cache: &CacheDev, policy: Option<&str>) -> StratisResult<()> {
+    let line = &cache.table().table;
+    let mut params = line.params.clone();
+    if let Some(policy) = policy {
+        params.policy = policy.to_string();
+        params.policy_args.clear();
+    }
+    // The params are displayed as the target type followed by its arguments.
+    let params = params.to_string();
+    let mut words = params.splitn(2, ' ');
+    let target_type = words.next().unwrap_or_default().to_string();
+    let args = words.next().unwrap_or_default().to_string();
+    let table = [(*line.start, *line.length, target_type, args)];
+
+    let id = DevId::Name(cache.name());
+    retry_dm(|| get_dm().table_load(&id, &table))?;
+    retry_dm(|| get_dm().device_suspend(&id, &DmOptions::new()))?;
+    Ok(())
+}
+
+/// Make the table for the origin sub-device from the segments allocated in
+/// the data tier. If the data tier is striped, the origin maps the stripe
+/// device, which is first set up or extended to cover all the segments.
+fn origin_table(
+    pool_uuid: PoolUuid,
+    data_tier: &DataTier,
+    stripe_dev: &mut Option<StripeDev>,
+) -> StratisResult<Vec<TargetLine<LinearDevTargetParams>>> {
+    let stripe = match data_tier.stripe {
+        Some(stripe) => stripe,
+        None => return Ok(map_to_dm(&data_tier.segments)),
+    };
+
+    match stripe_dev {
+        Some(dev) => dev.set_segments(stripe, &data_tier.segments)?,
+        None => *stripe_dev = Some(StripeDev::setup(pool_uuid, stripe, &data_tier.segments)?),
+    }
+    let dev = stripe_dev
+        .as_ref()
+        .expect("the stripe device was set up or updated above");
+    Ok(vec![TargetLine::new(
+        Sectors(0),
+        dev.size(),
+        LinearDevTargetParams::Linear(LinearTargetParams::new(dev.device(), Sectors(0))),
+    )])
+}
+
+/// The number of sectors shared by the range of length a_length at a_start
+/// and the range of length b_length at b_start.
+fn overlap(a_start: Sectors, a_length: Sectors, b_start: Sectors, b_length: Sectors) -> Sectors {
+    let start = cmp::max(a_start, b_start);
+    let end = cmp::min(a_start + a_length, b_start + b_length);
+    if end > start {
+        end - start
+    } else {
+        Sectors(0)
+    }
+}
+
+/// The number of sectors among the first end sectors of a group of stripes
+/// that are stored on the member at the given position in the group.
+/// The group stripes chunks of stripe.size sectors round-robin over its
+/// stripe.count members.
+fn striped_share(stripe: StripeConfig, position: usize, end: Sectors) -> Sectors {
+    let row = stripe.size * u64::from(stripe.count);
+    let full_rows = end / row;
+    let rest = end - row * full_rows;
+    let skipped = stripe.size * position as u64;
+    let partial = if rest > skipped {
+        cmp::min(rest - skipped, stripe.size)
+    } else {
+        Sectors(0)
+    };
+    stripe.size * full_rows + partial
+}
+
 /// This structure can allocate additional space to the upper layer, but it
 /// cannot accept returned space. When it is extended to be able to accept
 /// returned space the allocation algorithm will have to be revised.
@@ -94,8 +206,16 @@ pub struct Backstore {
     data_tier: DataTier,
     /// A linear DM device.
     linear: Option<LinearDev>,
+    /// A striped DM device below the cap device. Only present if the data
+    /// tier is striped and space has been allocated from it.
+    stripe: Option<StripeDev>,
+    /// Blockdevs that are reserved for use by a cache that has not yet been
+    /// initialized. Optional, since there may be no reserved blockdevs.
+    reserved_cache: Option<BlockDevMgr>,
     /// Index for managing allocation of cap device
     next: Sectors,
+    /// Whether the cache uses the cleaner policy while it is being flushed
+    cache_flushing: bool,
 }
 
 impl Backstore {
@@ -116,7 +236,8 @@ impl Backstore {
     ///   key description and that key description == key_description
     ///   * key_description.is_none() -> no StratBlockDev in datadevs has a
     ///   key description.
-    ///   * no StratBlockDev in cachedevs has a key description
+    ///   * no StratBlockDev in cachedevs or reserved_cachedevs has a key
+    ///   description
     ///
     /// Postcondition:
     /// self.linear.is_some() XOR self.cache.is_some()
@@ -126,17 +247,15 @@ impl Backstore {
         backstore_save: &BackstoreSave,
         datadevs: Vec<StratBlockDev>,
         cachedevs: Vec<StratBlockDev>,
+        reserved_cachedevs: Vec<StratBlockDev>,
         last_update_time: DateTime<Utc>,
     ) -> StratisResult<Backstore> {
         let block_mgr = BlockDevMgr::new(datadevs, Some(last_update_time));
         let data_tier = DataTier::setup(block_mgr, &backstore_save.data_tier)?;
+        let mut stripe = None;
+        let table = origin_table(pool_uuid, &data_tier, &mut stripe)?;
         let (dm_name, dm_uuid) = format_backstore_ids(pool_uuid, CacheRole::OriginSub);
-        let origin = LinearDev::setup(
-            get_dm(),
-            &dm_name,
-            Some(&dm_uuid),
-            map_to_dm(&data_tier.segments),
-        )?;
+        let origin = LinearDev::setup(get_dm(), &dm_name, Some(&dm_uuid), table)?;
 
         let (cache_tier, cache, origin) = if !cachedevs.is_empty() {
             let block_mgr = BlockDevMgr::new(cachedevs, Some(last_update_time));
@@ -156,12 +275,21 @@ impl Backstore {
             (None, None, Some(origin))
         };
 
+        let reserved_cache = if reserved_cachedevs.is_empty() {
+            None
+        } else {
+            Some(BlockDevMgr::new(reserved_cachedevs, Some(last_update_time)))
+        };
+
         Ok(Backstore {
             data_tier,
             cache_tier,
             linear: origin,
+            stripe,
+            reserved_cache,
             cache,
             next: backstore_save.cap.allocs[0].1,
+            cache_flushing: false,
         })
     }
 
@@ -173,30 +301,46 @@ impl Backstore {
     /// When the backstore is initialized it may be unencrypted, or it may
     /// be encrypted only with a kernel keyring and without Clevis information.
     ///
+    /// If a stripe configuration is specified, all space allocated from the
+    /// data tier is striped accordingly.
+    ///
     /// WARNING: metadata changing event
     pub fn initialize(
         pool_uuid: PoolUuid,
         paths: &[&Path],
         mda_data_size: MDADataSize,
         encryption_info: &EncryptionInfo,
+        stripe: Option<StripeConfig>,
     ) -> StratisResult<Backstore> {
-        let data_tier = DataTier::new(BlockDevMgr::initialize(
+        let mut data_tier = DataTier::new(BlockDevMgr::initialize(
             pool_uuid,
             paths,
             mda_data_size,
             encryption_info,
         )?);
 
+        if let Some(stripe) = stripe {
+            if let Err(err) = data_tier.set_stripe(stripe) {
+                let _ = data_tier.destroy();
+                return Err(err);
+            }
+        }
+
         Ok(Backstore {
             data_tier,
             cache_tier: None,
             linear: None,
+            stripe: None,
+            reserved_cache: None,
             cache: None,
             next: Sectors(0),
+            cache_flushing: false,
         })
     }
 
     /// Initialize the cache tier and add cachedevs to the backstore.
+    /// Any blockdevs that were reserved for the cache are added to the cache
+    /// together with the devices specified by paths, which may then be empty.
     ///
     /// Returns all `DevUuid`s of devices that were added to the cache on initialization.
     ///
@@ -208,29 +352,42 @@ impl Backstore {
         &mut self,
         pool_uuid: PoolUuid,
         paths: &[&Path],
+        block_size: Sectors,
     ) -> StratisResult<Vec<DevUuid>> {
         match self.cache_tier {
             Some(_) => unreachable!("self.cache.is_none()"),
             None => {
-                if paths.is_empty() {
-                    return Err(StratisError::Msg(
-                        "Must initialize cache with at least one blockdev.".to_string(),
-                    ));
-                }
-
                 // Note that variable length metadata is not stored on the
                 // cachedevs, so the mda_size can always be the minimum.
                 // If it is desired to change a cache dev to a data dev, it
                 // should be removed and then re-added in order to ensure
                 // that the MDA region is set to the correct size.
-                let bdm = BlockDevMgr::initialize(
-                    pool_uuid,
-                    paths,
-                    MDADataSize::default(),
-                    &EncryptionInfo::default(),
-                )?;
-
-                let cache_tier = CacheTier::new(bdm)?;
+                let bdm = match self.reserved_cache.take() {
+                    Some(mut bdm) => {
+                        if !paths.is_empty() {
+                            if let Err(err) = bdm.add(pool_uuid, paths) {
+                                self.reserved_cache = Some(bdm);
+                                return Err(err);
+                            }
+                        }
+                        bdm
+                    }
+                    None => {
+                        if paths.is_empty() {
+                            return Err(StratisError::Msg(
+                                "Must initialize cache with at least one blockdev.".to_string(),
+                            ));
+                        }
+                        BlockDevMgr::initialize(
+                            pool_uuid,
+                            paths,
+                            MDADataSize::default(),
+                            &EncryptionInfo::default(),
+                        )?
+                    }
+                };
+
+                let cache_tier = CacheTier::new(bdm, block_size)?;
 
                 let linear = self.linear
                     .take()
@@ -281,8 +438,8 @@ impl Backstore {
 
                 if cache_change {
                     let table = map_to_dm(&cache_tier.cache_segments);
-                    cache_device.set_cache_table(get_dm(), table)?;
-                    cache_device.resume(get_dm())?;
+                    retry_dm(|| cache_device.set_cache_table(get_dm(), table.clone()))?;
+                    retry_dm(|| cache_device.resume(get_dm()))?;
                 }
 
                 // NOTE: currently CacheTier::add() does not ever update the
@@ -290,8 +447,8 @@ impl Backstore {
                 // when CacheTier::add() is fixed, this code will become live.
                 if meta_change {
                     let table = map_to_dm(&cache_tier.meta_segments);
-                    cache_device.set_meta_table(get_dm(), table)?;
-                    cache_device.resume(get_dm())?;
+                    retry_dm(|| cache_device.set_meta_table(get_dm(), table.clone()))?;
+                    retry_dm(|| cache_device.resume(get_dm()))?;
                 }
 
                 Ok(uuids)
@@ -300,6 +457,176 @@ impl Backstore {
         }
     }
 
+    /// Prepare writing back all dirty blocks in the cache to the data tier.
+    /// Until finish_flush_cache() is called, the cache uses the cleaner
+    /// replacement policy, which writes back dirty blocks and promotes no
+    /// new ones. The returned work waits until no dirty blocks remain;
+    /// progress is called with the number of sectors written back so far
+    /// and the number of sectors that were dirty when flushing began. The
+    /// work returns the number of sectors written back.
+    ///
+    /// Returns an error if there is no cache.
+    pub fn start_flush_cache(&mut self) -> StratisResult<ReadWork<Sectors>> {
+        let (cache, block_size) = match (self.cache.as_ref(), self.cache_block_size()) {
+            (Some(cache), Some(block_size)) => (cache, block_size),
+            _ => {
+                return Err(StratisError::Msg(
+                    "The pool has no cache to flush".to_string(),
+                ))
+            }
+        };
+
+        let name = cache.name().to_owned();
+        let total = dirty_blocks(&name)?;
+        if total == 0 {
+            return Ok(Box::new(|_| Ok(Sectors(0))));
+        }
+
+        load_cache_policy(cache, Some("cleaner"))?;
+        self.cache_flushing = true;
+        Ok(Box::new(
+            move |progress: &mut dyn FnMut(Sectors, Sectors)| loop {
+                let dirty = dirty_blocks(&name)?;
+                progress(block_size * total.saturating_sub(dirty), block_size * total);
+                if dirty == 0 {
+                    return Ok(block_size * total);
+                }
+                thread::sleep(CACHE_FLUSH_POLL_INTERVAL);
+            },
+        ))
+    }
+
+    /// Restore the replacement policy of the cache after the work returned
+    /// by start_flush_cache() has finished.
+    pub fn finish_flush_cache(&mut self) -> StratisResult<()> {
+        if !self.cache_flushing {
+            return Ok(());
+        }
+        self.cache_flushing = false;
+        match self.cache {
+            Some(ref cache) => load_cache_policy(cache, None),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the cache is being flushed, see start_flush_cache().
+    pub fn cache_flushing(&self) -> bool {
+        self.cache_flushing
+    }
+
+    /// Set up a handle for the origin sub-device, which already exists if
+    /// space has been allocated from the data tier.
+    fn origin(&mut self, pool_uuid: PoolUuid) -> StratisResult<LinearDev> {
+        let table = origin_table(pool_uuid, &self.data_tier, &mut self.stripe)?;
+        let (dm_name, dm_uuid) = format_backstore_ids(pool_uuid, CacheRole::OriginSub);
+        Ok(LinearDev::setup(get_dm(), &dm_name, Some(&dm_uuid), table)?)
+    }
+
+    /// Prepare to remove the cache. Check that the cache holds no dirty
+    /// blocks and return the origin sub-device, which replaces the cache as
+    /// the cap device. Everything that uses the cache must be moved to the
+    /// origin before the cache is removed with remove_cache().
+    ///
+    /// Precondition: everything that uses the cache is suspended, so that no
+    /// more blocks can become dirty.
+    pub fn uncached_device(&mut self, pool_uuid: PoolUuid) -> StratisResult<Device> {
+        let dirty = match self.cache {
+            Some(ref cache) => dirty_blocks(cache.name())?,
+            None => {
+                return Err(StratisError::Msg(
+                    "The pool has no cache to remove".to_string(),
+                ))
+            }
+        };
+        if dirty != 0 {
+            return Err(StratisError::Msg(format!(
+                "The cache still holds {} dirty blocks; it must be flushed before it can be removed",
+                dirty
+            )));
+        }
+        Ok(self.origin(pool_uuid)?.device())
+    }
+
+    /// Remove the cache, so that the origin sub-device becomes the cap
+    /// device, and wipe the cache devices.
+    ///
+    /// Returns the UUIDs of the devices that were removed.
+    ///
+    /// Precondition: nothing uses the cache device; see uncached_device().
+    // Precondition: self.cache.is_some() && self.linear.is_none()
+    // Postcondition: self.cache.is_none() && self.linear.is_some()
+    pub fn remove_cache(&mut self, pool_uuid: PoolUuid) -> StratisResult<Vec<DevUuid>> {
+        let origin = self.origin(pool_uuid)?;
+
+        // The origin sub-device is retained, so the cache device and its
+        // other sub-devices are removed individually.
+        let (dm_name, _) = format_backstore_ids(pool_uuid, CacheRole::Cache);
+        retry_dm(|| get_dm().device_remove(&DevId::Name(&dm_name), &DmOptions::new()))?;
+        self.cache = None;
+        self.linear = Some(origin);
+        for role in &[CacheRole::CacheSub, CacheRole::MetaSub] {
+            let (dm_name, _) = format_backstore_ids(pool_uuid, *role);
+            retry_dm(|| get_dm().device_remove(&DevId::Name(&dm_name), &DmOptions::new()))?;
+        }
+
+        let mut cache_tier = self
+            .cache_tier
+            .take()
+            .expect("self.cache.is_some() <=> self.cache_tier.is_some()");
+        let uuids = cache_tier
+            .blockdevs()
+            .iter()
+            .map(|&(uuid, _)| uuid)
+            .collect::<Vec<_>>();
+        cache_tier.destroy()?;
+        Ok(uuids)
+    }
+
+    /// Reserve blockdevs for a cache that has not yet been initialized. The
+    /// devices are initialized as Stratis devices belonging to the pool, so
+    /// that nothing else can claim them, but they are not used until the
+    /// cache is initialized.
+    ///
+    /// Returns the `DevUuid`s of the reserved devices.
+    pub fn reserve_cachedevs(
+        &mut self,
+        pool_uuid: PoolUuid,
+        paths: &[&Path],
+    ) -> StratisResult<Vec<DevUuid>> {
+        if self.cache_tier.is_some() {
+            return Err(StratisError::Msg(
+                "The pool already has a cache; devices can be added to it directly".to_string(),
+            ));
+        }
+        match self.reserved_cache {
+            Some(ref mut reserved_cache) => reserved_cache.add(pool_uuid, paths),
+            None => {
+                let bdm = BlockDevMgr::initialize(
+                    pool_uuid,
+                    paths,
+                    MDADataSize::default(),
+                    &EncryptionInfo::default(),
+                )?;
+                let uuids = bdm
+                    .blockdevs()
+                    .iter()
+                    .map(|&(uuid, _)| uuid)
+                    .collect::<Vec<_>>();
+                self.reserved_cache = Some(bdm);
+                Ok(uuids)
+            }
+        }
+    }
+
+    /// Get the blockdevs that are reserved for a cache that has not yet been
+    /// initialized.
+    pub fn reserved_cachedevs(&self) -> Vec<(DevUuid, &StratBlockDev)> {
+        match self.reserved_cache {
+            Some(ref reserved_cache) => reserved_cache.blockdevs(),
+            None => Vec::new(),
+        }
+    }
+
     /// Add datadevs to the backstore. The data tier always exists if the
     /// backstore exists at all, so there is no need to create it.
     pub fn add_datadevs(
@@ -310,29 +637,95 @@ impl Backstore {
         self.data_tier.add(pool_uuid, paths)
     }
 
+    /// Remove a datadev from which no space is allocated from the backstore.
+    /// WARNING: metadata changing event
+    pub fn remove_datadev(&mut self, uuid: DevUuid) -> StratisResult<()> {
+        self.data_tier.remove(uuid)
+    }
+
+    /// The number of sectors of the given ranges of the cap device that are
+    /// stored on the datadev with the given UUID.
+    pub fn datadev_share(&self, uuid: DevUuid, ranges: &[(Sectors, Sectors)]) -> Sectors {
+        let segments = &self.data_tier.segments;
+        let mut offset = Sectors(0);
+        let mut share = Sectors(0);
+        match self.data_tier.stripe {
+            None => {
+                for seg in segments {
+                    let length = seg.segment.length;
+                    if seg.uuid == uuid {
+                        share += ranges
+                            .iter()
+                            .map(|&(start, len)| overlap(offset, length, start, len))
+                            .sum::<Sectors>();
+                    }
+                    offset += length;
+                }
+            }
+            Some(stripe) => {
+                for group in segments.chunks(usize::from(stripe.count)) {
+                    let length = group.iter().map(|seg| seg.segment.length).sum::<Sectors>();
+                    if let Some(position) = group.iter().position(|seg| seg.uuid == uuid) {
+                        for &(start, len) in ranges {
+                            let from = cmp::max(start, offset);
+                            let to = cmp::min(start + len, offset + length);
+                            if from < to {
+                                share += striped_share(stripe, position, to - offset)
+                                    - striped_share(stripe, position, from - offset);
+                            }
+                        }
+                    }
+                    offset += length;
+                }
+            }
+        }
+        share
+    }
+
+    /// Allocate from the given datadevs first whenever the data tier grows,
+    /// where possible. An empty list removes the preference.
+    pub fn set_preferred_datadevs(&mut self, uuids: &[DevUuid]) -> StratisResult<()> {
+        self.data_tier.set_preferred(uuids)
+    }
+
+    /// The strategy by which space is chosen for allocations from the data
+    /// tier.
+    pub fn allocation_strategy(&self) -> AllocationStrategy {
+        self.data_tier.allocation_strategy()
+    }
+
+    /// Choose space according to strategy in all future allocations from
+    /// the data tier.
+    pub fn set_allocation_strategy(&mut self, strategy: AllocationStrategy) {
+        self.data_tier.set_allocation_strategy(strategy)
+    }
+
     /// Extend the cap device whether it is a cache or not. Create the DM
     /// device if it does not already exist. Return an error if DM
     /// operations fail. Use all segments currently allocated in the data tier.
     fn extend_cap_device(&mut self, pool_uuid: PoolUuid) -> StratisResult<()> {
+        let table = origin_table(pool_uuid, &self.data_tier, &mut self.stripe)?;
         let create = match (self.cache.as_mut(), self.linear.as_mut()) {
             (None, None) => true,
             (Some(cache), None) => {
-                let table = map_to_dm(&self.data_tier.segments);
-                cache.set_origin_table(get_dm(), table)?;
-                cache.resume(get_dm())?;
+                retry_dm(|| cache.set_origin_table(get_dm(), table.clone()))?;
+                retry_dm(|| cache.resume(get_dm()))?;
+                // The cache's own table, which was just loaded, does not
+                // use the cleaner policy.
+                if self.cache_flushing {
+                    load_cache_policy(cache, Some("cleaner"))?;
+                }
                 false
             }
             (None, Some(linear)) => {
-                let table = map_to_dm(&self.data_tier.segments);
-                linear.set_table(get_dm(), table)?;
-                linear.resume(get_dm())?;
+                retry_dm(|| linear.set_table(get_dm(), table.clone()))?;
+                retry_dm(|| linear.resume(get_dm()))?;
                 false
             }
             _ => panic!("NOT (self.cache().is_some() AND self.linear.is_some())"),
         };
 
         if create {
-            let table = map_to_dm(&self.data_tier.segments);
             let (dm_name, dm_uuid) = format_backstore_ids(pool_uuid, CacheRole::OriginSub);
             let origin = LinearDev::setup(get_dm(), &dm_name, Some(&dm_uuid), table)?;
             self.linear = Some(origin);
@@ -389,6 +782,57 @@ impl Backstore {
         Ok(Some(chunks))
     }
 
+    /// Satisfy a request for multiple segments, as alloc() does, with space
+    /// that is stored only on the datadevs with the given UUIDs. The unused
+    /// space already in the cap device is handed out first, so it must be
+    /// stored on those datadevs as well.
+    ///
+    /// Returns an error if the request can not be satisfied from those
+    /// datadevs; in that case nothing is allocated.
+    ///
+    /// WARNING: metadata changing event
+    pub fn alloc_on_datadevs(
+        &mut self,
+        pool_uuid: PoolUuid,
+        sizes: &[Sectors],
+        uuids: &[DevUuid],
+    ) -> StratisResult<Vec<(Sectors, Sectors)>> {
+        if self.data_tier.stripe.is_some() {
+            return Err(StratisError::Msg(
+                "The data tier is striped; space can not be allocated from particular blockdevs"
+                    .to_string(),
+            ));
+        }
+
+        let total_required = sizes.iter().cloned().sum();
+        let available = self.available_in_cap();
+        let unused_elsewhere = available
+            - uuids
+                .iter()
+                .map(|uuid| self.datadev_share(*uuid, &[(self.next, available)]))
+                .sum::<Sectors>();
+        if unused_elsewhere != Sectors(0) {
+            return Err(StratisError::Msg(format!(
+                "{} of the unused space in the cap device is stored on other blockdevs",
+                unused_elsewhere
+            )));
+        }
+
+        if available < total_required {
+            if !self.data_tier.alloc_from(total_required - available, uuids) {
+                return Err(StratisError::Msg(format!(
+                    "The given blockdevs do not have {} available",
+                    total_required - available
+                )));
+            }
+            self.extend_cap_device(pool_uuid)?;
+        }
+
+        Ok(self
+            .alloc(pool_uuid, sizes)?
+            .expect("the cap device has room for all the sizes"))
+    }
+
     /// Allocate a single segment from the backstore.
     /// If it is impossible to allocate the requested amount, try
     /// something smaller. If it is impossible to allocate any amount
@@ -496,6 +940,11 @@ impl Backstore {
         self.data_tier.size()
     }
 
+    /// The stripe configuration of the data tier, if it is striped.
+    pub fn stripe_config(&self) -> Option<StripeConfig> {
+        self.data_tier.stripe
+    }
+
     /// The current usable size of all the blockdevs in the data tier.
     pub fn datatier_usable_size(&self) -> Sectors {
         self.data_tier.usable_size()
@@ -541,7 +990,7 @@ impl Backstore {
     pub fn destroy(&mut self) -> StratisResult<()> {
         match self.cache {
             Some(ref mut cache) => {
-                cache.teardown(get_dm())?;
+                retry_dm(|| cache.teardown(get_dm()))?;
                 self.cache_tier
                     .as_mut()
                     .expect("if dm_device is cache, cache tier exists")
@@ -549,15 +998,48 @@ impl Backstore {
             }
             None => {
                 if let Some(ref mut linear) = self.linear {
-                    linear.teardown(get_dm())?;
+                    retry_dm(|| linear.teardown(get_dm()))?;
                 }
             }
         };
+        if let Some(ref mut stripe) = self.stripe {
+            stripe.teardown()?;
+        }
+        if let Some(ref mut reserved_cache) = self.reserved_cache {
+            reserved_cache.destroy_all()?;
+        }
         self.data_tier.destroy()
     }
 
-    /// Teardown the DM devices in the backstore.
+    /// The UUIDs of the data tier blockdevs that hold any part of the given
+    /// segments of the backstore device. If the data tier is striped, every
+    /// blockdev in a stripe that holds a part is included.
     #[cfg(test)]
+    pub fn data_blockdevs_under(
+        &self,
+        segments: &[(Sectors, Sectors)],
+    ) -> std::collections::HashSet<DevUuid> {
+        let group_len = self
+            .data_tier
+            .stripe
+            .map_or(1, |stripe| usize::from(stripe.count));
+        let mut uuids = std::collections::HashSet::new();
+        let mut group_start = Sectors(0);
+        for group in self.data_tier.segments.chunks(group_len) {
+            let group_end =
+                group_start + group.iter().map(|seg| seg.segment.length).sum::<Sectors>();
+            if segments
+                .iter()
+                .any(|&(start, length)| start < group_end && group_start < start + length)
+            {
+                uuids.extend(group.iter().map(|seg| seg.uuid));
+            }
+            group_start = group_end;
+        }
+        uuids
+    }
+
+    /// Teardown the DM devices in the backstore.
     pub fn teardown(&mut self) -> StratisResult<()> {
         match self.cache {
             Some(ref mut cache) => cache.teardown(get_dm()),
@@ -568,8 +1050,11 @@ impl Backstore {
                     Ok(())
                 }
             }
+        }?;
+        match self.stripe {
+            Some(ref mut stripe) => stripe.teardown(),
+            None => Ok(()),
         }
-        .map_err(|e| e.into())
     }
 
     /// Return the device that this tier is currently using.
@@ -655,6 +1140,16 @@ impl Backstore {
         self.data_tier.block_mgr.encryption_info()
     }
 
+    /// The size of the cache sub-device, if the backstore has a cache.
+    pub fn cache_size(&self) -> Option<Sectors> {
+        self.cache_tier.as_ref().map(|c| c.cache_size())
+    }
+
+    /// The block size of the cache, if the backstore has a cache.
+    pub fn cache_block_size(&self) -> Option<Sectors> {
+        self.cache_tier.as_ref().map(|c| c.block_size)
+    }
+
     pub fn has_cache(&self) -> bool {
         self.cache_tier.is_some()
     }
@@ -711,6 +1206,12 @@ impl Recordable<BackstoreSave> for Backstore {
                 allocs: vec![(Sectors(0), self.next)],
             },
             data_tier: self.data_tier.record(),
+            reserved_cache: self.reserved_cache.as_ref().map(|r| {
+                r.blockdevs()
+                    .into_iter()
+                    .map(|(_, bd)| bd.record())
+                    .collect()
+            }),
         }
     }
 }
@@ -724,12 +1225,12 @@ mod tests {
     use crate::engine::strat_engine::{
         cmd,
         metadata::device_identifiers,
-        tests::{loopbacked, real},
+        tests::{assert_no_stratis_dm_devices, loopbacked, real},
     };
 
     use super::*;
 
-    const INITIAL_BACKSTORE_ALLOCATION: Sectors = CACHE_BLOCK_SIZE;
+    const INITIAL_BACKSTORE_ALLOCATION: Sectors = DEFAULT_CACHE_BLOCK_SIZE;
 
     /// Assert some invariants of the backstore
     /// * backstore.cache_tier.is_some() <=> backstore.cache.is_some() &&
@@ -776,6 +1277,7 @@ mod tests {
             initdatapaths,
             MDADataSize::default(),
             &EncryptionInfo::default(),
+            None,
         )
         .unwrap();
 
@@ -786,7 +1288,9 @@ mod tests {
             .alloc(pool_uuid, &[INITIAL_BACKSTORE_ALLOCATION])
             .unwrap();
 
-        let cache_uuids = backstore.init_cache(pool_uuid, initcachepaths).unwrap();
+        let cache_uuids = backstore
+            .init_cache(pool_uuid, initcachepaths, DEFAULT_CACHE_BLOCK_SIZE)
+            .unwrap();
 
         invariant(&backstore);
 
@@ -836,6 +1340,7 @@ mod tests {
         }
 
         backstore.destroy().unwrap();
+        assert_no_stratis_dm_devices();
     }
 
     #[test]
@@ -868,6 +1373,7 @@ mod tests {
             paths,
             MDADataSize::default(),
             &EncryptionInfo::default(),
+            None,
         )
         .unwrap();
 
@@ -902,6 +1408,7 @@ mod tests {
         cmd::udev_settle().unwrap();
         backstore.destroy().unwrap();
         cmd::udev_settle().unwrap();
+        assert_no_stratis_dm_devices();
     }
 
     #[test]
@@ -929,6 +1436,7 @@ mod tests {
             paths1,
             MDADataSize::default(),
             &EncryptionInfo::default(),
+            None,
         )
         .unwrap();
 
@@ -951,7 +1459,9 @@ mod tests {
 
         let old_device = backstore.device();
 
-        backstore.init_cache(pool_uuid, paths2).unwrap();
+        backstore
+            .init_cache(pool_uuid, paths2, DEFAULT_CACHE_BLOCK_SIZE)
+            .unwrap();
 
         for path in paths2 {
             assert_eq!(
@@ -979,4 +1489,37 @@ mod tests {
     fn real_test_setup() {
         real::test_with_spec(&real::DeviceLimits::AtLeast(2, None, None), test_setup);
     }
+
+    #[test]
+    /// Verify the share of each member of a group of stripes in ranges
+    /// that start and end within a stripe chunk.
+    fn test_striped_share() {
+        let stripe = StripeConfig {
+            count: 3,
+            size: Sectors(8),
+        };
+        // Two full rows and 20 sectors of the third: 8, 8, and 4.
+        let end = Sectors(2 * 24 + 20);
+        assert_eq!(striped_share(stripe, 0, end), Sectors(24));
+        assert_eq!(striped_share(stripe, 1, end), Sectors(24));
+        assert_eq!(striped_share(stripe, 2, end), Sectors(20));
+        assert_eq!(
+            (0..3)
+                .map(|position| striped_share(stripe, position, end))
+                .sum::<Sectors>(),
+            end
+        );
+        assert_eq!(
+            striped_share(stripe, 1, Sectors(12)) - striped_share(stripe, 1, Sectors(3)),
+            Sectors(4)
+        );
+        assert_eq!(
+            overlap(Sectors(0), Sectors(10), Sectors(5), Sectors(10)),
+            Sectors(5)
+        );
+        assert_eq!(
+            overlap(Sectors(0), Sectors(5), Sectors(5), Sectors(10)),
+            Sectors(0)
+        );
+    }
 }
@@ -4,7 +4,12 @@
 
 // Code to handle a single block device.
 
-use std::{borrow::Cow, fs::OpenOptions, path::Path};
+use std::{
+    borrow::Cow,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use chrono::{DateTime, TimeZone, Utc};
 use serde_json::Value;
@@ -19,10 +24,15 @@ use crate::{
                 crypt::CryptHandle,
                 range_alloc::{PerDevSegments, RangeAllocator},
             },
+            device::{surface_test, DIRECT_IO_ALIGN, SURFACE_TEST_SIZE},
+            devlinks,
             metadata::{disown_device, BDAExtendedSize, BlockdevSize, MDADataSize, BDA},
             serde_structs::{BaseBlockDevSave, Recordable},
         },
-        types::{DevUuid, DevicePath, EncryptionInfo, KeyDescription, PoolUuid},
+        types::{
+            AllocationStrategy, DevUuid, DevicePath, EncryptionInfo, KeyDescription, KeyslotUsage,
+            PoolUuid,
+        },
     },
     stratis::{StratisError, StratisResult},
 };
@@ -128,6 +138,16 @@ impl StratBlockDev {
         self.devnode()
     }
 
+    /// Record a new physical path for the block device, which has reappeared
+    /// under a different name. The path is not recorded in the metadata, as
+    /// devices are identified by their Stratis UUIDs.
+    pub fn set_physical_path(&mut self, path: DevicePath) {
+        match &mut self.underlying_device {
+            UnderlyingDevice::Encrypted(handle) => handle.set_luks2_device_path(path),
+            UnderlyingDevice::Unencrypted(old_path) => *old_path = path,
+        }
+    }
+
     /// Returns the path to the unencrypted metadata stored on the block device structure.
     /// On encrypted devices, this will point to a devicemapper device set up by libcryptsetup.
     /// On unencrypted devices, this will be the same as the physical device.
@@ -135,6 +155,30 @@ impl StratBlockDev {
         self.underlying_device.metadata_path()
     }
 
+    /// Close the dm-crypt device of an encrypted block device, leaving the
+    /// data and metadata on the device intact. Does nothing for an
+    /// unencrypted block device.
+    pub fn deactivate(&self) -> StratisResult<()> {
+        match self.underlying_device.crypt_handle() {
+            Some(handle) => handle.deactivate(),
+            None => Ok(()),
+        }
+    }
+
+    /// Send a synthetic udev change event for the physical device, so that
+    /// it is identified anew.
+    pub fn udev_change(&self) -> StratisResult<()> {
+        let devnode = fs::canonicalize(self.physical_path())?;
+        let name = devnode.file_name().ok_or_else(|| {
+            StratisError::Msg(format!("{} is not a device node", devnode.display()))
+        })?;
+        OpenOptions::new()
+            .write(true)
+            .open(Path::new("/sys/class/block").join(name).join("uevent"))?
+            .write_all(devlinks::UEVENT_CHANGE_EVENT.as_bytes())?;
+        Ok(())
+    }
+
     /// Remove information that identifies this device as belonging to Stratis
     ///
     /// If self.is_encrypted() is true, destroy all keyslots and wipe the LUKS2 header.
@@ -168,6 +212,37 @@ impl StratBlockDev {
         self.bda.save_state(time, metadata, &mut f)
     }
 
+    /// Read both copies of the latest metadata on the device and compare
+    /// them. Returns the time at which the metadata was written, the
+    /// metadata, and a description of each problem found.
+    pub fn verify_state(&self) -> StratisResult<Option<(DateTime<Utc>, Vec<u8>, Vec<String>)>> {
+        let mut f = OpenOptions::new()
+            .read(true)
+            .open(self.underlying_device.metadata_path())?;
+        Ok(self.bda.verify_state(&mut f)?.and_then(|(data, problems)| {
+            self.bda
+                .last_update_time()
+                .map(|time| (*time, data, problems))
+        }))
+    }
+
+    /// Write a pattern to the space reserved after the BDA and read it
+    /// back, to check that the device stores data faithfully. On encrypted
+    /// devices, the pattern is written through the dm-crypt device. Returns
+    /// true if the pattern was read back unchanged.
+    pub fn surface_test(&self) -> StratisResult<bool> {
+        let (start, length) = self.bda.reserved_region();
+        let align = DIRECT_IO_ALIGN as u64;
+        let offset = (*start.bytes() as u64 + align - 1) / align * align;
+        if offset + SURFACE_TEST_SIZE as u64 > *(start + length).bytes() as u64 {
+            return Err(StratisError::Msg(format!(
+                "The reserved space of block device {} is too small for a surface test",
+                self.uuid()
+            )));
+        }
+        surface_test(self.underlying_device.metadata_path(), offset)
+    }
+
     /// The pool's UUID.
     pub fn pool_uuid(&self) -> PoolUuid {
         self.bda.pool_uuid()
@@ -183,8 +258,8 @@ impl StratBlockDev {
     /// If all available sectors are desired, don't use this function.
     /// Define a request_all() function here and have it invoke the
     /// RangeAllocator::request_all() function.
-    pub fn request_space(&mut self, size: Sectors) -> PerDevSegments {
-        self.used.request(size)
+    pub fn request_space(&mut self, size: Sectors, strategy: AllocationStrategy) -> PerDevSegments {
+        self.used.request(size, strategy)
     }
 
     // ALL SIZE METHODS (except size(), which is in BlockDev impl.)
@@ -199,6 +274,12 @@ impl StratBlockDev {
         self.used.available()
     }
 
+    /// The length of the largest contiguous range of sectors on this device
+    /// not allocated for any purpose.
+    pub fn largest_available(&self) -> Sectors {
+        self.used.largest_available()
+    }
+
     /// The total size of the Stratis block device.
     pub fn total_size(&self) -> BlockdevSize {
         let size = self.used.size();
@@ -219,6 +300,114 @@ impl StratBlockDev {
         set_blockdev_user_info!(self; user_info)
     }
 
+    /// The sysfs directory of the disk that holds the device. Partitions
+    /// do not have their own queue or SCSI device; those of the disk that
+    /// contains the partition apply.
+    fn disk_sysfs_path(&self) -> PathBuf {
+        let sys_dev = [
+            "/sys/dev/block",
+            &format!("{}:{}", self.dev.major, self.dev.minor),
+        ]
+        .iter()
+        .collect::<PathBuf>();
+        if sys_dev.join("partition").exists() {
+            sys_dev.join("..")
+        } else {
+            sys_dev
+        }
+    }
+
+    /// The sysfs file that reports the kernel's view of the write cache
+    /// mode of the device.
+    fn write_cache_path(&self) -> PathBuf {
+        self.disk_sysfs_path().join("queue/write_cache")
+    }
+
+    /// The sysfs file through which the SCSI disk driver changes the
+    /// caching mode page of the device. ATA disks are attached as SCSI
+    /// disks, and libata translates the caching mode page to the write
+    /// cache feature of the disk.
+    fn scsi_cache_type_path(&self) -> StratisResult<PathBuf> {
+        fs::read_dir(self.disk_sysfs_path().join("device/scsi_disk"))
+            .ok()
+            .and_then(|mut entries| entries.next())
+            .and_then(|entry| entry.ok())
+            .map(|entry| entry.path().join("cache_type"))
+            .ok_or_else(|| {
+                StratisError::Msg(format!(
+                    "Device {} is not a SCSI or ATA disk, so its write cache can not be turned off",
+                    self.devnode().display()
+                ))
+            })
+    }
+
+    /// Returns true if the device has a volatile write cache enabled, so that
+    /// data may be lost on power failure.
+    pub fn write_cache_enabled(&self) -> StratisResult<bool> {
+        let path = self.write_cache_path();
+        let state = fs::read_to_string(&path)?;
+        match state.trim() {
+            "write back" => Ok(true),
+            "write through" => Ok(false),
+            state => Err(StratisError::Msg(format!(
+                "Unexpected write cache state \"{}\" in {}",
+                state,
+                path.display()
+            ))),
+        }
+    }
+
+    /// The number of I/O requests which have been issued to the device and
+    /// have not completed yet, as reported in sysfs.
+    pub fn in_flight_io(&self) -> StratisResult<u64> {
+        let path = [
+            "/sys/dev/block",
+            &format!("{}:{}", self.dev.major, self.dev.minor),
+            "inflight",
+        ]
+        .iter()
+        .collect::<PathBuf>();
+        let counts = fs::read_to_string(&path)?;
+        counts.split_whitespace().try_fold(0u64, |total, count| {
+            count.parse::<u64>().map(|c| total + c).map_err(|_| {
+                StratisError::Msg(format!(
+                    "Unexpected in-flight request counts \"{}\" in {}",
+                    counts.trim(),
+                    path.display()
+                ))
+            })
+        })
+    }
+
+    /// Turn off the volatile write cache of the device itself. The SCSI
+    /// disk driver clears the WCE bit in the caching mode page of the
+    /// device with MODE SELECT, which reaches an ATA disk as SET FEATURES,
+    /// and then treats the device as write through. The device keeps the
+    /// setting across power cycles only if it can save its caching mode
+    /// page.
+    ///
+    /// Writing to the queue/write_cache file instead would only stop the
+    /// kernel from sending flushes to a device that still caches writes,
+    /// so other devices are refused.
+    ///
+    /// Returns true if the write cache was previously enabled.
+    pub fn disable_write_cache(&mut self) -> StratisResult<bool> {
+        if !self.write_cache_enabled()? {
+            return Ok(false);
+        }
+        OpenOptions::new()
+            .write(true)
+            .open(self.scsi_cache_type_path()?)?
+            .write_all(b"write through")?;
+        if self.write_cache_enabled()? {
+            return Err(StratisError::Msg(format!(
+                "Device {} still reports a write back cache after it was asked to turn it off",
+                self.devnode().display()
+            )));
+        }
+        Ok(true)
+    }
+
     /// Get the physical path for a block device.
     pub fn devnode(&self) -> &Path {
         self.underlying_device.physical_path()
@@ -236,6 +425,16 @@ impl StratBlockDev {
         }
     }
 
+    /// Get the number of keyslots in use on the encrypted blockdev.
+    pub fn keyslot_usage(&self) -> StratisResult<KeyslotUsage> {
+        self.underlying_device
+            .crypt_handle()
+            .ok_or_else(|| {
+                StratisError::Msg("This device does not appear to be encrypted".to_string())
+            })?
+            .keyslot_usage()
+    }
+
     /// Bind encrypted device using the given clevis configuration.
     pub fn bind_clevis(&mut self, pin: &str, clevis_info: &Value) -> StratisResult<()> {
         let crypt_handle = self.underlying_device.crypt_handle_mut().ok_or_else(|| {
@@ -32,7 +32,7 @@ use crate::{
             metadata::MDADataSize,
             serde_structs::{BaseBlockDevSave, BaseDevSave, Recordable},
         },
-        types::{DevUuid, EncryptionInfo, KeyDescription, PoolUuid},
+        types::{AllocationStrategy, DevUuid, EncryptionInfo, KeyDescription, PoolUuid},
     },
     stratis::{StratisError, StratisResult},
 };
@@ -236,7 +236,7 @@ impl BlockDevMgr {
         for uuid in uuids {
             let mut found = false;
             let blockdevs_last_index = self.block_devs.len() - 1;
-            for i in 0..blockdevs_last_index {
+            for i in 0..=blockdevs_last_index {
                 let index = blockdevs_last_index - i;
                 if self.block_devs[index].uuid() == *uuid {
                     removed.push(self.block_devs.swap_remove(index));
@@ -261,13 +261,55 @@ impl BlockDevMgr {
     /// This method is atomic, it either allocates all requested or allocates
     /// nothing.
     pub fn alloc_space(&mut self, sizes: &[Sectors]) -> Option<Vec<Vec<BlkDevSegment>>> {
+        self.alloc_space_preferring(sizes, &[], AllocationStrategy::FirstFit)
+    }
+
+    /// Allocate space like alloc_space(), but take it from the blockdevs in
+    /// preferred first, in the order given. Space is taken from the other
+    /// blockdevs only if the preferred blockdevs do not have enough.
+    /// Among blockdevs that are equally preferred, and among the free ranges
+    /// of each blockdev, space is chosen according to strategy.
+    pub fn alloc_space_preferring(
+        &mut self,
+        sizes: &[Sectors],
+        preferred: &[DevUuid],
+        strategy: AllocationStrategy,
+    ) -> Option<Vec<Vec<BlkDevSegment>>> {
         let total_needed: Sectors = sizes.iter().cloned().sum();
         if self.avail_space() < total_needed {
             return None;
         }
 
+        let mut order = (0..self.block_devs.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| {
+            preferred
+                .iter()
+                .position(|uuid| *uuid == self.block_devs[i].uuid())
+                .unwrap_or(preferred.len())
+        });
+
         let mut lists = Vec::new();
         for &needed in sizes {
+            if strategy == AllocationStrategy::BestFit {
+                // The blockdev with the least space that holds all that is
+                // needed, then the others with the most space first. The
+                // sort is stable, so the preference is kept.
+                order.sort_by_key(|&i| {
+                    let available = self.block_devs[i].available();
+                    if available >= needed {
+                        (false, *available)
+                    } else {
+                        (true, u64::MAX - *available)
+                    }
+                });
+                order.sort_by_key(|&i| {
+                    preferred
+                        .iter()
+                        .position(|uuid| *uuid == self.block_devs[i].uuid())
+                        .unwrap_or(preferred.len())
+                });
+            }
+
             let mut alloc = Sectors(0);
             let mut segs = Vec::new();
             // TODO: Consider greater efficiency for allocation generally.
@@ -277,12 +319,13 @@ impl BlockDevMgr {
             // In the context of this major inefficiency that ensues over time
             // the obvious but more minor inefficiency of this inner loop is
             // not worth worrying about.
-            for bd in &mut self.block_devs {
+            for &i in &order {
                 if alloc == needed {
                     break;
                 }
 
-                let r_segs = bd.request_space(needed - alloc);
+                let bd = &mut self.block_devs[i];
+                let r_segs = bd.request_space(needed - alloc, strategy);
                 let blkdev_segs = r_segs.iter().map(|(&start, &length)| {
                     BlkDevSegment::new(bd.uuid(), Segment::new(*bd.device(), start, length))
                 });
@@ -290,12 +333,63 @@ impl BlockDevMgr {
                 alloc += r_segs.sum();
             }
             assert_eq!(alloc, needed);
+
+            if !preferred.is_empty() {
+                let from_preferred = segs
+                    .iter()
+                    .filter(|seg| preferred.contains(&seg.uuid))
+                    .map(|seg| seg.segment.length)
+                    .sum::<Sectors>();
+                if from_preferred < needed {
+                    warn!(
+                        "The preferred blockdevs could only provide {} of the {} requested; the rest was allocated from other blockdevs",
+                        from_preferred, needed
+                    );
+                }
+            }
             lists.push(segs);
         }
 
         Some(lists)
     }
 
+    /// Allocate per_dev contiguous sectors from each of the count blockdevs
+    /// with the largest free ranges, for a striped data tier. Return the
+    /// segments allocated, one for each blockdev, or None if there are not
+    /// count blockdevs with a free range of at least per_dev sectors.
+    /// This method is atomic, it either allocates all requested or allocates
+    /// nothing.
+    pub fn alloc_striped(&mut self, count: usize, per_dev: Sectors) -> Option<Vec<BlkDevSegment>> {
+        let mut candidates = self
+            .block_devs
+            .iter_mut()
+            .filter(|bd| bd.largest_available() >= per_dev)
+            .collect::<Vec<_>>();
+        if candidates.len() < count {
+            return None;
+        }
+        candidates.sort_by(|a, b| b.largest_available().cmp(&a.largest_available()));
+
+        let segs = candidates
+            .into_iter()
+            .take(count)
+            .flat_map(|bd| {
+                // A best fit request for no more than the largest free range
+                // of a blockdev is satisfied from a single range.
+                let r_segs = bd.request_space(per_dev, AllocationStrategy::BestFit);
+                let (uuid, device) = (bd.uuid(), *bd.device());
+                r_segs
+                    .iter()
+                    .map(|(&start, &length)| {
+                        BlkDevSegment::new(uuid, Segment::new(device, start, length))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        Some(segs)
+    }
+
     /// Write the given data to all blockdevs marking with current time.
     /// Return an error if data was not written to any blockdev.
     /// Omit blockdevs which do not have sufficient space in BDA to accommodate
@@ -772,6 +866,65 @@ mod tests {
         );
     }
 
+    /// Verify that space is allocated from a preferred blockdev first, even
+    /// if it is not the first blockdev, and that once it is exhausted the
+    /// remainder comes from the other blockdevs.
+    fn test_blockdevmgr_preferred(paths: &[&Path]) {
+        assert!(paths.len() > 1);
+
+        let mut mgr = BlockDevMgr::initialize(
+            PoolUuid::new_v4(),
+            paths,
+            MDADataSize::default(),
+            &EncryptionInfo::default(),
+        )
+        .unwrap();
+        let (preferred, preferred_avail) = mgr
+            .block_devs
+            .last()
+            .map(|bd| (bd.uuid(), bd.available()))
+            .unwrap();
+
+        let segs = mgr
+            .alloc_space_preferring(&[Sectors(2)], &[preferred], AllocationStrategy::FirstFit)
+            .unwrap();
+        assert!(segs[0].iter().all(|seg| seg.uuid == preferred));
+
+        let rest = preferred_avail - Sectors(2);
+        let segs = mgr
+            .alloc_space_preferring(
+                &[rest + Sectors(2)],
+                &[preferred],
+                AllocationStrategy::FirstFit,
+            )
+            .unwrap();
+        assert_eq!(
+            segs[0]
+                .iter()
+                .filter(|seg| seg.uuid == preferred)
+                .map(|seg| seg.segment.length)
+                .sum::<Sectors>(),
+            rest
+        );
+        assert!(segs[0].iter().any(|seg| seg.uuid != preferred));
+    }
+
+    #[test]
+    fn loop_test_blockdevmgr_preferred() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(2, 3, None),
+            test_blockdevmgr_preferred,
+        );
+    }
+
+    #[test]
+    fn real_test_blockdevmgr_preferred() {
+        real::test_with_spec(
+            &real::DeviceLimits::AtLeast(2, None, None),
+            test_blockdevmgr_preferred,
+        );
+    }
+
     /// Test that the `BlockDevMgr` will add devices if the same key
     /// is used to encrypted the existing devices and the added devices.
     fn test_blockdevmgr_same_key(paths: &[&Path]) {
@@ -784,6 +937,7 @@ mod tests {
                 &EncryptionInfo {
                     key_description: Some(key_desc.clone()),
                     clevis_info: None,
+                    ..EncryptionInfo::default()
                 },
             )?;
 
@@ -829,6 +983,7 @@ mod tests {
                 &EncryptionInfo {
                     key_description: Some(key_desc.clone()),
                     clevis_info: None,
+                    ..EncryptionInfo::default()
                 },
             )?;
 
@@ -940,6 +1095,7 @@ mod tests {
                     "tang".to_string(),
                     json!({"url": env::var("TANG_URL").unwrap(), "stratis:tang:trust_url": true}),
                 )),
+                ..EncryptionInfo::default()
             },
         )
         .unwrap();
@@ -983,6 +1139,7 @@ mod tests {
                         "tang".to_string(),
                         json!({"url": env::var("TANG_URL")?, "stratis:tang:trust_url": true}),
                     )),
+                    ..EncryptionInfo::default()
                 },
             )?;
             cmd::udev_settle()?;
@@ -1085,6 +1242,7 @@ mod tests {
                         "tang".to_string(),
                         json!({"url": env::var("TANG_URL")?, "stratis:tang:trust_url": true}),
                     )),
+                    ..EncryptionInfo::default()
                 },
             );
 
@@ -1106,6 +1264,7 @@ mod tests {
                         "tang".to_string(),
                         json!({"url": env::var("TANG_URL")?, "stratis:tang:trust_url": true}),
                     )),
+                    ..EncryptionInfo::default()
                 },
             )?;
 
@@ -10,6 +10,7 @@ use devicemapper::{Sectors, IEC, SECTOR_SIZE};
 
 use crate::{
     engine::{
+        shared::DEFAULT_CACHE_BLOCK_SIZE,
         strat_engine::{
             backstore::{
                 blockdev::StratBlockDev,
@@ -42,6 +43,8 @@ pub struct CacheTier {
     /// The list of segments granted by block_mgr and used by the metadata
     /// device.
     pub meta_segments: Vec<BlkDevSegment>,
+    /// The block size of the cache device.
+    pub block_size: Sectors,
 }
 
 impl CacheTier {
@@ -78,6 +81,9 @@ impl CacheTier {
             block_mgr,
             cache_segments,
             meta_segments,
+            block_size: cache_tier_save
+                .block_size
+                .unwrap_or(DEFAULT_CACHE_BLOCK_SIZE),
         })
     }
 
@@ -140,7 +146,7 @@ impl CacheTier {
     /// sub-device too big.
     ///
     /// WARNING: metadata changing event
-    pub fn new(mut block_mgr: BlockDevMgr) -> StratisResult<CacheTier> {
+    pub fn new(mut block_mgr: BlockDevMgr, block_size: Sectors) -> StratisResult<CacheTier> {
         let avail_space = block_mgr.avail_space();
 
         // FIXME: Come up with a better way to choose metadata device size
@@ -172,6 +178,7 @@ impl CacheTier {
             block_mgr,
             cache_segments,
             meta_segments,
+            block_size,
         })
     }
 
@@ -180,6 +187,14 @@ impl CacheTier {
         self.block_mgr.destroy_all()
     }
 
+    /// The size of the cache sub-device.
+    pub fn cache_size(&self) -> Sectors {
+        self.cache_segments
+            .iter()
+            .map(|x| x.segment.length)
+            .sum::<Sectors>()
+    }
+
     /// Get all the blockdevs belonging to this tier.
     pub fn blockdevs(&self) -> Vec<(DevUuid, &StratBlockDev)> {
         self.block_mgr.blockdevs()
@@ -214,6 +229,7 @@ impl Recordable<CacheTierSave> for CacheTier {
                 allocs: vec![self.cache_segments.record(), self.meta_segments.record()],
                 devs: self.block_mgr.record(),
             },
+            block_size: Some(self.block_size),
         }
     }
 }
@@ -249,7 +265,7 @@ mod tests {
         )
         .unwrap();
 
-        let mut cache_tier = CacheTier::new(mgr).unwrap();
+        let mut cache_tier = CacheTier::new(mgr, DEFAULT_CACHE_BLOCK_SIZE).unwrap();
 
         // A cache tier w/ some devices and everything promptly allocated to
         // the tier.
@@ -10,6 +10,7 @@ pub const TOKEN_KEYSLOTS_KEY: &str = "keyslots";
 pub const STRATIS_TOKEN_DEVNAME_KEY: &str = "activation_name";
 pub const STRATIS_TOKEN_POOL_UUID_KEY: &str = "pool_uuid";
 pub const STRATIS_TOKEN_DEV_UUID_KEY: &str = "device_uuid";
+pub const STRATIS_TOKEN_PBKDF_KEY: &str = "pbkdf";
 
 pub const STRATIS_TOKEN_ID: c_uint = 0;
 pub const LUKS2_TOKEN_ID: c_uint = 1;
@@ -19,7 +20,7 @@ pub const LUKS2_TOKEN_TYPE: &str = "luks2-keyring";
 pub const STRATIS_TOKEN_TYPE: &str = "stratis";
 
 /// The size of the media encryption key generated by cryptsetup for
-/// each block device.
+/// each block device if no other size is requested.
 pub const STRATIS_MEK_SIZE: usize = 512 / 8;
 
 /// Sector size as determined in `cryptsetup/lib/internal.h`
@@ -32,5 +33,13 @@ pub const DEVICEMAPPER_PATH: &str = "/dev/mapper";
 /// tang server does not need to be verified.
 pub const CLEVIS_TANG_TRUST_URL: &str = "stratis:tang:trust_url";
 
+/// The record of the locations of the detached LUKS2 headers of encrypted
+/// devices
+pub const DETACHED_HEADERS_PATH: &str = "/var/lib/stratisd/detached_headers.json";
+
+/// The directory of the links by which devices with a detached LUKS2 header
+/// are found
+pub const DEVICE_LINKS_DIR: &str = "/dev/disk/by-id";
+
 pub const DEFAULT_CRYPT_METADATA_SIZE: u64 = 16384;
 pub const DEFAULT_CRYPT_KEYSLOTS_SIZE: u64 = 16_744_448;
@@ -0,0 +1,212 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+// Code to keep track of encrypted devices whose LUKS2 header is stored
+// apart from the device.
+//
+// Such a device carries no signature at all, so neither udev nor
+// cryptsetup can tell that it belongs to Stratis. The location of the
+// header of each such device is therefore recorded in a file kept by the
+// engine, which is consulted whenever a crypt device context is acquired
+// and when the devices of pools are searched for.
+
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{
+    engine::{
+        strat_engine::backstore::crypt::consts::{DETACHED_HEADERS_PATH, DEVICE_LINKS_DIR},
+        types::{DevUuid, PoolUuid},
+    },
+    stratis::{StratisError, StratisResult},
+};
+
+lazy_static! {
+    static ref DETACHED_HEADERS: Mutex<HashMap<DevUuid, DetachedHeader>> =
+        Mutex::new(load_detached_headers());
+}
+
+/// The record of the detached header of a device. The device is found
+/// through a link which udev maintains for it in /dev/disk/by-id, so that
+/// it is found even if the kernel gives it a different name.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DetachedHeader {
+    pub pool_uuid: PoolUuid,
+    pub dev_uuid: DevUuid,
+    pub device_link: PathBuf,
+    pub header: PathBuf,
+}
+
+impl DetachedHeader {
+    /// The device node to which the link of the device currently points,
+    /// if the device is present.
+    fn devnode(&self) -> Option<PathBuf> {
+        fs::canonicalize(&self.device_link).ok()
+    }
+}
+
+/// Read the records of detached headers. A missing or unreadable file is
+/// treated as empty.
+fn load_detached_headers() -> HashMap<DevUuid, DetachedHeader> {
+    match fs::read(DETACHED_HEADERS_PATH) {
+        Ok(bytes) => serde_json::from_slice::<Vec<DetachedHeader>>(&bytes)
+            .map(|headers| {
+                headers
+                    .into_iter()
+                    .map(|header| (header.dev_uuid, header))
+                    .collect()
+            })
+            .unwrap_or_else(|e| {
+                warn!(
+                    "Failed to parse the record of detached LUKS2 headers in {}: {}",
+                    DETACHED_HEADERS_PATH, e
+                );
+                HashMap::new()
+            }),
+        Err(e) if e.kind() == ErrorKind::NotFound => HashMap::new(),
+        Err(e) => {
+            warn!(
+                "Failed to read the record of detached LUKS2 headers in {}: {}",
+                DETACHED_HEADERS_PATH, e
+            );
+            HashMap::new()
+        }
+    }
+}
+
+/// Write the records to a temporary file which then replaces the record,
+/// so that the record is never left half written.
+fn save_detached_headers(headers: &HashMap<DevUuid, DetachedHeader>) -> StratisResult<()> {
+    let path = Path::new(DETACHED_HEADERS_PATH);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&tmp_path)?;
+    file.write_all(&serde_json::to_vec(&headers.values().collect::<Vec<_>>())?)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// The link in /dev/disk/by-id which points to the device with the given
+/// device node. A link which gives the WWN of the device is preferred, as
+/// it identifies the device on whichever path it is reached.
+fn device_link(physical_path: &Path) -> StratisResult<PathBuf> {
+    let devnode = fs::canonicalize(physical_path)?;
+    let mut links = fs::read_dir(DEVICE_LINKS_DIR)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|link| fs::canonicalize(link).ok().as_ref() == Some(&devnode))
+        .collect::<Vec<_>>();
+    links.sort_by_key(|link| {
+        (
+            !link
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| name.starts_with("wwn-"))
+                .unwrap_or(false),
+            link.to_owned(),
+        )
+    });
+    links.into_iter().next().ok_or_else(|| {
+        StratisError::Msg(format!(
+            "Device {} has no link in {} by which it could be found if its name changed, so its LUKS2 header can not be detached",
+            physical_path.display(),
+            DEVICE_LINKS_DIR
+        ))
+    })
+}
+
+/// The record of the detached header of the device with the given device
+/// node, if it has one.
+pub fn detached_header(physical_path: &Path) -> Option<DetachedHeader> {
+    let devnode = fs::canonicalize(physical_path).ok()?;
+    DETACHED_HEADERS
+        .lock()
+        .expect("no thread panics while holding the lock")
+        .values()
+        .find(|header| header.devnode().as_ref() == Some(&devnode))
+        .cloned()
+}
+
+/// The device nodes of all devices which have a detached header and are
+/// present.
+pub fn detached_header_devices() -> Vec<PathBuf> {
+    DETACHED_HEADERS
+        .lock()
+        .expect("no thread panics while holding the lock")
+        .values()
+        .filter_map(|header| header.devnode())
+        .collect()
+}
+
+/// The path of the detached header of the device with the given UUID in
+/// the directory header_dir.
+pub fn detached_header_path(header_dir: &Path, dev_uuid: DevUuid) -> PathBuf {
+    header_dir.join(format!("{}.luks2", uuid_to_string!(dev_uuid)))
+}
+
+/// Create an empty header file for the device and record its location
+/// together with the link by which the device is found. The file must not
+/// exist yet.
+pub fn create_detached_header(
+    physical_path: &Path,
+    pool_uuid: PoolUuid,
+    dev_uuid: DevUuid,
+    header_path: &Path,
+) -> StratisResult<()> {
+    let device_link = device_link(physical_path)?;
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(header_path)?;
+
+    let mut headers = DETACHED_HEADERS
+        .lock()
+        .expect("no thread panics while holding the lock");
+    headers.insert(
+        dev_uuid,
+        DetachedHeader {
+            pool_uuid,
+            dev_uuid,
+            device_link,
+            header: header_path.to_owned(),
+        },
+    );
+    if let Err(e) = save_detached_headers(&headers) {
+        headers.remove(&dev_uuid);
+        let _ = fs::remove_file(header_path);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Remove the header file of the device with the given UUID, if it has
+/// one, and forget its location.
+pub fn remove_detached_header(dev_uuid: DevUuid) -> StratisResult<()> {
+    let mut headers = DETACHED_HEADERS
+        .lock()
+        .expect("no thread panics while holding the lock");
+    if let Some(header) = headers.remove(&dev_uuid) {
+        save_detached_headers(&headers)?;
+        if let Err(e) = fs::remove_file(&header.header) {
+            warn!(
+                "Failed to remove the detached LUKS2 header {} of device with UUID {}: {}",
+                header.header.display(),
+                dev_uuid,
+                e
+            );
+        }
+    }
+    Ok(())
+}
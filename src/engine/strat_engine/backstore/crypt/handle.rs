@@ -15,18 +15,19 @@ use crate::{
         strat_engine::{
             backstore::crypt::{
                 consts::{CLEVIS_LUKS_TOKEN_ID, LUKS2_TOKEN_ID},
+                detached_header::remove_detached_header,
                 metadata_handle::CryptMetadataHandle,
                 shared::{
                     acquire_crypt_device, add_keyring_keyslot, clevis_info_from_metadata,
-                    ensure_wiped, get_keyslot_number, interpret_clevis_config, setup_crypt_device,
-                    setup_crypt_handle,
+                    ensure_wiped, get_keyslot_number, interpret_clevis_config,
+                    keyslot_usage_from_metadata, setup_crypt_device, setup_crypt_handle,
                 },
             },
             cmd::{clevis_decrypt, clevis_luks_bind, clevis_luks_regen, clevis_luks_unbind},
             keys::MemoryPrivateFilesystem,
             metadata::StratisIdentifiers,
         },
-        types::{DevicePath, EncryptionInfo, KeyDescription, SizedKeyMemory},
+        types::{DevicePath, EncryptionInfo, KeyDescription, KeyslotUsage, SizedKeyMemory},
     },
     stratis::{StratisError, StratisResult},
 };
@@ -118,6 +119,12 @@ impl CryptHandle {
         self.metadata_handle.luks2_device_path()
     }
 
+    /// Record a new path to the device node of the underlying storage device,
+    /// which has reappeared under a different name.
+    pub fn set_luks2_device_path(&mut self, path: DevicePath) {
+        self.metadata_handle.physical_path = path;
+    }
+
     /// Return the path to the device node of the decrypted contents of the encrypted
     /// storage device. In an encrypted pool, this is the path that can be used to read
     /// the Stratis blockdev metatdata.
@@ -135,6 +142,11 @@ impl CryptHandle {
         get_keyslot_number(&mut self.acquire_crypt_device()?, token_id)
     }
 
+    /// Get the number of keyslots in use on the device.
+    pub fn keyslot_usage(&self) -> StratisResult<KeyslotUsage> {
+        keyslot_usage_from_metadata(&mut self.acquire_crypt_device()?)
+    }
+
     /// Get info for the clevis binding.
     pub fn clevis_info(&self) -> StratisResult<Option<(String, Value)>> {
         clevis_info_from_metadata(&mut self.acquire_crypt_device()?)
@@ -142,6 +154,12 @@ impl CryptHandle {
 
     /// Bind the given device using clevis.
     pub fn clevis_bind(&mut self, pin: &str, json: &Value) -> StratisResult<()> {
+        if self.encryption_info().header_dir.is_some() {
+            return Err(StratisError::Msg(
+                "Clevis can not bind a device whose LUKS2 header is detached".to_string(),
+            ));
+        }
+
         let mut json_owned = json.clone();
         let yes = interpret_clevis_config(pin, &mut json_owned)?;
 
@@ -250,7 +268,12 @@ impl CryptHandle {
             )
         })?;
 
-        add_keyring_keyslot(&mut device, key_desc, Some(Either::Left(key)))?;
+        add_keyring_keyslot(
+            &mut device,
+            key_desc,
+            Some(Either::Left(key)),
+            self.encryption_info().pbkdf_params.as_ref(),
+        )?;
 
         self.metadata_handle.encryption_info.key_description = Some(key_desc.clone());
         Ok(())
@@ -299,6 +322,7 @@ impl CryptHandle {
             &mut device,
             new_key_desc,
             Some(Either::Right(old_key_description)),
+            self.encryption_info().pbkdf_params.as_ref(),
         )?;
         self.metadata_handle.encryption_info.key_description = Some(new_key_desc.clone());
         Ok(())
@@ -324,18 +348,19 @@ impl CryptHandle {
     }
 
     /// Deactivate the device referenced by the current device handle.
-    #[cfg(test)]
     pub fn deactivate(&self) -> StratisResult<()> {
         super::shared::ensure_inactive(&mut self.acquire_crypt_device()?, &self.name)
     }
 
     /// Wipe all LUKS2 metadata on the device safely using libcryptsetup.
+    /// A detached header is removed along with its record.
     pub fn wipe(&self) -> StratisResult<()> {
         ensure_wiped(
             &mut self.acquire_crypt_device()?,
             self.luks2_device_path(),
             &self.name,
-        )
+        )?;
+        remove_detached_header(self.device_identifiers().device_uuid)
     }
 
     /// Get the size of the logical device built on the underlying encrypted physical
@@ -11,8 +11,7 @@ use either::Either;
 use serde_json::Value;
 
 use libcryptsetup_rs::{
-    CryptDevice, CryptInit, CryptVolumeKeyFlags, EncryptionFormat, KeyslotsSize, MetadataSize,
-    TokenInput,
+    CryptDevice, CryptVolumeKeyFlags, EncryptionFormat, KeyslotsSize, MetadataSize, TokenInput,
 };
 
 use crate::{
@@ -21,12 +20,16 @@ use crate::{
             backstore::crypt::{
                 consts::{
                     CLEVIS_LUKS_TOKEN_ID, DEFAULT_CRYPT_KEYSLOTS_SIZE, DEFAULT_CRYPT_METADATA_SIZE,
-                    STRATIS_MEK_SIZE, STRATIS_TOKEN_ID,
+                    STRATIS_TOKEN_ID,
+                },
+                detached_header::{
+                    create_detached_header, detached_header_path, remove_detached_header,
                 },
                 handle::CryptHandle,
                 shared::{
-                    acquire_crypt_device, activate, add_keyring_keyslot, clevis_info_from_metadata,
-                    ensure_wiped, interpret_clevis_config, StratisLuks2Token,
+                    acquire_crypt_device, activate, add_keyring_keyslot, cipher_info_from_metadata,
+                    clevis_info_from_metadata, ensure_wiped, init_crypt_device,
+                    interpret_clevis_config, StratisLuks2Token,
                 },
             },
             cmd::clevis_luks_bind,
@@ -34,7 +37,9 @@ use crate::{
             metadata::StratisIdentifiers,
             names::format_crypt_name,
         },
-        types::{DevUuid, DevicePath, EncryptionInfo, KeyDescription, PoolUuid},
+        types::{
+            CipherInfo, DevUuid, DevicePath, EncryptionInfo, KeyDescription, PbkdfParams, PoolUuid,
+        },
     },
     stratis::StratisResult,
 };
@@ -64,11 +69,56 @@ impl CryptInitializer {
         acquire_crypt_device(&self.physical_path)
     }
 
-    /// Initialize a device with the provided key description and Clevis info.
+    /// Initialize a device with the provided key description and Clevis info,
+    /// encrypting it with the given cipher. If PBKDF parameters are given,
+    /// they are used for the keyslot of the key description. If a header
+    /// directory is given, the LUKS2 header is placed in a file in that
+    /// directory instead of on the device.
     pub fn initialize(
         self,
         key_description: Option<&KeyDescription>,
         clevis_info: Option<(&str, &Value)>,
+        cipher_info: &CipherInfo,
+        pbkdf_params: Option<&PbkdfParams>,
+        header_dir: Option<&Path>,
+    ) -> StratisResult<CryptHandle> {
+        let physical_path = self.physical_path.to_path_buf();
+        let dev_uuid = self.identifiers.device_uuid;
+        if let Some(header_dir) = header_dir {
+            create_detached_header(
+                &physical_path,
+                self.identifiers.pool_uuid,
+                dev_uuid,
+                &detached_header_path(header_dir, dev_uuid),
+            )?;
+        }
+
+        let result = self.initialize_device(
+            key_description,
+            clevis_info,
+            cipher_info,
+            pbkdf_params,
+            header_dir,
+        );
+        if result.is_err() {
+            if let Err(e) = remove_detached_header(dev_uuid) {
+                warn!(
+                    "Failed to remove the detached header of device {}: {}",
+                    physical_path.display(),
+                    e
+                );
+            }
+        }
+        result
+    }
+
+    fn initialize_device(
+        self,
+        key_description: Option<&KeyDescription>,
+        clevis_info: Option<(&str, &Value)>,
+        cipher_info: &CipherInfo,
+        pbkdf_params: Option<&PbkdfParams>,
+        header_dir: Option<&Path>,
     ) -> StratisResult<CryptHandle> {
         let mut clevis_info_owned =
             clevis_info.map(|(pin, config)| (pin.to_owned(), config.clone()));
@@ -81,7 +131,7 @@ impl CryptInitializer {
         };
 
         let mut device = log_on_failure!(
-            CryptInit::init(&self.physical_path),
+            init_crypt_device(&self.physical_path),
             "Failed to acquire context for device {} while initializing; \
             nothing to clean up",
             self.physical_path.display()
@@ -90,7 +140,13 @@ impl CryptInitializer {
             MetadataSize::try_from(DEFAULT_CRYPT_METADATA_SIZE)?,
             KeyslotsSize::try_from(DEFAULT_CRYPT_KEYSLOTS_SIZE)?,
         )?;
-        let result = self.initialize_with_err(device, key_description, clevis_parsed);
+        let result = self.initialize_with_err(
+            device,
+            key_description,
+            clevis_parsed,
+            cipher_info,
+            pbkdf_params,
+        );
         let mut device = match self.acquire_crypt_device() {
             Ok(d) => d,
             Err(e) => {
@@ -111,6 +167,9 @@ impl CryptInitializer {
                 EncryptionInfo {
                     key_description: key_description.cloned(),
                     clevis_info: clevis_info_from_metadata(&mut device)?,
+                    cipher_info: cipher_info_from_metadata(&mut device)?,
+                    pbkdf_params: pbkdf_params.copied(),
+                    header_dir: header_dir.map(|dir| dir.to_owned()),
                 },
                 self.activation_name,
             )),
@@ -132,8 +191,9 @@ impl CryptInitializer {
     fn initialize_with_keyring(
         device: &mut CryptDevice,
         key_description: &KeyDescription,
+        pbkdf_params: Option<&PbkdfParams>,
     ) -> StratisResult<()> {
-        add_keyring_keyslot(device, key_description, None)?;
+        add_keyring_keyslot(device, key_description, None, pbkdf_params)?;
 
         Ok(())
     }
@@ -188,8 +248,9 @@ impl CryptInitializer {
         mut device: CryptDevice,
         key_description: &KeyDescription,
         (pin, json, yes): (&str, &Value, bool),
+        pbkdf_params: Option<&PbkdfParams>,
     ) -> StratisResult<()> {
-        Self::initialize_with_keyring(&mut device, key_description)?;
+        Self::initialize_with_keyring(&mut device, key_description, pbkdf_params)?;
 
         let fs = MemoryPrivateFilesystem::new()?;
         fs.key_op(key_description, |kf| {
@@ -211,13 +272,15 @@ impl CryptInitializer {
         mut device: CryptDevice,
         key_description: Option<&KeyDescription>,
         clevis_info: Option<(&str, &Value, bool)>,
+        cipher_info: &CipherInfo,
+        pbkdf_params: Option<&PbkdfParams>,
     ) -> StratisResult<PathBuf> {
         log_on_failure!(
             device.context_handle().format::<()>(
                 EncryptionFormat::Luks2,
-                ("aes", "xts-plain64"),
+                cipher_info.cipher_and_mode(),
                 None,
-                libcryptsetup_rs::Either::Right(STRATIS_MEK_SIZE),
+                libcryptsetup_rs::Either::Right(cipher_info.key_size_bytes()),
                 None,
             ),
             "Failed to format device {} with LUKS2 header",
@@ -226,11 +289,11 @@ impl CryptInitializer {
 
         let mut device = match (key_description, clevis_info) {
             (Some(kd), Some(ci)) => {
-                self.initialize_with_both(device, kd, ci)?;
+                self.initialize_with_both(device, kd, ci, pbkdf_params)?;
                 self.acquire_crypt_device()?
             }
             (Some(kd), _) => {
-                Self::initialize_with_keyring(&mut device, kd)?;
+                Self::initialize_with_keyring(&mut device, kd, pbkdf_params)?;
                 device
             }
             (_, Some(ci)) => {
@@ -247,6 +310,7 @@ impl CryptInitializer {
                 &StratisLuks2Token {
                     devname: self.activation_name.clone(),
                     identifiers: self.identifiers,
+                    pbkdf_params: pbkdf_params.copied(),
                 }
                 .into(),
             )),
@@ -263,6 +327,8 @@ impl CryptInitializer {
         )
     }
 
+    /// Wipe a device whose initialization failed. A detached header is
+    /// removed by the caller once the device has been wiped.
     pub fn rollback(
         device: &mut CryptDevice,
         physical_path: &Path,
@@ -7,6 +7,7 @@ mod macros;
 
 mod activate;
 mod consts;
+mod detached_header;
 mod handle;
 mod initialize;
 mod metadata_handle;
@@ -15,6 +16,7 @@ mod shared;
 pub use self::{
     activate::CryptActivationHandle,
     consts::CLEVIS_TANG_TRUST_URL,
+    detached_header::{detached_header, detached_header_devices},
     handle::CryptHandle,
     initialize::CryptInitializer,
     metadata_handle::CryptMetadataHandle,
@@ -30,15 +32,17 @@ mod tests {
         env,
         error::Error,
         ffi::CString,
-        fs::{File, OpenOptions},
+        fs::{self, File, OpenOptions},
         io::{self, Read, Write},
         mem::MaybeUninit,
+        os::unix::fs::{symlink, MetadataExt},
         path::Path,
         ptr, slice,
     };
 
     use devicemapper::Sectors;
     use libcryptsetup_rs::{CryptInit, CryptStatusInfo, Either, EncryptionFormat};
+    use nix::sys::stat::{mknod, Mode, SFlag};
 
     use crate::{
         engine::{
@@ -46,14 +50,20 @@ mod tests {
                 backstore::crypt::{
                     consts::{
                         CLEVIS_LUKS_TOKEN_ID, DEFAULT_CRYPT_KEYSLOTS_SIZE,
-                        DEFAULT_CRYPT_METADATA_SIZE, LUKS2_TOKEN_ID, STRATIS_MEK_SIZE,
+                        DEFAULT_CRYPT_METADATA_SIZE, DEVICE_LINKS_DIR, LUKS2_TOKEN_ID,
+                        STRATIS_MEK_SIZE,
                     },
                     shared::acquire_crypt_device,
                 },
+                backstore::devices::validate_devices,
                 keys::MemoryFilesystem,
+                metadata::StratisIdentifiers,
                 tests::{crypt, loopbacked, real},
             },
-            types::{DevUuid, DevicePath, KeyDescription, PoolUuid, UnlockMethod},
+            types::{
+                CipherInfo, DevUuid, DevicePath, DeviceVerdict, KeyDescription, PoolUuid,
+                UnlockMethod,
+            },
         },
         stratis::StratisError,
     };
@@ -78,7 +88,13 @@ mod tests {
             pool_uuid,
             dev_uuid,
         )
-        .initialize(Some(&key_description), None);
+        .initialize(
+            Some(&key_description),
+            None,
+            &CipherInfo::default(),
+            None,
+            None,
+        );
 
         // Initialization cannot occur with a non-existent key
         assert!(result.is_err());
@@ -122,7 +138,13 @@ mod tests {
                     pool_uuid,
                     dev_uuid,
                 )
-                .initialize(Some(key_desc), None)?;
+                .initialize(
+                    Some(key_desc),
+                    None,
+                    &CipherInfo::default(),
+                    None,
+                    None,
+                )?;
                 handles.push(handle);
             }
 
@@ -209,7 +231,7 @@ mod tests {
 
             let handle =
                 CryptInitializer::new(DevicePath::new(path.to_path_buf())?, pool_uuid, dev_uuid)
-                    .initialize(Some(key_desc), None)?;
+                    .initialize(Some(key_desc), None, &CipherInfo::default(), None, None)?;
             let logical_path = handle.activated_device_path();
 
             const WINDOW_SIZE: usize = 1024 * 1024;
@@ -373,6 +395,9 @@ mod tests {
                     "tang",
                     &json!({"url": env::var("TANG_URL")?, "stratis:tang:trust_url": true}),
                 )),
+                &CipherInfo::default(),
+                None,
+                None,
             )?;
 
             let mut device = acquire_crypt_device(handle.luks2_device_path())?;
@@ -414,6 +439,9 @@ mod tests {
                 "tang",
                 &json!({"url": env::var("TANG_URL").unwrap(), "stratis:tang:trust_url": true}),
             )),
+            &CipherInfo::default(),
+            None,
+            None,
         )
         .unwrap();
 
@@ -437,4 +465,103 @@ mod tests {
             test_clevis_initialize,
         );
     }
+
+    /// Verify that a device with a detached LUKS2 header is still found
+    /// after its name changes, since its header is recorded under the link
+    /// to it in /dev/disk/by-id rather than under its device node, and that
+    /// the device is not offered for use in another pool.
+    fn test_detached_header_rename(paths: &[&Path]) {
+        fn crypt_test(
+            paths: &[&Path],
+            key_desc: &KeyDescription,
+        ) -> std::result::Result<(), Box<dyn Error>> {
+            let path = paths.get(0).ok_or_else(|| {
+                Box::new(StratisError::Msg(
+                    "This test only accepts a single device".to_string(),
+                ))
+            })?;
+
+            let header_dir = tempfile::Builder::new()
+                .prefix("stratis_testing")
+                .tempdir()?;
+            let pool_uuid = PoolUuid::new_v4();
+            let dev_uuid = DevUuid::new_v4();
+
+            // Loop devices have no link in /dev/disk/by-id, so the test
+            // makes one.
+            let test_name = format!("stratis-test-{}", uuid_to_string!(dev_uuid));
+            let link = Path::new(DEVICE_LINKS_DIR).join(&test_name);
+            fs::create_dir_all(DEVICE_LINKS_DIR)?;
+            symlink(path, &link)?;
+
+            let mut handle =
+                CryptInitializer::new(DevicePath::new(path.to_path_buf())?, pool_uuid, dev_uuid)
+                    .initialize(
+                        Some(key_desc),
+                        None,
+                        &CipherInfo::default(),
+                        None,
+                        Some(header_dir.path()),
+                    )?;
+            handle.deactivate()?;
+
+            // The device gets a new name: a new device node for the same
+            // device, to which the link now points.
+            let renamed = Path::new("/dev").join(&test_name);
+            mknod(
+                &renamed,
+                SFlag::S_IFBLK,
+                Mode::S_IRUSR | Mode::S_IWUSR,
+                fs::metadata(path)?.rdev(),
+            )?;
+            fs::remove_file(&link)?;
+            symlink(&renamed, &link)?;
+
+            let result = || -> std::result::Result<(), Box<dyn Error>> {
+                if detached_header(path).is_some() {
+                    return Err(Box::new(StratisError::Msg(
+                        "The old name of the device should no longer have a detached header"
+                            .to_string(),
+                    )));
+                }
+                if detached_header(&renamed).map(|header| header.dev_uuid) != Some(dev_uuid) {
+                    return Err(Box::new(StratisError::Msg(
+                        "The new name of the device should have the detached header".to_string(),
+                    )));
+                }
+                let identifiers = CryptMetadataHandle::setup(&renamed)?
+                    .map(|metadata_handle| *metadata_handle.device_identifiers());
+                if identifiers != Some(StratisIdentifiers::new(pool_uuid, dev_uuid)) {
+                    return Err(Box::new(StratisError::Msg(
+                        "The detached header should be read under the new name".to_string(),
+                    )));
+                }
+                if validate_devices(&[&renamed], true)
+                    .into_iter()
+                    .any(|(_, verdict)| verdict != DeviceVerdict::Claimed(pool_uuid))
+                {
+                    return Err(Box::new(StratisError::Msg(
+                        "The device should be claimed by the pool".to_string(),
+                    )));
+                }
+                Ok(())
+            }();
+
+            handle.set_luks2_device_path(DevicePath::new(renamed.clone())?);
+            handle.wipe()?;
+            fs::remove_file(&link)?;
+            fs::remove_file(&renamed)?;
+            result
+        }
+
+        crypt::insert_and_cleanup_key(paths, crypt_test)
+    }
+
+    #[test]
+    fn loop_test_detached_header_rename() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Exactly(1, None),
+            test_detached_header_rename,
+        );
+    }
 }
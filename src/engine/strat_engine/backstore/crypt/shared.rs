@@ -15,8 +15,9 @@ use sha2::{Digest, Sha256};
 use tempfile::TempDir;
 
 use libcryptsetup_rs::{
-    c_uint, CryptActivateFlags, CryptDeactivateFlags, CryptDevice, CryptInit, CryptStatusInfo,
-    CryptVolumeKeyFlags, CryptWipePattern, EncryptionFormat, LibcryptErr,
+    c_uint, CryptActivateFlags, CryptDeactivateFlags, CryptDevice, CryptInit, CryptKdf,
+    CryptKeyslot, CryptPbkdfFlag, CryptPbkdfFlags, CryptPbkdfType, CryptStatusInfo,
+    CryptVolumeKeyFlags, CryptWipePattern, EncryptionFormat, KeyslotInfo, LibcryptErr,
 };
 
 use crate::{
@@ -27,9 +28,11 @@ use crate::{
                     CLEVIS_LUKS_TOKEN_ID, CLEVIS_TANG_TRUST_URL, DEFAULT_CRYPT_KEYSLOTS_SIZE,
                     DEFAULT_CRYPT_METADATA_SIZE, DEVICEMAPPER_PATH, LUKS2_TOKEN_ID,
                     LUKS2_TOKEN_TYPE, SECTOR_SIZE, STRATIS_TOKEN_DEVNAME_KEY,
-                    STRATIS_TOKEN_DEV_UUID_KEY, STRATIS_TOKEN_ID, STRATIS_TOKEN_POOL_UUID_KEY,
-                    STRATIS_TOKEN_TYPE, TOKEN_KEYSLOTS_KEY, TOKEN_TYPE_KEY,
+                    STRATIS_TOKEN_DEV_UUID_KEY, STRATIS_TOKEN_ID, STRATIS_TOKEN_PBKDF_KEY,
+                    STRATIS_TOKEN_POOL_UUID_KEY, STRATIS_TOKEN_TYPE, TOKEN_KEYSLOTS_KEY,
+                    TOKEN_TYPE_KEY,
                 },
+                detached_header::detached_header,
                 handle::CryptHandle,
                 metadata_handle::CryptMetadataHandle,
             },
@@ -38,8 +41,8 @@ use crate::{
             metadata::StratisIdentifiers,
         },
         types::{
-            DevUuid, DevicePath, EncryptionInfo, KeyDescription, PoolUuid, SizedKeyMemory,
-            UnlockMethod,
+            CipherInfo, DevUuid, DevicePath, EncryptionInfo, KeyDescription, KeyslotUsage,
+            PbkdfParams, PoolUuid, SizedKeyMemory, UnlockMethod,
         },
     },
     stratis::{StratisError, StratisResult},
@@ -48,17 +51,31 @@ use crate::{
 pub struct StratisLuks2Token {
     pub devname: String,
     pub identifiers: StratisIdentifiers,
+    /// The PBKDF parameters requested for the keyring keyslot, recorded so
+    /// that they can be audited and applied again when the keyslot changes.
+    pub pbkdf_params: Option<PbkdfParams>,
 }
 
 impl Into<Value> for StratisLuks2Token {
     fn into(self) -> Value {
-        json!({
+        let mut json = json!({
             TOKEN_TYPE_KEY: STRATIS_TOKEN_TYPE,
             TOKEN_KEYSLOTS_KEY: [],
             STRATIS_TOKEN_DEVNAME_KEY: self.devname,
             STRATIS_TOKEN_POOL_UUID_KEY: self.identifiers.pool_uuid.to_string(),
             STRATIS_TOKEN_DEV_UUID_KEY: self.identifiers.device_uuid.to_string(),
-        })
+        });
+        if let (Some(params), Some(map)) = (self.pbkdf_params, json.as_object_mut()) {
+            map.insert(
+                STRATIS_TOKEN_PBKDF_KEY.to_string(),
+                json!({
+                    "iterations": params.iterations,
+                    "memory_kib": params.memory_kib,
+                    "parallelism": params.parallelism,
+                }),
+            );
+        }
+        json
     }
 }
 
@@ -104,13 +121,24 @@ impl<'a> TryFrom<&'a Value> for StratisLuks2Token {
             STRATIS_TOKEN_DEV_UUID_KEY,
             DevUuid
         );
+        let pbkdf_params = pbkdf_params_from_token(map)?;
         Ok(StratisLuks2Token {
             devname,
             identifiers: StratisIdentifiers::new(pool_uuid, dev_uuid),
+            pbkdf_params,
         })
     }
 }
 
+/// Read the PBKDF parameters from the map of a Stratis token. Tokens written
+/// before the parameters could be specified have no PBKDF key.
+fn pbkdf_params_from_token(map: &Map<String, Value>) -> StratisResult<Option<PbkdfParams>> {
+    match map.get(STRATIS_TOKEN_PBKDF_KEY) {
+        Some(value) => Ok(Some(serde_json::from_value(value.clone())?)),
+        None => Ok(None),
+    }
+}
+
 /// Acquire a crypt device handle or return an error. This serves as a wrapper
 /// around device_from_physical_path removing the Option type.
 pub fn acquire_crypt_device(physical_path: &Path) -> StratisResult<CryptDevice> {
@@ -146,8 +174,24 @@ pub fn add_keyring_keyslot(
     device: &mut CryptDevice,
     key_description: &KeyDescription,
     pass: Option<Either<SizedKeyMemory, &KeyDescription>>,
+    pbkdf_params: Option<&PbkdfParams>,
 ) -> StratisResult<()> {
     let key = key_desc_to_passphrase(key_description)?;
+    if let Some(params) = pbkdf_params {
+        log_on_failure!(
+            device.settings_handle().set_pbkdf_type(&CryptPbkdfType {
+                type_: CryptKdf::Argon2Id,
+                hash: "sha256".to_string(),
+                time_ms: 0,
+                iterations: params.iterations,
+                max_memory_kb: params.memory_kib,
+                parallel_threads: params.parallelism,
+                flags: CryptPbkdfFlags::new(vec![CryptPbkdfFlag::NoBenchmark]),
+            }),
+            "Failed to set the PBKDF parameters for the keyring keyslot"
+        );
+        info!("Using PBKDF {} for the keyring keyslot", params);
+    }
     let keyslot = match pass {
         Some(Either::Left(ref pass)) => {
             log_on_failure!(
@@ -246,6 +290,10 @@ pub fn setup_crypt_metadata_handle(
         None => None,
     };
     let clevis_info = clevis_info_from_metadata(device)?;
+    let cipher_info = cipher_info_from_metadata(device)?;
+    let pbkdf_params = pbkdf_params_from_metadata(device)?;
+    let header_dir = detached_header(physical_path)
+        .and_then(|header| header.header.parent().map(|dir| dir.to_owned()));
 
     Ok(Some(CryptMetadataHandle::new(
         DevicePath::new(physical_path.to_owned())?,
@@ -253,6 +301,9 @@ pub fn setup_crypt_metadata_handle(
         EncryptionInfo {
             key_description,
             clevis_info,
+            cipher_info,
+            pbkdf_params,
+            header_dir,
         },
     )))
 }
@@ -302,11 +353,23 @@ pub fn setup_crypt_handle(
     )))
 }
 
+/// Acquire a context for the device at the physical path, which reads the
+/// LUKS2 header from the detached header of the device if it has one.
+pub fn init_crypt_device(physical_path: &Path) -> Result<CryptDevice, LibcryptErr> {
+    match detached_header(physical_path) {
+        Some(header) => CryptInit::init_with_data_device(libcryptsetup_rs::Either::Right((
+            &header.header,
+            physical_path,
+        ))),
+        None => CryptInit::init(physical_path),
+    }
+}
+
 /// Create a device handle and load the LUKS2 header into memory from
 /// a physical path.
 fn device_from_physical_path(physical_path: &Path) -> StratisResult<Option<CryptDevice>> {
     let mut device = log_on_failure!(
-        CryptInit::init(physical_path),
+        init_crypt_device(physical_path),
         "Failed to acquire a context for device {}",
         physical_path.display()
     );
@@ -350,6 +413,45 @@ pub fn clevis_info_from_metadata(
     pin_dispatch(&subjson).map(Some)
 }
 
+/// Read the cipher and the size of the media encryption key from the LUKS2
+/// header of the device.
+pub fn cipher_info_from_metadata(device: &mut CryptDevice) -> StratisResult<CipherInfo> {
+    let cipher = device.status_handle().get_cipher()?;
+    let mode = device.status_handle().get_cipher_mode()?;
+    let key_size = convert_int!(device.status_handle().get_volume_key_size(), i32, usize)?;
+    Ok(CipherInfo {
+        cipher: format!("{}-{}", cipher, mode),
+        key_size: key_size * 8,
+    })
+}
+
+/// Count the keyslots in the LUKS2 header of the device that hold a key.
+pub fn keyslot_usage_from_metadata(device: &mut CryptDevice) -> StratisResult<KeyslotUsage> {
+    let total = CryptKeyslot::max_keyslots(EncryptionFormat::Luks2)?;
+    let mut used = 0;
+    for keyslot in 0..total {
+        if let KeyslotInfo::Active | KeyslotInfo::ActiveLast =
+            device.keyslot_handle().status(keyslot)?
+        {
+            used += 1;
+        }
+    }
+    Ok(KeyslotUsage { used, total })
+}
+
+/// Query the Stratis token for the PBKDF parameters requested for the
+/// keyring keyslot, if any were.
+pub fn pbkdf_params_from_metadata(device: &mut CryptDevice) -> StratisResult<Option<PbkdfParams>> {
+    let json = log_on_failure!(
+        device.token_handle().json_get(STRATIS_TOKEN_ID),
+        "Failed to get Stratis JSON token from LUKS2 metadata"
+    );
+    match json.as_object() {
+        Some(map) => pbkdf_params_from_token(map),
+        None => Err(StratisError::Crypt(LibcryptErr::InvalidConversion)),
+    }
+}
+
 /// Interpret non-Clevis keys that may contain additional information about
 /// how to configure Clevis when binding. Remove any expected non-Clevis keys
 /// from the configuration.
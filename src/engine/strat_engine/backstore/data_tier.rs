@@ -16,11 +16,11 @@ use crate::{
                 blockdevmgr::{BlkDevSegment, BlockDevMgr},
                 shared::{coalesce_blkdevsegs, metadata_to_segment},
             },
-            serde_structs::{BaseDevSave, BlockDevSave, DataTierSave, Recordable},
+            serde_structs::{BaseDevSave, BlockDevSave, DataTierSave, Recordable, StripeSave},
         },
-        types::{BlockDevTier, DevUuid, PoolUuid},
+        types::{AllocationStrategy, BlockDevTier, DevUuid, PoolUuid, StripeConfig},
     },
-    stratis::StratisResult,
+    stratis::{StratisError, StratisResult},
 };
 
 /// Handles the lowest level, base layer of this tier.
@@ -30,6 +30,14 @@ pub struct DataTier {
     pub block_mgr: BlockDevMgr,
     /// The list of segments granted by block_mgr and used by dm_device
     pub segments: Vec<BlkDevSegment>,
+    /// The stripe configuration, if the tier is striped. If it is, the
+    /// segments form consecutive groups of stripe count segments of equal
+    /// length, each group allocated by a single call to alloc().
+    pub stripe: Option<StripeConfig>,
+    /// The blockdevs from which space is allocated first, where possible.
+    preferred: Vec<DevUuid>,
+    /// The strategy by which space is chosen for an allocation.
+    allocation_strategy: AllocationStrategy,
 }
 
 impl DataTier {
@@ -45,9 +53,26 @@ impl DataTier {
             .map(&mapper)
             .collect::<StratisResult<Vec<_>>>()?;
 
+        let stripe = data_tier_save.stripe.as_ref().map(|s| StripeConfig {
+            count: s.count,
+            size: s.size,
+        });
+        if let Some(stripe) = stripe {
+            if segments.len() % usize::from(stripe.count) != 0 {
+                return Err(StratisError::Msg(format!(
+                    "The {} segments allocated in the data tier can not be divided among {} stripes",
+                    segments.len(),
+                    stripe.count
+                )));
+            }
+        }
+
         Ok(DataTier {
             block_mgr,
             segments,
+            stripe,
+            preferred: Vec::new(),
+            allocation_strategy: AllocationStrategy::default(),
         })
     }
 
@@ -60,7 +85,47 @@ impl DataTier {
         DataTier {
             block_mgr,
             segments: vec![],
+            stripe: None,
+            preferred: Vec::new(),
+            allocation_strategy: AllocationStrategy::default(),
+        }
+    }
+
+    /// Stripe all future allocations from this tier.
+    ///
+    /// Returns an error if any segments have already been allocated, or if
+    /// there are not at least stripe.count blockdevs each with room for at
+    /// least one chunk.
+    ///
+    /// FIXME: A data tier can only be striped when it is created. Converting
+    /// a linear data tier to a striped one would require copying the
+    /// allocated segments to newly allocated striped segments while the cap
+    /// device is in use, for example by temporarily mirroring the origin
+    /// sub-device, and then returning the old segments to their blockdevs.
+    /// Neither is possible yet: there is no mechanism for moving data
+    /// within the data tier, and space allocated from the data tier is never
+    /// returned.
+    pub fn set_stripe(&mut self, stripe: StripeConfig) -> StratisResult<()> {
+        if !self.segments.is_empty() {
+            return Err(StratisError::Msg(
+                "A data tier can only be striped before any space is allocated from it".into(),
+            ));
+        }
+
+        let usable = self
+            .block_mgr
+            .blockdevs()
+            .iter()
+            .filter(|(_, bd)| bd.largest_available() >= stripe.size)
+            .count();
+        if usable < usize::from(stripe.count) {
+            return Err(StratisError::Msg(format!(
+                "Only {} blockdevs have room for a stripe of size {}, but a stripe count of {} was requested",
+                usable, stripe.size, stripe.count
+            )));
         }
+        self.stripe = Some(stripe);
+        Ok(())
     }
 
     /// Add the given paths to self. Return UUIDs of the new blockdevs
@@ -70,11 +135,100 @@ impl DataTier {
         self.block_mgr.add(pool_uuid, paths)
     }
 
+    /// Remove the blockdev with the given UUID from the data tier and wipe
+    /// it. No space on the blockdev may be allocated, and at least one
+    /// blockdev must remain in the data tier.
+    /// WARNING: metadata changing event
+    pub fn remove(&mut self, uuid: DevUuid) -> StratisResult<()> {
+        if self.segments.iter().any(|seg| seg.uuid == uuid) {
+            return Err(StratisError::Msg(format!(
+                "Space on blockdev {} is in use; it must be evacuated before it can be removed",
+                uuid
+            )));
+        }
+        if self.block_mgr.blockdevs().len() <= 1 {
+            return Err(StratisError::Msg(format!(
+                "Blockdev {} is the only blockdev in the data tier and can not be removed",
+                uuid
+            )));
+        }
+        self.block_mgr.remove_blockdevs(&[uuid])?;
+        self.preferred.retain(|preferred| *preferred != uuid);
+        Ok(())
+    }
+
+    /// Allocate from the given blockdevs first in all future allocations
+    /// from this tier, where possible. An empty list removes the preference.
+    /// A striped tier spreads every allocation over the blockdevs with the
+    /// most space available and does not take the preference into account.
+    pub fn set_preferred(&mut self, preferred: &[DevUuid]) -> StratisResult<()> {
+        let blockdevs = self.block_mgr.blockdevs();
+        if let Some(uuid) = preferred
+            .iter()
+            .find(|uuid| !blockdevs.iter().any(|(dev_uuid, _)| dev_uuid == *uuid))
+        {
+            return Err(StratisError::Msg(format!(
+                "Blockdev {} does not belong to the data tier",
+                uuid
+            )));
+        }
+        if self.stripe.is_some() && !preferred.is_empty() {
+            warn!("The data tier is striped; the preferred blockdevs will not be taken into account when allocating");
+        }
+        self.preferred = preferred.to_vec();
+        Ok(())
+    }
+
+    /// The strategy by which space is chosen for an allocation.
+    pub fn allocation_strategy(&self) -> AllocationStrategy {
+        self.allocation_strategy
+    }
+
+    /// Choose space according to strategy in all future allocations from
+    /// this tier. A striped tier always allocates from the blockdevs with
+    /// the most space available.
+    pub fn set_allocation_strategy(&mut self, strategy: AllocationStrategy) {
+        self.allocation_strategy = strategy;
+    }
+
     /// Allocate at least request sectors from unallocated segments in
     /// block devices belonging to the data tier. Return true if requested
     /// amount or more was allocated, otherwise, false.
     pub fn alloc(&mut self, request: Sectors) -> bool {
-        match self.block_mgr.alloc_space(&[request]) {
+        if let Some(stripe) = self.stripe {
+            return self.alloc_striped(stripe, request);
+        }
+        let preferred = self.preferred.clone();
+        self.alloc_linear(request, &preferred)
+    }
+
+    /// Allocate at least request sectors, as alloc() does, but only from
+    /// the blockdevs with the given UUIDs. Return true if the requested
+    /// amount or more was allocated, otherwise false. A striped tier can not
+    /// allocate from particular blockdevs, so nothing is allocated from it.
+    pub fn alloc_from(&mut self, request: Sectors, uuids: &[DevUuid]) -> bool {
+        if self.stripe.is_some() {
+            return false;
+        }
+        let available = self
+            .block_mgr
+            .blockdevs()
+            .iter()
+            .filter(|(uuid, _)| uuids.contains(uuid))
+            .map(|(_, bd)| bd.available())
+            .sum::<Sectors>();
+        // The given blockdevs are exhausted first, so if they have enough
+        // space, nothing is allocated from the others.
+        available >= request && self.alloc_linear(request, uuids)
+    }
+
+    /// Allocate at least request sectors from the blockdevs, preferring the
+    /// given ones.
+    fn alloc_linear(&mut self, request: Sectors, preferred: &[DevUuid]) -> bool {
+        match self
+            .block_mgr
+            .alloc_space_preferring(&[request], preferred, self.allocation_strategy)
+        {
             Some(segments) => {
                 self.segments = coalesce_blkdevsegs(
                     &self.segments,
@@ -90,6 +244,23 @@ impl DataTier {
         }
     }
 
+    /// Allocate at least request sectors as a group of stripe.count segments
+    /// of equal length, each a multiple of the stripe size.
+    fn alloc_striped(&mut self, stripe: StripeConfig, request: Sectors) -> bool {
+        let per_stripe = stripe.size * u64::from(stripe.count);
+        let per_dev = ((request + per_stripe - Sectors(1)) / per_stripe) * stripe.size;
+        match self
+            .block_mgr
+            .alloc_striped(usize::from(stripe.count), per_dev)
+        {
+            Some(segments) => {
+                self.segments.extend(segments);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// The sum of the lengths of all the sectors that have been mapped to an
     /// upper device.
     #[cfg(test)]
@@ -160,6 +331,10 @@ impl Recordable<DataTierSave> for DataTier {
                 allocs: vec![self.segments.record()],
                 devs: self.block_mgr.record(),
             },
+            stripe: self.stripe.map(|s| StripeSave {
+                count: s.count,
+                size: s.size,
+            }),
         }
     }
 }
@@ -5,6 +5,7 @@
 // Functions for dealing with devices.
 
 use std::{
+    cmp::max,
     collections::{HashMap, HashSet},
     fs::OpenOptions,
     path::{Path, PathBuf},
@@ -21,17 +22,24 @@ use crate::{
         strat_engine::{
             backstore::{
                 blockdev::{StratBlockDev, UnderlyingDevice},
-                crypt::{CryptHandle, CryptInitializer},
+                crypt::{crypt_metadata_size, detached_header, CryptHandle, CryptInitializer},
+            },
+            device::{
+                blkdev_logical_sector_size, blkdev_optimal_io_size, blkdev_physical_sector_size,
+                blkdev_size,
             },
-            device::blkdev_size,
             metadata::{
-                device_identifiers, disown_device, BlockdevSize, MDADataSize, StratisIdentifiers,
-                BDA,
+                bda_extended_size, device_identifiers, disown_device, read_device_identifiers,
+                BlockdevSize, MDADataSize, StratisIdentifiers, BDA,
             },
             names::KeyDescription,
+            thinpool::DATA_BLOCK_SIZE,
             udev::{block_device_apply, decide_ownership, get_udev_property, UdevOwnership},
         },
-        types::{DevUuid, DevicePath, EncryptionInfo, PoolUuid},
+        types::{
+            AllocationPreview, CipherInfo, DevUuid, DevicePath, DeviceVerdict, EncryptionInfo,
+            PbkdfParams, PoolUuid,
+        },
     },
     stratis::{StratisError, StratisResult},
 };
@@ -105,6 +113,19 @@ fn dev_info(
     Device,
 )> {
     let (ownership, devnum, hw_id) = udev_info(devnode)?;
+
+    // A device with a detached LUKS2 header carries no signature, so only
+    // the record of its header shows that it belongs to Stratis.
+    if let Some(header) = detached_header(devnode) {
+        let f = OpenOptions::new().read(true).open(&devnode)?;
+        return Ok((
+            hw_id,
+            blkdev_size(&f)?,
+            Some(StratisIdentifiers::new(header.pool_uuid, header.dev_uuid)),
+            devnum,
+        ));
+    }
+
     match ownership {
         UdevOwnership::Luks | UdevOwnership::MultipathMember | UdevOwnership::Theirs => {
             let err_str = format!(
@@ -373,6 +394,125 @@ pub fn process_and_verify_devices(
         })
 }
 
+/// Examine the devices, without modifying them, and decide for each whether
+/// it could be used to create a new pool. The checks are those that are
+/// made when initializing devices; in addition, all devices must have the
+/// same logical sector size as the first device that passes the other
+/// checks. Verdicts are returned in the order in which the devices were
+/// specified.
+pub fn validate_devices(paths: &[&Path], encrypted: bool) -> Vec<(PathBuf, DeviceVerdict)> {
+    // Return the device number, size, and logical sector size of a device
+    // that may be usable or, if the device can not be used because of how
+    // it is owned, the verdict for it.
+    fn examine(devnode: &Path) -> StratisResult<Result<(Device, Bytes, u64), DeviceVerdict>> {
+        if let Some(header) = detached_header(devnode) {
+            return Ok(Err(DeviceVerdict::Claimed(header.pool_uuid)));
+        }
+
+        let (ownership, devno, _) = udev_info(devnode)?;
+        match ownership {
+            UdevOwnership::Luks | UdevOwnership::MultipathMember | UdevOwnership::Theirs => {
+                Ok(Err(DeviceVerdict::ForeignSignature(ownership.to_string())))
+            }
+            UdevOwnership::Stratis | UdevOwnership::Unowned => {
+                let mut f = OpenOptions::new().read(true).open(&devnode)?;
+                match read_device_identifiers(&mut f)? {
+                    Some(ids) => Ok(Err(DeviceVerdict::Claimed(ids.pool_uuid))),
+                    None if ownership == UdevOwnership::Stratis => Err(StratisError::Msg(
+                        "udev identified the device as a Stratis device but device metadata does not show that it is a Stratis device".into(),
+                    )),
+                    None => Ok(Ok((
+                        devno,
+                        blkdev_size(&f)?,
+                        blkdev_logical_sector_size(&f)?,
+                    ))),
+                }
+            }
+        }
+    }
+
+    let overhead = bda_extended_size(MDADataSize::default()).sectors().bytes()
+        + if encrypted {
+            Bytes::from(crypt_metadata_size())
+        } else {
+            Bytes(0)
+        };
+
+    let mut devnos: HashMap<Device, PathBuf> = HashMap::new();
+    let mut reference_sector_size = None;
+    paths
+        .iter()
+        .map(|devnode| {
+            let verdict = match examine(devnode) {
+                Err(err) => DeviceVerdict::Unavailable(err.to_string()),
+                Ok(Err(verdict)) => verdict,
+                Ok(Ok((devno, size, sector_size))) => {
+                    if let Some(other) = devnos.get(&devno) {
+                        DeviceVerdict::Duplicate(other.to_owned())
+                    } else {
+                        devnos.insert(devno, devnode.to_path_buf());
+                        if size < MIN_DEV_SIZE {
+                            DeviceVerdict::TooSmall(size)
+                        } else if *reference_sector_size.get_or_insert(sector_size) != sector_size {
+                            DeviceVerdict::WrongSectorSize(sector_size)
+                        } else {
+                            DeviceVerdict::Usable(size - overhead)
+                        }
+                    }
+                }
+            };
+            (devnode.to_path_buf(), verdict)
+        })
+        .collect()
+}
+
+/// Describe how the device would be laid out if it were added to a pool,
+/// together with the I/O topology that it reports. The LUKS2 header of an
+/// encrypted device precedes the Stratis metadata.
+///
+/// FIXME: The backstore allocates from the data region of a device at
+/// sector granularity and does not align its allocations to the topology
+/// of the device; data_aligned only reports whether the start of the data
+/// region happens to suit it.
+///
+/// Precondition: the device is not in use.
+pub fn allocation_preview(devnode: &Path, encrypted: bool) -> StratisResult<AllocationPreview> {
+    let f = OpenOptions::new().read(true).open(devnode)?;
+    let size = blkdev_size(&f)?.sectors();
+    let logical_sector_size = blkdev_logical_sector_size(&f)?;
+    let physical_sector_size = blkdev_physical_sector_size(&f)?;
+    let optimal_io_size = blkdev_optimal_io_size(&f)?;
+    let alignment = max(physical_sector_size, optimal_io_size);
+
+    let mut layout = Vec::new();
+    let mut start = Sectors(0);
+    if encrypted {
+        let length = Bytes::from(crypt_metadata_size()).sectors();
+        layout.push(("luks2_header".to_string(), start, length));
+        start += length;
+    }
+    let length = bda_extended_size(MDADataSize::default()).sectors();
+    layout.push(("stratis_metadata".to_string(), start, length));
+    start += length;
+    if start >= size {
+        return Err(StratisError::Msg(format!(
+            "Device {} is too small to hold any data",
+            devnode.display()
+        )));
+    }
+    layout.push(("data".to_string(), start, size - start));
+
+    Ok(AllocationPreview {
+        logical_sector_size,
+        physical_sector_size,
+        optimal_io_size,
+        alignment,
+        extent_size: DATA_BLOCK_SIZE,
+        data_aligned: *start.bytes() % u128::from(alignment) == 0,
+        layout,
+    })
+}
+
 /// Initialze devices in devices.
 /// Clean up previously initialized devices if initialization of any single
 /// device fails during initialization. Log at the warning level if cleanup
@@ -398,9 +538,10 @@ pub fn initialize_devices(
     }
 
     /// Initialize an encrypted device on the given physical device
-    /// using the pool and device UUIDs of the new Stratis block device
-    /// and the key description for the key to use for encrypting the
-    /// data.
+    /// using the pool and device UUIDs of the new Stratis block device,
+    /// the key description for the key to use for encrypting the
+    /// data, and the cipher to encrypt the data with. If a header
+    /// directory is given, the LUKS2 header is detached from the device.
     ///
     /// On failure, this method will roll back the initialization
     /// process and clean up the device that it has just initialized.
@@ -410,13 +551,22 @@ pub fn initialize_devices(
         dev_uuid: DevUuid,
         key_description: Option<&KeyDescription>,
         enable_clevis: Option<(&str, &Value)>,
+        cipher_info: &CipherInfo,
+        pbkdf_params: Option<&PbkdfParams>,
+        header_dir: Option<&Path>,
     ) -> StratisResult<(CryptHandle, Device, Sectors)> {
         let handle = CryptInitializer::new(
             DevicePath::new(physical_path.to_owned())?,
             pool_uuid,
             dev_uuid,
         )
-        .initialize(key_description, enable_clevis)?;
+        .initialize(
+            key_description,
+            enable_clevis,
+            cipher_info,
+            pbkdf_params,
+            header_dir,
+        )?;
 
         let device_size = match handle.logical_device_size() {
             Ok(size) => size,
@@ -549,6 +699,9 @@ pub fn initialize_devices(
                     .clevis_info
                     .as_ref()
                     .map(|(pin, json)| (pin.as_str(), json)),
+                &encryption_info.cipher_info,
+                encryption_info.pbkdf_params.as_ref(),
+                encryption_info.header_dir.as_deref(),
             )
             .map(|(handle, devno, devsize)| {
                 debug!(
@@ -698,6 +851,7 @@ mod tests {
             &EncryptionInfo {
                 key_description: key_description.cloned(),
                 clevis_info: None,
+                ..EncryptionInfo::default()
             },
         )?;
 
@@ -951,6 +1105,7 @@ mod tests {
             &EncryptionInfo {
                 key_description: key_desc.cloned(),
                 clevis_info: None,
+                ..EncryptionInfo::default()
             },
         )
         .is_ok()
@@ -12,14 +12,16 @@ mod data_tier;
 mod devices;
 mod range_alloc;
 mod shared;
+mod stripe;
 
 pub use self::{
     backstore::Backstore,
     blockdev::{StratBlockDev, UnderlyingDevice},
     crypt::{
-        crypt_metadata_size, CryptActivationHandle, CryptHandle, CryptMetadataHandle,
-        CLEVIS_TANG_TRUST_URL,
+        crypt_metadata_size, detached_header, detached_header_devices, CryptActivationHandle,
+        CryptHandle, CryptMetadataHandle, CLEVIS_TANG_TRUST_URL,
     },
+    devices::{allocation_preview, validate_devices},
 };
 
 #[cfg(test)]
@@ -10,7 +10,7 @@ use std::{
 use devicemapper::Sectors;
 
 use crate::{
-    engine::strat_engine::metadata::BlockdevSize,
+    engine::{strat_engine::metadata::BlockdevSize, types::AllocationStrategy},
     stratis::{StratisError, StratisResult},
 };
 
@@ -350,6 +350,16 @@ impl RangeAllocator {
         self.segments.sum()
     }
 
+    /// The length of the largest contiguous range of available sectors
+    pub fn largest_available(&self) -> Sectors {
+        self.segments
+            .complement()
+            .iter()
+            .map(|(_, &len)| len)
+            .max()
+            .unwrap_or(Sectors(0))
+    }
+
     #[allow(dead_code)]
     /// Just allocate all the sectors that are available.
     pub fn request_all(&mut self) -> PerDevSegments {
@@ -363,13 +373,31 @@ impl RangeAllocator {
         segs
     }
 
-    /// Attempt to allocate.
+    /// Attempt to allocate, choosing among the free ranges according to
+    /// strategy.
     /// Returns a PerDevSegments object containing the allocated ranges.
-    pub fn request(&mut self, amount: Sectors) -> PerDevSegments {
+    pub fn request(&mut self, amount: Sectors, strategy: AllocationStrategy) -> PerDevSegments {
         let mut segs = PerDevSegments::new(self.segments.limit());
         let mut needed = amount;
 
-        for (&start, &len) in self.segments.complement().iter() {
+        let mut free = self
+            .segments
+            .complement()
+            .iter()
+            .map(|(&start, &len)| (start, len))
+            .collect::<Vec<_>>();
+        if strategy == AllocationStrategy::BestFit {
+            match free
+                .iter()
+                .filter(|&&(_, len)| len >= amount)
+                .min_by_key(|&&(_, len)| len)
+            {
+                Some(&range) => free = vec![range],
+                None => free.sort_by(|(_, len_a), (_, len_b)| len_b.cmp(len_a)),
+            }
+        }
+
+        for (start, len) in free {
             if needed == Sectors(0) {
                 break;
             }
@@ -404,7 +432,7 @@ impl RangeAllocator {
             },
         };
         let result1 = dup1.request_all();
-        let result2 = dup2.request(dup2.available());
+        let result2 = dup2.request(dup2.available(), AllocationStrategy::FirstFit);
         assert_eq!(result1.limit, result2.limit);
         assert_eq!(result1.used, result2.used);
 
@@ -441,20 +469,58 @@ mod tests {
 
         assert_eq!(allocator.used(), Sectors(100));
         assert_eq!(allocator.available(), Sectors(28));
+        assert_eq!(allocator.largest_available(), Sectors(18));
 
-        let request = allocator.request(Sectors(50));
+        let request = allocator.request(Sectors(50), AllocationStrategy::FirstFit);
         assert_eq!(request.len(), 2);
         assert_eq!(request.sum(), Sectors(28));
         assert_eq!(allocator.used(), Sectors(128));
         assert_eq!(allocator.available(), Sectors(0));
+        assert_eq!(allocator.largest_available(), Sectors(0));
 
         let available = allocator.available();
-        allocator.request(available);
+        allocator.request(available, AllocationStrategy::FirstFit);
         assert_eq!(allocator.available(), Sectors(0));
 
         allocator.invariant();
     }
 
+    #[test]
+    /// Verify that a best-fit request is satisfied from the smallest free
+    /// range that holds it, and otherwise from the largest free ranges
+    /// first, while a first-fit request takes the lowest free ranges.
+    fn test_allocator_best_fit() {
+        // Free ranges: (0, 10), (20, 30), (60, 5), (70, 58)
+        let used = [
+            (Sectors(10), Sectors(10)),
+            (Sectors(50), Sectors(10)),
+            (Sectors(65), Sectors(5)),
+        ];
+
+        let mut allocator = RangeAllocator::new(BlockdevSize::new(Sectors(128)), &used).unwrap();
+        let request = allocator.request(Sectors(25), AllocationStrategy::BestFit);
+        assert_eq!(
+            request.iter().collect::<Vec<_>>(),
+            vec![(&Sectors(20), &Sectors(25))]
+        );
+
+        let mut allocator = RangeAllocator::new(BlockdevSize::new(Sectors(128)), &used).unwrap();
+        let request = allocator.request(Sectors(70), AllocationStrategy::BestFit);
+        assert_eq!(
+            request.iter().collect::<Vec<_>>(),
+            vec![(&Sectors(20), &Sectors(12)), (&Sectors(70), &Sectors(58))]
+        );
+
+        let mut allocator = RangeAllocator::new(BlockdevSize::new(Sectors(128)), &used).unwrap();
+        let request = allocator.request(Sectors(25), AllocationStrategy::FirstFit);
+        assert_eq!(
+            request.iter().collect::<Vec<_>>(),
+            vec![(&Sectors(0), &Sectors(10)), (&Sectors(20), &Sectors(15))]
+        );
+
+        allocator.invariant();
+    }
+
     #[test]
     // Verify some proper functioning when allocator initialized with ranges.
     fn test_allocator_initialized_with_range() {
@@ -525,7 +591,7 @@ mod tests {
     fn test_allocator_failures_range_overwrite() {
         let mut allocator = RangeAllocator::new(BlockdevSize::new(Sectors(128)), &[]).unwrap();
 
-        let request = allocator.request(Sectors(128));
+        let request = allocator.request(Sectors(128), AllocationStrategy::FirstFit);
         assert_eq!(allocator.used(), Sectors(128));
         assert_eq!(
             request.iter().collect::<Vec<_>>(),
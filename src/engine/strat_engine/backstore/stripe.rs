@@ -0,0 +1,126 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+// Code to handle the DM device that stripes a striped data tier.
+
+use itertools::Itertools;
+
+use devicemapper::{device_exists, DevId, Device, DmNameBuf, DmOptions, Sectors};
+
+use crate::{
+    engine::{
+        strat_engine::{
+            backstore::blockdevmgr::BlkDevSegment,
+            dm::{get_dm, retry_dm},
+            names::{format_backstore_ids, CacheRole},
+        },
+        types::{PoolUuid, StripeConfig},
+    },
+    stratis::StratisResult,
+};
+
+/// Make a table for the stripe device. Each group of stripe.count
+/// consecutive segments becomes one line of the table.
+///
+/// Precondition: segments.len() % stripe.count == 0
+/// Precondition: the segments within each group are of equal length
+fn stripe_table(
+    stripe: StripeConfig,
+    segments: &[BlkDevSegment],
+) -> (Sectors, Vec<(u64, u64, String, String)>) {
+    let mut start = Sectors(0);
+    let table = segments
+        .chunks(usize::from(stripe.count))
+        .map(|group| {
+            let length = group.iter().map(|seg| seg.segment.length).sum::<Sectors>();
+            let params = format!(
+                "{} {} {}",
+                group.len(),
+                *stripe.size,
+                group
+                    .iter()
+                    .map(|seg| format!("{} {}", seg.segment.device, *seg.segment.start))
+                    .join(" ")
+            );
+            let line = (*start, *length, "striped".to_string(), params);
+            start += length;
+            line
+        })
+        .collect();
+    (start, table)
+}
+
+/// A DM device with a striped target, which lies below the origin
+/// sub-device of a pool with a striped data tier. The devicemapper crate
+/// has no device type for the striped target, so the device is managed
+/// with the DM ioctls directly.
+#[derive(Debug)]
+pub struct StripeDev {
+    name: DmNameBuf,
+    device: Device,
+    size: Sectors,
+}
+
+impl StripeDev {
+    /// Set up the stripe device with all the segments allocated in the data
+    /// tier, or load them into an existing device with the same name.
+    pub fn setup(
+        pool_uuid: PoolUuid,
+        stripe: StripeConfig,
+        segments: &[BlkDevSegment],
+    ) -> StratisResult<StripeDev> {
+        let (name, uuid) = format_backstore_ids(pool_uuid, CacheRole::StripeSub);
+        let dm = get_dm();
+        let exists = device_exists(dm, &name)?;
+        let device = if exists {
+            dm.device_info(&DevId::Name(&name))?.device()
+        } else {
+            dm.device_create(&name, Some(&uuid), &DmOptions::new())?
+                .device()
+        };
+
+        let mut dev = StripeDev {
+            name,
+            device,
+            size: Sectors(0),
+        };
+        if let Err(err) = dev.set_segments(stripe, segments) {
+            if !exists {
+                let _ = dev.teardown();
+            }
+            return Err(err);
+        }
+        Ok(dev)
+    }
+
+    /// Load a table covering all the given segments and make it live.
+    pub fn set_segments(
+        &mut self,
+        stripe: StripeConfig,
+        segments: &[BlkDevSegment],
+    ) -> StratisResult<()> {
+        let (size, table) = stripe_table(stripe, segments);
+        let id = DevId::Name(&self.name);
+        retry_dm(|| get_dm().table_load(&id, &table))?;
+        retry_dm(|| get_dm().device_suspend(&id, &DmOptions::new()))?;
+        self.size = size;
+        Ok(())
+    }
+
+    /// Remove the stripe device.
+    pub fn teardown(&mut self) -> StratisResult<()> {
+        retry_dm(|| get_dm().device_remove(&DevId::Name(&self.name), &DmOptions::new()))?;
+        Ok(())
+    }
+
+    /// The device number of the stripe device.
+    pub fn device(&self) -> Device {
+        self.device
+    }
+
+    /// The size of the stripe device.
+    pub fn size(&self) -> Sectors {
+        self.size
+    }
+}
@@ -16,19 +16,27 @@
 
 use std::{
     collections::HashMap,
-    io::{Read, Write},
+    io::{self, Read, Write},
+    os::unix::process::CommandExt,
     path::{Path, PathBuf},
     process::{Command, Stdio},
+    sync::RwLock,
+    time::Duration,
 };
 
 use libc::c_uint;
 use libcryptsetup_rs::SafeMemHandle;
+use nix::sched::{unshare, CloneFlags};
 use serde_json::Value;
 
+use devicemapper::Bytes;
+
 use crate::{
     engine::{
         engine::MAX_STRATIS_PASS_SIZE,
-        types::{FilesystemUuid, SizedKeyMemory, StratisUuid},
+        types::{
+            FilesystemCheck, FilesystemUuid, PlannedAction, SizedKeyMemory, StratisUuid, XfsParams,
+        },
     },
     stratis::{StratisError, StratisResult},
 };
@@ -52,10 +60,12 @@ fn find_binary(name: &str) -> Option<PathBuf> {
 // and vice-versa.
 const MKFS_XFS: &str = "mkfs.xfs";
 const THIN_CHECK: &str = "thin_check";
+const THIN_DUMP: &str = "thin_dump";
 const THIN_REPAIR: &str = "thin_repair";
 const UDEVADM: &str = "udevadm";
 const XFS_DB: &str = "xfs_db";
 const XFS_GROWFS: &str = "xfs_growfs";
+const XFS_REPAIR: &str = "xfs_repair";
 const CLEVIS: &str = "clevis";
 const CLEVIS_LIB: &str = "clevis-luks-common-functions";
 const CLEVIS_BIND: &str = "clevis-luks-bind";
@@ -103,14 +113,46 @@ const CLEVIS_EXEC_NAMES: &[&str] = &[
     MKTEMP,
 ];
 
+/// How the engine waits for udev to finish processing the events that its
+/// changes to devices cause.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct UdevSettlePolicy {
+    /// The longest time to wait for udev. If None, the default of udevadm,
+    /// 120 seconds, applies.
+    pub timeout: Option<Duration>,
+    /// Where the engine waits for udev before it removes a device, wait
+    /// only until nothing holds that device open anymore, rather than until
+    /// the whole udev event queue has been processed.
+    pub targeted: bool,
+}
+
+/// The restrictions under which the engine runs external commands.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CommandSandbox {
+    /// Run commands with an environment which holds only a PATH made up of
+    /// the directories in which binaries are looked for and the C locale,
+    /// rather than with the environment of stratisd.
+    pub clean_env: bool,
+    /// Run the commands which only operate on devices and filesystems in
+    /// network, IPC, and UTS namespaces of their own. The Clevis commands,
+    /// which may have to reach a Tang server, and udevadm, which talks to
+    /// udevd, are not affected.
+    pub namespaces: bool,
+}
+
 lazy_static! {
+    static ref UDEV_SETTLE_POLICY: RwLock<UdevSettlePolicy> =
+        RwLock::new(UdevSettlePolicy::default());
+    static ref COMMAND_SANDBOX: RwLock<CommandSandbox> = RwLock::new(CommandSandbox::default());
     static ref BINARIES: HashMap<String, Option<PathBuf>> = [
         (MKFS_XFS.to_string(), find_binary(MKFS_XFS)),
         (THIN_CHECK.to_string(), find_binary(THIN_CHECK)),
+        (THIN_DUMP.to_string(), find_binary(THIN_DUMP)),
         (THIN_REPAIR.to_string(), find_binary(THIN_REPAIR)),
         (UDEVADM.to_string(), find_binary(UDEVADM)),
         (XFS_DB.to_string(), find_binary(XFS_DB)),
         (XFS_GROWFS.to_string(), find_binary(XFS_GROWFS)),
+        (XFS_REPAIR.to_string(), find_binary(XFS_REPAIR)),
     ]
     .iter()
     .cloned()
@@ -156,6 +198,53 @@ pub fn verify_binaries() -> StratisResult<()> {
     }
 }
 
+/// Set the restrictions under which external commands are run. Commands
+/// which are already running are not affected.
+pub fn set_command_sandbox(sandbox: CommandSandbox) {
+    *COMMAND_SANDBOX
+        .write()
+        .expect("no thread panics while holding the lock") = sandbox;
+}
+
+/// The restrictions under which external commands are run. See
+/// set_command_sandbox().
+pub fn command_sandbox() -> CommandSandbox {
+    *COMMAND_SANDBOX
+        .read()
+        .expect("no thread panics while holding the lock")
+}
+
+/// Make a command which runs the executable with the environment that the
+/// command sandbox allows.
+fn command(executable: &Path) -> Command {
+    let mut cmd = Command::new(executable);
+    if command_sandbox().clean_env {
+        cmd.env_clear()
+            .env("PATH", BINARIES_PATHS.join(":"))
+            .env("LC_ALL", "C");
+    }
+    cmd
+}
+
+/// Make a command like command() does, which, if the command sandbox calls
+/// for it, runs in namespaces of its own.
+fn isolated_command(executable: &Path) -> Command {
+    let mut cmd = command(executable);
+    if command_sandbox().namespaces {
+        // unshare() is async-signal-safe, so it may be called in the child
+        // between fork and exec.
+        unsafe {
+            cmd.pre_exec(|| {
+                unshare(
+                    CloneFlags::CLONE_NEWNET | CloneFlags::CLONE_NEWIPC | CloneFlags::CLONE_NEWUTS,
+                )
+                .map_err(|err| io::Error::from_raw_os_error(err as i32))
+            });
+        }
+    }
+    cmd
+}
+
 /// Invoke the specified command. Return an error if invoking the command
 /// fails or if the command itself fails.
 fn execute_cmd(cmd: &mut Command) -> StratisResult<()> {
@@ -220,40 +309,199 @@ fn get_jose_executable() -> StratisResult<&'static Path> {
     })?)
 }
 
+/// The options passed to mkfs.xfs, following the devnode, by create_fs().
+fn create_fs_options(
+    uuid: Option<StratisUuid>,
+    noalign: bool,
+    params: Option<&XfsParams>,
+) -> Vec<String> {
+    let mut options = Vec::new();
+    if let Some(uuid) = uuid {
+        options.push("-m".to_string());
+        options.push(format!("uuid={}", uuid));
+    }
+    if noalign {
+        options.push("-d".to_string());
+        options.push("noalign".to_string());
+    }
+    if let Some(params) = params {
+        if let Some(block_size) = params.block_size {
+            options.push("-b".to_string());
+            options.push(format!("size={}", *block_size));
+        }
+        if let Some(inode_size) = params.inode_size {
+            options.push("-i".to_string());
+            options.push(format!("size={}", *inode_size));
+        }
+        if let Some(log_size) = params.log_size {
+            options.push("-l".to_string());
+            options.push(format!("size={}", *log_size));
+        }
+    }
+    options
+}
+
 /// Create a filesystem on devnode. If uuid specified, set the UUID of the
 /// filesystem on creation. `noalign` should be `true` when creating small metadata filesystems
-/// like the MDV.
-pub fn create_fs(devnode: &Path, uuid: Option<StratisUuid>, noalign: bool) -> StratisResult<()> {
-    let mut command = Command::new(get_executable(MKFS_XFS).as_os_str());
+/// like the MDV. If params is specified, the parameters it sets are passed
+/// to mkfs.xfs; it is the responsibility of the caller to validate them.
+pub fn create_fs(
+    devnode: &Path,
+    uuid: Option<StratisUuid>,
+    noalign: bool,
+    params: Option<&XfsParams>,
+) -> StratisResult<()> {
+    let mut command = isolated_command(get_executable(MKFS_XFS));
     command.arg("-f");
     command.arg("-q");
     command.arg(devnode);
+    command.args(create_fs_options(uuid, noalign, params));
+    execute_cmd(&mut command)
+}
 
-    if let Some(uuid) = uuid {
-        command.arg("-m");
-        command.arg(format!("uuid={}", uuid));
-    }
-    if noalign {
-        command.arg("-d");
-        command.arg("noalign");
+/// The command that create_fs() would run with the same arguments.
+pub fn explain_create_fs(
+    devnode: &Path,
+    uuid: Option<StratisUuid>,
+    noalign: bool,
+    params: Option<&XfsParams>,
+) -> PlannedAction {
+    let mut args = vec![
+        "-f".to_string(),
+        "-q".to_string(),
+        devnode.display().to_string(),
+    ];
+    args.extend(create_fs_options(uuid, noalign, params));
+    PlannedAction {
+        program: get_executable(MKFS_XFS).display().to_string(),
+        args,
     }
-    execute_cmd(&mut command)
 }
 
 /// Use the xfs_growfs command to expand a filesystem mounted at the given
 /// mount point.
 pub fn xfs_growfs(mount_point: &Path) -> StratisResult<()> {
     execute_cmd(
-        Command::new(get_executable(XFS_GROWFS).as_os_str())
+        isolated_command(get_executable(XFS_GROWFS))
             .arg(mount_point)
             .arg("-d"),
     )
 }
 
+/// Use xfs_repair in no-modify mode to check the unmounted filesystem on the
+/// given devnode.
+pub fn xfs_check(devnode: &Path) -> StratisResult<FilesystemCheck> {
+    let mut cmd = isolated_command(get_executable(XFS_REPAIR));
+    cmd.arg("-n").arg(devnode);
+    let result = cmd.output().map_err(|err| {
+        StratisError::Msg(format!(
+            "Failed to execute command {:?}, err: {:?}",
+            cmd, err
+        ))
+    })?;
+    match result.status.code() {
+        Some(0) => Ok(FilesystemCheck {
+            consistent: true,
+            problems: Vec::new(),
+        }),
+        // xfs_repair exits with 1 in no-modify mode if it found corruption.
+        // Its report is interleaved with progress messages, which start with
+        // "Phase" or are indented.
+        Some(1) => Ok(FilesystemCheck {
+            consistent: false,
+            problems: String::from_utf8_lossy(&result.stderr)
+                .lines()
+                .filter(|line| {
+                    !(line.is_empty() || line.starts_with("Phase") || line.starts_with(' '))
+                })
+                .map(|line| line.to_string())
+                .collect(),
+        }),
+        _ => {
+            let exit_reason = result
+                .status
+                .code()
+                .map_or(String::from("process terminated by signal"), |ec| {
+                    ec.to_string()
+                });
+            Err(StratisError::Msg(format!(
+                "Command failed: cmd: {:?}, exit reason: {} stdout: {} stderr: {}",
+                cmd,
+                exit_reason,
+                String::from_utf8_lossy(&result.stdout),
+                String::from_utf8_lossy(&result.stderr)
+            )))
+        }
+    }
+}
+
+/// Use xfs_db to read the size of the data section of the XFS filesystem on
+/// the given devnode from its superblock. The filesystem may be mounted.
+pub fn xfs_data_size(devnode: &Path) -> StratisResult<Bytes> {
+    let mut cmd = isolated_command(get_executable(XFS_DB));
+    cmd.arg("-r")
+        .arg("-c")
+        .arg("sb 0")
+        .arg("-c")
+        .arg("p dblocks blocksize")
+        .arg(devnode);
+    let result = cmd.output().map_err(|err| {
+        StratisError::Msg(format!(
+            "Failed to execute command {:?}, err: {:?}",
+            cmd, err
+        ))
+    })?;
+    if !result.status.success() {
+        let exit_reason = result
+            .status
+            .code()
+            .map_or(String::from("process terminated by signal"), |ec| {
+                ec.to_string()
+            });
+        return Err(StratisError::Msg(format!(
+            "Command failed: cmd: {:?}, exit reason: {} stdout: {} stderr: {}",
+            cmd,
+            exit_reason,
+            String::from_utf8_lossy(&result.stdout),
+            String::from_utf8_lossy(&result.stderr)
+        )));
+    }
+    parse_xfs_data_size(&String::from_utf8_lossy(&result.stdout)).ok_or_else(|| {
+        StratisError::Msg(format!(
+            "Unexpected output from command {:?}: {}",
+            cmd,
+            String::from_utf8_lossy(&result.stdout)
+        ))
+    })
+}
+
+/// Parse the dblocks and blocksize fields printed by xfs_db, one per line
+/// in the form "name = value", into the size of the data section.
+fn parse_xfs_data_size(output: &str) -> Option<Bytes> {
+    let field = |name: &str| {
+        output.lines().find_map(|line| {
+            let mut parts = line.splitn(2, '=');
+            if parts.next()?.trim() == name {
+                parts.next()?.trim().parse::<u64>().ok()
+            } else {
+                None
+            }
+        })
+    };
+    let dblocks = field("dblocks")?;
+    let blocksize = field("blocksize")?;
+    Some(Bytes::from(dblocks * blocksize))
+}
+
+/// Use xfs_repair to repair the unmounted filesystem on the given devnode.
+pub fn xfs_repair(devnode: &Path) -> StratisResult<()> {
+    execute_cmd(isolated_command(get_executable(XFS_REPAIR)).arg(devnode))
+}
+
 /// Set a new UUID for filesystem on the devnode.
 pub fn set_uuid(devnode: &Path, uuid: FilesystemUuid) -> StratisResult<()> {
     execute_cmd(
-        Command::new(get_executable(XFS_DB).as_os_str())
+        isolated_command(get_executable(XFS_DB))
             .arg("-x")
             .arg(format!("-c uuid {}", uuid))
             .arg(devnode),
@@ -263,7 +511,7 @@ pub fn set_uuid(devnode: &Path, uuid: FilesystemUuid) -> StratisResult<()> {
 /// Call thin_check on a thinpool
 pub fn thin_check(devnode: &Path) -> StratisResult<()> {
     execute_cmd(
-        Command::new(get_executable(THIN_CHECK).as_os_str())
+        isolated_command(get_executable(THIN_CHECK))
             .arg("-q")
             .arg(devnode),
     )
@@ -272,7 +520,7 @@ pub fn thin_check(devnode: &Path) -> StratisResult<()> {
 /// Call thin_repair on a thinpool
 pub fn thin_repair(meta_dev: &Path, new_meta_dev: &Path) -> StratisResult<()> {
     execute_cmd(
-        Command::new(get_executable(THIN_REPAIR).as_os_str())
+        isolated_command(get_executable(THIN_REPAIR))
             .arg("-i")
             .arg(meta_dev)
             .arg("-o")
@@ -280,9 +528,117 @@ pub fn thin_repair(meta_dev: &Path, new_meta_dev: &Path) -> StratisResult<()> {
     )
 }
 
-/// Call udevadm settle
+/// A run of blocks of a thin device which are mapped to consecutive data
+/// blocks of the thin pool, in units of the thin pool's data block size.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ThinMapping {
+    pub origin_begin: u64,
+    pub data_begin: u64,
+    pub length: u64,
+}
+
+/// Use thin_dump to read the mappings of the thin devices of a live thin
+/// pool from its metadata snapshot. Return the mappings of each thin
+/// device, keyed by thin device id.
+///
+/// Precondition: a metadata snapshot has been reserved for the thin pool.
+pub fn thin_dump_mappings(meta_dev: &Path) -> StratisResult<HashMap<u32, Vec<ThinMapping>>> {
+    let mut cmd = isolated_command(get_executable(THIN_DUMP));
+    cmd.arg("--metadata-snap").arg(meta_dev);
+    let result = cmd.output().map_err(|err| {
+        StratisError::Msg(format!(
+            "Failed to execute command {:?}, err: {:?}",
+            cmd, err
+        ))
+    })?;
+    if !result.status.success() {
+        let exit_reason = result
+            .status
+            .code()
+            .map_or(String::from("process terminated by signal"), |ec| {
+                ec.to_string()
+            });
+        return Err(StratisError::Msg(format!(
+            "Command failed: cmd: {:?}, exit reason: {} stdout: {} stderr: {}",
+            cmd,
+            exit_reason,
+            String::from_utf8_lossy(&result.stdout),
+            String::from_utf8_lossy(&result.stderr)
+        )));
+    }
+    Ok(parse_thin_dump(&String::from_utf8_lossy(&result.stdout)))
+}
+
+/// The value of the numeric attribute name in a line of XML, if there is one.
+fn xml_attribute(line: &str, name: &str) -> Option<u64> {
+    let prefix = format!(" {}=\"", name);
+    let start = line.find(&prefix)? + prefix.len();
+    line[start..].split('"').next()?.parse().ok()
+}
+
+/// Parse the XML output of thin_dump, which has a device element for each
+/// thin device, containing a range_mapping or single_mapping element per
+/// line for each run of mapped data blocks.
+fn parse_thin_dump(xml: &str) -> HashMap<u32, Vec<ThinMapping>> {
+    let mut mappings = HashMap::new();
+    let mut current = None;
+    for line in xml.lines().map(|line| line.trim()) {
+        if line.starts_with("<device ") {
+            current = xml_attribute(line, "dev_id").map(|id| id as u32);
+        } else if line.starts_with("</device>") {
+            current = None;
+        } else if let Some(id) = current {
+            let mapping = if line.starts_with("<range_mapping ") {
+                xml_attribute(line, "origin_begin")
+                    .zip(xml_attribute(line, "data_begin"))
+                    .zip(xml_attribute(line, "length"))
+                    .map(|((origin_begin, data_begin), length)| ThinMapping {
+                        origin_begin,
+                        data_begin,
+                        length,
+                    })
+            } else if line.starts_with("<single_mapping ") {
+                xml_attribute(line, "origin_block")
+                    .zip(xml_attribute(line, "data_block"))
+                    .map(|(origin_begin, data_begin)| ThinMapping {
+                        origin_begin,
+                        data_begin,
+                        length: 1,
+                    })
+            } else {
+                None
+            };
+            if let Some(mapping) = mapping {
+                mappings.entry(id).or_insert_with(Vec::new).push(mapping);
+            }
+        }
+    }
+    mappings
+}
+
+/// Set the policy for waiting for udev.
+pub fn set_udev_settle_policy(policy: UdevSettlePolicy) {
+    *UDEV_SETTLE_POLICY
+        .write()
+        .expect("no thread panics while holding the lock") = policy;
+}
+
+/// The policy for waiting for udev. See set_udev_settle_policy().
+pub fn udev_settle_policy() -> UdevSettlePolicy {
+    *UDEV_SETTLE_POLICY
+        .read()
+        .expect("no thread panics while holding the lock")
+}
+
+/// Call udevadm settle, waiting no longer than the timeout of the udev
+/// settle policy.
 pub fn udev_settle() -> StratisResult<()> {
-    execute_cmd(Command::new(get_executable(UDEVADM).as_os_str()).arg("settle"))
+    let mut cmd = command(get_executable(UDEVADM));
+    cmd.arg("settle");
+    if let Some(timeout) = udev_settle_policy().timeout {
+        cmd.arg(format!("--timeout={}", timeout.as_secs()));
+    }
+    execute_cmd(&mut cmd)
 }
 
 /// Bind a LUKS device using clevis.
@@ -294,7 +650,7 @@ pub fn clevis_luks_bind(
     json: &Value,
     yes: bool,
 ) -> StratisResult<()> {
-    let mut cmd = Command::new(get_clevis_executable()?);
+    let mut cmd = command(get_clevis_executable()?);
 
     cmd.arg("luks").arg("bind");
 
@@ -317,7 +673,7 @@ pub fn clevis_luks_bind(
 /// Unbind a LUKS device using clevis.
 pub fn clevis_luks_unbind(dev_path: &Path, keyslot: libc::c_uint) -> StratisResult<()> {
     execute_cmd(
-        Command::new(get_clevis_executable()?)
+        command(get_clevis_executable()?)
             .arg("luks")
             .arg("unbind")
             .arg("-d")
@@ -331,7 +687,7 @@ pub fn clevis_luks_unbind(dev_path: &Path, keyslot: libc::c_uint) -> StratisResu
 /// Unlock a device using the clevis CLI.
 pub fn clevis_luks_unlock(dev_path: &Path, dm_name: &str) -> StratisResult<()> {
     execute_cmd(
-        Command::new(get_clevis_executable()?)
+        command(get_clevis_executable()?)
             .arg("luks")
             .arg("unlock")
             .arg("-d")
@@ -343,7 +699,7 @@ pub fn clevis_luks_unlock(dev_path: &Path, dm_name: &str) -> StratisResult<()> {
 
 /// Safely query clevis for the decrypted passphrase stored on a LUKS2 volume.
 pub fn clevis_decrypt(jwe: &Value) -> StratisResult<SizedKeyMemory> {
-    let mut jose_child = Command::new(get_jose_executable()?)
+    let mut jose_child = command(get_jose_executable()?)
         .arg("jwe")
         .arg("fmt")
         .arg("-i-")
@@ -373,7 +729,7 @@ pub fn clevis_decrypt(jwe: &Value) -> StratisResult<SizedKeyMemory> {
         })?
         .read_to_string(&mut jose_output)?;
 
-    let mut clevis_child = Command::new(get_clevis_executable()?)
+    let mut clevis_child = command(get_clevis_executable()?)
         .arg("decrypt")
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
@@ -406,7 +762,7 @@ pub fn clevis_decrypt(jwe: &Value) -> StratisResult<SizedKeyMemory> {
 /// Regenerate the bindings for a device using the clevis CLI.
 pub fn clevis_luks_regen(dev_path: &Path, keyslot: c_uint) -> StratisResult<()> {
     execute_cmd(
-        Command::new(get_clevis_executable()?)
+        command(get_clevis_executable()?)
             .arg("luks")
             .arg("regen")
             .arg("-d")
@@ -416,3 +772,54 @@ pub fn clevis_luks_regen(dev_path: &Path, keyslot: c_uint) -> StratisResult<()>
             .arg("-q"),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Verify that the mapped blocks of each thin device are read from the
+    /// output of thin_dump, and that blocks outside a device are ignored.
+    fn test_parse_thin_dump() {
+        let xml = r#"<superblock uuid="" time="1" transaction="2" flags="0" version="2" data_block_size="2048" nr_data_blocks="1024">
+  <device dev_id="1" mapped_blocks="5" transaction="0" creation_time="0" snap_time="1">
+    <range_mapping origin_begin="0" data_begin="0" length="4" time="0"/>
+    <single_mapping origin_block="10" data_block="7" time="1"/>
+  </device>
+  <device dev_id="2" mapped_blocks="0" transaction="1" creation_time="1" snap_time="1">
+  </device>
+  <single_mapping origin_block="3" data_block="9" time="1"/>
+</superblock>
+"#;
+        let mappings = parse_thin_dump(xml);
+        assert_eq!(mappings.len(), 1);
+        assert_eq!(
+            mappings[&1],
+            vec![
+                ThinMapping {
+                    origin_begin: 0,
+                    data_begin: 0,
+                    length: 4
+                },
+                ThinMapping {
+                    origin_begin: 10,
+                    data_begin: 7,
+                    length: 1
+                }
+            ]
+        );
+    }
+
+    #[test]
+    /// Verify that the size of the data section is computed from the
+    /// superblock fields printed by xfs_db, and that missing fields are
+    /// detected.
+    fn test_parse_xfs_data_size() {
+        let output = "dblocks = 262144\nblocksize = 4096\n";
+        assert_eq!(
+            parse_xfs_data_size(output),
+            Some(Bytes::from(262_144u64 * 4096))
+        );
+        assert_eq!(parse_xfs_data_size("dblocks = 262144\n"), None);
+    }
+}
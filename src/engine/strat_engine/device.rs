@@ -4,11 +4,22 @@
 
 // Functions for dealing with devices.
 
-use std::{fs::File, os::unix::prelude::AsRawFd};
+use std::{
+    cmp::min,
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    os::unix::{fs::OpenOptionsExt, prelude::AsRawFd},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
-use devicemapper::Bytes;
+use devicemapper::{Bytes, Device, Sectors, IEC};
 
-use crate::stratis::{StratisError, StratisResult};
+use crate::{
+    engine::types::{DeviceBenchmark, ReadWork},
+    stratis::{StratisError, StratisResult},
+};
 
 ioctl_read!(
     /// # Safety
@@ -23,6 +34,42 @@ ioctl_read!(
     u64
 );
 
+ioctl_read_bad!(
+    /// # Safety
+    ///
+    /// This function is a wrapper for `libc::ioctl` and therefore is unsafe for the same reasons
+    /// as other libc bindings. It accepts a file descriptor and mutable pointer so the semantics
+    /// of the invoked `ioctl` command should be examined to determine the effect it will have
+    /// on the resources passed to the command.
+    blksszget,
+    request_code_none!(0x12, 104),
+    i32
+);
+
+ioctl_read_bad!(
+    /// # Safety
+    ///
+    /// This function is a wrapper for `libc::ioctl` and therefore is unsafe for the same reasons
+    /// as other libc bindings. It accepts a file descriptor and mutable pointer so the semantics
+    /// of the invoked `ioctl` command should be examined to determine the effect it will have
+    /// on the resources passed to the command.
+    blkioopt,
+    request_code_none!(0x12, 121),
+    u32
+);
+
+ioctl_read_bad!(
+    /// # Safety
+    ///
+    /// This function is a wrapper for `libc::ioctl` and therefore is unsafe for the same reasons
+    /// as other libc bindings. It accepts a file descriptor and mutable pointer so the semantics
+    /// of the invoked `ioctl` command should be examined to determine the effect it will have
+    /// on the resources passed to the command.
+    blkpbszget,
+    request_code_none!(0x12, 123),
+    u32
+);
+
 pub fn blkdev_size(file: &File) -> StratisResult<Bytes> {
     let mut val: u64 = 0;
 
@@ -31,3 +78,492 @@ pub fn blkdev_size(file: &File) -> StratisResult<Bytes> {
         Ok(_) => Ok(Bytes::from(val)),
     }
 }
+
+/// The logical sector size of the block device, in bytes.
+pub fn blkdev_logical_sector_size(file: &File) -> StratisResult<u64> {
+    let mut val: i32 = 0;
+
+    match unsafe { blksszget(file.as_raw_fd(), &mut val) } {
+        Err(x) => Err(StratisError::Nix(x)),
+        Ok(_) => Ok(val as u64),
+    }
+}
+
+/// The physical sector size of the block device, in bytes.
+pub fn blkdev_physical_sector_size(file: &File) -> StratisResult<u64> {
+    let mut val: u32 = 0;
+
+    match unsafe { blkpbszget(file.as_raw_fd(), &mut val) } {
+        Err(x) => Err(StratisError::Nix(x)),
+        Ok(_) => Ok(u64::from(val)),
+    }
+}
+
+/// The optimal I/O size of the block device, in bytes, 0 if the device
+/// does not report one.
+pub fn blkdev_optimal_io_size(file: &File) -> StratisResult<u64> {
+    let mut val: u32 = 0;
+
+    match unsafe { blkioopt(file.as_raw_fd(), &mut val) } {
+        Err(x) => Err(StratisError::Nix(x)),
+        Ok(_) => Ok(u64::from(val)),
+    }
+}
+
+// FIXME: stratisd can not check whether a device honours flushes, which
+// the durability of the pool metadata relies on. Without removing power
+// from the device, it can not be told whether the device only placed the
+// flushed data in a volatile cache, and data that was flushed can always
+// be read back as long as the device stays powered. The tests simulate
+// the loss of the writes made after a flush with a FailDevice that drops
+// them, see test_barrier_marker().
+
+/// The alignment of the buffers and offsets used for direct I/O, which
+/// satisfies the logical sector size of any block device.
+pub const DIRECT_IO_ALIGN: usize = 4096;
+
+/// The largest extent at the start of the device that is benchmarked.
+const BENCHMARK_EXTENT: u64 = 64 * IEC::Mi;
+
+/// The size of each I/O of the sequential part of the benchmark.
+const BENCHMARK_SEQUENTIAL_SIZE: usize = IEC::Mi as usize;
+
+/// The size and the number of the I/Os of the random part of the benchmark.
+const BENCHMARK_RANDOM_SIZE: usize = 4096;
+const BENCHMARK_RANDOM_OPS: u32 = 256;
+
+/// A buffer of len bytes within backing that is aligned for direct I/O.
+fn aligned_buffer(backing: &mut Vec<u8>, len: usize) -> &mut [u8] {
+    backing.resize(len + DIRECT_IO_ALIGN, 0);
+    let offset = backing.as_ptr().align_offset(DIRECT_IO_ALIGN);
+    &mut backing[offset..offset + len]
+}
+
+/// The throughput of transferring the given number of bytes in the given time,
+/// in bytes per second.
+fn throughput(bytes: u64, elapsed: Duration) -> u64 {
+    match elapsed.as_nanos() {
+        0 => 0,
+        nanos => (u128::from(bytes) * 1_000_000_000 / nanos) as u64,
+    }
+}
+
+/// Prepare benchmarking the device with direct I/O, sequentially and at
+/// random offsets, within the first BENCHMARK_EXTENT bytes of the device.
+/// The device is opened exclusively, so that it can not be mounted or set
+/// up as part of a pool while the benchmark runs, and kept open by the
+/// work, which runs the benchmark. All data that is read is written back
+/// unchanged, so that the contents of the device are preserved.
+///
+/// Returns an error if the device is in use or is smaller than
+/// BENCHMARK_EXTENT.
+pub fn benchmark_device(devnode: &Path) -> StratisResult<ReadWork<DeviceBenchmark>> {
+    let mut f = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_DIRECT | libc::O_EXCL)
+        .open(devnode)?;
+    let size = blkdev_size(&f)?;
+    if size < Bytes::from(BENCHMARK_EXTENT) {
+        return Err(StratisError::Msg(format!(
+            "Device {} has size {}, less than the {} needed to benchmark it",
+            devnode.display(),
+            size,
+            Bytes::from(BENCHMARK_EXTENT)
+        )));
+    }
+    let extent = BENCHMARK_EXTENT;
+
+    Ok(Box::new(move |_: &mut dyn FnMut(Sectors, Sectors)| {
+        let mut backing = Vec::new();
+        let buf = aligned_buffer(&mut backing, extent as usize);
+
+        let start = Instant::now();
+        f.seek(SeekFrom::Start(0))?;
+        for chunk in buf.chunks_mut(BENCHMARK_SEQUENTIAL_SIZE) {
+            f.read_exact(chunk)?;
+        }
+        let sequential_read = start.elapsed();
+
+        let start = Instant::now();
+        f.seek(SeekFrom::Start(0))?;
+        for chunk in buf.chunks(BENCHMARK_SEQUENTIAL_SIZE) {
+            f.write_all(chunk)?;
+        }
+        f.sync_all()?;
+        let sequential_write = start.elapsed();
+
+        let slots = extent / BENCHMARK_RANDOM_SIZE as u64;
+        let mut random_read = Duration::default();
+        let mut random_write = Duration::default();
+        let mut backing = Vec::new();
+        let buf = aligned_buffer(&mut backing, BENCHMARK_RANDOM_SIZE);
+        for _ in 0..BENCHMARK_RANDOM_OPS {
+            let offset = rand::random::<u64>() % slots * BENCHMARK_RANDOM_SIZE as u64;
+
+            let start = Instant::now();
+            f.seek(SeekFrom::Start(offset))?;
+            f.read_exact(buf)?;
+            random_read += start.elapsed();
+
+            let start = Instant::now();
+            f.seek(SeekFrom::Start(offset))?;
+            f.write_all(buf)?;
+            f.sync_data()?;
+            random_write += start.elapsed();
+        }
+
+        let random_bytes = u64::from(BENCHMARK_RANDOM_OPS) * BENCHMARK_RANDOM_SIZE as u64;
+        Ok(DeviceBenchmark {
+            sequential_read: throughput(extent, sequential_read),
+            sequential_write: throughput(extent, sequential_write),
+            random_read: throughput(random_bytes, random_read),
+            random_write: throughput(random_bytes, random_write),
+            read_latency: random_read / BENCHMARK_RANDOM_OPS,
+            write_latency: random_write / BENCHMARK_RANDOM_OPS,
+        })
+    }))
+}
+
+/// The size of the region of a device that is written by surface_test().
+pub const SURFACE_TEST_SIZE: usize = 64 * IEC::Ki as usize;
+
+/// Write pattern to the file at offset, flush it, and read it back into
+/// read. Returns true if what was read back is the pattern.
+fn write_and_read_back(
+    f: &mut File,
+    offset: u64,
+    pattern: &[u8],
+    read: &mut [u8],
+) -> StratisResult<bool> {
+    f.seek(SeekFrom::Start(offset))?;
+    f.write_all(pattern)?;
+    f.sync_data()?;
+    f.seek(SeekFrom::Start(offset))?;
+    f.read_exact(read)?;
+    Ok(*read == *pattern)
+}
+
+/// Write a pattern of random bytes to the SURFACE_TEST_SIZE bytes of the
+/// device at offset, flush it, and read it back. Direct I/O is used, so
+/// that the pattern is read from the device rather than from the page
+/// cache. Returns true if the pattern was read back unchanged. The previous
+/// contents of the region are restored afterwards.
+///
+/// Precondition: offset is a multiple of DIRECT_IO_ALIGN, and the region is
+/// not in use.
+pub fn surface_test(devnode: &Path, offset: u64) -> StratisResult<bool> {
+    let mut f = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(devnode)?;
+
+    let mut original_backing = Vec::new();
+    let original = aligned_buffer(&mut original_backing, SURFACE_TEST_SIZE);
+    f.seek(SeekFrom::Start(offset))?;
+    f.read_exact(original)?;
+
+    let mut pattern_backing = Vec::new();
+    let pattern = aligned_buffer(&mut pattern_backing, SURFACE_TEST_SIZE);
+    pattern
+        .iter_mut()
+        .for_each(|byte| *byte = rand::random::<u8>());
+
+    let mut read_backing = Vec::new();
+    let read = aligned_buffer(&mut read_backing, SURFACE_TEST_SIZE);
+
+    let result = write_and_read_back(&mut f, offset, pattern, read);
+
+    f.seek(SeekFrom::Start(offset))?;
+    f.write_all(original)?;
+    f.sync_data()?;
+    result
+}
+
+/// Read the given (start, length) ranges of the device with direct I/O, so
+/// that the data is read from the device rather than from the page cache.
+/// The ranges are read in pieces of at most chunk_size. visit is called with
+/// each piece, as a (start, length) pair, and the data read, or the error
+/// that occurred when reading it; reading stops if visit returns an error. After each
+/// piece, progress is called with the number of sectors read so far and the
+/// total number of sectors to read. The device is not opened if there is
+/// nothing to read.
+///
+/// Precondition: the starts and lengths of the ranges and chunk_size are
+/// multiples of DIRECT_IO_ALIGN.
+pub fn read_ranges(
+    devnode: &Path,
+    ranges: &[(Sectors, Sectors)],
+    chunk_size: Sectors,
+    visit: &mut dyn FnMut((Sectors, Sectors), io::Result<&[u8]>) -> StratisResult<()>,
+    progress: &mut dyn FnMut(Sectors, Sectors),
+) -> StratisResult<()> {
+    let total = ranges.iter().map(|&(_, length)| length).sum::<Sectors>();
+    if total == Sectors(0) {
+        return Ok(());
+    }
+
+    let mut f = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(devnode)?;
+
+    let mut backing = Vec::new();
+    let buf = aligned_buffer(
+        &mut backing,
+        convert_int!(*chunk_size.bytes(), u128, usize)?,
+    );
+    let mut done = Sectors(0);
+    for &(start, length) in ranges {
+        let end = start + length;
+        let mut offset = start;
+        while offset < end {
+            let piece = min(chunk_size, end - offset);
+            let data = &mut buf[..convert_int!(*piece.bytes(), u128, usize)?];
+            let result = f
+                .seek(SeekFrom::Start(convert_int!(*offset.bytes(), u128, u64)?))
+                .and_then(|_| f.read_exact(data));
+            let data: &[u8] = data;
+            visit((offset, piece), result.map(|_| data))?;
+            offset += piece;
+            done += piece;
+            progress(done, total);
+        }
+    }
+    Ok(())
+}
+
+/// Add the physical disks which back the block device with the given sysfs
+/// directory to disks. A partition is backed by the disk which contains
+/// it, and a device-mapper device by the devices listed in its slaves
+/// directory, such as the paths of a multipath device.
+fn add_physical_disks(sys_dev: &Path, disks: &mut HashSet<String>) -> StratisResult<()> {
+    let sys_dev = fs::canonicalize(sys_dev)?;
+    let sys_disk = if sys_dev.join("partition").exists() {
+        fs::canonicalize(sys_dev.join(".."))?
+    } else {
+        sys_dev
+    };
+
+    let slaves = sys_disk.join("slaves");
+    let mut has_slaves = false;
+    if slaves.is_dir() {
+        for entry in fs::read_dir(&slaves)? {
+            has_slaves = true;
+            add_physical_disks(&entry?.path(), disks)?;
+        }
+    }
+
+    if !has_slaves {
+        // Different paths to the same disk share its WWID; a disk which has
+        // none is known by its kernel name.
+        let wwid = ["wwid", "device/wwid"]
+            .iter()
+            .filter_map(|name| fs::read_to_string(sys_disk.join(name)).ok())
+            .map(|wwid| wwid.trim().to_string())
+            .find(|wwid| !wwid.is_empty());
+        let disk = match wwid {
+            Some(wwid) => wwid,
+            None => sys_disk
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .ok_or_else(|| {
+                    StratisError::Msg(format!(
+                        "Unexpected sysfs path {} of block device",
+                        sys_disk.display()
+                    ))
+                })?,
+        };
+        disks.insert(disk);
+    }
+    Ok(())
+}
+
+/// The physical disks which back the block device with the given device
+/// number, identified by their WWID or, if they have none, by their kernel
+/// name, as found in the sysfs topology of the device.
+pub fn physical_disks(device: Device) -> StratisResult<HashSet<String>> {
+    let sys_dev = [
+        "/sys/dev/block",
+        &format!("{}:{}", device.major, device.minor),
+    ]
+    .iter()
+    .collect::<PathBuf>();
+    let mut disks = HashSet::new();
+    add_physical_disks(&sys_dev, &mut disks)?;
+    Ok(disks)
+}
+
+#[cfg(test)]
+mod tests {
+    use nix::fcntl::{posix_fadvise, PosixFadviseAdvice};
+
+    use crate::engine::strat_engine::tests::{loopbacked, real, FailDevice};
+
+    use super::*;
+
+    /// The size of the marker written by write_barrier_marker(), the same as
+    /// the size of a block of the static header.
+    const BARRIER_MARKER_SIZE: usize = 4096;
+
+    /// Read the first BARRIER_MARKER_SIZE bytes of the device, after dropping
+    /// them from the page cache, so that they are read from the device itself.
+    fn read_device_start(devnode: &Path) -> StratisResult<Vec<u8>> {
+        let mut f = File::open(devnode)?;
+        posix_fadvise(
+            f.as_raw_fd(),
+            0,
+            BARRIER_MARKER_SIZE as i64,
+            PosixFadviseAdvice::POSIX_FADV_DONTNEED,
+        )?;
+        let mut buf = vec![0; BARRIER_MARKER_SIZE];
+        f.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Write the given bytes to the start of the device and flush them, the way
+    /// the static header is written.
+    fn write_device_start(devnode: &Path, buf: &[u8]) -> StratisResult<()> {
+        let mut f = OpenOptions::new().write(true).open(devnode)?;
+        f.seek(SeekFrom::Start(0))?;
+        f.write_all(buf)?;
+        f.sync_all()?;
+        Ok(())
+    }
+
+    /// Write a marker of random bytes to the start of the device and flush it.
+    /// Return the marker.
+    fn write_barrier_marker(devnode: &Path) -> StratisResult<Vec<u8>> {
+        let marker = (0..BARRIER_MARKER_SIZE)
+            .map(|_| rand::random::<u8>())
+            .collect::<Vec<_>>();
+        write_device_start(devnode, &marker)?;
+        Ok(marker)
+    }
+
+    /// Whether the device starts with the given marker, as read from the device
+    /// rather than from the page cache.
+    fn barrier_marker_present(devnode: &Path, marker: &[u8]) -> StratisResult<bool> {
+        Ok(read_device_start(devnode)? == marker)
+    }
+
+    /// Verify that a marker that was flushed survives the loss of all the
+    /// writes that were made after the flush, which the FailDevice drops
+    /// to simulate a power loss, and that a dropped marker is not found.
+    fn test_barrier_marker(paths: &[&Path]) {
+        let fail_device = FailDevice::new(paths[0], "stratis_fail_device").unwrap();
+        let devnode = fail_device.as_path();
+
+        let flushed = write_barrier_marker(&devnode).unwrap();
+        fail_device
+            .start_dropping_writes((BARRIER_MARKER_SIZE / 512) as u64)
+            .unwrap();
+        let dropped = write_barrier_marker(&devnode).unwrap();
+        fail_device.stop_failing().unwrap();
+
+        assert!(barrier_marker_present(&devnode, &flushed).unwrap());
+        assert!(!barrier_marker_present(&devnode, &dropped).unwrap());
+    }
+
+    #[test]
+    fn loop_test_barrier_marker() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Exactly(1, None),
+            test_barrier_marker,
+        );
+    }
+
+    #[test]
+    fn real_test_barrier_marker() {
+        real::test_with_spec(
+            &real::DeviceLimits::Exactly(1, None, None),
+            test_barrier_marker,
+        );
+    }
+
+    /// Verify that the surface test of a working device succeeds and leaves
+    /// the contents of the device as they were, and that the surface test of
+    /// a device that silently drops writes fails.
+    fn test_surface_test(paths: &[&Path]) {
+        let offset = IEC::Mi;
+        let marker = write_barrier_marker(paths[0]).unwrap();
+
+        assert!(surface_test(paths[0], offset).unwrap());
+        assert!(barrier_marker_present(paths[0], &marker).unwrap());
+
+        let fail_device = FailDevice::new(paths[0], "stratis_fail_device").unwrap();
+        let devnode = fail_device.as_path();
+        fail_device
+            .start_dropping_writes((offset + SURFACE_TEST_SIZE as u64) / 512)
+            .unwrap();
+        let result = surface_test(&devnode, offset).unwrap();
+        fail_device.stop_failing().unwrap();
+        assert!(!result);
+    }
+
+    #[test]
+    fn loop_test_surface_test() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Exactly(1, None),
+            test_surface_test,
+        );
+    }
+
+    #[test]
+    fn real_test_surface_test() {
+        real::test_with_spec(
+            &real::DeviceLimits::Exactly(1, None, None),
+            test_surface_test,
+        );
+    }
+
+    /// Verify that benchmarking a device measures some throughput and
+    /// leaves the contents of the device as they were.
+    fn test_benchmark_device(paths: &[&Path]) {
+        let marker = write_barrier_marker(paths[0]).unwrap();
+
+        let benchmark = benchmark_device(paths[0]).unwrap()(&mut |_, _| ()).unwrap();
+        assert!(benchmark.sequential_read > 0);
+        assert!(benchmark.sequential_write > 0);
+
+        assert!(barrier_marker_present(paths[0], &marker).unwrap());
+    }
+
+    #[test]
+    fn loop_test_benchmark_device() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Exactly(1, None),
+            test_benchmark_device,
+        );
+    }
+
+    #[test]
+    fn real_test_benchmark_device() {
+        real::test_with_spec(
+            &real::DeviceLimits::Exactly(1, None, None),
+            test_benchmark_device,
+        );
+    }
+
+    /// Verify that each of the devices is backed by exactly one physical
+    /// disk and that no two of them share it.
+    fn test_physical_disks(paths: &[&Path]) {
+        let mut all_disks = HashSet::new();
+        for path in paths {
+            let device = Device::from(nix::sys::stat::stat(*path).unwrap().st_rdev);
+            let disks = physical_disks(device).unwrap();
+            assert_eq!(disks.len(), 1);
+            all_disks.extend(disks);
+        }
+        assert_eq!(all_disks.len(), paths.len());
+    }
+
+    #[test]
+    fn loop_test_physical_disks() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Exactly(2, None),
+            test_physical_disks,
+        );
+    }
+}
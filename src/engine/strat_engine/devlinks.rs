@@ -2,16 +2,175 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use std::{path::PathBuf, str};
+use std::{
+    fs,
+    io::ErrorKind,
+    os::unix::fs::symlink,
+    path::{Path, PathBuf},
+    str,
+    sync::RwLock,
+};
 
-use crate::engine::engine::DEV_PATH;
+use crate::{engine::engine::DEV_PATH, stratis::StratisResult};
 
 pub const UEVENT_CHANGE_EVENT: &str = "change";
 
+lazy_static! {
+    static ref DEV_PATH_BASE: RwLock<PathBuf> = RwLock::new(PathBuf::from(DEV_PATH));
+}
+
+/// Set the directory in which the symlinks to the filesystem devices are
+/// placed. The udev rules always place the symlinks in DEV_PATH; if another
+/// directory is set, stratisd maintains the symlinks in it itself.
+pub fn set_dev_path(path: &Path) {
+    *DEV_PATH_BASE
+        .write()
+        .expect("no thread panics while holding the lock") = path.to_owned();
+}
+
+/// The directory in which the symlinks to the filesystem devices are placed.
+pub fn dev_path() -> PathBuf {
+    DEV_PATH_BASE
+        .read()
+        .expect("no thread panics while holding the lock")
+        .clone()
+}
+
+/// Whether stratisd, rather than udev, maintains the symlinks.
+fn maintains_symlinks() -> bool {
+    dev_path() != Path::new(DEV_PATH)
+}
+
 /// Given a pool name and a filesystem name, return the path it should be
 /// available as a device for mounting.
 pub fn filesystem_mount_path<T: AsRef<str>>(pool_name: T, fs_name: T) -> PathBuf {
-    vec![DEV_PATH, pool_name.as_ref(), fs_name.as_ref()]
-        .iter()
-        .collect()
+    let mut path = dev_path();
+    path.push(pool_name.as_ref());
+    path.push(fs_name.as_ref());
+    path
+}
+
+/// Why the directory of symlinks for a new pool named pool_name could not
+/// be created, if there is a reason: something is in its place already.
+pub fn pool_dir_conflict(pool_name: &str) -> Option<String> {
+    let path = dev_path().join(pool_name);
+    match fs::symlink_metadata(&path) {
+        Ok(_) => Some(format!("{} exists already", path.display())),
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => Some(format!("{} could not be checked: {}", path.display(), err)),
+    }
+}
+
+/// Remove the file at path, if there is one.
+fn remove_if_exists(path: &Path) -> StratisResult<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Create or replace the symlink for a filesystem, if stratisd maintains
+/// the symlinks.
+pub fn filesystem_added(pool_name: &str, fs_name: &str, devnode: &Path) {
+    fn add(pool_name: &str, fs_name: &str, devnode: &Path) -> StratisResult<()> {
+        let path = filesystem_mount_path(pool_name, fs_name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        remove_if_exists(&path)?;
+        symlink(devnode, &path)?;
+        Ok(())
+    }
+
+    if maintains_symlinks() {
+        if let Err(e) = add(pool_name, fs_name, devnode) {
+            warn!(
+                "Failed to create symlink for filesystem {} in pool {}: {}",
+                fs_name, pool_name, e
+            );
+        }
+    }
+}
+
+/// Remove the symlink for a filesystem, if stratisd maintains the symlinks.
+pub fn filesystem_removed(pool_name: &str, fs_name: &str) {
+    if maintains_symlinks() {
+        if let Err(e) = remove_if_exists(&filesystem_mount_path(pool_name, fs_name)) {
+            warn!(
+                "Failed to remove symlink for filesystem {} in pool {}: {}",
+                fs_name, pool_name, e
+            );
+        }
+    }
+}
+
+/// Remove the directory of symlinks for a pool, if stratisd maintains the
+/// symlinks.
+pub fn pool_removed(pool_name: &str) {
+    if maintains_symlinks() {
+        let path = dev_path().join(pool_name);
+        if let Err(e) = remove_pool_dir(&path) {
+            warn!(
+                "Failed to remove symlink directory {} for pool {}: {}",
+                path.display(),
+                pool_name,
+                e
+            );
+        }
+    }
+}
+
+/// Remove the symlinks in a pool's directory and then the directory
+/// itself. Anything that stratisd did not create is left in place.
+fn remove_pool_dir(path: &Path) -> StratisResult<()> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_symlink() {
+            remove_if_exists(&entry.path())?;
+        }
+    }
+    fs::remove_dir(path)?;
+    Ok(())
+}
+
+/// Remove all symlinks left behind by a previous run of stratisd, if
+/// stratisd maintains the symlinks. Unlike the symlinks that udev places in
+/// DEV_PATH, they may persist after their devices are gone.
+pub fn cleanup_devlinks() {
+    fn cleanup(base: &Path) -> StratisResult<()> {
+        let entries = match fs::read_dir(base) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                if let Err(e) = remove_pool_dir(&entry.path()) {
+                    warn!(
+                        "Failed to remove stale symlink directory {}: {}",
+                        entry.path().display(),
+                        e
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    if maintains_symlinks() {
+        let base = dev_path();
+        if let Err(e) = cleanup(&base) {
+            warn!(
+                "Failed to remove stale symlinks in {}: {}",
+                base.display(),
+                e
+            );
+        }
+    }
 }
@@ -4,15 +4,136 @@
 
 // Get ability to instantiate a devicemapper context.
 
-use std::sync::Once;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    sync::{Mutex, Once, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
 
-use devicemapper::{DmResult, DM};
+use nix::errno::Errno;
 
-use crate::stratis::{StratisError, StratisResult};
+use devicemapper::{
+    DevId, Device, DmError, DmFlags, DmName, DmNameBuf, DmOptions, DmResult, DmUuidBuf, DM,
+};
+
+use crate::{
+    engine::{
+        strat_engine::{
+            cmd::{udev_settle, udev_settle_policy},
+            names::{pool_dm_name_for_uuid, pool_dm_stack_level},
+        },
+        types::{KernelFeature, KernelFeatureSupport, PoolUuid},
+    },
+    stratis::{StratisError, StratisResult},
+};
+
+/// How long to wait for a DM device to be released if the udev settle
+/// policy sets no timeout; the same as the default timeout of udevadm.
+const DEFAULT_RELEASE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How often to check whether a DM device has been released.
+const RELEASE_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 static INIT: Once = Once::new();
 static mut DM_CONTEXT: Option<DmResult<DM>> = None;
 
+lazy_static! {
+    static ref KERNEL_FEATURES: Mutex<HashMap<KernelFeature, KernelFeatureSupport>> =
+        Mutex::new(HashMap::new());
+    static ref DM_RETRY_POLICY: RwLock<DmRetryPolicy> = RwLock::new(DmRetryPolicy::default());
+}
+
+/// How often and how patiently a devicemapper operation that failed with
+/// a transient error is attempted again.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DmRetryPolicy {
+    /// The total number of attempts; 1 means that operations are not
+    /// retried.
+    pub attempts: u32,
+    /// The time to wait before the first retry; it is doubled before each
+    /// further retry.
+    pub backoff: Duration,
+}
+
+impl Default for DmRetryPolicy {
+    fn default() -> Self {
+        DmRetryPolicy {
+            attempts: 3,
+            backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Set the policy for retrying devicemapper operations.
+pub fn set_dm_retry_policy(policy: DmRetryPolicy) {
+    *DM_RETRY_POLICY
+        .write()
+        .expect("no thread panics while holding the lock") = policy;
+}
+
+/// Whether a devicemapper error may go away if the operation is attempted
+/// again, because the device was only briefly busy.
+///
+/// FIXME: The devicemapper crate does not expose the kinds of its errors,
+/// so the errno of a failed ioctl can only be found in the error message.
+fn is_transient(err: &DmError) -> bool {
+    match err {
+        DmError::Core(err) => {
+            let msg = err.to_string();
+            [Errno::EBUSY, Errno::EAGAIN]
+                .iter()
+                .any(|errno| msg.contains(errno.desc()))
+        }
+        DmError::Dm(..) => false,
+    }
+}
+
+fn retry_dm_with<T, F>(policy: DmRetryPolicy, mut f: F) -> DmResult<T>
+where
+    F: FnMut() -> DmResult<T>,
+{
+    let mut backoff = policy.backoff;
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Err(err) if attempt < policy.attempts && is_transient(&err) => {
+                debug!(
+                    "Devicemapper operation failed on attempt {} of {}, retrying in {:?}: {}",
+                    attempt, policy.attempts, backoff, err
+                );
+                thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Perform a devicemapper operation, attempting it again according to the
+/// retry policy if it fails with a transient error.
+///
+/// Only operations that can safely be attempted again after a failure
+/// should be passed, such as loading a table, suspending, resuming, or
+/// removing a device; creating a device is not among them, as a failed
+/// attempt may leave a partially constructed device behind.
+pub fn retry_dm<T, F>(f: F) -> DmResult<T>
+where
+    F: FnMut() -> DmResult<T>,
+{
+    let policy = *DM_RETRY_POLICY
+        .read()
+        .expect("no thread panics while holding the lock");
+    retry_dm_with(policy, f)
+}
+
+// FIXME: The devicemapper crate always opens the DM control node at its
+// built-in path, /dev/mapper/control, and offers no way to construct a DM
+// context from another path or an already opened file. Running the engine
+// against an alternate control node requires such a constructor in the
+// devicemapper crate; get_dm_init() can then take an optional path.
 pub fn get_dm_init() -> StratisResult<&'static DM> {
     unsafe {
         INIT.call_once(|| DM_CONTEXT = Some(DM::new()));
@@ -33,3 +154,324 @@ pub fn get_dm() -> &'static DM {
         "the engine has already called get_dm_init() and exited if get_dm_init() returned an error",
     )
 }
+
+/// Wait for udev before removing a DM device. If the udev settle policy is
+/// targeted, wait only until nothing holds the device open anymore, such as
+/// the udev workers that probe it; otherwise, wait for udev to settle.
+pub fn udev_settle_dm_device(name: &DmName) -> StratisResult<()> {
+    let policy = udev_settle_policy();
+    if !policy.targeted {
+        return udev_settle();
+    }
+
+    let deadline = Instant::now() + policy.timeout.unwrap_or(DEFAULT_RELEASE_TIMEOUT);
+    loop {
+        if get_dm().device_info(&DevId::Name(name))?.open_count() == 0 {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(StratisError::Msg(format!(
+                "Timed out waiting for device {} to be released",
+                name
+            )));
+        }
+        thread::sleep(RELEASE_POLL_INTERVAL);
+    }
+}
+
+/// A DM device of the stack of a pool, as found before it is rebuilt.
+struct StackDevice {
+    device: Device,
+    open_count: i32,
+    table: Vec<(u64, u64, String, String)>,
+}
+
+impl StackDevice {
+    /// Whether the table of this device refers to the given device.
+    fn refers_to(&self, device: Device) -> bool {
+        let device = device.to_string();
+        self.table
+            .iter()
+            .any(|(_, _, _, params)| params.split_whitespace().any(|word| word == device))
+    }
+}
+
+/// Remove the devices with the given names, from the top of the stack down.
+/// The number of devices removed is recorded so that a failure part way
+/// through can be undone.
+fn remove_stack(names: &[DmNameBuf], removed: &mut usize) -> StratisResult<()> {
+    for name in names {
+        retry_dm(|| get_dm().device_remove(&DevId::Name(name), &DmOptions::new()))?;
+        *removed += 1;
+    }
+    Ok(())
+}
+
+/// Create the devices, from the bottom of the stack up, with the given names
+/// and the tables that they were found with. The tables refer to the devices
+/// below them by device number, which changes when a device is created anew,
+/// so the references to the devices created here are updated. The names of
+/// the devices created are recorded so that a failure part way through can
+/// be undone.
+fn create_stack(
+    devices: &[StackDevice],
+    names: &[DmNameBuf],
+    created: &mut Vec<DmNameBuf>,
+) -> StratisResult<()> {
+    let mut renumbered: HashMap<String, String> = HashMap::new();
+    for (device, name) in devices.iter().zip(names).rev() {
+        // stratisd gives every device it manages the same name and UUID.
+        let uuid = DmUuidBuf::new(name.to_string())?;
+        let info = get_dm().device_create(name, Some(&uuid), &DmOptions::new())?;
+        created.push(name.clone());
+
+        let table = device
+            .table
+            .iter()
+            .map(|(start, length, target_type, params)| {
+                let params = params
+                    .split_whitespace()
+                    .map(|word| renumbered.get(word).map_or(word, |new| new.as_str()))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                (*start, *length, target_type.clone(), params)
+            })
+            .collect::<Vec<_>>();
+        let id = DevId::Name(name);
+        get_dm().table_load(&id, &table)?;
+        get_dm().device_suspend(&id, &DmOptions::new())?;
+        renumbered.insert(device.device.to_string(), info.device().to_string());
+    }
+    Ok(())
+}
+
+/// Rebuild the live DM devices of the pool with UUID old_uuid so that their
+/// names and DM UUIDs embed new_uuid instead. The kernel does not allow the
+/// DM UUID of a device to be changed, so the devices are removed, from the
+/// top of the stack down, and created anew with the same tables, from the
+/// bottom up. They can not be created before the old devices are removed,
+/// since two thin pools must never be active on the same metadata. If
+/// rebuilding fails part way through, the devices are rebuilt with their
+/// old names and UUIDs.
+///
+/// The devices must not be in use other than by the devices above them in
+/// the stack; in particular, the filesystems of the pool must be unmounted.
+///
+/// Returns the old and the new name of every rebuilt device.
+pub fn rename_pool_devices(
+    old_uuid: PoolUuid,
+    new_uuid: PoolUuid,
+) -> StratisResult<Vec<(DmNameBuf, DmNameBuf)>> {
+    let mut renames = get_dm()
+        .list_devices()?
+        .into_iter()
+        .filter_map(|(name, _, _)| {
+            pool_dm_name_for_uuid(&name, old_uuid, new_uuid).map(|new_name| (name, new_name))
+        })
+        .collect::<Vec<_>>();
+    renames.sort_by_key(|(name, _)| pool_dm_stack_level(name));
+
+    let devices = renames
+        .iter()
+        .map(|(name, _)| {
+            let (info, table) = get_dm().table_status(
+                &DevId::Name(name),
+                DmOptions::new().set_flags(DmFlags::DM_STATUS_TABLE),
+            )?;
+            Ok(StackDevice {
+                device: info.device(),
+                open_count: info.open_count(),
+                table,
+            })
+        })
+        .collect::<StratisResult<Vec<_>>>()?;
+    for (i, (name, _)) in renames.iter().enumerate() {
+        let holders = devices[..i]
+            .iter()
+            .filter(|above| above.refers_to(devices[i].device))
+            .count();
+        if devices[i].open_count > convert_int!(holders, usize, i32)? {
+            return Err(StratisError::Msg(format!(
+                "Device {} is in use; the filesystems of the pool must be unmounted before its devices can be renamed",
+                name
+            )));
+        }
+    }
+
+    let (old_names, new_names): (Vec<_>, Vec<_>) = renames.iter().cloned().unzip();
+    let mut removed = 0;
+    let mut created = Vec::new();
+    let result = remove_stack(&old_names, &mut removed)
+        .and_then(|_| create_stack(&devices, &new_names, &mut created));
+    if let Err(err) = result {
+        created.reverse();
+        if let Err(e) = remove_stack(&created, &mut 0) {
+            warn!(
+                "Failed to remove the partially rebuilt devices of pool with UUID {}: {}",
+                new_uuid, e
+            );
+        }
+        if let Err(e) = create_stack(&devices[..removed], &old_names[..removed], &mut Vec::new()) {
+            error!(
+                "Failed to restore the devices of pool with UUID {}: {}",
+                old_uuid, e
+            );
+        }
+        return Err(err);
+    }
+    Ok(renames)
+}
+
+/// Resume those DM devices that stratisd manages for the pool with UUID
+/// pool_uuid which are suspended, from the bottom of the stack up. A crash
+/// while the devices were suspended leaves them suspended, so that all I/O
+/// to them blocks. The crypt devices, whose names embed the device UUIDs
+/// rather than the pool UUID, are not considered.
+///
+/// Returns the names of the devices that were resumed.
+pub fn resume_suspended_pool_devices(pool_uuid: PoolUuid) -> StratisResult<Vec<DmNameBuf>> {
+    let mut names = get_dm()
+        .list_devices()?
+        .into_iter()
+        .filter_map(|(name, _, _)| pool_dm_name_for_uuid(&name, pool_uuid, pool_uuid).map(|_| name))
+        .collect::<Vec<_>>();
+    names.sort_by_key(|name| Reverse(pool_dm_stack_level(name)));
+
+    let mut resumed = Vec::new();
+    for name in names {
+        let id = DevId::Name(&name);
+        if get_dm().device_info(&id)?.flags() & DmFlags::DM_SUSPEND == DmFlags::DM_SUSPEND {
+            retry_dm(|| get_dm().device_suspend(&id, &DmOptions::new()))?;
+            resumed.push(name);
+        }
+    }
+    Ok(resumed)
+}
+
+/// The devicemapper targets that provide a kernel feature, each with the
+/// minimum version that supports everything the engine requires of it.
+fn required_targets(feature: KernelFeature) -> &'static [(&'static str, (u32, u32, u32))] {
+    match feature {
+        // The thin pool is always set up with error_if_no_space.
+        KernelFeature::ThinProvisioning => &[("thin-pool", (1, 10, 0)), ("thin", (1, 10, 0))],
+        KernelFeature::Cache => &[("cache", (1, 0, 0))],
+        KernelFeature::Encryption => &[("crypt", (1, 0, 0))],
+    }
+}
+
+/// Determine whether the kernel supports a feature by inspecting the
+/// versions of the loaded devicemapper targets.
+///
+/// A target that is built as a module is only listed once the module has
+/// been loaded, which usually happens when a device that uses it is first
+/// set up. A missing target therefore makes the support unknown rather
+/// than unsupported. Only definite results are cached, so that a feature
+/// is probed again once its module may have been loaded.
+pub fn kernel_feature_support(feature: KernelFeature) -> KernelFeatureSupport {
+    let mut cache = KERNEL_FEATURES
+        .lock()
+        .expect("no thread panics while holding the lock");
+    if let Some(support) = cache.get(&feature) {
+        return *support;
+    }
+
+    let versions = match get_dm().list_versions() {
+        Ok(versions) => versions,
+        Err(err) => {
+            warn!("Failed to list the loaded devicemapper targets: {}", err);
+            return KernelFeatureSupport::Unknown;
+        }
+    };
+
+    let mut support = KernelFeatureSupport::Supported;
+    for (target, min_version) in required_targets(feature) {
+        match versions.iter().find(|(name, _, _, _)| name == target) {
+            Some((_, major, minor, patch)) if (*major, *minor, *patch) < *min_version => {
+                support = KernelFeatureSupport::Unsupported;
+                break;
+            }
+            Some(_) => (),
+            None => support = KernelFeatureSupport::Unknown,
+        }
+    }
+
+    if support != KernelFeatureSupport::Unknown {
+        cache.insert(feature, support);
+    }
+    support
+}
+
+/// Return an error with a clear explanation if the kernel is known not to
+/// support a feature that an operation requires.
+pub fn check_kernel_feature(feature: KernelFeature) -> StratisResult<()> {
+    if kernel_feature_support(feature) == KernelFeatureSupport::Unsupported {
+        return Err(StratisError::Msg(format!(
+            "The kernel lacks feature {}; it requires the devicemapper targets {}",
+            feature,
+            required_targets(feature)
+                .iter()
+                .map(|(target, (major, minor, patch))| format!(
+                    "{} version {}.{}.{} or later",
+                    target, major, minor, patch
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    fn busy() -> DmError {
+        DmError::from(io::Error::from_raw_os_error(Errno::EBUSY as i32))
+    }
+
+    #[test]
+    /// A transiently failing operation is attempted until it succeeds or
+    /// the attempts are exhausted.
+    fn retry_transient() {
+        let policy = DmRetryPolicy {
+            attempts: 3,
+            backoff: Duration::from_millis(0),
+        };
+
+        let mut calls = 0;
+        let result = retry_dm_with(policy, || {
+            calls += 1;
+            if calls < 3 {
+                Err(busy())
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_matches!(result, Ok(3));
+
+        let mut calls = 0;
+        let result: DmResult<()> = retry_dm_with(policy, || {
+            calls += 1;
+            Err(busy())
+        });
+        assert_matches!(result, Err(_));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    /// An operation that fails with an error that is not transient is not
+    /// attempted again.
+    fn no_retry_permanent() {
+        let mut calls = 0;
+        let result: DmResult<()> = retry_dm_with(DmRetryPolicy::default(), || {
+            calls += 1;
+            Err(DmError::from(io::Error::from_raw_os_error(
+                Errno::ENOENT as i32,
+            )))
+        });
+        assert_matches!(result, Err(_));
+        assert_eq!(calls, 1);
+    }
+}
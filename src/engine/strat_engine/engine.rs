@@ -2,7 +2,15 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use std::{clone::Clone, collections::HashMap, path::Path};
+use std::{
+    clone::Clone,
+    collections::HashMap,
+    convert::TryFrom,
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::{Duration, Instant},
+};
 
 use serde_json::Value;
 
@@ -10,25 +18,171 @@ use devicemapper::DmNameBuf;
 
 use crate::{
     engine::{
-        engine::KeyActions,
-        shared::{create_pool_idempotent_or_err, validate_name, validate_paths},
+        engine::{EngineObserver, KeyActions},
+        shared::{
+            create_pool_idempotent_or_err, finish_move_filesystem, process_resource_usage,
+            start_move_filesystem, startup_order, validate_header_dir, validate_name,
+            validate_paths, validate_stripe, with_report_version,
+        },
         strat_engine::{
+            backstore::{allocation_preview, validate_devices},
             cmd::verify_binaries,
-            dm::get_dm,
+            device::benchmark_device,
+            devlinks,
+            dm::{get_dm, kernel_feature_support, rename_pool_devices},
             keys::{MemoryFilesystem, StratKeyActions},
             liminal::{find_all, LiminalDevices},
+            names::is_stratis_dm_name,
             pool::StratPool,
         },
         structures::Table,
         types::{
-            CreateAction, DeleteAction, DevUuid, EncryptionInfo, LockedPoolInfo, RenameAction,
-            ReportType, SetUnlockAction, UdevEngineEvent, UnlockMethod,
+            AllocationPreview, CreateAction, DeleteAction, DevUuid, DeviceBenchmark, DeviceVerdict,
+            EncryptionInfo, EngineEvent, FilesystemUuid, KernelFeature, KernelFeatureSupport,
+            LockedPoolInfo, ReadWork, RenameAction, ReportType, ResourceUsage, SetUnlockAction,
+            StartupProgress, StripeConfig, UdevEngineEvent, UnlockMethod,
+            ENGINE_STATE_REPORT_VERSION,
         },
         Engine, Name, Pool, PoolUuid, Report,
     },
     stratis::{StratisError, StratisResult},
 };
 
+/// The interval at which the devices of pools that could not be set up at
+/// startup are looked for again.
+const STARTUP_DEVICE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The interval at which the engine retries to unlock pools whose key is
+/// not yet in the kernel keyring, if no interval is specified.
+const STARTUP_KEY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+lazy_static! {
+    static ref STARTUP_DEVICE_WAIT: RwLock<Duration> = RwLock::new(Duration::from_secs(0));
+    static ref STARTUP_KEY_POLICY: RwLock<StartupKeyPolicy> =
+        RwLock::new(StartupKeyPolicy::default());
+    static ref STARTUP_READ_ONLY_MODE: RwLock<bool> = RwLock::new(false);
+    static ref STARTUP_PROGRESS: RwLock<Option<(Instant, StartupProgress)>> = RwLock::new(None);
+}
+
+/// Set how long the engine waits at startup for the missing devices of
+/// pools that can not be set up yet. By default, the engine does not wait,
+/// and such pools are set up as soon as their missing devices appear.
+// FIXME: All pools have Redundancy::NONE, so a pool with a missing device
+// can never be started degraded. An option to start a redundant pool
+// degraded when the wait is over belongs here once redundancy exists.
+pub fn set_startup_device_wait(wait: Duration) {
+    *STARTUP_DEVICE_WAIT
+        .write()
+        .expect("no thread panics while holding the lock") = wait;
+}
+
+/// How the engine handles the locked pools that are bound to a key in the
+/// kernel keyring at startup.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StartupKeyPolicy {
+    /// Leave the pools locked until they are unlocked explicitly.
+    Manual,
+    /// Unlock the pools whose key is in the kernel keyring, and leave the
+    /// others locked.
+    FailFast,
+    /// Unlock the pools whose key is in the kernel keyring, and look for
+    /// the keys of the others again every interval until timeout has passed.
+    Wait {
+        timeout: Duration,
+        interval: Duration,
+    },
+}
+
+impl Default for StartupKeyPolicy {
+    fn default() -> Self {
+        StartupKeyPolicy::Manual
+    }
+}
+
+impl<'a> TryFrom<&'a str> for StartupKeyPolicy {
+    type Error = StratisError;
+
+    /// Parse a policy from one of "manual", "fail-fast", "wait:SECONDS",
+    /// and "wait:SECONDS:INTERVAL_SECONDS".
+    fn try_from(s: &str) -> StratisResult<StartupKeyPolicy> {
+        let parse_secs = |secs: &str| {
+            secs.parse::<u64>().map(Duration::from_secs).map_err(|e| {
+                StratisError::Msg(format!("{} is an invalid number of seconds: {}", secs, e))
+            })
+        };
+        match s.split(':').collect::<Vec<_>>().as_slice() {
+            ["manual"] => Ok(StartupKeyPolicy::Manual),
+            ["fail-fast"] => Ok(StartupKeyPolicy::FailFast),
+            ["wait", timeout] => Ok(StartupKeyPolicy::Wait {
+                timeout: parse_secs(timeout)?,
+                interval: STARTUP_KEY_POLL_INTERVAL,
+            }),
+            ["wait", timeout, interval] => {
+                let interval = parse_secs(interval)?;
+                if interval == Duration::from_secs(0) {
+                    return Err(StratisError::Msg(
+                        "The interval at which keys are looked for must not be 0".to_string(),
+                    ));
+                }
+                Ok(StartupKeyPolicy::Wait {
+                    timeout: parse_secs(timeout)?,
+                    interval,
+                })
+            }
+            _ => Err(StratisError::Msg(format!(
+                "{} is an invalid startup key policy; expected manual, fail-fast, wait:SECONDS, or wait:SECONDS:INTERVAL_SECONDS",
+                s
+            ))),
+        }
+    }
+}
+
+impl Display for StartupKeyPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StartupKeyPolicy::Manual => write!(f, "manual"),
+            StartupKeyPolicy::FailFast => write!(f, "fail-fast"),
+            StartupKeyPolicy::Wait { timeout, interval } => {
+                write!(f, "wait:{}:{}", timeout.as_secs(), interval.as_secs())
+            }
+        }
+    }
+}
+
+/// Set how the engine handles the locked pools that are bound to a key in
+/// the kernel keyring at startup. The key of such a pool is often added to
+/// the keyring only shortly after the engine has started.
+// FIXME: A pool whose key appears only after startup is not unlocked
+// automatically; it must still be unlocked explicitly.
+pub fn set_startup_key_policy(policy: StartupKeyPolicy) {
+    *STARTUP_KEY_POLICY
+        .write()
+        .expect("no thread panics while holding the lock") = policy;
+}
+
+/// Set whether the engine starts in read-only mode. In read-only mode the
+/// pools found at startup are set up without writing to them.
+pub fn set_startup_read_only_mode(read_only: bool) {
+    *STARTUP_READ_ONLY_MODE
+        .write()
+        .expect("no thread panics while holding the lock") = read_only;
+}
+
+/// The progress of the scan for the devices of pools and of the setup of the
+/// pools found while the engine is starting up, or None if it is not. Since
+/// the engine is locked for the whole of the startup, the progress is
+/// published here, where it can be read without the engine lock.
+pub fn startup_progress_so_far() -> Option<StartupProgress> {
+    STARTUP_PROGRESS
+        .read()
+        .expect("no thread panics while holding the lock")
+        .as_ref()
+        .map(|(start, progress)| StartupProgress {
+            duration: start.elapsed(),
+            ..progress.clone()
+        })
+}
+
 #[derive(Debug)]
 pub struct StratEngine {
     pools: Table<PoolUuid, StratPool>,
@@ -49,36 +203,84 @@ pub struct StratEngine {
     // In memory filesystem for passing keys to Clevis.
     // See GitHub issue: https://github.com/stratis-storage/project/issues/212.
     key_fs: MemoryFilesystem,
+
+    // If true, the engine does not act on devicemapper events, and pools
+    // are set up without writing to them.
+    read_only_mode: bool,
+
+    // The udev events received while monitoring is paused, which are
+    // handled once monitoring is resumed
+    deferred_events: Vec<UdevEngineEvent>,
+
+    // If true, the engine does not act on devicemapper or udev events, so
+    // that its behavior can be debugged without interference.
+    monitoring_paused: bool,
+
+    // The observers subscribed to the engine's events
+    observers: Vec<Box<dyn EngineObserver>>,
+
+    // The outcome of the startup scan, with the pools that were pending
+    // at the end of startup
+    startup_progress: StartupProgress,
 }
 
 impl StratEngine {
     /// Setup a StratEngine.
     /// 1. Verify the existence of Stratis /dev directory.
-    /// 2. Setup all the pools belonging to the engine.
-    ///    a. Places any devices which belong to a pool, but are not complete
-    ///       in the incomplete pools data structure.
+    /// 2. Setup all the pools belonging to the engine, as start_up() does.
     ///
     /// Returns an error if the kernel doesn't support required DM features.
     /// Returns an error if there was an error reading device nodes.
     /// Returns an error if the binaries on which it depends can not be found.
     pub fn initialize() -> StratisResult<StratEngine> {
-        verify_binaries()?;
+        let mut engine = StratEngine::new()?;
+        engine.start_up()?;
+        Ok(engine)
+    }
 
-        let mut liminal_devices = LiminalDevices::default();
-        let mut pools = Table::default();
-        for (pool_name, pool_uuid, pool) in liminal_devices.setup_pools(find_all()?) {
-            pools.insert(pool_name, pool_uuid, pool);
-        }
+    /// Create a StratEngine which has not yet set up any pools. The pools
+    /// are set up by start_up().
+    ///
+    /// Returns an error if the binaries on which it depends can not be found.
+    pub fn new() -> StratisResult<StratEngine> {
+        verify_binaries()?;
+        devlinks::cleanup_devlinks();
 
         Ok(StratEngine {
-            pools,
-            liminal_devices,
+            pools: Table::default(),
+            liminal_devices: LiminalDevices::default(),
             watched_dev_last_event_nrs: HashMap::new(),
             key_handler: StratKeyActions,
             key_fs: MemoryFilesystem::new()?,
+            read_only_mode: *STARTUP_READ_ONLY_MODE
+                .read()
+                .expect("no thread panics while holding the lock"),
+            deferred_events: Vec::new(),
+            monitoring_paused: false,
+            observers: Vec::new(),
+            startup_progress: StartupProgress::default(),
         })
     }
 
+    /// Publish the progress of the startup so far, so that it can be read
+    /// with startup_progress_so_far() while the engine is locked, or clear
+    /// it once the startup is over.
+    fn publish_startup_progress(progress: Option<(Instant, &StartupProgress)>) {
+        *STARTUP_PROGRESS
+            .write()
+            .expect("no thread panics while holding the lock") =
+            progress.map(|(start, progress)| (start, progress.clone()));
+    }
+
+    /// Get the pool designated by uuid, with access to the internals of the
+    /// StratPool.
+    #[cfg(test)]
+    pub fn get_strat_pool(&self, uuid: PoolUuid) -> Option<(Name, &StratPool)> {
+        self.pools
+            .get_by_uuid(uuid)
+            .map(|(name, pool)| (name.clone(), pool))
+    }
+
     /// Recursively remove all devicemapper devices in all pools.
     /// Do not remove the dm-crypt devices that comprise the backstore.
     #[cfg(test)]
@@ -127,6 +329,7 @@ impl<'a> Into<Value> for &'a StratEngine {
                     })
                     .collect()
             ),
+            "monitoring_paused": self.monitoring_paused,
         });
         if let (Value::Object(mut j), Value::Object(map)) = (
             json,
@@ -142,22 +345,32 @@ impl<'a> Into<Value> for &'a StratEngine {
 
 impl Report for StratEngine {
     fn engine_state_report(&self) -> Value {
-        self.into()
+        with_report_version(self.into(), ENGINE_STATE_REPORT_VERSION)
     }
 
     fn get_report(&self, report_type: ReportType) -> Value {
-        match report_type {
+        let report = match report_type {
             ReportType::ErroredPoolDevices => (&self.liminal_devices).into(),
-        }
+        };
+        with_report_version(report, report_type.version())
     }
 }
 
 impl Engine for StratEngine {
     fn handle_event(&mut self, event: &UdevEngineEvent) -> Option<(Name, PoolUuid, &dyn Pool)> {
+        if self.monitoring_paused {
+            self.deferred_events.push(event.clone());
+            return None;
+        }
+
         if let Some((pool_uuid, pool_name, pool)) =
-            self.liminal_devices.block_evaluate(&self.pools, event)
+            self.liminal_devices.block_evaluate(&mut self.pools, event)
         {
             self.pools.insert(pool_name.clone(), pool_uuid, pool);
+            self.notify_observers(&EngineEvent::PoolAdded {
+                uuid: pool_uuid,
+                name: pool_name.clone(),
+            });
             Some((
                 pool_name,
                 pool_uuid,
@@ -168,12 +381,27 @@ impl Engine for StratEngine {
         }
     }
 
+    fn process_deferred_events(&mut self) -> Vec<(Name, PoolUuid)> {
+        if self.monitoring_paused {
+            return Vec::new();
+        }
+
+        let mut set_up = Vec::new();
+        for event in std::mem::take(&mut self.deferred_events) {
+            if let Some((pool_name, pool_uuid, _)) = self.handle_event(&event) {
+                set_up.push((pool_name, pool_uuid));
+            }
+        }
+        set_up
+    }
+
     fn create_pool(
         &mut self,
         name: &str,
         blockdev_paths: &[&Path],
         redundancy: Option<u16>,
         encryption_info: &EncryptionInfo,
+        stripe: Option<StripeConfig>,
     ) -> StratisResult<CreateAction<PoolUuid>> {
         let redundancy = calculate_redundancy!(redundancy);
 
@@ -181,6 +409,26 @@ impl Engine for StratEngine {
 
         validate_paths(blockdev_paths)?;
 
+        if let Some(stripe) = stripe {
+            validate_stripe(stripe, blockdev_paths)?;
+        }
+
+        if encryption_info.is_encrypted() {
+            encryption_info.cipher_info.validate()?;
+            if let Some(ref params) = encryption_info.pbkdf_params {
+                params.validate()?;
+            }
+        }
+        validate_header_dir(encryption_info)?;
+        if let Some(ref dir) = encryption_info.header_dir {
+            if !dir.is_dir() {
+                return Err(StratisError::Msg(format!(
+                    "Header directory {} does not exist",
+                    dir.display()
+                )));
+            }
+        }
+
         match self.pools.get_by_name(name) {
             Some((_, pool)) => create_pool_idempotent_or_err(pool, name, blockdev_paths),
             None => {
@@ -189,11 +437,33 @@ impl Engine for StratEngine {
                         "At least one blockdev is required to create a pool.".to_string(),
                     ))
                 } else {
-                    let (uuid, pool) =
-                        StratPool::initialize(name, blockdev_paths, redundancy, encryption_info)?;
+                    let (uuid, mut pool) = StratPool::initialize(
+                        name,
+                        blockdev_paths,
+                        redundancy,
+                        encryption_info,
+                        stripe,
+                    )?;
 
                     let name = Name::new(name.to_owned());
-                    self.pools.insert(name, uuid, pool);
+                    // FIXME: Only the pools which are set up are taken into
+                    // account, so a pool may get the sequence number of a
+                    // pool which is stopped or locked.
+                    let sequence_number = self
+                        .pools
+                        .iter()
+                        .map(|(_, _, pool)| pool.sequence_number())
+                        .max()
+                        .unwrap_or(0)
+                        + 1;
+                    if let Err(err) = pool.set_sequence_number(&name, sequence_number) {
+                        warn!(
+                            "Failed to record the sequence number of pool {}: {}",
+                            name, err
+                        );
+                    }
+                    self.pools.insert(name.clone(), uuid, pool);
+                    self.notify_observers(&EngineEvent::PoolAdded { uuid, name });
                     Ok(CreateAction::Created(uuid))
                 }
             }
@@ -205,6 +475,12 @@ impl Engine for StratEngine {
             if pool.has_filesystems() {
                 return Err(StratisError::Msg("filesystems remaining on pool".into()));
             };
+            if let Some(operation) = pool.operation_in_progress() {
+                return Err(StratisError::Msg(format!(
+                    "operation {} is in progress on pool",
+                    operation
+                )));
+            }
         } else {
             return Ok(DeleteAction::Identity);
         }
@@ -218,10 +494,40 @@ impl Engine for StratEngine {
             self.pools.insert(pool_name, uuid, pool);
             Err(err)
         } else {
+            devlinks::pool_removed(&pool_name);
+            self.notify_observers(&EngineEvent::PoolRemoved(uuid));
             Ok(DeleteAction::Deleted(uuid))
         }
     }
 
+    fn export_pool(&mut self, uuid: PoolUuid) -> StratisResult<DeleteAction<PoolUuid>> {
+        let (pool_name, mut pool) = match self.pools.remove_by_uuid(uuid) {
+            Some(entry) => entry,
+            None => return Ok(DeleteAction::Identity),
+        };
+
+        if let Err(err) = pool.export(&pool_name) {
+            self.pools.insert(pool_name, uuid, pool);
+            return Err(err);
+        }
+
+        devlinks::pool_removed(&pool_name);
+        // The devices of the pool are only known as the devices of a
+        // stopped pool once they are identified anew.
+        for (_, _, bd) in pool.blockdevs() {
+            if let Err(e) = bd.udev_change() {
+                warn!(
+                    "Failed to trigger a udev change event for device {} of exported pool {}: {}",
+                    bd.physical_path().display(),
+                    pool_name,
+                    e
+                );
+            }
+        }
+        self.notify_observers(&EngineEvent::PoolRemoved(uuid));
+        Ok(DeleteAction::Deleted(uuid))
+    }
+
     fn rename_pool(
         &mut self,
         uuid: PoolUuid,
@@ -240,13 +546,39 @@ impl Engine for StratEngine {
             self.pools.insert(old_name, uuid, pool);
             Err(err)
         } else {
-            self.pools.insert(new_name, uuid, pool);
-            let (new_name, pool) = self.pools.get_by_uuid(uuid).expect("Inserted above");
+            self.pools.insert(new_name.clone(), uuid, pool);
+            devlinks::pool_removed(&old_name);
+            let (_, pool) = self.pools.get_by_uuid(uuid).expect("Inserted above");
             pool.udev_pool_change(&new_name);
+            self.notify_observers(&EngineEvent::PoolRenamed {
+                uuid,
+                name: new_name,
+            });
             Ok(RenameAction::Renamed(uuid))
         }
     }
 
+    fn rename_pool_devices(
+        &mut self,
+        old_uuid: PoolUuid,
+        new_uuid: PoolUuid,
+    ) -> StratisResult<Vec<(String, String)>> {
+        if let Some(uuid) = [old_uuid, new_uuid]
+            .iter()
+            .find(|uuid| self.pools.contains_uuid(**uuid))
+        {
+            return Err(StratisError::Msg(format!(
+                "The pool with UUID {} is started; the devices of a started pool can not be renamed",
+                uuid
+            )));
+        }
+
+        Ok(rename_pool_devices(old_uuid, new_uuid)?
+            .into_iter()
+            .map(|(old_name, new_name)| (old_name.to_string(), new_name.to_string()))
+            .collect())
+    }
+
     fn unlock_pool(
         &mut self,
         pool_uuid: PoolUuid,
@@ -258,6 +590,89 @@ impl Engine for StratEngine {
         Ok(SetUnlockAction::new(unlocked))
     }
 
+    fn start_pool(&mut self, pool_uuid: PoolUuid) -> StratisResult<CreateAction<PoolUuid>> {
+        if self.pools.contains_uuid(pool_uuid) {
+            return Ok(CreateAction::Identity);
+        }
+
+        let (pool_name, pool) = self.liminal_devices.start_pool(&self.pools, pool_uuid)?;
+        self.pools.insert(pool_name.clone(), pool_uuid, pool);
+        self.notify_observers(&EngineEvent::PoolAdded {
+            uuid: pool_uuid,
+            name: pool_name,
+        });
+        Ok(CreateAction::Created(pool_uuid))
+    }
+
+    fn startup_order(&self) -> Vec<PoolUuid> {
+        startup_order(
+            self.pools
+                .iter()
+                .filter(|(_, _, pool)| pool.auto_start())
+                .map(|(_, uuid, pool)| (*uuid, pool.start_priority())),
+        )
+    }
+
+    fn kernel_features(&self) -> HashMap<KernelFeature, KernelFeatureSupport> {
+        KernelFeature::ALL
+            .iter()
+            .map(|feature| (*feature, kernel_feature_support(*feature)))
+            .collect()
+    }
+
+    fn validate_pool_name(&self, name: &str) -> Vec<String> {
+        if let Err(err) = validate_name(name) {
+            return vec![err.to_string()];
+        }
+        if self.pools.contains_name(name) {
+            return vec![format!("A pool named {} exists already", name)];
+        }
+        devlinks::pool_dir_conflict(name).into_iter().collect()
+    }
+
+    fn validate_device_set(
+        &self,
+        blockdev_paths: &[&Path],
+        encrypted: bool,
+    ) -> StratisResult<Vec<(PathBuf, DeviceVerdict)>> {
+        validate_paths(blockdev_paths)?;
+        Ok(validate_devices(blockdev_paths, encrypted))
+    }
+
+    fn benchmark_device(&self, devnode: &Path) -> StratisResult<ReadWork<DeviceBenchmark>> {
+        match self
+            .validate_device_set(&[devnode], false)?
+            .pop()
+            .expect("one verdict for each device")
+        {
+            (_, DeviceVerdict::Usable(_)) => benchmark_device(devnode),
+            (_, verdict) => Err(StratisError::Msg(format!(
+                "Device {} can not be benchmarked: {}",
+                devnode.display(),
+                verdict
+            ))),
+        }
+    }
+
+    fn device_allocation_preview(
+        &self,
+        devnode: &Path,
+        encrypted: bool,
+    ) -> StratisResult<AllocationPreview> {
+        match self
+            .validate_device_set(&[devnode], encrypted)?
+            .pop()
+            .expect("one verdict for each device")
+        {
+            (_, DeviceVerdict::Usable(_)) => allocation_preview(devnode, encrypted),
+            (_, verdict) => Err(StratisError::Msg(format!(
+                "Device {} could not be added to a pool: {}",
+                devnode.display(),
+                verdict
+            ))),
+        }
+    }
+
     fn get_pool(&self, uuid: PoolUuid) -> Option<(Name, &dyn Pool)> {
         get_pool!(self; uuid)
     }
@@ -284,7 +699,31 @@ impl Engine for StratEngine {
             .collect()
     }
 
+    fn start_move_filesystem(
+        &mut self,
+        fs_uuid: FilesystemUuid,
+        dest_pool_uuid: PoolUuid,
+    ) -> StratisResult<ReadWork<()>> {
+        start_move_filesystem(&mut self.pools, fs_uuid, dest_pool_uuid)
+    }
+
+    fn finish_move_filesystem(
+        &mut self,
+        fs_uuid: FilesystemUuid,
+        dest_pool_uuid: PoolUuid,
+        copied: StratisResult<()>,
+    ) -> StratisResult<(PoolUuid, FilesystemUuid)> {
+        finish_move_filesystem(&mut self.pools, fs_uuid, dest_pool_uuid, copied)
+    }
+
     fn evented(&mut self) -> StratisResult<()> {
+        // The recorded event numbers are left untouched so that the pools
+        // are checked on the first event after read-only mode is left or
+        // monitoring is resumed.
+        if self.read_only_mode || self.monitoring_paused {
+            return Ok(());
+        }
+
         let device_list: HashMap<_, _> = get_dm()
             .list_devices()?
             .into_iter()
@@ -323,6 +762,16 @@ impl Engine for StratEngine {
         Ok(())
     }
 
+    fn purge_deleted_filesystems(&mut self) {
+        if self.read_only_mode || self.monitoring_paused {
+            return;
+        }
+
+        for (_, pool_uuid, pool) in self.pools.iter_mut() {
+            pool.purge_deleted_filesystems(*pool_uuid);
+        }
+    }
+
     fn get_key_handler(&self) -> &dyn KeyActions {
         &self.key_handler as &dyn KeyActions
     }
@@ -334,6 +783,155 @@ impl Engine for StratEngine {
     fn is_sim(&self) -> bool {
         false
     }
+
+    fn resource_usage(&self) -> StratisResult<ResourceUsage> {
+        let (rss, open_fds) = process_resource_usage()?;
+        let dm_devices = get_dm()
+            .list_devices()?
+            .into_iter()
+            .filter(|(dm_name, _, _)| is_stratis_dm_name(dm_name))
+            .count();
+        Ok(ResourceUsage {
+            rss,
+            open_fds,
+            dm_devices: dm_devices as u64,
+        })
+    }
+
+    fn start_up(&mut self) -> StratisResult<()> {
+        let start = Instant::now();
+        let mut progress = StartupProgress::default();
+        StratEngine::publish_startup_progress(Some((start, &progress)));
+
+        let (luks_devices, stratis_devices) = match find_all() {
+            Ok(devices) => devices,
+            Err(err) => {
+                StratEngine::publish_startup_progress(None);
+                return Err(err);
+            }
+        };
+        progress.devices_scanned = (luks_devices
+            .values()
+            .map(|infos| infos.len())
+            .sum::<usize>()
+            + stratis_devices
+                .values()
+                .map(|infos| infos.len())
+                .sum::<usize>()) as u64;
+        StratEngine::publish_startup_progress(Some((start, &progress)));
+        let read_only_mode = *STARTUP_READ_ONLY_MODE
+            .read()
+            .expect("no thread panics while holding the lock");
+        self.liminal_devices.set_read_only_mode(read_only_mode);
+
+        info!(
+            "Found {} devices of pools; setting up pools{}",
+            progress.devices_scanned,
+            if read_only_mode {
+                " without writing to them"
+            } else {
+                ""
+            }
+        );
+        for (pool_name, pool_uuid, pool) in self
+            .liminal_devices
+            .setup_pools((luks_devices, stratis_devices))
+        {
+            self.pools.insert(pool_name, pool_uuid, pool);
+        }
+
+        let mut report = |pools: &Table<PoolUuid, StratPool>, liminal: &LiminalDevices| {
+            progress.pools_set_up = pools.len() as u64;
+            progress.pools_pending = liminal.incomplete_pools();
+            StratEngine::publish_startup_progress(Some((start, &progress)));
+        };
+        report(&self.pools, &self.liminal_devices);
+
+        let wait = *STARTUP_DEVICE_WAIT
+            .read()
+            .expect("no thread panics while holding the lock");
+        if wait > Duration::from_secs(0) {
+            self.liminal_devices.wait_for_incomplete_pools(
+                &mut self.pools,
+                wait,
+                STARTUP_DEVICE_POLL_INTERVAL,
+                &mut report,
+            );
+        }
+
+        let key_policy = *STARTUP_KEY_POLICY
+            .read()
+            .expect("no thread panics while holding the lock");
+        match key_policy {
+            StartupKeyPolicy::Manual => (),
+            StartupKeyPolicy::FailFast => self.liminal_devices.unlock_pools_with_keyring(
+                &mut self.pools,
+                Duration::from_secs(0),
+                STARTUP_KEY_POLL_INTERVAL,
+                &mut report,
+            ),
+            StartupKeyPolicy::Wait { timeout, interval } => self
+                .liminal_devices
+                .unlock_pools_with_keyring(&mut self.pools, timeout, interval, &mut report),
+        }
+        report(&self.pools, &self.liminal_devices);
+
+        progress.duration = start.elapsed();
+        progress.complete = true;
+        info!(
+            "Set up {} pools in {:.1}s; {} pools are waiting for missing devices",
+            progress.pools_set_up,
+            progress.duration.as_secs_f64(),
+            progress.pools_pending.len()
+        );
+        self.startup_progress = progress;
+        StratEngine::publish_startup_progress(None);
+        Ok(())
+    }
+
+    fn startup_progress(&self) -> StartupProgress {
+        StartupProgress {
+            pools_pending: self
+                .startup_progress
+                .pools_pending
+                .iter()
+                .filter(|uuid| !self.pools.contains_uuid(**uuid))
+                .cloned()
+                .collect(),
+            ..self.startup_progress.clone()
+        }
+    }
+
+    fn read_only_mode(&self) -> bool {
+        self.read_only_mode
+    }
+
+    fn set_read_only_mode(&mut self, read_only: bool) -> bool {
+        let changed = self.read_only_mode != read_only;
+        self.read_only_mode = read_only;
+        self.liminal_devices.set_read_only_mode(read_only);
+        changed
+    }
+
+    fn monitoring_paused(&self) -> bool {
+        self.monitoring_paused
+    }
+
+    fn set_monitoring_paused(&mut self, paused: bool) -> bool {
+        let changed = self.monitoring_paused != paused;
+        self.monitoring_paused = paused;
+        changed
+    }
+
+    fn subscribe(&mut self, observer: Box<dyn EngineObserver>) {
+        self.observers.push(observer);
+    }
+
+    fn notify_observers(&self, event: &EngineEvent) {
+        for observer in &self.observers {
+            observer.notify(event);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -353,13 +951,51 @@ mod test {
 
     use super::*;
 
+    #[test]
+    /// Verify that startup key policies are parsed from all the accepted
+    /// forms, that anything else is rejected, and that a parsed policy is
+    /// displayed as a form that parses to the same policy.
+    fn test_startup_key_policy_parse() {
+        assert_eq!(
+            StartupKeyPolicy::try_from("manual").unwrap(),
+            StartupKeyPolicy::Manual
+        );
+        assert_eq!(
+            StartupKeyPolicy::try_from("fail-fast").unwrap(),
+            StartupKeyPolicy::FailFast
+        );
+        assert_eq!(
+            StartupKeyPolicy::try_from("wait:30").unwrap(),
+            StartupKeyPolicy::Wait {
+                timeout: Duration::from_secs(30),
+                interval: STARTUP_KEY_POLL_INTERVAL,
+            }
+        );
+        let policy = StartupKeyPolicy::try_from("wait:30:5").unwrap();
+        assert_eq!(
+            policy,
+            StartupKeyPolicy::Wait {
+                timeout: Duration::from_secs(30),
+                interval: Duration::from_secs(5),
+            }
+        );
+        assert_eq!(
+            StartupKeyPolicy::try_from(policy.to_string().as_str()).unwrap(),
+            policy
+        );
+        assert!(StartupKeyPolicy::try_from("wait").is_err());
+        assert!(StartupKeyPolicy::try_from("wait:soon").is_err());
+        assert!(StartupKeyPolicy::try_from("wait:30:0").is_err());
+        assert!(StartupKeyPolicy::try_from("retry").is_err());
+    }
+
     /// Verify that a pool rename causes the pool metadata to get the new name.
     fn test_pool_rename(paths: &[&Path]) {
         let mut engine = StratEngine::initialize().unwrap();
 
         let name1 = "name1";
         let uuid1 = engine
-            .create_pool(name1, paths, None, &EncryptionInfo::default())
+            .create_pool(name1, paths, None, &EncryptionInfo::default(), None)
             .unwrap()
             .changed()
             .unwrap();
@@ -455,14 +1091,14 @@ mod test {
 
         let name1 = "name1";
         let uuid1 = engine
-            .create_pool(name1, paths1, None, &EncryptionInfo::default())
+            .create_pool(name1, paths1, None, &EncryptionInfo::default(), None)
             .unwrap()
             .changed()
             .unwrap();
 
         let name2 = "name2";
         let uuid2 = engine
-            .create_pool(name2, paths2, None, &EncryptionInfo::default())
+            .create_pool(name2, paths2, None, &EncryptionInfo::default(), None)
             .unwrap()
             .changed()
             .unwrap();
@@ -497,6 +1133,75 @@ mod test {
         real::test_with_spec(&real::DeviceLimits::AtLeast(2, None, None), test_setup);
     }
 
+    /// Verify that a pool is set up when one of its devices has reappeared
+    /// under a different name between runs of the engine.
+    /// 1. Create a pool with a test device as one of its block devices.
+    /// 2. Teardown the engine.
+    /// 3. Replace the test device with one with a different name that maps
+    /// the same storage.
+    /// 4. Initialize the engine.
+    /// 5. Verify that the pool is set up on the renamed device.
+    fn test_device_renamed(paths: &[&Path]) {
+        let mut paths_with_fail_device = paths.to_vec();
+        let last_device = paths_with_fail_device.pop().unwrap();
+        let fail_device = FailDevice::new(last_device, "stratis_fail_device").unwrap();
+        cmd::udev_settle().unwrap();
+        let fail_device_path = fail_device.as_path();
+        paths_with_fail_device.push(&fail_device_path);
+
+        let mut engine = StratEngine::initialize().unwrap();
+
+        let name = "pool_name";
+        let uuid = engine
+            .create_pool(
+                name,
+                paths_with_fail_device.as_slice(),
+                None,
+                &EncryptionInfo::default(),
+                None,
+            )
+            .unwrap()
+            .changed()
+            .unwrap();
+
+        engine.teardown().unwrap();
+
+        drop(fail_device);
+        let fail_device = FailDevice::new(last_device, "stratis_fail_device_renamed").unwrap();
+        cmd::udev_settle().unwrap();
+        let renamed_path = fail_device.as_path().canonicalize().unwrap();
+
+        let mut engine = StratEngine::initialize().unwrap();
+
+        {
+            let (_, pool) = engine.get_pool(uuid).unwrap();
+            let blockdevs = pool.blockdevs();
+            assert_eq!(blockdevs.len(), paths.len());
+            assert!(blockdevs
+                .iter()
+                .any(|(_, _, bd)| bd.devnode() == renamed_path.as_path()));
+        }
+
+        engine.destroy_pool(uuid).unwrap();
+        engine.teardown().unwrap();
+    }
+
+    #[test]
+    fn loop_test_device_renamed() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(2, 3, None),
+            test_device_renamed,
+        );
+    }
+
+    #[test]
+    fn real_test_device_renamed() {
+        real::test_with_spec(
+            &real::DeviceLimits::AtLeast(2, None, None),
+            test_device_renamed,
+        );
+    }
+
     fn create_pool_and_test_rollback<F>(
         name: &str,
         paths_with_fail_device: &[&Path],
@@ -511,7 +1216,7 @@ mod test {
         let mut engine = StratEngine::initialize()?;
 
         let uuid = engine
-            .create_pool(name, paths_with_fail_device, None, encryption_info)?
+            .create_pool(name, paths_with_fail_device, None, encryption_info, None)?
             .changed()
             .ok_or_else(|| {
                 Box::new(StratisError::Msg(
@@ -572,6 +1277,7 @@ mod test {
                             "stratis:tang:trust_url": true
                         }),
                     )),
+                    ..EncryptionInfo::default()
                 },
                 |pool| {
                     pool.bind_keyring(key_desc)?;
@@ -628,6 +1334,7 @@ mod test {
                 &EncryptionInfo {
                     key_description: Some(key_desc1.to_owned()),
                     clevis_info: None,
+                    ..EncryptionInfo::default()
                 },
                 |pool| {
                     pool.rebind_keyring(key_desc2)?;
@@ -690,6 +1397,7 @@ mod test {
                             "stratis:tang:trust_url": true
                         }),
                     )),
+                    ..EncryptionInfo::default()
                 },
                 |pool| {
                     pool.unbind_keyring()?;
@@ -743,6 +1451,7 @@ mod test {
                 &EncryptionInfo {
                     key_description: Some(key_desc.to_owned()),
                     clevis_info: None,
+                    ..EncryptionInfo::default()
                 },
                 |pool| {
                     pool.bind_clevis(
@@ -804,6 +1513,7 @@ mod test {
                         "stratis:tang:trust_url": true
                     }),
                 )),
+                ..EncryptionInfo::default()
             },
             |pool| {
                 pool.rebind_clevis()?;
@@ -859,6 +1569,7 @@ mod test {
                             "stratis:tang:trust_url": true
                         }),
                     )),
+                    ..EncryptionInfo::default()
                 },
                 |pool| {
                     pool.unbind_clevis()?;
@@ -47,11 +47,11 @@ use std::{
 
 use serde_json::Value;
 
-use devicemapper::Device;
+use devicemapper::{devnode_to_devno, Device};
 
 use crate::engine::{
     strat_engine::{
-        backstore::CryptMetadataHandle,
+        backstore::{detached_header, detached_header_devices, CryptMetadataHandle},
         metadata::{device_identifiers, StratisIdentifiers},
         udev::{
             block_enumerator, decide_ownership, UdevOwnership, CRYPTO_FS_TYPE, FS_TYPE_KEY,
@@ -271,8 +271,57 @@ fn process_stratis_device(dev: &UdevEngineDevice) -> Option<StratisInfo> {
     }
 }
 
+/// Process a device which has a detached LUKS2 header. Such a device has
+/// no signature by which udev could identify it.
+fn process_detached_header_device(devnode: &Path) -> Option<LuksInfo> {
+    let device_number = match devnode_to_devno(devnode) {
+        Ok(Some(devno)) => Device::from(devno),
+        Ok(None) => {
+            warn!(
+                "Device {} has a detached LUKS2 header but is not a block device, disregarding the device",
+                devnode.display()
+            );
+            return None;
+        }
+        Err(err) => {
+            info!(
+                "Device {} with a detached LUKS2 header could not be found, disregarding the device: {}",
+                devnode.display(),
+                err
+            );
+            return None;
+        }
+    };
+    match CryptMetadataHandle::setup(devnode) {
+        Ok(None) => None,
+        Err(err) => {
+            warn!(
+                "Could not read the detached LUKS2 header of device {}, disregarding the device: {}",
+                devnode.display(),
+                err
+            );
+            None
+        }
+        Ok(Some(handle)) => Some(LuksInfo {
+            info: StratisInfo {
+                identifiers: *handle.device_identifiers(),
+                device_number,
+                devnode: handle.luks2_device_path().to_path_buf(),
+            },
+            encryption_info: handle.encryption_info().to_owned(),
+        }),
+    }
+    .map(|info| {
+        info!(
+            "LUKS block device belonging to Stratis with a detached header with {} discovered during initial search",
+            info,
+        );
+        info
+    })
+}
+
 // Find all devices identified by udev and cryptsetup as LUKS devices
-// belonging to Stratis.
+// belonging to Stratis, and all devices with a detached LUKS2 header.
 fn find_all_luks_devices() -> libudev::Result<HashMap<PoolUuid, Vec<LuksInfo>>> {
     let context = libudev::Context::new()?;
     let mut enumerator = block_enumerator(&context)?;
@@ -281,6 +330,11 @@ fn find_all_luks_devices() -> libudev::Result<HashMap<PoolUuid, Vec<LuksInfo>>>
     let pool_map = enumerator
         .scan_devices()?
         .filter_map(|dev| identify_luks_device(&UdevEngineDevice::from(&dev)))
+        .chain(
+            detached_header_devices()
+                .iter()
+                .filter_map(|devnode| process_detached_header_device(devnode)),
+        )
         .fold(HashMap::new(), |mut acc, info| {
             acc.entry(info.info.identifiers.pool_uuid)
                 .or_insert_with(Vec::new)
@@ -385,6 +439,12 @@ pub fn identify_block_device(event: &UdevEngineEvent) -> Option<DeviceInfo> {
         return None;
     };
 
+    if let Some(devnode) = event.device().devnode() {
+        if detached_header(devnode).is_some() {
+            return process_luks_device(event.device()).map(DeviceInfo::Luks);
+        }
+    }
+
     match decide_ownership(event.device()) {
         Err(err) => {
             warn!(
@@ -476,6 +536,7 @@ mod tests {
                 &EncryptionInfo {
                     key_description: Some(key_description.clone()),
                     clevis_info: None,
+                    ..EncryptionInfo::default()
                 },
             )?;
 
@@ -649,7 +710,7 @@ mod tests {
         }
 
         for path in paths {
-            create_fs(path, None, false).unwrap();
+            create_fs(path, None, false, None).unwrap();
             assert_eq!(
                 block_device_apply(path, |dev| process_stratis_device(dev))
                     .unwrap()
@@ -5,8 +5,10 @@
 //! Management of devices which are known to stratisd but not in a pool.
 
 use std::{
+    cmp::min,
     collections::{HashMap, HashSet},
-    fmt,
+    fmt, thread,
+    time::{Duration, Instant},
 };
 
 use serde_json::Value;
@@ -14,10 +16,14 @@ use serde_json::Value;
 use crate::{
     engine::{
         engine::Pool,
+        shared::startup_order,
         strat_engine::{
-            backstore::CryptActivationHandle,
+            backstore::{CryptActivationHandle, StratBlockDev},
+            cmd::udev_settle,
+            keys::search_key_persistent,
             liminal::{
                 device_info::{DeviceBag, DeviceSet, LInfo, LLuksInfo, LStratisInfo},
+                find_all,
                 identify::{identify_block_device, DeviceInfo, LuksInfo, StratisInfo},
                 setup::{get_bdas, get_blockdevs, get_metadata},
             },
@@ -25,16 +31,78 @@ use crate::{
             pool::StratPool,
         },
         structures::Table,
-        types::{DevUuid, LockedPoolInfo, Name, PoolUuid, UdevEngineEvent, UnlockMethod},
+        types::{
+            DevUuid, DevicePath, LockedPoolInfo, Name, PoolUuid, UdevEngineEvent, UnlockMethod,
+        },
     },
     stratis::{StratisError, StratisResult},
 };
 
-/// On an error, whether this set of devices is hopeless or just errored
+/// If a device that belongs to a running pool has reappeared under a
+/// different name, e.g. because kernel device names were reassigned, record
+/// its new path, so that it is not mistaken for a new device.
+///
+/// The device number of a blockdev in a running pool can not be changed, as
+/// the pool's DM devices are built on it. A device with the identifiers of
+/// an unencrypted blockdev but a different device number is a duplicate or a
+/// device that has been replugged, and is ignored with a warning.
+fn update_physical_path(blockdev: &mut StratBlockDev, info: &DeviceInfo) {
+    let devnode = match info {
+        DeviceInfo::Luks(luks_info) if blockdev.crypt_handle().is_some() => &luks_info.info.devnode,
+        DeviceInfo::Stratis(strat_info) if blockdev.crypt_handle().is_none() => {
+            if strat_info.device_number != *blockdev.device() {
+                warn!("Found a device with {} that identifies itself as a device in a running pool, which is set up on device number {}; ignoring it",
+                      strat_info,
+                      blockdev.device());
+                return;
+            }
+            &strat_info.devnode
+        }
+        // The activated device of an encrypted blockdev is found at a path
+        // that Stratis chooses.
+        _ => return,
+    };
+
+    match DevicePath::new(devnode.to_owned()) {
+        Ok(path) => {
+            if *path != *blockdev.physical_path() {
+                info!(
+                    "Device {} in a running pool was found at path {}; recording the new path",
+                    blockdev.physical_path().display(),
+                    path.display()
+                );
+                blockdev.set_physical_path(path);
+            }
+        }
+        Err(err) => {
+            warn!(
+                "Could not resolve path {} of a device in a running pool: {}",
+                devnode.display(),
+                err
+            );
+        }
+    }
+}
+
+/// Read the start priority of the pool from the metadata on the devices in
+/// the set. Return None if the pool has no start priority or if the metadata
+/// can not be read; in the latter case setting up the pool will fail anyway.
+fn start_priority(infos: &DeviceSet) -> Option<u32> {
+    let opened = infos.as_opened_set()?;
+    let bdas = get_bdas(&opened).ok()?;
+    get_metadata(&opened, &bdas)
+        .ok()
+        .flatten()
+        .and_then(|(_, metadata)| metadata.start_priority)
+}
+
+/// On an error, whether this set of devices is hopeless or just errored, or
+/// whether the pool was intentionally not started
 #[derive(Debug)]
 enum Destination {
     Hopeless(String),
     Errored(String),
+    NotStarted(String),
 }
 
 impl fmt::Display for Destination {
@@ -42,6 +110,7 @@ impl fmt::Display for Destination {
         match self {
             Destination::Hopeless(val) => write!(f, "{}", val),
             Destination::Errored(val) => write!(f, "{}", val),
+            Destination::NotStarted(val) => write!(f, "{}", val),
         }
     }
 }
@@ -56,25 +125,39 @@ pub struct LiminalDevices {
     /// Sets of devices which possess some internal contradiction which makes
     /// it impossible for them to be made into sensible pools ever.
     hopeless_device_sets: HashMap<PoolUuid, DeviceBag>,
+    /// Sets of devices belonging to pools which are marked not to be started
+    /// automatically. These pools are only started on request.
+    stopped_pool_devices: HashMap<PoolUuid, DeviceSet>,
+    /// If true, pools are set up without writing to them.
+    read_only_mode: bool,
 }
 
 impl LiminalDevices {
+    /// Set whether pools are set up without writing to them.
+    pub fn set_read_only_mode(&mut self, read_only: bool) {
+        self.read_only_mode = read_only;
+    }
+
     #[allow(dead_code)]
     fn invariant(&self) {
-        assert!(self
+        let errored = self
             .errored_pool_devices
             .keys()
             .cloned()
-            .collect::<HashSet<PoolUuid>>()
-            .intersection(
-                &self
-                    .hopeless_device_sets
-                    .keys()
-                    .cloned()
-                    .collect::<HashSet<PoolUuid>>()
-            )
-            .next()
-            .is_none());
+            .collect::<HashSet<PoolUuid>>();
+        let hopeless = self
+            .hopeless_device_sets
+            .keys()
+            .cloned()
+            .collect::<HashSet<PoolUuid>>();
+        let stopped = self
+            .stopped_pool_devices
+            .keys()
+            .cloned()
+            .collect::<HashSet<PoolUuid>>();
+        assert!(errored.intersection(&hopeless).next().is_none());
+        assert!(errored.intersection(&stopped).next().is_none());
+        assert!(hopeless.intersection(&stopped).next().is_none());
     }
 
     /// Unlock the liminal encrypted devices that correspond to the given pool UUID.
@@ -153,9 +236,44 @@ impl LiminalDevices {
             .collect()
     }
 
+    /// The UUIDs of the pools which could not be set up because some of
+    /// their devices are missing, and which may still be set up when the
+    /// devices appear.
+    pub fn incomplete_pools(&self) -> Vec<PoolUuid> {
+        self.errored_pool_devices.keys().cloned().collect()
+    }
+
+    /// Start the pool with the given UUID, which was not started because it
+    /// is marked not to be started automatically. If the pool can not be set
+    /// up, its devices are distributed as for any other failed setup.
+    pub fn start_pool(
+        &mut self,
+        pools: &Table<PoolUuid, StratPool>,
+        pool_uuid: PoolUuid,
+    ) -> StratisResult<(Name, StratPool)> {
+        let infos = self
+            .stopped_pool_devices
+            .remove(&pool_uuid)
+            .ok_or_else(|| {
+                StratisError::Msg(format!(
+                "No pool with UUID {} which is marked not to be started automatically was found",
+                pool_uuid
+            ))
+            })?;
+        self.try_setup_pool(pools, pool_uuid, infos, true)
+            .ok_or_else(|| {
+                StratisError::Msg(format!(
+                    "Pool with UUID {} could not be set up; see the log for details",
+                    pool_uuid
+                ))
+            })
+    }
+
     /// Take maps of pool UUIDs to sets of devices and return a list of
     /// information about created pools.
     ///
+    /// Pools are set up in the order given by their start priorities.
+    ///
     /// Precondition: No pools have yet been set up, i.e., it is unnecessary
     /// to check for membership in any of the existing categories of device
     /// sets.
@@ -177,7 +295,7 @@ impl LiminalDevices {
             .cloned()
             .collect();
 
-        pool_uuids
+        let mut device_sets: HashMap<PoolUuid, DeviceSet> = pool_uuids
             .iter()
             .filter_map(|pool_uuid| {
                 let luks_infos = luks_devices.remove(pool_uuid);
@@ -204,13 +322,194 @@ impl LiminalDevices {
                 }
 
                 if !self.hopeless_device_sets.contains_key(pool_uuid) {
-                    self.try_setup_pool(&table, *pool_uuid, info_map)
-                        .map(|(pool_name, pool)| (pool_name, *pool_uuid, pool))
+                    Some((*pool_uuid, info_map))
                 } else {
                     None
                 }
             })
-            .collect::<Vec<(Name, PoolUuid, StratPool)>>()
+            .collect();
+
+        startup_order(
+            device_sets
+                .iter()
+                .map(|(pool_uuid, info_map)| (*pool_uuid, start_priority(info_map))),
+        )
+        .into_iter()
+        .filter_map(|pool_uuid| {
+            let info_map = device_sets
+                .remove(&pool_uuid)
+                .expect("pool_uuid obtained from device_sets");
+            self.try_setup_pool(&table, pool_uuid, info_map, false)
+                .map(|(pool_name, pool)| (pool_name, pool_uuid, pool))
+        })
+        .collect::<Vec<(Name, PoolUuid, StratPool)>>()
+    }
+
+    /// Look for the devices of the pools that could not be set up again
+    /// every interval, and set up the pools that have become complete,
+    /// until all have been set up or wait has passed. This gives devices
+    /// that are slow to be enumerated a chance to appear before startup is
+    /// complete. A pool which still can not be set up remains liminal and
+    /// is set up when its missing devices appear. progress is called with
+    /// the pools and the liminal devices after each look.
+    pub fn wait_for_incomplete_pools(
+        &mut self,
+        pools: &mut Table<PoolUuid, StratPool>,
+        wait: Duration,
+        interval: Duration,
+        progress: &mut dyn FnMut(&Table<PoolUuid, StratPool>, &LiminalDevices),
+    ) {
+        let deadline = Instant::now() + wait;
+        loop {
+            if self.errored_pool_devices.is_empty() {
+                return;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            info!(
+                "Waiting for the missing devices of {} pools",
+                self.errored_pool_devices.len()
+            );
+            thread::sleep(min(interval, deadline - now));
+            self.rescan_incomplete_pools(pools);
+            progress(pools, self);
+        }
+
+        warn!(
+            "Stopped waiting for the missing devices of pools with UUIDs {}; the pools will be set up when their devices appear",
+            self.errored_pool_devices
+                .keys()
+                .map(|uuid| uuid.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    /// Look for the devices of the pools that could not be set up again, and
+    /// set up the pools that have become complete.
+    fn rescan_incomplete_pools(&mut self, pools: &mut Table<PoolUuid, StratPool>) {
+        if let Err(err) = udev_settle() {
+            warn!("Could not wait for udev to settle: {}", err);
+        }
+        let (mut luks_devices, mut stratis_devices) = match find_all() {
+            Ok(devices) => devices,
+            Err(err) => {
+                warn!("Could not look for the devices of pools: {}", err);
+                return;
+            }
+        };
+
+        let pool_uuids = self
+            .errored_pool_devices
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        'pools: for pool_uuid in pool_uuids {
+            let mut infos = self
+                .errored_pool_devices
+                .remove(&pool_uuid)
+                .expect("pool_uuid obtained from errored_pool_devices");
+            let found = stratis_devices
+                .remove(&pool_uuid)
+                .unwrap_or_else(Vec::new)
+                .into_iter()
+                .map(DeviceInfo::Stratis)
+                .chain(
+                    luks_devices
+                        .remove(&pool_uuid)
+                        .unwrap_or_else(Vec::new)
+                        .into_iter()
+                        .map(DeviceInfo::Luks),
+                );
+            for info in found {
+                if let Err(hopeless) = infos.process_info_add(info) {
+                    self.hopeless_device_sets.insert(pool_uuid, hopeless);
+                    continue 'pools;
+                }
+            }
+            if let Some((pool_name, pool)) = self.try_setup_pool(pools, pool_uuid, infos, false) {
+                pools.insert(pool_name, pool_uuid, pool);
+            }
+        }
+    }
+
+    /// Unlock the locked pools that are bound to a key in the kernel
+    /// keyring and whose key is present, and set them up. Look for the keys
+    /// that are not present yet again every interval until all pools have
+    /// been unlocked or timeout has passed. A pool that can not be unlocked
+    /// remains locked, and must be unlocked explicitly. progress is called
+    /// with the pools and the liminal devices whenever pools were unlocked.
+    pub fn unlock_pools_with_keyring(
+        &mut self,
+        pools: &mut Table<PoolUuid, StratPool>,
+        timeout: Duration,
+        interval: Duration,
+        progress: &mut dyn FnMut(&Table<PoolUuid, StratPool>, &LiminalDevices),
+    ) {
+        let deadline = Instant::now() + timeout;
+        let mut failed = HashSet::new();
+        loop {
+            let mut waiting = Vec::new();
+            let mut unlocked = false;
+            for (pool_uuid, info) in self.locked_pools() {
+                let key_desc = match info.info.key_description {
+                    Some(ref key_desc) if !failed.contains(&pool_uuid) => key_desc,
+                    _ => continue,
+                };
+                match search_key_persistent(key_desc) {
+                    Ok(Some(_)) => {
+                        match self.unlock_pool(pools, pool_uuid, UnlockMethod::Keyring) {
+                            Ok(_) => {
+                                info!("Unlocked pool with UUID {} at startup", pool_uuid);
+                                unlocked = true;
+                            }
+                            Err(err) => {
+                                warn!(
+                                "Failed to unlock pool with UUID {} with the key in the kernel keyring: {}",
+                                pool_uuid, err
+                            );
+                                failed.insert(pool_uuid);
+                            }
+                        }
+                    }
+                    Ok(None) => waiting.push(pool_uuid),
+                    Err(err) => {
+                        warn!(
+                            "Failed to look for the key of pool with UUID {} in the kernel keyring: {}",
+                            pool_uuid, err
+                        );
+                        failed.insert(pool_uuid);
+                    }
+                }
+            }
+            if unlocked {
+                self.rescan_incomplete_pools(pools);
+                progress(pools, self);
+            }
+
+            if waiting.is_empty() {
+                return;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                warn!(
+                    "The keys of pools with UUIDs {} are not in the kernel keyring; the pools remain locked",
+                    waiting
+                        .iter()
+                        .map(|uuid| uuid.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                return;
+            }
+            info!(
+                "Waiting for the keys of {} pools to appear in the kernel keyring",
+                waiting.len()
+            );
+            thread::sleep(min(interval, deadline - now));
+        }
     }
 
     /// Given a set of devices, try to set up a pool.
@@ -221,18 +520,24 @@ impl LiminalDevices {
     /// If there is a name conflict between the set of devices in devices
     /// and some existing pool, return an error.
     ///
+    /// If the pool is marked not to be started automatically, it is set up
+    /// only if start_requested is true.
+    ///
     /// Precondition: pools.get_by_uuid(pool_uuid).is_none() &&
     ///               self.errored_pool_devices.get(pool_uuid).is_none() &&
-    ///               self.hopeless_device_sets.get(pool_uuid).is_none()
+    ///               self.hopeless_device_sets.get(pool_uuid).is_none() &&
+    ///               self.stopped_pool_devices.get(pool_uuid).is_none()
     fn try_setup_pool(
         &mut self,
         pools: &Table<PoolUuid, StratPool>,
         pool_uuid: PoolUuid,
         infos: DeviceSet,
+        start_requested: bool,
     ) -> Option<(Name, StratPool)> {
         assert!(pools.get_by_uuid(pool_uuid).is_none());
         assert!(self.errored_pool_devices.get(&pool_uuid).is_none());
         assert!(self.hopeless_device_sets.get(&pool_uuid).is_none());
+        assert!(self.stopped_pool_devices.get(&pool_uuid).is_none());
 
         // Setup a pool from constituent devices in the context of some already
         // setup pools.
@@ -243,6 +548,8 @@ impl LiminalDevices {
             pools: &Table<PoolUuid, StratPool>,
             pool_uuid: PoolUuid,
             infos: &HashMap<DevUuid, &LStratisInfo>,
+            start_requested: bool,
+            read_only_mode: bool,
         ) -> Result<(Name, StratPool), Destination> {
             let bdas = match get_bdas(infos) {
                 Err(err) => Err(
@@ -277,6 +584,20 @@ impl LiminalDevices {
                 Ok(Some((timestamp, metadata))) => (timestamp, metadata),
             };
 
+            if !start_requested && metadata.auto_start == Some(false) {
+                return Err(Destination::NotStarted(format!(
+                    "Pool with UUID {} and name {} is marked not to be started automatically",
+                    pool_uuid, &metadata.name
+                )));
+            }
+
+            if !start_requested && metadata.exported == Some(true) {
+                return Err(Destination::NotStarted(format!(
+                    "Pool with UUID {} and name {} was exported",
+                    pool_uuid, &metadata.name
+                )));
+            }
+
             if let Some((uuid, _)) = pools.get_by_name(&metadata.name) {
                 return Err(
                     Destination::Errored(format!(
@@ -286,14 +607,14 @@ impl LiminalDevices {
                         uuid)));
             }
 
-            let (datadevs, cachedevs) = match get_blockdevs(&metadata.backstore, infos, bdas) {
+            let (datadevs, cachedevs, reserveddevs) = match get_blockdevs(&metadata.backstore, infos, bdas) {
                 Err(err) => return Err(
                     Destination::Errored(format!(
                         "There was an error encountered when calculating the block devices for pool with UUID {} and name {}: {}",
                         pool_uuid,
                         &metadata.name,
                         err))),
-                Ok((datadevs, cachedevs)) => (datadevs, cachedevs),
+                Ok((datadevs, cachedevs, reserveddevs)) => (datadevs, cachedevs, reserveddevs),
             };
 
             if datadevs.get(0).is_none() {
@@ -324,7 +645,16 @@ impl LiminalDevices {
                             &metadata.name)));
             }
 
-            StratPool::setup(pool_uuid, datadevs, cachedevs, timestamp, &metadata).map_err(|err| {
+            StratPool::setup(
+                pool_uuid,
+                datadevs,
+                cachedevs,
+                reserveddevs,
+                timestamp,
+                &metadata,
+                read_only_mode,
+            )
+            .map_err(|err| {
                 Destination::Errored(format!(
                     "An attempt to set up pool with UUID {} from the assembled devices failed: {}",
                     pool_uuid, err
@@ -340,7 +670,13 @@ impl LiminalDevices {
             }
         };
 
-        let result = setup_pool(pools, pool_uuid, &opened);
+        let result = setup_pool(
+            pools,
+            pool_uuid,
+            &opened,
+            start_requested,
+            self.read_only_mode,
+        );
 
         match result {
             Ok((pool_name, pool)) => {
@@ -364,6 +700,11 @@ impl LiminalDevices {
                 self.errored_pool_devices.insert(pool_uuid, infos);
                 None
             }
+            Err(Destination::NotStarted(err)) => {
+                info!("Pool not set up: {}", err);
+                self.stopped_pool_devices.insert(pool_uuid, infos);
+                None
+            }
         }
     }
 
@@ -375,7 +716,7 @@ impl LiminalDevices {
     /// constructing the pool, retain the set of devices.
     pub fn block_evaluate(
         &mut self,
-        pools: &Table<PoolUuid, StratPool>,
+        pools: &mut Table<PoolUuid, StratPool>,
         event: &UdevEngineEvent,
     ) -> Option<(PoolUuid, Name, StratPool)> {
         let event_type = event.event_type();
@@ -384,19 +725,32 @@ impl LiminalDevices {
                 let stratis_identifiers = info.stratis_identifiers();
                 let pool_uuid = stratis_identifiers.pool_uuid;
                 let device_uuid = stratis_identifiers.device_uuid;
-                if let Some((_, pool)) = pools.get_by_uuid(pool_uuid) {
-                    if pool.get_strat_blockdev(device_uuid).is_none() {
-                        warn!("Found a device with {} that identifies itself as belonging to pool with UUID {}, but that pool is already up and running and does not appear to contain the device",
-                              info,
-                              pool_uuid);
+                if let Some((_, pool)) = pools.get_mut_by_uuid(pool_uuid) {
+                    match pool.get_mut_strat_blockdev(device_uuid) {
+                        Some((_, blockdev)) => update_physical_path(blockdev, &info),
+                        None => {
+                            if !pool.is_reserved_cachedev(device_uuid) {
+                                warn!("Found a device with {} that identifies itself as belonging to pool with UUID {}, but that pool is already up and running and does not appear to contain the device",
+                                      info,
+                                      pool_uuid);
+                            }
+                        }
                     }
-                    // FIXME: There might be something to check if the device is
-                    // included in the pool, but that is less clear.
                     None
                 } else if let Some(mut set) = self.hopeless_device_sets.remove(&pool_uuid) {
                     set.insert(info.into());
                     self.hopeless_device_sets.insert(pool_uuid, set);
                     None
+                } else if let Some(mut set) = self.stopped_pool_devices.remove(&pool_uuid) {
+                    match set.process_info_add(info) {
+                        Ok(()) => {
+                            self.stopped_pool_devices.insert(pool_uuid, set);
+                        }
+                        Err(hopeless) => {
+                            self.hopeless_device_sets.insert(pool_uuid, hopeless);
+                        }
+                    }
+                    None
                 } else {
                     let mut devices = self
                         .errored_pool_devices
@@ -417,7 +771,7 @@ impl LiminalDevices {
                     // leave a pool that could be set up in limbo forever. An
                     // alternative, where the user can explicitly ask to try to
                     // set up an incomplete pool would be a better choice.
-                    self.try_setup_pool(pools, pool_uuid, devices)
+                    self.try_setup_pool(pools, pool_uuid, devices, false)
                         .map(|(name, pool)| (pool_uuid, name, pool))
                 }
             })
@@ -437,6 +791,10 @@ impl LiminalDevices {
                     set.remove(&info.into());
                     self.hopeless_device_sets.insert(pool_uuid, set);
                     None
+                } else if let Some(mut set) = self.stopped_pool_devices.remove(&pool_uuid) {
+                    set.process_info_remove(info);
+                    self.stopped_pool_devices.insert(pool_uuid, set);
+                    None
                 } else {
                     let mut devices = self
                         .errored_pool_devices
@@ -445,7 +803,7 @@ impl LiminalDevices {
 
                     devices.process_info_remove(info);
 
-                    self.try_setup_pool(pools, pool_uuid, devices)
+                    self.try_setup_pool(pools, pool_uuid, devices, false)
                         .map(|(name, pool)| (pool_uuid, name, pool))
                 }
             })
@@ -479,6 +837,17 @@ impl<'a> Into<Value> for &'a LiminalDevices {
                         })
                     })
                     .collect()
+            ),
+            "stopped_pools": Value::Array(
+                self.stopped_pool_devices
+                    .iter()
+                    .map(|(uuid, map)| {
+                        json!({
+                            "pool_uuid": uuid.to_string(),
+                            "devices": <&DeviceSet as Into<Value>>::into(map),
+                        })
+                    })
+                    .collect(),
             )
         })
     }
@@ -8,4 +8,8 @@ mod identify;
 mod liminal;
 mod setup;
 
-pub use self::{identify::find_all, liminal::LiminalDevices};
+pub use self::{
+    identify::{find_all, LuksInfo, StratisInfo},
+    liminal::LiminalDevices,
+    setup::reload_metadata,
+};
@@ -8,6 +8,7 @@
 use std::{
     collections::{HashMap, HashSet},
     fs::OpenOptions,
+    path::Path,
 };
 
 use chrono::{DateTime, Utc};
@@ -37,45 +38,39 @@ use crate::{
 /// Postconditions: keys in result are equal to keys in infos OR an error
 /// is returned.
 pub fn get_bdas(infos: &HashMap<DevUuid, &LStratisInfo>) -> StratisResult<HashMap<DevUuid, BDA>> {
-    fn read_bda(info: &LStratisInfo) -> StratisResult<BDA> {
-        OpenOptions::new()
-            .read(true)
-            .open(&info.ids.devnode)
-            .map_err(StratisError::from)
-            .and_then(|mut f| {
-                let read_results = StaticHeader::read_sigblocks(&mut f);
-                let header = match StaticHeader::repair_sigblocks(
-                    &mut f,
-                    read_results,
-                    StaticHeader::write_header,
-                ) {
-                    Ok(Some(header)) => header,
-                    Ok(None) => {
-                        return Err(StratisError::Msg(format!(
-                            "Failed to find valid Stratis signature in header from device: {}",
-                            info.ids
-                        )))
-                    }
-                    Err(err) => return Err(err),
-                };
-
-                BDA::load(header, &mut f)
-            })
-            .and_then(|res| res.ok_or_else(|| StratisError::Msg("No BDA found".to_string())))
-            .map_err(|e| {
-                StratisError::Msg(format!(
-                    "Failed to read BDA from device {}: {}",
-                    info.ids, e
-                ))
-            })
-    }
-
     infos
         .iter()
-        .map(|(dev_uuid, info)| read_bda(info).map(|bda| (*dev_uuid, bda)))
+        .map(|(dev_uuid, info)| {
+            read_bda(&info.ids.devnode)
+                .map(|bda| (*dev_uuid, bda))
+                .map_err(|e| {
+                    StratisError::Msg(format!(
+                        "Failed to read BDA from device {}: {}",
+                        info.ids, e
+                    ))
+                })
+        })
         .collect()
 }
 
+/// Read the BDA from the device with the given device node, repairing its
+/// signature blocks if necessary.
+fn read_bda(devnode: &Path) -> StratisResult<BDA> {
+    let mut f = OpenOptions::new().read(true).open(devnode)?;
+    let read_results = StaticHeader::read_sigblocks(&mut f);
+    let header =
+        match StaticHeader::repair_sigblocks(&mut f, read_results, StaticHeader::write_header)? {
+            Some(header) => header,
+            None => {
+                return Err(StratisError::Msg(
+                    "Failed to find valid Stratis signature in header".to_string(),
+                ))
+            }
+        };
+
+    BDA::load(header, &mut f)?.ok_or_else(|| StratisError::Msg("No BDA found".to_string()))
+}
+
 /// Get the most recent metadata from a set of devices.
 /// Returns None if no metadata found for this pool on any device. This can
 /// happen if the pool was constructed but failed in the interval before the
@@ -88,6 +83,51 @@ pub fn get_metadata(
     infos: &HashMap<DevUuid, &LStratisInfo>,
     bdas: &HashMap<DevUuid, BDA>,
 ) -> StratisResult<Option<(DateTime<Utc>, PoolSave)>> {
+    latest_metadata(
+        &bdas
+            .iter()
+            .map(|(uuid, bda)| {
+                (
+                    infos
+                        .get(uuid)
+                        .expect("equal sets of UUID keys")
+                        .ids
+                        .devnode
+                        .as_path(),
+                    bda,
+                )
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Read the BDAs of the devices with the given device nodes afresh and get
+/// the most recent metadata from them, exactly as it is read when the pool
+/// is set up. See get_metadata().
+pub fn reload_metadata(devnodes: &[&Path]) -> StratisResult<Option<(DateTime<Utc>, PoolSave)>> {
+    let bdas = devnodes
+        .iter()
+        .map(|devnode| {
+            read_bda(devnode).map(|bda| (*devnode, bda)).map_err(|e| {
+                StratisError::Msg(format!(
+                    "Failed to read BDA from device {}: {}",
+                    devnode.display(),
+                    e
+                ))
+            })
+        })
+        .collect::<StratisResult<Vec<_>>>()?;
+    latest_metadata(
+        &bdas
+            .iter()
+            .map(|(devnode, bda)| (*devnode, bda))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Get the most recent metadata from the BDAs of a set of devices, each
+/// given with its device node. See get_metadata().
+fn latest_metadata(bdas: &[(&Path, &BDA)]) -> StratisResult<Option<(DateTime<Utc>, PoolSave)>> {
     // Most recent time should never be None if this was a properly
     // created pool; this allows for the method to be called in other
     // circumstances.
@@ -106,17 +146,11 @@ pub fn get_metadata(
     // recent metadata. In the event of errors, continue to try until all are
     // exhausted.
     bdas.iter()
-        .filter_map(|(uuid, bda)| {
+        .filter_map(|(devnode, bda)| {
             if bda.last_update_time() == Some(most_recent_time) {
                 OpenOptions::new()
                     .read(true)
-                    .open(
-                        &infos
-                            .get(uuid)
-                            .expect("equal sets of UUID keys")
-                            .ids
-                            .devnode,
-                    )
+                    .open(devnode)
                     .ok()
                     .and_then(|mut f| bda.load_state(&mut f).unwrap_or(None))
                     .and_then(|data| serde_json::from_slice(&data).ok())
@@ -137,8 +171,9 @@ pub fn get_metadata(
 /// the given devices. Sort the blockdevs in the order in which they were
 /// recorded in the metadata.
 /// Returns an error if the blockdevs obtained do not match the metadata.
-/// Returns a tuple, of which the first are the data devs, and the second
-/// are the devs that support the cache tier.
+/// Returns a tuple, of which the first are the data devs, the second
+/// are the devs that support the cache tier, and the third are the devs
+/// that are reserved for a cache that has not yet been initialized.
 /// Precondition: Every device in infos has already been determined to
 /// belong to one pool; all BDAs agree on their pool UUID, set of keys in
 /// infos and bdas are identical.
@@ -146,7 +181,7 @@ pub fn get_blockdevs(
     backstore_save: &BackstoreSave,
     infos: &HashMap<DevUuid, &LStratisInfo>,
     mut bdas: HashMap<DevUuid, BDA>,
-) -> StratisResult<(Vec<StratBlockDev>, Vec<StratBlockDev>)> {
+) -> StratisResult<(Vec<StratBlockDev>, Vec<StratBlockDev>, Vec<StratBlockDev>)> {
     let recorded_data_map: HashMap<DevUuid, (usize, &BaseBlockDevSave)> = backstore_save
         .data_tier
         .blockdev
@@ -168,6 +203,16 @@ pub fn get_blockdevs(
             None => HashMap::new(),
         };
 
+    let recorded_reserved_map: HashMap<DevUuid, (usize, &BaseBlockDevSave)> =
+        match backstore_save.reserved_cache {
+            Some(ref reserved_cache) => reserved_cache
+                .iter()
+                .enumerate()
+                .map(|(i, bds)| (bds.uuid, (i, bds)))
+                .collect(),
+            None => HashMap::new(),
+        };
+
     let mut segment_table: HashMap<DevUuid, Vec<(Sectors, Sectors)>> = HashMap::new();
     for seg in &backstore_save.data_tier.blockdev.allocs[0] {
         segment_table
@@ -186,17 +231,19 @@ pub fn get_blockdevs(
     }
 
     // Construct a single StratBlockDev. Return the tier to which the
-    // blockdev has been found to belong. Returns an error if the block
-    // device has shrunk, no metadata can be found for the block device,
-    // or it is impossible to set up the device because the recorded
-    // allocation information is impossible.
+    // blockdev has been found to belong, or None if the blockdev is reserved
+    // for a future cache. Returns an error if the block device has shrunk,
+    // no metadata can be found for the block device, or it is impossible to
+    // set up the device because the recorded allocation information is
+    // impossible.
     fn get_blockdev(
         info: &LStratisInfo,
         bda: BDA,
         data_map: &HashMap<DevUuid, (usize, &BaseBlockDevSave)>,
         cache_map: &HashMap<DevUuid, (usize, &BaseBlockDevSave)>,
+        reserved_map: &HashMap<DevUuid, (usize, &BaseBlockDevSave)>,
         segment_table: &HashMap<DevUuid, Vec<(Sectors, Sectors)>>,
-    ) -> StratisResult<(BlockDevTier, StratBlockDev)> {
+    ) -> StratisResult<(Option<BlockDevTier>, StratBlockDev)> {
         // Return an error if apparent size of Stratis block device appears to
         // have decreased since metadata was recorded or if size of block
         // device could not be obtained.
@@ -221,15 +268,16 @@ pub fn get_blockdevs(
         let dev_uuid = bda.dev_uuid();
 
         // Locate the device in the metadata using its uuid. Return the device
-        // metadata and whether it was a cache or a datadev.
+        // metadata and whether it was a cache, a reserved, or a datadev.
         let (tier, &(_, bd_save)) = data_map
             .get(&dev_uuid)
-            .map(|bd_save| (BlockDevTier::Data, bd_save))
+            .map(|bd_save| (Some(BlockDevTier::Data), bd_save))
             .or_else(|| {
                 cache_map
                     .get(&dev_uuid)
-                    .map(|bd_save| (BlockDevTier::Cache, bd_save))
+                    .map(|bd_save| (Some(BlockDevTier::Cache), bd_save))
             })
+            .or_else(|| reserved_map.get(&dev_uuid).map(|bd_save| (None, bd_save)))
             .ok_or_else(|| {
                 let err_msg = format!(
                     "Stratis device with {} had no record in pool metadata",
@@ -267,7 +315,11 @@ pub fn get_blockdevs(
         ))
     }
 
-    let (mut datadevs, mut cachedevs): (Vec<StratBlockDev>, Vec<StratBlockDev>) = (vec![], vec![]);
+    let (mut datadevs, mut cachedevs, mut reserveddevs): (
+        Vec<StratBlockDev>,
+        Vec<StratBlockDev>,
+        Vec<StratBlockDev>,
+    ) = (vec![], vec![], vec![]);
     for (dev_uuid, info) in infos {
         get_blockdev(
             info,
@@ -275,12 +327,14 @@ pub fn get_blockdevs(
                 .expect("sets of keys in bdas and infos are identical"),
             &recorded_data_map,
             &recorded_cache_map,
+            &recorded_reserved_map,
             &segment_table,
         )
         .map(|(tier, blockdev)| {
             match tier {
-                BlockDevTier::Data => &mut datadevs,
-                BlockDevTier::Cache => &mut cachedevs,
+                Some(BlockDevTier::Data) => &mut datadevs,
+                Some(BlockDevTier::Cache) => &mut cachedevs,
+                None => &mut reserveddevs,
             }
             .push(blockdev)
         })?;
@@ -342,5 +396,13 @@ pub fn get_blockdevs(
         ))
     })?;
 
-    Ok((datadevs, cachedevs))
+    let reserveddevs =
+        check_and_sort_devs(reserveddevs, &recorded_reserved_map).map_err(|err| {
+            StratisError::Msg(format!(
+                "Devices reserved for the cache did not appear consistent with metadata: {}",
+                err
+            ))
+        })?;
+
+    Ok((datadevs, cachedevs, reserveddevs))
 }
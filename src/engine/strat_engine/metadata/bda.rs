@@ -6,6 +6,8 @@ use std::io::{Read, Seek};
 
 use chrono::{DateTime, Utc};
 
+use devicemapper::Sectors;
+
 use crate::{
     engine::{
         strat_engine::{
@@ -108,6 +110,16 @@ impl BDA {
             .load_state(STATIC_HEADER_SIZE.sectors().bytes(), &mut f)
     }
 
+    /// Read both copies of the latest metadata from the disk and compare
+    /// them. See MDARegions::verify_state().
+    pub fn verify_state<F>(&self, mut f: &mut F) -> StratisResult<Option<(Vec<u8>, Vec<String>)>>
+    where
+        F: Read + Seek,
+    {
+        self.regions
+            .verify_state(STATIC_HEADER_SIZE.sectors().bytes(), &mut f)
+    }
+
     /// The time when the most recent metadata was written to the BDA,
     /// if any.
     pub fn last_update_time(&self) -> Option<&DateTime<Utc>> {
@@ -134,6 +146,15 @@ impl BDA {
         self.header.bda_extended_size()
     }
 
+    /// The start and the length of the space that is reserved after the
+    /// BDA. Nothing is stored in it.
+    pub fn reserved_region(&self) -> (Sectors, Sectors) {
+        (
+            self.header.mda_size.bda_size().sectors(),
+            self.header.reserved_size.sectors(),
+        )
+    }
+
     /// The maximum size of variable length metadata that can be accommodated.
     pub fn max_data_size(&self) -> MDADataSize {
         self.regions.max_data_size()
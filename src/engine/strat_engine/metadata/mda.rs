@@ -14,15 +14,17 @@ use crc::crc32;
 use devicemapper::Bytes;
 
 use crate::{
-    engine::strat_engine::{
-        metadata::sizes::{mda_size, MDADataSize, MDARegionSize, MDASize},
-        writing::SyncAll,
+    engine::{
+        shared::STRAT_METADATA_VERSION,
+        strat_engine::{
+            metadata::sizes::{mda_size, MDADataSize, MDARegionSize, MDASize},
+            writing::SyncAll,
+        },
     },
     stratis::{StratisError, StratisResult},
 };
 
 const STRAT_REGION_HDR_VERSION: u8 = 1;
-const STRAT_METADATA_VERSION: u8 = 1;
 
 /// Manages the MDA regions which hold the variable length metadata.
 #[derive(Debug)]
@@ -231,6 +233,61 @@ impl MDARegions {
             .map(Some)
     }
 
+    /// Load both copies of the metadata in the newer MDA region and compare
+    /// them. Returns the metadata, if there is any, together with a
+    /// description of each problem found. Return an error if neither copy
+    /// can be read.
+    pub fn verify_state<F>(
+        &self,
+        header_size: Bytes,
+        f: &mut F,
+    ) -> StratisResult<Option<(Vec<u8>, Vec<String>)>>
+    where
+        F: Read + Seek,
+    {
+        let newer_region = self.newer();
+        let mda = match self.mda_headers[newer_region] {
+            None => return Ok(None),
+            Some(ref mda) => mda,
+        };
+        let region_size = self.region_size.sectors().bytes();
+
+        let mut load_region = |index: usize| -> StratisResult<Vec<u8>> {
+            let offset = MDARegions::mda_offset(header_size, index, region_size)
+                + mda_size::_MDA_REGION_HDR_SIZE as u128;
+            f.seek(SeekFrom::Start(convert_int!(offset, u128, u64)?))?;
+            mda.load_region(f)
+        };
+
+        let primary = load_region(newer_region);
+        let backup = load_region(newer_region + mda_size::NUM_PRIMARY_MDA_REGIONS);
+        match (primary, backup) {
+            (Ok(primary), Ok(backup)) => {
+                let problems = if primary == backup {
+                    Vec::new()
+                } else {
+                    vec!["The primary and backup copies of the metadata differ".to_string()]
+                };
+                Ok(Some((primary, problems)))
+            }
+            (Ok(primary), Err(err)) => Ok(Some((
+                primary,
+                vec![format!(
+                    "The backup copy of the metadata could not be read: {}",
+                    err
+                )],
+            ))),
+            (Err(err), Ok(backup)) => Ok(Some((
+                backup,
+                vec![format!(
+                    "The primary copy of the metadata could not be read: {}",
+                    err
+                )],
+            ))),
+            (Err(err), Err(_)) => Err(err),
+        }
+    }
+
     /// The index of the older region, or 0 if there is a tie.
     fn older(&self) -> usize {
         match (&self.mda_headers[0], &self.mda_headers[1]) {
@@ -461,6 +518,41 @@ mod tests {
         assert_matches!(regions.last_update_time(), None);
     }
 
+    #[test]
+    /// Verify that a corrupted backup copy of the metadata is reported,
+    /// and that the primary copy is still returned.
+    fn test_verify_mda_regions() {
+        let offset = Bytes(100);
+        let buf_length = convert_test!(
+            *(offset + MDASize::default().sectors().bytes()),
+            u128,
+            usize
+        );
+        let mut buf = Cursor::new(vec![0; buf_length]);
+        let mut regions = MDARegions::new(MDASize::default());
+        regions.initialize(offset, &mut buf).unwrap();
+        assert_matches!(regions.verify_state(offset, &mut buf), Ok(None));
+
+        let data = b"metadata";
+        regions
+            .save_state(offset, &Utc::now(), data, &mut buf)
+            .unwrap();
+        let (loaded, problems) = regions.verify_state(offset, &mut buf).unwrap().unwrap();
+        assert_eq!(loaded, data);
+        assert!(problems.is_empty());
+
+        let region_size = regions.region_size.sectors().bytes();
+        let backup_data_offset = MDARegions::mda_offset(
+            offset,
+            regions.newer() + mda_size::NUM_PRIMARY_MDA_REGIONS,
+            region_size,
+        ) + mda_size::_MDA_REGION_HDR_SIZE as u128;
+        buf.get_mut()[convert_test!(backup_data_offset, u128, usize)] ^= 0xff;
+        let (loaded, problems) = regions.verify_state(offset, &mut buf).unwrap().unwrap();
+        assert_eq!(loaded, data);
+        assert_eq!(problems.len(), 1);
+    }
+
     proptest! {
         #[test]
         /// Using an arbitrary data buffer, construct an mda header buffer
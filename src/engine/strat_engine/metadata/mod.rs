@@ -19,6 +19,7 @@ pub use self::{
     bda::BDA,
     sizes::{BDAExtendedSize, BlockdevSize, MDADataSize},
     static_header::{
-        device_identifiers, disown_device, StaticHeader, StaticHeaderResult, StratisIdentifiers,
+        bda_extended_size, device_identifiers, disown_device, read_device_identifiers,
+        StaticHeader, StaticHeaderResult, StratisIdentifiers,
     },
 };
@@ -107,6 +107,26 @@ where
         .map(|sh| sh.map(|sh| sh.identifiers))
 }
 
+/// Like device_identifiers, but never write to the device, so that it may
+/// be opened read-only. A damaged signature block is not repaired.
+pub fn read_device_identifiers<F>(f: &mut F) -> StratisResult<Option<StratisIdentifiers>>
+where
+    F: Read + Seek + SyncAll,
+{
+    let read_results = StaticHeader::read_sigblocks(f);
+    StaticHeader::repair_sigblocks(f, read_results, StaticHeader::do_nothing)
+        .map(|sh| sh.map(|sh| sh.identifiers))
+}
+
+/// The space at the start of a device that is taken by the BDA and the
+/// reserved space after it, when the device is initialized with an MDA
+/// of the given size.
+pub fn bda_extended_size(mda_data_size: MDADataSize) -> BDAExtendedSize {
+    BDAExtendedSize::new(
+        mda_data_size.region_size().mda_size().bda_size().sectors() + RESERVED_SECTORS,
+    )
+}
+
 /// Remove Stratis identifying information from device.
 pub fn disown_device<F>(f: &mut F) -> StratisResult<()>
 where
@@ -20,9 +20,17 @@ mod writing;
 
 pub use self::{
     backstore::{crypt_metadata_size, CLEVIS_TANG_TRUST_URL},
+    cmd::{
+        command_sandbox, set_command_sandbox, set_udev_settle_policy, udev_settle_policy,
+        CommandSandbox, UdevSettlePolicy,
+    },
     device::blkdev_size,
-    dm::{get_dm, get_dm_init},
-    engine::StratEngine,
+    devlinks::set_dev_path,
+    dm::{get_dm, get_dm_init, set_dm_retry_policy, DmRetryPolicy},
+    engine::{
+        set_startup_device_wait, set_startup_key_policy, set_startup_read_only_mode,
+        startup_progress_so_far, StartupKeyPolicy, StratEngine,
+    },
     keys::StratKeyActions,
     metadata::{StaticHeader, StaticHeaderResult, BDA},
 };
@@ -9,7 +9,7 @@ use std::{
     fmt::{self, Display},
 };
 
-use devicemapper::{DmNameBuf, DmUuidBuf};
+use devicemapper::{DmName, DmNameBuf, DmUuidBuf};
 
 pub use crate::engine::types::KeyDescription;
 use crate::{
@@ -53,6 +53,66 @@ impl KeyDescription {
     }
 }
 
+/// Whether the devicemapper name is one of the names given by stratisd to
+/// the devices it manages.
+pub fn is_stratis_dm_name(name: &DmName) -> bool {
+    name.to_string()
+        .starts_with(&format!("stratis-{}-", FORMAT_VERSION))
+}
+
+/// If the devicemapper name is the name of one of the devices that stratisd
+/// manages for the pool with UUID old_uuid, return the name that the device
+/// has if the pool's UUID is new_uuid. The names of the crypt devices embed
+/// the device UUIDs rather than the pool UUID, so that None is returned for
+/// them.
+pub fn pool_dm_name_for_uuid(
+    name: &DmName,
+    old_uuid: PoolUuid,
+    new_uuid: PoolUuid,
+) -> Option<DmNameBuf> {
+    let prefixes = |uuid: PoolUuid| {
+        vec![
+            format!(
+                "stratis-{}-private-{}-",
+                FORMAT_VERSION,
+                uuid_to_string!(uuid)
+            ),
+            format!("stratis-{}-{}-thin-", FORMAT_VERSION, uuid_to_string!(uuid)),
+        ]
+    };
+    let name = name.to_string();
+    prefixes(old_uuid)
+        .into_iter()
+        .zip(prefixes(new_uuid))
+        .find_map(|(old_prefix, new_prefix)| {
+            name.strip_prefix(old_prefix.as_str()).map(|rest| {
+                DmNameBuf::new(format!("{}{}", new_prefix, rest))
+                    .expect("same length as the name for the old UUID")
+            })
+        })
+}
+
+/// The level of a device that stratisd manages for a pool in the stack of
+/// devices, counting from 0 for the thin devices at the top. Devices must
+/// be suspended or removed from the top down, and resumed or created from
+/// the bottom up.
+pub fn pool_dm_stack_level(name: &DmName) -> u8 {
+    let name = name.to_string();
+    if name.ends_with(&format!("-physical-{}", CacheRole::StripeSub)) {
+        5
+    } else if name.ends_with(&format!("-physical-{}", CacheRole::Cache)) {
+        3
+    } else if name.contains("-physical-") {
+        4
+    } else if name.contains("-flex-") {
+        2
+    } else if name.contains("-thinpool-") {
+        1
+    } else {
+        0
+    }
+}
+
 /// Get a devicemapper name from the device UUID.
 ///
 /// Prerequisite: len(format!("{}", FORMAT_VERSION)
@@ -72,7 +132,7 @@ pub fn format_crypt_name(dev_uuid: &DevUuid) -> String {
     )
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum FlexRole {
     MetadataVolume,
     ThinData,
@@ -128,6 +188,9 @@ pub enum CacheRole {
     MetaSub,
     /// The origin sub-device of the DM cache device, holds the actual data.
     OriginSub,
+    /// The striped device below the origin sub-device, if the data tier is
+    /// striped.
+    StripeSub,
 }
 
 impl Display for CacheRole {
@@ -137,6 +200,7 @@ impl Display for CacheRole {
             CacheRole::CacheSub => write!(f, "cachesub"),
             CacheRole::MetaSub => write!(f, "metasub"),
             CacheRole::OriginSub => write!(f, "originsub"),
+            CacheRole::StripeSub => write!(f, "stripesub"),
         }
     }
 }
@@ -261,4 +325,59 @@ mod tests {
             Some(KeyDescription::try_from("stratis-1-key".to_string()).expect("no semi-colons"))
         );
     }
+
+    #[test]
+    fn test_is_stratis_dm_name() {
+        let (name, _) = format_thin_ids(
+            PoolUuid::new_v4(),
+            ThinRole::Filesystem(FilesystemUuid::new_v4()),
+        );
+        assert!(is_stratis_dm_name(&name));
+        assert!(!is_stratis_dm_name(
+            &DmNameBuf::new("luks-stratis-1".to_string()).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_pool_dm_name_for_uuid() {
+        let old_uuid = PoolUuid::new_v4();
+        let new_uuid = PoolUuid::new_v4();
+        let role = ThinRole::Filesystem(FilesystemUuid::new_v4());
+
+        let (old_thin, _) = format_thin_ids(old_uuid, role);
+        let (new_thin, _) = format_thin_ids(new_uuid, role);
+        assert_eq!(
+            pool_dm_name_for_uuid(&old_thin, old_uuid, new_uuid),
+            Some(new_thin)
+        );
+
+        let (old_flex, _) = format_flex_ids(old_uuid, FlexRole::ThinData);
+        let (new_flex, _) = format_flex_ids(new_uuid, FlexRole::ThinData);
+        assert_eq!(
+            pool_dm_name_for_uuid(&old_flex, old_uuid, new_uuid),
+            Some(new_flex)
+        );
+
+        let (other, _) = format_backstore_ids(PoolUuid::new_v4(), CacheRole::Cache);
+        assert_eq!(pool_dm_name_for_uuid(&other, old_uuid, new_uuid), None);
+        let crypt = DmNameBuf::new(format_crypt_name(&DevUuid::new_v4())).unwrap();
+        assert_eq!(pool_dm_name_for_uuid(&crypt, old_uuid, new_uuid), None);
+    }
+
+    #[test]
+    fn test_pool_dm_stack_level() {
+        let uuid = PoolUuid::new_v4();
+        let levels = [
+            format_thin_ids(uuid, ThinRole::Filesystem(FilesystemUuid::new_v4())).0,
+            format_thinpool_ids(uuid, ThinPoolRole::Pool).0,
+            format_flex_ids(uuid, FlexRole::ThinMeta).0,
+            format_backstore_ids(uuid, CacheRole::Cache).0,
+            format_backstore_ids(uuid, CacheRole::OriginSub).0,
+            format_backstore_ids(uuid, CacheRole::StripeSub).0,
+        ]
+        .iter()
+        .map(|name| pool_dm_stack_level(name))
+        .collect::<Vec<_>>();
+        assert_eq!(levels, vec![0, 1, 2, 3, 4, 5]);
+    }
 }
@@ -2,35 +2,64 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use std::{borrow::Cow, path::Path, vec::Vec};
+use std::{
+    borrow::Cow,
+    cmp::min,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fmt::Debug,
+    fs::{File, OpenOptions},
+    mem,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+    vec::Vec,
+};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use serde_json::{Map, Value};
 
-use devicemapper::{Bytes, DmNameBuf, Sectors};
+use devicemapper::{Bytes, DmNameBuf, MetaBlocks, Sectors};
 
 use crate::{
     engine::{
         engine::{BlockDev, Filesystem, Pool},
         shared::{
-            init_cache_idempotent_or_err, validate_filesystem_size_specs, validate_name,
-            validate_paths,
+            filesystem_size_limits, init_cache_idempotent_or_err, latency_stats,
+            recommended_max_filesystems, supported_operations, validate_alert_thresholds,
+            validate_auto_prune_threshold, validate_cache_block_size,
+            validate_filesystem_size_specs, validate_filesystem_target_size, validate_name,
+            validate_paths, validate_xfs_params, CapacityHistory, DEFAULT_CACHE_BLOCK_SIZE,
+            LATENCY_HISTOGRAM_BOUNDARIES,
         },
         strat_engine::{
-            backstore::{Backstore, StratBlockDev},
-            metadata::MDADataSize,
+            backstore::{validate_devices, Backstore, StratBlockDev},
+            device::{blkdev_size, physical_disks},
+            dm::{check_kernel_feature, resume_suspended_pool_devices},
+            liminal::reload_metadata,
+            metadata::{bda_extended_size, MDADataSize},
             serde_structs::{FlexDevsSave, PoolSave, Recordable},
             thinpool::{ThinPool, ThinPoolSizeParams, DATA_BLOCK_SIZE},
         },
         types::{
-            BlockDevTier, Clevis, CreateAction, DeleteAction, DevUuid, EncryptionInfo,
-            FilesystemUuid, Key, KeyDescription, Name, PoolUuid, Redundancy, RegenAction,
-            RenameAction, SetCreateAction, SetDeleteAction,
+            ActiveAlert, AlertMetric, AlertThresholds, AllocationStrategy, BlockDevTier,
+            CapacityEstimate, CapacitySample, Clevis, CreateAction, DeleteAction,
+            DeletedFilesystem, DevUuid, DeviceVerdict, EncryptionInfo, EngineAction,
+            FilesystemUuid, HealthCheck, KernelFeature, Key, KeyDescription, KeyslotUsage,
+            LatencyStats, MetadataCheck, MetadataWriteFailurePolicy, Name, OperationLogEntry,
+            PlannedAction, PoolCapability, PoolEvent, PoolOperation, PoolUuid, ReadWork,
+            Reconciliation, ReconfigurationChanges, Redundancy, RedundancyWarning, RegenAction,
+            RenameAction, SetCreateAction, SetDeleteAction, SmallDevicePolicy, StripeConfig,
+            SurfaceTestResult, ThinPoolLimits, TierStructure, XfsParams,
         },
     },
     stratis::{StratisError, StratisResult},
 };
 
+/// The number of times a failed metadata write is retried before the pool
+/// is marked read-only under MetadataWriteFailurePolicy::RetryThenReadOnly.
+const METADATA_WRITE_RETRIES: u64 = 3;
+
 /// Get the index which indicates the start of unallocated space in the cap
 /// device.
 /// NOTE: Since segments are always allocated to each flex dev in order, the
@@ -65,6 +94,21 @@ fn next_index(flex_devs: &FlexDevsSave) -> Sectors {
     .expect("iterator is non-empty")
 }
 
+/// Set the value held in memory to the value recorded on disk if they
+/// differ, and record the difference in corrected.
+pub(super) fn adopt<T>(what: &str, memory: &mut T, disk: T, corrected: &mut Vec<String>)
+where
+    T: Debug + PartialEq,
+{
+    if *memory != disk {
+        corrected.push(format!(
+            "{}: {:?} in memory, {:?} on disk",
+            what, memory, disk
+        ));
+        *memory = disk;
+    }
+}
+
 /// Check the metadata of an individual pool for consistency.
 /// Precondition: This method is called only when setting up a pool, which
 /// ensures that the flex devs metadata lists are all non-empty.
@@ -131,11 +175,34 @@ fn check_metadata(metadata: &PoolSave) -> StratisResult<()> {
     Ok(())
 }
 
+/// Format a list of device paths for the operation log.
+fn paths_to_string(paths: &[&Path]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 #[derive(Debug)]
 pub struct StratPool {
     backstore: Backstore,
     redundancy: Redundancy,
     thin_pool: ThinPool,
+    metadata_write_failure_policy: MetadataWriteFailurePolicy,
+    small_device_policy: SmallDevicePolicy,
+    auto_start: bool,
+    start_priority: Option<u32>,
+    read_only: bool,
+    events: Vec<PoolEvent>,
+    capacity_history: CapacityHistory,
+    alert_thresholds: AlertThresholds,
+    active_alerts: HashMap<AlertMetric, ActiveAlert>,
+    metadata_undersized: Option<(MetaBlocks, MetaBlocks)>,
+    auto_prune_snapshots_on_pressure: Option<u64>,
+    cow_alerts: HashSet<FilesystemUuid>,
+    sequence_number: u64,
+    moving_filesystems: HashSet<FilesystemUuid>,
 }
 
 impl StratPool {
@@ -148,14 +215,25 @@ impl StratPool {
         paths: &[&Path],
         redundancy: Redundancy,
         encryption_info: &EncryptionInfo,
+        stripe: Option<StripeConfig>,
     ) -> StratisResult<(PoolUuid, StratPool)> {
+        check_kernel_feature(KernelFeature::ThinProvisioning)?;
+        if encryption_info.is_encrypted() {
+            check_kernel_feature(KernelFeature::Encryption)?;
+        }
+
         let pool_uuid = PoolUuid::new_v4();
 
         // FIXME: Initializing with the minimum MDA size is not necessarily
         // enough. If there are enough devices specified, more space will be
         // required.
-        let mut backstore =
-            Backstore::initialize(pool_uuid, paths, MDADataSize::default(), encryption_info)?;
+        let mut backstore = Backstore::initialize(
+            pool_uuid,
+            paths,
+            MDADataSize::default(),
+            encryption_info,
+            stripe,
+        )?;
 
         let thinpool = ThinPool::new(
             pool_uuid,
@@ -178,7 +256,23 @@ impl StratPool {
             backstore,
             redundancy,
             thin_pool: thinpool,
+            metadata_write_failure_policy: MetadataWriteFailurePolicy::default(),
+            small_device_policy: SmallDevicePolicy::default(),
+            auto_start: true,
+            start_priority: None,
+            read_only: false,
+            events: Vec::new(),
+            capacity_history: CapacityHistory::default(),
+            alert_thresholds: AlertThresholds::default(),
+            active_alerts: HashMap::new(),
+            metadata_undersized: None,
+            auto_prune_snapshots_on_pressure: None,
+            cow_alerts: HashSet::new(),
+            sequence_number: 0,
+            moving_filesystems: HashSet::new(),
         };
+        pool.record_capacity_sample();
+        pool.warn_shared_disks(pool_uuid);
 
         pool.write_metadata(&Name::new(name.to_owned()))?;
 
@@ -196,17 +290,45 @@ impl StratPool {
     ///   * key_description.is_none() -> no StratBlockDev in datadevs has a
     ///   key description.
     ///   * no StratBlockDev in cachdevs has a key description
+    ///
+    /// If read_only_mode is true, the thin pool and the filesystems are not
+    /// extended and the metadata is not written; that is left to the first
+    /// check of the pool after read-only mode is left.
     pub fn setup(
         uuid: PoolUuid,
         datadevs: Vec<StratBlockDev>,
         cachedevs: Vec<StratBlockDev>,
+        reserved_cachedevs: Vec<StratBlockDev>,
         timestamp: DateTime<Utc>,
         metadata: &PoolSave,
+        read_only_mode: bool,
     ) -> StratisResult<(Name, StratPool)> {
         check_metadata(metadata)?;
 
-        let mut backstore =
-            Backstore::setup(uuid, &metadata.backstore, datadevs, cachedevs, timestamp)?;
+        // Devices that a crash left suspended would block the setup.
+        match resume_suspended_pool_devices(uuid) {
+            Ok(resumed) => {
+                for name in resumed {
+                    warn!(
+                        "Resumed device {} of pool with UUID {}, which had been left suspended",
+                        name, uuid
+                    );
+                }
+            }
+            Err(err) => warn!(
+                "Failed to resume suspended devices of pool with UUID {}: {}",
+                uuid, err
+            ),
+        }
+
+        let mut backstore = Backstore::setup(
+            uuid,
+            &metadata.backstore,
+            datadevs,
+            cachedevs,
+            reserved_cachedevs,
+            timestamp,
+        )?;
         let pool_name = &metadata.name;
 
         let mut thinpool = ThinPool::setup(
@@ -216,16 +338,60 @@ impl StratPool {
             &metadata.flex_devs,
             &backstore,
         )?;
+        // Restore the grace period before the check, which would otherwise
+        // destroy all deleted filesystems right away.
+        thinpool.set_fs_deletion_grace_period(
+            uuid,
+            metadata.fs_deletion_grace_period.map(Duration::from_secs),
+        );
+
+        backstore.set_allocation_strategy(metadata.allocation_strategy.unwrap_or_default());
 
-        let changed = thinpool.check(uuid, &mut backstore)?;
+        let changed = if read_only_mode {
+            thinpool.refresh_status()?;
+            false
+        } else {
+            thinpool.check(uuid, &mut backstore)?
+        };
 
         let mut pool = StratPool {
             backstore,
             redundancy: Redundancy::NONE,
             thin_pool: thinpool,
+            metadata_write_failure_policy: metadata
+                .metadata_write_failure_policy
+                .unwrap_or_default(),
+            small_device_policy: metadata.small_device_policy.unwrap_or_default(),
+            auto_start: metadata.auto_start.unwrap_or(true),
+            start_priority: metadata.start_priority,
+            read_only: false,
+            events: Vec::new(),
+            capacity_history: CapacityHistory::default(),
+            alert_thresholds: metadata.alert_thresholds.unwrap_or_default(),
+            active_alerts: HashMap::new(),
+            metadata_undersized: None,
+            auto_prune_snapshots_on_pressure: metadata.auto_prune_snapshots_on_pressure,
+            cow_alerts: HashSet::new(),
+            sequence_number: metadata.sequence_number.unwrap_or(0),
+            moving_filesystems: HashSet::new(),
         };
+        pool.record_capacity_sample();
+        pool.check_alerts();
+        pool.check_metadata_size();
+        pool.warn_shared_disks(uuid);
+
+        if metadata.detailed_stats.unwrap_or(false) {
+            if let Err(err) = pool.thin_pool.set_detailed_stats(true) {
+                warn!(
+                    "Could not enable detailed statistics for pool with UUID {}: {}",
+                    uuid, err
+                );
+            }
+        }
 
-        if changed {
+        // Writing the metadata clears the mark of an exported pool, which
+        // is only set up on request.
+        if !read_only_mode && (changed || metadata.exported.is_some()) {
             pool.write_metadata(pool_name)?;
         }
 
@@ -235,27 +401,161 @@ impl StratPool {
     /// Send a synthetic udev change event to every filesystem on the given pool.
     pub fn udev_pool_change(&self, pool_name: &str) {
         for (name, uuid, fs) in self.thin_pool.filesystems() {
-            fs.udev_fs_change(pool_name, uuid, &name);
+            if !self.thin_pool.has_name_conflict(uuid) {
+                fs.udev_fs_change(pool_name, uuid, &name);
+            }
         }
     }
 
     /// Write current metadata to pool members.
+    /// If the write fails, the pool's metadata write failure policy is
+    /// applied and an event recording the failure is queued. If the write
+    /// succeeds, the pool is no longer read-only.
     pub fn write_metadata(&mut self, name: &str) -> StratisResult<()> {
         let data = serde_json::to_string(&self.record(name))?;
-        self.backstore.save_state(data.as_bytes())
+        let mut result = self.backstore.save_state(data.as_bytes());
+
+        let policy = self.metadata_write_failure_policy;
+        if policy == MetadataWriteFailurePolicy::RetryThenReadOnly {
+            let mut attempt = 0;
+            while result.is_err() && attempt < METADATA_WRITE_RETRIES {
+                attempt += 1;
+                warn!(
+                    "Writing metadata for pool {} failed; retrying ({}/{})",
+                    name, attempt, METADATA_WRITE_RETRIES
+                );
+                thread::sleep(Duration::from_millis(100 * attempt));
+                result = self.backstore.save_state(data.as_bytes());
+            }
+        }
+
+        if result.is_ok() && self.read_only {
+            self.read_only = false;
+            info!(
+                "Writing metadata for pool {} succeeded; pool is no longer read-only",
+                name
+            );
+        }
+
+        if let Err(ref err) = result {
+            if policy != MetadataWriteFailurePolicy::Alert {
+                self.read_only = true;
+            }
+            warn!(
+                "Writing metadata for pool {} failed: {}; applied policy {}{}",
+                name,
+                err,
+                policy,
+                if self.read_only {
+                    ", pool is now read-only"
+                } else {
+                    ""
+                }
+            );
+            self.events.push(PoolEvent::MetadataWriteFailed {
+                policy,
+                read_only: self.read_only,
+                error: err.to_string(),
+            });
+        }
+
+        result
     }
 
-    /// Teardown a pool.
+    /// The devnodes of the data tier blockdevs that hold each of the flex
+    /// devices, so that tests can inject failures into a particular one.
     #[cfg(test)]
+    pub fn flex_device_roles(
+        &self,
+    ) -> HashMap<crate::engine::strat_engine::names::FlexRole, Vec<PathBuf>> {
+        self.thin_pool
+            .flex_segments()
+            .into_iter()
+            .map(|(role, segments)| {
+                let devnodes = self
+                    .backstore
+                    .data_blockdevs_under(segments)
+                    .into_iter()
+                    .map(|uuid| {
+                        self.backstore
+                            .get_blockdev_by_uuid(uuid)
+                            .expect("segments are allocated from the pool's blockdevs")
+                            .1
+                            .devnode()
+                            .to_owned()
+                    })
+                    .collect();
+                (role, devnodes)
+            })
+            .collect()
+    }
+
+    /// Teardown a pool.
     pub fn teardown(&mut self) -> StratisResult<()> {
         self.thin_pool.teardown()?;
         self.backstore.teardown()
     }
 
+    /// Mark the pool as exported in its metadata, then tear down its
+    /// devicemapper devices and close the dm-crypt devices of its block
+    /// devices, leaving all data and metadata on them intact. No
+    /// filesystem of the pool may be mounted. If the teardown fails, the
+    /// mark is removed again.
+    pub fn export(&mut self, pool_name: &str) -> StratisResult<()> {
+        self.check_no_operation_in_progress("export the pool")?;
+        let mounted = self
+            .thin_pool
+            .filesystems()
+            .into_iter()
+            .filter(|(_, _, fs)| fs.is_mounted().unwrap_or(true))
+            .map(|(name, _, _)| name.to_string())
+            .collect::<Vec<_>>();
+        if !mounted.is_empty() {
+            return Err(StratisError::Msg(format!(
+                "Filesystems {} are mounted; they must be unmounted to export the pool",
+                mounted.join(", ")
+            )));
+        }
+
+        let mut record = self.record(pool_name);
+        record.exported = Some(true);
+        self.backstore
+            .save_state(serde_json::to_string(&record)?.as_bytes())?;
+
+        if let Err(err) = self.teardown().and_then(|_| {
+            self.backstore
+                .blockdevs()
+                .into_iter()
+                .try_for_each(|(_, _, bd)| bd.deactivate())
+        }) {
+            if let Err(e) = self.write_metadata(pool_name) {
+                warn!(
+                    "Failed to remove the export mark from the metadata of pool {}: {}",
+                    pool_name, e
+                );
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+
     pub fn has_filesystems(&self) -> bool {
         self.thin_pool.has_filesystems()
     }
 
+    /// Permanently destroy the deleted filesystems whose deletion grace
+    /// period is over. Nothing is destroyed while the pool is read-only or
+    /// an operation is in progress on it; that is left to a later call.
+    pub fn purge_deleted_filesystems(&mut self, pool_uuid: PoolUuid) {
+        if self.read_only || self.operation_in_progress().is_some() {
+            return;
+        }
+        for uuid in self.thin_pool.purge_deleted_filesystems(pool_uuid) {
+            self.thin_pool
+                .log_operation(&format!("purge deleted filesystem with UUID {}", uuid));
+        }
+    }
+
     /// The names of DM devices belonging to this pool that may generate events
     pub fn get_eventing_dev_names(&self, pool_uuid: PoolUuid) -> Vec<DmNameBuf> {
         self.thin_pool.get_eventing_dev_names(pool_uuid)
@@ -265,216 +565,454 @@ impl StratPool {
     // TODO: Just check the device that evented. Currently checks
     // everything.
     pub fn event_on(&mut self, pool_uuid: PoolUuid, pool_name: &Name) -> StratisResult<()> {
-        if self.thin_pool.check(pool_uuid, &mut self.backstore)? {
-            self.write_metadata(pool_name)?;
+        let operation = self.operation_in_progress();
+        let result = self
+            .thin_pool
+            .check(pool_uuid, &mut self.backstore)
+            .map(|changed| self.prune_snapshots_on_pressure(pool_uuid, pool_name) || changed)
+            .and_then(|changed| {
+                if changed {
+                    self.write_metadata(pool_name)
+                } else {
+                    Ok(())
+                }
+            });
+        self.record_capacity_sample();
+        self.check_alerts();
+        self.check_cow_alerts();
+        self.check_metadata_size();
+        if self.operation_in_progress() != operation {
+            self.events
+                .push(PoolEvent::OperationChanged(self.operation_in_progress()));
         }
-        Ok(())
+        result
     }
 
-    pub fn record(&self, name: &str) -> PoolSave {
-        PoolSave {
-            name: name.to_owned(),
-            backstore: self.backstore.record(),
-            flex_devs: self.thin_pool.record(),
-            thinpool_dev: self.thin_pool.record(),
+    /// Add a sample of the current used and total physical space of the
+    /// pool to its capacity history.
+    fn record_capacity_sample(&mut self) {
+        match self.total_physical_used() {
+            Ok(used) => {
+                let total = self.total_physical_size();
+                self.capacity_history.record(used, total);
+            }
+            Err(err) => {
+                warn!(
+                    "Could not sample the used space of the pool for its capacity history: {}",
+                    err
+                );
+            }
         }
     }
 
-    fn datadevs_encrypted(&self) -> bool {
-        self.backstore.data_tier_is_encrypted()
-    }
+    /// The current value of a metric for which an alert threshold can be
+    /// set, or None if it can not be determined.
+    fn alert_metric_value(&self, metric: AlertMetric) -> Option<u64> {
+        fn percentage(part: u64, whole: u64) -> Option<u64> {
+            if whole == 0 {
+                None
+            } else {
+                u64::try_from(u128::from(part) * 100 / u128::from(whole)).ok()
+            }
+        }
 
-    pub fn get_strat_blockdev(&self, uuid: DevUuid) -> Option<(BlockDevTier, &StratBlockDev)> {
-        self.backstore.get_blockdev_by_uuid(uuid)
+        let total = *self.total_physical_size();
+        match metric {
+            AlertMetric::PhysicalUsage => self
+                .total_physical_used()
+                .ok()
+                .and_then(|used| percentage(*used, total)),
+            AlertMetric::MetadataUsage => self
+                .thin_pool
+                .metadata_usage()
+                .and_then(|(used, total)| percentage(*used, *total)),
+            AlertMetric::OverprovisionRatio => percentage(
+                self.thin_pool
+                    .filesystems()
+                    .iter()
+                    .map(|(_, _, fs)| *fs.size())
+                    .sum(),
+                total,
+            ),
+            AlertMetric::Degraded => Some(u64::from(self.thin_pool.is_degraded())),
+        }
     }
 
-    pub fn get_mut_strat_blockdev(
-        &mut self,
-        uuid: DevUuid,
-    ) -> Option<(BlockDevTier, &mut StratBlockDev)> {
-        self.backstore.get_mut_blockdev_by_uuid(uuid)
+    /// Raise an alert for every metric which is at or above its threshold,
+    /// unless the alert has been acknowledged. An alert stops being active
+    /// once its metric falls below the threshold.
+    fn check_alerts(&mut self) {
+        for metric in AlertMetric::ALL.iter() {
+            let threshold = match self.alert_thresholds.get(*metric) {
+                Some(threshold) => threshold,
+                None => {
+                    self.active_alerts.remove(metric);
+                    continue;
+                }
+            };
+            match self.alert_metric_value(*metric) {
+                Some(value) if value >= threshold => {
+                    let alert = self.active_alerts.entry(*metric).or_insert_with(|| {
+                        warn!(
+                            "Pool metric {} is {}, which reaches its alert threshold of {}",
+                            metric, value, threshold
+                        );
+                        ActiveAlert {
+                            metric: *metric,
+                            value,
+                            threshold,
+                            acknowledged: false,
+                        }
+                    });
+                    alert.value = value;
+                    alert.threshold = threshold;
+                    if !alert.acknowledged {
+                        self.events.push(PoolEvent::Alert {
+                            metric: *metric,
+                            value,
+                            threshold,
+                        });
+                    }
+                }
+                Some(_) => {
+                    self.active_alerts.remove(metric);
+                }
+                None => (),
+            }
+        }
     }
 
-    /// Destroy the pool.
-    /// Precondition: All filesystems belonging to this pool must be
-    /// unmounted.
-    pub fn destroy(&mut self) -> StratisResult<()> {
-        self.thin_pool.teardown()?;
-        self.backstore.destroy()?;
-        Ok(())
-    }
-}
+    /// Raise an alert for every filesystem whose unique data, i.e. the data
+    /// it does not share with its origin or its snapshots, exceeds its
+    /// copy-on-write alert threshold, unless an alert has already been
+    /// raised for it. An alert may be raised again once the unique data has
+    /// fallen to the threshold or below. Finding the unique data requires
+    /// reading the thin pool metadata, so nothing is done unless some
+    /// filesystem has a threshold.
+    fn check_cow_alerts(&mut self) {
+        let watched = self
+            .thin_pool
+            .filesystems()
+            .into_iter()
+            .filter_map(|(name, uuid, fs)| {
+                fs.cow_alert_threshold()
+                    .map(|threshold| (name, uuid, threshold))
+            })
+            .collect::<Vec<_>>();
+        self.cow_alerts
+            .retain(|uuid| watched.iter().any(|(_, fs_uuid, _)| fs_uuid == uuid));
+        if watched.is_empty() {
+            return;
+        }
 
-impl<'a> Into<Value> for &'a StratPool {
-    // Precondition: (&ThinPool).into() pattern matches Value::Object(_)
-    // Precondition: (&Backstore).into() pattern matches Value::Object(_)
-    fn into(self) -> Value {
-        let mut map: Map<_, _> =
-            if let Value::Object(map) = <&ThinPool as Into<Value>>::into(&self.thin_pool) {
-                map.into_iter()
-            } else {
-                unreachable!("ThinPool conversion returns a JSON object")
+        let uuids = watched.iter().map(|(_, uuid, _)| *uuid).collect::<Vec<_>>();
+        let sizes = match self.thin_pool.filesystem_shared_sizes(&uuids) {
+            Ok(sizes) => sizes,
+            Err(err) => {
+                warn!(
+                    "Could not determine the unique data of the filesystems to check their copy-on-write alert thresholds: {}",
+                    err
+                );
+                return;
             }
-            .collect();
-        map.extend(
-            if let Value::Object(map) = <&Backstore as Into<Value>>::into(&self.backstore) {
-                map.into_iter()
-            } else {
-                unreachable!("Backstore conversion returns a JSON object")
-            },
-        );
-        Value::from(map)
-    }
-}
-
-impl Pool for StratPool {
-    fn init_cache(
-        &mut self,
-        pool_uuid: PoolUuid,
-        pool_name: &str,
-        blockdevs: &[&Path],
-    ) -> StratisResult<SetCreateAction<DevUuid>> {
-        validate_paths(blockdevs)?;
+        };
 
-        if self.is_encrypted() {
-            return Err(StratisError::Msg(
-                "Use of a cache is not supported with an encrypted pool".to_string(),
-            ));
+        for ((name, uuid, threshold), (_, unique)) in watched.into_iter().zip(sizes) {
+            let unique = unique.bytes();
+            if unique <= threshold {
+                self.cow_alerts.remove(&uuid);
+            } else if self.cow_alerts.insert(uuid) {
+                warn!(
+                    "Filesystem {} with UUID {} holds {} of unique data, which exceeds its copy-on-write alert threshold of {}",
+                    name, uuid, unique, threshold
+                );
+                self.events.push(PoolEvent::FilesystemCowAlert {
+                    uuid,
+                    name,
+                    unique,
+                    threshold,
+                });
+            }
         }
-        if !self.has_cache() {
-            if blockdevs.is_empty() {
-                return Err(StratisError::Msg(
-                    "At least one blockdev path is required to initialize a cache.".to_string(),
-                ));
+    }
+
+    /// The space that the filesystems, other than the one with the given
+    /// UUID, may still claim under their minimum free space guarantees.
+    fn unbacked_guarantees(&self, excluding: Option<FilesystemUuid>) -> StratisResult<Bytes> {
+        let mut guaranteed = Bytes(0);
+        for (_, uuid, fs) in self.thin_pool.filesystems() {
+            if Some(uuid) != excluding {
+                guaranteed += fs.unbacked_guarantee()?;
             }
+        }
+        Ok(guaranteed)
+    }
 
-            // If adding cache devices, must suspend the pool, since the cache
-            // must be augmented with the new devices.
-            self.thin_pool.suspend()?;
-            let devices_result = self.backstore.init_cache(pool_uuid, blockdevs);
-            self.thin_pool.resume()?;
-            let devices = devices_result?;
-            self.write_metadata(pool_name)?;
-            Ok(SetCreateAction::new(devices))
+    /// The amount by which the free physical space of the pool falls short
+    /// of the space that its filesystems may still claim under their
+    /// minimum free space guarantees. Returns 0 if there is no shortfall.
+    fn guarantee_shortfall(&self) -> StratisResult<Bytes> {
+        let guaranteed = self.unbacked_guarantees(None)?;
+        let free = self.free_physical_space()?;
+        Ok(if guaranteed > free {
+            guaranteed - free
         } else {
-            init_cache_idempotent_or_err(
-                blockdevs,
-                self.backstore
-                    .cachedevs()
-                    .into_iter()
-                    .map(|(_, bd)| bd.physical_path().to_owned()),
-            )
-        }
+            Bytes(0)
+        })
     }
 
-    fn bind_clevis(
-        &mut self,
-        pin: &str,
-        clevis_info: &Value,
-    ) -> StratisResult<CreateAction<Clevis>> {
-        let changed = self.backstore.bind_clevis(pin, clevis_info)?;
-        if changed {
-            Ok(CreateAction::Created(Clevis))
+    /// Return an error if the action, which lets the filesystems of the pool
+    /// claim more of its physical space, some of it because their minimum
+    /// free space guarantees grow by additional, can not be backed by the
+    /// pool: if it has no free space left, or none beyond what those
+    /// guarantees may still claim. Since the filesystems are thinly
+    /// provisioned, their total size may exceed the physical size of the
+    /// pool.
+    fn check_free_space_for(&self, action: &str, additional: Bytes) -> StratisResult<()> {
+        let free = self.free_physical_space()?;
+        if free == Bytes(0) {
+            return Err(StratisError::Msg(format!(
+                "Can not {}: the pool has no free space left",
+                action
+            )));
+        }
+        let guaranteed = self.unbacked_guarantees(None)? + additional;
+        if guaranteed > Bytes(0) && guaranteed >= free {
+            let shortfall = self.guarantee_shortfall()?;
+            Err(StratisError::Msg(format!(
+                "Can not {}: the pool has {} of free space, but the minimum free space guarantees of its filesystems may claim {}{}",
+                action,
+                free,
+                guaranteed,
+                if shortfall > Bytes(0) {
+                    format!(", which already exceeds it by {}", shortfall)
+                } else {
+                    String::new()
+                }
+            )))
         } else {
-            Ok(CreateAction::Identity)
+            Ok(())
         }
     }
 
-    fn unbind_clevis(&mut self) -> StratisResult<DeleteAction<Clevis>> {
-        let changed = self.backstore.unbind_clevis()?;
-        if changed {
-            Ok(DeleteAction::Deleted(Clevis))
+    /// The physical space of the pool that is not yet in use.
+    fn free_physical_space(&self) -> StratisResult<Bytes> {
+        let size = self.total_physical_size();
+        let used = self.total_physical_used()?;
+        Ok(if size > used {
+            (size - used).bytes()
         } else {
-            Ok(DeleteAction::Identity)
+            Bytes(0)
+        })
+    }
+
+    /// Destroy snapshots, oldest first, while the physical usage of the pool
+    /// is at or above the threshold of its automatic snapshot pruning
+    /// policy, or while its free space falls short of what the minimum free
+    /// space guarantees of its filesystems require. A snapshot that has a
+    /// guarantee of its own is never pruned, and one that can not be
+    /// destroyed, e.g. because it is mounted, is skipped. Returns true if
+    /// checking the thin pool after destroying a snapshot changed it, so
+    /// that the metadata must be written.
+    // FIXME: Filesystems can not yet be marked immutable, so no snapshot is
+    // protected from pruning other than by being in use or by having a
+    // guarantee.
+    fn prune_snapshots_on_pressure(&mut self, pool_uuid: PoolUuid, pool_name: &str) -> bool {
+        let threshold = match self.auto_prune_snapshots_on_pressure {
+            Some(threshold) => threshold,
+            None => return false,
+        };
+
+        let mut changed = false;
+        for (uuid, name) in self.thin_pool.snapshots_oldest_first() {
+            let (reason, short_reason) = match (
+                self.alert_metric_value(AlertMetric::PhysicalUsage),
+                self.guarantee_shortfall(),
+            ) {
+                (Some(usage), _) if usage >= threshold => (
+                    format!(
+                        "whose physical usage of {}% reached the threshold of {}%",
+                        usage, threshold
+                    ),
+                    format!("physical usage of {}%", usage),
+                ),
+                (_, Ok(shortfall)) if shortfall > Bytes(0) => (
+                    format!(
+                        "whose free space fell {} short of the minimum free space guaranteed to its filesystems",
+                        shortfall
+                    ),
+                    format!("free space shortfall of {}", shortfall),
+                ),
+                _ => break,
+            };
+            if self
+                .thin_pool
+                .get_filesystem_by_uuid(uuid)
+                .and_then(|(_, fs)| fs.min_free_guarantee())
+                .is_some()
+            {
+                continue;
+            }
+            if let Err(err) = self.thin_pool.destroy_filesystem(pool_name, uuid) {
+                warn!(
+                    "Failed to prune snapshot {} with UUID {} of pool {}: {}",
+                    name, uuid, pool_name, err
+                );
+                continue;
+            }
+            warn!(
+                "Pruned snapshot {} with UUID {} of pool {}, {}",
+                name, uuid, pool_name, reason
+            );
+            self.thin_pool.log_operation(&format!(
+                "prune snapshot {} with UUID {} at {}",
+                name, uuid, short_reason
+            ));
+            self.events.push(PoolEvent::SnapshotPruned { uuid, name });
+
+            // Refresh the status of the thin pool, on which the physical
+            // usage is based.
+            match self.thin_pool.check(pool_uuid, &mut self.backstore) {
+                Ok(check_changed) => changed |= check_changed,
+                Err(err) => {
+                    warn!(
+                        "Failed to check the thin pool of pool {} after pruning a snapshot: {}",
+                        pool_name, err
+                    );
+                    break;
+                }
+            }
         }
+        changed
     }
 
-    fn bind_keyring(
-        &mut self,
-        key_description: &KeyDescription,
-    ) -> StratisResult<CreateAction<Key>> {
-        let changed = self.backstore.bind_keyring(key_description)?;
-        if changed {
-            Ok(CreateAction::Created(Key))
-        } else {
-            Ok(CreateAction::Identity)
+    /// Check whether the thin pool metadata device is undersized for the
+    /// current size of the data tier. An event is raised when it becomes
+    /// undersized, or when the sizes change while it remains undersized.
+    fn check_metadata_size(&mut self) {
+        let undersized = self
+            .thin_pool
+            .metadata_undersized(self.backstore.datatier_usable_size());
+        if let Some((current, recommended)) = undersized {
+            if self.metadata_undersized != undersized {
+                warn!(
+                    "Thin pool metadata device of {} is smaller than the recommended size of {} for the data tier",
+                    current.sectors().bytes(),
+                    recommended.sectors().bytes()
+                );
+                self.events.push(PoolEvent::MetadataUndersized {
+                    current: current.sectors().bytes(),
+                    recommended: recommended.sectors().bytes(),
+                });
+            }
         }
+        self.metadata_undersized = undersized;
     }
 
-    fn unbind_keyring(&mut self) -> StratisResult<DeleteAction<Key>> {
-        let changed = self.backstore.unbind_keyring()?;
-        if changed {
-            Ok(DeleteAction::Deleted(Key))
-        } else {
-            Ok(DeleteAction::Identity)
+    pub fn record(&self, name: &str) -> PoolSave {
+        PoolSave {
+            name: name.to_owned(),
+            backstore: self.backstore.record(),
+            flex_devs: self.thin_pool.record(),
+            thinpool_dev: self.thin_pool.record(),
+            metadata_write_failure_policy: Some(self.metadata_write_failure_policy),
+            small_device_policy: Some(self.small_device_policy),
+            auto_start: Some(self.auto_start),
+            start_priority: self.start_priority,
+            detailed_stats: Some(self.thin_pool.detailed_stats()),
+            alert_thresholds: Some(self.alert_thresholds),
+            fs_deletion_grace_period: self
+                .thin_pool
+                .fs_deletion_grace_period()
+                .map(|period| period.as_secs()),
+            auto_prune_snapshots_on_pressure: self.auto_prune_snapshots_on_pressure,
+            allocation_strategy: Some(self.backstore.allocation_strategy()),
+            exported: None,
+            sequence_number: Some(self.sequence_number).filter(|n| *n != 0),
         }
     }
 
-    fn rebind_keyring(
+    /// Set the sequence number of the pool and write it to the metadata.
+    /// This is done once, when the pool is created.
+    pub fn set_sequence_number(
         &mut self,
-        new_key_desc: &KeyDescription,
-    ) -> StratisResult<RenameAction<Key>> {
-        match self.backstore.rebind_keyring(new_key_desc)? {
-            Some(true) => Ok(RenameAction::Renamed(Key)),
-            Some(false) => Ok(RenameAction::Identity),
-            None => Ok(RenameAction::NoSource),
-        }
+        pool_name: &Name,
+        sequence_number: u64,
+    ) -> StratisResult<()> {
+        self.sequence_number = sequence_number;
+        self.write_metadata(pool_name)
     }
 
-    fn rebind_clevis(&mut self) -> StratisResult<RegenAction> {
-        self.backstore.rebind_clevis().map(|_| RegenAction)
+    fn datadevs_encrypted(&self) -> bool {
+        self.backstore.data_tier_is_encrypted()
     }
 
-    fn create_filesystems<'a, 'b>(
-        &'a mut self,
+    /// Check the data devices to be added against the smallest existing
+    /// data device and act according to the pool's small device policy.
+    /// Returns an error if any of the new devices is smaller and the policy
+    /// forbids adding it.
+    fn check_small_datadevs(
+        &self,
         pool_name: &str,
-        pool_uuid: PoolUuid,
-        specs: &[(&'b str, Option<Bytes>)],
-    ) -> StratisResult<SetCreateAction<(&'b str, FilesystemUuid, Sectors)>> {
-        let spec_map = validate_filesystem_size_specs(specs)?;
+        paths: &[&Path],
+        size_override: bool,
+    ) -> StratisResult<()> {
+        let smallest = match self
+            .backstore
+            .datadevs()
+            .iter()
+            .map(|(_, bd)| bd.total_size().sectors().bytes())
+            .min()
+        {
+            Some(smallest) => smallest,
+            None => return Ok(()),
+        };
 
-        spec_map.iter().fold(Ok(()), |res, (name, size)| {
-            res.and_then(|()| validate_name(name))
-                .and_then(|()| {
-                    if let Some((_, fs)) = self.thin_pool.get_filesystem_by_name(name) {
-                        if fs.thindev_size() == *size {
-                            Ok(())
-                        } else {
-                            Err(StratisError::Msg(format!(
-                                "Size {} of filesystem {} to be created conflicts with size {} for existing filesystem",
-                                size,
-                                name,
-                                fs.thindev_size()
-                            )))
-                        }
-                    } else {
-                        Ok(())
-                    }
-                })
-        })?;
-
-        // TODO: Roll back on filesystem initialization failure.
-        let mut result = Vec::new();
-        for (name, size) in spec_map {
-            if self.thin_pool.get_mut_filesystem_by_name(name).is_none() {
-                let fs_uuid = self
-                    .thin_pool
-                    .create_filesystem(pool_name, pool_uuid, name, size)?;
-                result.push((name, fs_uuid, size));
+        let mut small = Vec::new();
+        for path in paths {
+            let size = blkdev_size(&File::open(path)?)?;
+            if size < smallest {
+                small.push(format!("{} ({})", path.display(), size));
             }
         }
+        if small.is_empty() {
+            return Ok(());
+        }
 
-        Ok(SetCreateAction::new(result))
+        let msg = format!(
+            "Devices {} are smaller than the smallest data device ({}) of pool {}",
+            small.join(", "),
+            smallest,
+            pool_name
+        );
+        match self.small_device_policy {
+            SmallDevicePolicy::Reject => Err(StratisError::Msg(msg)),
+            SmallDevicePolicy::RequireOverride if !size_override => Err(StratisError::Msg(
+                format!("{}; adding them requires an override", msg),
+            )),
+            _ => {
+                warn!(
+                    "{}; some of the space on the larger devices may be wasted",
+                    msg
+                );
+                Ok(())
+            }
+        }
     }
 
-    fn add_blockdevs(
+    /// Add blockdevs to the pool. If size_override is set, data devices that
+    /// are smaller than the existing ones are added even if the small
+    /// device policy requires an override.
+    fn add_blockdevs_checked(
         &mut self,
         pool_uuid: PoolUuid,
         pool_name: &str,
         paths: &[&Path],
         tier: BlockDevTier,
+        size_override: bool,
     ) -> StratisResult<SetCreateAction<DevUuid>> {
         validate_paths(paths)?;
+        self.check_no_operation_in_progress("add block devices")?;
 
         let bdev_info = if tier == BlockDevTier::Cache && !self.has_cache() {
             return Err(StratisError::Msg(
@@ -506,6 +1044,8 @@ impl Pool for StratPool {
             let bdev_info = bdev_info_res?;
             Ok(SetCreateAction::new(bdev_info))
         } else {
+            self.check_small_datadevs(pool_name, paths, size_override)?;
+
             // If just adding data devices, no need to suspend the pool.
             // No action will be taken on the DM devices.
             let bdev_info = self.backstore.add_datadevs(pool_uuid, paths)?;
@@ -520,211 +1060,2735 @@ impl Pool for StratPool {
             Ok(SetCreateAction::new(bdev_info))
         };
         self.write_metadata(pool_name)?;
+        if bdev_info
+            .as_ref()
+            .map_or(false, |action| action.is_changed())
+        {
+            self.thin_pool.log_operation(&format!(
+                "add {} devices {}",
+                match tier {
+                    BlockDevTier::Data => "data",
+                    BlockDevTier::Cache => "cache",
+                },
+                paths_to_string(paths)
+            ));
+            self.warn_shared_disks(pool_uuid);
+        }
         bdev_info
     }
 
-    fn destroy_filesystems<'a>(
-        &'a mut self,
-        pool_name: &str,
-        fs_uuids: &[FilesystemUuid],
-    ) -> StratisResult<SetDeleteAction<FilesystemUuid>> {
-        let mut removed = Vec::new();
-        for &uuid in fs_uuids {
-            if let Some(uuid) = self.thin_pool.destroy_filesystem(pool_name, uuid)? {
-                removed.push(uuid);
-            }
+    /// Log a warning for each physical disk which backs more than one block
+    /// device of the pool.
+    fn warn_shared_disks(&self, pool_uuid: PoolUuid) {
+        for warning in self.redundancy_warnings() {
+            warn!(
+                "Block devices {} of pool with UUID {} are all backed by the physical disk {}; the failure of that disk would affect all of them",
+                warning
+                    .dev_uuids
+                    .iter()
+                    .map(|uuid| uuid.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                pool_uuid,
+                warning.disk
+            );
+        }
+    }
+
+    /// Return an error if the pool has been marked read-only or if a
+    /// long-running operation is in progress on this pool, since the
+    /// requested action would conflict with it.
+    fn check_no_operation_in_progress(&self, action: &str) -> StratisResult<()> {
+        if self.read_only {
+            return Err(StratisError::Msg(format!(
+                "Can not {} since the pool has been marked read-only after a metadata write failure",
+                action
+            )));
         }
+        match self.operation_in_progress() {
+            Some(operation) => Err(StratisError::Msg(format!(
+                "Can not {} while operation {} is in progress on the pool",
+                action, operation
+            ))),
+            None => Ok(()),
+        }
+    }
 
-        Ok(SetDeleteAction::new(removed))
+    /// Return an error if the filesystem with the given UUID is being moved
+    /// to another pool, since the requested action would conflict with the
+    /// copy.
+    fn check_filesystem_not_moving(&self, uuid: FilesystemUuid, action: &str) -> StratisResult<()> {
+        if self.moving_filesystems.contains(&uuid) {
+            return Err(StratisError::Msg(format!(
+                "Can not {} with UUID {} while it is being moved to another pool",
+                action, uuid
+            )));
+        }
+        Ok(())
     }
 
-    fn rename_filesystem(
+    /// Create filesystems according to specs, passing mkfs_params, if
+    /// specified, to mkfs.xfs.
+    fn create_filesystems_with_params<'b>(
         &mut self,
         pool_name: &str,
-        uuid: FilesystemUuid,
-        new_name: &str,
-    ) -> StratisResult<RenameAction<FilesystemUuid>> {
-        validate_name(new_name)?;
-        match self
-            .thin_pool
-            .rename_filesystem(pool_name, uuid, new_name)?
+        pool_uuid: PoolUuid,
+        specs: &[(&'b str, Option<Bytes>)],
+        mkfs_params: Option<&XfsParams>,
+    ) -> StratisResult<SetCreateAction<(&'b str, FilesystemUuid, Sectors)>> {
+        let spec_map = validate_filesystem_size_specs(specs)?;
+        self.check_no_operation_in_progress("create filesystems")?;
+        if spec_map
+            .keys()
+            .any(|name| self.thin_pool.get_filesystem_by_name(name).is_none())
         {
-            Some(true) => Ok(RenameAction::Renamed(uuid)),
-            Some(false) => Ok(RenameAction::Identity),
-            None => Ok(RenameAction::NoSource),
+            self.check_free_space_for("create filesystems", Bytes(0))?;
+        }
+
+        spec_map.iter().fold(Ok(()), |res, (name, size)| {
+            res.and_then(|()| validate_name(name))
+                .and_then(|()| {
+                    if let Some((_, fs)) = self.thin_pool.get_filesystem_by_name(name) {
+                        if fs.thindev_size() == *size {
+                            Ok(())
+                        } else {
+                            Err(StratisError::Msg(format!(
+                                "Size {} of filesystem {} to be created conflicts with size {} for existing filesystem",
+                                size,
+                                name,
+                                fs.thindev_size()
+                            )))
+                        }
+                    } else {
+                        Ok(())
+                    }
+                })
+        })?;
+
+        // TODO: Roll back on filesystem initialization failure.
+        let mut result = Vec::new();
+        for (name, size) in spec_map {
+            if self.thin_pool.get_mut_filesystem_by_name(name).is_none() {
+                let fs_uuid = self.thin_pool.create_filesystem_with_params(
+                    pool_name,
+                    pool_uuid,
+                    name,
+                    size,
+                    mkfs_params,
+                )?;
+                self.thin_pool.log_operation(&format!(
+                    "create filesystem {} with UUID {} and size {}",
+                    name, fs_uuid, size
+                ));
+                result.push((name, fs_uuid, size));
+            }
         }
+
+        Ok(SetCreateAction::new(result))
     }
 
-    fn snapshot_filesystem(
+    pub fn get_strat_blockdev(&self, uuid: DevUuid) -> Option<(BlockDevTier, &StratBlockDev)> {
+        self.backstore.get_blockdev_by_uuid(uuid)
+    }
+
+    /// Whether the blockdev with the given UUID is reserved for a cache
+    /// that has not yet been initialized.
+    pub fn is_reserved_cachedev(&self, uuid: DevUuid) -> bool {
+        self.backstore
+            .reserved_cachedevs()
+            .iter()
+            .any(|(dev_uuid, _)| *dev_uuid == uuid)
+    }
+
+    pub fn get_mut_strat_blockdev(
+        &mut self,
+        uuid: DevUuid,
+    ) -> Option<(BlockDevTier, &mut StratBlockDev)> {
+        self.backstore.get_mut_blockdev_by_uuid(uuid)
+    }
+
+    /// Destroy the pool.
+    /// Precondition: All filesystems belonging to this pool must be
+    /// unmounted.
+    pub fn destroy(&mut self) -> StratisResult<()> {
+        self.thin_pool.teardown()?;
+        self.backstore.destroy()?;
+        Ok(())
+    }
+}
+
+impl<'a> Into<Value> for &'a StratPool {
+    // Precondition: (&ThinPool).into() pattern matches Value::Object(_)
+    // Precondition: (&Backstore).into() pattern matches Value::Object(_)
+    fn into(self) -> Value {
+        let mut map: Map<_, _> =
+            if let Value::Object(map) = <&ThinPool as Into<Value>>::into(&self.thin_pool) {
+                map.into_iter()
+            } else {
+                unreachable!("ThinPool conversion returns a JSON object")
+            }
+            .collect();
+        map.extend(
+            if let Value::Object(map) = <&Backstore as Into<Value>>::into(&self.backstore) {
+                map.into_iter()
+            } else {
+                unreachable!("Backstore conversion returns a JSON object")
+            },
+        );
+        Value::from(map)
+    }
+}
+
+impl Pool for StratPool {
+    fn init_cache(
         &mut self,
-        pool_name: &str,
         pool_uuid: PoolUuid,
-        origin_uuid: FilesystemUuid,
-        snapshot_name: &str,
-    ) -> StratisResult<CreateAction<(FilesystemUuid, &mut dyn Filesystem)>> {
-        validate_name(snapshot_name)?;
+        pool_name: &str,
+        blockdevs: &[&Path],
+        cache_block_size: Option<Sectors>,
+    ) -> StratisResult<SetCreateAction<DevUuid>> {
+        validate_paths(blockdevs)?;
+        if let Some(block_size) = cache_block_size {
+            validate_cache_block_size(block_size)?;
+        }
+        self.check_no_operation_in_progress("initialize a cache")?;
 
-        if self
-            .thin_pool
-            .get_filesystem_by_name(snapshot_name)
-            .is_some()
-        {
-            return Ok(CreateAction::Identity);
+        if self.is_encrypted() {
+            return Err(StratisError::Msg(
+                "Use of a cache is not supported with an encrypted pool".to_string(),
+            ));
         }
+        if !self.has_cache() {
+            check_kernel_feature(KernelFeature::Cache)?;
+            if blockdevs.is_empty() && self.backstore.reserved_cachedevs().is_empty() {
+                return Err(StratisError::Msg(
+                    "At least one blockdev path is required to initialize a cache.".to_string(),
+                ));
+            }
 
-        self.thin_pool
-            .snapshot_filesystem(pool_name, pool_uuid, origin_uuid, snapshot_name)
-            .map(CreateAction::Created)
+            // If adding cache devices, must suspend the pool, since the cache
+            // must be augmented with the new devices.
+            self.thin_pool.suspend()?;
+            let devices_result = self.backstore.init_cache(
+                pool_uuid,
+                blockdevs,
+                cache_block_size.unwrap_or(DEFAULT_CACHE_BLOCK_SIZE),
+            );
+            self.thin_pool.resume()?;
+            let devices = devices_result?;
+            self.write_metadata(pool_name)?;
+            self.thin_pool.log_operation(&format!(
+                "initialize cache with devices {}",
+                paths_to_string(blockdevs)
+            ));
+            Ok(SetCreateAction::new(devices))
+        } else {
+            init_cache_idempotent_or_err(
+                blockdevs,
+                self.backstore
+                    .cachedevs()
+                    .into_iter()
+                    .map(|(_, bd)| bd.physical_path().to_owned()),
+            )
+        }
     }
 
-    fn total_physical_size(&self) -> Sectors {
-        self.backstore.datatier_size()
+    fn remove_cache(
+        &mut self,
+        pool_uuid: PoolUuid,
+        pool_name: &str,
+    ) -> StratisResult<SetDeleteAction<DevUuid>> {
+        if !self.has_cache() {
+            return Ok(SetDeleteAction::empty());
+        }
+        self.check_no_operation_in_progress("remove the cache")?;
+
+        // Write back the dirty blocks while the pool is still in use, so
+        // that the pool need only be suspended briefly.
+        let flushed = self.backstore.start_flush_cache()?(&mut |_, _| ());
+        self.backstore.finish_flush_cache()?;
+        flushed?;
+
+        self.thin_pool.suspend()?;
+        let set_device_result = self
+            .backstore
+            .uncached_device(pool_uuid)
+            .and_then(|device| self.thin_pool.set_device(device));
+        self.thin_pool.resume()?;
+        set_device_result?;
+
+        let uuids = self.backstore.remove_cache(pool_uuid)?;
+        self.write_metadata(pool_name)?;
+        self.thin_pool.log_operation("remove cache");
+        Ok(SetDeleteAction::new(uuids))
     }
 
-    fn total_physical_used(&self) -> StratisResult<Sectors> {
-        // TODO: note that with the addition of another layer, the
-        // calculation of the amount of physical spaced used by means
-        // of adding the amount used by Stratis metadata to the amount used
-        // by the pool abstraction will be invalid. In the event of, e.g.,
-        // software RAID, the amount will be far too low to be useful, in the
-        // event of, e.g, VDO, the amount will be far too large to be useful.
-        self.thin_pool
-            .total_physical_used()
-            .map(|v| v + self.backstore.datatier_metadata_size())
+    fn reserve_cache_devices(
+        &mut self,
+        pool_uuid: PoolUuid,
+        pool_name: &str,
+        blockdevs: &[&Path],
+    ) -> StratisResult<SetCreateAction<DevUuid>> {
+        validate_paths(blockdevs)?;
+        self.check_no_operation_in_progress("reserve cache devices")?;
+
+        if self.is_encrypted() {
+            return Err(StratisError::Msg(
+                "Use of a cache is not supported with an encrypted pool".to_string(),
+            ));
+        }
+        if self.has_cache() {
+            return Err(StratisError::Msg(
+                "The cache has already been initialized; you must use add_blockdevs to add devices to it.".to_string(),
+            ));
+        }
+
+        let reserved = self
+            .backstore
+            .reserved_cachedevs()
+            .into_iter()
+            .map(|(_, bd)| bd.physical_path().to_owned())
+            .collect::<Vec<_>>();
+        let new_paths = blockdevs
+            .iter()
+            .filter(|p| !reserved.iter().any(|r| r.as_path() == **p))
+            .cloned()
+            .collect::<Vec<_>>();
+        if new_paths.is_empty() {
+            return Ok(SetCreateAction::empty());
+        }
+
+        let devices = self.backstore.reserve_cachedevs(pool_uuid, &new_paths)?;
+        self.write_metadata(pool_name)?;
+        self.thin_pool.log_operation(&format!(
+            "reserve cache devices {}",
+            paths_to_string(&new_paths)
+        ));
+        Ok(SetCreateAction::new(devices))
     }
 
-    fn filesystems(&self) -> Vec<(Name, FilesystemUuid, &dyn Filesystem)> {
-        self.thin_pool
-            .filesystems()
+    fn reserved_cache_devices(&self) -> Vec<(DevUuid, PathBuf)> {
+        self.backstore
+            .reserved_cachedevs()
             .into_iter()
-            .map(|(n, u, f)| (n, u, f as &dyn Filesystem))
+            .map(|(uuid, bd)| (uuid, bd.physical_path().to_owned()))
             .collect()
     }
 
-    fn get_filesystem(&self, uuid: FilesystemUuid) -> Option<(Name, &dyn Filesystem)> {
-        self.thin_pool
-            .get_filesystem_by_uuid(uuid)
-            .map(|(name, fs)| (name, fs as &dyn Filesystem))
+    fn bind_clevis(
+        &mut self,
+        pin: &str,
+        clevis_info: &Value,
+    ) -> StratisResult<CreateAction<Clevis>> {
+        let changed = self.backstore.bind_clevis(pin, clevis_info)?;
+        if changed {
+            self.thin_pool
+                .log_operation(&format!("bind clevis with pin {}", pin));
+            Ok(CreateAction::Created(Clevis))
+        } else {
+            Ok(CreateAction::Identity)
+        }
     }
 
-    fn get_filesystem_by_name(&self, fs_name: &Name) -> Option<(FilesystemUuid, &dyn Filesystem)> {
-        self.thin_pool
-            .get_filesystem_by_name(fs_name)
-            .map(|(uuid, fs)| (uuid, fs as &dyn Filesystem))
+    fn unbind_clevis(&mut self) -> StratisResult<DeleteAction<Clevis>> {
+        let changed = self.backstore.unbind_clevis()?;
+        if changed {
+            self.thin_pool.log_operation("unbind clevis");
+            Ok(DeleteAction::Deleted(Clevis))
+        } else {
+            Ok(DeleteAction::Identity)
+        }
+    }
+
+    fn bind_keyring(
+        &mut self,
+        key_description: &KeyDescription,
+    ) -> StratisResult<CreateAction<Key>> {
+        let changed = self.backstore.bind_keyring(key_description)?;
+        if changed {
+            self.thin_pool.log_operation(&format!(
+                "bind keyring with key description {}",
+                key_description.as_application_str()
+            ));
+            Ok(CreateAction::Created(Key))
+        } else {
+            Ok(CreateAction::Identity)
+        }
+    }
+
+    fn unbind_keyring(&mut self) -> StratisResult<DeleteAction<Key>> {
+        let changed = self.backstore.unbind_keyring()?;
+        if changed {
+            self.thin_pool.log_operation("unbind keyring");
+            Ok(DeleteAction::Deleted(Key))
+        } else {
+            Ok(DeleteAction::Identity)
+        }
+    }
+
+    fn rebind_keyring(
+        &mut self,
+        new_key_desc: &KeyDescription,
+    ) -> StratisResult<RenameAction<Key>> {
+        match self.backstore.rebind_keyring(new_key_desc)? {
+            Some(true) => {
+                self.thin_pool.log_operation(&format!(
+                    "rebind keyring with key description {}",
+                    new_key_desc.as_application_str()
+                ));
+                Ok(RenameAction::Renamed(Key))
+            }
+            Some(false) => Ok(RenameAction::Identity),
+            None => Ok(RenameAction::NoSource),
+        }
+    }
+
+    fn rebind_clevis(&mut self) -> StratisResult<RegenAction> {
+        self.backstore.rebind_clevis()?;
+        self.thin_pool.log_operation("rebind clevis");
+        Ok(RegenAction)
+    }
+
+    fn create_filesystems<'a, 'b>(
+        &'a mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        specs: &[(&'b str, Option<Bytes>)],
+    ) -> StratisResult<SetCreateAction<(&'b str, FilesystemUuid, Sectors)>> {
+        self.create_filesystems_with_params(pool_name, pool_uuid, specs, None)
+    }
+
+    fn create_filesystem_with_params<'a>(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        name: &'a str,
+        size: Option<Bytes>,
+        mkfs_params: &XfsParams,
+    ) -> StratisResult<SetCreateAction<(&'a str, FilesystemUuid, Sectors)>> {
+        validate_xfs_params(mkfs_params)?;
+        self.create_filesystems_with_params(
+            pool_name,
+            pool_uuid,
+            &[(name, size)],
+            Some(mkfs_params),
+        )
+    }
+
+    fn create_filesystem_on_devices<'a>(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        name: &'a str,
+        size: Option<Bytes>,
+        devices: &[DevUuid],
+    ) -> StratisResult<SetCreateAction<(&'a str, FilesystemUuid, Sectors)>> {
+        self.check_no_operation_in_progress("create filesystems")?;
+        self.backstore.set_preferred_datadevs(devices)?;
+        self.create_filesystems(pool_name, pool_uuid, &[(name, size)])
+    }
+
+    fn explain_create_filesystem(
+        &self,
+        pool_uuid: PoolUuid,
+        name: &str,
+        size: Option<Bytes>,
+        mkfs_params: &XfsParams,
+    ) -> StratisResult<Vec<PlannedAction>> {
+        validate_xfs_params(mkfs_params)?;
+        let spec_map = validate_filesystem_size_specs(&[(name, size)])?;
+        let size = spec_map[name];
+        validate_name(name)?;
+
+        if let Some((_, fs)) = self.thin_pool.get_filesystem_by_name(name) {
+            return if fs.thindev_size() == size {
+                Ok(Vec::new())
+            } else {
+                Err(StratisError::Msg(format!(
+                    "Size {} of filesystem {} to be created conflicts with size {} for existing filesystem",
+                    size,
+                    name,
+                    fs.thindev_size()
+                )))
+            };
+        }
+
+        self.thin_pool
+            .explain_create_filesystem(pool_uuid, name, size, Some(mkfs_params))
+    }
+
+    fn filesystem_size_limits(&self) -> (Sectors, Sectors) {
+        filesystem_size_limits()
+    }
+
+    fn thin_pool_limits(&self) -> ThinPoolLimits {
+        self.thin_pool.size_limits()
+    }
+
+    fn sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
+
+    fn recommended_max_filesystems(&self) -> StratisResult<u64> {
+        let (meta_used, meta_total) = self.thin_pool.metadata_usage().ok_or_else(|| {
+            StratisError::Msg(
+                "The usage of the thin pool metadata is not known since the thin pool is not working"
+                    .to_string(),
+            )
+        })?;
+        Ok(recommended_max_filesystems(
+            self.thin_pool.mdv_size(),
+            meta_used,
+            meta_total,
+            self.thin_pool.filesystems().len() as u64,
+        ))
+    }
+
+    fn stripe_config(&self) -> Option<StripeConfig> {
+        self.backstore.stripe_config()
+    }
+
+    fn filesystem_mapped_ranges(
+        &self,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<Vec<(Sectors, Sectors)>> {
+        self.thin_pool.filesystem_mapped_ranges(uuid)
+    }
+
+    fn start_copy_filesystem_from(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        name: &str,
+        source: &dyn Filesystem,
+        source_ranges: Vec<(Sectors, Sectors)>,
+    ) -> StratisResult<ReadWork<()>> {
+        validate_name(name)?;
+        self.check_no_operation_in_progress("copy a filesystem")?;
+
+        if self.thin_pool.get_filesystem_by_name(name).is_some() {
+            return Err(StratisError::Msg(format!(
+                "A filesystem named {} already exists in pool {}",
+                name, pool_name
+            )));
+        }
+
+        // Extend the thin pool beforehand, so that all the available space
+        // is usable and the check below is accurate.
+        if self.thin_pool.check(pool_uuid, &mut self.backstore)? {
+            self.write_metadata(pool_name)?;
+        }
+
+        let required = source.used()?.sectors();
+        let available = self.total_physical_size() - self.total_physical_used()?;
+        if required > available {
+            return Err(StratisError::Msg(format!(
+                "The filesystem to be copied stores {} but only {} are available in pool {}",
+                required, available, pool_name
+            )));
+        }
+
+        let work = self
+            .thin_pool
+            .start_copy_filesystem(pool_uuid, name, source, source_ranges)?;
+        self.events.push(PoolEvent::OperationChanged(Some(
+            PoolOperation::CopyFilesystem,
+        )));
+        Ok(work)
+    }
+
+    fn finish_copy_filesystem_from(
+        &mut self,
+        pool_name: &str,
+        copied: StratisResult<()>,
+    ) -> StratisResult<FilesystemUuid> {
+        let result = self.thin_pool.finish_copy_filesystem(pool_name, copied);
+        self.events
+            .push(PoolEvent::OperationChanged(self.operation_in_progress()));
+        let fs_uuid = result?;
+        let (name, _) = self
+            .thin_pool
+            .get_filesystem_by_uuid(fs_uuid)
+            .expect("added by finish_copy_filesystem()");
+        self.thin_pool.log_operation(&format!(
+            "copy filesystem {} into pool as filesystem with UUID {}",
+            name, fs_uuid
+        ));
+        Ok(fs_uuid)
+    }
+
+    fn set_filesystem_moving(&mut self, uuid: FilesystemUuid, moving: bool) {
+        if moving {
+            self.moving_filesystems.insert(uuid);
+        } else {
+            self.moving_filesystems.remove(&uuid);
+        }
+    }
+
+    fn add_blockdevs(
+        &mut self,
+        pool_uuid: PoolUuid,
+        pool_name: &str,
+        paths: &[&Path],
+        tier: BlockDevTier,
+    ) -> StratisResult<SetCreateAction<DevUuid>> {
+        self.add_blockdevs_checked(pool_uuid, pool_name, paths, tier, false)
+    }
+
+    fn add_datadevs_with_override(
+        &mut self,
+        pool_uuid: PoolUuid,
+        pool_name: &str,
+        paths: &[&Path],
+    ) -> StratisResult<SetCreateAction<DevUuid>> {
+        self.add_blockdevs_checked(pool_uuid, pool_name, paths, BlockDevTier::Data, true)
+    }
+
+    fn shrink_by_device(
+        &mut self,
+        pool_name: &str,
+        dev_uuid: DevUuid,
+    ) -> StratisResult<DeleteAction<DevUuid>> {
+        match self.backstore.get_blockdev_by_uuid(dev_uuid) {
+            None => return Ok(DeleteAction::Identity),
+            Some((BlockDevTier::Cache, _)) => {
+                return Err(StratisError::Msg(format!(
+                    "Blockdev {} is a cache device; only data devices can be removed",
+                    dev_uuid
+                )));
+            }
+            Some((BlockDevTier::Data, _)) => (),
+        }
+        self.check_no_operation_in_progress("remove a block device")?;
+
+        self.backstore.remove_datadev(dev_uuid)?;
+        self.write_metadata(pool_name)?;
+        self.thin_pool
+            .log_operation(&format!("remove data device with UUID {}", dev_uuid));
+        Ok(DeleteAction::Deleted(dev_uuid))
+    }
+
+    fn destroy_filesystems<'a>(
+        &'a mut self,
+        pool_name: &str,
+        fs_uuids: &[FilesystemUuid],
+    ) -> StratisResult<SetDeleteAction<FilesystemUuid>> {
+        self.check_no_operation_in_progress("destroy filesystems")?;
+        for &uuid in fs_uuids {
+            self.check_filesystem_not_moving(uuid, "destroy filesystem")?;
+        }
+
+        let mut removed = Vec::new();
+        let soft_delete = self.thin_pool.fs_deletion_grace_period().is_some();
+        for &uuid in fs_uuids {
+            let destroyed = if soft_delete {
+                self.thin_pool.soft_delete_filesystem(pool_name, uuid)?
+            } else {
+                self.thin_pool.destroy_filesystem(pool_name, uuid)?
+            };
+            if let Some(uuid) = destroyed {
+                self.thin_pool
+                    .log_operation(&format!("destroy filesystem with UUID {}", uuid));
+                removed.push(uuid);
+            }
+        }
+
+        Ok(SetDeleteAction::new(removed))
+    }
+
+    fn rename_filesystem(
+        &mut self,
+        pool_name: &str,
+        uuid: FilesystemUuid,
+        new_name: &str,
+    ) -> StratisResult<RenameAction<FilesystemUuid>> {
+        validate_name(new_name)?;
+        match self
+            .thin_pool
+            .rename_filesystem(pool_name, uuid, new_name)?
+        {
+            Some(true) => Ok(RenameAction::Renamed(uuid)),
+            Some(false) => Ok(RenameAction::Identity),
+            None => Ok(RenameAction::NoSource),
+        }
+    }
+
+    fn set_filesystem_size(
+        &mut self,
+        _pool_name: &str,
+        uuid: FilesystemUuid,
+        target_size: Sectors,
+        grow_fs: bool,
+    ) -> StratisResult<bool> {
+        self.check_no_operation_in_progress("resize a filesystem")?;
+        self.check_filesystem_not_moving(uuid, "resize filesystem")?;
+
+        let (_, fs) = self.thin_pool.get_filesystem_by_uuid(uuid).ok_or_else(|| {
+            StratisError::Msg(format!("Filesystem with UUID {} does not exist", uuid))
+        })?;
+        let current_size = fs.thindev_size();
+        validate_filesystem_target_size(current_size, target_size)?;
+
+        if target_size > current_size {
+            let current = fs.unbacked_guarantee()?;
+            let grown = fs.unbacked_guarantee_at(target_size)?;
+            self.check_free_space_for(
+                "grow filesystem",
+                if grown > current {
+                    grown - current
+                } else {
+                    Bytes(0)
+                },
+            )?;
+        }
+
+        let changed = self
+            .thin_pool
+            .set_filesystem_size(uuid, target_size, grow_fs)?;
+        if changed {
+            self.thin_pool.log_operation(&format!(
+                "resize filesystem with UUID {} from {} to {}",
+                uuid, current_size, target_size
+            ));
+        }
+        Ok(changed)
+    }
+
+    fn snapshot_filesystem(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        origin_uuid: FilesystemUuid,
+        snapshot_name: &str,
+    ) -> StratisResult<CreateAction<(FilesystemUuid, &mut dyn Filesystem)>> {
+        validate_name(snapshot_name)?;
+        self.check_no_operation_in_progress("snapshot a filesystem")?;
+        self.check_filesystem_not_moving(origin_uuid, "snapshot filesystem")?;
+
+        if self
+            .thin_pool
+            .get_filesystem_by_name(snapshot_name)
+            .is_some()
+        {
+            return Ok(CreateAction::Identity);
+        }
+        self.check_free_space_for("snapshot filesystem", Bytes(0))?;
+
+        let (snapshot_uuid, _) =
+            self.thin_pool
+                .snapshot_filesystem(pool_name, pool_uuid, origin_uuid, snapshot_name)?;
+        self.thin_pool.log_operation(&format!(
+            "snapshot filesystem with UUID {} as {} with UUID {}",
+            origin_uuid, snapshot_name, snapshot_uuid
+        ));
+        let (_, snapshot) = self
+            .thin_pool
+            .get_mut_filesystem_by_uuid(snapshot_uuid)
+            .expect("snapshot was created above");
+        Ok(CreateAction::Created((
+            snapshot_uuid,
+            snapshot as &mut dyn Filesystem,
+        )))
+    }
+
+    fn total_physical_size(&self) -> Sectors {
+        self.backstore.datatier_size()
+    }
+
+    fn total_physical_used(&self) -> StratisResult<Sectors> {
+        // TODO: note that with the addition of another layer, the
+        // calculation of the amount of physical spaced used by means
+        // of adding the amount used by Stratis metadata to the amount used
+        // by the pool abstraction will be invalid. In the event of, e.g.,
+        // software RAID, the amount will be far too low to be useful, in the
+        // event of, e.g, VDO, the amount will be far too large to be useful.
+        self.thin_pool
+            .total_physical_used()
+            .map(|v| v + self.backstore.datatier_metadata_size())
+    }
+
+    fn filesystems(&self) -> Vec<(Name, FilesystemUuid, &dyn Filesystem)> {
+        self.thin_pool
+            .filesystems()
+            .into_iter()
+            .map(|(n, u, f)| (n, u, f as &dyn Filesystem))
+            .collect()
+    }
+
+    fn get_filesystem(&self, uuid: FilesystemUuid) -> Option<(Name, &dyn Filesystem)> {
+        self.thin_pool
+            .get_filesystem_by_uuid(uuid)
+            .map(|(name, fs)| (name, fs as &dyn Filesystem))
+    }
+
+    fn scrub_filesystem(
+        &self,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<ReadWork<Vec<(Sectors, Sectors)>>> {
+        let (_, fs) = self
+            .thin_pool
+            .get_filesystem_by_uuid(uuid)
+            .ok_or_else(|| StratisError::Msg(format!("Filesystem with UUID {} not found", uuid)))?;
+        Ok(fs.scrub_work(self.thin_pool.filesystem_mapped_ranges(uuid)?))
+    }
+
+    fn warm_cache(
+        &self,
+        uuid: FilesystemUuid,
+        budget: Option<Sectors>,
+    ) -> StratisResult<ReadWork<Sectors>> {
+        let cache_size = self
+            .backstore
+            .cache_size()
+            .ok_or_else(|| StratisError::Msg("The pool has no cache to warm".to_string()))?;
+        let (_, fs) = self
+            .thin_pool
+            .get_filesystem_by_uuid(uuid)
+            .ok_or_else(|| StratisError::Msg(format!("Filesystem with UUID {} not found", uuid)))?;
+        Ok(fs.warm_cache_work(
+            self.thin_pool.filesystem_mapped_ranges(uuid)?,
+            budget.map_or(cache_size, |budget| min(budget, cache_size)),
+        ))
+    }
+
+    fn start_flush_cache(&mut self) -> StratisResult<ReadWork<Sectors>> {
+        self.check_no_operation_in_progress("flush the cache")?;
+        let work = self.backstore.start_flush_cache()?;
+        if self.backstore.cache_flushing() {
+            self.events
+                .push(PoolEvent::OperationChanged(Some(PoolOperation::FlushCache)));
+        }
+        Ok(work)
+    }
+
+    fn finish_flush_cache(&mut self, flushed: StratisResult<Sectors>) -> StratisResult<Sectors> {
+        let was_flushing = self.backstore.cache_flushing();
+        let result = self.backstore.finish_flush_cache();
+        if was_flushing {
+            self.events
+                .push(PoolEvent::OperationChanged(self.operation_in_progress()));
+        }
+        result?;
+        let flushed = flushed?;
+        if flushed != Sectors(0) {
+            self.thin_pool
+                .log_operation(&format!("flush {} from cache", flushed));
+        }
+        Ok(flushed)
+    }
+
+    fn relocate_metadata(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        devices: &[DevUuid],
+        progress: &mut dyn FnMut(Sectors, Sectors),
+    ) -> StratisResult<bool> {
+        if devices.is_empty() {
+            return Err(StratisError::Msg(
+                "At least one data device must be given to relocate the metadata to".to_string(),
+            ));
+        }
+        for uuid in devices {
+            match self.backstore.get_blockdev_by_uuid(*uuid) {
+                Some((BlockDevTier::Data, _)) => (),
+                Some((BlockDevTier::Cache, _)) => {
+                    return Err(StratisError::Msg(format!(
+                        "Blockdev {} is a cache device; the metadata can only be relocated to data devices",
+                        uuid
+                    )));
+                }
+                None => {
+                    return Err(StratisError::Msg(format!(
+                        "Blockdev {} does not belong to pool {}",
+                        uuid, pool_name
+                    )));
+                }
+            }
+        }
+        self.check_no_operation_in_progress("relocate the metadata")?;
+
+        let result =
+            self.thin_pool
+                .relocate_metadata(pool_uuid, &mut self.backstore, devices, progress);
+        // Space is allocated from the backstore even if the relocation
+        // fails, so the metadata must be written in either case.
+        self.write_metadata(pool_name)?;
+        if result? {
+            self.thin_pool.log_operation(&format!(
+                "relocate metadata to data devices with UUIDs {}",
+                devices
+                    .iter()
+                    .map(|uuid| uuid.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn compact_metadata(&mut self) -> StratisResult<u64> {
+        self.check_no_operation_in_progress("compact the metadata")?;
+        let discarded = self.thin_pool.compact_metadata()?;
+        self.thin_pool.log_operation(&format!(
+            "compact metadata, discarding {} stale entries",
+            discarded
+        ));
+        Ok(discarded)
+    }
+
+    fn verify_metadata_consistency(&self, pool_name: &str) -> StratisResult<MetadataCheck> {
+        let mut problems = Vec::new();
+        let mut copies = Vec::new();
+        for (dev_uuid, _, bd) in self.backstore.blockdevs() {
+            match bd.verify_state() {
+                Ok(Some((time, data, dev_problems))) => {
+                    problems.extend(dev_problems.into_iter().map(|problem| {
+                        format!(
+                            "Block device {} ({}): {}",
+                            dev_uuid,
+                            bd.devnode().display(),
+                            problem
+                        )
+                    }));
+                    copies.push((dev_uuid, time, data));
+                }
+                Ok(None) => (),
+                Err(err) => problems.push(format!(
+                    "Block device {} ({}): the metadata could not be read: {}",
+                    dev_uuid,
+                    bd.devnode().display(),
+                    err
+                )),
+            }
+        }
+
+        // Metadata is only written to some of the block devices at a time,
+        // so only the copies with the latest time stamp must agree.
+        let latest = copies.iter().map(|(_, time, _)| *time).max();
+        if latest.is_none() {
+            problems.push("No block device holds metadata for the pool".to_string());
+        }
+        let current = self.record(pool_name);
+        for (dev_uuid, _, data) in copies.iter().filter(|(_, time, _)| Some(*time) == latest) {
+            match serde_json::from_slice::<PoolSave>(data) {
+                Ok(ref saved) if *saved == current => (),
+                Ok(_) => problems.push(format!(
+                    "Block device {}: the latest metadata differs from the configuration of the pool",
+                    dev_uuid
+                )),
+                Err(err) => problems.push(format!(
+                    "Block device {}: the latest metadata could not be parsed: {}",
+                    dev_uuid, err
+                )),
+            }
+        }
+
+        problems.extend(
+            self.thin_pool
+                .verify_metadata()?
+                .into_iter()
+                .map(|problem| format!("Metadata volume: {}", problem)),
+        );
+
+        Ok(MetadataCheck {
+            consistent: problems.is_empty(),
+            problems,
+        })
+    }
+
+    fn device_health_checks(&self) -> Vec<HealthCheck> {
+        let device_problems = self
+            .backstore
+            .blockdevs()
+            .into_iter()
+            .flat_map(|(dev_uuid, _, bd)| {
+                let mut paths = vec![bd.devnode()];
+                if bd.metadata_path() != bd.devnode() {
+                    paths.push(bd.metadata_path());
+                }
+                paths.into_iter().filter_map(move |path| {
+                    OpenOptions::new().read(true).open(path).err().map(|err| {
+                        format!(
+                            "Block device {} ({}) can not be opened: {}",
+                            dev_uuid,
+                            path.display(),
+                            err
+                        )
+                    })
+                })
+            })
+            .collect();
+        vec![
+            HealthCheck {
+                name: "devices".to_string(),
+                problems: device_problems,
+            },
+            HealthCheck {
+                name: "thin_pool".to_string(),
+                problems: self.thin_pool.health_problems(),
+            },
+        ]
+    }
+
+    fn redundancy_warnings(&self) -> Vec<RedundancyWarning> {
+        let mut disks: HashMap<String, Vec<DevUuid>> = HashMap::new();
+        for (dev_uuid, _, bd) in self.backstore.blockdevs() {
+            match physical_disks(*bd.device()) {
+                Ok(bd_disks) => {
+                    for disk in bd_disks {
+                        disks.entry(disk).or_insert_with(Vec::new).push(dev_uuid);
+                    }
+                }
+                Err(err) => warn!(
+                    "Could not find the physical disks backing block device {} ({}): {}",
+                    dev_uuid,
+                    bd.devnode().display(),
+                    err
+                ),
+            }
+        }
+        let mut warnings = disks
+            .into_iter()
+            .filter(|(_, dev_uuids)| dev_uuids.len() > 1)
+            .map(|(disk, dev_uuids)| RedundancyWarning { disk, dev_uuids })
+            .collect::<Vec<_>>();
+        warnings.sort_by(|a, b| a.disk.cmp(&b.disk));
+        warnings
+    }
+
+    fn surface_test(&self) -> StratisResult<Vec<SurfaceTestResult>> {
+        self.check_no_operation_in_progress("run a surface test")?;
+        Ok(self
+            .backstore
+            .blockdevs()
+            .into_iter()
+            .map(|(dev_uuid, _, bd)| {
+                let problem = match bd.surface_test() {
+                    Ok(true) => None,
+                    Ok(false) => Some(
+                        "The pattern written to the device was not read back unchanged".to_string(),
+                    ),
+                    Err(err) => Some(format!("The surface test could not be run: {}", err)),
+                };
+                if let Some(ref problem) = problem {
+                    warn!(
+                        "Surface test of block device {} ({}) failed: {}",
+                        dev_uuid,
+                        bd.devnode().display(),
+                        problem
+                    );
+                }
+                SurfaceTestResult { dev_uuid, problem }
+            })
+            .collect())
+    }
+
+    fn reconcile(&mut self, pool_name: &str, pool_uuid: PoolUuid) -> StratisResult<Reconciliation> {
+        self.check_no_operation_in_progress("reconcile the pool")?;
+        let devnodes = self
+            .backstore
+            .blockdevs()
+            .into_iter()
+            .map(|(_, _, bd)| bd.metadata_path().to_owned())
+            .collect::<Vec<_>>();
+        let saved =
+            match reload_metadata(&devnodes.iter().map(|p| p.as_path()).collect::<Vec<_>>())? {
+                Some((_, saved)) => saved,
+                None => {
+                    return Err(StratisError::Msg(
+                        "No block device holds metadata for the pool".to_string(),
+                    ))
+                }
+            };
+        let current = self.record(pool_name);
+        let mut reconciliation = Reconciliation::default();
+
+        // Every field of the metadata is named here, so that a field which
+        // is added to it must also be reconciled.
+        let PoolSave {
+            name,
+            backstore,
+            flex_devs,
+            thinpool_dev,
+            metadata_write_failure_policy,
+            small_device_policy,
+            auto_start,
+            start_priority,
+            detailed_stats,
+            alert_thresholds,
+            fs_deletion_grace_period,
+            auto_prune_snapshots_on_pressure,
+            allocation_strategy,
+            // The mark of an exported pool is cleared by writing the
+            // metadata, and the pool is not set up while it is marked.
+            exported: _,
+            sequence_number,
+        } = saved;
+
+        // The layout of the pool is only read from the metadata when the
+        // devices of the pool are set up.
+        for (what, differs) in &[
+            ("name", name != current.name),
+            ("block devices", backstore != current.backstore),
+            ("flex devices", flex_devs != current.flex_devs),
+            ("thin pool device", thinpool_dev != current.thinpool_dev),
+        ] {
+            if *differs {
+                reconciliation.unresolved.push(format!(
+                    "{}: the metadata on disk differs from the pool as it is set up",
+                    what
+                ));
+            }
+        }
+
+        let corrected = &mut reconciliation.corrected;
+        adopt(
+            "metadata write failure policy",
+            &mut self.metadata_write_failure_policy,
+            metadata_write_failure_policy.unwrap_or_default(),
+            corrected,
+        );
+        adopt(
+            "small device policy",
+            &mut self.small_device_policy,
+            small_device_policy.unwrap_or_default(),
+            corrected,
+        );
+        adopt(
+            "auto start",
+            &mut self.auto_start,
+            auto_start.unwrap_or(true),
+            corrected,
+        );
+        adopt(
+            "start priority",
+            &mut self.start_priority,
+            start_priority,
+            corrected,
+        );
+        adopt(
+            "alert thresholds",
+            &mut self.alert_thresholds,
+            alert_thresholds.unwrap_or_default(),
+            corrected,
+        );
+        adopt(
+            "snapshot pruning threshold",
+            &mut self.auto_prune_snapshots_on_pressure,
+            auto_prune_snapshots_on_pressure,
+            corrected,
+        );
+        adopt(
+            "sequence number",
+            &mut self.sequence_number,
+            sequence_number.unwrap_or(0),
+            corrected,
+        );
+
+        let allocation_strategy = allocation_strategy.unwrap_or_default();
+        if allocation_strategy != self.backstore.allocation_strategy() {
+            corrected.push(format!(
+                "allocation strategy: {:?} in memory, {:?} on disk",
+                self.backstore.allocation_strategy(),
+                allocation_strategy
+            ));
+            self.backstore.set_allocation_strategy(allocation_strategy);
+        }
+
+        let grace_period = fs_deletion_grace_period.map(Duration::from_secs);
+        if grace_period != self.thin_pool.fs_deletion_grace_period() {
+            corrected.push(format!(
+                "filesystem deletion grace period: {:?} in memory, {:?} on disk",
+                self.thin_pool.fs_deletion_grace_period(),
+                grace_period
+            ));
+            self.thin_pool
+                .set_fs_deletion_grace_period(pool_uuid, grace_period);
+        }
+
+        let detailed_stats = detailed_stats.unwrap_or(false);
+        if detailed_stats != self.thin_pool.detailed_stats() {
+            match self.thin_pool.set_detailed_stats(detailed_stats) {
+                Ok(_) => corrected.push(format!(
+                    "detailed statistics: {:?} in memory, {:?} on disk",
+                    !detailed_stats, detailed_stats
+                )),
+                Err(err) => reconciliation.unresolved.push(format!(
+                    "detailed statistics: {:?} on disk, but could not be changed: {}",
+                    detailed_stats, err
+                )),
+            }
+        }
+
+        self.thin_pool
+            .reconcile_filesystems(pool_name, &mut reconciliation)?;
+
+        if !reconciliation.corrected.is_empty() {
+            self.check_alerts();
+            self.thin_pool.log_operation(&format!(
+                "reconcile with the metadata on disk, correcting {} differences",
+                reconciliation.corrected.len()
+            ));
+        }
+        Ok(reconciliation)
+    }
+
+    fn simulate_reconfiguration(
+        &self,
+        changes: &ReconfigurationChanges,
+    ) -> StratisResult<CapacityEstimate> {
+        let mut total = self.backstore.datatier_size();
+        let mut overhead = self.backstore.datatier_metadata_size();
+        let mut data_devices = self.backstore.datadevs().len();
+
+        let removed = changes
+            .remove_data_devices
+            .iter()
+            .cloned()
+            .collect::<HashSet<_>>();
+        for dev_uuid in removed {
+            match self.backstore.get_blockdev_by_uuid(dev_uuid) {
+                Some((BlockDevTier::Data, blockdev)) => {
+                    total -= blockdev.total_size().sectors();
+                    overhead -= blockdev.metadata_size().sectors();
+                    data_devices -= 1;
+                }
+                Some((BlockDevTier::Cache, _)) => {
+                    return Err(StratisError::Msg(format!(
+                        "Blockdev {} is a cache device; only data devices can be removed",
+                        dev_uuid
+                    )));
+                }
+                None => {
+                    return Err(StratisError::Msg(format!(
+                        "Pool has no blockdev with UUID {}",
+                        dev_uuid
+                    )));
+                }
+            }
+        }
+
+        if !changes.add_data_devices.is_empty() {
+            let paths = changes
+                .add_data_devices
+                .iter()
+                .map(|p| p.as_path())
+                .collect::<Vec<_>>();
+            validate_paths(&paths)?;
+            let bda_size = bda_extended_size(MDADataSize::default()).sectors();
+            for (path, verdict) in validate_devices(&paths, self.is_encrypted()) {
+                match verdict {
+                    DeviceVerdict::Usable(size) => {
+                        total += size.sectors() + bda_size;
+                        overhead += bda_size;
+                        data_devices += 1;
+                    }
+                    verdict => {
+                        return Err(StratisError::Msg(format!(
+                            "Device {} can not be added to the pool: {}",
+                            path.display(),
+                            verdict
+                        )));
+                    }
+                }
+            }
+        }
+
+        if data_devices == 0 {
+            return Err(StratisError::Msg(
+                "The pool must retain at least one data device".into(),
+            ));
+        }
+
+        overhead = min(
+            total,
+            overhead + self.thin_pool.metadata_overhead(total - overhead),
+        );
+        Ok(CapacityEstimate {
+            total,
+            overhead,
+            usable: total - overhead,
+        })
+    }
+
+    fn get_filesystem_by_name(&self, fs_name: &Name) -> Option<(FilesystemUuid, &dyn Filesystem)> {
+        self.thin_pool
+            .get_filesystem_by_name(fs_name)
+            .map(|(uuid, fs)| (uuid, fs as &dyn Filesystem))
+    }
+
+    fn structure(&self) -> Vec<TierStructure> {
+        let tier_structure = |tier, blockdevs: Vec<(DevUuid, &StratBlockDev)>| {
+            let total_size = blockdevs.iter().map(|(_, bd)| bd.size()).sum::<Sectors>();
+            let available = blockdevs
+                .iter()
+                .map(|(_, bd)| bd.available())
+                .sum::<Sectors>();
+            TierStructure {
+                tier,
+                device_count: blockdevs.len(),
+                total_size: total_size.bytes(),
+                used_size: (total_size - available).bytes(),
+                redundancy: Redundancy::NONE,
+                // Without redundancy a tier can not run with block devices
+                // missing; a pool is only set up once all of them are found.
+                degraded: false,
+            }
+        };
+
+        let mut tiers = vec![tier_structure(
+            BlockDevTier::Data,
+            self.backstore.datadevs(),
+        )];
+        if self.backstore.has_cache() {
+            tiers.push(tier_structure(
+                BlockDevTier::Cache,
+                self.backstore.cachedevs(),
+            ));
+        }
+        tiers
+    }
+
+    fn filesystems_on_device(
+        &self,
+        dev_uuid: DevUuid,
+    ) -> StratisResult<Vec<(FilesystemUuid, Bytes)>> {
+        match self.backstore.get_blockdev_by_uuid(dev_uuid) {
+            Some((BlockDevTier::Data, _)) => (),
+            Some((BlockDevTier::Cache, _)) => {
+                return Err(StratisError::Msg(format!(
+                    "Blockdev {} is a cache device; filesystem data is only stored on data devices",
+                    dev_uuid
+                )))
+            }
+            None => {
+                return Err(StratisError::Msg(format!(
+                    "Blockdev {} does not belong to the pool",
+                    dev_uuid
+                )))
+            }
+        }
+        Ok(self
+            .thin_pool
+            .filesystem_cap_ranges()?
+            .into_iter()
+            .filter_map(|(fs_uuid, ranges)| {
+                let share = self.backstore.datadev_share(dev_uuid, &ranges);
+                if share == Sectors(0) {
+                    None
+                } else {
+                    Some((fs_uuid, share.bytes()))
+                }
+            })
+            .collect())
+    }
+
+    fn filesystems_pending_grow(&self) -> StratisResult<Vec<(FilesystemUuid, Bytes)>> {
+        self.thin_pool.filesystems_pending_grow()
     }
 
     fn blockdevs(&self) -> Vec<(DevUuid, BlockDevTier, &dyn BlockDev)> {
         self.backstore
             .blockdevs()
             .iter()
-            .map(|&(u, t, b)| (u, t, b as &dyn BlockDev))
-            .collect()
+            .map(|&(u, t, b)| (u, t, b as &dyn BlockDev))
+            .collect()
+    }
+
+    fn get_blockdev(&self, uuid: DevUuid) -> Option<(BlockDevTier, &dyn BlockDev)> {
+        self.get_strat_blockdev(uuid)
+            .map(|(t, b)| (t, b as &dyn BlockDev))
+    }
+
+    fn set_blockdev_user_info(
+        &mut self,
+        pool_name: &str,
+        uuid: DevUuid,
+        user_info: Option<&str>,
+    ) -> StratisResult<RenameAction<DevUuid>> {
+        let result = self.backstore.set_blockdev_user_info(uuid, user_info);
+        match result {
+            Ok(Some(uuid)) => {
+                self.write_metadata(pool_name)?;
+                Ok(RenameAction::Renamed(uuid))
+            }
+            Ok(None) => Ok(RenameAction::Identity),
+            Err(_) => Ok(RenameAction::NoSource),
+        }
+    }
+
+    fn device_write_cache_enabled(&self, uuid: DevUuid) -> StratisResult<bool> {
+        self.get_strat_blockdev(uuid)
+            .ok_or_else(|| StratisError::Msg(format!("Blockdev with UUID {} not found", uuid)))?
+            .1
+            .write_cache_enabled()
+    }
+
+    fn device_in_flight_io(&self, uuid: DevUuid) -> StratisResult<u64> {
+        self.get_strat_blockdev(uuid)
+            .ok_or_else(|| StratisError::Msg(format!("Blockdev with UUID {} not found", uuid)))?
+            .1
+            .in_flight_io()
+    }
+
+    fn disable_device_write_cache(&mut self, uuid: DevUuid) -> StratisResult<bool> {
+        let changed = self
+            .get_mut_strat_blockdev(uuid)
+            .ok_or_else(|| StratisError::Msg(format!("Blockdev with UUID {} not found", uuid)))?
+            .1
+            .disable_write_cache()?;
+        if changed {
+            self.thin_pool
+                .log_operation(&format!("disable write cache of device with UUID {}", uuid));
+        }
+        Ok(changed)
+    }
+
+    fn cache_block_size(&self) -> Option<Sectors> {
+        self.backstore.cache_block_size()
+    }
+
+    fn has_cache(&self) -> bool {
+        self.backstore.has_cache()
+    }
+
+    fn supported_operations(&self) -> Vec<PoolCapability> {
+        supported_operations(
+            self,
+            self.read_only || self.operation_in_progress().is_some(),
+        )
+    }
+
+    fn is_encrypted(&self) -> bool {
+        self.datadevs_encrypted()
+    }
+
+    fn encryption_info(&self) -> Cow<EncryptionInfo> {
+        self.backstore.data_tier_encryption_info()
+    }
+
+    fn keyslot_usage(&self) -> StratisResult<KeyslotUsage> {
+        if !self.is_encrypted() {
+            return Err(StratisError::Msg(
+                "Requested pool does not appear to be encrypted".to_string(),
+            ));
+        }
+        let mut usage: Option<KeyslotUsage> = None;
+        for (_, bd) in self.backstore.datadevs() {
+            let bd_usage = bd.keyslot_usage()?;
+            if usage.map_or(true, |u| bd_usage.used > u.used) {
+                usage = Some(bd_usage);
+            }
+        }
+        Ok(usage.expect("an encrypted pool has at least one data device"))
+    }
+
+    fn operation_in_progress(&self) -> Option<PoolOperation> {
+        if self.thin_pool.needs_repair() {
+            Some(PoolOperation::ThinRepair)
+        } else if self.thin_pool.copy_in_progress() {
+            Some(PoolOperation::CopyFilesystem)
+        } else if self.backstore.cache_flushing() {
+            Some(PoolOperation::FlushCache)
+        } else {
+            None
+        }
+    }
+
+    fn metadata_write_failure_policy(&self) -> MetadataWriteFailurePolicy {
+        self.metadata_write_failure_policy
+    }
+
+    fn set_metadata_write_failure_policy(
+        &mut self,
+        pool_name: &str,
+        policy: MetadataWriteFailurePolicy,
+    ) -> StratisResult<bool> {
+        if self.metadata_write_failure_policy == policy {
+            return Ok(false);
+        }
+        let old_policy = self.metadata_write_failure_policy;
+        let was_read_only = self.read_only;
+        self.metadata_write_failure_policy = policy;
+        // A pool that only alerts on a failed write is never read-only.
+        if policy == MetadataWriteFailurePolicy::Alert {
+            self.read_only = false;
+        }
+        if let Err(err) = self.write_metadata(pool_name) {
+            self.metadata_write_failure_policy = old_policy;
+            self.read_only = was_read_only;
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    fn small_device_policy(&self) -> SmallDevicePolicy {
+        self.small_device_policy
+    }
+
+    fn set_small_device_policy(
+        &mut self,
+        pool_name: &str,
+        policy: SmallDevicePolicy,
+    ) -> StratisResult<bool> {
+        if self.small_device_policy == policy {
+            return Ok(false);
+        }
+        let old_policy = self.small_device_policy;
+        self.small_device_policy = policy;
+        if let Err(err) = self.write_metadata(pool_name) {
+            self.small_device_policy = old_policy;
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    fn allocation_strategy(&self) -> AllocationStrategy {
+        self.backstore.allocation_strategy()
+    }
+
+    fn set_allocation_strategy(
+        &mut self,
+        pool_name: &str,
+        strategy: AllocationStrategy,
+    ) -> StratisResult<bool> {
+        let old_strategy = self.backstore.allocation_strategy();
+        if old_strategy == strategy {
+            return Ok(false);
+        }
+        self.backstore.set_allocation_strategy(strategy);
+        if let Err(err) = self.write_metadata(pool_name) {
+            self.backstore.set_allocation_strategy(old_strategy);
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    fn auto_start(&self) -> bool {
+        self.auto_start
+    }
+
+    fn set_auto_start(&mut self, pool_name: &str, auto_start: bool) -> StratisResult<bool> {
+        if self.auto_start == auto_start {
+            return Ok(false);
+        }
+        self.auto_start = auto_start;
+        if let Err(err) = self.write_metadata(pool_name) {
+            self.auto_start = !auto_start;
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    fn start_priority(&self) -> Option<u32> {
+        self.start_priority
+    }
+
+    fn set_start_priority(
+        &mut self,
+        pool_name: &str,
+        start_priority: Option<u32>,
+    ) -> StratisResult<bool> {
+        if self.start_priority == start_priority {
+            return Ok(false);
+        }
+        let old_priority = self.start_priority;
+        self.start_priority = start_priority;
+        if let Err(err) = self.write_metadata(pool_name) {
+            self.start_priority = old_priority;
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    fn detailed_stats(&self) -> bool {
+        self.thin_pool.detailed_stats()
+    }
+
+    fn set_detailed_stats(&mut self, pool_name: &str, enabled: bool) -> StratisResult<bool> {
+        if self.thin_pool.detailed_stats() == enabled {
+            return Ok(false);
+        }
+        if let Err(err) = self
+            .thin_pool
+            .set_detailed_stats(enabled)
+            .and_then(|_| self.write_metadata(pool_name))
+        {
+            return match self.thin_pool.set_detailed_stats(!enabled) {
+                Ok(_) => Err(err),
+                Err(rollback_err) => Err(StratisError::NoActionRollbackError {
+                    causal_error: Box::new(err),
+                    rollback_error: Box::new(rollback_err),
+                }),
+            };
+        }
+        Ok(true)
+    }
+
+    fn filesystem_shared_size(&self, uuid: FilesystemUuid) -> StratisResult<(Bytes, Bytes)> {
+        let (shared, unique) = self.thin_pool.filesystem_shared_size(uuid)?;
+        Ok((shared.bytes(), unique.bytes()))
+    }
+
+    fn set_fs_cow_alert_threshold(
+        &mut self,
+        _pool_name: &str,
+        uuid: FilesystemUuid,
+        threshold: Option<Bytes>,
+    ) -> StratisResult<bool> {
+        let changed = self
+            .thin_pool
+            .set_filesystem_cow_alert_threshold(uuid, threshold)?;
+        if changed {
+            // Let the next check raise the alert anew against the new
+            // threshold.
+            self.cow_alerts.remove(&uuid);
+            self.thin_pool.log_operation(&format!(
+                "set copy-on-write alert threshold of filesystem with UUID {} to {}",
+                uuid,
+                threshold.map_or_else(|| "none".to_string(), |t| t.to_string())
+            ));
+        }
+        Ok(changed)
+    }
+
+    fn set_fs_min_free_guarantee(
+        &mut self,
+        pool_name: &str,
+        uuid: FilesystemUuid,
+        guarantee: Option<Bytes>,
+    ) -> StratisResult<bool> {
+        let (fs_name, fs) = self.thin_pool.get_filesystem_by_uuid(uuid).ok_or_else(|| {
+            StratisError::Msg(format!("Filesystem with UUID {} does not exist", uuid))
+        })?;
+        if fs.min_free_guarantee() == guarantee {
+            return Ok(false);
+        }
+        if let Some(guarantee) = guarantee {
+            let size = fs.thindev_size().bytes();
+            if guarantee > size {
+                return Err(StratisError::Msg(format!(
+                    "The guarantee of {} exceeds the size {} of filesystem {}",
+                    guarantee, size, fs_name
+                )));
+            }
+            let used = min(fs.used()?, size);
+            let guaranteed = self.unbacked_guarantees(Some(uuid))? + min(guarantee, size - used);
+            let free = self.free_physical_space()?;
+            if guaranteed > free {
+                return Err(StratisError::Msg(format!(
+                    "Pool {} has {} of free space, which can not honor a guarantee of {} for filesystem {} along with the guarantees of the other filesystems",
+                    pool_name, free, guarantee, fs_name
+                )));
+            }
+        }
+
+        let changed = self
+            .thin_pool
+            .set_filesystem_min_free_guarantee(uuid, guarantee)?;
+        if changed {
+            self.thin_pool.log_operation(&format!(
+                "set minimum free space guarantee of filesystem with UUID {} to {}",
+                uuid,
+                guarantee.map_or_else(|| "none".to_string(), |g| g.to_string())
+            ));
+        }
+        Ok(changed)
+    }
+
+    fn filesystem_latency_histogram(&self, uuid: FilesystemUuid) -> StratisResult<Vec<(u64, u64)>> {
+        if !self.thin_pool.detailed_stats() {
+            return Err(StratisError::Msg(
+                "Detailed statistics are not enabled for the pool".to_string(),
+            ));
+        }
+        let (_, fs) = self
+            .thin_pool
+            .get_filesystem_by_uuid(uuid)
+            .ok_or_else(|| StratisError::Msg(format!("Filesystem with UUID {} not found", uuid)))?;
+        fs.latency_histogram()
+    }
+
+    fn latency_stats(&self) -> StratisResult<LatencyStats> {
+        if !self.thin_pool.detailed_stats() {
+            return Err(StratisError::Msg(
+                "Detailed statistics are not enabled for the pool".to_string(),
+            ));
+        }
+        let mut totals = (0, 0, 0, 0);
+        let mut histogram = vec![0; LATENCY_HISTOGRAM_BOUNDARIES.len() + 1];
+        for (_, _, fs) in self.thin_pool.filesystems() {
+            let ((reads, read_ns, writes, write_ns), counts) = fs.latency_totals()?;
+            totals.0 += reads;
+            totals.1 += read_ns;
+            totals.2 += writes;
+            totals.3 += write_ns;
+            histogram
+                .iter_mut()
+                .zip(counts)
+                .for_each(|(total, count)| *total += count);
+        }
+        Ok(latency_stats(totals, &histogram))
+    }
+
+    fn drain_events(&mut self) -> Vec<PoolEvent> {
+        mem::take(&mut self.events)
+    }
+
+    fn operation_log(&self, limit: Option<usize>) -> StratisResult<Vec<OperationLogEntry>> {
+        let log = self.thin_pool.operation_log()?;
+        let skip = limit.map_or(0, |limit| log.len().saturating_sub(limit));
+        Ok(log
+            .into_iter()
+            .skip(skip)
+            .map(|entry| OperationLogEntry {
+                timestamp: Utc.timestamp(entry.timestamp as i64, 0),
+                operation: entry.operation,
+            })
+            .collect())
+    }
+
+    fn capacity_history(&self, since: Option<DateTime<Utc>>) -> Vec<CapacitySample> {
+        self.capacity_history.samples(since)
+    }
+
+    fn alert_thresholds(&self) -> AlertThresholds {
+        self.alert_thresholds
+    }
+
+    fn set_alert_thresholds(
+        &mut self,
+        pool_name: &str,
+        thresholds: AlertThresholds,
+    ) -> StratisResult<bool> {
+        validate_alert_thresholds(&thresholds)?;
+        if self.alert_thresholds == thresholds {
+            return Ok(false);
+        }
+        let old_thresholds = self.alert_thresholds;
+        self.alert_thresholds = thresholds;
+        if let Err(err) = self.write_metadata(pool_name) {
+            self.alert_thresholds = old_thresholds;
+            return Err(err);
+        }
+        self.check_alerts();
+        Ok(true)
+    }
+
+    fn auto_prune_snapshots_on_pressure(&self) -> Option<u64> {
+        self.auto_prune_snapshots_on_pressure
+    }
+
+    fn set_auto_prune_snapshots_on_pressure(
+        &mut self,
+        pool_name: &str,
+        threshold: Option<u64>,
+    ) -> StratisResult<bool> {
+        validate_auto_prune_threshold(threshold)?;
+        if self.auto_prune_snapshots_on_pressure == threshold {
+            return Ok(false);
+        }
+        let old_threshold = self.auto_prune_snapshots_on_pressure;
+        self.auto_prune_snapshots_on_pressure = threshold;
+        if let Err(err) = self.write_metadata(pool_name) {
+            self.auto_prune_snapshots_on_pressure = old_threshold;
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    fn fs_deletion_grace_period(&self) -> Option<Duration> {
+        self.thin_pool.fs_deletion_grace_period()
+    }
+
+    fn set_fs_deletion_grace_period(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        period: Option<Duration>,
+    ) -> StratisResult<bool> {
+        let old_period = self.thin_pool.fs_deletion_grace_period();
+        if old_period == period {
+            return Ok(false);
+        }
+        self.thin_pool
+            .set_fs_deletion_grace_period(pool_uuid, period);
+        if let Err(err) = self.write_metadata(pool_name) {
+            self.thin_pool
+                .set_fs_deletion_grace_period(pool_uuid, old_period);
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    fn deleted_filesystems(&self) -> Vec<DeletedFilesystem> {
+        let grace_period = self
+            .thin_pool
+            .fs_deletion_grace_period()
+            .map_or(0, |period| period.as_secs());
+        self.thin_pool
+            .deleted_filesystems()
+            .into_iter()
+            .map(|record| {
+                let deleted = record.deleted.unwrap_or(0);
+                DeletedFilesystem {
+                    uuid: record.uuid,
+                    name: record.name.clone(),
+                    size: record.size,
+                    deleted: Utc.timestamp(deleted as i64, 0),
+                    purge_after: Utc.timestamp(deleted.saturating_add(grace_period) as i64, 0),
+                }
+            })
+            .collect()
+    }
+
+    fn purge_deleted_filesystem(
+        &mut self,
+        pool_uuid: PoolUuid,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<bool> {
+        self.check_no_operation_in_progress("purge deleted filesystem")?;
+        let purged = self.thin_pool.destroy_deleted_filesystem(pool_uuid, uuid)?;
+        if purged {
+            self.thin_pool
+                .log_operation(&format!("purge deleted filesystem with UUID {}", uuid));
+        }
+        Ok(purged)
+    }
+
+    fn undelete_filesystem(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<bool> {
+        self.check_no_operation_in_progress("undelete filesystem")?;
+        let undeleted = self
+            .thin_pool
+            .undelete_filesystem(pool_name, pool_uuid, uuid)?;
+        if undeleted {
+            self.thin_pool
+                .log_operation(&format!("undelete filesystem with UUID {}", uuid));
+        }
+        Ok(undeleted)
+    }
+
+    fn active_alerts(&self) -> Vec<ActiveAlert> {
+        AlertMetric::ALL
+            .iter()
+            .filter_map(|metric| self.active_alerts.get(metric).cloned())
+            .collect()
+    }
+
+    fn metadata_undersized(&self) -> Option<(Bytes, Bytes)> {
+        self.metadata_undersized.map(|(current, recommended)| {
+            (current.sectors().bytes(), recommended.sectors().bytes())
+        })
+    }
+
+    fn acknowledge_alert(&mut self, metric: AlertMetric) -> StratisResult<bool> {
+        let alert = self.active_alerts.get_mut(&metric).ok_or_else(|| {
+            StratisError::Msg(format!("There is no active alert on metric {}", metric))
+        })?;
+        if alert.acknowledged {
+            return Ok(false);
+        }
+        alert.acknowledged = true;
+        Ok(true)
+    }
+
+    fn reset_transient_state(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+    ) -> StratisResult<bool> {
+        let resumed = resume_suspended_pool_devices(pool_uuid)?;
+        for name in resumed.iter() {
+            warn!(
+                "Resumed device {} of pool {}, which had been left suspended",
+                name, pool_name
+            );
+        }
+
+        let was_read_only = self.read_only;
+        let operation = self.operation_in_progress();
+        self.read_only = false;
+        let result = self
+            .thin_pool
+            .check(pool_uuid, &mut self.backstore)
+            .and_then(|changed| {
+                if changed || was_read_only {
+                    self.write_metadata(pool_name)
+                } else {
+                    Ok(())
+                }
+            });
+        self.check_alerts();
+        self.check_metadata_size();
+        if self.operation_in_progress() != operation {
+            self.events
+                .push(PoolEvent::OperationChanged(self.operation_in_progress()));
+        }
+        result?;
+
+        Ok(!resumed.is_empty()
+            || was_read_only != self.read_only
+            || self.operation_in_progress() != operation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::OpenOptions,
+        io::{BufWriter, Read, Write},
+    };
+
+    use nix::mount::{mount, umount, MsFlags};
+
+    use devicemapper::{
+        Bytes, DevId, DmDevice, DmName, LinearDev, LinearDevTargetParams, LinearTargetParams,
+        TargetLine, ThinPoolStatus, ThinPoolStatusSummary, IEC, SECTOR_SIZE,
+    };
+
+    use crate::engine::{
+        strat_engine::{
+            cmd::thin_check,
+            dm::{get_dm, rename_pool_devices},
+            serde_structs::ThinPoolDevSave,
+            tests::{loopbacked, real},
+        },
+        types::{EngineAction, Redundancy},
+    };
+
+    use super::*;
+
+    fn invariant(pool: &StratPool, pool_name: &str) {
+        check_metadata(&pool.record(&Name::new(pool_name.into()))).unwrap();
+        assert!(!(pool.is_encrypted() && pool.backstore.has_cache()));
+        assert!(pool
+            .backstore
+            .blockdevs()
+            .iter()
+            .all(|(_, _, bd)| bd.metadata_path().is_absolute()))
+    }
+
+    /// Verify that a pool with no devices does not have the minimum amount of
+    /// space required.
+    fn test_empty_pool(paths: &[&Path]) {
+        assert_eq!(paths.len(), 0);
+        assert_matches!(
+            StratPool::initialize(
+                "stratis_test_pool",
+                paths,
+                Redundancy::NONE,
+                &EncryptionInfo::default(),
+                None
+            ),
+            Err(_)
+        );
+    }
+
+    #[test]
+    fn loop_test_empty_pool() {
+        loopbacked::test_with_spec(&loopbacked::DeviceLimits::Exactly(0, None), test_empty_pool);
+    }
+
+    #[test]
+    fn real_test_empty_pool() {
+        real::test_with_spec(&real::DeviceLimits::Exactly(0, None, None), test_empty_pool);
+    }
+
+    /// Test that initializing a cache causes metadata to be updated. Verify
+    /// that data written before the cache was initialized can be read
+    /// afterwards.
+    fn test_add_cachedevs(paths: &[&Path]) {
+        assert!(paths.len() > 1);
+
+        let (paths1, paths2) = paths.split_at(paths.len() / 2);
+
+        let name = "stratis-test-pool";
+        let (uuid, mut pool) = StratPool::initialize(
+            name,
+            paths2,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        invariant(&pool, name);
+
+        let metadata1 = pool.record(name);
+        assert_matches!(metadata1.backstore.cache_tier, None);
+
+        let (_, fs_uuid, _) = pool
+            .create_filesystems(name, uuid, &[("stratis-filesystem", None)])
+            .unwrap()
+            .changed()
+            .and_then(|mut fs| fs.pop())
+            .unwrap();
+        invariant(&pool, name);
+
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("stratis_testing")
+            .tempdir()
+            .unwrap();
+        let new_file = tmp_dir.path().join("stratis_test.txt");
+        let bytestring = b"some bytes";
+        {
+            let (_, fs) = pool.get_filesystem(fs_uuid).unwrap();
+            mount(
+                Some(&fs.devnode()),
+                tmp_dir.path(),
+                Some("xfs"),
+                MsFlags::empty(),
+                None as Option<&str>,
+            )
+            .unwrap();
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .open(&new_file)
+                .unwrap()
+                .write_all(bytestring)
+                .unwrap();
+        }
+
+        pool.init_cache(uuid, name, paths1, None).unwrap();
+        invariant(&pool, name);
+
+        let metadata2 = pool.record(name);
+        assert!(metadata2.backstore.cache_tier.is_some());
+
+        let mut buf = [0u8; 10];
+        {
+            OpenOptions::new()
+                .read(true)
+                .open(&new_file)
+                .unwrap()
+                .read_exact(&mut buf)
+                .unwrap();
+        }
+        assert_eq!(&buf, bytestring);
+        umount(tmp_dir.path()).unwrap();
+        pool.teardown().unwrap();
+    }
+
+    #[test]
+    fn loop_test_add_cachedevs() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(2, 3, None),
+            test_add_cachedevs,
+        );
+    }
+
+    #[test]
+    fn real_test_add_cachedevs() {
+        real::test_with_spec(
+            &real::DeviceLimits::AtLeast(2, None, None),
+            test_add_cachedevs,
+        );
+    }
+
+    /// Verify that adding additional blockdevs will cause a pool that is
+    /// out of space to be extended.
+    fn test_add_datadevs(paths: &[&Path]) {
+        assert!(paths.len() > 1);
+
+        let (paths1, paths2) = paths.split_at(1);
+
+        let name = "stratis-test-pool";
+        let (pool_uuid, mut pool) = StratPool::initialize(
+            name,
+            paths1,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        invariant(&pool, name);
+
+        let fs_name = "stratis_test_filesystem";
+        let (_, fs_uuid, _) = pool
+            .create_filesystems(name, pool_uuid, &[(fs_name, None)])
+            .unwrap()
+            .changed()
+            .and_then(|mut fs| fs.pop())
+            .expect("just created one");
+
+        let devnode = pool.get_filesystem(fs_uuid).unwrap().1.devnode();
+
+        {
+            let buffer_length = IEC::Mi;
+            let mut f = BufWriter::with_capacity(
+                convert_test!(buffer_length, u64, usize),
+                OpenOptions::new().write(true).open(devnode).unwrap(),
+            );
+
+            let buf = &[1u8; SECTOR_SIZE];
+
+            let mut amount_written = Sectors(0);
+            let buffer_length = Bytes::from(buffer_length).sectors();
+            while match pool.thin_pool.state() {
+                Some(ThinPoolStatus::Working(working)) => {
+                    working.summary == ThinPoolStatusSummary::Good
+                }
+                _ => false,
+            } {
+                f.write_all(buf).unwrap();
+                amount_written += Sectors(1);
+                // Run check roughly every time the buffer is cleared.
+                // Running it more often is pointless as the pool is guaranteed
+                // not to see any effects unless the buffer is cleared.
+                if amount_written % buffer_length == Sectors(1) {
+                    pool.thin_pool
+                        .check(pool_uuid, &mut pool.backstore)
+                        .unwrap();
+                }
+            }
+
+            pool.add_blockdevs(pool_uuid, name, paths2, BlockDevTier::Data)
+                .unwrap();
+
+            match pool.thin_pool.state() {
+                Some(ThinPoolStatus::Working(working)) => {
+                    assert_eq!(working.summary, ThinPoolStatusSummary::Good)
+                }
+                _ => panic!("thin pool status should be back to working"),
+            }
+        }
+    }
+
+    #[test]
+    fn loop_test_add_datadevs() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(2, 3, Some(Bytes::from(IEC::Gi * 4).sectors())),
+            test_add_datadevs,
+        );
+    }
+
+    #[test]
+    fn real_test_add_datadevs() {
+        real::test_with_spec(
+            &real::DeviceLimits::AtLeast(
+                2,
+                Some(Bytes::from(IEC::Gi * 2).sectors()),
+                Some(Bytes::from(IEC::Gi * 4).sectors()),
+            ),
+            test_add_datadevs,
+        );
+    }
+
+    /// Verify that every flex device is found on at least one of the
+    /// pool's blockdevs.
+    fn test_flex_device_roles(paths: &[&Path]) {
+        let name = "stratis-test-pool";
+        let (_, mut pool) = StratPool::initialize(
+            name,
+            paths,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        invariant(&pool, name);
+
+        let devnodes = pool
+            .backstore
+            .blockdevs()
+            .iter()
+            .map(|(_, _, bd)| bd.devnode().to_owned())
+            .collect::<Vec<_>>();
+        let roles = pool.flex_device_roles();
+        assert_eq!(roles.len(), 4);
+        for role_devnodes in roles.values() {
+            assert!(!role_devnodes.is_empty());
+            assert!(role_devnodes.iter().all(|d| devnodes.contains(d)));
+        }
+
+        pool.teardown().unwrap();
+    }
+
+    #[test]
+    fn loop_test_flex_device_roles() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(1, 3, None),
+            test_flex_device_roles,
+        );
+    }
+
+    /// Verify that an alert is raised when the overprovision ratio of a pool
+    /// reaches its threshold, that it is raised again until it is
+    /// acknowledged, and that the threshold is recorded in the metadata.
+    fn test_alert_thresholds(paths: &[&Path]) {
+        let name = "stratis-test-pool";
+        let (uuid, mut pool) = StratPool::initialize(
+            name,
+            paths,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        invariant(&pool, name);
+
+        let pool_name = Name::new(name.into());
+        pool.create_filesystems(name, uuid, &[("stratis_test_filesystem", None)])
+            .unwrap();
+        pool.drain_events();
+
+        let thresholds = AlertThresholds {
+            overprovision_ratio: Some(1),
+            ..AlertThresholds::default()
+        };
+        assert!(pool.set_alert_thresholds(name, thresholds).unwrap());
+        assert_eq!(pool.record(name).alert_thresholds, Some(thresholds));
+
+        let events = pool.drain_events();
+        assert_eq!(events.len(), 1);
+        assert_matches!(
+            events[0],
+            PoolEvent::Alert {
+                metric: AlertMetric::OverprovisionRatio,
+                value,
+                threshold: 1,
+            } if value >= 1
+        );
+
+        pool.event_on(uuid, &pool_name).unwrap();
+        assert!(pool
+            .drain_events()
+            .iter()
+            .any(|event| matches!(event, PoolEvent::Alert { .. })));
+
+        assert!(pool
+            .acknowledge_alert(AlertMetric::OverprovisionRatio)
+            .unwrap());
+        assert!(!pool
+            .acknowledge_alert(AlertMetric::OverprovisionRatio)
+            .unwrap());
+        assert!(pool.acknowledge_alert(AlertMetric::Degraded).is_err());
+        let alerts = pool.active_alerts();
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0].acknowledged);
+
+        pool.event_on(uuid, &pool_name).unwrap();
+        assert!(pool
+            .drain_events()
+            .iter()
+            .all(|event| !matches!(event, PoolEvent::Alert { .. })));
+
+        pool.teardown().unwrap();
+    }
+
+    #[test]
+    fn loop_test_alert_thresholds() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(1, 3, None),
+            test_alert_thresholds,
+        );
+    }
+
+    #[test]
+    fn real_test_alert_thresholds() {
+        real::test_with_spec(
+            &real::DeviceLimits::AtLeast(1, None, None),
+            test_alert_thresholds,
+        );
+    }
+
+    /// Verify that the metadata of a pool is found consistent after
+    /// filesystems are created, renamed and destroyed.
+    fn test_verify_metadata_consistency(paths: &[&Path]) {
+        let name = "stratis-test-pool";
+        let (uuid, mut pool) = StratPool::initialize(
+            name,
+            paths,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        invariant(&pool, name);
+
+        let check = pool.verify_metadata_consistency(name).unwrap();
+        assert!(check.consistent, "{:?}", check.problems);
+
+        let created = pool
+            .create_filesystems(name, uuid, &[("fs_one", None), ("fs_two", None)])
+            .unwrap()
+            .changed()
+            .unwrap()
+            .iter()
+            .map(|(_, fs_uuid, _)| *fs_uuid)
+            .collect::<Vec<_>>();
+        pool.rename_filesystem(name, created[0], "fs_three")
+            .unwrap();
+        pool.destroy_filesystems(name, &created[1..]).unwrap();
+
+        let check = pool.verify_metadata_consistency(name).unwrap();
+        assert!(check.consistent, "{:?}", check.problems);
+
+        pool.teardown().unwrap();
+    }
+
+    #[test]
+    fn loop_test_verify_metadata_consistency() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(1, 3, None),
+            test_verify_metadata_consistency,
+        );
+    }
+
+    #[test]
+    fn real_test_verify_metadata_consistency() {
+        real::test_with_spec(
+            &real::DeviceLimits::AtLeast(1, None, None),
+            test_verify_metadata_consistency,
+        );
+    }
+
+    /// Verify that while a deletion grace period is set, destroyed
+    /// filesystems are kept and can be undeleted, and that they are
+    /// destroyed once there is no grace period any more.
+    fn test_fs_deletion_grace_period(paths: &[&Path]) {
+        let name = "stratis-test-pool";
+        let (uuid, mut pool) = StratPool::initialize(
+            name,
+            paths,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        invariant(&pool, name);
+
+        let fs_uuid = pool
+            .create_filesystems(name, uuid, &[("stratis_test_filesystem", None)])
+            .unwrap()
+            .changed()
+            .unwrap()[0]
+            .1;
+
+        let period = Some(Duration::from_secs(3600));
+        assert!(pool
+            .set_fs_deletion_grace_period(name, uuid, period)
+            .unwrap());
+        assert_eq!(pool.record(name).fs_deletion_grace_period, Some(3600));
+
+        pool.destroy_filesystems(name, &[fs_uuid]).unwrap();
+        assert!(pool.get_filesystem(fs_uuid).is_none());
+        let deleted = pool.deleted_filesystems();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].uuid, fs_uuid);
+        assert_eq!(deleted[0].name, "stratis_test_filesystem");
+        assert_eq!(
+            deleted[0].purge_after - deleted[0].deleted,
+            chrono::Duration::seconds(3600)
+        );
+
+        // The grace period is not over, so a periodic purge keeps the
+        // filesystem.
+        pool.purge_deleted_filesystems(uuid);
+        assert_eq!(pool.deleted_filesystems().len(), 1);
+
+        assert!(pool.undelete_filesystem(name, uuid, fs_uuid).unwrap());
+        assert!(!pool.undelete_filesystem(name, uuid, fs_uuid).unwrap());
+        assert!(pool.get_filesystem(fs_uuid).is_some());
+        assert!(pool.deleted_filesystems().is_empty());
+
+        pool.destroy_filesystems(name, &[fs_uuid]).unwrap();
+        assert!(pool.purge_deleted_filesystem(uuid, fs_uuid).unwrap());
+        assert!(!pool.purge_deleted_filesystem(uuid, fs_uuid).unwrap());
+        assert!(pool.deleted_filesystems().is_empty());
+
+        let fs_uuid = pool
+            .create_filesystems(name, uuid, &[("stratis_test_filesystem", None)])
+            .unwrap()
+            .changed()
+            .unwrap()[0]
+            .1;
+        pool.destroy_filesystems(name, &[fs_uuid]).unwrap();
+        assert!(pool.set_fs_deletion_grace_period(name, uuid, None).unwrap());
+        assert!(pool.deleted_filesystems().is_empty());
+        assert!(pool.undelete_filesystem(name, uuid, fs_uuid).is_err());
+        invariant(&pool, name);
+
+        pool.teardown().unwrap();
+    }
+
+    #[test]
+    fn loop_test_fs_deletion_grace_period() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(1, 3, None),
+            test_fs_deletion_grace_period,
+        );
+    }
+
+    #[test]
+    fn real_test_fs_deletion_grace_period() {
+        real::test_with_spec(
+            &real::DeviceLimits::AtLeast(1, None, None),
+            test_fs_deletion_grace_period,
+        );
+    }
+
+    /// Verify that explaining the creation of a filesystem lists the
+    /// commands that would create it without creating it, and that nothing
+    /// is listed for a filesystem that already exists.
+    fn test_explain_create_filesystem(paths: &[&Path]) {
+        let name = "stratis-test-pool";
+        let (uuid, mut pool) = StratPool::initialize(
+            name,
+            paths,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        invariant(&pool, name);
+
+        let params = XfsParams {
+            block_size: Some(Bytes(4096)),
+            ..XfsParams::default()
+        };
+        let actions = pool
+            .explain_create_filesystem(uuid, "stratis_test_filesystem", None, &params)
+            .unwrap();
+        assert_eq!(actions.len(), 3);
+        assert_eq!(actions[0].program, "dmsetup");
+        assert_eq!(actions[0].args[0], "message");
+        assert_eq!(actions[1].program, "dmsetup");
+        assert_eq!(actions[1].args[0], "create");
+        assert!(actions[2].program.ends_with("mkfs.xfs"));
+        assert!(actions[2].args.contains(&"size=4096".to_string()));
+        assert!(pool.filesystems().is_empty());
+
+        pool.create_filesystem_with_params(name, uuid, "stratis_test_filesystem", None, &params)
+            .unwrap();
+        assert!(pool
+            .explain_create_filesystem(uuid, "stratis_test_filesystem", None, &params)
+            .unwrap()
+            .is_empty());
+        assert!(pool
+            .explain_create_filesystem(
+                uuid,
+                "stratis_test_filesystem",
+                Some(Bytes::from(IEC::Ti)),
+                &params
+            )
+            .is_err());
+
+        pool.teardown().unwrap();
+    }
+
+    #[test]
+    fn loop_test_explain_create_filesystem() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(1, 3, None),
+            test_explain_create_filesystem,
+        );
+    }
+
+    #[test]
+    fn real_test_explain_create_filesystem() {
+        real::test_with_spec(
+            &real::DeviceLimits::AtLeast(1, None, None),
+            test_explain_create_filesystem,
+        );
+    }
+
+    /// Verify that the structure of a new pool has a single data tier with
+    /// all the devices, on which the thin pool and metadata use some space.
+    fn test_structure(paths: &[&Path]) {
+        let name = "stratis-test-pool";
+        let (_, mut pool) = StratPool::initialize(
+            name,
+            paths,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        invariant(&pool, name);
+
+        let tiers = pool.structure();
+        assert_eq!(tiers.len(), 1);
+        assert_eq!(tiers[0].tier, BlockDevTier::Data);
+        assert_eq!(tiers[0].device_count, paths.len());
+        assert_eq!(tiers[0].total_size, pool.total_physical_size().bytes());
+        assert!(tiers[0].used_size > Bytes(0));
+        assert!(tiers[0].used_size <= tiers[0].total_size);
+
+        pool.teardown().unwrap();
+    }
+
+    #[test]
+    fn loop_test_structure() {
+        loopbacked::test_with_spec(&loopbacked::DeviceLimits::Range(1, 3, None), test_structure);
+    }
+
+    #[test]
+    fn real_test_structure() {
+        real::test_with_spec(&real::DeviceLimits::AtLeast(1, None, None), test_structure);
+    }
+
+    /// Verify that a new filesystem, which mkfs has written to, is found to
+    /// have data on the data devices of the pool, and that the amounts add
+    /// up over all the data devices.
+    fn test_filesystems_on_device(paths: &[&Path]) {
+        let name = "stratis-test-pool";
+        let (uuid, mut pool) = StratPool::initialize(
+            name,
+            paths,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        invariant(&pool, name);
+
+        let fs_uuid = pool
+            .create_filesystems(name, uuid, &[("stratis_test_filesystem", None)])
+            .unwrap()
+            .changed()
+            .and_then(|created| created.first().map(|&(_, uuid, _)| uuid))
+            .unwrap();
+
+        let data_devs = pool
+            .blockdevs()
+            .into_iter()
+            .filter(|(_, tier, _)| *tier == BlockDevTier::Data)
+            .map(|(dev_uuid, _, _)| dev_uuid)
+            .collect::<Vec<_>>();
+        let total = data_devs
+            .iter()
+            .map(|dev_uuid| {
+                let filesystems = pool.filesystems_on_device(*dev_uuid).unwrap();
+                assert!(filesystems.iter().all(|(uuid, _)| *uuid == fs_uuid));
+                filesystems.iter().map(|(_, amount)| *amount).sum::<Bytes>()
+            })
+            .sum::<Bytes>();
+        assert!(total > Bytes(0));
+
+        assert!(pool.filesystems_on_device(DevUuid::new_v4()).is_err());
+
+        pool.teardown().unwrap();
     }
 
-    fn get_blockdev(&self, uuid: DevUuid) -> Option<(BlockDevTier, &dyn BlockDev)> {
-        self.get_strat_blockdev(uuid)
-            .map(|(t, b)| (t, b as &dyn BlockDev))
+    #[test]
+    fn loop_test_filesystems_on_device() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(1, 3, None),
+            test_filesystems_on_device,
+        );
     }
 
-    fn set_blockdev_user_info(
-        &mut self,
-        pool_name: &str,
-        uuid: DevUuid,
-        user_info: Option<&str>,
-    ) -> StratisResult<RenameAction<DevUuid>> {
-        let result = self.backstore.set_blockdev_user_info(uuid, user_info);
-        match result {
-            Ok(Some(uuid)) => {
-                self.write_metadata(pool_name)?;
-                Ok(RenameAction::Renamed(uuid))
-            }
-            Ok(None) => Ok(RenameAction::Identity),
-            Err(_) => Ok(RenameAction::NoSource),
-        }
+    #[test]
+    fn real_test_filesystems_on_device() {
+        real::test_with_spec(
+            &real::DeviceLimits::AtLeast(1, None, None),
+            test_filesystems_on_device,
+        );
     }
 
-    fn has_cache(&self) -> bool {
-        self.backstore.has_cache()
+    /// Verify that simulating the addition of devices predicts the size of
+    /// the pool after they are added, and that a removal that could not be
+    /// made is rejected.
+    fn test_simulate_reconfiguration(paths: &[&Path]) {
+        assert!(paths.len() > 1);
+
+        let (paths1, paths2) = paths.split_at(1);
+
+        let name = "stratis-test-pool";
+        let (pool_uuid, mut pool) = StratPool::initialize(
+            name,
+            paths1,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        invariant(&pool, name);
+
+        let unchanged = pool
+            .simulate_reconfiguration(&ReconfigurationChanges::default())
+            .unwrap();
+        assert_eq!(unchanged.total, pool.total_physical_size());
+        assert_eq!(unchanged.usable + unchanged.overhead, unchanged.total);
+
+        let only_dev = pool.backstore.datadevs()[0].0;
+        assert!(pool
+            .simulate_reconfiguration(&ReconfigurationChanges {
+                remove_data_devices: vec![only_dev],
+                ..ReconfigurationChanges::default()
+            })
+            .is_err());
+
+        let estimate = pool
+            .simulate_reconfiguration(&ReconfigurationChanges {
+                add_data_devices: paths2.iter().map(|p| p.to_path_buf()).collect(),
+                ..ReconfigurationChanges::default()
+            })
+            .unwrap();
+        assert!(estimate.usable > unchanged.usable);
+
+        pool.add_blockdevs(pool_uuid, name, paths2, BlockDevTier::Data)
+            .unwrap();
+        assert_eq!(estimate.total, pool.total_physical_size());
+
+        pool.teardown().unwrap();
     }
 
-    fn is_encrypted(&self) -> bool {
-        self.datadevs_encrypted()
+    #[test]
+    fn loop_test_simulate_reconfiguration() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(2, 3, None),
+            test_simulate_reconfiguration,
+        );
     }
 
-    fn encryption_info(&self) -> Cow<EncryptionInfo> {
-        self.backstore.data_tier_encryption_info()
+    #[test]
+    fn real_test_simulate_reconfiguration() {
+        real::test_with_spec(
+            &real::DeviceLimits::AtLeast(2, None, None),
+            test_simulate_reconfiguration,
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::{
-        fs::OpenOptions,
-        io::{BufWriter, Read, Write},
-    };
+    /// Verify that resetting the transient state of a pool resumes its
+    /// devices that were left suspended and clears the read-only mark.
+    fn test_reset_transient_state(paths: &[&Path]) {
+        let name = "stratis-test-pool";
+        let (uuid, mut pool) = StratPool::initialize(
+            name,
+            paths,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        invariant(&pool, name);
 
-    use nix::mount::{mount, umount, MsFlags};
+        assert!(!pool.reset_transient_state(name, uuid).unwrap());
 
-    use devicemapper::{Bytes, ThinPoolStatus, ThinPoolStatusSummary, IEC, SECTOR_SIZE};
+        pool.thin_pool.suspend().unwrap();
+        assert!(pool.reset_transient_state(name, uuid).unwrap());
+        assert!(resume_suspended_pool_devices(uuid).unwrap().is_empty());
 
-    use crate::engine::{
-        strat_engine::tests::{loopbacked, real},
-        types::{EngineAction, Redundancy},
-    };
+        pool.read_only = true;
+        assert!(pool.reset_transient_state(name, uuid).unwrap());
+        assert!(!pool.read_only);
 
-    use super::*;
+        pool.teardown().unwrap();
+    }
 
-    fn invariant(pool: &StratPool, pool_name: &str) {
-        check_metadata(&pool.record(&Name::new(pool_name.into()))).unwrap();
-        assert!(!(pool.is_encrypted() && pool.backstore.has_cache()));
-        assert!(pool
-            .backstore
-            .blockdevs()
-            .iter()
-            .all(|(_, _, bd)| bd.metadata_path().is_absolute()))
+    #[test]
+    fn loop_test_reset_transient_state() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(1, 3, None),
+            test_reset_transient_state,
+        );
     }
 
-    /// Verify that a pool with no devices does not have the minimum amount of
-    /// space required.
-    fn test_empty_pool(paths: &[&Path]) {
-        assert_eq!(paths.len(), 0);
-        assert_matches!(
-            StratPool::initialize(
-                "stratis_test_pool",
-                paths,
-                Redundancy::NONE,
-                &EncryptionInfo::default()
-            ),
-            Err(_)
+    #[test]
+    fn real_test_reset_transient_state() {
+        real::test_with_spec(
+            &real::DeviceLimits::AtLeast(1, None, None),
+            test_reset_transient_state,
         );
     }
 
+    /// Verify that a pool that was marked read-only becomes writable again
+    /// when its metadata is written successfully, and when its metadata
+    /// write failure policy is changed to alert.
+    fn test_read_only_cleared(paths: &[&Path]) {
+        let name = "stratis-test-pool";
+        let (_, mut pool) = StratPool::initialize(
+            name,
+            paths,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        invariant(&pool, name);
+
+        assert!(pool
+            .set_metadata_write_failure_policy(name, MetadataWriteFailurePolicy::ReadOnly)
+            .unwrap());
+        pool.read_only = true;
+        pool.write_metadata(name).unwrap();
+        assert!(!pool.read_only);
+
+        pool.read_only = true;
+        assert!(pool
+            .set_metadata_write_failure_policy(name, MetadataWriteFailurePolicy::Alert)
+            .unwrap());
+        assert!(!pool.read_only);
+
+        pool.teardown().unwrap();
+    }
+
     #[test]
-    fn loop_test_empty_pool() {
-        loopbacked::test_with_spec(&loopbacked::DeviceLimits::Exactly(0, None), test_empty_pool);
+    fn loop_test_read_only_cleared() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(1, 3, None),
+            test_read_only_cleared,
+        );
     }
 
     #[test]
-    fn real_test_empty_pool() {
-        real::test_with_spec(&real::DeviceLimits::Exactly(0, None, None), test_empty_pool);
+    fn real_test_read_only_cleared() {
+        real::test_with_spec(
+            &real::DeviceLimits::AtLeast(1, None, None),
+            test_read_only_cleared,
+        );
     }
 
-    /// Test that initializing a cache causes metadata to be updated. Verify
-    /// that data written before the cache was initialized can be read
-    /// afterwards.
-    fn test_add_cachedevs(paths: &[&Path]) {
+    /// Verify that the thin pool metadata, once relocated to another data
+    /// device, passes thin_check, and that data written to a filesystem
+    /// after the relocation survives a teardown and a setup of the thin pool.
+    fn test_relocate_metadata(paths: &[&Path]) {
         assert!(paths.len() > 1);
 
-        let (paths1, paths2) = paths.split_at(paths.len() / 2);
+        let (paths1, paths2) = paths.split_at(1);
 
         let name = "stratis-test-pool";
-        let (uuid, mut pool) =
-            StratPool::initialize(name, paths2, Redundancy::NONE, &EncryptionInfo::default())
-                .unwrap();
+        let (uuid, mut pool) = StratPool::initialize(
+            name,
+            paths1,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
         invariant(&pool, name);
 
-        let metadata1 = pool.record(name);
-        assert_matches!(metadata1.backstore.cache_tier, None);
+        let new_devs = pool
+            .add_blockdevs(uuid, name, paths2, BlockDevTier::Data)
+            .unwrap()
+            .changed()
+            .unwrap();
+        assert!(pool
+            .relocate_metadata(name, uuid, &new_devs, &mut |_, _| ())
+            .unwrap());
+        invariant(&pool, name);
+
+        let flexdevs: FlexDevsSave = pool.thin_pool.record();
+        let meta_size = flexdevs.thin_meta_dev.iter().map(|s| s.1).sum::<Sectors>();
+        assert_eq!(
+            new_devs
+                .iter()
+                .map(|dev_uuid| pool
+                    .backstore
+                    .datadev_share(*dev_uuid, &flexdevs.thin_meta_dev))
+                .sum::<Sectors>(),
+            meta_size
+        );
 
         let (_, fs_uuid, _) = pool
             .create_filesystems(name, uuid, &[("stratis-filesystem", None)])
@@ -732,7 +3796,6 @@ mod tests {
             .changed()
             .and_then(|mut fs| fs.pop())
             .unwrap();
-        invariant(&pool, name);
 
         let tmp_dir = tempfile::Builder::new()
             .prefix("stratis_testing")
@@ -757,125 +3820,220 @@ mod tests {
                 .unwrap()
                 .write_all(bytestring)
                 .unwrap();
+            umount(tmp_dir.path()).unwrap();
         }
 
-        pool.init_cache(uuid, name, paths1).unwrap();
-        invariant(&pool, name);
+        let flexdevs: FlexDevsSave = pool.thin_pool.record();
+        let thinpoolsave: ThinPoolDevSave = pool.thin_pool.record();
+        retry_operation!(pool.thin_pool.teardown());
 
-        let metadata2 = pool.record(name);
-        assert!(metadata2.backstore.cache_tier.is_some());
+        // Check the relocated metadata while the thin pool is not set up.
+        {
+            let device = pool.backstore.device().unwrap();
+            let mut offset = Sectors(0);
+            let table = flexdevs
+                .thin_meta_dev
+                .iter()
+                .map(|&(start, length)| {
+                    let line = TargetLine::new(
+                        offset,
+                        length,
+                        LinearDevTargetParams::Linear(LinearTargetParams::new(device, start)),
+                    );
+                    offset += length;
+                    line
+                })
+                .collect::<Vec<_>>();
+            let mut meta_dev = LinearDev::setup(
+                get_dm(),
+                DmName::new("stratis_test_relocated_meta").unwrap(),
+                None,
+                table,
+            )
+            .unwrap();
+            thin_check(&meta_dev.devnode()).unwrap();
+            meta_dev.teardown(get_dm()).unwrap();
+        }
+
+        pool.thin_pool =
+            ThinPool::setup(name, uuid, &thinpoolsave, &flexdevs, &pool.backstore).unwrap();
+        invariant(&pool, name);
 
         let mut buf = [0u8; 10];
         {
+            let (_, fs) = pool.get_filesystem(fs_uuid).unwrap();
+            mount(
+                Some(&fs.devnode()),
+                tmp_dir.path(),
+                Some("xfs"),
+                MsFlags::empty(),
+                None as Option<&str>,
+            )
+            .unwrap();
             OpenOptions::new()
                 .read(true)
                 .open(&new_file)
                 .unwrap()
                 .read_exact(&mut buf)
                 .unwrap();
+            umount(tmp_dir.path()).unwrap();
         }
         assert_eq!(&buf, bytestring);
-        umount(tmp_dir.path()).unwrap();
+
         pool.teardown().unwrap();
     }
 
     #[test]
-    fn loop_test_add_cachedevs() {
+    fn loop_test_relocate_metadata() {
         loopbacked::test_with_spec(
             &loopbacked::DeviceLimits::Range(2, 3, None),
-            test_add_cachedevs,
+            test_relocate_metadata,
         );
     }
 
     #[test]
-    fn real_test_add_cachedevs() {
+    fn real_test_relocate_metadata() {
         real::test_with_spec(
             &real::DeviceLimits::AtLeast(2, None, None),
-            test_add_cachedevs,
+            test_relocate_metadata,
         );
     }
 
-    /// Verify that adding additional blockdevs will cause a pool that is
-    /// out of space to be extended.
-    fn test_add_datadevs(paths: &[&Path]) {
-        assert!(paths.len() > 1);
-
-        let (paths1, paths2) = paths.split_at(1);
-
+    /// Verify that renaming the devices of a pool is refused while one of
+    /// its filesystems is mounted, and that otherwise the devices are rebuilt
+    /// with names and DM UUIDs that embed the new pool UUID.
+    fn test_rename_pool_devices(paths: &[&Path]) {
         let name = "stratis-test-pool";
-        let (pool_uuid, mut pool) =
-            StratPool::initialize(name, paths1, Redundancy::NONE, &EncryptionInfo::default())
-                .unwrap();
+        let (uuid, mut pool) = StratPool::initialize(
+            name,
+            paths,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
         invariant(&pool, name);
 
-        let fs_name = "stratis_test_filesystem";
         let (_, fs_uuid, _) = pool
-            .create_filesystems(name, pool_uuid, &[(fs_name, None)])
+            .create_filesystems(name, uuid, &[("stratis-filesystem", None)])
             .unwrap()
             .changed()
             .and_then(|mut fs| fs.pop())
-            .expect("just created one");
-
-        let devnode = pool.get_filesystem(fs_uuid).unwrap().1.devnode();
+            .unwrap();
 
+        let new_uuid = PoolUuid::new_v4();
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("stratis_testing")
+            .tempdir()
+            .unwrap();
         {
-            let buffer_length = IEC::Mi;
-            let mut f = BufWriter::with_capacity(
-                convert_test!(buffer_length, u64, usize),
-                OpenOptions::new().write(true).open(devnode).unwrap(),
-            );
-
-            let buf = &[1u8; SECTOR_SIZE];
-
-            let mut amount_written = Sectors(0);
-            let buffer_length = Bytes::from(buffer_length).sectors();
-            while match pool.thin_pool.state() {
-                Some(ThinPoolStatus::Working(working)) => {
-                    working.summary == ThinPoolStatusSummary::Good
-                }
-                _ => false,
-            } {
-                f.write_all(buf).unwrap();
-                amount_written += Sectors(1);
-                // Run check roughly every time the buffer is cleared.
-                // Running it more often is pointless as the pool is guaranteed
-                // not to see any effects unless the buffer is cleared.
-                if amount_written % buffer_length == Sectors(1) {
-                    pool.thin_pool
-                        .check(pool_uuid, &mut pool.backstore)
-                        .unwrap();
-                }
-            }
-
-            pool.add_blockdevs(pool_uuid, name, paths2, BlockDevTier::Data)
-                .unwrap();
+            let (_, fs) = pool.get_filesystem(fs_uuid).unwrap();
+            mount(
+                Some(&fs.devnode()),
+                tmp_dir.path(),
+                Some("xfs"),
+                MsFlags::empty(),
+                None as Option<&str>,
+            )
+            .unwrap();
+            assert!(rename_pool_devices(uuid, new_uuid).is_err());
+            umount(tmp_dir.path()).unwrap();
+        }
 
-            match pool.thin_pool.state() {
-                Some(ThinPoolStatus::Working(working)) => {
-                    assert_eq!(working.summary, ThinPoolStatusSummary::Good)
-                }
-                _ => panic!("thin pool status should be back to working"),
-            }
+        let renamed = rename_pool_devices(uuid, new_uuid).unwrap();
+        assert!(!renamed.is_empty());
+        for (_, new_name) in renamed.iter() {
+            let info = get_dm().device_info(&DevId::Name(new_name)).unwrap();
+            assert!(new_name.to_string().contains(&uuid_to_string!(new_uuid)));
+            assert_eq!(
+                info.uuid().map(|dm_uuid| dm_uuid.to_string()),
+                Some(new_name.to_string())
+            );
         }
+
+        // The pool refers to its devices by their names, so they get back
+        // the names that embed its UUID before it is torn down.
+        rename_pool_devices(new_uuid, uuid).unwrap();
+        pool.teardown().unwrap();
     }
 
     #[test]
-    fn loop_test_add_datadevs() {
+    fn loop_test_rename_pool_devices() {
         loopbacked::test_with_spec(
-            &loopbacked::DeviceLimits::Range(2, 3, Some(Bytes::from(IEC::Gi * 4).sectors())),
-            test_add_datadevs,
+            &loopbacked::DeviceLimits::Range(1, 3, None),
+            test_rename_pool_devices,
         );
     }
 
     #[test]
-    fn real_test_add_datadevs() {
+    fn real_test_rename_pool_devices() {
         real::test_with_spec(
-            &real::DeviceLimits::AtLeast(
-                2,
-                Some(Bytes::from(IEC::Gi * 2).sectors()),
-                Some(Bytes::from(IEC::Gi * 4).sectors()),
-            ),
-            test_add_datadevs,
+            &real::DeviceLimits::AtLeast(1, None, None),
+            test_rename_pool_devices,
+        );
+    }
+
+    /// Verify that reconciling a pool adopts the settings of the pool and of
+    /// its filesystems that are recorded on disk, and that a pool which
+    /// matches its metadata is left as it is.
+    fn test_reconcile(paths: &[&Path]) {
+        let name = "stratis-test-pool";
+        let (uuid, mut pool) = StratPool::initialize(
+            name,
+            paths,
+            Redundancy::NONE,
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        invariant(&pool, name);
+
+        let (_, fs_uuid, _) = pool
+            .create_filesystems(name, uuid, &[("stratis-filesystem", None)])
+            .unwrap()
+            .changed()
+            .and_then(|mut fs| fs.pop())
+            .unwrap();
+
+        assert_eq!(
+            pool.reconcile(name, uuid).unwrap(),
+            Reconciliation::default()
+        );
+
+        pool.auto_start = false;
+        pool.sequence_number = 42;
+        pool.thin_pool
+            .get_mut_filesystem_by_uuid(fs_uuid)
+            .unwrap()
+            .1
+            .set_cow_alert_threshold(Some(Bytes::from(IEC::Mi)));
+
+        let reconciliation = pool.reconcile(name, uuid).unwrap();
+        assert_eq!(reconciliation.corrected.len(), 3);
+        assert!(reconciliation.unresolved.is_empty());
+        assert!(pool.auto_start);
+        assert_eq!(pool.sequence_number, 0);
+        assert_eq!(
+            pool.thin_pool
+                .get_filesystem_by_uuid(fs_uuid)
+                .map(|(name, fs)| fs.record(&name, fs_uuid).cow_alert_threshold),
+            Some(None)
+        );
+
+        assert_eq!(
+            pool.reconcile(name, uuid).unwrap(),
+            Reconciliation::default()
         );
+        pool.teardown().unwrap();
+    }
+
+    #[test]
+    fn loop_test_reconcile() {
+        loopbacked::test_with_spec(&loopbacked::DeviceLimits::Range(1, 3, None), test_reconcile);
+    }
+
+    #[test]
+    fn real_test_reconcile() {
+        real::test_with_spec(&real::DeviceLimits::AtLeast(1, None, None), test_reconcile);
     }
 }
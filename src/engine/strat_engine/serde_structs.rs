@@ -16,7 +16,10 @@ use serde::Serialize;
 
 use devicemapper::{Sectors, ThinDevId};
 
-use crate::engine::types::{DevUuid, FilesystemUuid};
+use crate::engine::types::{
+    AlertThresholds, AllocationStrategy, DevUuid, FilesystemUuid, MetadataWriteFailurePolicy,
+    SmallDevicePolicy, XfsParams,
+};
 
 /// Implements saving struct data to a serializable form. The form should be
 /// sufficient, in conjunction with the environment, to reconstruct the
@@ -35,6 +38,34 @@ pub struct PoolSave {
     pub backstore: BackstoreSave,
     pub flex_devs: FlexDevsSave,
     pub thinpool_dev: ThinPoolDevSave,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_write_failure_policy: Option<MetadataWriteFailurePolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub small_device_policy: Option<SmallDevicePolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_start: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_priority: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detailed_stats: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert_thresholds: Option<AlertThresholds>,
+    // In seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fs_deletion_grace_period: Option<u64>,
+    // In percent of the total physical size of the pool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_prune_snapshots_on_pressure: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allocation_strategy: Option<AllocationStrategy>,
+    // Set when the pool was exported; an exported pool is only set up on
+    // request, which clears the mark
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exported: Option<bool>,
+    // The position of the pool in the order in which the pools were
+    // created, counting from 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence_number: Option<u64>,
 }
 
 #[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -43,11 +74,22 @@ pub struct BackstoreSave {
     pub cap: CapSave,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cache_tier: Option<CacheTierSave>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reserved_cache: Option<Vec<BaseBlockDevSave>>,
 }
 
 #[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct DataTierSave {
     pub blockdev: BlockDevSave,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stripe: Option<StripeSave>,
+}
+
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StripeSave {
+    pub count: u16,
+    pub size: Sectors,
 }
 
 #[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -80,6 +122,9 @@ pub struct CapSave {
 #[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct CacheTierSave {
     pub blockdev: BlockDevSave,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_size: Option<Sectors>,
 }
 
 #[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -98,11 +143,42 @@ pub struct ThinPoolDevSave {
 // Struct representing filesystem metadata. This metadata is not held in the
 // variable length metadata but on a separate filesystem that is maintained
 // by stratisd.
-#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct FilesystemSave {
     pub name: String,
     pub uuid: FilesystemUuid,
     pub thin_id: ThinDevId,
     pub size: Sectors,
     pub created: u64, // Unix timestamp
+    // The parameters passed to mkfs.xfs, recorded for reference
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mkfs_params: Option<XfsParams>,
+    // When the filesystem was deleted, if it is kept for the deletion grace
+    // period of the pool so that it can be undeleted; Unix timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<u64>,
+    // The UUID of the filesystem of which this filesystem was created as a
+    // snapshot, if it is a snapshot
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<FilesystemUuid>,
+    // The amount of data in bytes that the filesystem may hold apart from
+    // its origin and its snapshots before an alert is raised
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cow_alert_threshold: Option<u64>,
+    // The amount of free space in bytes that the pool keeps available for
+    // the filesystem to write to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_free_guarantee: Option<u64>,
+    // The position of the filesystem in the order in which the filesystems
+    // of the pool were created, counting from 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence_number: Option<u64>,
+}
+
+// Struct representing an entry in the operation log of a pool. Like filesystem
+// metadata, the log is kept on the separate filesystem maintained by stratisd.
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct OperationLogEntrySave {
+    pub timestamp: u64, // Unix timestamp
+    pub operation: String,
 }
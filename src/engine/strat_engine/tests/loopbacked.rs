@@ -3,7 +3,9 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use std::{
-    fs::OpenOptions,
+    env,
+    fs::{self, OpenOptions},
+    io,
     os::unix::io::AsRawFd,
     panic,
     path::{Path, PathBuf},
@@ -13,7 +15,10 @@ use loopdev::{LoopControl, LoopDevice};
 
 use devicemapper::{Bytes, Sectors, IEC};
 
-use crate::engine::strat_engine::tests::{logger::init_logger, util::clean_up};
+use crate::engine::strat_engine::tests::{
+    logger::init_logger,
+    util::{clean_up, CleanupScope},
+};
 
 /// Ways of specifying range of numbers of devices to use for tests.
 /// Unlike real tests, there is no AtLeast constructor, as, at least in theory
@@ -66,6 +71,57 @@ impl Drop for LoopTestDev {
     }
 }
 
+/// Prefix of the temporary directories that hold the backing files of the
+/// loop devices.
+const TMPDIR_PREFIX: &str = "stratis";
+
+/// Detach all loop devices whose backing files are in a temporary directory
+/// made for the loopbacked tests. Loop devices are leaked if a test process
+/// panics while it holds them, and if they pile up the supply of free loop
+/// devices is eventually exhausted.
+///
+/// The backing files of leaked loop devices have usually been deleted
+/// along with their directories, so a suffix of " (deleted)" is ignored.
+pub fn cleanup_loop_devices() -> io::Result<()> {
+    let tmpdir = env::temp_dir();
+    for entry in fs::read_dir("/sys/block")? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !name.starts_with("loop") {
+            continue;
+        }
+
+        let backing_file = match fs::read_to_string(entry.path().join("loop/backing_file")) {
+            Ok(backing_file) => backing_file,
+            // The loop device is not attached.
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        let backing_file = PathBuf::from(
+            backing_file
+                .trim_end_matches('\n')
+                .trim_end_matches(" (deleted)"),
+        );
+        let in_test_dir = backing_file
+            .parent()
+            .filter(|dir| dir.parent() == Some(tmpdir.as_path()))
+            .and_then(|dir| dir.file_name())
+            .map_or(false, |dir| {
+                dir.to_string_lossy().starts_with(TMPDIR_PREFIX)
+            });
+        if in_test_dir {
+            debug!(
+                "Detaching leaked loop device {} with backing file {}",
+                name,
+                backing_file.display()
+            );
+            LoopDevice::open(Path::new("/dev").join(&*name))?.detach()?;
+        }
+    }
+    Ok(())
+}
+
 /// Get a list of counts of devices to use for tests.
 fn get_device_counts(limits: &DeviceLimits) -> Vec<(usize, Option<Sectors>)> {
     match limits {
@@ -98,9 +154,13 @@ where
 
     init_logger();
 
+    // Loop tests are run one at a time, so any loop devices in test
+    // directories were leaked by an earlier test.
+    cleanup_loop_devices().unwrap();
+
     for (count, size) in counts {
         let tmpdir = tempfile::Builder::new()
-            .prefix("stratis")
+            .prefix(TMPDIR_PREFIX)
             .tempdir()
             .unwrap();
         let loop_devices: Vec<LoopTestDev> = get_devices(count, size, &tmpdir);
@@ -108,12 +168,12 @@ where
             loop_devices.iter().map(|x| x.ld.path().unwrap()).collect();
         let device_paths: Vec<&Path> = device_paths.iter().map(|x| x.as_path()).collect();
 
-        clean_up().unwrap();
+        clean_up(&CleanupScope::All).unwrap();
 
         let result = panic::catch_unwind(|| {
             test(&device_paths);
         });
-        let tear_down = clean_up();
+        let tear_down = clean_up(&CleanupScope::All);
 
         result.unwrap();
         tear_down.unwrap();
@@ -8,4 +8,7 @@ pub mod loopbacked;
 pub mod real;
 mod util;
 
-pub use util::{dm_stratis_devices_remove, FailDevice};
+pub use util::{
+    assert_no_stratis_dm_devices, clean_up_dry_run, dm_stratis_devices_remove, flex_device_roles,
+    CleanupScope, FailDevice,
+};
@@ -21,7 +21,10 @@ use devicemapper::{
 use crate::engine::strat_engine::{
     device::blkdev_size,
     dm::get_dm,
-    tests::{logger::init_logger, util::clean_up},
+    tests::{
+        logger::init_logger,
+        util::{clean_up, CleanupScope},
+    },
     writing::wipe_sectors,
 };
 
@@ -230,7 +233,7 @@ where
     init_logger();
 
     for run_paths in runs {
-        clean_up().unwrap();
+        clean_up(&CleanupScope::All).unwrap();
 
         let mut devices: Vec<_> = run_paths
             .iter()
@@ -245,7 +248,7 @@ where
         let paths: Vec<PathBuf> = devices.iter().map(|x| x.as_path()).collect();
         let paths: Vec<&Path> = paths.iter().map(|x| x.as_path()).collect();
         let result = panic::catch_unwind(|| test(&paths));
-        let tear_down = clean_up();
+        let tear_down = clean_up(&CleanupScope::All);
 
         result.unwrap();
         tear_down.unwrap();
@@ -3,6 +3,7 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use std::{
+    collections::{HashMap, HashSet},
     fs::File,
     io::Read,
     path::{Path, PathBuf},
@@ -12,15 +13,20 @@ use std::{
 
 use nix::mount::{umount2, MntFlags};
 
-use devicemapper::{DevId, DmFlags, DmName, DmNameBuf, DmOptions, DM};
+use devicemapper::{DevId, Device, DmFlags, DmName, DmNameBuf, DmOptions, DM};
 
 use crate::{
-    engine::strat_engine::{
-        cmd::udev_settle,
-        device::blkdev_size,
-        dm::{get_dm, get_dm_init},
+    engine::{
+        strat_engine::{
+            cmd::udev_settle,
+            device::blkdev_size,
+            dm::{get_dm, get_dm_init},
+            names::FlexRole,
+            StratEngine,
+        },
+        types::PoolUuid,
     },
-    stratis::StratisResult,
+    stratis::{StratisError, StratisResult},
 };
 
 mod cleanup_errors {
@@ -35,11 +41,134 @@ mod cleanup_errors {
 
 use self::cleanup_errors::{Error, Result};
 
+/// For the pool designated by pool_uuid, return the devnodes of the
+/// blockdevs that hold each of its flex devices. With this, a test can wrap
+/// a device in a FailDevice and know whether failing it will break the
+/// thin pool metadata, the thin pool data, or the pool-level metadata.
+///
+/// Panics if the engine has no pool with the given UUID.
+pub fn flex_device_roles(
+    engine: &StratEngine,
+    pool_uuid: PoolUuid,
+) -> HashMap<FlexRole, Vec<PathBuf>> {
+    engine
+        .get_strat_pool(pool_uuid)
+        .expect("the pool must be set up in the engine")
+        .1
+        .flex_device_roles()
+}
+
+/// Which devices and filesystems clean_up() tears down. A narrower scope
+/// than All leaves alone the devices of other test runs and of real pools
+/// on the same host.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CleanupScope {
+    /// All device mapper devices which match the stratis naming convention
+    /// and all filesystems with "stratis" in their mount point.
+    All,
+    /// The device mapper devices with the tag in their name, and the
+    /// filesystems mounted from them or with the tag in their mount point.
+    RunTag(String),
+    /// The device mapper devices of the pools, including the crypt devices
+    /// below them, and the filesystems mounted from them.
+    Pools(Vec<PoolUuid>),
+    /// The device mapper devices whose names start with the prefix, and the
+    /// filesystems mounted from them.
+    DmPrefix(String),
+}
+
+impl CleanupScope {
+    /// Whether the device mapper device of the given name is in the scope,
+    /// judging by its name alone.
+    fn contains_dm_name(&self, name: &DmName) -> bool {
+        let name = name.to_string();
+        match self {
+            CleanupScope::All => name.starts_with("stratis-1"),
+            CleanupScope::RunTag(tag) => name.contains(tag.as_str()),
+            CleanupScope::Pools(pool_uuids) => {
+                name.starts_with("stratis-1")
+                    && pool_uuids
+                        .iter()
+                        .any(|uuid| name.contains(&uuid_to_string!(uuid)))
+            }
+            CleanupScope::DmPrefix(prefix) => name.starts_with(prefix.as_str()),
+        }
+    }
+}
+
+/// List the device mapper devices in the scope. For the scope of a set of
+/// pools, the crypt devices of their block devices, whose names contain
+/// only the UUIDs of the block devices, are found as the devices that the
+/// devices of the pools are stacked on.
+fn scoped_dm_devices(scope: &CleanupScope) -> Result<Vec<(DmNameBuf, Device)>> {
+    let dm = get_dm_init().map_err(|err| Error::with_chain(err, "Unable to initialize DM"))?;
+    let devices = dm
+        .list_devices()
+        .map_err(|e| Error::with_chain(e, "failed while listing DM devices"))?;
+    let (mut in_scope, mut rest): (Vec<_>, Vec<_>) = devices
+        .into_iter()
+        .map(|(name, device, _)| (name, device))
+        .partition(|(name, _)| scope.contains_dm_name(name));
+
+    if let CleanupScope::Pools(_) = scope {
+        let mut checked = 0;
+        while checked < in_scope.len() {
+            let deps = in_scope[checked..]
+                .iter()
+                .map(|(name, _)| {
+                    dm.table_deps(&DevId::Name(name), &DmOptions::new())
+                        .map_err(|e| Error::with_chain(e, "failed while listing DM device deps"))
+                })
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .flatten()
+                .collect::<HashSet<_>>();
+            checked = in_scope.len();
+            let (below, others): (Vec<_>, Vec<_>) = rest.into_iter().partition(|(name, device)| {
+                deps.contains(device) && CleanupScope::All.contains_dm_name(name)
+            });
+            in_scope.extend(below);
+            rest = others;
+        }
+    }
+
+    Ok(in_scope)
+}
+
+/// List the device mapper devices which match the stratis naming convention.
+fn stratis_dm_devices() -> Result<Vec<DmNameBuf>> {
+    Ok(scoped_dm_devices(&CleanupScope::All)?
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect())
+}
+
+/// Panic if any device mapper devices which match the stratis naming
+/// convention remain. Call at the end of a test to catch device leaks.
+pub fn assert_no_stratis_dm_devices() {
+    let remain = stratis_dm_devices()
+        .unwrap()
+        .iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>();
+    assert!(
+        remain.is_empty(),
+        "Stratis DM devices remaining: {}",
+        remain.join(", ")
+    );
+}
+
 /// Attempt to remove all device mapper devices which match the stratis naming convention.
-/// FIXME: Current implementation complicated by https://bugzilla.redhat.com/show_bug.cgi?id=1506287
 pub fn dm_stratis_devices_remove() -> Result<()> {
+    dm_devices_remove(&CleanupScope::All)
+}
+
+/// Attempt to remove all device mapper devices in the scope. The devices
+/// are determined once, before any of them is removed.
+/// FIXME: Current implementation complicated by https://bugzilla.redhat.com/show_bug.cgi?id=1506287
+fn dm_devices_remove(scope: &CleanupScope) -> Result<()> {
     /// One iteration of removing devicemapper devices
-    fn one_iteration() -> Result<(bool, Vec<DmNameBuf>)> {
+    fn one_iteration(targets: &HashSet<DmNameBuf>) -> Result<(bool, Vec<DmNameBuf>)> {
         let mut progress_made = false;
         let mut remain = get_dm()
             .list_devices()
@@ -50,7 +179,7 @@ pub fn dm_stratis_devices_remove() -> Result<()> {
             .iter()
             .map(|d| &d.0)
             .filter_map(|n| {
-                if !n.to_string().starts_with("stratis-1") {
+                if !targets.contains(n) {
                     None
                 } else {
                     match get_dm().device_remove(&DevId::Name(n), &DmOptions::new()) {
@@ -95,18 +224,21 @@ pub fn dm_stratis_devices_remove() -> Result<()> {
 
     /// Do one iteration of removals until progress stops. Return remaining
     /// dm devices.
-    fn do_while_progress() -> Result<Vec<DmNameBuf>> {
-        let mut result = one_iteration()?;
+    fn do_while_progress(targets: &HashSet<DmNameBuf>) -> Result<Vec<DmNameBuf>> {
+        let mut result = one_iteration(targets)?;
         while result.0 {
-            result = one_iteration()?;
+            result = one_iteration(targets)?;
         }
         Ok(result.1)
     }
 
     || -> Result<()> {
         udev_settle().unwrap();
-        get_dm_init().map_err(|err| Error::with_chain(err, "Unable to initialize DM"))?;
-        do_while_progress().and_then(|remain| {
+        let targets = scoped_dm_devices(scope)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<HashSet<_>>();
+        do_while_progress(&targets).and_then(|remain| {
             if !remain.is_empty() {
                 Err(format!("Some Stratis DM devices remaining: {:?}", remain).into())
             } else {
@@ -117,20 +249,12 @@ pub fn dm_stratis_devices_remove() -> Result<()> {
     .map_err(|e| e.chain_err(|| "Failed to ensure removal of all Stratis DM devices"))
 }
 
-/// Try and un-mount any filesystems that have the name stratis in the mount point, returning
-/// immediately on the first one we are unable to unmount.
-fn stratis_filesystems_unmount() -> Result<()> {
+/// Try and un-mount the filesystems in the scope, returning immediately on
+/// the first one we are unable to unmount.
+fn scoped_filesystems_unmount(scope: &CleanupScope) -> Result<()> {
     || -> Result<()> {
-        let mut mount_data = String::new();
-        File::open("/proc/self/mountinfo")?.read_to_string(&mut mount_data)?;
-        let parser = libmount::mountinfo::Parser::new(mount_data.as_bytes());
-
-        for mount_point in parser
-            .filter_map(|x| x.ok())
-            .filter_map(|m| m.mount_point.into_owned().into_string().ok())
-            .filter(|mp| mp.contains("stratis"))
-        {
-            umount2(&PathBuf::from(mount_point), MntFlags::MNT_DETACH)?;
+        for mount_point in scoped_mount_points(scope)? {
+            umount2(&mount_point, MntFlags::MNT_DETACH)?;
         }
 
         Ok(())
@@ -138,12 +262,96 @@ fn stratis_filesystems_unmount() -> Result<()> {
     .map_err(|e| e.chain_err(|| "Failed to ensure all Stratis filesystems were unmounted"))
 }
 
-/// When a unit test panics we can leave the system in an inconsistent state.  This function
-/// tries to clean up by un-mounting any mounted file systems which contain the string
-/// "stratis_testing" and then it tries to remove any device mapper tables which are also stratis
-/// created.
-pub fn clean_up() -> Result<()> {
-    stratis_filesystems_unmount().and_then(|_| dm_stratis_devices_remove())
+/// List the mount points of the filesystems in the scope.
+fn scoped_mount_points(scope: &CleanupScope) -> Result<Vec<PathBuf>> {
+    let devices = match scope {
+        CleanupScope::All => HashSet::new(),
+        _ => scoped_dm_devices(scope)?
+            .into_iter()
+            .map(|(_, device)| device)
+            .collect::<HashSet<_>>(),
+    };
+
+    let mut mount_data = String::new();
+    File::open("/proc/self/mountinfo")?.read_to_string(&mut mount_data)?;
+    let parser = libmount::mountinfo::Parser::new(mount_data.as_bytes());
+
+    Ok(parser
+        .filter_map(|x| x.ok())
+        .filter_map(|m| {
+            let device = Device {
+                major: m.major as u32,
+                minor: m.minor as u32,
+            };
+            m.mount_point
+                .into_owned()
+                .into_string()
+                .ok()
+                .map(|mp| (mp, device))
+        })
+        .filter(|(mp, device)| match scope {
+            CleanupScope::All => mp.contains("stratis"),
+            CleanupScope::RunTag(tag) => mp.contains(tag.as_str()) || devices.contains(device),
+            _ => devices.contains(device),
+        })
+        .map(|(mp, _)| PathBuf::from(mp))
+        .collect())
+}
+
+/// When a unit test panics we can leave the system in an inconsistent state. This function
+/// tries to clean up by un-mounting the file systems in the scope and then removing the
+/// device mapper devices in the scope. With CleanupScope::All, those are any file systems
+/// which contain the string "stratis" in their mount point and all stratis created device
+/// mapper devices.
+pub fn clean_up(scope: &CleanupScope) -> Result<()> {
+    scoped_filesystems_unmount(scope).and_then(|_| dm_devices_remove(scope))
+}
+
+/// Report what clean_up() would do for the scope without doing it: return
+/// the mount points of the filesystems that it would un-mount and the names
+/// of the device mapper devices that it would remove, and log each of them.
+pub fn clean_up_dry_run(scope: &CleanupScope) -> Result<(Vec<PathBuf>, Vec<DmNameBuf>)> {
+    let mount_points = scoped_mount_points(scope)?;
+    for mount_point in &mount_points {
+        info!("clean_up would un-mount {}", mount_point.display());
+    }
+    let dm_devices = scoped_dm_devices(scope)?
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+    for name in &dm_devices {
+        info!("clean_up would remove DM device {}", name);
+    }
+    Ok((mount_points, dm_devices))
+}
+
+/// The target type and parameters of a table line that maps the backing
+/// device of a FailDevice from its start, delaying reads and writes by
+/// the given number of milliseconds if delay is set.
+fn backing_target(backing_device: &Path, delay: Option<(u32, u32)>) -> (String, String) {
+    match delay {
+        Some((read_delay_ms, write_delay_ms)) => (
+            "delay".to_string(),
+            format!(
+                "{dev} 0 {} {dev} 0 {}",
+                read_delay_ms,
+                write_delay_ms,
+                dev = backing_device.display()
+            ),
+        ),
+        None => (
+            "linear".to_string(),
+            format!("{} 0", backing_device.display()),
+        ),
+    }
+}
+
+/// The parameters of a flakey target that maps the backing device of a
+/// FailDevice from its start and is permanently down, with the single
+/// given feature deciding which I/O fails.
+fn flakey_params(backing_device: &Path, feature: &str) -> String {
+    // An up interval of 0 seconds and a down interval of 1 second
+    format!("{} 0 0 1 1 {}", backing_device.display(), feature)
 }
 
 pub struct FailDevice {
@@ -151,10 +359,34 @@ pub struct FailDevice {
     test_device_name: String,
     dm_context: DM,
     size: u64,
+    delay: Option<(u32, u32)>,
 }
 
 impl FailDevice {
     pub fn new(backing_device: &Path, test_device_name: &str) -> StratisResult<Self> {
+        FailDevice::setup(backing_device, test_device_name, None)
+    }
+
+    /// Make a test device that delays every read by read_delay_ms and every
+    /// write by write_delay_ms milliseconds, to simulate slow storage.
+    pub fn new_delayed(
+        backing_device: &Path,
+        test_device_name: &str,
+        read_delay_ms: u32,
+        write_delay_ms: u32,
+    ) -> StratisResult<Self> {
+        FailDevice::setup(
+            backing_device,
+            test_device_name,
+            Some((read_delay_ms, write_delay_ms)),
+        )
+    }
+
+    fn setup(
+        backing_device: &Path,
+        test_device_name: &str,
+        delay: Option<(u32, u32)>,
+    ) -> StratisResult<Self> {
         let dm = DM::new()?;
         let dm_name = DmName::new(test_device_name)?;
         let dev_id = DevId::Name(dm_name);
@@ -164,16 +396,9 @@ impl FailDevice {
             blkdev_size(&file)?
         };
 
+        let (target_type, params) = backing_target(backing_device, delay);
         dm.device_create(dm_name, None, &DmOptions::new())?;
-        dm.table_load(
-            &dev_id,
-            &[(
-                0,
-                *size.sectors(),
-                "linear".to_string(),
-                format!("{} 0", backing_device.display()),
-            )],
-        )?;
+        dm.table_load(&dev_id, &[(0, *size.sectors(), target_type, params)])?;
         dm.device_suspend(&dev_id, &DmOptions::new())?;
 
         Ok(FailDevice {
@@ -181,6 +406,7 @@ impl FailDevice {
             test_device_name: test_device_name.to_owned(),
             dm_context: dm,
             size: *size.sectors(),
+            delay,
         })
     }
 
@@ -191,6 +417,49 @@ impl FailDevice {
     }
 
     pub fn start_failing(&self, num_sectors_after_start: u64) -> StratisResult<()> {
+        self.load_failing_table(num_sectors_after_start, "error".to_string(), String::new())
+    }
+
+    /// Like start_failing(), but fail only writes; reads still succeed.
+    pub fn start_failing_writes(&self, num_sectors_after_start: u64) -> StratisResult<()> {
+        self.load_failing_table(
+            num_sectors_after_start,
+            "flakey".to_string(),
+            flakey_params(&self.backing_device, "error_writes"),
+        )
+    }
+
+    /// Like start_failing(), but silently drop writes instead of failing
+    /// them, as if they had been lost in a power failure; reads succeed.
+    pub fn start_dropping_writes(&self, num_sectors_after_start: u64) -> StratisResult<()> {
+        self.load_failing_table(
+            num_sectors_after_start,
+            "flakey".to_string(),
+            flakey_params(&self.backing_device, "drop_writes"),
+        )
+    }
+
+    /// Like start_failing(), but fail only reads; writes still succeed.
+    /// Requires a kernel whose flakey target supports the error_reads
+    /// feature.
+    pub fn start_failing_reads(&self, num_sectors_after_start: u64) -> StratisResult<()> {
+        self.load_failing_table(
+            num_sectors_after_start,
+            "flakey".to_string(),
+            flakey_params(&self.backing_device, "error_reads"),
+        )
+    }
+
+    /// Load a table which maps the first num_sectors_after_start sectors
+    /// with the given failing target and the remainder with the backing
+    /// device.
+    fn load_failing_table(
+        &self,
+        num_sectors_after_start: u64,
+        failing_type: String,
+        failing_params: String,
+    ) -> StratisResult<()> {
+        let (target_type, params) = backing_target(&self.backing_device, self.delay);
         let dm_name = DmName::new(self.test_device_name.as_str())?;
         let dev_id = DevId::Name(dm_name);
 
@@ -199,17 +468,12 @@ impl FailDevice {
         self.dm_context.table_load(
             &dev_id,
             &[
-                (
-                    0,
-                    num_sectors_after_start,
-                    "error".to_string(),
-                    String::new(),
-                ),
+                (0, num_sectors_after_start, failing_type, failing_params),
                 (
                     num_sectors_after_start,
                     self.size - num_sectors_after_start,
-                    "linear".to_string(),
-                    format!("{} 0", self.backing_device.display()),
+                    target_type,
+                    params,
                 ),
             ],
         )?;
@@ -219,24 +483,76 @@ impl FailDevice {
     }
 
     pub fn stop_failing(&self) -> StratisResult<()> {
+        let (target_type, params) = backing_target(&self.backing_device, self.delay);
         let dm_name = DmName::new(self.test_device_name.as_str())?;
         let dev_id = DevId::Name(dm_name);
 
         self.dm_context
             .device_suspend(&dev_id, DmOptions::new().set_flags(DmFlags::DM_SUSPEND))?;
-        self.dm_context.table_load(
-            &dev_id,
-            &[(
-                0,
-                self.size,
-                "linear".to_string(),
-                format!("{} 0", self.backing_device.display()),
-            )],
-        )?;
+        self.dm_context
+            .table_load(&dev_id, &[(0, self.size, target_type, params)])?;
         self.dm_context.device_suspend(&dev_id, &DmOptions::new())?;
 
         Ok(())
     }
+
+    /// Delay every read by read_delay_ms and every write by write_delay_ms
+    /// milliseconds from now on. The test device maps the backing device
+    /// in full afterwards, even if it was failing before.
+    pub fn set_delay(&mut self, read_delay_ms: u32, write_delay_ms: u32) -> StratisResult<()> {
+        let delay = Some((read_delay_ms, write_delay_ms));
+        let (target_type, params) = backing_target(&self.backing_device, delay);
+        let dm_name = DmName::new(self.test_device_name.as_str())?;
+        let dev_id = DevId::Name(dm_name);
+
+        self.dm_context
+            .device_suspend(&dev_id, DmOptions::new().set_flags(DmFlags::DM_SUSPEND))?;
+        self.dm_context
+            .table_load(&dev_id, &[(0, self.size, target_type, params)])?;
+        self.dm_context.device_suspend(&dev_id, &DmOptions::new())?;
+
+        self.delay = delay;
+        Ok(())
+    }
+
+    /// Resize the test device to new_size sectors, clamped to the size of
+    /// the backing device, so that it can be made to grow or shrink below a
+    /// pool. The test device maps the backing device in full afterwards,
+    /// even if it was failing before.
+    /// Return the new size of the test device.
+    ///
+    /// Shrinking a device below the space that has been allocated from it
+    /// is allowed, as that is a situation that tests will want to set up.
+    /// If the new table can not be loaded the device is resumed with its old
+    /// table.
+    pub fn resize(&mut self, new_size: u64) -> StratisResult<u64> {
+        let backing_size = {
+            let file = File::open(&self.backing_device)?;
+            *blkdev_size(&file)?.sectors()
+        };
+        let new_size = std::cmp::min(new_size, backing_size);
+        if new_size == 0 {
+            return Err(StratisError::Msg(format!(
+                "Can not resize test device {} to 0 sectors",
+                self.test_device_name
+            )));
+        }
+
+        let dm_name = DmName::new(self.test_device_name.as_str())?;
+        let dev_id = DevId::Name(dm_name);
+
+        self.dm_context
+            .device_suspend(&dev_id, DmOptions::new().set_flags(DmFlags::DM_SUSPEND))?;
+        let (target_type, params) = backing_target(&self.backing_device, self.delay);
+        let load_result = self
+            .dm_context
+            .table_load(&dev_id, &[(0, new_size, target_type, params)]);
+        self.dm_context.device_suspend(&dev_id, &DmOptions::new())?;
+        load_result?;
+
+        self.size = new_size;
+        Ok(new_size)
+    }
 }
 
 impl Drop for FailDevice {
@@ -3,8 +3,11 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use std::{
+    cmp::min,
     fs::{File, OpenOptions},
-    io::{Read, Write},
+    io::{Read, Seek, SeekFrom, Write},
+    iter::once,
+    os::unix::fs::OpenOptionsExt,
     path::{Path, PathBuf},
     thread::sleep,
     time::Duration,
@@ -15,7 +18,8 @@ use data_encoding::BASE32_NOPAD;
 use serde_json::{Map, Value};
 
 use devicemapper::{
-    Bytes, DmDevice, DmName, DmUuid, Sectors, ThinDev, ThinDevId, ThinPoolDev, ThinStatus,
+    Bytes, DevId, DmDevice, DmName, DmUuid, Sectors, ThinDev, ThinDevId, ThinPoolDev, ThinStatus,
+    IEC,
 };
 
 use nix::{
@@ -26,15 +30,24 @@ use nix::{
 use crate::{
     engine::{
         engine::Filesystem,
+        shared::LATENCY_HISTOGRAM_BOUNDARIES,
         strat_engine::{
-            cmd::{create_fs, set_uuid, udev_settle, xfs_growfs},
+            cmd::{
+                create_fs, explain_create_fs, set_uuid, xfs_check, xfs_data_size, xfs_growfs,
+                xfs_repair,
+            },
+            device::{read_ranges, DIRECT_IO_ALIGN},
             devlinks,
-            dm::get_dm,
+            dm::{get_dm, retry_dm, udev_settle_dm_device},
             names::{format_thin_ids, ThinRole},
+            pool::adopt,
             serde_structs::FilesystemSave,
             thinpool::{thinpool::DATA_LOWATER, DATA_BLOCK_SIZE},
         },
-        types::{FilesystemUuid, Name, PoolUuid, StratisUuid},
+        types::{
+            FilesystemCheck, FilesystemIoStats, FilesystemUuid, Name, PlannedAction, PoolUuid,
+            ReadWork, Reconciliation, StratisUuid, XfsParams,
+        },
     },
     stratis::{StratisError, StratisResult},
 };
@@ -45,10 +58,46 @@ const TEMP_MNT_POINT_PREFIX: &str = "stratis_mp_";
 /// expansion check is triggered by crossing the data low water mark for the thin pool.
 pub const FILESYSTEM_LOWATER: Sectors = Sectors(4 * (DATA_LOWATER.0 * DATA_BLOCK_SIZE.0));
 
+/// The largest amount by which the thin device may exceed the XFS filesystem
+/// without the filesystem being reported as pending a grow. xfs_growfs does
+/// not add a final allocation group that would be too small, so a grown
+/// filesystem may still leave a little of the thin device unused.
+pub const GROW_PENDING_SLACK: Sectors = Sectors(2 * IEC::Ki); // 1 MiB
+
+/// The amount read from the thin device at a time when scrubbing. This is
+/// also the granularity with which unreadable ranges are reported.
+const SCRUB_CHUNK_SIZE: Sectors = Sectors(2 * IEC::Ki); // 1 MiB
+
+/// The amount read from the thin device at a time when warming the cache.
+const WARM_CACHE_CHUNK_SIZE: Sectors = Sectors(2 * IEC::Ki); // 1 MiB
+
+/// The amount read from the source and written to the new thin device at a
+/// time when copying a filesystem.
+const COPY_CHUNK_SIZE: Sectors = Sectors(2 * IEC::Ki); // 1 MiB
+
+/// The program ID of the dm-stats regions created by stratisd, which
+/// distinguishes them from regions created by other programs.
+const STATS_PROGRAM_ID: &str = "stratisd";
+
+/// The number of counters that dm-stats prints for an area before its
+/// histogram, and the positions of those that the latency is derived from.
+/// Since the regions are created with precise timestamps, times are given
+/// in nanoseconds.
+const DM_STATS_COUNTERS: usize = 13;
+const DM_STATS_READS: usize = 0;
+const DM_STATS_READ_NS: usize = 3;
+const DM_STATS_WRITES: usize = 4;
+const DM_STATS_WRITE_NS: usize = 7;
+
 #[derive(Debug)]
 pub struct StratFilesystem {
     thin_dev: ThinDev,
     created: DateTime<Utc>,
+    mkfs_params: Option<XfsParams>,
+    origin: Option<FilesystemUuid>,
+    cow_alert_threshold: Option<Bytes>,
+    min_free_guarantee: Option<Bytes>,
+    sequence_number: u64,
 }
 
 impl StratFilesystem {
@@ -58,14 +107,20 @@ impl StratFilesystem {
         thinpool_dev: &ThinPoolDev,
         size: Sectors,
         id: ThinDevId,
+        mkfs_params: Option<&XfsParams>,
     ) -> StratisResult<(FilesystemUuid, StratFilesystem)> {
         let fs_uuid = FilesystemUuid::new_v4();
         let (dm_name, dm_uuid) = format_thin_ids(pool_uuid, ThinRole::Filesystem(fs_uuid));
         let mut thin_dev =
             ThinDev::new(get_dm(), &dm_name, Some(&dm_uuid), size, thinpool_dev, id)?;
 
-        if let Err(err) = create_fs(&thin_dev.devnode(), Some(StratisUuid::Fs(fs_uuid)), false) {
-            udev_settle().unwrap_or_else(|err| {
+        if let Err(err) = create_fs(
+            &thin_dev.devnode(),
+            Some(StratisUuid::Fs(fs_uuid)),
+            false,
+            mkfs_params,
+        ) {
+            udev_settle_dm_device(&dm_name).unwrap_or_else(|err| {
                 warn!("{}", err);
                 sleep(Duration::from_secs(5));
             });
@@ -87,10 +142,139 @@ impl StratFilesystem {
             StratFilesystem {
                 thin_dev,
                 created: Utc::now(),
+                mkfs_params: mkfs_params.copied(),
+                origin: None,
+                cow_alert_threshold: None,
+                min_free_guarantee: None,
+                sequence_number: 0,
             },
         ))
     }
 
+    /// The actions that initialize() would perform with the same arguments,
+    /// for a filesystem with the given UUID. The creation of the thin device
+    /// is expressed as the equivalent dmsetup commands.
+    pub fn explain_initialize(
+        pool_uuid: PoolUuid,
+        fs_uuid: FilesystemUuid,
+        thinpool_dev: &ThinPoolDev,
+        size: Sectors,
+        id: ThinDevId,
+        mkfs_params: Option<&XfsParams>,
+    ) -> Vec<PlannedAction> {
+        let (dm_name, dm_uuid) = format_thin_ids(pool_uuid, ThinRole::Filesystem(fs_uuid));
+        vec![
+            PlannedAction {
+                program: "dmsetup".to_string(),
+                args: vec![
+                    "message".to_string(),
+                    thinpool_dev.name().to_string(),
+                    "0".to_string(),
+                    format!("create_thin {}", id),
+                ],
+            },
+            PlannedAction {
+                program: "dmsetup".to_string(),
+                args: vec![
+                    "create".to_string(),
+                    dm_name.to_string(),
+                    "--uuid".to_string(),
+                    dm_uuid.to_string(),
+                    "--table".to_string(),
+                    format!("0 {} thin {} {}", *size, thinpool_dev.device(), id),
+                ],
+            },
+            explain_create_fs(
+                &Path::new("/dev/mapper").join(dm_name.to_string()),
+                Some(StratisUuid::Fs(fs_uuid)),
+                false,
+                mkfs_params,
+            ),
+        ]
+    }
+
+    /// Create a StratFilesystem on top of a new, empty ThinDev of the size of
+    /// the source filesystem, into which the source is to be copied by the
+    /// work returned by copy_work(). The source filesystem must be unmounted.
+    pub fn initialize_copy(
+        pool_uuid: PoolUuid,
+        thinpool_dev: &ThinPoolDev,
+        id: ThinDevId,
+        source: &dyn Filesystem,
+    ) -> StratisResult<(FilesystemUuid, StratFilesystem)> {
+        if source.is_mounted()? {
+            return Err(StratisError::Msg(format!(
+                "Filesystem on {} is mounted; it must be unmounted to be copied",
+                source.devnode().display()
+            )));
+        }
+
+        let fs_uuid = FilesystemUuid::new_v4();
+        let (dm_name, dm_uuid) = format_thin_ids(pool_uuid, ThinRole::Filesystem(fs_uuid));
+        let thin_dev = ThinDev::new(
+            get_dm(),
+            &dm_name,
+            Some(&dm_uuid),
+            source.size(),
+            thinpool_dev,
+            id,
+        )?;
+
+        Ok((
+            fs_uuid,
+            StratFilesystem {
+                thin_dev,
+                created: source.created(),
+                mkfs_params: None,
+                origin: None,
+                cow_alert_threshold: None,
+                min_free_guarantee: None,
+                sequence_number: 0,
+            },
+        ))
+    }
+
+    /// Work which copies the given ranges of the source device, which are
+    /// those mapped to data in the source's thin pool, to the same offsets of
+    /// this filesystem's thin device, which was created by
+    /// initialize_copy(). Chunks which contain only zeroes are not written,
+    /// so that space is only allocated in the thin pool for data that is
+    /// actually stored. The copy has the XFS UUID of the source, so it is
+    /// changed to fs_uuid once the data is copied. claim, the source opened
+    /// exclusively, is held until the work is done.
+    pub fn copy_work(
+        &self,
+        fs_uuid: FilesystemUuid,
+        source: PathBuf,
+        claim: File,
+        ranges: Vec<(Sectors, Sectors)>,
+    ) -> ReadWork<()> {
+        let devnode = self.devnode();
+        Box::new(move |progress: &mut dyn FnMut(Sectors, Sectors)| {
+            let _claim = claim;
+            let mut dest = OpenOptions::new()
+                .write(true)
+                .custom_flags(libc::O_DIRECT)
+                .open(&devnode)?;
+            read_ranges(
+                &source,
+                &ranges,
+                COPY_CHUNK_SIZE,
+                &mut |(offset, _), result| {
+                    let data = result?;
+                    if data.iter().any(|b| *b != 0) {
+                        dest.seek(SeekFrom::Start(convert_int!(*offset.bytes(), u128, u64)?))?;
+                        dest.write_all(data)?;
+                    }
+                    Ok(())
+                },
+                progress,
+            )?;
+            dest.sync_all()?;
+            set_uuid(&devnode, fs_uuid)
+        })
+    }
+
     /// Build a StratFilesystem that includes the ThinDev and related info.
     pub fn setup(
         pool_uuid: PoolUuid,
@@ -109,11 +293,85 @@ impl StratFilesystem {
         Ok(StratFilesystem {
             thin_dev,
             created: Utc.timestamp(fssave.created as i64, 0),
+            mkfs_params: fssave.mkfs_params,
+            origin: fssave.origin,
+            cow_alert_threshold: fssave.cow_alert_threshold.map(|t| Bytes(u128::from(t))),
+            min_free_guarantee: fssave.min_free_guarantee.map(|g| Bytes(u128::from(g))),
+            sequence_number: fssave.sequence_number.unwrap_or(0),
+        })
+    }
+
+    /// Work which reads the given ranges of the thin device, which are those
+    /// mapped to data in the thin pool, so that any media errors on the
+    /// underlying devices are reported. The work returns the ranges that
+    /// could not be read as (start, length) pairs.
+    pub fn scrub_work(&self, ranges: Vec<(Sectors, Sectors)>) -> ReadWork<Vec<(Sectors, Sectors)>> {
+        let devnode = self.devnode();
+        let device = self.thin_dev.device();
+        Box::new(move |progress: &mut dyn FnMut(Sectors, Sectors)| {
+            let mut unreadable: Vec<(Sectors, Sectors)> = Vec::new();
+            read_ranges(
+                &devnode,
+                &ranges,
+                SCRUB_CHUNK_SIZE,
+                &mut |(offset, length), result| {
+                    if let Err(e) = result {
+                        warn!(
+                            "Failed to read {} starting at {} from filesystem thin device {}: {}",
+                            length, offset, device, e
+                        );
+                        match unreadable.last_mut() {
+                            Some((start, len)) if *start + *len == offset => *len += length,
+                            _ => unreadable.push((offset, length)),
+                        }
+                    }
+                    Ok(())
+                },
+                progress,
+            )?;
+            Ok(unreadable)
+        })
+    }
+
+    /// Work which reads the given ranges of the thin device, which are those
+    /// mapped to data in the thin pool, from the start, so that the data read
+    /// may be promoted to the pool's cache. At most budget sectors are read.
+    /// The work returns the number of sectors read.
+    pub fn warm_cache_work(
+        &self,
+        ranges: Vec<(Sectors, Sectors)>,
+        budget: Sectors,
+    ) -> ReadWork<Sectors> {
+        // Direct I/O requires that the ranges remain aligned.
+        let align = Bytes(DIRECT_IO_ALIGN as u128).sectors();
+        let mut remaining = Sectors(*budget / *align * *align);
+        let ranges = ranges
+            .into_iter()
+            .map(|(start, length)| {
+                let length = min(length, remaining);
+                remaining -= length;
+                (start, length)
+            })
+            .take_while(|&(_, length)| length > Sectors(0))
+            .collect::<Vec<_>>();
+        let total = ranges.iter().map(|&(_, length)| length).sum::<Sectors>();
+
+        let devnode = self.devnode();
+        Box::new(move |progress: &mut dyn FnMut(Sectors, Sectors)| {
+            read_ranges(
+                &devnode,
+                &ranges,
+                WARM_CACHE_CHUNK_SIZE,
+                &mut |_, result| result.map(|_| ()).map_err(StratisError::from),
+                progress,
+            )?;
+            Ok(total)
         })
     }
 
     /// Send a synthetic udev change event to the devicemapper device representing
-    /// the filesystem.
+    /// the filesystem. If stratisd maintains the symlinks itself, also create
+    /// or replace the symlink.
     pub fn udev_fs_change(&self, pool_name: &str, fs_uuid: FilesystemUuid, fs_name: &str) {
         fn udev_change_event(
             thin_dev: &ThinDev,
@@ -148,6 +406,131 @@ impl StratFilesystem {
         if let Err(e) = udev_change_event(&self.thin_dev, pool_name, fs_uuid, fs_name) {
             warn!("Failed to notify udev to perform symlink operation: {}", e);
         }
+        devlinks::filesystem_added(pool_name, fs_name, &self.thin_dev.devnode());
+    }
+
+    /// Send a dm-stats message to the thin device and return the output.
+    fn stats_msg(&self, msg: &str) -> StratisResult<String> {
+        let (_, output) =
+            retry_dm(|| get_dm().target_msg(&DevId::Name(self.thin_dev.name()), None, msg))?;
+        Ok(output.unwrap_or_default())
+    }
+
+    /// The IDs of the dm-stats regions that stratisd has created on the thin
+    /// device.
+    fn stats_regions(&self) -> StratisResult<Vec<u64>> {
+        self.stats_msg(&format!("@stats_list {}", STATS_PROGRAM_ID))?
+            .lines()
+            .map(|line| {
+                line.split(':')
+                    .next()
+                    .and_then(|id| id.trim().parse::<u64>().ok())
+                    .ok_or_else(|| {
+                        StratisError::Msg(format!(
+                            "Unexpected dm-stats region description \"{}\"",
+                            line
+                        ))
+                    })
+            })
+            .collect()
+    }
+
+    /// Create a dm-stats region that covers the whole thin device and
+    /// collects a latency histogram, unless one exists already.
+    pub fn enable_detailed_stats(&self) -> StratisResult<()> {
+        if !self.stats_regions()?.is_empty() {
+            return Ok(());
+        }
+        self.stats_msg(&format!(
+            "@stats_create - /1 2 precise_timestamps histogram:{} {}",
+            LATENCY_HISTOGRAM_BOUNDARIES
+                .iter()
+                .map(|b| b.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            STATS_PROGRAM_ID
+        ))?;
+        Ok(())
+    }
+
+    /// Remove all dm-stats regions that stratisd has created on the thin
+    /// device.
+    pub fn disable_detailed_stats(&self) -> StratisResult<()> {
+        for region in self.stats_regions()? {
+            self.stats_msg(&format!("@stats_delete {}", region))?;
+        }
+        Ok(())
+    }
+
+    /// The counters and the histogram counts of the dm-stats region that
+    /// stratisd has created on the thin device.
+    fn stats_print(&self) -> StratisResult<(Vec<u64>, Vec<u64>)> {
+        let region = self.stats_regions()?.first().copied().ok_or_else(|| {
+            StratisError::Msg(format!(
+                "No detailed statistics are collected for filesystem thin device {}",
+                self.thin_dev.device()
+            ))
+        })?;
+
+        // The region has a single area, which is printed on a single line:
+        // the extent of the area, the counters, and the histogram counts
+        // separated by colons.
+        let stats = self.stats_msg(&format!("@stats_print {}", region))?;
+        let mut fields = stats.split_whitespace().skip(1).collect::<Vec<_>>();
+        let counts = fields.pop().and_then(|histogram| {
+            histogram
+                .split(':')
+                .map(|count| count.parse::<u64>())
+                .collect::<Result<Vec<_>, _>>()
+                .ok()
+        });
+        let counters = fields
+            .into_iter()
+            .map(|counter| counter.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .ok();
+        match (counters, counts) {
+            (Some(counters), Some(counts))
+                if counters.len() >= DM_STATS_COUNTERS
+                    && counts.len() == LATENCY_HISTOGRAM_BOUNDARIES.len() + 1 =>
+            {
+                Ok((counters, counts))
+            }
+            _ => Err(StratisError::Msg(format!(
+                "Could not parse dm-stats output \"{}\" for thin device {}",
+                stats.trim(),
+                self.thin_dev.device()
+            ))),
+        }
+    }
+
+    /// The latency histogram of the I/O done on the thin device since
+    /// detailed statistics were enabled, as a list of pairs of the lower
+    /// bound of each bucket in nanoseconds and the number of I/Os in it.
+    pub fn latency_histogram(&self) -> StratisResult<Vec<(u64, u64)>> {
+        let (_, counts) = self.stats_print()?;
+        Ok(once(0)
+            .chain(LATENCY_HISTOGRAM_BOUNDARIES.iter().copied())
+            .zip(counts)
+            .collect())
+    }
+
+    /// The totals of the I/O done on the thin device since detailed
+    /// statistics were enabled: the number of reads and the time in
+    /// nanoseconds spent on them, and the number of writes and the time in
+    /// nanoseconds spent on them; and the counts of the buckets of its
+    /// latency histogram.
+    pub fn latency_totals(&self) -> StratisResult<((u64, u64, u64, u64), Vec<u64>)> {
+        let (counters, counts) = self.stats_print()?;
+        Ok((
+            (
+                counters[DM_STATS_READS],
+                counters[DM_STATS_READ_NS],
+                counters[DM_STATS_WRITES],
+                counters[DM_STATS_WRITE_NS],
+            ),
+            counts,
+        ))
     }
 
     /// Create a snapshot of the filesystem. Return the resulting filesystem/ThinDev
@@ -155,11 +538,13 @@ impl StratFilesystem {
     /// snapshot_dmname for the new name of the ThinDev allocated for the snapshot.
     /// Mounting a filesystem with a duplicate UUID would require special handling,
     /// so snapshot_fs_uuid is used to update the new snapshot filesystem so it has
-    /// a unique UUID.
+    /// a unique UUID. The snapshot records origin_uuid, the UUID of this
+    /// filesystem, as its origin.
     #[allow(clippy::too_many_arguments)]
     pub fn snapshot(
         &self,
         thin_pool: &ThinPoolDev,
+        origin_uuid: FilesystemUuid,
         snapshot_name: &str,
         snapshot_dm_name: &DmName,
         snapshot_dm_uuid: Option<&DmUuid>,
@@ -189,12 +574,18 @@ impl StratFilesystem {
                         .prefix(TEMP_MNT_POINT_PREFIX)
                         .tempdir()?;
                     // Mount the snapshot with the "nouuid" option. mount
-                    // will fail due to duplicate UUID otherwise.
+                    // will fail due to duplicate UUID otherwise. Access
+                    // times are not updated, so that the mount writes
+                    // nothing beyond the log replay to the snapshot.
+                    // FIXME: this temporary mount is the only mount that
+                    // stratisd makes of a filesystem. A per-filesystem
+                    // access time policy, recorded in the MDV and applied
+                    // on mount, requires daemon-managed mounts.
                     mount(
                         Some(&thin_dev.devnode()),
                         tmp_dir.path(),
                         Some("xfs"),
-                        MsFlags::empty(),
+                        MsFlags::MS_NOATIME,
                         Some("nouuid"),
                     )?;
                     umount(tmp_dir.path())?;
@@ -204,6 +595,11 @@ impl StratFilesystem {
                 Ok(StratFilesystem {
                     thin_dev,
                     created: Utc::now(),
+                    mkfs_params: self.mkfs_params,
+                    origin: Some(origin_uuid),
+                    cow_alert_threshold: None,
+                    min_free_guarantee: None,
+                    sequence_number: 0,
                 })
             }
             Err(e) => Err(StratisError::Msg(format!(
@@ -225,7 +621,7 @@ impl StratFilesystem {
                         let mut table = self.thin_dev.table().table.clone();
                         table.length =
                             self.thin_dev.size() + Self::extend_size(self.thin_dev.size());
-                        if self.thin_dev.set_table(get_dm(), table).is_err() {
+                        if retry_dm(|| self.thin_dev.set_table(get_dm(), table.clone())).is_err() {
                             return Ok(false);
                         }
                         if xfs_growfs(mount_point).is_err() {
@@ -247,6 +643,42 @@ impl StratFilesystem {
         }
     }
 
+    /// Set the size of the thin device under the filesystem to exactly the
+    /// target size, which must not be less than the current size. If grow_fs
+    /// is true, also grow the XFS filesystem, which must be mounted, to fill
+    /// the thin device.
+    /// Returns true if the size of the thin device was changed.
+    pub fn set_size(&mut self, target_size: Sectors, grow_fs: bool) -> StratisResult<bool> {
+        let mount_point = if grow_fs {
+            match self.mount_points()?.first() {
+                Some(mount_point) => Some(mount_point.to_owned()),
+                None => {
+                    return Err(StratisError::Msg(format!(
+                        "Filesystem thin device {} must be mounted to grow the filesystem",
+                        self.thin_dev.device()
+                    )));
+                }
+            }
+        } else {
+            None
+        };
+
+        let changed = if self.thin_dev.size() == target_size {
+            false
+        } else {
+            let mut table = self.thin_dev.table().table.clone();
+            table.length = target_size;
+            retry_dm(|| self.thin_dev.set_table(get_dm(), table.clone()))?;
+            true
+        };
+
+        if let Some(mount_point) = mount_point {
+            xfs_growfs(&mount_point)?;
+        }
+
+        Ok(changed)
+    }
+
     /// Return an extend size for the thindev under the filesystem
     /// TODO: returning the current size will double the space provisioned to
     /// the thin device.  We should determine if this is a reasonable value.
@@ -256,7 +688,7 @@ impl StratFilesystem {
 
     /// Tear down the filesystem.
     pub fn teardown(&mut self) -> StratisResult<()> {
-        self.thin_dev.teardown(get_dm())?;
+        retry_dm(|| self.thin_dev.teardown(get_dm()))?;
         Ok(())
     }
 
@@ -273,16 +705,145 @@ impl StratFilesystem {
             thin_id: self.thin_dev.id(),
             size: self.thin_dev.size(),
             created: self.created.timestamp() as u64,
+            mkfs_params: self.mkfs_params,
+            deleted: None,
+            origin: self.origin,
+            cow_alert_threshold: self.cow_alert_threshold.map(|t| *t as u64),
+            min_free_guarantee: self.min_free_guarantee.map(|g| *g as u64),
+            sequence_number: Some(self.sequence_number).filter(|n| *n != 0),
+        }
+    }
+
+    /// The UUID of the filesystem of which this filesystem was created as a
+    /// snapshot, if it is a snapshot. The origin may since have been
+    /// destroyed.
+    pub fn origin(&self) -> Option<FilesystemUuid> {
+        self.origin
+    }
+
+    /// Set the copy-on-write alert threshold of the filesystem.
+    /// Returns true if the threshold was changed.
+    pub fn set_cow_alert_threshold(&mut self, threshold: Option<Bytes>) -> bool {
+        if self.cow_alert_threshold == threshold {
+            return false;
+        }
+        self.cow_alert_threshold = threshold;
+        true
+    }
+
+    /// Set the minimum free space guarantee of the filesystem.
+    /// Returns true if the guarantee was changed.
+    pub fn set_min_free_guarantee(&mut self, guarantee: Option<Bytes>) -> bool {
+        if self.min_free_guarantee == guarantee {
+            return false;
+        }
+        self.min_free_guarantee = guarantee;
+        true
+    }
+
+    /// Set the sequence number of the filesystem. This is done once, when
+    /// the filesystem is created.
+    pub fn set_sequence_number(&mut self, sequence_number: u64) {
+        self.sequence_number = sequence_number;
+    }
+
+    /// Adopt the settings in the given record of the filesystem wherever
+    /// they differ from those held in memory. Differences in the thin device
+    /// of the filesystem are reported but not corrected. The name and the
+    /// deletion state of the filesystem are left to the thin pool.
+    pub fn reconcile(&mut self, record: &FilesystemSave) -> Reconciliation {
+        let FilesystemSave {
+            name: _,
+            uuid: _,
+            thin_id,
+            size,
+            created,
+            mkfs_params,
+            deleted: _,
+            origin,
+            cow_alert_threshold,
+            min_free_guarantee,
+            sequence_number,
+        } = record;
+        let mut reconciliation = Reconciliation::default();
+
+        for (what, differs) in &[
+            ("thin device ID", *thin_id != self.thin_dev.id()),
+            ("size", *size != self.thin_dev.size()),
+        ] {
+            if *differs {
+                reconciliation.unresolved.push(format!(
+                    "{}: the metadata on disk differs from the filesystem as it is set up",
+                    what
+                ));
+            }
+        }
+
+        let corrected = &mut reconciliation.corrected;
+        // The creation time is recorded to the second.
+        if self.created.timestamp() != *created as i64 {
+            adopt(
+                "creation time",
+                &mut self.created,
+                Utc.timestamp(*created as i64, 0),
+                corrected,
+            );
+        }
+        adopt(
+            "mkfs parameters",
+            &mut self.mkfs_params,
+            *mkfs_params,
+            corrected,
+        );
+        adopt("origin", &mut self.origin, *origin, corrected);
+        adopt(
+            "copy-on-write alert threshold",
+            &mut self.cow_alert_threshold,
+            cow_alert_threshold.map(|t| Bytes(u128::from(t))),
+            corrected,
+        );
+        adopt(
+            "minimum free space guarantee",
+            &mut self.min_free_guarantee,
+            min_free_guarantee.map(|g| Bytes(u128::from(g))),
+            corrected,
+        );
+        adopt(
+            "sequence number",
+            &mut self.sequence_number,
+            sequence_number.unwrap_or(0),
+            corrected,
+        );
+        reconciliation
+    }
+
+    /// The part of the free space guarantee of the filesystem for which the
+    /// pool must still have free space: the guarantee, or the amount of
+    /// the filesystem that is not yet backed by data, if that is less.
+    pub fn unbacked_guarantee(&self) -> StratisResult<Bytes> {
+        self.unbacked_guarantee_at(self.thindev_size())
+    }
+
+    /// The space that the filesystem could claim under its minimum free
+    /// space guarantee if its thin device had the given size.
+    pub fn unbacked_guarantee_at(&self, size: Sectors) -> StratisResult<Bytes> {
+        match self.min_free_guarantee {
+            Some(guarantee) => {
+                let size = size.bytes();
+                let used = min(self.used()?, size);
+                Ok(min(guarantee, size - used))
+            }
+            None => Ok(Bytes(0)),
         }
     }
 
     pub fn suspend(&mut self, flush: bool) -> StratisResult<()> {
-        self.thin_dev.suspend(get_dm(), flush)?;
+        retry_dm(|| self.thin_dev.suspend(get_dm(), flush))?;
         Ok(())
     }
 
     pub fn resume(&mut self) -> StratisResult<()> {
-        self.thin_dev.resume(get_dm())?;
+        retry_dm(|| self.thin_dev.resume(get_dm()))?;
         Ok(())
     }
 
@@ -314,9 +875,52 @@ impl StratFilesystem {
         Ok(ret_vec)
     }
 
+    /// Unmount this filesystem from every place where it is mounted.
+    pub fn unmount(&self) -> StratisResult<()> {
+        for mount_point in self.mount_points()? {
+            umount(&mount_point)?;
+        }
+        Ok(())
+    }
+
+    /// Return an error if this filesystem is mounted anywhere.
+    fn check_unmounted(&self) -> StratisResult<()> {
+        let mount_points = self.mount_points()?;
+        if mount_points.is_empty() {
+            Ok(())
+        } else {
+            Err(StratisError::Msg(format!(
+                "Filesystem thin device {} is mounted at {}; it must be unmounted",
+                self.thin_dev.device(),
+                mount_points
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )))
+        }
+    }
+
     pub fn thindev_size(&self) -> Sectors {
         self.thin_dev.size()
     }
+
+    /// The size of the data section of the XFS filesystem, which is less
+    /// than the size of the thin device if the filesystem has not been grown
+    /// since the thin device was extended.
+    pub fn xfs_size(&self) -> StratisResult<Bytes> {
+        xfs_data_size(&self.thin_dev.devnode())
+    }
+
+    /// The id of the thin device of the filesystem within the thin pool.
+    pub fn thin_id(&self) -> ThinDevId {
+        self.thin_dev.id()
+    }
+
+    /// Wait for udev before the thin device of the filesystem is removed.
+    pub fn udev_settle(&self) -> StratisResult<()> {
+        udev_settle_dm_device(self.thin_dev.name())
+    }
 }
 
 impl Filesystem for StratFilesystem {
@@ -328,10 +932,79 @@ impl Filesystem for StratFilesystem {
         self.created
     }
 
+    fn size(&self) -> Sectors {
+        self.thin_dev.size()
+    }
+
+    fn is_mounted(&self) -> StratisResult<bool> {
+        Ok(!self.mount_points()?.is_empty())
+    }
+
     fn path_to_mount_filesystem(&self, pool_name: &str, fs_name: &str) -> PathBuf {
         devlinks::filesystem_mount_path(pool_name, fs_name)
     }
 
+    fn io_stats(&self) -> StratisResult<FilesystemIoStats> {
+        let device = self.thin_dev.device();
+        let stat_file = [
+            "/sys/dev/block",
+            &format!("{}:{}", device.major, device.minor),
+            "stat",
+        ]
+        .iter()
+        .collect::<PathBuf>();
+        let mut stat = String::new();
+        File::open(&stat_file)?.read_to_string(&mut stat)?;
+
+        // The fields are described in the kernel's block/stat documentation.
+        // Sector counts are always in units of 512 bytes.
+        let fields = stat
+            .split_whitespace()
+            .map(|field| field.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| {
+                StratisError::Msg(format!("Could not parse {}: {}", stat_file.display(), e))
+            })?;
+        if fields.len() < 7 {
+            return Err(StratisError::Msg(format!(
+                "{} has {} fields, expected at least 7",
+                stat_file.display(),
+                fields.len()
+            )));
+        }
+
+        Ok(FilesystemIoStats {
+            read_bytes: Sectors(fields[2]).bytes(),
+            write_bytes: Sectors(fields[6]).bytes(),
+            read_ops: fields[0],
+            write_ops: fields[4],
+        })
+    }
+
+    fn check_consistency(&self) -> StratisResult<ReadWork<FilesystemCheck>> {
+        self.check_unmounted()?;
+        let devnode = self.devnode();
+        Ok(Box::new(move |_: &mut dyn FnMut(Sectors, Sectors)| {
+            xfs_check(&devnode)
+        }))
+    }
+
+    // Should the filesystem be mounted once the work has started,
+    // xfs_repair refuses to modify it.
+    fn repair(&self) -> StratisResult<ReadWork<bool>> {
+        self.check_unmounted()?;
+        let devnode = self.devnode();
+        let device = self.thin_dev.device();
+        Ok(Box::new(move |_: &mut dyn FnMut(Sectors, Sectors)| {
+            if xfs_check(&devnode)?.consistent {
+                return Ok(false);
+            }
+            xfs_repair(&devnode)?;
+            info!("Repaired filesystem on thin device {}", device);
+            Ok(true)
+        }))
+    }
+
     fn used(&self) -> StratisResult<Bytes> {
         match self.thin_dev.status(get_dm())? {
             ThinStatus::Working(wk_status) => Ok(wk_status.nr_mapped_sectors.bytes()),
@@ -348,6 +1021,18 @@ impl Filesystem for StratFilesystem {
             }
         }
     }
+
+    fn cow_alert_threshold(&self) -> Option<Bytes> {
+        self.cow_alert_threshold
+    }
+
+    fn min_free_guarantee(&self) -> Option<Bytes> {
+        self.min_free_guarantee
+    }
+
+    fn sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
 }
 
 /// Return total bytes allocated to the filesystem, total bytes used by data/metadata
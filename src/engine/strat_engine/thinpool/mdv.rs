@@ -5,25 +5,34 @@
 // Manage the linear volume that stores metadata on pool levels 5-7.
 
 use std::{
+    collections::HashSet,
     convert::From,
-    fs::{create_dir, create_dir_all, read_dir, remove_dir, remove_file, rename, OpenOptions},
+    fs::{
+        create_dir, create_dir_all, read_dir, remove_dir, remove_dir_all, remove_file, rename,
+        OpenOptions,
+    },
     io::{prelude::*, ErrorKind},
     path::{Path, PathBuf},
 };
 
-use nix::mount::{mount, umount, MsFlags};
+use nix::{
+    fcntl::{renameat2, RenameFlags},
+    mount::{mount, umount, MsFlags},
+};
 
 use devicemapper::{DmDevice, LinearDev, LinearDevTargetParams, TargetLine};
 
 use crate::{
     engine::{
         strat_engine::{
-            cmd::create_fs, dm::get_dm, serde_structs::FilesystemSave,
+            cmd::create_fs,
+            dm::{get_dm, retry_dm},
+            serde_structs::{FilesystemSave, OperationLogEntrySave},
             thinpool::filesystem::StratFilesystem,
         },
         types::{FilesystemUuid, Name, PoolUuid, StratisUuid},
     },
-    stratis::StratisResult,
+    stratis::{StratisError, StratisResult},
 };
 
 // TODO: Monitor fs size and extend linear and fs if needed
@@ -31,6 +40,14 @@ use crate::{
 
 const RUN_DIR: &str = "/run/stratisd";
 const FILESYSTEM_DIR: &str = "filesystems";
+/// The directory in which the filesystem records are rewritten when the MDV
+/// is compacted, before it is exchanged with FILESYSTEM_DIR.
+const COMPACT_DIR: &str = "filesystems.compact";
+const OPERATION_LOG_FILE: &str = "operation_log.json";
+
+/// The maximum number of entries kept in the operation log. When an entry
+/// is appended to a full log, the oldest entry is discarded.
+const OPERATION_LOG_MAX_ENTRIES: usize = 1024;
 
 #[derive(Debug)]
 pub struct MetadataVol {
@@ -90,7 +107,12 @@ impl<'a> Drop for MountedMDV<'a> {
 impl MetadataVol {
     /// Initialize a new Metadata Volume.
     pub fn initialize(pool_uuid: PoolUuid, dev: LinearDev) -> StratisResult<MetadataVol> {
-        create_fs(&dev.devnode(), Some(StratisUuid::Pool(pool_uuid)), true)?;
+        create_fs(
+            &dev.devnode(),
+            Some(StratisUuid::Pool(pool_uuid)),
+            true,
+            None,
+        )?;
         MetadataVol::setup(pool_uuid, dev)
     }
 
@@ -112,6 +134,13 @@ impl MetadataVol {
             }
 
             let _ = remove_temp_files(&filesystem_path)?;
+
+            // A compaction was interrupted. FILESYSTEM_DIR is complete
+            // whether or not the directories were exchanged.
+            let compact_path = mount.mount_pt().join(COMPACT_DIR);
+            if compact_path.exists() {
+                remove_dir_all(&compact_path)?;
+            }
         }
 
         Ok(mdv)
@@ -128,12 +157,17 @@ impl MetadataVol {
         uuid: FilesystemUuid,
         fs: &StratFilesystem,
     ) -> StratisResult<()> {
-        let data = serde_json::to_string(&fs.record(name, uuid))?;
+        self.save_fs_record(&fs.record(name, uuid))
+    }
+
+    /// Write the given record of a filesystem to the MDV, replacing any
+    /// existing record of the filesystem.
+    pub fn save_fs_record(&self, record: &FilesystemSave) -> StratisResult<()> {
+        let data = serde_json::to_string(record)?;
         let path = self
             .mount_pt
             .join(FILESYSTEM_DIR)
-            .join(uuid_to_string!(uuid))
-            .with_extension("json");
+            .join(record_file_name(record.uuid));
 
         let temp_path = path.with_extension("temp");
 
@@ -161,8 +195,7 @@ impl MetadataVol {
         let fs_path = self
             .mount_pt
             .join(FILESYSTEM_DIR)
-            .join(uuid_to_string!(fs_uuid))
-            .with_extension("json");
+            .join(record_file_name(fs_uuid));
 
         let _mount = MountedMDV::mount(self)?;
 
@@ -198,22 +231,111 @@ impl MetadataVol {
         Ok(filesystems)
     }
 
+    /// Rewrite the filesystem records on the MDV so that only the given
+    /// records remain, discarding records of filesystems that no longer
+    /// exist and leftover temp files, and letting XFS free the directory
+    /// blocks that accumulated through many creations and removals.
+    /// The records are written to a new directory and read back to verify
+    /// them before the new directory is exchanged with the current one in a
+    /// single atomic rename, so that the MDV holds a complete set of records
+    /// at all times.
+    /// Returns the number of entries discarded.
+    pub fn compact(&self, records: &[FilesystemSave]) -> StratisResult<u64> {
+        let mount = MountedMDV::mount(self)?;
+        let filesystem_path = mount.mount_pt().join(FILESYSTEM_DIR);
+        let compact_path = mount.mount_pt().join(COMPACT_DIR);
+
+        if compact_path.exists() {
+            remove_dir_all(&compact_path)?;
+        }
+        create_dir(&compact_path)?;
+        if let Err(err) = write_and_verify_records(&compact_path, records) {
+            if let Err(e) = remove_dir_all(&compact_path) {
+                warn!("Could not remove incomplete compacted MDV directory: {}", e);
+            }
+            return Err(err);
+        }
+
+        let kept = records
+            .iter()
+            .map(|record| record_file_name(record.uuid))
+            .collect::<HashSet<_>>();
+        let discarded = read_dir(&filesystem_path)?
+            .filter_map(|e| e.ok())
+            .filter(|e| !kept.contains(&e.file_name().to_string_lossy().into_owned()))
+            .count() as u64;
+
+        renameat2(
+            None,
+            &compact_path,
+            None,
+            &filesystem_path,
+            RenameFlags::RENAME_EXCHANGE,
+        )?;
+
+        // The old records are now in COMPACT_DIR. If they can not be
+        // removed, they are removed when the MDV is next set up.
+        if let Err(err) = remove_dir_all(&compact_path) {
+            warn!("Could not remove the replaced MDV directory: {}", err);
+        }
+
+        Ok(discarded)
+    }
+
+    /// Append an entry to the operation log, discarding the oldest entries
+    /// if the log has grown beyond its maximum size.
+    // Write to a temp file and then rename, as in save_fs.
+    pub fn append_operation_log(&self, entry: OperationLogEntrySave) -> StratisResult<()> {
+        let mount = MountedMDV::mount(self)?;
+        let path = mount.mount_pt().join(OPERATION_LOG_FILE);
+
+        let mut log = read_operation_log(&path)?;
+        log.push(entry);
+        if log.len() > OPERATION_LOG_MAX_ENTRIES {
+            log.drain(..log.len() - OPERATION_LOG_MAX_ENTRIES);
+        }
+        let data = serde_json::to_string(&log)?;
+
+        let temp_path = path.with_extension("temp");
+
+        // Braces to ensure f is closed before renaming
+        {
+            let mut f = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&temp_path)?;
+            f.write_all(data.as_bytes())?;
+            f.sync_all()?;
+        }
+
+        rename(temp_path, path)?;
+
+        Ok(())
+    }
+
+    /// Get the operation log stored on the MDV, oldest entry first.
+    pub fn operation_log(&self) -> StratisResult<Vec<OperationLogEntrySave>> {
+        let mount = MountedMDV::mount(self)?;
+        read_operation_log(&mount.mount_pt().join(OPERATION_LOG_FILE))
+    }
+
     /// Tear down a Metadata Volume.
     pub fn teardown(&mut self) -> StratisResult<()> {
-        self.dev.teardown(get_dm())?;
+        retry_dm(|| self.dev.teardown(get_dm()))?;
 
         Ok(())
     }
 
     /// Suspend the metadata volume DM devices
     pub fn suspend(&mut self) -> StratisResult<()> {
-        self.dev.suspend(get_dm(), true)?;
+        retry_dm(|| self.dev.suspend(get_dm(), true))?;
         Ok(())
     }
 
     /// Resume the metadata volume DM devices
     pub fn resume(&mut self) -> StratisResult<()> {
-        self.dev.resume(get_dm())?;
+        retry_dm(|| self.dev.resume(get_dm()))?;
         Ok(())
     }
 
@@ -227,11 +349,61 @@ impl MetadataVol {
         &mut self,
         table: Vec<TargetLine<LinearDevTargetParams>>,
     ) -> StratisResult<()> {
-        self.dev.set_table(get_dm(), table)?;
+        retry_dm(|| self.dev.set_table(get_dm(), table.clone()))?;
         Ok(())
     }
 }
 
+/// Read the operation log at the given path. A missing log is treated as
+/// an empty one.
+fn read_operation_log(path: &Path) -> StratisResult<Vec<OperationLogEntrySave>> {
+    match OpenOptions::new().read(true).open(path) {
+        Ok(mut f) => {
+            let mut data = Vec::new();
+            f.read_to_end(&mut data)?;
+            Ok(serde_json::from_slice(&data)?)
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(From::from(err)),
+    }
+}
+
+/// The name of the file in which the record of the filesystem with the
+/// given UUID is stored.
+fn record_file_name(uuid: FilesystemUuid) -> String {
+    format!("{}.json", uuid_to_string!(uuid))
+}
+
+/// Write the records into files in dir, and check that reading the files
+/// back yields the same records.
+fn write_and_verify_records(dir: &Path, records: &[FilesystemSave]) -> StratisResult<()> {
+    for record in records {
+        let mut f = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dir.join(record_file_name(record.uuid)))?;
+        f.write_all(serde_json::to_string(record)?.as_bytes())?;
+        f.sync_all()?;
+    }
+
+    for record in records {
+        let mut f = OpenOptions::new()
+            .read(true)
+            .open(dir.join(record_file_name(record.uuid)))?;
+        let mut data = Vec::new();
+        f.read_to_end(&mut data)?;
+        let read_back: FilesystemSave = serde_json::from_slice(&data)?;
+        if &read_back != record {
+            return Err(StratisError::Msg(format!(
+                "Record of filesystem with UUID {} read back from the MDV differs from the record written",
+                record.uuid
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 /// Remove temp files from the designated directory.
 /// Returns an error if the directory can not be read.
 /// Persists if an individual directory entry can not be read due to an
@@ -24,6 +24,11 @@ impl ThinDevIdPool {
         }
     }
 
+    /// The id that the next call to new_id() will return.
+    pub fn peek_id(&self) -> StratisResult<ThinDevId> {
+        Ok(ThinDevId::new_u64(u64::from(self.next_id))?)
+    }
+
     /// Get a new id for a thindev.
     /// Returns an error if no thindev id can be constructed.
     // TODO: Improve this so that it is guaranteed only to fail if every 24 bit
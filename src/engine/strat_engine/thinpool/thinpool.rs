@@ -6,36 +6,55 @@
 
 use std::{
     cmp::{max, min},
+    collections::{HashMap, HashSet},
     fmt,
+    fs::OpenOptions,
+    io::{Seek, SeekFrom, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
     thread::sleep,
     time::Duration,
 };
 
+use chrono::Utc;
 use serde_json::{Map, Value};
 
 use devicemapper::{
-    device_exists, DataBlocks, Device, DmDevice, DmName, DmNameBuf, FlakeyTargetParams, LinearDev,
-    LinearDevTargetParams, LinearTargetParams, MetaBlocks, Sectors, TargetLine, ThinDevId,
-    ThinPoolDev, ThinPoolStatus, ThinPoolStatusSummary, IEC,
+    device_exists, Bytes, DataBlocks, DevId, Device, DmDevice, DmName, DmNameBuf,
+    FlakeyTargetParams, LinearDev, LinearDevTargetParams, LinearTargetParams, MetaBlocks, Sectors,
+    TargetLine, ThinDevId, ThinPoolDev, ThinPoolStatus, ThinPoolStatusSummary, IEC,
 };
 
 use crate::{
     engine::{
         engine::Filesystem,
+        shared::{recommended_meta_size, thin_pool_limits, INITIAL_MDV_SIZE, META_UNDERSIZED_PCT},
         strat_engine::{
             backstore::Backstore,
-            cmd::{thin_check, thin_repair, udev_settle},
-            dm::get_dm,
+            cmd::{thin_check, thin_dump_mappings, thin_repair, ThinMapping},
+            device::read_ranges,
+            devlinks,
+            dm::{get_dm, retry_dm},
             names::{
                 format_flex_ids, format_thin_ids, format_thinpool_ids, FlexRole, ThinPoolRole,
                 ThinRole,
             },
-            serde_structs::{FlexDevsSave, Recordable, ThinPoolDevSave},
-            thinpool::{filesystem::StratFilesystem, mdv::MetadataVol, thinids::ThinDevIdPool},
+            pool::adopt,
+            serde_structs::{
+                FilesystemSave, FlexDevsSave, OperationLogEntrySave, Recordable, ThinPoolDevSave,
+            },
+            thinpool::{
+                filesystem::{StratFilesystem, GROW_PENDING_SLACK},
+                mdv::MetadataVol,
+                thinids::ThinDevIdPool,
+            },
             writing::wipe_sectors,
         },
         structures::Table,
-        types::{FilesystemUuid, Name, PoolUuid},
+        types::{
+            DevUuid, FilesystemUuid, Name, PlannedAction, PoolUuid, ReadWork, Reconciliation,
+            ThinPoolLimits, XfsParams,
+        },
     },
     stratis::{StratisError, StratisResult},
 };
@@ -47,10 +66,8 @@ const INITIAL_META_SIZE: MetaBlocks = MetaBlocks(4 * IEC::Ki);
 // The smallest amount allocated to the thinpool meta device at one time
 const MIN_META_SEGMENT_SIZE: MetaBlocks = MetaBlocks(4 * IEC::Ki);
 const INITIAL_DATA_SIZE: DataBlocks = DataBlocks(768);
-const INITIAL_MDV_SIZE: Sectors = Sectors(32 * IEC::Ki); // 16 MiB
-
-// The maximum allowable size of the thinpool metadata device
-const MAX_META_SIZE: MetaBlocks = MetaBlocks(255 * ((1 << 14) - 64));
+// The amount of the meta device copied at one time when it is relocated
+const META_COPY_CHUNK_SIZE: Sectors = Sectors(2 * IEC::Ki); // 1 MiB
 
 const SPACE_CRIT_PCT: u8 = 95;
 
@@ -95,6 +112,59 @@ fn segs_to_table(
     table
 }
 
+/// Map the range of length sectors at start of a device that is made of the
+/// given segments of the cap device, in order, to the ranges of the cap
+/// device that hold it.
+fn sub_device_ranges(
+    segments: &[(Sectors, Sectors)],
+    start: Sectors,
+    length: Sectors,
+) -> Vec<(Sectors, Sectors)> {
+    let end = start + length;
+    let mut offset = Sectors(0);
+    let mut ranges = Vec::new();
+    for &(seg_start, seg_length) in segments {
+        let from = max(start, offset);
+        let to = min(end, offset + seg_length);
+        if from < to {
+            ranges.push((seg_start + (from - offset), to - from));
+        }
+        offset += seg_length;
+    }
+    ranges
+}
+
+/// The number of data blocks in the ranges own, given as (start, length)
+/// pairs, that are also in any of the ranges others. The ranges of a single
+/// thin device do not overlap, but those of different thin devices overlap
+/// where the devices share data blocks.
+fn shared_blocks(own: &[(u64, u64)], others: &[(u64, u64)]) -> u64 {
+    let mut sorted = others
+        .iter()
+        .map(|&(start, length)| (start, start + length))
+        .collect::<Vec<_>>();
+    sorted.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::new();
+    for (start, end) in sorted {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = max(last.1, end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    own.iter()
+        .map(|&(start, length)| {
+            let end = start + length;
+            let first = merged.partition_point(|&(_, other_end)| other_end <= start);
+            merged[first..]
+                .iter()
+                .take_while(|&&(other_start, _)| other_start < end)
+                .map(|&(other_start, other_end)| min(end, other_end) - max(start, other_start))
+                .sum::<u64>()
+        })
+        .sum()
+}
+
 /// Append the second list of segments to the first, or if the last
 /// segment of the first argument is adjacent to the first segment of the
 /// second argument, merge those two together.
@@ -138,6 +208,12 @@ fn coalesce_segs(
     segments
 }
 
+/// The name by which a filesystem whose name conflicts with the name of some
+/// other filesystem is known until the conflict is resolved.
+fn conflict_placeholder_name(name: &str, uuid: FilesystemUuid) -> Name {
+    Name::new(format!("{}-{}", name, uuid))
+}
+
 /// Calculate new low water based on the current thinpool data device size and
 /// the number of free sectors in the backstore (free in data tier; or
 /// allocated *to* the backstore cap device, but not yet allocated *from* the
@@ -268,6 +344,28 @@ pub struct ThinPool {
     segments: Segments,
     id_gen: ThinDevIdPool,
     filesystems: Table<FilesystemUuid, StratFilesystem>,
+    /// Filesystems which share their name in the metadata with some other
+    /// filesystem in the pool, mapped to that name. These filesystems are
+    /// known by a placeholder name which includes their UUID, and no
+    /// symlinks are made for them, until the conflict is resolved.
+    name_conflicts: HashMap<FilesystemUuid, Name>,
+    /// Whether dm-stats regions that collect latency histograms are created
+    /// for the filesystems.
+    detailed_stats: bool,
+    /// Filesystems that have been deleted, but are kept, torn down, until
+    /// the deletion grace period is over, so that they can be undeleted.
+    deleted_filesystems: HashMap<FilesystemUuid, FilesystemSave>,
+    /// How long deleted filesystems are kept. If None, filesystems are
+    /// destroyed right away.
+    fs_deletion_grace_period: Option<Duration>,
+    /// The sequence number of the next filesystem to be created. Like the
+    /// thin ids, it is not saved, but derived from the sequence numbers of
+    /// the filesystems in the metadata when the pool is set up.
+    next_sequence_number: u64,
+    /// The filesystem which is being copied into the pool, with the name
+    /// that it is to be given, from the time its thin device is created
+    /// until the copy is finished.
+    pending_copy: Option<(Name, FilesystemUuid, StratFilesystem)>,
     mdv: MetadataVol,
     /// The single DM device that the backstore presents as its upper-most
     /// layer. All DM components obtain their storage from this layer.
@@ -378,6 +476,12 @@ impl ThinPool {
             },
             id_gen: ThinDevIdPool::new_from_ids(&[]),
             filesystems: Table::default(),
+            name_conflicts: HashMap::new(),
+            detailed_stats: false,
+            deleted_filesystems: HashMap::new(),
+            fs_deletion_grace_period: None,
+            next_sequence_number: 1,
+            pending_copy: None,
             mdv,
             backstore_device,
             thin_pool_status: None,
@@ -448,15 +552,29 @@ impl ThinPool {
         )?;
         let mdv = MetadataVol::setup(pool_uuid, mdv_dev)?;
         let filesystem_metadatas = mdv.filesystems()?;
-
+        let thin_ids: Vec<ThinDevId> = filesystem_metadatas.iter().map(|x| x.thin_id).collect();
+        let next_sequence_number = filesystem_metadatas
+            .iter()
+            .filter_map(|x| x.sequence_number)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let (deleted_metadatas, filesystem_metadatas): (Vec<_>, Vec<_>) = filesystem_metadatas
+            .into_iter()
+            .partition(|fssave| fssave.deleted.is_some());
+
+        // A filesystem that can not be set up is left out rather than
+        // failing the setup of the whole pool.
+        // FIXME: stratisd does not mount filesystems itself, so there is no
+        // mount failure at pool start to report or retry. If daemon-managed
+        // mounts are added, a filesystem that fails to mount here should be
+        // kept in the pool and flagged with its mount state, and a way to
+        // retry the mount should be provided.
         let filesystems = filesystem_metadatas
             .iter()
             .filter_map(
                 |fssave| match StratFilesystem::setup(pool_uuid, &thinpool_dev, fssave) {
-                    Ok(fs) => {
-                        fs.udev_fs_change(pool_name, fssave.uuid, &fssave.name);
-                        Some((Name::new(fssave.name.to_owned()), fssave.uuid, fs))
-                    },
+                    Ok(fs) => Some((Name::new(fssave.name.to_owned()), fssave.uuid, fs)),
                     Err(err) => {
                         warn!(
                             "Filesystem specified by metadata {:?} could not be setup, reason: {:?}",
@@ -469,18 +587,43 @@ impl ThinPool {
             )
             .collect::<Vec<_>>();
 
+        let mut name_counts: HashMap<&Name, usize> = HashMap::new();
+        for (name, _, _) in filesystems.iter() {
+            *name_counts.entry(name).or_insert(0) += 1;
+        }
+        let conflicting_names = name_counts
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+
         let mut fs_table = Table::default();
+        let mut name_conflicts = HashMap::new();
         for (name, uuid, fs) in filesystems {
-            let evicted = fs_table.insert(name, uuid, fs);
+            let table_name = if conflicting_names.contains(&name) {
+                let table_name = conflict_placeholder_name(&name, uuid);
+                warn!(
+                    "Filesystem with UUID {} belonging to pool with UUID {} has the name {}, which is also the name of some other filesystem in the pool; it will be known as {} until it is renamed",
+                    uuid,
+                    pool_uuid,
+                    name,
+                    table_name
+                );
+                name_conflicts.insert(uuid, name);
+                table_name
+            } else {
+                fs.udev_fs_change(pool_name, uuid, &name);
+                name
+            };
+            let evicted = fs_table.insert(table_name, uuid, fs);
             if evicted.is_some() {
                 // TODO: Recover here. Failing the entire pool setup because
                 // of this is too harsh.
-                let err_msg = "filesystems with duplicate UUID or name specified in metadata";
+                let err_msg = "filesystems with duplicate UUID specified in metadata";
                 return Err(StratisError::Msg(err_msg.into()));
             }
         }
 
-        let thin_ids: Vec<ThinDevId> = filesystem_metadatas.iter().map(|x| x.thin_id).collect();
         Ok(ThinPool {
             thin_pool: thinpool_dev,
             segments: Segments {
@@ -491,6 +634,15 @@ impl ThinPool {
             },
             id_gen: ThinDevIdPool::new_from_ids(&thin_ids),
             filesystems: fs_table,
+            name_conflicts,
+            detailed_stats: false,
+            deleted_filesystems: deleted_metadatas
+                .into_iter()
+                .map(|fssave| (fssave.uuid, fssave))
+                .collect(),
+            fs_deletion_grace_period: None,
+            next_sequence_number,
+            pending_copy: None,
             mdv,
             backstore_device,
             thin_pool_status: None,
@@ -511,6 +663,14 @@ impl ThinPool {
         Ok(should_save)
     }
 
+    /// Update the recorded status of the thin pool without taking any
+    /// action on it, as check() would.
+    pub fn refresh_status(&mut self) -> StratisResult<()> {
+        let thin_pool_status = self.thin_pool.status(get_dm())?;
+        self.set_state(thin_pool_status);
+        Ok(())
+    }
+
     /// Do the real work of check().
     fn do_check(&mut self, pool_uuid: PoolUuid, backstore: &mut Backstore) -> StratisResult<bool> {
         assert_eq!(
@@ -528,10 +688,7 @@ impl ThinPool {
 
             // Ensure meta subdevice is approx. 1/1000th of total usable
             // size, but no larger than the maximum allowed by devicemapper.
-            let target_meta_size = min(
-                (backstore.datatier_usable_size() / 1000u16).metablocks(),
-                MAX_META_SIZE,
-            );
+            let target_meta_size = recommended_meta_size(backstore.datatier_usable_size());
             if usage.total_meta < target_meta_size {
                 let meta_request = target_meta_size - usage.total_meta;
 
@@ -569,7 +726,7 @@ impl ThinPool {
                 sectors_to_datablocks(backstore.available_in_backstore()),
             );
 
-            self.thin_pool.set_low_water_mark(get_dm(), lowater)?;
+            retry_dm(|| self.thin_pool.set_low_water_mark(get_dm(), lowater))?;
             self.resume()?;
         }
 
@@ -578,12 +735,14 @@ impl ThinPool {
         for (name, uuid, fs) in self.filesystems.iter_mut() {
             let save_mdv = fs.check()?;
             if save_mdv {
+                let name = self.name_conflicts.get(uuid).unwrap_or(name);
                 if let Err(e) = self.mdv.save_fs(name, *uuid, fs) {
                     error!("Could not save MDV for fs with UUID {} and name {} belonging to pool with UUID {}, reason: {:?}",
                                 uuid, name, pool_uuid, e);
                 }
             }
         }
+
         Ok(should_save)
     }
 
@@ -620,6 +779,18 @@ impl ThinPool {
         self.thin_pool_status = Some(thin_pool_status);
     }
 
+    /// The segments of the backstore device allocated to each of the flex
+    /// devices.
+    #[cfg(test)]
+    pub fn flex_segments(&self) -> Vec<(FlexRole, &[(Sectors, Sectors)])> {
+        vec![
+            (FlexRole::ThinMeta, &self.segments.meta_segments),
+            (FlexRole::ThinMetaSpare, &self.segments.meta_spare_segments),
+            (FlexRole::ThinData, &self.segments.data_segments),
+            (FlexRole::MetadataVolume, &self.segments.mdv_segments),
+        ]
+    }
+
     /// Tear down the components managed here: filesystems, the MDV,
     /// and the actual thinpool device itself.
     pub fn teardown(&mut self) -> StratisResult<()> {
@@ -628,7 +799,10 @@ impl ThinPool {
         for (_, _, ref mut fs) in &mut self.filesystems {
             fs.teardown()?;
         }
-        self.thin_pool.teardown(get_dm())?;
+        if let Some((_, _, ref mut fs)) = self.pending_copy {
+            fs.teardown()?;
+        }
+        retry_dm(|| self.thin_pool.teardown(get_dm()))?;
 
         // ..but MDV has no DM dependencies with the above
         self.mdv.teardown()?;
@@ -702,12 +876,16 @@ impl ThinPool {
                 .expect("If request succeeded, backstore must have cap device.");
             let mut segments = coalesce_segs(existing_segs, &[region]);
             if data {
-                thinpooldev.set_data_table(get_dm(), segs_to_table(device, &segments))?;
+                retry_dm(|| {
+                    thinpooldev.set_data_table(get_dm(), segs_to_table(device, &segments))
+                })?;
             } else {
-                thinpooldev.set_meta_table(get_dm(), segs_to_table(device, &segments))?;
+                retry_dm(|| {
+                    thinpooldev.set_meta_table(get_dm(), segs_to_table(device, &segments))
+                })?;
             }
 
-            thinpooldev.resume(get_dm())?;
+            retry_dm(|| thinpooldev.resume(get_dm()))?;
             existing_segs.clear();
             existing_segs.append(&mut segments);
 
@@ -743,6 +921,15 @@ impl ThinPool {
         result
     }
 
+    /// The current sizes of the data and metadata devices of the thin pool
+    /// and the largest sizes to which they can be extended.
+    pub fn size_limits(&self) -> ThinPoolLimits {
+        thin_pool_limits(
+            self.thin_pool.data_dev().size(),
+            self.thin_pool.meta_dev().size(),
+        )
+    }
+
     /// The number of physical sectors in use by this thinpool abstraction.
     /// All sectors allocated to the mdv, all sectors allocated to the
     /// metadata spare, and all sectors actually in use by the thinpool DM
@@ -808,7 +995,12 @@ impl ThinPool {
     }
 
     pub fn has_filesystems(&self) -> bool {
-        !self.filesystems.is_empty()
+        !self.filesystems.is_empty() || self.pending_copy.is_some()
+    }
+
+    /// Whether a filesystem is being copied into the pool.
+    pub fn copy_in_progress(&self) -> bool {
+        self.pending_copy.is_some()
     }
 
     pub fn filesystems(&self) -> Vec<(Name, FilesystemUuid, &StratFilesystem)> {
@@ -834,11 +1026,31 @@ impl ThinPool {
         name: &str,
         size: Sectors,
     ) -> StratisResult<FilesystemUuid> {
-        let (fs_uuid, mut new_filesystem) =
-            StratFilesystem::initialize(pool_uuid, &self.thin_pool, size, self.id_gen.new_id()?)?;
+        self.create_filesystem_with_params(pool_name, pool_uuid, name, size, None)
+    }
+
+    /// Create a filesystem within the thin pool, passing the given
+    /// parameters to mkfs.xfs.
+    pub fn create_filesystem_with_params(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        name: &str,
+        size: Sectors,
+        mkfs_params: Option<&XfsParams>,
+    ) -> StratisResult<FilesystemUuid> {
+        self.check_name_conflict(name)?;
+        let (fs_uuid, mut new_filesystem) = StratFilesystem::initialize(
+            pool_uuid,
+            &self.thin_pool,
+            size,
+            self.id_gen.new_id()?,
+            mkfs_params,
+        )?;
+        new_filesystem.set_sequence_number(self.new_sequence_number());
         let name = Name::new(name.to_owned());
         if let Err(err) = self.mdv.save_fs(&name, fs_uuid, &new_filesystem) {
-            udev_settle().unwrap_or_else(|err| {
+            new_filesystem.udev_settle().unwrap_or_else(|err| {
                 warn!("{}", err);
                 sleep(Duration::from_secs(5));
             });
@@ -856,6 +1068,284 @@ impl ThinPool {
             .get_by_uuid(fs_uuid)
             .expect("Inserted above");
         fs.udev_fs_change(pool_name, fs_uuid, &name);
+        self.init_detailed_stats(fs_uuid, fs);
+
+        Ok(fs_uuid)
+    }
+
+    /// The filesystems whose XFS filesystem is smaller than their thin
+    /// device, each with the amount of the thin device that is unused.
+    pub fn filesystems_pending_grow(&self) -> StratisResult<Vec<(FilesystemUuid, Bytes)>> {
+        let mut pending = Vec::new();
+        for (_, fs_uuid, fs) in self.filesystems.iter() {
+            let thindev_size = fs.thindev_size().bytes();
+            let xfs_size = fs.xfs_size()?;
+            if xfs_size < thindev_size && thindev_size - xfs_size > GROW_PENDING_SLACK.bytes() {
+                pending.push((*fs_uuid, thindev_size - xfs_size));
+            }
+        }
+        Ok(pending)
+    }
+
+    /// The mappings of each thin device of the thin pool, keyed by thin
+    /// device id. The mappings are read from a metadata snapshot, so that
+    /// the thin pool can remain in use.
+    fn thin_mappings(&self) -> StratisResult<HashMap<u32, Vec<ThinMapping>>> {
+        let id = DevId::Name(self.thin_pool.name());
+        get_dm().target_msg(&id, None, "reserve_metadata_snap")?;
+        let mappings = thin_dump_mappings(&self.thin_pool.meta_dev().devnode());
+        if let Err(err) = get_dm().target_msg(&id, None, "release_metadata_snap") {
+            warn!(
+                "Failed to release the metadata snapshot of thin pool {}: {}",
+                self.thin_pool.name(),
+                err
+            );
+        }
+        mappings
+    }
+
+    /// The ranges of data blocks mapped by each thin device of the thin
+    /// pool, as (start, length) pairs, keyed by thin device id.
+    fn data_block_mappings(&self) -> StratisResult<HashMap<u32, Vec<(u64, u64)>>> {
+        Ok(self
+            .thin_mappings()?
+            .into_iter()
+            .map(|(id, mappings)| {
+                (
+                    id,
+                    mappings
+                        .into_iter()
+                        .map(|mapping| (mapping.data_begin, mapping.length))
+                        .collect(),
+                )
+            })
+            .collect())
+    }
+
+    /// The ranges of the thin device of the filesystem that are mapped to
+    /// data in the thin pool, as (start, length) pairs in ascending order.
+    /// Reading these ranges reads all the data stored on the filesystem;
+    /// the rest of the thin device reads as zeroes.
+    pub fn filesystem_mapped_ranges(
+        &self,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<Vec<(Sectors, Sectors)>> {
+        let thin_id = self
+            .get_filesystem_by_uuid(uuid)
+            .map(|(_, fs)| u32::from(fs.thin_id()))
+            .ok_or_else(|| StratisError::Msg(format!("Filesystem with UUID {} not found", uuid)))?;
+        let mut blocks = self
+            .thin_mappings()?
+            .remove(&thin_id)
+            .unwrap_or_else(Vec::new)
+            .into_iter()
+            .map(|mapping| (mapping.origin_begin, mapping.length))
+            .collect::<Vec<_>>();
+        blocks.sort_unstable();
+
+        let block_size = self.thin_pool.data_block_size();
+        let mut ranges: Vec<(Sectors, Sectors)> = Vec::new();
+        for (start, length) in blocks {
+            let (start, length) = (block_size * start, block_size * length);
+            match ranges.last_mut() {
+                Some((last_start, last_length)) if *last_start + *last_length == start => {
+                    *last_length += length
+                }
+                _ => ranges.push((start, length)),
+            }
+        }
+        Ok(ranges)
+    }
+
+    /// The ranges of the cap device in which the data of each filesystem is
+    /// stored. Data that a filesystem shares with its snapshots is listed
+    /// for each of them.
+    pub fn filesystem_cap_ranges(
+        &self,
+    ) -> StratisResult<Vec<(FilesystemUuid, Vec<(Sectors, Sectors)>)>> {
+        let mappings = self.data_block_mappings()?;
+
+        let block_size = self.thin_pool.data_block_size();
+        Ok(self
+            .filesystems
+            .iter()
+            .map(|(_, uuid, fs)| {
+                let ranges = mappings
+                    .get(&u32::from(fs.thin_id()))
+                    .map(|blocks| {
+                        blocks
+                            .iter()
+                            .flat_map(|&(start, length)| {
+                                sub_device_ranges(
+                                    &self.segments.data_segments,
+                                    block_size * start,
+                                    block_size * length,
+                                )
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_else(Vec::new);
+                (*uuid, ranges)
+            })
+            .collect())
+    }
+
+    /// The amount of the data of the filesystem that it shares with other
+    /// thin devices, i.e. its origin and its snapshots, and the amount that
+    /// only it maps, as a pair (shared, unique).
+    pub fn filesystem_shared_size(
+        &self,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<(Sectors, Sectors)> {
+        self.filesystem_shared_sizes(&[uuid]).map(|sizes| sizes[0])
+    }
+
+    /// The shared and unique amounts of data of each of the given
+    /// filesystems, in the same order, as filesystem_shared_size() would
+    /// return them. The mappings of the thin devices are read only once.
+    pub fn filesystem_shared_sizes(
+        &self,
+        uuids: &[FilesystemUuid],
+    ) -> StratisResult<Vec<(Sectors, Sectors)>> {
+        let thin_ids = uuids
+            .iter()
+            .map(|uuid| {
+                self.get_filesystem_by_uuid(*uuid)
+                    .map(|(_, fs)| u32::from(fs.thin_id()))
+                    .ok_or_else(|| {
+                        StratisError::Msg(format!("Filesystem with UUID {} not found", uuid))
+                    })
+            })
+            .collect::<StratisResult<Vec<_>>>()?;
+        let mappings = self.data_block_mappings()?;
+
+        let block_size = self.thin_pool.data_block_size();
+        Ok(thin_ids
+            .into_iter()
+            .map(|thin_id| {
+                let own = mappings.get(&thin_id).map(|v| v.as_slice()).unwrap_or(&[]);
+                let others = mappings
+                    .iter()
+                    .filter(|(id, _)| **id != thin_id)
+                    .flat_map(|(_, ranges)| ranges.iter().cloned())
+                    .collect::<Vec<_>>();
+
+                let total = own.iter().map(|&(_, length)| length).sum::<u64>();
+                let shared = shared_blocks(own, &others);
+                (block_size * shared, block_size * (total - shared))
+            })
+            .collect())
+    }
+
+    /// The actions that create_filesystem_with_params() would perform with
+    /// the same arguments. The UUID of the filesystem is generated anew, so it
+    /// differs from the UUID of a filesystem actually created.
+    pub fn explain_create_filesystem(
+        &self,
+        pool_uuid: PoolUuid,
+        name: &str,
+        size: Sectors,
+        mkfs_params: Option<&XfsParams>,
+    ) -> StratisResult<Vec<PlannedAction>> {
+        self.check_name_conflict(name)?;
+        Ok(StratFilesystem::explain_initialize(
+            pool_uuid,
+            FilesystemUuid::new_v4(),
+            &self.thin_pool,
+            size,
+            self.id_gen.peek_id()?,
+            mkfs_params,
+        ))
+    }
+
+    /// Start to create a filesystem within the thin pool which is a copy of
+    /// the source filesystem. Given name must not already be in use. The
+    /// returned work copies the given mapped ranges of the source, see
+    /// StratFilesystem::copy_work(); it does not require access to the
+    /// pool, so it may be run without holding the engine lock. Once it is
+    /// done, finish_copy_filesystem() must be called with its result.
+    pub fn start_copy_filesystem(
+        &mut self,
+        pool_uuid: PoolUuid,
+        name: &str,
+        source: &dyn Filesystem,
+        source_ranges: Vec<(Sectors, Sectors)>,
+    ) -> StratisResult<ReadWork<()>> {
+        if self.pending_copy.is_some() {
+            return Err(StratisError::Msg(
+                "A filesystem is already being copied into the pool".into(),
+            ));
+        }
+        self.check_name_conflict(name)?;
+        // The source is held open exclusively while it is copied, so that
+        // it can not be mounted in the meantime.
+        let claim = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_EXCL)
+            .open(source.devnode())
+            .map_err(|err| {
+                StratisError::Msg(format!(
+                    "Could not open {} exclusively; it may be mounted or otherwise in use: {}",
+                    source.devnode().display(),
+                    err
+                ))
+            })?;
+        let (fs_uuid, new_filesystem) = StratFilesystem::initialize_copy(
+            pool_uuid,
+            &self.thin_pool,
+            self.id_gen.new_id()?,
+            source,
+        )?;
+        let work = new_filesystem.copy_work(fs_uuid, source.devnode(), claim, source_ranges);
+        self.pending_copy = Some((Name::new(name.to_owned()), fs_uuid, new_filesystem));
+        Ok(work)
+    }
+
+    /// Finish the copy started by start_copy_filesystem(), given the result
+    /// of its work. If the copy succeeded, the new filesystem is added to
+    /// the pool; otherwise, or if the name has been taken in the meantime,
+    /// its thin device is destroyed.
+    pub fn finish_copy_filesystem(
+        &mut self,
+        pool_name: &str,
+        copied: StratisResult<()>,
+    ) -> StratisResult<FilesystemUuid> {
+        let (name, fs_uuid, mut new_filesystem) = self.pending_copy.take().ok_or_else(|| {
+            StratisError::Msg("No filesystem is being copied into the pool".into())
+        })?;
+
+        let result = copied
+            .and_then(|_| match self.filesystems.get_by_name(&name) {
+                Some(_) => Err(StratisError::Msg(format!(
+                    "A filesystem named {} was created while the copy was in progress",
+                    name
+                ))),
+                None => self.check_name_conflict(&name),
+            })
+            .and_then(|_| {
+                new_filesystem.set_sequence_number(self.new_sequence_number());
+                self.mdv.save_fs(&name, fs_uuid, &new_filesystem)
+            });
+        if let Err(err) = result {
+            new_filesystem.udev_settle().unwrap_or_else(|err| {
+                warn!("{}", err);
+                sleep(Duration::from_secs(5));
+            });
+            if let Err(err2) = new_filesystem.destroy(&self.thin_pool) {
+                error!(
+                    "When handling failed filesystem copy, fs.destroy() failed: {}",
+                    err2
+                )
+            }
+            return Err(err);
+        }
+        self.filesystems.insert(name, fs_uuid, new_filesystem);
+        let (name, fs) = self
+            .filesystems
+            .get_by_uuid(fs_uuid)
+            .expect("Inserted above");
+        fs.udev_fs_change(pool_name, fs_uuid, &name);
+        self.init_detailed_stats(fs_uuid, fs);
 
         Ok(fs_uuid)
     }
@@ -869,13 +1359,15 @@ impl ThinPool {
         origin_uuid: FilesystemUuid,
         snapshot_name: &str,
     ) -> StratisResult<(FilesystemUuid, &mut dyn Filesystem)> {
+        self.check_name_conflict(snapshot_name)?;
         let snapshot_fs_uuid = FilesystemUuid::new_v4();
         let (snapshot_dm_name, snapshot_dm_uuid) =
             format_thin_ids(pool_uuid, ThinRole::Filesystem(snapshot_fs_uuid));
         let snapshot_id = self.id_gen.new_id()?;
-        let new_filesystem = match self.get_filesystem_by_uuid(origin_uuid) {
+        let mut new_filesystem = match self.get_filesystem_by_uuid(origin_uuid) {
             Some((fs_name, filesystem)) => filesystem.snapshot(
                 &self.thin_pool,
+                origin_uuid,
                 snapshot_name,
                 &snapshot_dm_name,
                 Some(&snapshot_dm_uuid),
@@ -889,6 +1381,7 @@ impl ThinPool {
                 ));
             }
         };
+        new_filesystem.set_sequence_number(self.new_sequence_number());
         let new_fs_name = Name::new(snapshot_name.to_owned());
         self.mdv
             .save_fs(&new_fs_name, snapshot_fs_uuid, &new_filesystem)?;
@@ -899,6 +1392,7 @@ impl ThinPool {
             .get_by_uuid(snapshot_fs_uuid)
             .expect("Inserted above");
         fs.udev_fs_change(pool_name, snapshot_fs_uuid, &new_fs_name);
+        self.init_detailed_stats(snapshot_fs_uuid, fs);
         Ok((
             snapshot_fs_uuid,
             self.filesystems
@@ -908,6 +1402,28 @@ impl ThinPool {
         ))
     }
 
+    /// Take the sequence number for a new filesystem.
+    fn new_sequence_number(&mut self) -> u64 {
+        let sequence_number = self.next_sequence_number;
+        self.next_sequence_number += 1;
+        sequence_number
+    }
+
+    /// The filesystems that were created as snapshots, oldest first.
+    pub fn snapshots_oldest_first(&self) -> Vec<(FilesystemUuid, Name)> {
+        let mut snapshots = self
+            .filesystems
+            .iter()
+            .filter(|(_, _, fs)| fs.origin().is_some())
+            .map(|(name, uuid, fs)| (fs.created(), *uuid, name.clone()))
+            .collect::<Vec<_>>();
+        snapshots.sort_by_key(|(created, _, _)| *created);
+        snapshots
+            .into_iter()
+            .map(|(_, uuid, name)| (uuid, name))
+            .collect()
+    }
+
     /// Destroy a filesystem within the thin pool. Destroy metadata associated
     /// with the thinpool. If there is a failure to destroy the filesystem,
     /// retain it, and return an error.
@@ -930,6 +1446,10 @@ impl ThinPool {
                                pool_name,
                                err);
                     }
+                    devlinks::filesystem_removed(pool_name, &fs_name);
+                    if let Some(conflict_name) = self.name_conflicts.remove(&uuid) {
+                        self.resolve_name_conflict(pool_name, &conflict_name);
+                    }
                     Ok(Some(uuid))
                 }
                 Err(err) => {
@@ -941,11 +1461,646 @@ impl ThinPool {
         }
     }
 
+    /// Delete a filesystem within the thin pool, but keep its thin device
+    /// until the deletion grace period is over. The filesystem is unmounted
+    /// and its DM device torn down, and its record in the MDV is marked as
+    /// deleted.
+    ///
+    /// * Ok(Some(uuid)) provides the uuid of the deleted filesystem
+    /// * Ok(None) is returned if the filesystem did not exist
+    /// * Err(_) is returned if the filesystem could not be deleted
+    pub fn soft_delete_filesystem(
+        &mut self,
+        pool_name: &str,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<Option<FilesystemUuid>> {
+        let (fs_name, mut fs) = match self.filesystems.remove_by_uuid(uuid) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let name = self
+            .name_conflicts
+            .get(&uuid)
+            .cloned()
+            .unwrap_or_else(|| fs_name.clone());
+        let mut record = fs.record(&name, uuid);
+        record.deleted = Some(Utc::now().timestamp() as u64);
+
+        if let Err(err) = fs.unmount().and_then(|_| self.mdv.save_fs_record(&record)) {
+            self.filesystems.insert(fs_name, uuid, fs);
+            return Err(err);
+        }
+        if let Err(err) = fs.teardown() {
+            if let Err(e) = self.mdv.save_fs(&name, uuid, &fs) {
+                error!("Could not restore metadata for fs with UUID {} and name {} belonging to pool {}, reason: {:?}",
+                       uuid,
+                       name,
+                       pool_name,
+                       e);
+            }
+            self.filesystems.insert(fs_name, uuid, fs);
+            return Err(err);
+        }
+
+        devlinks::filesystem_removed(pool_name, &fs_name);
+        if let Some(conflict_name) = self.name_conflicts.remove(&uuid) {
+            self.resolve_name_conflict(pool_name, &conflict_name);
+        }
+        self.deleted_filesystems.insert(uuid, record);
+        Ok(Some(uuid))
+    }
+
+    /// Set up the thin device of a deleted filesystem again and return the
+    /// filesystem to the pool.
+    ///
+    /// * Ok(true) is returned if the filesystem was undeleted
+    /// * Ok(false) is returned if the filesystem exists and is not deleted
+    /// * Err(_) is returned if there is no such deleted filesystem or it
+    /// could not be undeleted
+    pub fn undelete_filesystem(
+        &mut self,
+        pool_name: &str,
+        pool_uuid: PoolUuid,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<bool> {
+        if self.filesystems.contains_uuid(uuid) {
+            return Ok(false);
+        }
+        let record = self.deleted_filesystems.get(&uuid).ok_or_else(|| {
+            StratisError::Msg(format!("No deleted filesystem with UUID {}", uuid))
+        })?;
+        if self.filesystems.contains_name(&record.name) {
+            return Err(StratisError::Msg(format!(
+                "A filesystem with name {} already exists; rename it before undeleting filesystem with UUID {}",
+                record.name, uuid
+            )));
+        }
+        self.check_name_conflict(&record.name)?;
+
+        let name = Name::new(record.name.clone());
+        let mut fs = StratFilesystem::setup(pool_uuid, &self.thin_pool, record)?;
+        if let Err(err) = self.mdv.save_fs(&name, uuid, &fs) {
+            if let Err(e) = fs.teardown() {
+                warn!(
+                    "Failed to tear down filesystem with UUID {} after failing to undelete it: {}",
+                    uuid, e
+                );
+            }
+            return Err(err);
+        }
+
+        self.deleted_filesystems.remove(&uuid);
+        self.filesystems.insert(name, uuid, fs);
+        let (name, fs) = self.filesystems.get_by_uuid(uuid).expect("Inserted above");
+        fs.udev_fs_change(pool_name, uuid, &name);
+        self.init_detailed_stats(uuid, fs);
+        Ok(true)
+    }
+
+    /// Permanently destroy the deleted filesystems whose deletion grace
+    /// period is over. If there is no grace period, all deleted filesystems
+    /// are destroyed. A failure is logged and the filesystem is retried on
+    /// the next call. Returns the UUIDs of the filesystems destroyed.
+    pub fn purge_deleted_filesystems(&mut self, pool_uuid: PoolUuid) -> Vec<FilesystemUuid> {
+        let now = Utc::now().timestamp() as u64;
+        let grace_period = self.fs_deletion_grace_period.map_or(0, |p| p.as_secs());
+        let expired = self
+            .deleted_filesystems
+            .values()
+            .filter(|record| record.deleted.unwrap_or(0).saturating_add(grace_period) <= now)
+            .map(|record| record.uuid)
+            .collect::<Vec<_>>();
+
+        expired
+            .into_iter()
+            .filter(|uuid| match self.destroy_deleted_filesystem(pool_uuid, *uuid) {
+                Ok(destroyed) => destroyed,
+                Err(err) => {
+                    warn!(
+                        "Failed to destroy deleted filesystem with UUID {} belonging to pool with UUID {}: {}",
+                        uuid, pool_uuid, err
+                    );
+                    false
+                }
+            })
+            .collect()
+    }
+
+    /// Permanently destroy a deleted filesystem without waiting for its
+    /// deletion grace period to be over.
+    ///
+    /// * Ok(true) is returned if the filesystem was destroyed
+    /// * Ok(false) is returned if there is no such deleted filesystem
+    /// * Err(_) is returned if the filesystem could not be destroyed, in
+    /// which case it is kept
+    pub fn destroy_deleted_filesystem(
+        &mut self,
+        pool_uuid: PoolUuid,
+        uuid: FilesystemUuid,
+    ) -> StratisResult<bool> {
+        let record = match self.deleted_filesystems.remove(&uuid) {
+            Some(record) => record,
+            None => return Ok(false),
+        };
+        let result = StratFilesystem::setup(pool_uuid, &self.thin_pool, &record)
+            .and_then(|mut fs| fs.destroy(&self.thin_pool))
+            .and_then(|_| self.mdv.rm_fs(uuid));
+        match result {
+            Ok(()) => {
+                info!(
+                    "Destroyed deleted filesystem {} with UUID {} belonging to pool with UUID {}",
+                    record.name, uuid, pool_uuid
+                );
+                Ok(true)
+            }
+            Err(err) => {
+                self.deleted_filesystems.insert(uuid, record);
+                Err(err)
+            }
+        }
+    }
+
+    /// The records of the deleted filesystems that are kept for the
+    /// deletion grace period.
+    pub fn deleted_filesystems(&self) -> Vec<&FilesystemSave> {
+        self.deleted_filesystems.values().collect()
+    }
+
+    /// How long deleted filesystems are kept before they are destroyed.
+    pub fn fs_deletion_grace_period(&self) -> Option<Duration> {
+        self.fs_deletion_grace_period
+    }
+
+    /// Set how long deleted filesystems are kept. Filesystems whose new
+    /// grace period is already over are destroyed right away.
+    pub fn set_fs_deletion_grace_period(&mut self, pool_uuid: PoolUuid, period: Option<Duration>) {
+        self.fs_deletion_grace_period = period;
+        self.purge_deleted_filesystems(pool_uuid);
+    }
+
+    /// Set the size of the thin device of the filesystem with the given UUID
+    /// and record the new size in the MDV. See StratFilesystem::set_size().
+    pub fn set_filesystem_size(
+        &mut self,
+        uuid: FilesystemUuid,
+        target_size: Sectors,
+        grow_fs: bool,
+    ) -> StratisResult<bool> {
+        let (name, fs) = self.filesystems.get_mut_by_uuid(uuid).ok_or_else(|| {
+            StratisError::Msg(format!("Filesystem with UUID {} does not exist", uuid))
+        })?;
+        let changed = fs.set_size(target_size, grow_fs)?;
+        if changed {
+            let name = self.name_conflicts.get(&uuid).unwrap_or(&name);
+            self.mdv.save_fs(name, uuid, fs)?;
+        }
+        Ok(changed)
+    }
+
+    /// Set the copy-on-write alert threshold of the filesystem with the
+    /// given UUID and record it in the MDV.
+    /// Returns true if the threshold was changed.
+    pub fn set_filesystem_cow_alert_threshold(
+        &mut self,
+        uuid: FilesystemUuid,
+        threshold: Option<Bytes>,
+    ) -> StratisResult<bool> {
+        let (name, fs) = self.filesystems.get_mut_by_uuid(uuid).ok_or_else(|| {
+            StratisError::Msg(format!("Filesystem with UUID {} does not exist", uuid))
+        })?;
+        let old_threshold = fs.cow_alert_threshold();
+        if !fs.set_cow_alert_threshold(threshold) {
+            return Ok(false);
+        }
+        let name = self.name_conflicts.get(&uuid).unwrap_or(&name);
+        if let Err(err) = self.mdv.save_fs(name, uuid, fs) {
+            fs.set_cow_alert_threshold(old_threshold);
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    /// Set the minimum free space guarantee of the filesystem with the
+    /// given UUID and record it in the MDV.
+    /// Returns true if the guarantee was changed.
+    pub fn set_filesystem_min_free_guarantee(
+        &mut self,
+        uuid: FilesystemUuid,
+        guarantee: Option<Bytes>,
+    ) -> StratisResult<bool> {
+        let (name, fs) = self.filesystems.get_mut_by_uuid(uuid).ok_or_else(|| {
+            StratisError::Msg(format!("Filesystem with UUID {} does not exist", uuid))
+        })?;
+        let old_guarantee = fs.min_free_guarantee();
+        if !fs.set_min_free_guarantee(guarantee) {
+            return Ok(false);
+        }
+        let name = self.name_conflicts.get(&uuid).unwrap_or(&name);
+        if let Err(err) = self.mdv.save_fs(name, uuid, fs) {
+            fs.set_min_free_guarantee(old_guarantee);
+            return Err(err);
+        }
+        Ok(true)
+    }
+
     #[cfg(test)]
     pub fn state(&self) -> Option<&ThinPoolStatus> {
         self.thin_pool_status.as_ref()
     }
 
+    /// The size of the MDV.
+    pub fn mdv_size(&self) -> Sectors {
+        self.mdv.device().size()
+    }
+
+    /// The number of metadata blocks in use in the thin pool metadata device
+    /// and its total size, if the thin pool is working.
+    pub fn metadata_usage(&self) -> Option<(MetaBlocks, MetaBlocks)> {
+        match &self.thin_pool_status {
+            Some(ThinPoolStatus::Working(status)) => {
+                Some((status.usage.used_meta, status.usage.total_meta))
+            }
+            _ => None,
+        }
+    }
+
+    /// The size of the thin pool metadata device and the recommended size for
+    /// a data tier of the given usable size, if the metadata device is
+    /// smaller than META_UNDERSIZED_PCT percent of the recommended size.
+    /// check() grows the metadata device to the recommended size, so it is
+    /// only undersized if growing it failed, e.g. for lack of space.
+    pub fn metadata_undersized(
+        &self,
+        datatier_usable: Sectors,
+    ) -> Option<(MetaBlocks, MetaBlocks)> {
+        let (_, current) = self.metadata_usage()?;
+        let recommended = recommended_meta_size(datatier_usable);
+        if u128::from(*current) * 100 < u128::from(*recommended) * u128::from(META_UNDERSIZED_PCT) {
+            Some((current, recommended))
+        } else {
+            None
+        }
+    }
+
+    /// Whether the thin pool has been found to be failed, read-only, or out
+    /// of data space when its status was last checked.
+    pub fn is_degraded(&self) -> bool {
+        self.thin_pool_status
+            .as_ref()
+            .map(|status| ThinPoolStatusDigest::from(status) != ThinPoolStatusDigest::Good)
+            .unwrap_or(false)
+    }
+
+    /// The problems with the thin pool found when its status was last
+    /// checked, one per entry.
+    pub fn health_problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Some(status) = &self.thin_pool_status {
+            match ThinPoolStatusDigest::from(status) {
+                ThinPoolStatusDigest::Good => (),
+                ThinPoolStatusDigest::ReadOnly => {
+                    problems.push("The thin pool is read-only".to_string())
+                }
+                ThinPoolStatusDigest::OutOfSpace => {
+                    problems.push("The thin pool is out of data space".to_string())
+                }
+                ThinPoolStatusDigest::Fail => problems.push("The thin pool has failed".to_string()),
+                ThinPoolStatusDigest::Error => {
+                    problems.push("The status of the thin pool could not be obtained".to_string())
+                }
+            }
+        }
+        if self.needs_repair() {
+            problems.push("The thin pool metadata needs to be repaired".to_string());
+        }
+        problems
+    }
+
+    /// Whether the kernel has set the needs_check flag in the thin pool
+    /// metadata superblock, in which case the metadata must be repaired
+    /// before the thin pool can be written to again.
+    pub fn needs_repair(&self) -> bool {
+        match &self.thin_pool_status {
+            Some(ThinPoolStatus::Working(status)) => status.needs_check,
+            _ => false,
+        }
+    }
+
+    /// Append an entry describing an operation on the pool to the
+    /// operation log on the MDV. A failure to record the entry is logged,
+    /// but does not cause the operation itself to fail.
+    pub fn log_operation(&self, operation: &str) {
+        let entry = OperationLogEntrySave {
+            timestamp: Utc::now().timestamp() as u64,
+            operation: operation.to_owned(),
+        };
+        if let Err(err) = self.mdv.append_operation_log(entry) {
+            warn!(
+                "Failed to record operation \"{}\" in the operation log: {}",
+                operation, err
+            );
+        }
+    }
+
+    /// Compact the filesystem records on the MDV, so that it holds exactly
+    /// the records of the filesystems in the pool, including the deleted
+    /// filesystems that are still kept.
+    /// Returns the number of entries discarded.
+    pub fn compact_metadata(&self) -> StratisResult<u64> {
+        let records = self
+            .filesystems
+            .iter()
+            .map(|(name, uuid, fs)| {
+                let name = self.name_conflicts.get(uuid).unwrap_or(name);
+                fs.record(name, *uuid)
+            })
+            .chain(self.deleted_filesystems.values().cloned())
+            .collect::<Vec<_>>();
+        self.mdv.compact(&records)
+    }
+
+    /// Compare the filesystem records on the MDV with the filesystems in the
+    /// pool, including the deleted filesystems that are still kept.
+    /// Returns a description of each divergence.
+    pub fn verify_metadata(&self) -> StratisResult<Vec<String>> {
+        let mut expected = self
+            .filesystems
+            .iter()
+            .map(|(name, uuid, fs)| {
+                let name = self.name_conflicts.get(uuid).unwrap_or(name);
+                (*uuid, fs.record(name, *uuid))
+            })
+            .chain(
+                self.deleted_filesystems
+                    .iter()
+                    .map(|(uuid, record)| (*uuid, record.clone())),
+            )
+            .collect::<HashMap<_, _>>();
+
+        let mut problems = Vec::new();
+        for record in self.mdv.filesystems()? {
+            match expected.remove(&record.uuid) {
+                Some(ref current) if *current == record => (),
+                Some(_) => problems.push(format!(
+                    "The record of filesystem with UUID {} differs from the filesystem",
+                    record.uuid
+                )),
+                None => problems.push(format!(
+                    "There is a record of filesystem with UUID {}, which is not in the pool",
+                    record.uuid
+                )),
+            }
+        }
+        problems.extend(
+            expected
+                .keys()
+                .map(|uuid| format!("There is no record of filesystem with UUID {}", uuid)),
+        );
+        Ok(problems)
+    }
+
+    /// Compare the filesystem records on the MDV with the filesystems in the
+    /// pool, including the deleted filesystems that are still kept, and
+    /// adopt the settings in the records wherever they differ from those
+    /// held in memory. A filesystem without a record, a record of a
+    /// filesystem which is not in the pool, and a difference in whether a
+    /// filesystem is deleted are reported but not corrected.
+    pub fn reconcile_filesystems(
+        &mut self,
+        pool_name: &str,
+        reconciliation: &mut Reconciliation,
+    ) -> StratisResult<()> {
+        let mut unrecorded = self
+            .filesystems
+            .iter()
+            .map(|(_, uuid, _)| *uuid)
+            .chain(self.deleted_filesystems.keys().cloned())
+            .collect::<HashSet<_>>();
+
+        for record in self.mdv.filesystems()? {
+            let uuid = record.uuid;
+            let what = format!("filesystem with UUID {}", uuid);
+            unrecorded.remove(&uuid);
+            if let Some(sequence_number) = record.sequence_number {
+                self.next_sequence_number = max(self.next_sequence_number, sequence_number + 1);
+            }
+
+            let in_pool = self.filesystems.get_by_uuid(uuid).is_some();
+            let deleted = self.deleted_filesystems.contains_key(&uuid);
+            match (record.deleted.is_some(), in_pool, deleted) {
+                (_, false, false) => reconciliation.unresolved.push(format!(
+                    "{}: there is a record on disk, but the filesystem is not in the pool",
+                    what
+                )),
+                (true, true, _) | (false, _, true) => reconciliation.unresolved.push(format!(
+                    "{}: the record on disk differs in whether the filesystem is deleted",
+                    what
+                )),
+                (false, true, false) => {
+                    let (name, fs) = self
+                        .filesystems
+                        .get_mut_by_uuid(uuid)
+                        .expect("the filesystem is in the pool");
+                    let fs_reconciliation = fs.reconcile(&record);
+                    reconciliation.corrected.extend(
+                        fs_reconciliation
+                            .corrected
+                            .into_iter()
+                            .map(|entry| format!("{}: {}", what, entry)),
+                    );
+                    reconciliation.unresolved.extend(
+                        fs_reconciliation
+                            .unresolved
+                            .into_iter()
+                            .map(|entry| format!("{}: {}", what, entry)),
+                    );
+
+                    let name = self.name_conflicts.get(&uuid).cloned().unwrap_or(name);
+                    if *name != record.name {
+                        match self.rename_filesystem(pool_name, uuid, &record.name) {
+                            Ok(_) => reconciliation.corrected.push(format!(
+                                "{}: name: {} in memory, {} on disk",
+                                what, name, record.name
+                            )),
+                            Err(err) => reconciliation.unresolved.push(format!(
+                                "{}: name: {} on disk, but the filesystem could not be renamed: {}",
+                                what, record.name, err
+                            )),
+                        }
+                    }
+                }
+                (true, false, true) => {
+                    let current = self
+                        .deleted_filesystems
+                        .get_mut(&uuid)
+                        .expect("the filesystem is deleted");
+                    if (current.thin_id, current.size) != (record.thin_id, record.size) {
+                        reconciliation.unresolved.push(format!(
+                            "{}: thin device: the metadata on disk differs from the deleted filesystem as it is kept",
+                            what
+                        ));
+                    } else {
+                        adopt(
+                            &format!("{}: record of the deleted filesystem", what),
+                            current,
+                            record,
+                            &mut reconciliation.corrected,
+                        );
+                    }
+                }
+            }
+        }
+
+        reconciliation.unresolved.extend(
+            unrecorded
+                .into_iter()
+                .map(|uuid| format!("filesystem with UUID {}: there is no record on disk", uuid)),
+        );
+        Ok(())
+    }
+
+    /// The space that the thin pool would occupy with metadata if the data
+    /// tier had the given usable size: the meta device, grown as check()
+    /// would grow it, its spare, and the MDV.
+    pub fn metadata_overhead(&self, datatier_usable: Sectors) -> Sectors {
+        let sum = |segments: &[(Sectors, Sectors)]| segments.iter().map(|s| s.1).sum::<Sectors>();
+        let target_meta_size = recommended_meta_size(datatier_usable).sectors();
+        max(sum(&self.segments.meta_segments), target_meta_size)
+            + sum(&self.segments.meta_spare_segments)
+            + sum(&self.segments.mdv_segments)
+    }
+
+    /// Move the meta device of the thin pool and its spare to space newly
+    /// allocated on the datadevs with the given UUIDs. The contents of the
+    /// meta device are copied while the thin pool is suspended, after which
+    /// the meta device is switched over to the copy. Since space is never
+    /// returned to the backstore, the space that is vacated is added to the
+    /// data device instead. progress is called with the number of sectors
+    /// copied so far and the size of the meta device.
+    ///
+    /// Returns false if the meta device and its spare are already stored
+    /// entirely on the given datadevs.
+    ///
+    /// FIXME: The MDV is not moved. It holds a mounted filesystem, which
+    /// would have to be unmounted for the duration of the copy.
+    pub fn relocate_metadata(
+        &mut self,
+        pool_uuid: PoolUuid,
+        backstore: &mut Backstore,
+        uuids: &[DevUuid],
+        progress: &mut dyn FnMut(Sectors, Sectors),
+    ) -> StratisResult<bool> {
+        fn copy_meta(
+            source: &Path,
+            dest: &Path,
+            size: Sectors,
+            progress: &mut dyn FnMut(Sectors, Sectors),
+        ) -> StratisResult<()> {
+            // The source is read with direct I/O, so that what is copied is
+            // what the kernel has written to the device, not stale data
+            // in the page cache.
+            let mut dest = OpenOptions::new()
+                .write(true)
+                .custom_flags(libc::O_DIRECT)
+                .open(dest)?;
+            read_ranges(
+                source,
+                &[(Sectors(0), size)],
+                META_COPY_CHUNK_SIZE,
+                &mut |(offset, _), result| {
+                    dest.seek(SeekFrom::Start(convert_int!(*offset.bytes(), u128, u64)?))?;
+                    dest.write_all(result?)?;
+                    Ok(())
+                },
+                progress,
+            )?;
+            dest.sync_all()?;
+            Ok(())
+        }
+
+        let sum = |segments: &[(Sectors, Sectors)]| segments.iter().map(|s| s.1).sum::<Sectors>();
+        let meta_size = sum(&self.segments.meta_segments);
+        let spare_size = sum(&self.segments.meta_spare_segments);
+        let on_datadevs = uuids
+            .iter()
+            .map(|uuid| {
+                backstore.datadev_share(*uuid, &self.segments.meta_segments)
+                    + backstore.datadev_share(*uuid, &self.segments.meta_spare_segments)
+            })
+            .sum::<Sectors>();
+        if on_datadevs == meta_size + spare_size {
+            return Ok(false);
+        }
+
+        let mut new_segments =
+            backstore.alloc_on_datadevs(pool_uuid, &[meta_size, spare_size], uuids)?;
+        let new_spare_segments = vec![new_segments.pop().expect("two sizes were requested")];
+        let new_meta_segments = vec![new_segments.pop().expect("two sizes were requested")];
+
+        let device = self.backstore_device;
+        let (dm_name, dm_uuid) = format_flex_ids(pool_uuid, FlexRole::ThinMetaSpare);
+        let mut new_meta_dev = LinearDev::setup(
+            get_dm(),
+            &dm_name,
+            Some(&dm_uuid),
+            segs_to_table(device, &new_meta_segments),
+        )?;
+
+        let result = self.suspend().and_then(|_| {
+            let copied = copy_meta(
+                &self.thin_pool.meta_dev().devnode(),
+                &new_meta_dev.devnode(),
+                meta_size,
+                progress,
+            )
+            .and_then(|_| {
+                retry_dm(|| {
+                    self.thin_pool
+                        .set_meta_table(get_dm(), segs_to_table(device, &new_meta_segments))
+                })?;
+                Ok(())
+            });
+            copied.and(self.resume())
+        });
+        if let Err(err) = retry_dm(|| new_meta_dev.teardown(get_dm())) {
+            warn!(
+                "Failed to tear down the device used to copy the thin pool meta device of pool {}: {}",
+                pool_uuid, err
+            );
+        }
+
+        // Whichever space is no longer used for the meta device and its
+        // spare is added to the data device.
+        let vacated = match result {
+            Ok(_) => {
+                let mut vacated = self.segments.meta_segments.clone();
+                vacated.extend_from_slice(&self.segments.meta_spare_segments);
+                self.segments.meta_segments = new_meta_segments;
+                self.segments.meta_spare_segments = new_spare_segments;
+                vacated
+            }
+            Err(_) => {
+                let mut vacated = new_meta_segments;
+                vacated.extend(new_spare_segments);
+                vacated
+            }
+        };
+        let data_segments = coalesce_segs(&self.segments.data_segments, &vacated);
+        retry_dm(|| {
+            self.thin_pool
+                .set_data_table(get_dm(), segs_to_table(device, &data_segments))
+        })?;
+        retry_dm(|| self.thin_pool.resume(get_dm()))?;
+        self.segments.data_segments = data_segments;
+
+        result.map(|_| true)
+    }
+
+    /// The operation log stored on the MDV, oldest entry first.
+    pub fn operation_log(&self) -> StratisResult<Vec<OperationLogEntrySave>> {
+        self.mdv.operation_log()
+    }
+
     /// Rename a filesystem within the thin pool.
     ///
     /// * Ok(Some(true)) is returned if the filesystem was successfully renamed.
@@ -959,6 +2114,7 @@ impl ThinPool {
         new_name: &str,
     ) -> StratisResult<Option<bool>> {
         let old_name = rename_filesystem_pre!(self; uuid; new_name);
+        self.check_name_conflict(new_name)?;
         let new_name = Name::new(new_name.to_owned());
 
         let filesystem = self
@@ -972,12 +2128,96 @@ impl ThinPool {
             Err(err)
         } else {
             self.filesystems.insert(new_name, uuid, filesystem);
+            devlinks::filesystem_removed(pool_name, &old_name);
             let (new_name, fs) = self.filesystems.get_by_uuid(uuid).expect("Inserted above");
             fs.udev_fs_change(pool_name, uuid, &new_name);
+            if let Some(conflict_name) = self.name_conflicts.remove(&uuid) {
+                self.resolve_name_conflict(pool_name, &conflict_name);
+            }
             Ok(Some(true))
         }
     }
 
+    /// Whether detailed statistics are collected for the filesystems.
+    pub fn detailed_stats(&self) -> bool {
+        self.detailed_stats
+    }
+
+    /// Create or remove the dm-stats regions of all filesystems in the
+    /// pool. Filesystems created later get a region if detailed statistics
+    /// are enabled. The setting is changed even if some regions could not be
+    /// created or removed.
+    pub fn set_detailed_stats(&mut self, enabled: bool) -> StratisResult<()> {
+        self.detailed_stats = enabled;
+        for (_, _, fs) in self.filesystems.iter() {
+            if enabled {
+                fs.enable_detailed_stats()?;
+            } else {
+                fs.disable_detailed_stats()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Create the dm-stats region of a new filesystem if detailed
+    /// statistics are enabled. Failure to do so does not prevent the
+    /// filesystem from being used, so it is only logged.
+    fn init_detailed_stats(&self, uuid: FilesystemUuid, fs: &StratFilesystem) {
+        if self.detailed_stats {
+            if let Err(err) = fs.enable_detailed_stats() {
+                warn!(
+                    "Could not enable detailed statistics for filesystem with UUID {}: {}",
+                    uuid, err
+                );
+            }
+        }
+    }
+
+    /// Return an error if the given name is shared by several filesystems
+    /// in the metadata. A new filesystem with this name would only add to
+    /// the conflict.
+    fn check_name_conflict(&self, name: &str) -> StratisResult<()> {
+        if self.name_conflicts.values().any(|n| &**n == name) {
+            Err(StratisError::Msg(format!(
+                "The name {} is shared by several filesystems; rename all but one of them first",
+                name
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Whether the filesystem shares its name with some other filesystem.
+    pub fn has_name_conflict(&self, uuid: FilesystemUuid) -> bool {
+        self.name_conflicts.contains_key(&uuid)
+    }
+
+    /// If only one filesystem is left that has the given conflicting name,
+    /// give it back its name and make its symlinks.
+    fn resolve_name_conflict(&mut self, pool_name: &str, name: &Name) {
+        let remaining = self
+            .name_conflicts
+            .iter()
+            .filter(|(_, n)| *n == name)
+            .map(|(uuid, _)| *uuid)
+            .collect::<Vec<_>>();
+        if let [uuid] = remaining.as_slice() {
+            let uuid = *uuid;
+            self.name_conflicts.remove(&uuid);
+            let (_, fs) = self
+                .filesystems
+                .remove_by_uuid(uuid)
+                .expect("filesystems with conflicting names are in the table");
+            self.filesystems.insert(name.clone(), uuid, fs);
+            let (_, fs) = self.filesystems.get_by_uuid(uuid).expect("Inserted above");
+            fs.udev_fs_change(pool_name, uuid, name);
+            info!(
+                "Filesystem with UUID {} no longer shares its name {} with another filesystem",
+                uuid, name
+            );
+        }
+    }
+
     /// The names of DM devices belonging to this pool that may generate events
     pub fn get_eventing_dev_names(&self, pool_uuid: PoolUuid) -> Vec<DmNameBuf> {
         let mut eventing = vec![
@@ -997,10 +2237,10 @@ impl ThinPool {
     /// Suspend the thinpool
     pub fn suspend(&mut self) -> StratisResult<()> {
         // thindevs automatically suspended when thinpool is suspended
-        self.thin_pool.suspend(get_dm(), true)?;
+        retry_dm(|| self.thin_pool.suspend(get_dm(), true))?;
         // If MDV suspend fails, resume the thin pool and return the error
         if let Err(err) = self.mdv.suspend() {
-            if let Err(e) = self.thin_pool.resume(get_dm()) {
+            if let Err(e) = retry_dm(|| self.thin_pool.resume(get_dm())) {
                 Err(StratisError::Chained(
                     "Suspending the MDV failed and MDV suspend clean up action of resuming the thin pool also failed".to_string(),
                     // NOTE: This should potentially put the pool in maintenance-only
@@ -1022,7 +2262,7 @@ impl ThinPool {
     pub fn resume(&mut self) -> StratisResult<()> {
         self.mdv.resume()?;
         // thindevs automatically resumed here
-        self.thin_pool.resume(get_dm())?;
+        retry_dm(|| self.thin_pool.resume(get_dm()))?;
         Ok(())
     }
 
@@ -1083,8 +2323,8 @@ impl ThinPool {
             .map(&xform_target_line)
             .collect::<Vec<_>>();
 
-        self.thin_pool.set_meta_table(get_dm(), meta_table)?;
-        self.thin_pool.set_data_table(get_dm(), data_table)?;
+        retry_dm(|| self.thin_pool.set_meta_table(get_dm(), meta_table.clone()))?;
+        retry_dm(|| self.thin_pool.set_data_table(get_dm(), data_table.clone()))?;
         self.mdv.set_table(mdv_table)?;
 
         self.backstore_device = backstore_device;
@@ -1195,8 +2435,8 @@ fn attempt_thin_repair(
     thin_repair(&meta_dev.devnode(), &new_meta_dev.devnode())?;
 
     let name = meta_dev.name().to_owned();
-    meta_dev.teardown(get_dm())?;
-    new_meta_dev.set_name(get_dm(), &name)?;
+    retry_dm(|| meta_dev.teardown(get_dm()))?;
+    retry_dm(|| new_meta_dev.set_name(get_dm(), &name))?;
 
     Ok(new_meta_dev)
 }
@@ -1204,6 +2444,7 @@ fn attempt_thin_repair(
 #[cfg(test)]
 mod tests {
     use std::{
+        collections::HashSet,
         fs::OpenOptions,
         io::{BufWriter, Read, Write},
         path::Path,
@@ -1214,7 +2455,7 @@ mod tests {
     use devicemapper::{Bytes, SECTOR_SIZE};
 
     use crate::engine::{
-        shared::DEFAULT_THIN_DEV_SIZE,
+        shared::{DEFAULT_CACHE_BLOCK_SIZE, DEFAULT_THIN_DEV_SIZE},
         strat_engine::{
             cmd,
             metadata::MDADataSize,
@@ -1261,6 +2502,7 @@ mod tests {
             paths,
             MDADataSize::default(),
             &EncryptionInfo::default(),
+            None,
         )
         .unwrap();
 
@@ -1308,6 +2550,7 @@ mod tests {
             first_path,
             MDADataSize::default(),
             &EncryptionInfo::default(),
+            None,
         )
         .unwrap();
         let mut pool = ThinPool::new(
@@ -1426,6 +2669,7 @@ mod tests {
             paths,
             MDADataSize::default(),
             &EncryptionInfo::default(),
+            None,
         )
         .unwrap();
         let mut pool = ThinPool::new(
@@ -1526,6 +2770,9 @@ mod tests {
                 assert_eq!(read_buf[0..SECTOR_SIZE], write_buf[0..SECTOR_SIZE]);
             }
         }
+
+        let (shared, _) = pool.filesystem_shared_size(fs_uuid).unwrap();
+        assert!(shared > Sectors(0));
     }
 
     #[test]
@@ -1556,6 +2803,7 @@ mod tests {
             paths,
             MDADataSize::default(),
             &EncryptionInfo::default(),
+            None,
         )
         .unwrap();
         let mut pool = ThinPool::new(
@@ -1613,6 +2861,90 @@ mod tests {
         );
     }
 
+    /// Verify that filesystems which share a name in the metadata are set
+    /// up under placeholder names, that the shared name can not be reused,
+    /// and that renaming one of the filesystems resolves the conflict.
+    fn test_filesystem_name_conflict(paths: &[&Path]) {
+        let name1 = "name1";
+        let name2 = "name2";
+        let name3 = "name3";
+
+        let pool_uuid = PoolUuid::new_v4();
+        let mut backstore = Backstore::initialize(
+            pool_uuid,
+            paths,
+            MDADataSize::default(),
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        let mut pool = ThinPool::new(
+            pool_uuid,
+            &ThinPoolSizeParams::default(),
+            DATA_BLOCK_SIZE,
+            &mut backstore,
+        )
+        .unwrap();
+
+        let pool_name = "stratis_test_pool";
+        let fs_uuid1 = pool
+            .create_filesystem(pool_name, pool_uuid, name1, DEFAULT_THIN_DEV_SIZE)
+            .unwrap();
+        let fs_uuid2 = pool
+            .create_filesystem(pool_name, pool_uuid, name2, DEFAULT_THIN_DEV_SIZE)
+            .unwrap();
+
+        // Corrupt the metadata so that both filesystems have the same name.
+        {
+            let (_, fs) = pool.get_filesystem_by_uuid(fs_uuid2).unwrap();
+            pool.mdv
+                .save_fs(&Name::new(name1.to_owned()), fs_uuid2, fs)
+                .unwrap();
+        }
+
+        let flexdevs: FlexDevsSave = pool.record();
+        let thinpoolsave: ThinPoolDevSave = pool.record();
+
+        retry_operation!(pool.teardown());
+
+        let mut pool =
+            ThinPool::setup(pool_name, pool_uuid, &thinpoolsave, &flexdevs, &backstore).unwrap();
+
+        assert!(pool.has_name_conflict(fs_uuid1));
+        assert!(pool.has_name_conflict(fs_uuid2));
+        assert!(pool.get_filesystem_by_name(name1).is_none());
+        assert!(pool
+            .create_filesystem(pool_name, pool_uuid, name1, DEFAULT_THIN_DEV_SIZE)
+            .is_err());
+        assert!(pool.rename_filesystem(pool_name, fs_uuid2, name1).is_err());
+
+        assert_matches!(
+            pool.rename_filesystem(pool_name, fs_uuid2, name3),
+            Ok(Some(true))
+        );
+
+        assert!(!pool.has_name_conflict(fs_uuid1));
+        assert!(!pool.has_name_conflict(fs_uuid2));
+        assert_eq!(&*pool.get_filesystem_by_uuid(fs_uuid1).unwrap().0, name1);
+        assert_eq!(&*pool.get_filesystem_by_uuid(fs_uuid2).unwrap().0, name3);
+    }
+
+    #[test]
+    fn loop_test_filesystem_name_conflict() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(1, 3, None),
+            test_filesystem_name_conflict,
+        );
+    }
+
+    #[test]
+    fn real_test_filesystem_name_conflict() {
+        real::test_with_spec(
+            &real::DeviceLimits::AtLeast(1, None, None),
+            test_filesystem_name_conflict,
+        );
+    }
+
     /// Verify that setting up a pool when the pool has not been previously torn
     /// down does not fail. Clutter the original pool with a filesystem with
     /// some data on it.
@@ -1624,6 +2956,7 @@ mod tests {
             paths,
             MDADataSize::default(),
             &EncryptionInfo::default(),
+            None,
         )
         .unwrap();
         let mut pool = ThinPool::new(
@@ -1701,6 +3034,7 @@ mod tests {
             paths,
             MDADataSize::default(),
             &EncryptionInfo::default(),
+            None,
         )
         .unwrap();
         let mut pool = ThinPool::new(
@@ -1770,6 +3104,7 @@ mod tests {
             paths,
             MDADataSize::default(),
             &EncryptionInfo::default(),
+            None,
         )
         .unwrap();
         let mut pool = ThinPool::new(
@@ -1868,6 +3203,7 @@ mod tests {
             paths,
             MDADataSize::default(),
             &EncryptionInfo::default(),
+            None,
         )
         .unwrap();
         let mut pool = ThinPool::new(
@@ -1908,6 +3244,75 @@ mod tests {
         );
     }
 
+    /// Verify that compacting the MDV discards a stale filesystem record and
+    /// keeps the records of all the filesystems in the pool.
+    fn test_compact_metadata(paths: &[&Path]) {
+        let pool_name = "pool";
+        let pool_uuid = PoolUuid::new_v4();
+        let mut backstore = Backstore::initialize(
+            pool_uuid,
+            paths,
+            MDADataSize::default(),
+            &EncryptionInfo::default(),
+            None,
+        )
+        .unwrap();
+        let mut pool = ThinPool::new(
+            pool_uuid,
+            &ThinPoolSizeParams::default(),
+            DATA_BLOCK_SIZE,
+            &mut backstore,
+        )
+        .unwrap();
+
+        let fs_uuids = ["stratis_test_filesystem_1", "stratis_test_filesystem_2"]
+            .iter()
+            .map(|name| {
+                pool.create_filesystem(pool_name, pool_uuid, name, DEFAULT_THIN_DEV_SIZE)
+                    .unwrap()
+            })
+            .collect::<HashSet<_>>();
+
+        {
+            let fs_uuid = *fs_uuids.iter().next().unwrap();
+            let (_, fs) = pool.get_filesystem_by_uuid(fs_uuid).unwrap();
+            pool.mdv
+                .save_fs(&Name::new("stale".into()), FilesystemUuid::new_v4(), fs)
+                .unwrap();
+        }
+        assert_eq!(pool.mdv.filesystems().unwrap().len(), 3);
+
+        assert_eq!(pool.compact_metadata().unwrap(), 1);
+        let saved_uuids = pool
+            .mdv
+            .filesystems()
+            .unwrap()
+            .iter()
+            .map(|record| record.uuid)
+            .collect::<HashSet<_>>();
+        assert_eq!(saved_uuids, fs_uuids);
+
+        assert_eq!(pool.compact_metadata().unwrap(), 0);
+
+        pool.teardown().unwrap();
+    }
+
+    #[test]
+    fn loop_test_compact_metadata() {
+        loopbacked::test_with_spec(
+            &loopbacked::DeviceLimits::Range(1, 3, None),
+            test_compact_metadata,
+        );
+    }
+
+    #[test]
+    fn real_test_compact_metadata() {
+        real::test_with_spec(
+            &real::DeviceLimits::AtLeast(1, None, None),
+            test_compact_metadata,
+        );
+    }
+
     /// Set up thinpool and backstore. Set up filesystem and write to it.
     /// Add cachedev to backstore, causing cache to be built.
     /// Update device on self. Read written bits from filesystem
@@ -1924,6 +3329,7 @@ mod tests {
             paths2,
             MDADataSize::default(),
             &EncryptionInfo::default(),
+            None,
         )
         .unwrap();
         let mut pool = ThinPool::new(
@@ -1983,7 +3389,9 @@ mod tests {
         let old_device = backstore
             .device()
             .expect("Space already allocated from backstore, backstore must have device");
-        backstore.init_cache(pool_uuid, paths1).unwrap();
+        backstore
+            .init_cache(pool_uuid, paths1, DEFAULT_CACHE_BLOCK_SIZE)
+            .unwrap();
         let new_device = backstore
             .device()
             .expect("Space already allocated from backstore, backstore must have device");
@@ -2025,4 +3433,32 @@ mod tests {
     fn real_test_set_device() {
         real::test_with_spec(&real::DeviceLimits::AtLeast(2, None, None), test_set_device);
     }
+
+    #[test]
+    /// Verify that a range of a sub-device is mapped to the parts of the
+    /// segments of the cap device that hold it.
+    fn test_sub_device_ranges() {
+        let segments = [(Sectors(100), Sectors(10)), (Sectors(500), Sectors(20))];
+        assert_eq!(
+            sub_device_ranges(&segments, Sectors(5), Sectors(10)),
+            vec![(Sectors(105), Sectors(5)), (Sectors(500), Sectors(5))]
+        );
+        assert_eq!(
+            sub_device_ranges(&segments, Sectors(12), Sectors(3)),
+            vec![(Sectors(502), Sectors(3))]
+        );
+        assert!(sub_device_ranges(&segments, Sectors(30), Sectors(3)).is_empty());
+    }
+
+    #[test]
+    /// Verify that only the blocks of a device that are also mapped by some
+    /// other device are counted as shared, however the ranges overlap.
+    fn test_shared_blocks() {
+        let own = [(0, 10), (20, 10)];
+        assert_eq!(shared_blocks(&own, &[]), 0);
+        assert_eq!(shared_blocks(&own, &[(0, 10), (20, 10)]), 20);
+        assert_eq!(shared_blocks(&own, &[(5, 20)]), 10);
+        assert_eq!(shared_blocks(&own, &[(2, 3), (3, 4), (25, 2), (40, 5)]), 7);
+        assert_eq!(shared_blocks(&own, &[(10, 10)]), 0);
+    }
 }
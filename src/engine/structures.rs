@@ -5,10 +5,17 @@
 use std::{
     any::type_name,
     collections::{hash_map, HashMap},
+    convert::TryFrom,
     fmt,
+    future::Future,
     iter::IntoIterator,
     ops::{Deref, DerefMut},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex as StdMutex, RwLock as StdRwLock, Weak,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
 use futures::executor::block_on;
@@ -16,9 +23,34 @@ use tokio::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use crate::engine::{
     engine::Engine,
-    types::{AsUuid, Name},
+    types::{AsUuid, LockStats, Name},
 };
 
+/// How often a lock that is being waited for with a timeout is polled.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+lazy_static! {
+    static ref LOCK_COUNTERS: StdMutex<Vec<(&'static str, Weak<LockCounters>)>> =
+        StdMutex::new(Vec::new());
+    static ref OPERATION_TIMEOUT: StdRwLock<Option<Duration>> = StdRwLock::new(None);
+}
+
+/// Set how long a request waits for the engine to finish the operation it
+/// is busy with before the request fails with a timeout. If None, requests
+/// wait indefinitely.
+pub fn set_operation_timeout(timeout: Option<Duration>) {
+    *OPERATION_TIMEOUT
+        .write()
+        .expect("no thread panics while holding the lock") = timeout;
+}
+
+/// How long a request waits for the engine. See set_operation_timeout().
+pub fn operation_timeout() -> Option<Duration> {
+    *OPERATION_TIMEOUT
+        .read()
+        .expect("no thread panics while holding the lock")
+}
+
 /// Map UUID and name to T items.
 pub struct Table<U, T> {
     name_to_uuid: HashMap<Name, U>,
@@ -348,20 +380,98 @@ impl<G> Drop for ExclusiveGuard<G> {
     }
 }
 
-pub struct Lockable<T>(T);
+/// Counters of the contention on a lock, shared by all clones of the
+/// Lockable that holds the lock.
+#[derive(Debug, Default)]
+struct LockCounters {
+    waiters: AtomicU64,
+    acquisitions: AtomicU64,
+    wait_micros: AtomicU64,
+}
+
+impl LockCounters {
+    /// Create the counters for a lock, and register them under name so
+    /// that they are reported by lock_stats() while the lock exists.
+    fn register(name: &'static str) -> Arc<LockCounters> {
+        let counters = Arc::new(LockCounters::default());
+        let mut registered = LOCK_COUNTERS
+            .lock()
+            .expect("no thread panics while holding the lock");
+        registered.retain(|(_, c)| c.strong_count() > 0);
+        registered.push((name, Arc::downgrade(&counters)));
+        counters
+    }
+
+    /// Await the acquisition of the lock, counting the task as a waiter
+    /// and adding the time waited to the total, even if the acquisition is
+    /// abandoned.
+    async fn acquire<G>(&self, acquisition: impl Future<Output = G>) -> G {
+        self.try_acquire(async { Some(acquisition.await) })
+            .await
+            .expect("acquisition always yields a guard")
+    }
+
+    /// Like acquire(), but the acquisition may give up and yield None, in
+    /// which case the lock is not counted as acquired.
+    async fn try_acquire<G>(&self, acquisition: impl Future<Output = Option<G>>) -> Option<G> {
+        struct Waiter<'a>(&'a LockCounters, Instant);
+
+        impl<'a> Drop for Waiter<'a> {
+            fn drop(&mut self) {
+                let waited = u64::try_from(self.1.elapsed().as_micros()).unwrap_or(u64::MAX);
+                self.0.wait_micros.fetch_add(waited, Ordering::Relaxed);
+                self.0.waiters.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+
+        self.waiters.fetch_add(1, Ordering::Relaxed);
+        let waiter = Waiter(self, Instant::now());
+        let guard = acquisition.await;
+        drop(waiter);
+        if guard.is_some() {
+            self.acquisitions.fetch_add(1, Ordering::Relaxed);
+        }
+        guard
+    }
+
+    fn stats(&self) -> LockStats {
+        LockStats {
+            waiters: self.waiters.load(Ordering::Relaxed),
+            acquisitions: self.acquisitions.load(Ordering::Relaxed),
+            total_wait: Duration::from_micros(self.wait_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// The contention on each of the locks that exist, by the name of the lock.
+pub fn lock_stats() -> Vec<(&'static str, LockStats)> {
+    LOCK_COUNTERS
+        .lock()
+        .expect("no thread panics while holding the lock")
+        .iter()
+        .filter_map(|(name, counters)| counters.upgrade().map(|c| (*name, c.stats())))
+        .collect()
+}
+
+pub struct Lockable<T>(T, Arc<LockCounters>);
 
 impl<T> Lockable<Arc<Mutex<T>>>
 where
     T: 'static + Engine,
 {
     pub fn new_exclusive(t: T) -> Lockable<Arc<Mutex<dyn Engine>>> {
-        Lockable(Arc::new(Mutex::new(t)) as Arc<Mutex<dyn Engine>>)
+        Lockable(
+            Arc::new(Mutex::new(t)) as Arc<Mutex<dyn Engine>>,
+            LockCounters::register("engine"),
+        )
     }
 }
 
 impl<T> Lockable<Arc<RwLock<T>>> {
-    pub fn new_shared(t: T) -> Self {
-        Lockable(Arc::new(RwLock::new(t)))
+    /// Create a lock whose contention is reported by lock_stats() under
+    /// name.
+    pub fn new_shared(name: &'static str, t: T) -> Self {
+        Lockable(Arc::new(RwLock::new(t)), LockCounters::register(name))
     }
 }
 
@@ -371,7 +481,7 @@ where
 {
     pub async fn lock(&self) -> ExclusiveGuard<MutexGuard<'_, T>> {
         trace!("Acquiring exclusive lock on {}", type_name::<Self>());
-        let lock = ExclusiveGuard(self.0.lock().await);
+        let lock = ExclusiveGuard(self.1.acquire(self.0.lock()).await);
         trace!("Acquired exclusive lock on {}", type_name::<Self>());
         lock
     }
@@ -379,6 +489,45 @@ where
     pub fn blocking_lock(&self) -> ExclusiveGuard<MutexGuard<'_, T>> {
         block_on(self.lock())
     }
+
+    /// Acquire the lock, giving up if it could not be acquired within the
+    /// given time. If no time is given, wait for the lock indefinitely.
+    pub fn blocking_lock_timeout(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Option<ExclusiveGuard<MutexGuard<'_, T>>> {
+        let timeout = match timeout {
+            Some(timeout) => timeout,
+            None => return Some(self.blocking_lock()),
+        };
+        trace!(
+            "Acquiring exclusive lock on {} with a timeout of {:?}",
+            type_name::<Self>(),
+            timeout
+        );
+        let deadline = Instant::now() + timeout;
+        let lock = block_on(self.1.try_acquire(async {
+            loop {
+                if let Ok(guard) = self.0.try_lock() {
+                    return Some(guard);
+                }
+                if Instant::now() >= deadline {
+                    return None;
+                }
+                thread::sleep(LOCK_POLL_INTERVAL);
+            }
+        }))
+        .map(ExclusiveGuard);
+        if lock.is_some() {
+            trace!("Acquired exclusive lock on {}", type_name::<Self>());
+        } else {
+            trace!(
+                "Timed out acquiring exclusive lock on {}",
+                type_name::<Self>()
+            );
+        }
+        lock
+    }
 }
 
 impl<T> Lockable<Arc<RwLock<T>>>
@@ -387,7 +536,7 @@ where
 {
     pub async fn read(&self) -> SharedGuard<RwLockReadGuard<'_, T>> {
         trace!("Acquiring shared lock on {}", type_name::<Self>());
-        let lock = SharedGuard(self.0.read().await);
+        let lock = SharedGuard(self.1.acquire(self.0.read()).await);
         trace!("Acquired shared lock on {}", type_name::<Self>());
         lock
     }
@@ -398,7 +547,7 @@ where
 
     pub async fn write(&self) -> ExclusiveGuard<RwLockWriteGuard<'_, T>> {
         trace!("Acquiring exclusive lock on {}", type_name::<Self>());
-        let lock = ExclusiveGuard(self.0.write().await);
+        let lock = ExclusiveGuard(self.1.acquire(self.0.write()).await);
         trace!("Acquired exclusive lock on {}", type_name::<Self>());
         lock
     }
@@ -413,7 +562,7 @@ where
     T: ?Sized,
 {
     fn clone(&self) -> Self {
-        Lockable(Arc::clone(&self.0))
+        Lockable(Arc::clone(&self.0), Arc::clone(&self.1))
     }
 }
 
@@ -685,4 +834,62 @@ mod tests {
         assert_eq!(t.get_by_name(name3).unwrap().1.stuff, thing_key3);
         assert_eq!(t.len(), 1);
     }
+
+    #[test]
+    /// Acquisitions of a lock and the time spent waiting for them are
+    /// counted, and the counts are reported under the name of the lock for
+    /// as long as it exists.
+    fn lock_stats_counted() {
+        let name = "test_lock_stats_counted";
+        let lock = Lockable::new_shared(name, TestThing::new());
+        let find = || {
+            lock_stats()
+                .into_iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, stats)| stats)
+        };
+        assert_eq!(find().unwrap().acquisitions, 0);
+
+        {
+            let _read = lock.blocking_read();
+            let _also_read = lock.clone().blocking_read();
+        }
+        let _ = lock.blocking_write();
+
+        let stats = find().unwrap();
+        assert_eq!(stats.acquisitions, 3);
+        assert_eq!(stats.waiters, 0);
+
+        drop(lock);
+        assert!(find().is_none());
+    }
+
+    #[test]
+    /// Waiting for a held lock with a timeout gives up, and is not counted
+    /// as an acquisition.
+    fn lock_timeout() {
+        let name = "test_lock_timeout";
+        let lock = Lockable(
+            Arc::new(Mutex::new(TestThing::new())),
+            LockCounters::register(name),
+        );
+        {
+            let _held = lock.blocking_lock();
+            assert!(lock
+                .blocking_lock_timeout(Some(Duration::from_millis(50)))
+                .is_none());
+        }
+        assert!(lock
+            .blocking_lock_timeout(Some(Duration::from_millis(50)))
+            .is_some());
+        assert!(lock.blocking_lock_timeout(None).is_some());
+
+        let stats = lock_stats()
+            .into_iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, stats)| stats)
+            .unwrap();
+        assert_eq!(stats.acquisitions, 3);
+        assert_eq!(stats.waiters, 0);
+    }
 }
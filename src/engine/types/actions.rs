@@ -601,6 +601,27 @@ impl Display for SetDeleteAction<FilesystemUuid> {
     }
 }
 
+impl Display for SetDeleteAction<DevUuid> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.changed.is_empty() {
+            write!(
+                f,
+                "The requested devices are already absent; no action taken"
+            )
+        } else {
+            write!(
+                f,
+                "Devices with UUIDs {} were successfully removed from a pool",
+                self.changed
+                    .iter()
+                    .map(|u| u.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+    }
+}
+
 /// Action indicating a Clevis binding regeneration
 pub struct RegenAction;
 
@@ -6,6 +6,7 @@ use std::{
     convert::TryFrom,
     fmt::{self, Debug},
     hash::{Hash, Hasher},
+    path::PathBuf,
 };
 
 use serde_json::{Map, Value};
@@ -44,10 +45,166 @@ impl AsRef<[u8]> for SizedKeyMemory {
     }
 }
 
+/// The ciphers that may be used to encrypt a pool, each with the key sizes
+/// in bits that are supported for it.
+const SUPPORTED_CIPHERS: &[(&str, &[usize])] = &[
+    ("aes-xts-plain64", &[256, 512]),
+    ("serpent-xts-plain64", &[256, 512]),
+    ("twofish-xts-plain64", &[256, 512]),
+];
+
+/// The cipher and the size in bits of the media encryption key used to
+/// encrypt the devices of a pool.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct CipherInfo {
+    pub cipher: String,
+    pub key_size: usize,
+}
+
+impl CipherInfo {
+    /// Create a CipherInfo for encrypting a new pool. Returns an error if the
+    /// cipher or the key size is not supported.
+    pub fn new(cipher: &str, key_size: usize) -> StratisResult<CipherInfo> {
+        let cipher_info = CipherInfo {
+            cipher: cipher.to_owned(),
+            key_size,
+        };
+        cipher_info.validate()?;
+        Ok(cipher_info)
+    }
+
+    /// Return an error if the cipher or the key size is not supported for
+    /// encrypting a new pool.
+    pub fn validate(&self) -> StratisResult<()> {
+        match SUPPORTED_CIPHERS
+            .iter()
+            .find(|(name, _)| *name == self.cipher)
+        {
+            Some((_, key_sizes)) if key_sizes.contains(&self.key_size) => Ok(()),
+            Some((_, key_sizes)) => Err(StratisError::Msg(format!(
+                "Key size {} is not supported for cipher {}; supported key sizes are {:?}",
+                self.key_size, self.cipher, key_sizes
+            ))),
+            None => Err(StratisError::Msg(format!(
+                "Cipher {} is not supported; supported ciphers are {:?}",
+                self.cipher,
+                SUPPORTED_CIPHERS
+                    .iter()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>()
+            ))),
+        }
+    }
+
+    /// The cipher split into the cipher name and the cipher mode, as
+    /// cryptsetup expects it, e.g. ("aes", "xts-plain64").
+    pub fn cipher_and_mode(&self) -> (&str, &str) {
+        match self.cipher.find('-') {
+            Some(idx) => (&self.cipher[..idx], &self.cipher[idx + 1..]),
+            None => (&self.cipher, ""),
+        }
+    }
+
+    /// The size of the media encryption key in bytes.
+    pub fn key_size_bytes(&self) -> usize {
+        self.key_size / 8
+    }
+}
+
+impl Default for CipherInfo {
+    fn default() -> Self {
+        CipherInfo {
+            cipher: "aes-xts-plain64".to_string(),
+            key_size: 512,
+        }
+    }
+}
+
+impl fmt::Display for CipherInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} with a {} bit key", self.cipher, self.key_size)
+    }
+}
+
+/// The limits that cryptsetup places on the parameters of the argon2id PBKDF.
+const PBKDF_MIN_ITERATIONS: u32 = 4;
+const PBKDF_MIN_MEMORY_KIB: u32 = 32;
+const PBKDF_MAX_MEMORY_KIB: u32 = 4 * 1024 * 1024;
+const PBKDF_MAX_PARALLELISM: u32 = 4;
+
+/// The parameters of the argon2id PBKDF used to derive the key of the
+/// keyslot that holds the passphrase in the kernel keyring. If they are not
+/// specified, cryptsetup benchmarks the device to choose them.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct PbkdfParams {
+    pub iterations: u32,
+    pub memory_kib: u32,
+    pub parallelism: u32,
+}
+
+impl PbkdfParams {
+    /// Create PbkdfParams for binding a passphrase. Returns an error if any
+    /// of the parameters is outside the range that cryptsetup accepts.
+    pub fn new(iterations: u32, memory_kib: u32, parallelism: u32) -> StratisResult<PbkdfParams> {
+        let params = PbkdfParams {
+            iterations,
+            memory_kib,
+            parallelism,
+        };
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Return an error if any of the parameters is outside the range that
+    /// cryptsetup accepts.
+    pub fn validate(&self) -> StratisResult<()> {
+        if self.iterations < PBKDF_MIN_ITERATIONS {
+            return Err(StratisError::Msg(format!(
+                "PBKDF iterations must be at least {}, got {}",
+                PBKDF_MIN_ITERATIONS, self.iterations
+            )));
+        }
+        if self.memory_kib < PBKDF_MIN_MEMORY_KIB || self.memory_kib > PBKDF_MAX_MEMORY_KIB {
+            return Err(StratisError::Msg(format!(
+                "PBKDF memory must be between {} KiB and {} KiB, got {} KiB",
+                PBKDF_MIN_MEMORY_KIB, PBKDF_MAX_MEMORY_KIB, self.memory_kib
+            )));
+        }
+        if self.parallelism < 1 || self.parallelism > PBKDF_MAX_PARALLELISM {
+            return Err(StratisError::Msg(format!(
+                "PBKDF parallelism must be between 1 and {}, got {}",
+                PBKDF_MAX_PARALLELISM, self.parallelism
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PbkdfParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "argon2id with {} iterations, {} KiB of memory, and {} threads",
+            self.iterations, self.memory_kib, self.parallelism
+        )
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct EncryptionInfo {
     pub key_description: Option<KeyDescription>,
     pub clevis_info: Option<(String, Value)>,
+    /// The cipher used if the pool is encrypted.
+    #[serde(default)]
+    pub cipher_info: CipherInfo,
+    /// The PBKDF parameters used for the passphrase in the kernel keyring,
+    /// if they were specified when the pool was created.
+    #[serde(default)]
+    pub pbkdf_params: Option<PbkdfParams>,
+    /// The directory in which the LUKS2 headers of the devices are stored,
+    /// if they are detached from the devices.
+    #[serde(default)]
+    pub header_dir: Option<PathBuf>,
 }
 
 impl EncryptionInfo {
@@ -61,6 +218,9 @@ impl Default for EncryptionInfo {
         EncryptionInfo {
             key_description: None,
             clevis_info: None,
+            cipher_info: CipherInfo::default(),
+            pbkdf_params: None,
+            header_dir: None,
         }
     }
 }
@@ -77,10 +237,20 @@ impl fmt::Display for EncryptionInfo {
                 f,
                 "{}, clevis pin: \"{}\", clevis configuration: \"{}\"",
                 key_desc_str, pin, config
-            )
+            )?;
         } else {
-            write!(f, "{}, no Clevis information", key_desc_str)
+            write!(f, "{}, no Clevis information", key_desc_str)?;
+        }
+        if self.is_encrypted() {
+            write!(f, ", cipher: {}", self.cipher_info)?;
         }
+        if let Some(ref params) = self.pbkdf_params {
+            write!(f, ", PBKDF: {}", params)?;
+        }
+        if let Some(ref dir) = self.header_dir {
+            write!(f, ", detached headers in: {}", dir.display())?;
+        }
+        Ok(())
     }
 }
 
@@ -95,6 +265,9 @@ impl Hash for EncryptionInfo {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.key_description.hash(state);
         self.clevis_info.as_ref().map(|(pin, _)| pin).hash(state);
+        self.cipher_info.hash(state);
+        self.pbkdf_params.hash(state);
+        self.header_dir.hash(state);
     }
 }
 
@@ -111,6 +284,32 @@ impl<'a> Into<Value> for &'a EncryptionInfo {
             json.insert("clevis_pin".to_string(), Value::from(info.0.to_owned()));
             json.insert("clevis_config".to_string(), info.1.clone());
         }
+        if self.is_encrypted() {
+            json.insert(
+                "cipher".to_string(),
+                Value::from(self.cipher_info.cipher.to_owned()),
+            );
+            json.insert(
+                "key_size".to_string(),
+                Value::from(self.cipher_info.key_size),
+            );
+        }
+        if let Some(ref params) = self.pbkdf_params {
+            json.insert(
+                "pbkdf".to_string(),
+                json!({
+                    "iterations": params.iterations,
+                    "memory_kib": params.memory_kib,
+                    "parallelism": params.parallelism,
+                }),
+            );
+        }
+        if let Some(ref dir) = self.header_dir {
+            json.insert(
+                "header_dir".to_string(),
+                Value::from(dir.display().to_string()),
+            );
+        }
         Value::from(json)
     }
 }
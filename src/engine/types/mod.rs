@@ -9,16 +9,22 @@ use std::{
     ffi::OsStr,
     fmt::{self, Debug, Display},
     hash::Hash,
+    iter::once,
     ops::Deref,
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
 
+use chrono::{DateTime, Utc};
 use libudev::EventType;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
+use devicemapper::{Bytes, Sectors};
+
 pub use crate::engine::{
     engine::Engine,
     structures::Lockable,
@@ -28,7 +34,7 @@ pub use crate::engine::{
             MappingDeleteAction, RegenAction, RenameAction, SetCreateAction, SetDeleteAction,
             SetUnlockAction,
         },
-        keys::{EncryptionInfo, KeyDescription, SizedKeyMemory},
+        keys::{CipherInfo, EncryptionInfo, KeyDescription, PbkdfParams, SizedKeyMemory},
     },
 };
 use crate::stratis::{StratisError, StratisResult};
@@ -76,6 +82,13 @@ macro_rules! uuid {
 /// An engine that can be locked for synchronization.
 pub type LockableEngine = Lockable<Arc<Mutex<dyn Engine>>>;
 
+/// Work which reads the data of a device, prepared while the engine is
+/// locked and done after the lock has been released, so that other requests
+/// are not held up while the data is read. The work is passed a function
+/// which it calls with the number of sectors done so far and the total
+/// number of sectors.
+pub type ReadWork<T> = Box<dyn FnOnce(&mut dyn FnMut(Sectors, Sectors)) -> StratisResult<T> + Send>;
+
 pub trait AsUuid:
     Copy
     + Clone
@@ -155,14 +168,1081 @@ pub enum BlockDevTier {
     Cache = 1,
 }
 
+/// A long-running operation that the engine may be performing on a pool.
+/// While an operation is in progress, mutating operations on the pool
+/// which would conflict with it are rejected.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PoolOperation {
+    /// The kernel has flagged the thin pool metadata as requiring a check,
+    /// and the metadata is awaiting or undergoing repair via thin_repair.
+    ThinRepair,
+    /// A filesystem is being copied into the pool, for example because it
+    /// is being moved from another pool.
+    CopyFilesystem,
+    /// The dirty blocks in the pool's cache are being written back.
+    FlushCache,
+}
+
+impl Display for PoolOperation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PoolOperation::ThinRepair => write!(f, "thin_repair"),
+            PoolOperation::CopyFilesystem => write!(f, "copy_filesystem"),
+            PoolOperation::FlushCache => write!(f, "flush_cache"),
+        }
+    }
+}
+
+/// An operation that a client may request on a pool, and that may not be
+/// valid given the state and the configuration of the pool.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PoolCapability {
+    CreateFilesystems,
+    DestroyFilesystems,
+    SnapshotFilesystem,
+    AddDataDevices,
+    RemoveDataDevice,
+    InitCache,
+    ReserveCacheDevices,
+    AddCacheDevices,
+    FlushCache,
+    RemoveCache,
+    BindKeyring,
+    UnbindKeyring,
+    RebindKeyring,
+    BindClevis,
+    UnbindClevis,
+    RebindClevis,
+    CompactMetadata,
+}
+
+impl PoolCapability {
+    /// All operations whose validity can be queried.
+    pub const ALL: [PoolCapability; 17] = [
+        PoolCapability::CreateFilesystems,
+        PoolCapability::DestroyFilesystems,
+        PoolCapability::SnapshotFilesystem,
+        PoolCapability::AddDataDevices,
+        PoolCapability::RemoveDataDevice,
+        PoolCapability::InitCache,
+        PoolCapability::ReserveCacheDevices,
+        PoolCapability::AddCacheDevices,
+        PoolCapability::FlushCache,
+        PoolCapability::RemoveCache,
+        PoolCapability::BindKeyring,
+        PoolCapability::UnbindKeyring,
+        PoolCapability::RebindKeyring,
+        PoolCapability::BindClevis,
+        PoolCapability::UnbindClevis,
+        PoolCapability::RebindClevis,
+        PoolCapability::CompactMetadata,
+    ];
+}
+
+impl Display for PoolCapability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PoolCapability::CreateFilesystems => write!(f, "create_filesystems"),
+            PoolCapability::DestroyFilesystems => write!(f, "destroy_filesystems"),
+            PoolCapability::SnapshotFilesystem => write!(f, "snapshot_filesystem"),
+            PoolCapability::AddDataDevices => write!(f, "add_data_devices"),
+            PoolCapability::RemoveDataDevice => write!(f, "remove_data_device"),
+            PoolCapability::InitCache => write!(f, "init_cache"),
+            PoolCapability::ReserveCacheDevices => write!(f, "reserve_cache_devices"),
+            PoolCapability::AddCacheDevices => write!(f, "add_cache_devices"),
+            PoolCapability::FlushCache => write!(f, "flush_cache"),
+            PoolCapability::RemoveCache => write!(f, "remove_cache"),
+            PoolCapability::BindKeyring => write!(f, "bind_keyring"),
+            PoolCapability::UnbindKeyring => write!(f, "unbind_keyring"),
+            PoolCapability::RebindKeyring => write!(f, "rebind_keyring"),
+            PoolCapability::BindClevis => write!(f, "bind_clevis"),
+            PoolCapability::UnbindClevis => write!(f, "unbind_clevis"),
+            PoolCapability::RebindClevis => write!(f, "rebind_clevis"),
+            PoolCapability::CompactMetadata => write!(f, "compact_metadata"),
+        }
+    }
+}
+
+/// A feature of the kernel on which some engine operations depend, and
+/// which older kernels may lack.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum KernelFeature {
+    ThinProvisioning,
+    Cache,
+    Encryption,
+}
+
+impl KernelFeature {
+    /// All kernel features that the engine checks for.
+    pub const ALL: [KernelFeature; 3] = [
+        KernelFeature::ThinProvisioning,
+        KernelFeature::Cache,
+        KernelFeature::Encryption,
+    ];
+}
+
+impl Display for KernelFeature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KernelFeature::ThinProvisioning => write!(f, "thin_provisioning"),
+            KernelFeature::Cache => write!(f, "cache"),
+            KernelFeature::Encryption => write!(f, "encryption"),
+        }
+    }
+}
+
+/// Whether the kernel supports a feature.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KernelFeatureSupport {
+    Supported,
+    Unsupported,
+    /// Support could not be determined, for example because the kernel
+    /// module that provides the feature has not been loaded yet.
+    Unknown,
+}
+
+impl Display for KernelFeatureSupport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KernelFeatureSupport::Supported => write!(f, "supported"),
+            KernelFeatureSupport::Unsupported => write!(f, "unsupported"),
+            KernelFeatureSupport::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// The sizes of the data and metadata devices of a thin pool, and the
+/// largest sizes to which they can be extended.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ThinPoolLimits {
+    pub data_size: Sectors,
+    pub max_data_size: Sectors,
+    pub meta_size: Sectors,
+    pub max_meta_size: Sectors,
+}
+
+/// The layout of a striped data tier. Each allocation from the data tier is
+/// spread over count devices, size sectors at a time.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StripeConfig {
+    pub count: u16,
+    pub size: Sectors,
+}
+
+/// Whether a device could be used to create a new pool.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DeviceVerdict {
+    /// The device can be used; the value is the space on the device that
+    /// would be available for data.
+    Usable(Bytes),
+    /// The device, of the given size, is smaller than the minimum size of
+    /// a Stratis block device.
+    TooSmall(Bytes),
+    /// The device already belongs to the Stratis pool with the given UUID.
+    Claimed(PoolUuid),
+    /// The device appears to be in use for something other than Stratis.
+    ForeignSignature(String),
+    /// The logical sector size of the device, in bytes, differs from that
+    /// of the other devices.
+    WrongSectorSize(u64),
+    /// The device is the same device as the one at the given path, which
+    /// was also specified.
+    Duplicate(PathBuf),
+    /// The device could not be examined.
+    Unavailable(String),
+}
+
+impl DeviceVerdict {
+    pub fn is_usable(&self) -> bool {
+        matches!(self, DeviceVerdict::Usable(_))
+    }
+
+    /// A short identifier for the kind of verdict.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeviceVerdict::Usable(_) => "usable",
+            DeviceVerdict::TooSmall(_) => "too_small",
+            DeviceVerdict::Claimed(_) => "claimed",
+            DeviceVerdict::ForeignSignature(_) => "foreign_signature",
+            DeviceVerdict::WrongSectorSize(_) => "wrong_sector_size",
+            DeviceVerdict::Duplicate(_) => "duplicate",
+            DeviceVerdict::Unavailable(_) => "unavailable",
+        }
+    }
+}
+
+impl Display for DeviceVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeviceVerdict::Usable(size) => write!(f, "{} available for data", size),
+            DeviceVerdict::TooSmall(size) => write!(
+                f,
+                "device size {} is smaller than the minimum size of a Stratis block device",
+                size
+            ),
+            DeviceVerdict::Claimed(pool_uuid) => {
+                write!(f, "device belongs to Stratis pool with UUID {}", pool_uuid)
+            }
+            DeviceVerdict::ForeignSignature(description) => {
+                write!(f, "device is a {}", description)
+            }
+            DeviceVerdict::WrongSectorSize(size) => write!(
+                f,
+                "logical sector size {} differs from that of the other devices",
+                size
+            ),
+            DeviceVerdict::Duplicate(path) => {
+                write!(f, "device is the same device as {}", path.display())
+            }
+            DeviceVerdict::Unavailable(reason) => {
+                write!(f, "device could not be examined: {}", reason)
+            }
+        }
+    }
+}
+
+/// The action taken by the engine when writing the pool-level metadata
+/// to the devices of a pool fails.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MetadataWriteFailurePolicy {
+    /// Mark the pool read-only immediately; further changes to the pool
+    /// are rejected until a later metadata write succeeds, which happens
+    /// when the policy is changed or the pool's transient state is reset,
+    /// or until stratisd is restarted. Changing the policy to Alert makes
+    /// the pool writable again even if the write fails.
+    ReadOnly,
+    /// Retry the write a small number of times, then mark the pool
+    /// read-only if it still fails, as ReadOnly does.
+    RetryThenReadOnly,
+    /// Only report the failure; the pool remains writable.
+    Alert,
+}
+
+impl Default for MetadataWriteFailurePolicy {
+    fn default() -> Self {
+        MetadataWriteFailurePolicy::Alert
+    }
+}
+
+impl<'a> TryFrom<&'a str> for MetadataWriteFailurePolicy {
+    type Error = StratisError;
+
+    fn try_from(s: &str) -> StratisResult<MetadataWriteFailurePolicy> {
+        match s {
+            "read_only" => Ok(MetadataWriteFailurePolicy::ReadOnly),
+            "retry_then_read_only" => Ok(MetadataWriteFailurePolicy::RetryThenReadOnly),
+            "alert" => Ok(MetadataWriteFailurePolicy::Alert),
+            _ => Err(StratisError::Msg(format!(
+                "{} is an invalid metadata write failure policy",
+                s
+            ))),
+        }
+    }
+}
+
+impl Display for MetadataWriteFailurePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MetadataWriteFailurePolicy::ReadOnly => write!(f, "read_only"),
+            MetadataWriteFailurePolicy::RetryThenReadOnly => write!(f, "retry_then_read_only"),
+            MetadataWriteFailurePolicy::Alert => write!(f, "alert"),
+        }
+    }
+}
+
+/// The action taken by the engine when a data device that is smaller than
+/// the existing data devices of a pool is added to it. With a striped data
+/// tier, only as much of each device as the smallest one provides can be
+/// used, so that the excess on the larger devices is wasted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SmallDevicePolicy {
+    /// Log a warning and add the device.
+    Warn,
+    /// Refuse to add the device.
+    Reject,
+    /// Refuse to add the device unless the override is given.
+    RequireOverride,
+}
+
+impl Default for SmallDevicePolicy {
+    fn default() -> Self {
+        SmallDevicePolicy::Warn
+    }
+}
+
+impl<'a> TryFrom<&'a str> for SmallDevicePolicy {
+    type Error = StratisError;
+
+    fn try_from(s: &str) -> StratisResult<SmallDevicePolicy> {
+        match s {
+            "warn" => Ok(SmallDevicePolicy::Warn),
+            "reject" => Ok(SmallDevicePolicy::Reject),
+            "require_override" => Ok(SmallDevicePolicy::RequireOverride),
+            _ => Err(StratisError::Msg(format!(
+                "{} is an invalid small device policy",
+                s
+            ))),
+        }
+    }
+}
+
+impl Display for SmallDevicePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SmallDevicePolicy::Warn => write!(f, "warn"),
+            SmallDevicePolicy::Reject => write!(f, "reject"),
+            SmallDevicePolicy::RequireOverride => write!(f, "require_override"),
+        }
+    }
+}
+
+/// The strategy by which space is chosen for an allocation from the data
+/// devices of a pool, among the devices and among the free ranges of each
+/// device.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum AllocationStrategy {
+    /// Take the first free space found, in the order of the devices and of
+    /// the ranges on each device. This is the quickest.
+    FirstFit,
+    /// Take the smallest free space that holds the whole allocation, or if
+    /// there is none, the largest free spaces first. This splits
+    /// allocations into fewer pieces and leaves larger free spaces intact.
+    BestFit,
+}
+
+impl Default for AllocationStrategy {
+    fn default() -> Self {
+        AllocationStrategy::FirstFit
+    }
+}
+
+impl<'a> TryFrom<&'a str> for AllocationStrategy {
+    type Error = StratisError;
+
+    fn try_from(s: &str) -> StratisResult<AllocationStrategy> {
+        match s {
+            "first_fit" => Ok(AllocationStrategy::FirstFit),
+            "best_fit" => Ok(AllocationStrategy::BestFit),
+            _ => Err(StratisError::Msg(format!(
+                "{} is an invalid allocation strategy",
+                s
+            ))),
+        }
+    }
+}
+
+impl Display for AllocationStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AllocationStrategy::FirstFit => write!(f, "first_fit"),
+            AllocationStrategy::BestFit => write!(f, "best_fit"),
+        }
+    }
+}
+
+/// The kind of path by which a device is reported.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DevicePathStyle {
+    /// The kernel devnode, e.g. /dev/sda, which may change across reboots.
+    Devnode,
+    /// A symlink in /dev/disk/by-id, derived from the identity of the
+    /// device.
+    ById,
+    /// A symlink in /dev/disk/by-path, derived from the hardware path to
+    /// the device.
+    ByPath,
+}
+
+impl Default for DevicePathStyle {
+    fn default() -> Self {
+        DevicePathStyle::Devnode
+    }
+}
+
+impl<'a> TryFrom<&'a str> for DevicePathStyle {
+    type Error = StratisError;
+
+    fn try_from(s: &str) -> StratisResult<DevicePathStyle> {
+        match s {
+            "devnode" => Ok(DevicePathStyle::Devnode),
+            "by-id" => Ok(DevicePathStyle::ById),
+            "by-path" => Ok(DevicePathStyle::ByPath),
+            _ => Err(StratisError::Msg(format!(
+                "{} is an invalid device path style",
+                s
+            ))),
+        }
+    }
+}
+
+impl Display for DevicePathStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DevicePathStyle::Devnode => write!(f, "devnode"),
+            DevicePathStyle::ById => write!(f, "by-id"),
+            DevicePathStyle::ByPath => write!(f, "by-path"),
+        }
+    }
+}
+
+/// A metric of the state of a pool for which an alert threshold can be set.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum AlertMetric {
+    /// The physical space used by the pool, as a percentage of its total
+    /// physical size.
+    PhysicalUsage,
+    /// The space used in the thin pool metadata device, as a percentage of
+    /// its size.
+    MetadataUsage,
+    /// The total logical size of the filesystems in the pool, as a
+    /// percentage of the total physical size of the pool.
+    OverprovisionRatio,
+    /// 1 if the thin pool is in a degraded state, i.e. failed, read-only or
+    /// out of data space, otherwise 0.
+    Degraded,
+}
+
+impl AlertMetric {
+    /// All the metrics, in the order in which they are checked.
+    pub const ALL: [AlertMetric; 4] = [
+        AlertMetric::PhysicalUsage,
+        AlertMetric::MetadataUsage,
+        AlertMetric::OverprovisionRatio,
+        AlertMetric::Degraded,
+    ];
+}
+
+impl<'a> TryFrom<&'a str> for AlertMetric {
+    type Error = StratisError;
+
+    fn try_from(s: &str) -> StratisResult<AlertMetric> {
+        match s {
+            "physical_usage" => Ok(AlertMetric::PhysicalUsage),
+            "metadata_usage" => Ok(AlertMetric::MetadataUsage),
+            "overprovision_ratio" => Ok(AlertMetric::OverprovisionRatio),
+            "degraded" => Ok(AlertMetric::Degraded),
+            _ => Err(StratisError::Msg(format!(
+                "{} is an invalid alert metric",
+                s
+            ))),
+        }
+    }
+}
+
+impl Display for AlertMetric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AlertMetric::PhysicalUsage => write!(f, "physical_usage"),
+            AlertMetric::MetadataUsage => write!(f, "metadata_usage"),
+            AlertMetric::OverprovisionRatio => write!(f, "overprovision_ratio"),
+            AlertMetric::Degraded => write!(f, "degraded"),
+        }
+    }
+}
+
+/// The thresholds at or above which an alert is raised for the metrics of a
+/// pool. No alert is raised for a metric without a threshold.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AlertThresholds {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub physical_usage: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_usage: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overprovision_ratio: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub degraded: Option<u64>,
+}
+
+impl AlertThresholds {
+    /// The threshold for the given metric.
+    pub fn get(&self, metric: AlertMetric) -> Option<u64> {
+        match metric {
+            AlertMetric::PhysicalUsage => self.physical_usage,
+            AlertMetric::MetadataUsage => self.metadata_usage,
+            AlertMetric::OverprovisionRatio => self.overprovision_ratio,
+            AlertMetric::Degraded => self.degraded,
+        }
+    }
+
+    /// Set the threshold for the given metric.
+    pub fn set(&mut self, metric: AlertMetric, threshold: Option<u64>) {
+        match metric {
+            AlertMetric::PhysicalUsage => self.physical_usage = threshold,
+            AlertMetric::MetadataUsage => self.metadata_usage = threshold,
+            AlertMetric::OverprovisionRatio => self.overprovision_ratio = threshold,
+            AlertMetric::Degraded => self.degraded = threshold,
+        }
+    }
+}
+
+/// An alert on a metric of a pool which is at or above its threshold.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ActiveAlert {
+    pub metric: AlertMetric,
+    /// The value of the metric when the pool was last monitored.
+    pub value: u64,
+    pub threshold: u64,
+    /// Whether an operator has acknowledged the alert, so that it is no
+    /// longer raised.
+    pub acknowledged: bool,
+}
+
+/// A change in the state of a pool which the engine detected on its own,
+/// rather than as the direct result of a request, and of which IPC clients
+/// should be notified.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PoolEvent {
+    /// The long-running operation in progress on the pool changed.
+    OperationChanged(Option<PoolOperation>),
+    /// Writing the pool-level metadata failed. The policy that was applied
+    /// and whether the pool is now read-only are recorded with the error.
+    MetadataWriteFailed {
+        policy: MetadataWriteFailurePolicy,
+        read_only: bool,
+        error: String,
+    },
+    /// The value of a metric of the pool is at or above the alert threshold
+    /// set for it. The alert is raised each time the pool is monitored until
+    /// it is acknowledged; after that it is raised again only once the value
+    /// has fallen below the threshold and reached it again.
+    Alert {
+        metric: AlertMetric,
+        value: u64,
+        threshold: u64,
+    },
+    /// The thin pool metadata device became too small for the current size
+    /// of the data tier. The current and the recommended size are recorded.
+    MetadataUndersized { current: Bytes, recommended: Bytes },
+    /// A snapshot was destroyed because the physical usage of the pool
+    /// reached the threshold of its automatic snapshot pruning policy.
+    SnapshotPruned { uuid: FilesystemUuid, name: Name },
+    /// The amount of data that only the filesystem holds, rather than
+    /// sharing it with its origin and its snapshots, exceeds the
+    /// copy-on-write alert threshold set for the filesystem. The alert is
+    /// raised once each time the amount comes to exceed the threshold.
+    FilesystemCowAlert {
+        uuid: FilesystemUuid,
+        name: Name,
+        unique: Bytes,
+        threshold: Bytes,
+    },
+}
+
+/// A change in the state of the engine, of which the observers subscribed
+/// to the engine are notified.
+// FIXME: Changes to the filesystems and blockdevs of a pool are made by the
+// pool, which has no access to the observers, and so are not yet reported.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EngineEvent {
+    /// A pool was created or set up, and now belongs to the engine.
+    PoolAdded { uuid: PoolUuid, name: Name },
+    /// A pool was destroyed.
+    PoolRemoved(PoolUuid),
+    /// A pool was renamed.
+    PoolRenamed { uuid: PoolUuid, name: Name },
+    /// The engine detected a change in the state of a pool on its own.
+    Pool(PoolUuid, PoolEvent),
+}
+
+/// The state of a pool, as reported in a PoolSummary.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PoolState {
+    /// The pool is set up and no long-running operation is in progress.
+    Running,
+    /// The pool is set up and the given long-running operation is in
+    /// progress on it.
+    Busy(PoolOperation),
+    /// The pool is encrypted and has not yet been unlocked and set up.
+    Locked,
+}
+
+impl Display for PoolState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PoolState::Running => write!(f, "running"),
+            PoolState::Busy(operation) => write!(f, "busy ({})", operation),
+            PoolState::Locked => write!(f, "locked"),
+        }
+    }
+}
+
+/// A summary of a pool known to the engine, whether it is set up or locked.
+/// The name and sizes of a locked pool can not be known until it is
+/// unlocked, so they are None for a locked pool.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolSummary {
+    pub uuid: PoolUuid,
+    pub name: Option<Name>,
+    pub state: PoolState,
+    pub total_physical_size: Option<Sectors>,
+    pub total_physical_used: Option<Sectors>,
+    pub encrypted: bool,
+}
+
+/// The number of LUKS2 keyslots in use on a device, and the number of
+/// keyslots that the LUKS2 format provides.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct KeyslotUsage {
+    pub used: u32,
+    pub total: u32,
+}
+
+/// The encryption of a pool, as reported for an audit. No key material or
+/// Clevis configuration is included. The name and the keyslot usage of a
+/// locked pool can not be known until it is unlocked, so they are None for
+/// a locked pool; the keyslot usage is also None if it could not be read.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EncryptionAudit {
+    pub uuid: PoolUuid,
+    pub name: Option<Name>,
+    pub cipher_info: CipherInfo,
+    pub pbkdf_params: Option<PbkdfParams>,
+    /// Whether the pool is bound to a passphrase in the kernel keyring
+    pub keyring: bool,
+    /// The Clevis pin the pool is bound with, if it is bound with Clevis
+    pub clevis_pin: Option<String>,
+    /// The keyslot usage of the device of the pool with the most keyslots
+    /// in use
+    pub keyslots: Option<KeyslotUsage>,
+}
+
+/// An entry in the persistent log of significant operations performed on
+/// a pool.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OperationLogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub operation: String,
+}
+
+/// A filesystem that has been deleted, but is kept until the deletion
+/// grace period of its pool is over, so that it can be undeleted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeletedFilesystem {
+    pub uuid: FilesystemUuid,
+    pub name: String,
+    pub size: Sectors,
+    pub deleted: DateTime<Utc>,
+    /// The time after which the filesystem is destroyed when the pool is
+    /// next monitored
+    pub purge_after: DateTime<Utc>,
+}
+
+/// The contention on a lock since the lock was created.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LockStats {
+    /// The number of tasks waiting to acquire the lock
+    pub waiters: u64,
+    /// The number of times the lock has been acquired
+    pub acquisitions: u64,
+    /// The total time spent waiting to acquire the lock
+    pub total_wait: Duration,
+}
+
+/// A sample of the physical space used by a pool and the total physical
+/// space available to it, taken at the given time.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CapacitySample {
+    pub timestamp: DateTime<Utc>,
+    pub used: Sectors,
+    pub total: Sectors,
+}
+
+/// The configuration of a pool, without its devices, its data, or any
+/// UUIDs, from which a pool with the same configuration can be created on
+/// other devices. Secrets are never part of the configuration: only the
+/// key description and the Clevis configuration are recorded.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PoolTemplate {
+    pub encryption_info: EncryptionInfo,
+    pub stripe: Option<StripeConfig>,
+    pub metadata_write_failure_policy: MetadataWriteFailurePolicy,
+    pub auto_start: bool,
+    pub start_priority: Option<u32>,
+    pub detailed_stats: bool,
+    pub alert_thresholds: AlertThresholds,
+    pub filesystems: Vec<FilesystemTemplate>,
+}
+
+/// A filesystem of a pool template.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FilesystemTemplate {
+    pub name: String,
+    pub size: Sectors,
+}
+
+/// An operation that is part of a transaction. Pools are referred to by
+/// name, so that an operation may refer to a pool created by an earlier
+/// operation of the same transaction.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum TransactionOperation {
+    CreatePool {
+        name: String,
+        devices: Vec<PathBuf>,
+        #[serde(default)]
+        encryption_info: EncryptionInfo,
+    },
+    CreateFilesystem {
+        pool: String,
+        name: String,
+        size: Option<Bytes>,
+    },
+    BindKeyring {
+        pool: String,
+        key_description: KeyDescription,
+    },
+    BindClevis {
+        pool: String,
+        pin: String,
+        clevis_info: Value,
+    },
+}
+
+/// A change made by an operation of a transaction, which is reverted if a
+/// later operation of the transaction fails.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TransactionEffect {
+    PoolCreated(PoolUuid),
+    FilesystemCreated(PoolUuid, FilesystemUuid),
+    KeyringBound(PoolUuid),
+    ClevisBound(PoolUuid),
+}
+
+/// A set of changes to the devices of a pool, used to simulate their effect
+/// on the capacity of the pool without applying them.
+// FIXME: Only changes to the set of data devices can be simulated. Changes
+// to redundancy or integrity can be added once a pool supports them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ReconfigurationChanges {
+    /// Devices that would be added to the data tier
+    pub add_data_devices: Vec<PathBuf>,
+    /// Data devices of the pool that would be removed
+    pub remove_data_devices: Vec<DevUuid>,
+}
+
+/// The capacity of a pool that would result from a reconfiguration.
+/// usable + overhead == total
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CapacityEstimate {
+    /// The total size of the data tier
+    pub total: Sectors,
+    /// The space occupied by metadata: the Stratis metadata on each device,
+    /// the thin pool metadata device and its spare, and the MDV
+    pub overhead: Sectors,
+    /// The space that would be available for filesystem data
+    pub usable: Sectors,
+}
+
+/// A checksum algorithm with which an integrity layer could detect the
+/// corruption of the data of a pool.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IntegrityAlgorithm {
+    Crc32c,
+    Xxhash64,
+    Sha1,
+    Sha256,
+}
+
+impl IntegrityAlgorithm {
+    /// The size of the checksum of one block.
+    pub fn tag_size(self) -> Bytes {
+        match self {
+            IntegrityAlgorithm::Crc32c => Bytes(4),
+            IntegrityAlgorithm::Xxhash64 => Bytes(8),
+            IntegrityAlgorithm::Sha1 => Bytes(20),
+            IntegrityAlgorithm::Sha256 => Bytes(32),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for IntegrityAlgorithm {
+    type Error = StratisError;
+
+    fn try_from(s: &str) -> StratisResult<IntegrityAlgorithm> {
+        match s {
+            "crc32c" => Ok(IntegrityAlgorithm::Crc32c),
+            "xxhash64" => Ok(IntegrityAlgorithm::Xxhash64),
+            "sha1" => Ok(IntegrityAlgorithm::Sha1),
+            "sha256" => Ok(IntegrityAlgorithm::Sha256),
+            _ => Err(StratisError::Msg(format!(
+                "{} is an invalid integrity algorithm",
+                s
+            ))),
+        }
+    }
+}
+
+impl Display for IntegrityAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntegrityAlgorithm::Crc32c => write!(f, "crc32c"),
+            IntegrityAlgorithm::Xxhash64 => write!(f, "xxhash64"),
+            IntegrityAlgorithm::Sha1 => write!(f, "sha1"),
+            IntegrityAlgorithm::Sha256 => write!(f, "sha256"),
+        }
+    }
+}
+
+/// An advisory estimate of the overhead of protecting the data of a pool
+/// with checksums.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct IntegrityOverhead {
+    /// The size of the blocks of which the checksums are kept
+    pub block_size: Bytes,
+    /// The size of the checksum of each block
+    pub tag_size: Bytes,
+    /// The space taken up by the checksums and the journals on all the
+    /// data devices
+    pub metadata: Sectors,
+    /// The bytes written to the devices for every 100 bytes of data
+    /// written
+    pub write_amplification_percent: u64,
+}
+
+/// Parameters passed to mkfs.xfs when creating a filesystem. A parameter
+/// that is not specified is left at the mkfs.xfs default.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct XfsParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_size: Option<Bytes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inode_size: Option<Bytes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_size: Option<Bytes>,
+}
+
+/// The result of checking the consistency of a filesystem.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FilesystemCheck {
+    /// true if no inconsistencies were found
+    pub consistent: bool,
+    /// The inconsistencies reported by the check, one per entry
+    pub problems: Vec<String>,
+}
+
+/// The result of comparing the copies of the metadata of a pool.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MetadataCheck {
+    /// true if all copies agree
+    pub consistent: bool,
+    /// The divergences found, one per entry
+    pub problems: Vec<String>,
+}
+
+/// The result of one of the checks that make up a health report.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HealthCheck {
+    /// The name of the check, e.g. "metadata"
+    pub name: String,
+    /// The problems found by the check, one per entry
+    pub problems: Vec<String>,
+}
+
+/// The outcome of the surface test of one block device of a pool.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SurfaceTestResult {
+    pub dev_uuid: DevUuid,
+    /// The problem found, or None if the device read back the pattern
+    /// written to it unchanged
+    pub problem: Option<String>,
+}
+
+/// Block devices of a pool which are backed by the same physical disk, so
+/// that the failure of that disk affects all of them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RedundancyWarning {
+    /// The physical disk, identified by its WWID or, if it has none, by its
+    /// kernel name
+    pub disk: String,
+    pub dev_uuids: Vec<DevUuid>,
+}
+
+/// The outcome of reconciling the state of a pool held in memory with the
+/// most recent metadata on its devices.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Reconciliation {
+    /// The differences that were corrected by adopting the value on disk,
+    /// one per entry
+    pub corrected: Vec<String>,
+    /// The differences that can only be corrected by stopping the pool and
+    /// starting it again, one per entry
+    pub unresolved: Vec<String>,
+}
+
+/// The results of all the checks of the health of a pool.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HealthReport {
+    pub checks: Vec<HealthCheck>,
+}
+
+impl HealthReport {
+    /// Whether no check found any problem.
+    pub fn healthy(&self) -> bool {
+        self.checks.iter().all(|check| check.problems.is_empty())
+    }
+}
+
+/// The resources used by the daemon.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ResourceUsage {
+    /// The resident set size of the process
+    pub rss: Bytes,
+    /// The number of file descriptors the process has open
+    pub open_fds: u64,
+    /// The number of devicemapper devices managed by the engine
+    pub dm_devices: u64,
+}
+
+/// The outcome of the scan for the devices of pools, and of the setup of the
+/// pools found, which the engine performs at startup.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StartupProgress {
+    /// The number of Stratis devices and encrypted devices of pools found
+    pub devices_scanned: u64,
+    /// The number of pools set up at startup
+    pub pools_set_up: u64,
+    /// The pools found at startup which could not be set up because some of
+    /// their devices were missing, and which are still not set up
+    pub pools_pending: Vec<PoolUuid>,
+    /// The time taken by the scan and the setup, or so far if they are not
+    /// complete
+    pub duration: Duration,
+    /// Whether the scan and the setup are complete
+    pub complete: bool,
+}
+
+/// An external command, or a device-mapper operation expressed as the
+/// equivalent dmsetup command, that the engine would run to perform an
+/// operation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlannedAction {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl Display for PlannedAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in self.args.iter() {
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                write!(f, " \"{}\"", arg)?;
+            } else {
+                write!(f, " {}", arg)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The I/O done on a filesystem since its device was activated.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FilesystemIoStats {
+    /// The amount of data read
+    pub read_bytes: Bytes,
+    /// The amount of data written
+    pub write_bytes: Bytes,
+    /// The number of read operations completed
+    pub read_ops: u64,
+    /// The number of write operations completed
+    pub write_ops: u64,
+}
+
+/// The latency of the I/O done on the filesystems of a pool since detailed
+/// statistics were enabled. An average or a percentile is None if no I/O
+/// was done that it could be computed from.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LatencyStats {
+    /// The number of read operations completed
+    pub read_ops: u64,
+    /// The mean latency of a read
+    pub read_average: Option<Duration>,
+    /// The number of write operations completed
+    pub write_ops: u64,
+    /// The mean latency of a write
+    pub write_average: Option<Duration>,
+    /// The 99th percentile of the latency of all I/O, as the upper bound of
+    /// the bucket of the latency histogram that it falls in, or the last
+    /// bound if it falls beyond it
+    pub p99: Option<Duration>,
+}
+
+/// The results of a short benchmark of a device with direct I/O.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DeviceBenchmark {
+    /// The throughput of sequential reads, in bytes per second
+    pub sequential_read: u64,
+    /// The throughput of sequential writes, in bytes per second
+    pub sequential_write: u64,
+    /// The throughput of random 4 KiB reads, in bytes per second
+    pub random_read: u64,
+    /// The throughput of random 4 KiB writes, in bytes per second
+    pub random_write: u64,
+    /// The mean latency of a random 4 KiB read
+    pub read_latency: Duration,
+    /// The mean latency of a random 4 KiB write
+    pub write_latency: Duration,
+}
+
+/// How the engine would lay out a device that is added to a pool, and the
+/// I/O topology that the device reports.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AllocationPreview {
+    /// The logical sector size of the device, in bytes
+    pub logical_sector_size: u64,
+    /// The physical sector size of the device, in bytes
+    pub physical_sector_size: u64,
+    /// The optimal I/O size of the device, in bytes, 0 if it reports none
+    pub optimal_io_size: u64,
+    /// The alignment that suits the topology of the device, in bytes: the
+    /// larger of its physical sector size and its optimal I/O size
+    pub alignment: u64,
+    /// The granularity at which the pool allocates data from the device
+    pub extent_size: Sectors,
+    /// Whether the data region of the device starts at a multiple of the
+    /// alignment
+    pub data_aligned: bool,
+    /// The regions of the device, in order, as the name, the start, and the
+    /// length of each
+    pub layout: Vec<(String, Sectors, Sectors)>,
+}
+
 /// Redundancy classifications which the engine allows for pools.
 #[derive(Debug, Eq, PartialEq)]
 #[allow(non_camel_case_types)]
 /// Redundancy specification for a pool.
+///
+/// FIXME: Stratis does not yet manage redundancy itself, so there is never a
+/// rebuild or resync for it to report on. Once it does, the progress and an
+/// estimate of the time remaining should be derived from the sync ratio and
+/// sync rate in the status of the dm-raid device.
 pub enum Redundancy {
     NONE = 0,
 }
 
+impl fmt::Display for Redundancy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Redundancy::NONE => write!(f, "none"),
+        }
+    }
+}
+
+/// The shape of one tier of a pool.
+#[derive(Debug, Eq, PartialEq)]
+pub struct TierStructure {
+    pub tier: BlockDevTier,
+    /// The number of block devices in the tier
+    pub device_count: usize,
+    /// The total size of the block devices in the tier
+    pub total_size: Bytes,
+    /// The space on the block devices that is allocated or holds metadata
+    pub used_size: Bytes,
+    pub redundancy: Redundancy,
+    /// Whether the tier is running without some of its block devices
+    pub degraded: bool,
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct Name(String);
 
@@ -213,12 +1293,49 @@ pub enum ReportType {
     ErroredPoolDevices,
 }
 
+impl ReportType {
+    /// All report types that may be requested by name.
+    pub const ALL: [ReportType; 1] = [ReportType::ErroredPoolDevices];
+
+    /// The name by which the report is requested.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReportType::ErroredPoolDevices => "errored_pool_report",
+        }
+    }
+
+    /// The version of the JSON schema of the report. It is incremented
+    /// whenever the schema changes.
+    pub fn version(&self) -> u64 {
+        match self {
+            ReportType::ErroredPoolDevices => 1,
+        }
+    }
+}
+
+/// The name of the engine state report.
+pub const ENGINE_STATE_REPORT_NAME: &str = "engine_state_report";
+
+/// The version of the JSON schema of the engine state report. It is
+/// incremented whenever the schema changes.
+pub const ENGINE_STATE_REPORT_VERSION: u64 = 1;
+
+/// The name and schema version of every report, including the engine state
+/// report.
+pub fn report_versions() -> Vec<(&'static str, u64)> {
+    once((ENGINE_STATE_REPORT_NAME, ENGINE_STATE_REPORT_VERSION))
+        .chain(ReportType::ALL.iter().map(|rt| (rt.name(), rt.version())))
+        .collect()
+}
+
 impl<'a> TryFrom<&'a str> for ReportType {
     type Error = StratisError;
 
     fn try_from(name: &str) -> StratisResult<ReportType> {
         match name {
-            "errored_pool_report" => Ok(ReportType::ErroredPoolDevices),
+            name if name == ReportType::ErroredPoolDevices.name() => {
+                Ok(ReportType::ErroredPoolDevices)
+            }
             _ => Err(StratisError::Msg(format!(
                 "Report name {} not understood",
                 name
@@ -239,6 +1356,7 @@ pub struct LockedPoolInfo {
 
 /// A sendable event with all of the necessary information for the engine
 /// to process a received udev event.
+#[derive(Clone, Debug)]
 pub struct UdevEngineEvent {
     event_type: EventType,
     device: UdevEngineDevice,
@@ -265,6 +1383,7 @@ impl<'a> From<&'a libudev::Event<'a>> for UdevEngineEvent {
 
 /// A sendable device with all of the necessary information for the engine
 /// to process a received udev event.
+#[derive(Clone, Debug)]
 pub struct UdevEngineDevice {
     is_initialized: bool,
     devnode: Option<PathBuf>,
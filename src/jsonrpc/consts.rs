@@ -9,3 +9,5 @@ pub const OP_OK_STR: &str = "OK";
 
 pub const RPC_SOCKADDR: &str = "/run/stratisd/stratisd-min-jsonrpc";
 pub const RPC_CONNADDR: &str = "/run/stratisd/stratisd-min-jsonrpc";
+
+pub const HTTP_SOCKADDR: &str = "/run/stratisd/stratisd-min-http";
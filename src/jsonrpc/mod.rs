@@ -3,4 +3,7 @@ mod consts;
 mod interface;
 mod server;
 
-pub use self::{consts::*, server::run_server};
+pub use self::{
+    consts::*,
+    server::{run_http_server, run_server},
+};
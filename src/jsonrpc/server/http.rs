@@ -0,0 +1,221 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+// A read-only HTTP front-end, for environments in which neither the system
+// D-Bus nor the JSON RPC protocol of stratis-min is convenient to use. It
+// answers the following requests with the same JSON values as the
+// corresponding JSON RPC requests:
+//
+// GET /pools: stratis-min pool [list]
+// GET /filesystems: stratis-min filesystem [list]
+// GET /report: stratis-min report
+//
+// It listens on a Unix socket rather than on TCP, so that clients can be
+// identified by their peer credentials. The socket is accessible only to
+// root, and only requests from a peer with user ID 0 are answered.
+
+use std::{
+    fs::{create_dir_all, remove_file, set_permissions, Permissions},
+    io::ErrorKind,
+    os::unix::fs::PermissionsExt,
+    path::Path,
+};
+
+use serde_json::Value;
+use tokio::{
+    net::{UnixListener, UnixStream},
+    task::JoinHandle,
+};
+
+use crate::{
+    engine::LockableEngine,
+    jsonrpc::{
+        consts::HTTP_SOCKADDR,
+        server::{filesystem, pool, report},
+    },
+    stratis::{StratisError, StratisResult},
+};
+
+/// The maximum size of the head of a request, which is all that is read.
+const MAX_REQUEST_HEAD_SIZE: usize = 8192;
+
+#[derive(Debug, Eq, PartialEq)]
+enum Endpoint {
+    Pools,
+    Filesystems,
+    Report,
+}
+
+/// Find the endpoint requested in the head of a request, or the status and
+/// message of the error with which the request is refused.
+fn parse_request(head: &[u8]) -> Result<Endpoint, (&'static str, String)> {
+    let head = String::from_utf8_lossy(head);
+    let mut request_line = head.lines().next().unwrap_or("").split_whitespace();
+    match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/pools")) => Ok(Endpoint::Pools),
+        (Some("GET"), Some("/filesystems")) => Ok(Endpoint::Filesystems),
+        (Some("GET"), Some("/report")) => Ok(Endpoint::Report),
+        (Some("GET"), Some(path)) => Err(("404 Not Found", format!("No endpoint {}", path))),
+        (Some(method), Some(_)) => Err((
+            "405 Method Not Allowed",
+            format!(
+                "Method {} is not supported; all endpoints are read-only",
+                method
+            ),
+        )),
+        _ => Err(("400 Bad Request", "Malformed request line".to_string())),
+    }
+}
+
+/// Read the head of a request, up to and including the empty line which
+/// ends it.
+async fn read_request_head(stream: &UnixStream) -> StratisResult<Vec<u8>> {
+    let mut head = Vec::new();
+    let mut buf = [0; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_HEAD_SIZE {
+            return Err(StratisError::Msg(format!(
+                "HTTP request head exceeds {} bytes",
+                MAX_REQUEST_HEAD_SIZE
+            )));
+        }
+        stream.readable().await?;
+        match stream.try_read(&mut buf) {
+            Ok(0) => {
+                return Err(StratisError::Msg(
+                    "Connection closed before the end of the HTTP request head".to_string(),
+                ))
+            }
+            Ok(n) => head.extend_from_slice(&buf[..n]),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => (),
+            Err(e) => return Err(StratisError::from(e)),
+        }
+    }
+    Ok(head)
+}
+
+async fn write_all(stream: &UnixStream, mut bytes: &[u8]) -> StratisResult<()> {
+    while !bytes.is_empty() {
+        stream.writable().await?;
+        match stream.try_write(bytes) {
+            Ok(n) => bytes = &bytes[n..],
+            Err(e) if e.kind() == ErrorKind::WouldBlock => (),
+            Err(e) => return Err(StratisError::from(e)),
+        }
+    }
+    Ok(())
+}
+
+async fn answer(engine: LockableEngine, stream: &UnixStream) -> (&'static str, Value) {
+    match stream.peer_cred() {
+        Ok(cred) if cred.uid() == 0 => (),
+        Ok(_) => {
+            return (
+                "403 Forbidden",
+                json!({ "error": "Only root may query stratisd" }),
+            )
+        }
+        Err(e) => return ("403 Forbidden", json!({ "error": e.to_string() })),
+    }
+
+    let endpoint = match read_request_head(stream).await {
+        Ok(head) => parse_request(&head),
+        Err(e) => Err(("400 Bad Request", e.to_string())),
+    };
+    let value = match endpoint {
+        Ok(Endpoint::Pools) => serde_json::to_value(pool::pool_list(engine).await),
+        Ok(Endpoint::Filesystems) => {
+            serde_json::to_value(filesystem::filesystem_list(engine).await)
+        }
+        Ok(Endpoint::Report) => Ok(report::report(engine).await),
+        Err((status, message)) => return (status, json!({ "error": message })),
+    };
+    match value {
+        Ok(value) => ("200 OK", value),
+        Err(e) => (
+            "500 Internal Server Error",
+            json!({ "error": e.to_string() }),
+        ),
+    }
+}
+
+async fn handle_connection(engine: LockableEngine, stream: UnixStream) -> StratisResult<()> {
+    let (status, value) = answer(engine, &stream).await;
+    let body = serde_json::to_vec(&value)?;
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    write_all(&stream, head.as_bytes()).await?;
+    write_all(&stream, &body).await
+}
+
+fn bind<P>(path: P) -> StratisResult<UnixListener>
+where
+    P: AsRef<Path>,
+{
+    let _ = create_dir_all(
+        Path::new(HTTP_SOCKADDR)
+            .parent()
+            .expect("Static path always has parent"),
+    );
+    let _ = remove_file(path.as_ref());
+    let listener = UnixListener::bind(path.as_ref())?;
+    set_permissions(path.as_ref(), Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+pub fn run_http_server(engine: LockableEngine) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let listener = match bind(HTTP_SOCKADDR) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to start stratisd-min HTTP server: {}", e);
+                return;
+            }
+        };
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let engine = engine.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_connection(engine, stream).await {
+                            warn!("Failed to answer HTTP request: {}", e);
+                        }
+                    });
+                }
+                Err(e) => {
+                    warn!("Failed to accept HTTP connection: {}", e);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!(
+            parse_request(b"GET /pools HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            Ok(Endpoint::Pools)
+        );
+        assert_eq!(
+            parse_request(b"GET /report HTTP/1.1\r\n\r\n"),
+            Ok(Endpoint::Report)
+        );
+        assert_matches!(
+            parse_request(b"GET /keys HTTP/1.1\r\n\r\n"),
+            Err(("404 Not Found", _))
+        );
+        assert_matches!(
+            parse_request(b"POST /pools HTTP/1.1\r\n\r\n"),
+            Err(("405 Method Not Allowed", _))
+        );
+        assert_matches!(parse_request(b"\r\n\r\n"), Err(("400 Bad Request", _)));
+    }
+}
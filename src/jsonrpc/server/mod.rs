@@ -2,10 +2,11 @@
 mod utils;
 
 mod filesystem;
+mod http;
 mod key;
 mod pool;
 mod report;
 #[allow(clippy::module_inception)]
 mod server;
 
-pub use server::run_server;
+pub use self::{http::run_http_server, server::run_server};
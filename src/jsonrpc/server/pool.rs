@@ -63,7 +63,7 @@ pub async fn pool_create(
 ) -> StratisResult<bool> {
     let mut lock = engine.lock().await;
     Ok(
-        match block_in_place(|| lock.create_pool(name, blockdev_paths, None, &enc_info))? {
+        match block_in_place(|| lock.create_pool(name, blockdev_paths, None, &enc_info, None))? {
             CreateAction::Created(_) => true,
             CreateAction::Identity => false,
         },
@@ -90,7 +90,7 @@ pub async fn pool_init_cache(
     let mut lock = engine.lock().await;
     let (uuid, pool) = name_to_uuid_and_pool(&mut *lock, name)
         .ok_or_else(|| StratisError::Msg(format!("No pool found with name {}", name)))?;
-    block_in_place(|| Ok(pool.init_cache(uuid, name, paths)?.is_changed()))
+    block_in_place(|| Ok(pool.init_cache(uuid, name, paths, None)?.is_changed()))
 }
 
 // stratis-min pool rename
@@ -47,6 +47,7 @@ impl StratisParams {
     async fn process(self, engine: LockableEngine) -> StratisRet {
         match self.type_ {
             StratisParamType::KeySet(key_desc) => {
+                rejects_in_read_only_mode!(engine, self.fd_opt, KeySet, None);
                 let fd = expects_fd!(self.fd_opt, KeySet, None, true);
                 StratisRet::KeySet(stratis_result_to_return(
                     key::key_set(engine, &key_desc, fd).await,
@@ -54,6 +55,7 @@ impl StratisParams {
                 ))
             }
             StratisParamType::KeyUnset(key_desc) => {
+                rejects_in_read_only_mode!(engine, self.fd_opt, KeyUnset, false);
                 expects_fd!(self.fd_opt, KeyUnset, false, false);
                 StratisRet::KeyUnset(stratis_result_to_return(
                     key::key_unset(engine, &key_desc).await,
@@ -68,6 +70,7 @@ impl StratisParams {
                 ))
             }
             StratisParamType::PoolCreate(name, paths, encryption_info) => {
+                rejects_in_read_only_mode!(engine, self.fd_opt, PoolCreate, false);
                 expects_fd!(self.fd_opt, PoolCreate, false, false);
                 let path_ref: Vec<_> = paths.iter().map(|p| p.as_path()).collect();
                 StratisRet::PoolCreate(stratis_result_to_return(
@@ -77,6 +80,7 @@ impl StratisParams {
                 ))
             }
             StratisParamType::PoolRename(name, new_name) => {
+                rejects_in_read_only_mode!(engine, self.fd_opt, PoolRename, false);
                 expects_fd!(self.fd_opt, PoolRename, false, false);
                 StratisRet::PoolRename(stratis_result_to_return(
                     pool::pool_rename(engine, name.as_str(), new_name.as_str()).await,
@@ -84,6 +88,7 @@ impl StratisParams {
                 ))
             }
             StratisParamType::PoolAddData(name, paths) => {
+                rejects_in_read_only_mode!(engine, self.fd_opt, PoolAddData, false);
                 expects_fd!(self.fd_opt, PoolAddData, false, false);
                 let path_ref: Vec<_> = paths.iter().map(|p| p.as_path()).collect();
                 StratisRet::PoolInitCache(stratis_result_to_return(
@@ -92,6 +97,7 @@ impl StratisParams {
                 ))
             }
             StratisParamType::PoolInitCache(name, paths) => {
+                rejects_in_read_only_mode!(engine, self.fd_opt, PoolInitCache, false);
                 expects_fd!(self.fd_opt, PoolInitCache, false, false);
                 let path_ref: Vec<_> = paths.iter().map(|p| p.as_path()).collect();
                 StratisRet::PoolInitCache(stratis_result_to_return(
@@ -100,6 +106,7 @@ impl StratisParams {
                 ))
             }
             StratisParamType::PoolAddCache(name, paths) => {
+                rejects_in_read_only_mode!(engine, self.fd_opt, PoolAddCache, false);
                 expects_fd!(self.fd_opt, PoolAddCache, false, false);
                 let path_ref: Vec<_> = paths.iter().map(|p| p.as_path()).collect();
                 StratisRet::PoolAddCache(stratis_result_to_return(
@@ -108,6 +115,7 @@ impl StratisParams {
                 ))
             }
             StratisParamType::PoolDestroy(name) => {
+                rejects_in_read_only_mode!(engine, self.fd_opt, PoolDestroy, false);
                 expects_fd!(self.fd_opt, PoolDestroy, false, false);
                 StratisRet::PoolDestroy(stratis_result_to_return(
                     pool::pool_destroy(engine, name.as_str()).await,
@@ -115,6 +123,7 @@ impl StratisParams {
                 ))
             }
             StratisParamType::PoolUnlock(unlock_method, uuid) => {
+                rejects_in_read_only_mode!(engine, self.fd_opt, PoolUnlock, false);
                 StratisRet::PoolUnlock(stratis_result_to_return(
                     pool::pool_unlock(engine, unlock_method, uuid, self.fd_opt).await,
                     false,
@@ -168,6 +177,7 @@ impl StratisParams {
                 ))
             }
             StratisParamType::FsCreate(pool_name, fs_name) => {
+                rejects_in_read_only_mode!(engine, self.fd_opt, FsCreate, false);
                 expects_fd!(self.fd_opt, FsCreate, false, false);
                 StratisRet::FsCreate(stratis_result_to_return(
                     filesystem::filesystem_create(engine, &pool_name, &fs_name).await,
@@ -187,6 +197,7 @@ impl StratisParams {
                 StratisRet::FsList(filesystem::filesystem_list(engine).await)
             }
             StratisParamType::FsDestroy(pool_name, fs_name) => {
+                rejects_in_read_only_mode!(engine, self.fd_opt, FsDestroy, false);
                 expects_fd!(self.fd_opt, FsDestroy, false, false);
                 StratisRet::FsDestroy(stratis_result_to_return(
                     filesystem::filesystem_destroy(engine, &pool_name, &fs_name).await,
@@ -194,6 +205,7 @@ impl StratisParams {
                 ))
             }
             StratisParamType::FsRename(pool_name, fs_name, new_fs_name) => {
+                rejects_in_read_only_mode!(engine, self.fd_opt, FsRename, false);
                 expects_fd!(self.fd_opt, FsRename, false, false);
                 StratisRet::FsRename(stratis_result_to_return(
                     filesystem::filesystem_rename(engine, &pool_name, &fs_name, &new_fs_name).await,
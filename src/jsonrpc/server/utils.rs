@@ -46,6 +46,30 @@ macro_rules! expects_fd {
     };
 }
 
+// Reject a method that would modify the engine in read-only mode, closing
+// the file descriptor that was passed with it, if any.
+macro_rules! rejects_in_read_only_mode {
+    ($engine:expr, $fd_opt:expr, $ret:ident, $default:expr) => {
+        if $engine.lock().await.read_only_mode() {
+            if let Some(fd) = $fd_opt {
+                if let Err(e) = nix::unistd::close(fd) {
+                    warn!(
+                        "Failed to close file descriptor {}: {}; a file descriptor \
+                        may have been leaked",
+                        fd, e,
+                    );
+                }
+            }
+            let res = Err($crate::stratis::StratisError::Msg(
+                "stratisd is in read-only mode; the method was rejected".to_string(),
+            ));
+            return $crate::jsonrpc::interface::StratisRet::$ret(
+                $crate::jsonrpc::server::utils::stratis_result_to_return(res, $default),
+            );
+        }
+    };
+}
+
 pub fn stratis_result_to_return<T>(result: StratisResult<T>, default_value: T) -> (T, u16, String) {
     match result {
         Ok(r) => (r, OP_OK, OP_OK_STR.to_string()),
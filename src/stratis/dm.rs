@@ -17,7 +17,8 @@ const REQUIRED_DM_MINOR_VERSION: u32 = 37;
 // Waits for devicemapper event. On devicemapper event, transfers control
 // to engine to handle event and waits until control is returned from engine.
 // Accepts None as an argument; this indicates that devicemapper events are
-// to be ignored.
+// to be ignored. The engine observers are notified of pool events that
+// result from handling a devicemapper event.
 pub async fn dm_event_thread(engine: Option<LockableEngine>) -> StratisResult<()> {
     async fn process_dm_event(engine: &LockableEngine, fd: &AsyncFd<RawFd>) -> StratisResult<()> {
         {
@@ -26,8 +27,9 @@ pub async fn dm_event_thread(engine: Option<LockableEngine>) -> StratisResult<()
         }
         get_dm().arm_poll()?;
         let mut lock = engine.lock().await;
-        lock.evented()?;
-        Ok(())
+        let result = lock.evented();
+        lock.drain_pool_events();
+        result
     }
 
     match engine {
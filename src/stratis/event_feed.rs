@@ -0,0 +1,120 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+// An ordered feed of the engine events which clients can read from any
+// point they have seen, rather than having to listen for every signal.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    engine::{EngineEvent, EngineObserver},
+    stratis::event_sink::event_to_json,
+};
+
+/// The number of events that the feed keeps. A client that falls further
+/// behind misses the oldest events, which it can tell from the sequence
+/// number of the first event it gets.
+const EVENT_FEED_CAPACITY: usize = 1024;
+
+/// An event as it is kept in the feed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeedEntry {
+    /// The position of the event in the feed, counting from 0
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    /// The event as a JSON object, as it is forwarded to an event sink
+    pub event: String,
+}
+
+#[derive(Debug, Default)]
+struct FeedState {
+    next_seq: u64,
+    entries: VecDeque<FeedEntry>,
+}
+
+/// An observer of the engine which numbers every event in the order in
+/// which it occurs and keeps the most recent ones, so that a client can
+/// read all the events since the last one it has seen. Clones of the feed
+/// share the same events.
+#[derive(Clone, Debug, Default)]
+pub struct EventFeed {
+    state: Arc<Mutex<FeedState>>,
+}
+
+impl EventFeed {
+    /// The events kept with a sequence number of at least since, oldest
+    /// first, and the sequence number that the next event will get.
+    pub fn events_since(&self, since: u64) -> (Vec<FeedEntry>, u64) {
+        let state = self
+            .state
+            .lock()
+            .expect("no thread panics while holding the lock");
+        (
+            state
+                .entries
+                .iter()
+                .filter(|entry| entry.seq >= since)
+                .cloned()
+                .collect(),
+            state.next_seq,
+        )
+    }
+}
+
+impl EngineObserver for EventFeed {
+    fn notify(&self, event: &EngineEvent) {
+        let mut state = self
+            .state
+            .lock()
+            .expect("no thread panics while holding the lock");
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        if state.entries.len() == EVENT_FEED_CAPACITY {
+            state.entries.pop_front();
+        }
+        state.entries.push_back(FeedEntry {
+            seq,
+            timestamp: Utc::now(),
+            event: event_to_json(event).to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::PoolUuid;
+
+    use super::*;
+
+    #[test]
+    /// Verify that events are numbered in order, that reading from a
+    /// sequence number skips the events before it, and that only the most
+    /// recent events are kept.
+    fn test_event_feed() {
+        let feed = EventFeed::default();
+        assert_eq!(feed.events_since(0), (Vec::new(), 0));
+
+        let pool_uuid = PoolUuid::new_v4();
+        for _ in 0..EVENT_FEED_CAPACITY + 2 {
+            feed.notify(&EngineEvent::PoolRemoved(pool_uuid));
+        }
+
+        let (entries, next) = feed.events_since(0);
+        assert_eq!(next, EVENT_FEED_CAPACITY as u64 + 2);
+        assert_eq!(entries.len(), EVENT_FEED_CAPACITY);
+        assert_eq!(entries[0].seq, 2);
+        assert!(entries.windows(2).all(|w| w[1].seq == w[0].seq + 1));
+
+        let (entries, _) = feed.events_since(next - 1);
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].event.contains("PoolRemoved"));
+
+        assert_eq!(feed.events_since(next), (Vec::new(), next));
+    }
+}
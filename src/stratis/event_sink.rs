@@ -0,0 +1,295 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+// Forwarding of engine events to an external syslog or UDP endpoint.
+
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display},
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    os::unix::net::UnixDatagram,
+    process,
+};
+
+use chrono::Utc;
+use serde_json::Value;
+
+use crate::{
+    engine::{EngineEvent, EngineObserver, PoolEvent},
+    stratis::{StratisError, StratisResult},
+};
+
+/// The socket of the local syslog daemon.
+const SYSLOG_SOCKET_PATH: &str = "/dev/log";
+
+/// The syslog facility of system daemons.
+const SYSLOG_FACILITY_DAEMON: u8 = 3;
+const SYSLOG_SEVERITY_WARNING: u8 = 4;
+const SYSLOG_SEVERITY_NOTICE: u8 = 5;
+
+/// An external endpoint to which the engine events are forwarded.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EventSink {
+    /// Send each event as a JSON object in a UDP datagram to the address.
+    Udp(SocketAddr),
+    /// Send each event as a syslog message with a JSON object as its
+    /// content, to the syslog daemon at the address over UDP, or to the
+    /// local syslog daemon if no address is given.
+    Syslog(Option<SocketAddr>),
+}
+
+/// Resolve a HOST:PORT string to the first address it designates.
+fn resolve(addr: &str) -> StratisResult<SocketAddr> {
+    addr.to_socket_addrs()?
+        .next()
+        .ok_or_else(|| StratisError::Msg(format!("{} does not resolve to any address", addr)))
+}
+
+impl<'a> TryFrom<&'a str> for EventSink {
+    type Error = StratisError;
+
+    /// Parse an event sink from one of "udp:HOST:PORT", "syslog", and
+    /// "syslog:HOST:PORT".
+    fn try_from(s: &str) -> StratisResult<EventSink> {
+        match s.split_once(':') {
+            Some(("udp", addr)) => Ok(EventSink::Udp(resolve(addr)?)),
+            Some(("syslog", addr)) => Ok(EventSink::Syslog(Some(resolve(addr)?))),
+            None if s == "syslog" => Ok(EventSink::Syslog(None)),
+            _ => Err(StratisError::Msg(format!(
+                "{} is an invalid event sink; expected udp:HOST:PORT, syslog, or syslog:HOST:PORT",
+                s
+            ))),
+        }
+    }
+}
+
+impl Display for EventSink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventSink::Udp(addr) => write!(f, "udp:{}", addr),
+            EventSink::Syslog(Some(addr)) => write!(f, "syslog:{}", addr),
+            EventSink::Syslog(None) => write!(f, "syslog"),
+        }
+    }
+}
+
+/// Convert an engine event to the JSON object which is forwarded for it.
+/// The fields of the object correspond to the arguments of the D-Bus signal
+/// sent for the same event.
+pub(super) fn event_to_json(event: &EngineEvent) -> Value {
+    match event {
+        EngineEvent::PoolAdded { uuid, name } => json!({
+            "event": "PoolAdded",
+            "pool_uuid": uuid.to_simple_ref().to_string(),
+            "pool_name": name.to_string(),
+        }),
+        EngineEvent::PoolRemoved(uuid) => json!({
+            "event": "PoolRemoved",
+            "pool_uuid": uuid.to_simple_ref().to_string(),
+        }),
+        EngineEvent::PoolRenamed { uuid, name } => json!({
+            "event": "PoolRenamed",
+            "pool_uuid": uuid.to_simple_ref().to_string(),
+            "pool_name": name.to_string(),
+        }),
+        EngineEvent::Pool(pool_uuid, event) => {
+            let mut value = match event {
+                PoolEvent::OperationChanged(operation) => json!({
+                    "event": "OperationChanged",
+                    "operation": operation.map(|op| op.to_string()),
+                }),
+                PoolEvent::MetadataWriteFailed {
+                    policy,
+                    read_only,
+                    error,
+                } => json!({
+                    "event": "MetadataWriteFailed",
+                    "policy": policy.to_string(),
+                    "read_only": read_only,
+                    "error": error,
+                }),
+                PoolEvent::Alert {
+                    metric,
+                    value,
+                    threshold,
+                } => json!({
+                    "event": "Alert",
+                    "metric": metric.to_string(),
+                    "value": value,
+                    "threshold": threshold,
+                }),
+                PoolEvent::MetadataUndersized {
+                    current,
+                    recommended,
+                } => json!({
+                    "event": "MetadataUndersized",
+                    "current": **current as u64,
+                    "recommended": **recommended as u64,
+                }),
+                PoolEvent::SnapshotPruned { uuid, name } => json!({
+                    "event": "SnapshotPruned",
+                    "filesystem_uuid": uuid.to_simple_ref().to_string(),
+                    "filesystem_name": name.to_string(),
+                }),
+                PoolEvent::FilesystemCowAlert {
+                    uuid,
+                    name,
+                    unique,
+                    threshold,
+                } => json!({
+                    "event": "CowAlert",
+                    "filesystem_uuid": uuid.to_simple_ref().to_string(),
+                    "filesystem_name": name.to_string(),
+                    "unique": **unique as u64,
+                    "threshold": **threshold as u64,
+                }),
+            };
+            value["pool_uuid"] = Value::from(pool_uuid.to_simple_ref().to_string());
+            value
+        }
+    }
+}
+
+/// The syslog severity of the message for an event.
+fn syslog_severity(event: &EngineEvent) -> u8 {
+    match event {
+        EngineEvent::Pool(_, PoolEvent::MetadataWriteFailed { .. })
+        | EngineEvent::Pool(_, PoolEvent::Alert { .. })
+        | EngineEvent::Pool(_, PoolEvent::MetadataUndersized { .. })
+        | EngineEvent::Pool(_, PoolEvent::FilesystemCowAlert { .. }) => SYSLOG_SEVERITY_WARNING,
+        _ => SYSLOG_SEVERITY_NOTICE,
+    }
+}
+
+#[derive(Debug)]
+enum SinkSocket {
+    Udp(UdpSocket, SocketAddr),
+    Unix(UnixDatagram),
+}
+
+/// An observer of the engine which forwards every event to an event sink.
+/// The sockets are non-blocking, so that an unreachable or slow endpoint
+/// never holds up the engine; an event that can not be sent is dropped.
+#[derive(Debug)]
+pub struct EventSinkObserver {
+    sink: EventSink,
+    socket: SinkSocket,
+}
+
+impl EventSinkObserver {
+    pub fn new(sink: EventSink) -> StratisResult<EventSinkObserver> {
+        let socket = match sink {
+            EventSink::Udp(addr) | EventSink::Syslog(Some(addr)) => {
+                let bind_addr = if addr.is_ipv4() {
+                    "0.0.0.0:0"
+                } else {
+                    "[::]:0"
+                };
+                let socket = UdpSocket::bind(bind_addr)?;
+                socket.set_nonblocking(true)?;
+                SinkSocket::Udp(socket, addr)
+            }
+            EventSink::Syslog(None) => {
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(SYSLOG_SOCKET_PATH)?;
+                socket.set_nonblocking(true)?;
+                SinkSocket::Unix(socket)
+            }
+        };
+        Ok(EventSinkObserver { sink, socket })
+    }
+
+    /// Format the message which is sent for the event.
+    fn message(&self, event: &EngineEvent) -> String {
+        let mut value = event_to_json(event);
+        value["timestamp"] = Value::from(Utc::now().to_rfc3339());
+        match self.sink {
+            EventSink::Udp(_) => value.to_string(),
+            EventSink::Syslog(_) => format!(
+                "<{}>stratisd[{}]: {}",
+                SYSLOG_FACILITY_DAEMON * 8 + syslog_severity(event),
+                process::id(),
+                value
+            ),
+        }
+    }
+}
+
+impl EngineObserver for EventSinkObserver {
+    fn notify(&self, event: &EngineEvent) {
+        let message = self.message(event);
+        let result = match &self.socket {
+            SinkSocket::Udp(socket, addr) => socket.send_to(message.as_bytes(), addr),
+            SinkSocket::Unix(socket) => socket.send(message.as_bytes()),
+        };
+        if let Err(e) = result {
+            warn!("Failed to forward event to {}: {}", self.sink, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::{AlertMetric, Name, PoolUuid};
+
+    use super::*;
+
+    #[test]
+    /// Verify that event sinks are parsed from all the accepted forms and
+    /// that anything else is rejected.
+    fn test_event_sink_parse() {
+        assert_eq!(
+            EventSink::try_from("udp:127.0.0.1:5140").unwrap(),
+            EventSink::Udp("127.0.0.1:5140".parse().unwrap())
+        );
+        assert_eq!(
+            EventSink::try_from("syslog:[::1]:514").unwrap(),
+            EventSink::Syslog(Some("[::1]:514".parse().unwrap()))
+        );
+        assert_eq!(
+            EventSink::try_from("syslog").unwrap(),
+            EventSink::Syslog(None)
+        );
+        assert!(EventSink::try_from("udp").is_err());
+        assert!(EventSink::try_from("udp:127.0.0.1").is_err());
+        assert!(EventSink::try_from("tcp:127.0.0.1:5140").is_err());
+    }
+
+    #[test]
+    /// Verify that an event sent to a UDP sink arrives as a JSON object
+    /// carrying the pool UUID and the fields of the event.
+    fn test_event_sink_udp() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let observer =
+            EventSinkObserver::new(EventSink::Udp(receiver.local_addr().unwrap())).unwrap();
+
+        let pool_uuid = PoolUuid::new_v4();
+        observer.notify(&EngineEvent::Pool(
+            pool_uuid,
+            PoolEvent::Alert {
+                metric: AlertMetric::PhysicalUsage,
+                value: 95,
+                threshold: 90,
+            },
+        ));
+        observer.notify(&EngineEvent::PoolRenamed {
+            uuid: pool_uuid,
+            name: Name::new("renamed".to_string()),
+        });
+
+        let mut buf = [0u8; 1024];
+        let len = receiver.recv(&mut buf).unwrap();
+        let value: Value = serde_json::from_slice(&buf[..len]).unwrap();
+        assert_eq!(value["event"], "Alert");
+        assert_eq!(value["pool_uuid"], pool_uuid.to_simple_ref().to_string());
+        assert_eq!(value["value"], 95);
+        assert_eq!(value["threshold"], 90);
+        assert!(value["timestamp"].is_string());
+
+        let len = receiver.recv(&mut buf).unwrap();
+        let value: Value = serde_json::from_slice(&buf[..len]).unwrap();
+        assert_eq!(value["event"], "PoolRenamed");
+        assert_eq!(value["pool_name"], "renamed");
+    }
+}
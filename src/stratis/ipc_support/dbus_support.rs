@@ -27,17 +27,9 @@ pub async fn setup(
     receiver: UnboundedReceiver<UdevEngineEvent>,
     trigger: Sender<()>,
 ) -> StratisResult<()> {
+    let cloned_engine = engine.clone();
     let (mut conn, mut udev, mut tree) = spawn_blocking(move || {
-        create_dbus_handlers(engine.clone(), receiver, trigger)
-            .map(|(conn, udev, tree)| {
-                let mutex_lock = engine.blocking_lock();
-                for (pool_name, pool_uuid, pool) in mutex_lock.pools() {
-                    udev.register_pool(&pool_name, pool_uuid, pool)
-                }
-                info!("D-Bus API is available");
-                (conn, udev, tree)
-            })
-            .map_err(StratisError::from)
+        create_dbus_handlers(cloned_engine, receiver, trigger).map_err(StratisError::from)
     })
     .await
     .map_err(StratisError::from)
@@ -54,7 +46,18 @@ pub async fn setup(
         }
     });
     let mut conn_handle = task::spawn_blocking(move || conn.process_dbus_requests());
+    info!("D-Bus API is available");
+    // The engine is locked while it sets up the pools at startup, so the
+    // pools are registered once it is done, but requests are answered
+    // meanwhile. The udev events are handled only after the pools are
+    // registered, so that no pool is registered twice.
     let mut udev_handle = task::spawn(async move {
+        {
+            let mutex_lock = engine.lock().await;
+            for (pool_name, pool_uuid, pool) in mutex_lock.pools() {
+                udev.register_pool(&pool_name, pool_uuid, pool)
+            }
+        }
         loop {
             if let Err(e) = udev.handle_udev_event().await {
                 error!(
@@ -10,7 +10,7 @@ use tokio::{
 
 use crate::{
     engine::{LockableEngine, UdevEngineEvent},
-    jsonrpc::run_server,
+    jsonrpc::{run_http_server, run_server},
     stratis::{StratisError, StratisResult},
 };
 
@@ -35,13 +35,16 @@ fn handle_udev(
     })
 }
 
+// Pool events are not reported because JSON RPC has no signals, so no
+// observer is subscribed to the engine.
 pub async fn setup(
     engine: LockableEngine,
     recv: UnboundedReceiver<UdevEngineEvent>,
     _: Sender<()>,
 ) -> StratisResult<()> {
     let mut udev_join = handle_udev(engine.clone(), recv);
-    let mut server_join = run_server(engine);
+    let mut server_join = run_server(engine.clone());
+    let mut http_server_join = run_http_server(engine);
 
     select! {
         res = &mut udev_join => {
@@ -52,5 +55,9 @@ pub async fn setup(
             error!("The server handler thread exited...");
             res.map_err(StratisError::from)
         }
+        res = &mut http_server_join => {
+            error!("The HTTP server handler thread exited...");
+            res.map_err(StratisError::from)
+        }
     }
 }
@@ -4,6 +4,12 @@
 // Both modules are declared if both features are active for the purpose of
 // clippy.
 
+// FIXME: The HTTP front-end served along with JSON RPC answers only
+// read-only requests, on a Unix socket. Requests which change the state of
+// the engine, and a TCP listener for remote management, await a design for
+// authenticating clients, since a TCP listener can not rely on peer
+// credentials.
+
 #[cfg(feature = "dbus_enabled")]
 mod dbus_support;
 #[cfg(feature = "min")]
@@ -4,12 +4,16 @@
 
 pub use self::{
     errors::{StratisError, StratisResult},
+    event_feed::{EventFeed, FeedEntry},
+    event_sink::EventSink,
     run::run,
     stratis::VERSION,
 };
 
 mod dm;
 mod errors;
+mod event_feed;
+mod event_sink;
 mod ipc_support;
 mod run;
 #[allow(clippy::module_inception)]
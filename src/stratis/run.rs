@@ -4,23 +4,50 @@
 
 //! Main loop
 
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
 
 use tokio::{
     runtime::Builder,
     select, signal,
     sync::{broadcast::channel, mpsc::unbounded_channel},
-    task,
+    task, time,
 };
 
 use crate::{
-    engine::{Lockable, LockableEngine, SimEngine, StratEngine, UdevEngineEvent},
+    engine::{
+        set_dev_path, set_startup_read_only_mode, Lockable, LockableEngine, SimEngine, StratEngine,
+        UdevEngineEvent,
+    },
     stratis::{
-        dm::dm_event_thread, errors::StratisResult, ipc_support::setup, stratis::VERSION,
+        dm::dm_event_thread,
+        errors::StratisResult,
+        event_sink::{EventSink, EventSinkObserver},
+        ipc_support::setup,
+        stratis::VERSION,
         udev_monitor::udev_thread,
     },
 };
 
+/// How often deleted filesystems are checked for the end of their deletion
+/// grace period.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60);
+
+// Periodically destroys the deleted filesystems whose deletion grace period
+// is over. The engine observers are notified of the resulting pool events.
+async fn purge_thread(engine: LockableEngine) {
+    let mut interval = time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let mut lock = engine.lock().await;
+        lock.purge_deleted_filesystems();
+        lock.drain_pool_events();
+    }
+}
+
 // Waits for SIGINT. If received, sends true to all blocking calls in blocking
 // threads which will then terminate.
 async fn signal_thread() {
@@ -33,8 +60,21 @@ async fn signal_thread() {
 /// Initialize the engine and keep it running until a signal is received
 /// or a fatal error is encountered.
 /// If sim is true, start the sim engine rather than the real engine.
+/// If read_only is true, start the engine in read-only mode.
+/// If dev_path is specified, place the symlinks to the filesystem devices
+/// in that directory instead of the default.
+/// If event_sink is specified, forward the engine events to it.
 /// Always check for devicemapper context.
-pub fn run(sim: bool) -> StratisResult<()> {
+pub fn run(
+    sim: bool,
+    read_only: bool,
+    dev_path: Option<&Path>,
+    event_sink: Option<EventSink>,
+) -> StratisResult<()> {
+    if let Some(dev_path) = dev_path {
+        set_dev_path(dev_path);
+    }
+
     let runtime = Builder::new_multi_thread()
         .enable_all()
         .thread_name_fn(|| {
@@ -61,7 +101,8 @@ pub fn run(sim: bool) -> StratisResult<()> {
                 Lockable::new_exclusive(SimEngine::default())
             } else {
                 info!("Using StratEngine");
-                Lockable::new_exclusive(match StratEngine::initialize() {
+                set_startup_read_only_mode(read_only);
+                Lockable::new_exclusive(match StratEngine::new() {
                     Ok(engine) => engine,
                     Err(e) => {
                         error!("Failed to start up stratisd engine: {}; exiting", e);
@@ -71,18 +112,48 @@ pub fn run(sim: bool) -> StratisResult<()> {
             }
         };
 
+        if read_only {
+            info!("Starting in read-only mode");
+            engine.lock().await.set_read_only_mode(true);
+        }
+
+        if let Some(sink) = event_sink {
+            info!("Forwarding engine events to {}", sink);
+            let observer = match EventSinkObserver::new(sink) {
+                Ok(observer) => observer,
+                Err(e) => {
+                    error!("Failed to set up the event sink: {}; exiting", e);
+                    return Err(e);
+                }
+            };
+            engine.lock().await.subscribe(Box::new(observer));
+        }
+
+        // The engine sets up the pools only once the IPC layer has been
+        // started, so that the progress of the startup can be queried. It
+        // is locked meanwhile, so that no request is handled before the
+        // pools are set up.
+        let mut startup_lock = engine.lock().await;
+
         let (trigger, should_exit) = channel(1);
         let (sender, receiver) = unbounded_channel::<UdevEngineEvent>();
 
         let join_udev = task::spawn_blocking(move || udev_thread(sender, should_exit));
         let join_ipc = task::spawn(setup(engine.clone(), receiver, trigger.clone()));
         let join_signal = task::spawn(signal_thread());
+        task::spawn(purge_thread(engine.clone()));
         let join_dm = task::spawn(dm_event_thread(if sim {
             None
         } else {
             Some(engine.clone())
         }));
 
+        if let Err(e) = task::block_in_place(|| startup_lock.start_up()) {
+            error!("Failed to start up stratisd engine: {}; exiting", e);
+            return Err(e);
+        }
+        drop(startup_lock);
+
         select! {
             res = join_udev => {
                 if let Ok(Err(e)) = res {
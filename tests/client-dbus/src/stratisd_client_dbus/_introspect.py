@@ -17,12 +17,40 @@ SPECS = {
 """,
     "org.storage.stratis3.Manager.r0": """
 <interface name="org.storage.stratis3.Manager.r0">
+    <method name="ActiveOperations">
+      <arg name="results" type="a(sot)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="BenchmarkDevice">
+      <arg name="devnode" type="s" direction="in" />
+      <arg name="result" type="(tttttt)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="Capabilities">
+      <arg name="results" type="a{ss}" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="CreateFromTemplate">
+      <arg name="name" type="s" direction="in" />
+      <arg name="template" type="s" direction="in" />
+      <arg name="devices" type="as" direction="in" />
+      <arg name="result" type="(b(oao))" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
     <method name="CreatePool">
       <arg name="name" type="s" direction="in" />
       <arg name="redundancy" type="(bq)" direction="in" />
       <arg name="devices" type="as" direction="in" />
       <arg name="key_desc" type="(bs)" direction="in" />
       <arg name="clevis_info" type="(b(ss))" direction="in" />
+      <arg name="cipher_info" type="(b(st))" direction="in" />
+      <arg name="stripe_info" type="(b(qt))" direction="in" />
+      <arg name="pbkdf_params" type="(b(uuu))" direction="in" />
+      <arg name="header_dir" type="(bs)" direction="in" />
       <arg name="result" type="(b(oao))" direction="out" />
       <arg name="return_code" type="q" direction="out" />
       <arg name="return_string" type="s" direction="out" />
@@ -33,11 +61,82 @@ SPECS = {
       <arg name="return_code" type="q" direction="out" />
       <arg name="return_string" type="s" direction="out" />
     </method>
+    <method name="DeviceAllocationPreview">
+      <arg name="devnode" type="s" direction="in" />
+      <arg name="encrypted" type="b" direction="in" />
+      <arg name="result" type="(tttttba(stt))" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="EncryptionAudit">
+      <arg name="result" type="a(s(bs)st(b(uuu))b(bs)(b(uu)))" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
     <method name="EngineStateReport">
       <arg name="result" type="s" direction="out" />
       <arg name="return_code" type="q" direction="out" />
       <arg name="return_string" type="s" direction="out" />
     </method>
+    <method name="EventsSince">
+      <arg name="since" type="t" direction="in" />
+      <arg name="results" type="(a(tss)t)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="ExportPool">
+      <arg name="pool" type="o" direction="in" />
+      <arg name="result" type="(bs)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="ListObjects">
+      <arg name="results" type="a(oss)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="LockStats">
+      <arg name="results" type="a(sttt)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="MoveFilesystem">
+      <arg name="fs_uuid" type="s" direction="in" />
+      <arg name="dest_pool_uuid" type="s" direction="in" />
+      <arg name="result" type="o" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="PauseMonitoring">
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="PendingBackgroundWork">
+      <arg name="results" type="a(os(b(tt)))" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="Reconcile">
+      <arg name="results" type="a(s(bs)asas)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="ReportVersions">
+      <arg name="results" type="a(st)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="ResourceUsage">
+      <arg name="result" type="(ttt)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="ResumeMonitoring">
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
     <method name="SetKey">
       <arg name="key_desc" type="s" direction="in" />
       <arg name="key_fd" type="h" direction="in" />
@@ -46,6 +145,46 @@ SPECS = {
       <arg name="return_code" type="q" direction="out" />
       <arg name="return_string" type="s" direction="out" />
     </method>
+    <method name="SetReadOnlyMode">
+      <arg name="read_only" type="b" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="SetUdevSettlePolicy">
+      <arg name="timeout" type="(bt)" direction="in" />
+      <arg name="targeted" type="b" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="StartPool">
+      <arg name="pool_uuid" type="s" direction="in" />
+      <arg name="result" type="(bo)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="StartupOrder">
+      <arg name="results" type="a(ss)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="StartupProgress">
+      <arg name="result" type="(bttast)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="Transaction">
+      <arg name="operations" type="s" direction="in" />
+      <arg name="result" type="(bao)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="UdevSettlePolicy">
+      <arg name="result" type="((bt)b)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
     <method name="UnlockPool">
       <arg name="pool_uuid" type="s" direction="in" />
       <arg name="unlock_method" type="s" direction="in" />
@@ -59,6 +198,28 @@ SPECS = {
       <arg name="return_code" type="q" direction="out" />
       <arg name="return_string" type="s" direction="out" />
     </method>
+    <method name="ValidateDeviceSet">
+      <arg name="devices" type="as" direction="in" />
+      <arg name="encrypted" type="b" direction="in" />
+      <arg name="results" type="(bsa(ssss))" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="ValidatePoolName">
+      <arg name="name" type="s" direction="in" />
+      <arg name="results" type="as" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="VersionInfo">
+      <arg name="results" type="(sta(sst))" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <signal name="FilesystemMoveProgress">
+      <arg name="copied" type="t" />
+      <arg name="total" type="t" />
+    </signal>
     <property name="Version" type="s" access="read">
       <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="const" />
     </property>
@@ -110,22 +271,99 @@ SPECS = {
 """,
     "org.storage.stratis3.filesystem.r0": """
 <interface name="org.storage.stratis3.filesystem.r0">
+    <method name="CheckConsistency">
+      <arg name="results" type="(bas)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="IoStats">
+      <arg name="results" type="(sstt)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="LatencyHistogram">
+      <arg name="results" type="a(tt)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="Repair">
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="Scrub">
+      <arg name="results" type="a(tt)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="SetCowAlertThreshold">
+      <arg name="threshold" type="(bt)" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="SetMinFreeGuarantee">
+      <arg name="guarantee" type="(bt)" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
     <method name="SetName">
       <arg name="name" type="s" direction="in" />
       <arg name="result" type="(bs)" direction="out" />
       <arg name="return_code" type="q" direction="out" />
       <arg name="return_string" type="s" direction="out" />
     </method>
+    <method name="SetSize">
+      <arg name="target_sectors" type="t" direction="in" />
+      <arg name="grow_fs" type="b" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="SharedSize">
+      <arg name="results" type="(tt)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="WarmCache">
+      <arg name="budget" type="(bt)" direction="in" />
+      <arg name="result" type="t" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <signal name="CowAlert">
+      <arg name="filesystem_uuid" type="s" />
+      <arg name="unique" type="t" />
+      <arg name="threshold" type="t" />
+    </signal>
+    <signal name="ScrubProgress">
+      <arg name="read" type="t" />
+      <arg name="total" type="t" />
+    </signal>
+    <signal name="WarmCacheProgress">
+      <arg name="read" type="t" />
+      <arg name="total" type="t" />
+    </signal>
+    <property name="CowAlertThreshold" type="(bt)" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="false" />
+    </property>
     <property name="Created" type="s" access="read">
       <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="const" />
     </property>
     <property name="Devnode" type="s" access="read">
       <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="invalidates" />
     </property>
+    <property name="MinFreeGuarantee" type="(bt)" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="false" />
+    </property>
     <property name="Name" type="s" access="read" />
     <property name="Pool" type="o" access="read">
       <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="const" />
     </property>
+    <property name="SequenceNumber" type="t" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="const" />
+    </property>
     <property name="Uuid" type="s" access="read">
       <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="const" />
     </property>
@@ -133,6 +371,12 @@ SPECS = {
 """,
     "org.storage.stratis3.pool.r0": """
 <interface name="org.storage.stratis3.pool.r0">
+    <method name="AcknowledgeAlert">
+      <arg name="metric" type="s" direction="in" />
+      <arg name="results" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
     <method name="AddCacheDevs">
       <arg name="devices" type="as" direction="in" />
       <arg name="results" type="(bao)" direction="out" />
@@ -145,6 +389,18 @@ SPECS = {
       <arg name="return_code" type="q" direction="out" />
       <arg name="return_string" type="s" direction="out" />
     </method>
+    <method name="AddDataDevsBestEffort">
+      <arg name="devices" type="as" direction="in" />
+      <arg name="results" type="a(sbos)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="AddDataDevsWithOverride">
+      <arg name="devices" type="as" direction="in" />
+      <arg name="results" type="(bao)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
     <method name="Bind">
       <arg name="pin" type="s" direction="in" />
       <arg name="json" type="s" direction="in" />
@@ -158,6 +414,46 @@ SPECS = {
       <arg name="return_code" type="q" direction="out" />
       <arg name="return_string" type="s" direction="out" />
     </method>
+    <method name="BlockDevPaths">
+      <arg name="style" type="s" direction="in" />
+      <arg name="results" type="a(ss)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="CapacityHistory">
+      <arg name="since" type="(bs)" direction="in" />
+      <arg name="results" type="a(sss)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="CompactMetadata">
+      <arg name="result" type="t" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="CreateFilesystemFromTemplate">
+      <arg name="template" type="s" direction="in" />
+      <arg name="size" type="(bs)" direction="in" />
+      <arg name="result" type="(os)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="CreateFilesystemOnDevices">
+      <arg name="name" type="s" direction="in" />
+      <arg name="size" type="(bs)" direction="in" />
+      <arg name="devices" type="as" direction="in" />
+      <arg name="results" type="(ba(os))" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="CreateFilesystemWithParams">
+      <arg name="name" type="s" direction="in" />
+      <arg name="size" type="(bs)" direction="in" />
+      <arg name="xfs_params" type="a{st}" direction="in" />
+      <arg name="results" type="(ba(os))" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
     <method name="CreateFilesystems">
       <arg name="specs" type="a(s(bs))" direction="in" />
       <arg name="results" type="(ba(os))" direction="out" />
@@ -170,12 +466,109 @@ SPECS = {
       <arg name="return_code" type="q" direction="out" />
       <arg name="return_string" type="s" direction="out" />
     </method>
+    <method name="DeviceInFlightIo">
+      <arg name="dev_uuid" type="s" direction="in" />
+      <arg name="result" type="t" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="DeviceWriteCacheState">
+      <arg name="dev_uuid" type="s" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="DisableDeviceWriteCache">
+      <arg name="dev_uuid" type="s" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="EnableDetailedStats">
+      <arg name="enabled" type="b" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="EstimateIntegrityOverhead">
+      <arg name="algorithm" type="s" direction="in" />
+      <arg name="results" type="(ssst)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="ExplainCreateFilesystem">
+      <arg name="name" type="s" direction="in" />
+      <arg name="size" type="(bs)" direction="in" />
+      <arg name="xfs_params" type="a{st}" direction="in" />
+      <arg name="results" type="as" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="ExportConfig">
+      <arg name="results" type="s" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="FilesystemSizeLimits">
+      <arg name="results" type="(ss)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="FilesystemsOnDevice">
+      <arg name="dev_uuid" type="s" direction="in" />
+      <arg name="results" type="a(os)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="FilesystemsPendingGrow">
+      <arg name="results" type="a(os)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="FlushCache">
+      <arg name="result" type="t" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="FullHealthReport">
+      <arg name="results" type="(ba(sas))" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
     <method name="InitCache">
       <arg name="devices" type="as" direction="in" />
+      <arg name="block_size" type="(bt)" direction="in" />
       <arg name="results" type="(bao)" direction="out" />
       <arg name="return_code" type="q" direction="out" />
       <arg name="return_string" type="s" direction="out" />
     </method>
+    <method name="LatencyStats">
+      <arg name="results" type="(t(bt)t(bt)(bt))" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="ListActiveAlerts">
+      <arg name="results" type="a(sttb)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="ListDeletedFilesystems">
+      <arg name="results" type="a(sssss)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="OperationLog">
+      <arg name="limit" type="(bt)" direction="in" />
+      <arg name="results" type="a(ss)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="PurgeDeletedFilesystem">
+      <arg name="uuid" type="s" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
     <method name="RebindClevis">
       <arg name="results" type="b" direction="out" />
       <arg name="return_code" type="q" direction="out" />
@@ -187,12 +580,105 @@ SPECS = {
       <arg name="return_code" type="q" direction="out" />
       <arg name="return_string" type="s" direction="out" />
     </method>
+    <method name="RecommendedMaxFilesystems">
+      <arg name="result" type="t" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="RecommendedOverprovisionLimit">
+      <arg name="result" type="t" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="RelocateMetadata">
+      <arg name="devices" type="as" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="RemoveCache">
+      <arg name="results" type="(bas)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="ReserveCacheDevice">
+      <arg name="devices" type="as" direction="in" />
+      <arg name="results" type="(bas)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="ResetTransientState">
+      <arg name="results" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="SetAlertThresholds">
+      <arg name="thresholds" type="a{st}" direction="in" />
+      <arg name="results" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="SetAllocationStrategy">
+      <arg name="strategy" type="s" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="SetAutoPruneSnapshotsOnPressure">
+      <arg name="threshold" type="(bt)" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="SetAutoStart">
+      <arg name="auto_start" type="b" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="SetFilesystemDeletionGracePeriod">
+      <arg name="period" type="(bt)" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
     <method name="SetName">
       <arg name="name" type="s" direction="in" />
       <arg name="result" type="(bs)" direction="out" />
       <arg name="return_code" type="q" direction="out" />
       <arg name="return_string" type="s" direction="out" />
     </method>
+    <method name="SetMetadataWriteFailurePolicy">
+      <arg name="policy" type="s" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="SetSmallDevicePolicy">
+      <arg name="policy" type="s" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="SetStartPriority">
+      <arg name="start_priority" type="(bu)" direction="in" />
+      <arg name="result" type="b" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="ShrinkByDevice">
+      <arg name="dev_uuid" type="s" direction="in" />
+      <arg name="result" type="(bs)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="SimulateReconfiguration">
+      <arg name="add_devices" type="as" direction="in" />
+      <arg name="remove_devices" type="as" direction="in" />
+      <arg name="results" type="(sss)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
     <method name="SnapshotFilesystem">
       <arg name="origin" type="o" direction="in" />
       <arg name="snapshot_name" type="s" direction="in" />
@@ -200,6 +686,26 @@ SPECS = {
       <arg name="return_code" type="q" direction="out" />
       <arg name="return_string" type="s" direction="out" />
     </method>
+    <method name="Structure">
+      <arg name="results" type="a(qtsssb)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="SupportedOperations">
+      <arg name="results" type="as" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="SurfaceTest">
+      <arg name="results" type="(ba(sbs))" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="ThinPoolLimits">
+      <arg name="results" type="(ssss)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
     <method name="Unbind">
       <arg name="results" type="b" direction="out" />
       <arg name="return_code" type="q" direction="out" />
@@ -210,10 +716,90 @@ SPECS = {
       <arg name="return_code" type="q" direction="out" />
       <arg name="return_string" type="s" direction="out" />
     </method>
+    <method name="UndeleteFilesystem">
+      <arg name="uuid" type="s" direction="in" />
+      <arg name="result" type="(bo)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <method name="VerifyMetadataConsistency">
+      <arg name="results" type="(bas)" direction="out" />
+      <arg name="return_code" type="q" direction="out" />
+      <arg name="return_string" type="s" direction="out" />
+    </method>
+    <signal name="FlushCacheProgress">
+      <arg name="flushed" type="t" />
+      <arg name="total" type="t" />
+    </signal>
+    <signal name="MetadataUndersized">
+      <arg name="pool_uuid" type="s" />
+      <arg name="current" type="t" />
+      <arg name="recommended" type="t" />
+    </signal>
+    <signal name="MetadataWriteFailed">
+      <arg name="policy" type="s" />
+      <arg name="read_only" type="b" />
+      <arg name="error" type="s" />
+    </signal>
+    <signal name="PoolAlert">
+      <arg name="pool_uuid" type="s" />
+      <arg name="metric" type="s" />
+      <arg name="value" type="t" />
+      <arg name="threshold" type="t" />
+    </signal>
+    <signal name="RelocateMetadataProgress">
+      <arg name="copied" type="t" />
+      <arg name="total" type="t" />
+    </signal>
+    <signal name="SnapshotPruned">
+      <arg name="pool_uuid" type="s" />
+      <arg name="filesystem_uuid" type="s" />
+      <arg name="name" type="s" />
+    </signal>
+    <property name="AllocationStrategy" type="s" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="false" />
+    </property>
+    <property name="AutoPruneSnapshotsOnPressure" type="(bt)" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="false" />
+    </property>
+    <property name="AutoStart" type="b" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="false" />
+    </property>
+    <property name="CipherInfo" type="(b(st))" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="const" />
+    </property>
+    <property name="DetailedStats" type="b" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="false" />
+    </property>
     <property name="Encrypted" type="b" access="read">
       <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="const" />
     </property>
+    <property name="MetadataUndersized" type="(b(tt))" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="false" />
+    </property>
+    <property name="MetadataWriteFailurePolicy" type="s" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="false" />
+    </property>
     <property name="Name" type="s" access="read" />
+    <property name="OperationInProgress" type="(bs)" access="read" />
+    <property name="PbkdfParams" type="(b(uuu))" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="const" />
+    </property>
+    <property name="RedundancyWarnings" type="a(sas)" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="false" />
+    </property>
+    <property name="ReservedCacheDevices" type="a(ss)" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="false" />
+    </property>
+    <property name="SequenceNumber" type="t" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="const" />
+    </property>
+    <property name="SmallDevicePolicy" type="s" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="false" />
+    </property>
+    <property name="StartPriority" type="(bu)" access="read">
+      <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="false" />
+    </property>
     <property name="Uuid" type="s" access="read">
       <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="const" />
     </property>
@@ -85,6 +85,10 @@ def create_pool(name, devices, *, key_description=None):
             if key_description is None
             else (True, key_description),
             "clevis_info": (False, ("", "")),
+            "cipher_info": (False, ("", 0)),
+            "stripe_info": (False, (0, 0)),
+            "pbkdf_params": (False, (0, 0, 0)),
+            "header_dir": (False, ""),
         },
     )
 